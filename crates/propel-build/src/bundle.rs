@@ -1,9 +1,170 @@
-use std::path::{Path, PathBuf};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::path::{Component, Path, PathBuf};
 use std::process::Command;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use propel_core::CargoProject;
+
+use crate::cargo_config::CARGO_CONFIG_FILES;
+
+/// Directory, inside the project, that deploy stages the bundle in.
+pub const BUNDLE_DIR: &str = ".propel-bundle";
 
 /// Files/directories that propel always excludes from bundles,
 /// regardless of .gitignore content.
-const PROPEL_EXCLUDES: &[&str] = &[".propel-bundle", ".propel", ".git"];
+const PROPEL_EXCLUDES: &[&str] = &[BUNDLE_DIR, ".propel", ".git"];
+
+/// Project root a path inside a bundle copy belongs to: everything before
+/// the first [`BUNDLE_DIR`] component, or `None` when `dir` is not in one.
+///
+/// Purely lexical: `.` and `..` are resolved against the preceding
+/// components (so `proj/.propel-bundle/..` is not inside a bundle), and
+/// leading `..` are kept. Pass an absolute (canonical) path to get an
+/// absolute suggestion back.
+pub fn enclosing_bundle_root(dir: &Path) -> Option<PathBuf> {
+    let mut normalized: Vec<Component<'_>> = Vec::new();
+    for component in dir.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.last() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normalized.push(component),
+            },
+            _ => normalized.push(component),
+        }
+    }
+    let bundle = normalized
+        .iter()
+        .position(|c| c.as_os_str() == BUNDLE_DIR)?;
+    let root: PathBuf = normalized[..bundle].iter().collect();
+    if root.as_os_str().is_empty() {
+        Some(PathBuf::from("."))
+    } else {
+        Some(root)
+    }
+}
+
+/// Refuse to treat `project_dir` as a project when it is a bundle copy,
+/// or a subdirectory of a project that has been deployed before.
+///
+/// Deploying from inside `.propel-bundle/` would bundle the bundle;
+/// deploying from a subdirectory would bundle only part of the project.
+/// A Cargo workspace root is not such a project: its members are bundled
+/// from there (see `CargoProject::bundle_root`), so its bundle is theirs.
+pub fn check_project_dir(project_dir: &Path) -> Result<(), BundleError> {
+    let dir = std::fs::canonicalize(project_dir).map_err(|e| BundleError::ResolveProjectDir {
+        path: project_dir.to_path_buf(),
+        source: e,
+    })?;
+    if let Some(project_root) = enclosing_bundle_root(&dir) {
+        return Err(BundleError::InsideBundle { dir, project_root });
+    }
+    if let Some(project_root) = dir
+        .ancestors()
+        .skip(1)
+        .find(|ancestor| ancestor.join(BUNDLE_DIR).is_dir() && !is_workspace_root(ancestor))
+    {
+        return Err(BundleError::InsideDeployedProject {
+            project_root: project_root.to_path_buf(),
+            dir,
+        });
+    }
+    Ok(())
+}
+
+/// Whether `dir/Cargo.toml` declares a `[workspace]`. An unreadable or
+/// invalid manifest counts as no workspace.
+fn is_workspace_root(dir: &Path) -> bool {
+    let Ok(manifest) = std::fs::read_to_string(dir.join("Cargo.toml")) else {
+        return false;
+    };
+    match toml::from_str::<toml::Table>(&manifest) {
+        Ok(manifest) => manifest.contains_key("workspace"),
+        Err(e) => {
+            tracing::debug!(dir = %dir.display(), error = %e, "unparsable Cargo.toml");
+            false
+        }
+    }
+}
+
+/// How [`create_bundle_with`] lays out the bundle.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BundleFormat {
+    /// A [`BUNDLE_DIR`] directory inside the source, as `gcloud builds
+    /// submit` uploads it.
+    #[default]
+    Directory,
+}
+
+/// Inputs of [`create_bundle_with`].
+///
+/// Only the source directory and the Dockerfile are required; everything
+/// else has a setter, so new inputs do not change the signature of
+/// [`BundleOptions::new`].
+///
+/// ```no_run
+/// use propel_build::bundle::{BundleFormat, BundleOptions, create_bundle_with};
+///
+/// let options = BundleOptions::new(".", "FROM rust:1.93-bookworm\n")
+///     .exclude("fixtures/large")
+///     .format(BundleFormat::Directory);
+/// let bundle_dir = create_bundle_with(&options)?;
+/// # Ok::<(), propel_build::bundle::BundleError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct BundleOptions {
+    source: PathBuf,
+    dockerfile: String,
+    excludes: Vec<PathBuf>,
+    format: BundleFormat,
+    verify: bool,
+}
+
+impl BundleOptions {
+    /// Bundle `source` with `dockerfile` as its `Dockerfile`.
+    pub fn new(source: impl Into<PathBuf>, dockerfile: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+            dockerfile: dockerfile.into(),
+            excludes: Vec::new(),
+            format: BundleFormat::default(),
+            verify: false,
+        }
+    }
+
+    /// Leave out `path`, relative to the source, and everything under it —
+    /// on top of the paths propel always excludes.
+    pub fn exclude(mut self, path: impl Into<PathBuf>) -> Self {
+        self.excludes.push(path.into());
+        self
+    }
+
+    /// Layout of the bundle (default: [`BundleFormat::Directory`]).
+    pub fn format(mut self, format: BundleFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Check, once the files are copied, that none changed while they were
+    /// (default: off). See [`BundleError::ChangedDuringBundle`].
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    fn is_excluded(&self, relative_path: &Path) -> bool {
+        PROPEL_EXCLUDES
+            .iter()
+            .map(Path::new)
+            .chain(self.excludes.iter().map(PathBuf::as_path))
+            .any(|excluded| relative_path.starts_with(excluded))
+    }
+}
 
 /// Bundles project files for Cloud Build submission.
 ///
@@ -12,6 +173,13 @@ const PROPEL_EXCLUDES: &[&str] = &[".propel-bundle", ".propel", ".git"];
 /// Dockerfile. This mirrors a clean `git clone` — no manual file lists
 /// needed.
 ///
+/// # Outside git
+///
+/// When the source is not in a git repository (a CI container without
+/// `.git`, a vendored source tree), the files come from walking the
+/// directory instead, skipping what its `.gitignore` files ignore. The
+/// same exclusions apply.
+///
 /// # Exclusions
 ///
 /// The following are always excluded regardless of `.gitignore`:
@@ -19,12 +187,64 @@ const PROPEL_EXCLUDES: &[&str] = &[".propel-bundle", ".propel", ".git"];
 /// - `.propel/` (ejected Dockerfile directory)
 /// - `.git/` (repository metadata)
 ///
+/// [`BundleOptions::exclude`] adds more.
+///
+/// # Cargo config
+///
+/// `.cargo/config.toml` (or legacy `.cargo/config`) is always included,
+/// even when `.gitignore` excludes it, because source replacement and
+/// registry settings must reach the image build. Use [`is_ignored`] to warn
+/// about that, or [`BundleOptions::exclude`] to leave it out. Other
+/// `.cargo/` files such as `credentials.toml` are never forced in.
+///
 /// # Safety gate
 ///
 /// Call [`is_dirty`] before this function to verify the working tree
 /// is clean. `propel deploy` enforces this unless `--allow-dirty` is passed.
-pub fn create_bundle(project_dir: &Path, dockerfile_content: &str) -> Result<PathBuf, BundleError> {
-    let bundle_dir = project_dir.join(".propel-bundle");
+///
+/// A watcher or build writing into the project can still change files
+/// while they are copied. With [`BundleOptions::verify`], every copy is
+/// hashed against its original afterwards and `git status --porcelain` is
+/// compared with its output from before the copy; any difference fails
+/// with [`BundleError::ChangedDuringBundle`].
+#[tracing::instrument(
+    name = "bundle",
+    level = "debug",
+    skip_all,
+    fields(project_dir = %options.source.display(), file_count = tracing::field::Empty)
+)]
+pub fn create_bundle_with(options: &BundleOptions) -> Result<PathBuf, BundleError> {
+    create_bundle_with_progress(options, |_, _| {})
+}
+
+/// [`create_bundle_with`], calling `progress(copied, total)` from the
+/// calling thread as files are copied into the bundle.
+///
+/// Files are copied by up to [`MAX_COPY_WORKERS`] threads; the result is
+/// the same as copying them one by one. When copies fail, the error for
+/// the first failing file in bundle order is returned.
+///
+/// ```no_run
+/// use propel_build::bundle::{BundleOptions, create_bundle_with_progress};
+///
+/// let options = BundleOptions::new(".", "FROM rust:1.93-bookworm\n");
+/// create_bundle_with_progress(&options, |copied, total| eprint!("\r{copied}/{total}"))?;
+/// # Ok::<(), propel_build::bundle::BundleError>(())
+/// ```
+pub fn create_bundle_with_progress(
+    options: &BundleOptions,
+    mut progress: impl FnMut(usize, usize),
+) -> Result<PathBuf, BundleError> {
+    // The only layout so far; a new format branches here.
+    let BundleFormat::Directory = options.format;
+    let project_dir = options.source.as_path();
+    let to_copy = bundle_files(options)?;
+    let bundle_dir = project_dir.join(BUNDLE_DIR);
+    let status_before = if options.verify {
+        Some(bundle_status(options)?)
+    } else {
+        None
+    };
 
     // Clean previous bundle
     if bundle_dir.exists() {
@@ -38,46 +258,406 @@ pub fn create_bundle(project_dir: &Path, dockerfile_content: &str) -> Result<Pat
         source: e,
     })?;
 
+    copy_into_bundle(project_dir, &bundle_dir, &to_copy, &mut progress)?;
+    if let Some(status_before) = status_before {
+        verify_bundle(options, &bundle_dir, &to_copy, &status_before)?;
+    }
+
+    // Write generated Dockerfile
+    std::fs::write(bundle_dir.join("Dockerfile"), &options.dockerfile).map_err(|e| {
+        BundleError::WriteDockerfile {
+            path: bundle_dir.join("Dockerfile"),
+            source: e,
+        }
+    })?;
+
+    tracing::debug!(path = %bundle_dir.display(), "bundle created");
+    Ok(bundle_dir)
+}
+
+/// The project files [`create_bundle_with`] would copy, relative to the
+/// source, in bundle order. The generated `Dockerfile` is not among them.
+///
+/// Nothing is written, so this previews a bundle (`propel deploy
+/// --dry-run`).
+pub fn bundle_files(options: &BundleOptions) -> Result<Vec<PathBuf>, BundleError> {
+    let project_dir = options.source.as_path();
+    if let Some(project_root) = enclosing_bundle_root(project_dir) {
+        return Err(BundleError::InsideBundle {
+            dir: project_dir.to_path_buf(),
+            project_root,
+        });
+    }
+
     // Get file list from git (respects .gitignore)
-    let files = git_ls_files(project_dir)?;
+    let files = match git_ls_files(project_dir) {
+        Err(BundleError::NotARepo { .. }) => {
+            tracing::info!("not a git repository; bundling files from the directory tree");
+            walk_files(project_dir)?
+        }
+        files => files?,
+    };
+    tracing::Span::current().record("file_count", files.len());
     tracing::debug!(file_count = files.len(), "git ls-files collected");
 
-    // Copy each file into the bundle
-    for relative_path in &files {
-        // Skip propel-specific directories
-        if PROPEL_EXCLUDES
-            .iter()
-            .any(|ex| relative_path.starts_with(ex))
+    let mut to_copy: Vec<PathBuf> = files
+        .iter()
+        .filter(|relative_path| !options.is_excluded(relative_path))
+        .cloned()
+        .collect();
+    // git lists an unmerged path once per conflict stage.
+    to_copy.dedup();
+    for config in CARGO_CONFIG_FILES {
+        let relative_path = Path::new(config);
+        if project_dir.join(relative_path).is_file()
+            && !files.iter().any(|f| f == relative_path)
+            && !options.is_excluded(relative_path)
         {
-            continue;
+            tracing::debug!(path = config, "bundling ignored cargo config");
+            to_copy.push(relative_path.to_path_buf());
         }
+    }
+    Ok(to_copy)
+}
+
+/// Bundles `project_dir` with `dockerfile_content` as its `Dockerfile`.
+#[cfg(feature = "deprecated-shims")]
+#[deprecated(
+    since = "0.4.3",
+    note = "use `create_bundle_with(&BundleOptions::new(project_dir, dockerfile_content))`"
+)]
+pub fn create_bundle(project_dir: &Path, dockerfile_content: &str) -> Result<PathBuf, BundleError> {
+    create_bundle_with(&BundleOptions::new(project_dir, dockerfile_content))
+}
 
-        let src = project_dir.join(relative_path);
-        let dst = bundle_dir.join(relative_path);
+/// Most threads [`create_bundle_with_progress`] copies files with.
+pub const MAX_COPY_WORKERS: usize = 8;
 
-        if let Some(parent) = dst.parent() {
-            std::fs::create_dir_all(parent).map_err(|e| BundleError::Create {
-                path: parent.to_path_buf(),
-                source: e,
-            })?;
+/// Copy `files` (relative to `project_dir`) into `bundle_dir`, creating
+/// every directory first and then copying in parallel.
+fn copy_into_bundle(
+    project_dir: &Path,
+    bundle_dir: &Path,
+    files: &[PathBuf],
+    progress: &mut dyn FnMut(usize, usize),
+) -> Result<(), BundleError> {
+    let dirs: BTreeSet<&Path> = files.iter().filter_map(|f| f.parent()).collect();
+    for dir in dirs {
+        let path = bundle_dir.join(dir);
+        std::fs::create_dir_all(&path).map_err(|e| BundleError::Create { path, source: e })?;
+    }
+
+    let total = files.len();
+    let workers = std::thread::available_parallelism()
+        .map_or(1, std::num::NonZeroUsize::get)
+        .clamp(1, MAX_COPY_WORKERS)
+        .min(total);
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let (copied_tx, copied_rx) = std::sync::mpsc::channel::<()>();
+
+    let mut errors: Vec<(usize, BundleError)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                let copied_tx = copied_tx.clone();
+                let (next, failed) = (&next, &failed);
+                scope.spawn(move || {
+                    let mut errors = Vec::new();
+                    while !failed.load(Ordering::Relaxed) {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(relative_path) = files.get(index) else {
+                            break;
+                        };
+                        let src = project_dir.join(relative_path);
+                        match std::fs::copy(&src, bundle_dir.join(relative_path)) {
+                            Ok(_) => {
+                                // arch-lint: allow(no-silent-result-drop) reason="the receiver outlives every worker"
+                                let _ = copied_tx.send(());
+                            }
+                            Err(e) => {
+                                failed.store(true, Ordering::Relaxed);
+                                errors.push((
+                                    index,
+                                    BundleError::CopyFile {
+                                        path: src,
+                                        source: e,
+                                    },
+                                ));
+                            }
+                        }
+                    }
+                    errors
+                })
+            })
+            .collect();
+        drop(copied_tx);
+
+        let mut copied = 0;
+        for () in copied_rx {
+            copied += 1;
+            progress(copied, total);
+        }
+        handles
+            .into_iter()
+            .flat_map(|handle| match handle.join() {
+                Ok(errors) => errors,
+                Err(panic) => std::panic::resume_unwind(panic),
+            })
+            .collect()
+    });
+
+    errors.sort_by_key(|(index, _)| *index);
+    match errors.into_iter().next() {
+        Some((_, error)) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// Fail with [`BundleError::ChangedDuringBundle`] when a copied file no
+/// longer matches its original, or when `git status` differs from
+/// `status_before`.
+fn verify_bundle(
+    options: &BundleOptions,
+    bundle_dir: &Path,
+    files: &[PathBuf],
+    status_before: &BTreeMap<PathBuf, String>,
+) -> Result<(), BundleError> {
+    let mut changed: BTreeSet<PathBuf> =
+        crate::manifest::changed_files(&options.source, bundle_dir, files)
+            .map_err(|e| BundleError::Verify {
+                source: Box::new(e),
+            })?
+            .into_iter()
+            .collect();
+    let status_after = bundle_status(options)?;
+    for (path, code) in status_before.iter().chain(&status_after) {
+        if status_before.get(path) != Some(code) || status_after.get(path) != Some(code) {
+            changed.insert(path.clone());
+        }
+    }
+    if changed.is_empty() {
+        return Ok(());
+    }
+    Err(BundleError::ChangedDuringBundle {
+        paths: changed.into_iter().collect(),
+    })
+}
+
+/// `git status --porcelain` for the files a bundle may hold: the
+/// two-letter status of each changed path, relative to the source. Empty
+/// outside git.
+fn bundle_status(options: &BundleOptions) -> Result<BTreeMap<PathBuf, String>, BundleError> {
+    let project_dir = options.source.as_path();
+    let output = Command::new("git")
+        .args(["status", "--porcelain", "--", "."])
+        .args(
+            PROPEL_EXCLUDES
+                .iter()
+                .map(Path::new)
+                .chain(options.excludes.iter().map(PathBuf::as_path))
+                .map(|excluded| format!(":(exclude){}", excluded.display())),
+        )
+        .current_dir(project_dir)
+        .output()
+        .map_err(|e| git_spawn_error("failed to execute git status", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if is_not_a_repo(&stderr) {
+            return Ok(BTreeMap::new());
         }
+        return Err(BundleError::GitFailed {
+            detail: format!(
+                "git status exited with {}: {}",
+                output.status,
+                stderr.trim()
+            ),
+        });
+    }
+
+    // Porcelain paths are relative to the repository root.
+    let prefix = git_show_prefix(project_dir)?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout
+        .lines()
+        .filter_map(|line| {
+            let (code, path) = (line.get(..2)?, line.get(3..)?);
+            let path = path.rsplit(" -> ").next()?;
+            // arch-lint: allow(no-silent-result-drop) reason="Option: quoted paths keep their prefix"
+            let path = path.strip_prefix(prefix.as_str()).unwrap_or(path);
+            Some((PathBuf::from(path), code.to_owned()))
+        })
+        .collect())
+}
+
+/// `git rev-parse --show-prefix`: the path of `project_dir` inside its
+/// repository, with a trailing `/`, or empty at the root.
+fn git_show_prefix(project_dir: &Path) -> Result<String, BundleError> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--show-prefix"])
+        .current_dir(project_dir)
+        .output()
+        .map_err(|e| git_spawn_error("failed to execute git rev-parse", e))?;
+    if !output.status.success() {
+        return Err(BundleError::GitFailed {
+            detail: format!(
+                "git rev-parse exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+/// Whether the bundle will carry a `Cargo.lock`: the package's own, or the
+/// workspace root's for a member bundled from the root.
+///
+/// Decided before bundling, so a lockfile that exists but is gitignored
+/// still counts; set `[build] locked = false` for such projects.
+pub fn lockfile_bundled(project: &CargoProject) -> bool {
+    project.package_dir.join("Cargo.lock").is_file()
+        || project.workspace_root.join("Cargo.lock").is_file()
+}
+
+/// Writes a generated Cloud Build config into an existing bundle.
+///
+/// Returns the path to pass to `gcloud builds submit --config`. Overwrites
+/// any `cloudbuild.yaml` copied from the project — the generated file is
+/// the one the build must use.
+pub fn write_cloudbuild_config(bundle_dir: &Path, content: &str) -> Result<PathBuf, BundleError> {
+    let path = bundle_dir.join(crate::cloudbuild::CLOUDBUILD_FILE);
+    std::fs::write(&path, content).map_err(|e| BundleError::WriteCloudBuildConfig {
+        path: path.clone(),
+        source: e,
+    })?;
+    Ok(path)
+}
 
-        std::fs::copy(&src, &dst).map_err(|e| BundleError::CopyFile {
-            path: src,
+/// Lists every file in a bundle, relative to `bundle_dir` and sorted.
+pub fn bundle_entries(bundle_dir: &Path) -> Result<Vec<PathBuf>, BundleError> {
+    let mut entries = Vec::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative_dir) = pending.pop() {
+        let dir = bundle_dir.join(&relative_dir);
+        let read_dir = std::fs::read_dir(&dir).map_err(|e| BundleError::ReadDir {
+            path: dir.clone(),
             source: e,
         })?;
+        for entry in read_dir {
+            let entry = entry.map_err(|e| BundleError::ReadDir {
+                path: dir.clone(),
+                source: e,
+            })?;
+            let relative_path = relative_dir.join(entry.file_name());
+            if entry.path().is_dir() {
+                pending.push(relative_path);
+            } else {
+                entries.push(relative_path);
+            }
+        }
     }
+    entries.sort();
+    Ok(entries)
+}
 
-    // Write generated Dockerfile
-    std::fs::write(bundle_dir.join("Dockerfile"), dockerfile_content).map_err(|e| {
-        BundleError::WriteDockerfile {
-            path: bundle_dir.join("Dockerfile"),
-            source: e,
+/// Files listed when a bundle is over the upload limit.
+pub const LARGEST_FILES_SHOWN: usize = 5;
+
+/// Total size of a bundle, with its files from largest to smallest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundleSize {
+    /// Sum of all file sizes, in bytes.
+    pub total: u64,
+    /// Every file with its size, largest first (ties in path order).
+    pub files: Vec<(PathBuf, u64)>,
+}
+
+impl BundleSize {
+    /// Measure `entries` (as returned by [`bundle_entries`]) in `bundle_dir`.
+    pub fn measure(bundle_dir: &Path, entries: &[PathBuf]) -> Result<Self, BundleError> {
+        let mut files = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let path = bundle_dir.join(entry);
+            let size = std::fs::metadata(&path)
+                .map_err(|e| BundleError::ReadSize { path, source: e })?
+                .len();
+            files.push((entry.clone(), size));
         }
-    })?;
+        files.sort_by(|(a_path, a_size), (b_path, b_size)| {
+            b_size.cmp(a_size).then_with(|| a_path.cmp(b_path))
+        });
+        Ok(Self {
+            total: files.iter().map(|(_, size)| size).sum(),
+            files,
+        })
+    }
 
-    tracing::debug!(path = %bundle_dir.display(), "bundle created");
-    Ok(bundle_dir)
+    /// Fail when the bundle is larger than `limit` bytes, naming the
+    /// largest files.
+    pub fn check_limit(&self, limit: u64) -> Result<(), BundleError> {
+        if self.total <= limit {
+            return Ok(());
+        }
+        let mut largest = String::new();
+        for (path, size) in self.files.iter().take(LARGEST_FILES_SHOWN) {
+            // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+            let _ = writeln!(largest, "  {:>10}  {}", format_size(*size), path.display());
+        }
+        Err(BundleError::TooLarge {
+            size: format_size(self.total),
+            limit: format_size(limit),
+            largest,
+        })
+    }
+}
+
+/// Byte count for people: `512 B`, `3.4 MiB`, `1.50 GiB`.
+pub fn format_size(bytes: u64) -> String {
+    const KIB: f64 = 1024.0;
+    let b = bytes as f64;
+    if b < KIB {
+        format!("{bytes} B")
+    } else if b < KIB * KIB {
+        format!("{:.1} KiB", b / KIB)
+    } else if b < KIB * KIB * KIB {
+        format!("{:.1} MiB", b / (KIB * KIB))
+    } else {
+        format!("{:.2} GiB", b / (KIB * KIB * KIB))
+    }
+}
+
+/// Summarizes bundle entries grouped by top-level directory.
+///
+/// Each group shows its file count and at most `per_dir_limit` paths;
+/// files at the bundle root are grouped under `./`.
+pub fn format_bundle_summary(entries: &[PathBuf], per_dir_limit: usize) -> String {
+    let mut groups: BTreeMap<String, Vec<&Path>> = BTreeMap::new();
+    for entry in entries {
+        let mut components = entry.components();
+        let top = match (components.next(), components.next()) {
+            (Some(first), Some(_)) => format!("{}/", first.as_os_str().to_string_lossy()),
+            _ => "./".to_owned(),
+        };
+        groups.entry(top).or_default().push(entry);
+    }
+
+    let mut out = String::new();
+    // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+    let _ = writeln!(out, "Bundle: {} file(s)", entries.len());
+    for (top, files) in &groups {
+        // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+        let _ = writeln!(out, "  {top} ({})", files.len());
+        for file in files.iter().take(per_dir_limit) {
+            // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+            let _ = writeln!(out, "    {}", file.display());
+        }
+        if files.len() > per_dir_limit {
+            // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+            let _ = writeln!(out, "    ... {} more", files.len() - per_dir_limit);
+        }
+    }
+    out
 }
 
 /// Returns the list of files git considers part of the project:
@@ -87,13 +667,15 @@ fn git_ls_files(project_dir: &Path) -> Result<Vec<PathBuf>, BundleError> {
         .args(["ls-files", "--cached", "--others", "--exclude-standard"])
         .current_dir(project_dir)
         .output()
-        .map_err(|e| BundleError::GitCommand {
-            detail: "failed to execute git ls-files".to_owned(),
-            source: e,
-        })?;
+        .map_err(|e| git_spawn_error("failed to execute git ls-files", e))?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
+        if is_not_a_repo(&stderr) {
+            return Err(BundleError::NotARepo {
+                dir: project_dir.to_path_buf(),
+            });
+        }
         return Err(BundleError::GitFailed {
             detail: format!(
                 "git ls-files exited with {}: {}",
@@ -113,28 +695,99 @@ fn git_ls_files(project_dir: &Path) -> Result<Vec<PathBuf>, BundleError> {
     Ok(files)
 }
 
+/// Files under `project_dir`, relative to it and sorted, for a project
+/// outside git: `.gitignore` files are honored as git would, and the
+/// always-excluded directories are not entered.
+fn walk_files(project_dir: &Path) -> Result<Vec<PathBuf>, BundleError> {
+    let walk_error = |source: ignore::Error| BundleError::Walk {
+        path: project_dir.to_path_buf(),
+        source,
+    };
+    let mut files = Vec::new();
+    let walker = ignore::WalkBuilder::new(project_dir)
+        .standard_filters(false)
+        .git_ignore(true)
+        .require_git(false)
+        .filter_entry(|entry| {
+            entry.depth() != 1 || !PROPEL_EXCLUDES.iter().any(|ex| entry.file_name() == *ex)
+        })
+        .build();
+    for entry in walker {
+        let entry = entry.map_err(walk_error)?;
+        if entry.file_type().is_some_and(|t| t.is_dir()) || !entry.path().is_file() {
+            continue;
+        }
+        if let Ok(relative) = entry.path().strip_prefix(project_dir) {
+            files.push(relative.to_path_buf());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Whether `.gitignore` files under `project_dir` ignore `relative_path`,
+/// for a project outside git. Deeper `.gitignore` files take precedence,
+/// as in git.
+fn gitignore_matches(project_dir: &Path, relative_path: &Path) -> Result<bool, BundleError> {
+    let path = project_dir.join(relative_path);
+    let is_dir = path.is_dir();
+    let mut ignored = false;
+    let mut dir = project_dir.to_path_buf();
+    let mut components = relative_path.components();
+    loop {
+        let gitignore_path = dir.join(".gitignore");
+        if gitignore_path.is_file() {
+            let (gitignore, error) = ignore::gitignore::Gitignore::new(&gitignore_path);
+            if let Some(source) = error {
+                return Err(BundleError::Walk {
+                    path: gitignore_path,
+                    source,
+                });
+            }
+            match gitignore.matched_path_or_any_parents(&path, is_dir) {
+                ignore::Match::Ignore(_) => ignored = true,
+                ignore::Match::Whitelist(_) => ignored = false,
+                ignore::Match::None => {}
+            }
+        }
+        match components.next() {
+            Some(component) if components.as_path() != Path::new("") => dir.push(component),
+            _ => return Ok(ignored),
+        }
+    }
+}
+
+/// Whether git's `stderr` says the directory is outside any repository.
+fn is_not_a_repo(stderr: &str) -> bool {
+    stderr.contains("not a git repository")
+}
+
 /// Checks whether the git working tree has uncommitted changes.
 ///
 /// Uses `git status --porcelain` — returns `true` if there are staged,
 /// unstaged, or untracked files. This is the safety gate that prevents
-/// deploying unintended changes.
+/// deploying unintended changes. A project outside git has nothing to
+/// compare against, so it is never dirty. Changes under a [`BUNDLE_DIR`]
+/// do not count: a workspace member's deploy stages its bundle at the
+/// workspace root, which the member's `.gitignore` does not cover.
 ///
 /// # Errors
 ///
-/// Returns [`BundleError::GitCommand`] if git is not installed, or
-/// [`BundleError::GitFailed`] if the directory is not a git repository.
+/// Returns [`BundleError::GitNotFound`] if git is not installed, or
+/// [`BundleError::GitFailed`] if git status fails.
 pub fn is_dirty(project_dir: &Path) -> Result<bool, BundleError> {
     let output = Command::new("git")
         .args(["status", "--porcelain"])
         .current_dir(project_dir)
         .output()
-        .map_err(|e| BundleError::GitCommand {
-            detail: "failed to execute git status".to_owned(),
-            source: e,
-        })?;
+        .map_err(|e| git_spawn_error("failed to execute git status", e))?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
+        if is_not_a_repo(&stderr) {
+            tracing::debug!(dir = %project_dir.display(), "not a git repository; treating as clean");
+            return Ok(false);
+        }
         return Err(BundleError::GitFailed {
             detail: format!(
                 "git status exited with {}: {}",
@@ -144,10 +797,156 @@ pub fn is_dirty(project_dir: &Path) -> Result<bool, BundleError> {
         });
     }
 
-    Ok(!output.stdout.is_empty())
+    Ok(String::from_utf8_lossy(&output.stdout).lines().any(|line| {
+        !line
+            .get(3..)
+            .is_some_and(|path| path.split('/').any(|component| component == BUNDLE_DIR))
+    }))
+}
+
+/// Checks whether git ignores `relative_path` (and it is not tracked).
+///
+/// Outside git, checks the project's `.gitignore` files instead, which
+/// is what the bundle honors there.
+///
+/// # Errors
+///
+/// Returns [`BundleError::GitNotFound`] if git is not installed,
+/// [`BundleError::GitFailed`] if git check-ignore fails, or
+/// [`BundleError::Walk`] for an unreadable `.gitignore` outside git.
+pub fn is_ignored(project_dir: &Path, relative_path: &Path) -> Result<bool, BundleError> {
+    let output = Command::new("git")
+        .arg("check-ignore")
+        .arg("--quiet")
+        .arg(relative_path)
+        .current_dir(project_dir)
+        .output()
+        .map_err(|e| git_spawn_error("failed to execute git check-ignore", e))?;
+
+    // 0 = ignored, 1 = not ignored, anything else is an error.
+    match output.status.code() {
+        Some(0) => Ok(true),
+        Some(1) => Ok(false),
+        _ => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if is_not_a_repo(&stderr) {
+                return gitignore_matches(project_dir, relative_path);
+            }
+            Err(BundleError::GitFailed {
+                detail: format!(
+                    "git check-ignore exited with {}: {}",
+                    output.status,
+                    stderr.trim()
+                ),
+            })
+        }
+    }
+}
+
+/// Checks whether git tracks `relative_path` (`git ls-files --error-unmatch`).
+///
+/// Untracked files are bundled by local deploys but never reach a CI
+/// checkout.
+///
+/// # Errors
+///
+/// Returns [`BundleError::GitNotFound`] if git is not installed,
+/// [`BundleError::NotARepo`] if the directory is not in a git repository,
+/// or [`BundleError::GitFailed`] if git ls-files fails otherwise.
+pub fn is_tracked(project_dir: &Path, relative_path: &Path) -> Result<bool, BundleError> {
+    let output = Command::new("git")
+        .args(["ls-files", "--error-unmatch", "--"])
+        .arg(relative_path)
+        .current_dir(project_dir)
+        .output()
+        .map_err(|e| git_spawn_error("failed to execute git ls-files", e))?;
+
+    // 0 = tracked, 1 = no tracked file matches, anything else is an error.
+    match output.status.code() {
+        Some(0) => Ok(true),
+        Some(1) => Ok(false),
+        _ => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if is_not_a_repo(&stderr) {
+                return Err(BundleError::NotARepo {
+                    dir: project_dir.to_path_buf(),
+                });
+            }
+            Err(BundleError::GitFailed {
+                detail: format!(
+                    "git ls-files exited with {}: {}",
+                    output.status,
+                    stderr.trim()
+                ),
+            })
+        }
+    }
+}
+
+/// Full SHA of `HEAD` in the repository containing `project_dir`.
+///
+/// # Errors
+///
+/// Returns [`BundleError::GitNotFound`] if git is not installed,
+/// [`BundleError::NotARepo`] outside a git repository, or
+/// [`BundleError::GitFailed`] if there is no commit to resolve.
+pub fn head_commit(project_dir: &Path) -> Result<String, BundleError> {
+    rev_parse(project_dir, &["rev-parse", "HEAD"])
+}
+
+/// Abbreviated SHA of `HEAD` (`git rev-parse --short HEAD`), as used for
+/// image tags.
+///
+/// # Errors
+///
+/// Returns [`BundleError::GitNotFound`] if git is not installed,
+/// [`BundleError::NotARepo`] outside a git repository, or
+/// [`BundleError::GitFailed`] if there is no commit to resolve.
+pub fn short_head_commit(project_dir: &Path) -> Result<String, BundleError> {
+    rev_parse(project_dir, &["rev-parse", "--short", "HEAD"])
+}
+
+fn rev_parse(project_dir: &Path, args: &[&str]) -> Result<String, BundleError> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(project_dir)
+        .output()
+        .map_err(|e| git_spawn_error("failed to execute git rev-parse", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if is_not_a_repo(&stderr) {
+            return Err(BundleError::NotARepo {
+                dir: project_dir.to_path_buf(),
+            });
+        }
+        return Err(BundleError::GitFailed {
+            detail: format!(
+                "git {} exited with {}: {}",
+                args.join(" "),
+                output.status,
+                stderr.trim()
+            ),
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+/// Classify a failure to start git: [`BundleError::GitNotFound`] when
+/// there is no git binary, so the message can say to install it.
+fn git_spawn_error(detail: &str, source: std::io::Error) -> BundleError {
+    if source.kind() == std::io::ErrorKind::NotFound {
+        return BundleError::GitNotFound;
+    }
+    BundleError::GitCommand {
+        detail: detail.to_owned(),
+        source,
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum BundleError {
     #[error("failed to clean up bundle directory {path}")]
     Cleanup {
@@ -164,11 +963,31 @@ pub enum BundleError {
         path: std::path::PathBuf,
         source: std::io::Error,
     },
+    #[error("failed to read bundle directory {path}")]
+    ReadDir {
+        path: std::path::PathBuf,
+        source: std::io::Error,
+    },
+    #[error("failed to read size of {path}")]
+    ReadSize {
+        path: std::path::PathBuf,
+        source: std::io::Error,
+    },
     #[error("failed to write Dockerfile at {path}")]
     WriteDockerfile {
         path: std::path::PathBuf,
         source: std::io::Error,
     },
+    #[error("failed to write Cloud Build config at {path}")]
+    WriteCloudBuildConfig {
+        path: std::path::PathBuf,
+        source: std::io::Error,
+    },
+    #[error(
+        "git not found on PATH — propel uses git to pick the files to deploy and to \
+         check for uncommitted changes; install git: https://git-scm.com/downloads"
+    )]
+    GitNotFound,
     #[error("git command failed: {detail}")]
     GitCommand {
         detail: String,
@@ -176,4 +995,66 @@ pub enum BundleError {
     },
     #[error("git failed: {detail}")]
     GitFailed { detail: String },
+    #[error(
+        "{dir} is not in a git repository, so there is no commit to tag the image with or \
+         substitute for {{{{commit}}}}; set [build].tag_strategy = \"version\" or \"latest\" \
+         and drop {{{{commit}}}} from env values, or commit the project to git"
+    )]
+    NotARepo { dir: std::path::PathBuf },
+    #[error("failed to walk {path}")]
+    Walk {
+        path: std::path::PathBuf,
+        source: ignore::Error,
+    },
+    #[error("failed to resolve project directory {path}")]
+    ResolveProjectDir {
+        path: std::path::PathBuf,
+        source: std::io::Error,
+    },
+    #[error(
+        "{dir} is inside a propel bundle, not a project; run propel from {project_root} instead"
+    )]
+    InsideBundle {
+        dir: std::path::PathBuf,
+        project_root: std::path::PathBuf,
+    },
+    #[error(
+        "{project_root} contains {BUNDLE_DIR}/, so {dir} looks like a directory inside \
+         that project; run propel from {project_root} instead"
+    )]
+    InsideDeployedProject {
+        dir: std::path::PathBuf,
+        project_root: std::path::PathBuf,
+    },
+    #[error(
+        "bundle is {size}, over the {limit} upload limit ([cloud_build].max_bundle_mb). \
+         Largest files:\n{largest}\
+         The bundle holds what `git ls-files --cached --others --exclude-standard` lists: \
+         add untracked files to .gitignore, `git rm --cached` tracked ones the image does \
+         not need, or pass --force-large-bundle to upload anyway"
+    )]
+    TooLarge {
+        size: String,
+        limit: String,
+        largest: String,
+    },
+    #[error(
+        "files changed while the bundle was being copied, so it matches neither the commit \
+         nor the working tree:\n{}\
+         Stop watchers or builds that write into the project and deploy again",
+        list_paths(.paths)
+    )]
+    ChangedDuringBundle { paths: Vec<std::path::PathBuf> },
+    #[error("failed to verify the bundle against the project")]
+    Verify {
+        source: Box<crate::manifest::ManifestError>,
+    },
+}
+
+/// One `  path` line per path, for [`BundleError::ChangedDuringBundle`].
+fn list_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| format!("  {}\n", path.display()))
+        .collect()
 }
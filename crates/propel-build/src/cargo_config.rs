@@ -0,0 +1,110 @@
+//! Project-level `.cargo/config.toml` handling for the image build.
+//!
+//! Source replacement (`[source.crates-io] replace-with = "vendored-sources"`)
+//! and custom registries live in `.cargo/config.toml`, which `cargo chef`'s
+//! recipe does not capture. The generated Dockerfile copies the config —
+//! and any vendored source directories it points at — into the cacher stage
+//! so `cargo chef cook` resolves dependencies the same way the final build
+//! does.
+
+use std::path::{Component, Path};
+
+/// Config files Cargo reads from a project's `.cargo/` directory, in the
+/// order propel looks for them.
+///
+/// `.cargo/credentials.toml` is deliberately absent: registry tokens must
+/// never reach the bundle.
+pub const CARGO_CONFIG_FILES: &[&str] = &[".cargo/config.toml", ".cargo/config"];
+
+/// The project's `.cargo/config.toml` and the source directories it uses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CargoConfig {
+    /// Config file path relative to the project directory.
+    pub path: &'static str,
+    /// `directory` / `local-registry` sources inside the project, relative
+    /// to the project directory.
+    pub vendor_dirs: Vec<String>,
+    /// Sources outside the project (absolute or `..` paths). They cannot be
+    /// bundled, so the image build will not find them.
+    pub external_dirs: Vec<String>,
+}
+
+impl CargoConfig {
+    /// Read the first of [`CARGO_CONFIG_FILES`] present in `project_dir`.
+    ///
+    /// Returns `Ok(None)` when the project has no `.cargo` config.
+    pub fn detect(project_dir: &Path) -> Result<Option<Self>, CargoConfigError> {
+        for &path in CARGO_CONFIG_FILES {
+            let full = project_dir.join(path);
+            if !full.is_file() {
+                continue;
+            }
+            let content = std::fs::read_to_string(&full).map_err(|e| CargoConfigError::Read {
+                path: full.clone(),
+                source: e,
+            })?;
+            return Self::parse(path, &content)
+                .map(Some)
+                .map_err(|e| CargoConfigError::Parse {
+                    path: full,
+                    source: e,
+                });
+        }
+        Ok(None)
+    }
+
+    /// Parse config `content` read from `path`.
+    pub fn parse(path: &'static str, content: &str) -> Result<Self, toml::de::Error> {
+        let table: toml::Table = toml::from_str(content)?;
+
+        let mut vendor_dirs = Vec::new();
+        let mut external_dirs = Vec::new();
+        let sources = table.get("source").and_then(toml::Value::as_table);
+        for source in sources.into_iter().flat_map(|s| s.values()) {
+            for key in ["directory", "local-registry"] {
+                let Some(dir) = source.get(key).and_then(toml::Value::as_str) else {
+                    continue;
+                };
+                let dir = dir.trim_end_matches('/').to_owned();
+                if is_inside_project(&dir) {
+                    vendor_dirs.push(dir);
+                } else {
+                    external_dirs.push(dir);
+                }
+            }
+        }
+        vendor_dirs.sort();
+        vendor_dirs.dedup();
+
+        Ok(Self {
+            path,
+            vendor_dirs,
+            external_dirs,
+        })
+    }
+}
+
+/// Relative paths that stay below the project directory (Cargo resolves
+/// config paths against the directory containing `.cargo/`).
+fn is_inside_project(dir: &str) -> bool {
+    let path = Path::new(dir);
+    !dir.is_empty()
+        && path
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum CargoConfigError {
+    #[error("failed to read {path}")]
+    Read {
+        path: std::path::PathBuf,
+        source: std::io::Error,
+    },
+    #[error("failed to parse {path}")]
+    Parse {
+        path: std::path::PathBuf,
+        source: toml::de::Error,
+    },
+}
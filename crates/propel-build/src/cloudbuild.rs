@@ -0,0 +1,210 @@
+//! `cloudbuild.yaml` generation for builds that go beyond `--tag`.
+//!
+//! Plain deploys use `gcloud builds submit --tag`, which cannot pass
+//! secrets to `docker build` or run anything after the image is built.
+//! Propel writes an explicit build config instead when:
+//!
+//! - private dependencies need git credentials — the secret is declared
+//!   under `availableSecrets`, exposed to the docker step via `secretEnv`,
+//!   and forwarded as a BuildKit secret;
+//! - `[ci].deploy_via_cloud_build` is set — the image is pushed and
+//!   deployed to Cloud Run by a final step running as a dedicated build
+//!   service account;
+//! - `[build].platform` is set — the image is built with
+//!   `docker buildx build --platform`. Cloud Build workers are amd64, so an
+//!   arm64 build first registers QEMU emulators with `tonistiigi/binfmt`
+//!   and then compiles natively under emulation; the binary still lands in
+//!   `target/release`, so the Dockerfile is the same for every platform;
+//! - `[build].cache_mounts` is set — the Dockerfile's `RUN --mount=type=cache`
+//!   steps need BuildKit, so the image is built with `docker buildx build`.
+
+use std::fmt::Write;
+
+use propel_core::Platform;
+
+use crate::dockerfile::GIT_CREDENTIALS_SECRET_ID;
+
+/// File name of the generated build config inside the bundle.
+pub const CLOUDBUILD_FILE: &str = "cloudbuild.yaml";
+
+/// Environment variable the docker step receives the secret under.
+const GIT_CREDENTIALS_ENV: &str = "PROPEL_GIT_CREDENTIALS";
+
+/// Image used for the in-build `gcloud run deploy` step.
+const CLOUD_SDK_IMAGE: &str = "gcr.io/google.com/cloudsdktool/cloud-sdk:slim";
+
+/// Image that registers QEMU emulators for cross-platform builds.
+const BINFMT_IMAGE: &str = "tonistiigi/binfmt";
+
+/// Architecture of the Cloud Build workers; builds for it need no emulation.
+const BUILDER_PLATFORM: Platform = Platform::Amd64;
+
+/// Final build step that deploys the freshly pushed image to Cloud Run.
+pub struct DeployStep<'a> {
+    /// Arguments to `gcloud` (starting with `run deploy`).
+    pub gcloud_args: &'a [String],
+    /// Service account email the build runs as.
+    pub service_account: &'a str,
+}
+
+/// Generates a `cloudbuild.yaml` that builds and pushes one image.
+pub struct CloudBuildYaml<'a> {
+    project_id: &'a str,
+    image_tag: &'a str,
+    git_credentials_secret: Option<&'a str>,
+    logs_bucket: Option<&'a str>,
+    platform: Option<Platform>,
+    buildx: bool,
+    deploy: Option<DeployStep<'a>>,
+}
+
+impl<'a> CloudBuildYaml<'a> {
+    pub fn new(project_id: &'a str, image_tag: &'a str) -> Self {
+        Self {
+            project_id,
+            image_tag,
+            git_credentials_secret: None,
+            logs_bucket: None,
+            platform: None,
+            buildx: false,
+            deploy: None,
+        }
+    }
+
+    /// Make the latest version of Secret Manager secret `secret_name`
+    /// available as the [`GIT_CREDENTIALS_SECRET_ID`] BuildKit secret.
+    pub fn git_credentials_secret(mut self, secret_name: &'a str) -> Self {
+        self.git_credentials_secret = Some(secret_name);
+        self
+    }
+
+    /// Write build logs to `bucket` (a `gs://` URL) instead of the default.
+    pub fn logs_bucket(mut self, bucket: &'a str) -> Self {
+        self.logs_bucket = Some(bucket);
+        self
+    }
+
+    /// Build the image for `platform` with `docker buildx`.
+    pub fn platform(mut self, platform: Platform) -> Self {
+        self.platform = Some(platform);
+        self
+    }
+
+    /// Build with `docker buildx` even without a platform, for a
+    /// Dockerfile with BuildKit cache mounts.
+    pub fn cache_mounts(mut self) -> Self {
+        self.buildx = true;
+        self
+    }
+
+    /// Append a Cloud Run deploy step after the image is pushed.
+    pub fn deploy_step(mut self, step: DeployStep<'a>) -> Self {
+        self.deploy = Some(step);
+        self
+    }
+
+    pub fn render(&self) -> String {
+        let image_tag = self.image_tag;
+        let secret_flag = if self.git_credentials_secret.is_some() {
+            format!("--secret id={GIT_CREDENTIALS_SECRET_ID},env={GIT_CREDENTIALS_ENV} ")
+        } else {
+            String::new()
+        };
+
+        let mut out = String::from("steps:\n");
+        let build = match self.platform {
+            Some(platform) => {
+                if platform != BUILDER_PLATFORM {
+                    // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+                    let _ = writeln!(
+                        out,
+                        "  - name: gcr.io/cloud-builders/docker\n    \
+                         args: ['run', '--privileged', '--rm', '{BINFMT_IMAGE}', '--install', '{arch}']",
+                        arch = platform.arch(),
+                    );
+                }
+                // `--load` keeps the image in the local daemon for `images:`
+                // and the push step.
+                format!(
+                    "docker buildx build --platform {platform} --load {secret_flag}-t {image_tag} ."
+                )
+            }
+            None if self.buildx => {
+                format!("docker buildx build --load {secret_flag}-t {image_tag} .")
+            }
+            None => format!("docker build {secret_flag}-t {image_tag} ."),
+        };
+
+        // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+        let _ = write!(
+            out,
+            r#"  - name: gcr.io/cloud-builders/docker
+    entrypoint: bash
+    args:
+      - -c
+      - {build}
+    env:
+      - DOCKER_BUILDKIT=1
+"#
+        );
+        if self.git_credentials_secret.is_some() {
+            // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+            let _ = writeln!(out, "    secretEnv:\n      - {GIT_CREDENTIALS_ENV}");
+        }
+
+        if let Some(deploy) = &self.deploy {
+            // The deploy step needs the image in the registry; `images:`
+            // only pushes after all steps finish.
+            // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+            let _ = writeln!(
+                out,
+                "  - name: gcr.io/cloud-builders/docker\n    args: ['push', '{image_tag}']\n  \
+                 - name: {CLOUD_SDK_IMAGE}\n    entrypoint: gcloud\n    args:"
+            );
+            for arg in deploy.gcloud_args {
+                // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+                let _ = writeln!(out, "      - {}", yaml_quote(arg));
+            }
+        }
+
+        // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+        let _ = writeln!(out, "images:\n  - {image_tag}");
+
+        if let Some(bucket) = self.logs_bucket {
+            // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+            let _ = writeln!(out, "logsBucket: {bucket}");
+        }
+
+        if let Some(deploy) = &self.deploy {
+            // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+            let _ = writeln!(
+                out,
+                "serviceAccount: projects/{project}/serviceAccounts/{sa}",
+                project = self.project_id,
+                sa = deploy.service_account,
+            );
+            // A user-specified service account requires choosing where logs go.
+            if self.logs_bucket.is_none() {
+                // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+                let _ = writeln!(out, "options:\n  logging: CLOUD_LOGGING_ONLY");
+            }
+        }
+
+        if let Some(secret_name) = self.git_credentials_secret {
+            // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+            let _ = writeln!(
+                out,
+                "availableSecrets:\n  secretManager:\n    \
+                 - versionName: projects/{project}/secrets/{secret_name}/versions/latest\n      \
+                 env: {GIT_CREDENTIALS_ENV}",
+                project = self.project_id,
+            );
+        }
+        out
+    }
+}
+
+/// Single-quote a YAML scalar (`'` is escaped by doubling).
+fn yaml_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
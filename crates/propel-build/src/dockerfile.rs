@@ -1,7 +1,26 @@
-use std::fmt::Write;
+use std::collections::HashMap;
+use std::fmt::{self, Write};
 
 use propel_core::{BuildConfig, CargoProject};
 
+use crate::cargo_config::CargoConfig;
+use crate::manifest::MANIFEST_FILE;
+
+/// BuildKit secret id carrying git credentials into the build stages.
+///
+/// Cloud Build passes the Secret Manager value under this id (see
+/// [`crate::cloudbuild`]); the Dockerfile mounts it at
+/// `/run/secrets/git_credentials` for the duration of a single `RUN`.
+pub const GIT_CREDENTIALS_SECRET_ID: &str = "git_credentials";
+
+/// BuildKit cache mounts of the dependency and build `RUN` steps under
+/// [`BuildConfig::cache_mounts`].
+pub const CACHE_MOUNTS: &[&str] = &[
+    "/usr/local/cargo/registry",
+    "/usr/local/cargo/git",
+    "/app/target",
+];
+
 /// Generates an optimized multi-stage Dockerfile using Cargo Chef.
 ///
 /// The Dockerfile has four stages:
@@ -22,25 +41,249 @@ use propel_core::{BuildConfig, CargoProject};
 ///   `COPY` directives. The binary is always copied regardless.
 ///
 /// [`BuildConfig::env`] entries become `ENV` directives in the runtime stage.
+/// [`BuildConfig::build_env`] entries go into the chef base stage instead, so
+/// the cacher and builder stages compile with them and the runtime image
+/// never sees them.
+/// [`BuildConfig::runtime_packages`] are installed with `apt-get` right after
+/// `FROM`, which requires a Debian-based (non-distroless) runtime image.
+///
+/// # Runtime user
+///
+/// With [`BuildConfig::run_as_user`], the binary and bundle copies in the
+/// runtime stage are `COPY --chown` to that user, and `USER` is set just
+/// before `EXPOSE` — after the package installs, which need root. A numeric
+/// UID renders as `USER 65532:65532`; a name is used as written.
+///
+/// # Private dependencies
+///
+/// When [`BuildConfig::git_credentials_secret`] is set, the dependency and
+/// build `RUN` steps mount the credentials as a BuildKit secret and point
+/// git at them through `GIT_CONFIG_*` environment variables scoped to that
+/// command. Nothing is written to a layer, so the credentials appear in
+/// neither the builder stages nor the runtime image.
+///
+/// # Platform
+///
+/// [`BuildConfig::platform`] only adds a note to the header: the build runs
+/// under emulation for the target platform (see [`crate::cloudbuild`]), so
+/// the stages and the `target/release` binary path do not change. The one
+/// exception is [`BuildConfig::static_binary`], which picks the platform's
+/// musl target.
+///
+/// # Cargo config
+///
+/// With [`DockerfileOptions::cargo_config`] set, the cacher stage copies
+/// `.cargo/config.toml` and the vendored source directories it names before
+/// `cargo chef cook`, so source replacement and custom registries apply to
+/// the dependency build. The planner and builder stages see them through
+/// `COPY . .`.
+///
+/// # Locked and offline builds
+///
+/// `cargo chef cook` and `cargo build` get `--locked` when
+/// [`BuildConfig::is_locked`] says so for the bundle's lockfile (see
+/// [`DockerfileOptions::lockfile_bundled`]). [`BuildConfig::offline`]
+/// sets `CARGO_NET_OFFLINE=true` in the chef base stage, after cargo-chef
+/// is installed, so the cacher and builder stages inherit it.
+///
+/// # Features and RUSTFLAGS
+///
+/// [`BuildConfig::features`] and [`BuildConfig::no_default_features`] go to
+/// both `cargo chef cook` and `cargo build`, and [`BuildConfig::rustflags`]
+/// becomes `ENV RUSTFLAGS` in the chef base stage next to
+/// `CARGO_NET_OFFLINE`. The cached dependencies are therefore built exactly
+/// as the final build needs them; a mismatch would make `cargo build`
+/// recompile them. cargo-chef itself is installed before `RUSTFLAGS` is
+/// set, so a `target-cpu` flag cannot stop it from running on the build
+/// machine.
+///
+/// # Cache mounts
+///
+/// With [`BuildConfig::cache_mounts`], `cargo chef cook` and `cargo build`
+/// run with BuildKit cache mounts ([`CACHE_MOUNTS`]) for the cargo registry,
+/// git checkouts, and `/app/target`, and the file starts with
+/// `# syntax=docker/dockerfile:1`. A cache mount is not part of the layer,
+/// so the builder stage does not copy `target` from the cacher (the
+/// shared mount already holds the cooked dependencies) and copies the
+/// binary out to `/usr/local/bin/app` in the same `RUN` as `cargo build`;
+/// the runtime stage copies it from there.
+///
+/// # Target triple and static binaries
+///
+/// With [`BuildConfig::rust_target`] set (by [`BuildConfig::target`] or
+/// [`BuildConfig::static_binary`]), the chef base stage runs
+/// `rustup target add`, plus an `apt-get install musl-tools` for a musl
+/// target, before `RUSTFLAGS` is set. `cargo chef cook` and `cargo build`
+/// get `--target`, and the runtime stage copies the binary from
+/// `target/<target>/release/`. A static build runs `FROM`
+/// [`BuildConfig::effective_runtime_image`], which is
+/// [`BuildConfig::STATIC_RUNTIME_IMAGE`] unless `runtime_image` is set.
+///
+/// # Workspace members
+///
+/// For a workspace member ([`CargoProject::member_path`]) the bundle is
+/// the whole workspace ([`CargoProject::bundle_root`]), so `cargo chef
+/// cook` and `cargo build` get `--package <name>` and the binary comes
+/// from the workspace `target` directory. The runtime stage copies from
+/// the member's directory instead of the bundle root — `COPY api/ ./`, or
+/// `COPY api/migrations/ ./migrations/` for an [`BuildConfig::include`]
+/// entry — so the service sees the same files at the same paths as a
+/// standalone package would.
+///
+/// # Runtime compatibility
+///
+/// The builder links the binary against the builder image's libc, so a
+/// glibc build cannot start on an Alpine (musl) or `scratch` runtime — the
+/// container dies with a loader error before `main`. Callers run
+/// [`check_runtime_image`](Self::check_runtime_image) before
+/// [`render`](Self::render); see [`ImageFamily::classify`] for how images
+/// are told apart. A static build runs on any runtime image.
 pub struct DockerfileGenerator<'a> {
     config: &'a BuildConfig,
     project: &'a CargoProject,
     port: u16,
+    cargo_config: Option<&'a CargoConfig>,
+    lockfile_bundled: bool,
+}
+
+/// Port the generated Dockerfile `EXPOSE`s unless
+/// [`DockerfileOptions::port`] says otherwise — Cloud Run's default.
+pub const DEFAULT_PORT: u16 = 8080;
+
+/// Inputs of a [`DockerfileGenerator`].
+///
+/// Only `config` and `project` are required; everything else has a
+/// setter, so new inputs do not change the signature of
+/// [`DockerfileOptions::new`].
+///
+/// ```
+/// use propel_build::{DockerfileGenerator, DockerfileOptions};
+/// # let config = propel_core::BuildConfig::default();
+/// # let project = propel_core::CargoProject {
+/// #     name: "svc".to_owned(),
+/// #     version: "0.1.0".to_owned(),
+/// #     manifest_path: "Cargo.toml".into(),
+/// #     package_dir: ".".into(),
+/// #     workspace_root: ".".into(),
+/// #     binaries: Vec::new(),
+/// #     default_binary: "svc".to_owned(),
+/// # };
+///
+/// let options = DockerfileOptions::new(&config, &project)
+///     .port(3000)
+///     .lockfile_bundled(true);
+/// let dockerfile = DockerfileGenerator::with_options(options).render();
+/// assert!(dockerfile.contains("EXPOSE 3000"));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct DockerfileOptions<'a> {
+    config: &'a BuildConfig,
+    project: &'a CargoProject,
+    port: u16,
+    cargo_config: Option<&'a CargoConfig>,
+    lockfile_bundled: bool,
+}
+
+impl<'a> DockerfileOptions<'a> {
+    /// Options for `project` built with `config`, exposing [`DEFAULT_PORT`].
+    pub fn new(config: &'a BuildConfig, project: &'a CargoProject) -> Self {
+        Self {
+            config,
+            project,
+            port: DEFAULT_PORT,
+            cargo_config: None,
+            lockfile_bundled: false,
+        }
+    }
+
+    /// Port the runtime stage `EXPOSE`s.
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// Honor the project's `.cargo/config.toml` in the dependency build.
+    pub fn cargo_config(mut self, cargo_config: &'a CargoConfig) -> Self {
+        self.cargo_config = Some(cargo_config);
+        self
+    }
+
+    /// The bundle carries a `Cargo.lock`, so builds are locked unless
+    /// [`BuildConfig::locked`] says otherwise.
+    pub fn lockfile_bundled(mut self, bundled: bool) -> Self {
+        self.lockfile_bundled = bundled;
+        self
+    }
 }
 
 impl<'a> DockerfileGenerator<'a> {
-    pub fn new(config: &'a BuildConfig, project: &'a CargoProject, port: u16) -> Self {
+    /// Generator for `options`.
+    pub fn with_options(options: DockerfileOptions<'a>) -> Self {
+        let DockerfileOptions {
+            config,
+            project,
+            port,
+            cargo_config,
+            lockfile_bundled,
+        } = options;
         Self {
             config,
             project,
             port,
+            cargo_config,
+            lockfile_bundled,
         }
     }
 
+    #[cfg(feature = "deprecated-shims")]
+    #[deprecated(
+        since = "0.4.3",
+        note = "use `DockerfileGenerator::with_options(DockerfileOptions::new(config, project).port(port))`"
+    )]
+    pub fn new(config: &'a BuildConfig, project: &'a CargoProject, port: u16) -> Self {
+        Self::with_options(DockerfileOptions::new(config, project).port(port))
+    }
+
+    /// Honor the project's `.cargo/config.toml` in the dependency build.
+    #[cfg(feature = "deprecated-shims")]
+    #[deprecated(since = "0.4.3", note = "use `DockerfileOptions::cargo_config`")]
+    pub fn cargo_config(mut self, cargo_config: &'a CargoConfig) -> Self {
+        self.cargo_config = Some(cargo_config);
+        self
+    }
+
+    /// The bundle carries a `Cargo.lock`, so builds are locked unless
+    /// [`BuildConfig::locked`] says otherwise.
+    #[cfg(feature = "deprecated-shims")]
+    #[deprecated(since = "0.4.3", note = "use `DockerfileOptions::lockfile_bundled`")]
+    pub fn lockfile_bundled(mut self, bundled: bool) -> Self {
+        self.lockfile_bundled = bundled;
+        self
+    }
+
+    /// Check that the runtime image can execute a binary built on the
+    /// builder image, unless [`BuildConfig::runtime_image_check`] is off.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`propel_core::Error::IncompatibleRuntimeImage`] for a glibc
+    /// builder paired with a musl or empty runtime image, unless the build
+    /// is static ([`BuildConfig::is_static`]).
+    pub fn check_runtime_image(&self) -> propel_core::Result<()> {
+        if !self.config.runtime_image_check || self.config.is_static() {
+            return Ok(());
+        }
+        check_runtime_compat(
+            self.config.builder_image(),
+            self.config.effective_runtime_image(),
+        )
+    }
+
     pub fn render(&self) -> String {
         tracing::debug!(
-            base = %self.config.base_image,
-            runtime = %self.config.runtime_image,
+            base = %self.config.builder_image(),
+            runtime = %self.config.effective_runtime_image(),
+            target = ?self.config.rust_target(),
             binary = %self.project.default_binary,
             port = self.port,
             "generating Dockerfile"
@@ -54,14 +297,128 @@ impl<'a> DockerfileGenerator<'a> {
             )
         };
 
+        let runtime_packages = if self.config.runtime_packages.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "RUN apt-get update && apt-get install -y --no-install-recommends {} && rm -rf /var/lib/apt/lists/*\n",
+                self.config.runtime_packages.join(" ")
+            )
+        };
+
+        let (mut chef_env, mut fetch) = if self.config.git_credentials_secret.is_some() {
+            (
+                "ENV CARGO_NET_GIT_FETCH_WITH_CLI=true\n".to_owned(),
+                format!(
+                    "--mount=type=secret,id={id} \\\n    \
+                     GIT_CONFIG_COUNT=2 \\\n    \
+                     GIT_CONFIG_KEY_0=credential.helper \
+                     GIT_CONFIG_VALUE_0='store --file=/run/secrets/{id}' \\\n    \
+                     GIT_CONFIG_KEY_1=url.https://.insteadOf \
+                     GIT_CONFIG_VALUE_1=ssh://git@ \\\n    ",
+                    id = GIT_CREDENTIALS_SECRET_ID
+                ),
+            )
+        } else {
+            (String::new(), String::new())
+        };
+        // The secret and cache mounts both need the BuildKit frontend.
+        let syntax = if self.config.git_credentials_secret.is_some() || self.config.cache_mounts {
+            "# syntax=docker/dockerfile:1\n"
+        } else {
+            ""
+        };
+        if self.config.cache_mounts {
+            let mut mounts = String::new();
+            for target in CACHE_MOUNTS {
+                // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+                let _ = write!(mounts, "--mount=type=cache,target={target} ");
+            }
+            fetch = format!("{mounts}\\\n    {fetch}");
+        }
+        let (target_setup, target_dir) = match self.config.rust_target() {
+            Some(target) => {
+                let mut setup = format!("RUN rustup target add {target}\n");
+                if target.contains("-musl") {
+                    setup.push_str(
+                        "RUN apt-get update && apt-get install -y musl-tools && rm -rf /var/lib/apt/lists/*\n",
+                    );
+                }
+                (setup, format!("{target}/"))
+            }
+            None => (String::new(), String::new()),
+        };
+        if self.config.offline {
+            chef_env.push_str("ENV CARGO_NET_OFFLINE=true\n");
+        }
+        if let Some(rustflags) = &self.config.rustflags {
+            let rustflags = rustflags.replace('\\', "\\\\").replace('"', "\\\"");
+            // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+            let _ = writeln!(chef_env, "ENV RUSTFLAGS=\"{rustflags}\"");
+        }
+        let mut cargo_flags = self.config.cargo_flags(self.lockfile_bundled);
+        if self.project.member_path().is_some() {
+            // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+            let _ = write!(cargo_flags, " --package {}", self.project.name);
+        }
+        let release_binary = format!(
+            "target/{target_dir}release/{binary}",
+            binary = self.project.default_binary
+        );
+        // Under a cache mount `target` never reaches a layer, so the
+        // binary leaves the mount in the `RUN` that builds it.
+        let (cacher_target, copy_out, binary_path) = if self.config.cache_mounts {
+            (
+                "",
+                format!(" \\\n    && cp {release_binary} /usr/local/bin/app"),
+                "/usr/local/bin/app".to_owned(),
+            )
+        } else {
+            (
+                "COPY --from=cacher /app/target target\n",
+                String::new(),
+                format!("/app/{release_binary}"),
+            )
+        };
+
+        // Cloud Build emulates the target platform, so the stages are the
+        // same everywhere; only the images must exist for it.
+        let platform_note = match self.config.platform {
+            Some(platform) => format!(
+                "# Target platform: {platform} — the base and runtime images \
+                 must publish a {platform} variant\n"
+            ),
+            None => String::new(),
+        };
+        let (chef_heading, chef_install) = if self.config.chef_preinstalled {
+            ("cargo-chef preinstalled in image", String::new())
+        } else {
+            (
+                "cargo-chef installed once",
+                format!(
+                    "RUN cargo install cargo-chef --version {} --locked\n",
+                    self.config.cargo_chef_version
+                ),
+            )
+        };
+        let cargo_config_copies = self.render_cargo_config_copies();
         let runtime_copies = self.render_runtime_copies();
-        let env_directives = self.render_env_directives();
+        // Copy as the runtime user so it owns the binary and bundled files;
+        // USER comes last, after any root-only runtime package installs.
+        let (chown, user) = match &self.config.run_as_user {
+            Some(user) => (
+                format!(" --chown={}", user.chown()),
+                format!("USER {}\n", user.user_instruction()),
+            ),
+            None => (String::new(), String::new()),
+        };
+        let build_env_directives = render_env_directives(&self.config.build_env);
+        let env_directives = render_env_directives(&self.config.env);
 
         format!(
-            r#"# === Base: cargo-chef installed once ===
+            r#"{syntax}{platform_note}# === Base: {chef_heading} ===
 FROM {base} AS chef
-RUN cargo install cargo-chef --version {chef_version} --locked
-WORKDIR /app
+{chef_install}{target_setup}{chef_env}{build_env_directives}WORKDIR /app
 
 # === Stage 1: Planner ===
 FROM chef AS planner
@@ -70,34 +427,64 @@ RUN cargo chef prepare --recipe-path recipe.json
 
 # === Stage 2: Cacher (dependency build) ===
 FROM chef AS cacher
-{extra_packages}COPY --from=planner /app/recipe.json recipe.json
-RUN cargo chef cook --release --recipe-path recipe.json
+{extra_packages}{cargo_config_copies}COPY --from=planner /app/recipe.json recipe.json
+RUN {fetch}cargo chef cook --release{cargo_flags} --recipe-path recipe.json
 
 # === Stage 3: Builder ===
 FROM chef AS builder
-{extra_packages}COPY --from=cacher /app/target target
-COPY --from=cacher /usr/local/cargo /usr/local/cargo
+{extra_packages}{cacher_target}COPY --from=cacher /usr/local/cargo /usr/local/cargo
 COPY . .
-RUN cargo build --release --bin {binary}
+RUN {fetch}cargo build --release{cargo_flags} --bin {binary}{copy_out}
 
 # === Stage 4: Runtime ===
 FROM {runtime}
-COPY --from=builder /app/target/release/{binary} /usr/local/bin/app
+{runtime_packages}COPY{chown} --from=builder {binary_path} /usr/local/bin/app
 WORKDIR /app
-{runtime_copies}{env_directives}EXPOSE {port}
+{runtime_copies}{env_directives}{user}EXPOSE {port}
 CMD ["app"]
 "#,
-            base = self.config.base_image,
-            chef_version = self.config.cargo_chef_version,
-            runtime = self.config.runtime_image,
+            syntax = syntax,
+            platform_note = platform_note,
+            target_setup = target_setup,
+            chef_env = chef_env,
+            fetch = fetch,
+            cargo_flags = cargo_flags,
+            chef_heading = chef_heading,
+            chef_install = chef_install,
+            base = self.config.builder_image(),
+            runtime = self.config.effective_runtime_image(),
+            cacher_target = cacher_target,
+            copy_out = copy_out,
+            binary_path = binary_path,
             binary = self.project.default_binary,
             extra_packages = extra_packages,
+            runtime_packages = runtime_packages,
+            cargo_config_copies = cargo_config_copies,
             runtime_copies = runtime_copies,
+            build_env_directives = build_env_directives,
             env_directives = env_directives,
+            chown = chown,
+            user = user,
             port = self.port,
         )
     }
 
+    /// Generates COPY directives for `.cargo/config.toml` and vendored
+    /// sources in the cacher stage.
+    fn render_cargo_config_copies(&self) -> String {
+        let Some(cargo_config) = self.cargo_config else {
+            return String::new();
+        };
+        let mut out = String::new();
+        // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+        let _ = writeln!(out, "COPY {path} ./{path}", path = cargo_config.path);
+        for dir in &cargo_config.vendor_dirs {
+            // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+            let _ = writeln!(out, "COPY {dir}/ ./{dir}/");
+        }
+        out
+    }
+
     /// Generates COPY directives for the runtime stage.
     ///
     /// - `include = None`: copies entire build context (`COPY . .`)
@@ -105,42 +492,299 @@ CMD ["app"]
     ///
     /// Paths ending with `/` are treated as directories (`COPY dir/ ./dir/`).
     /// Paths without a trailing `/` are treated as files (`COPY file ./file`).
+    /// For a workspace member, sources are relative to the member's
+    /// directory in the bundle (`COPY api/ ./`, `COPY api/dir/ ./dir/`).
+    /// With [`BuildConfig::bundle_manifest`], the manifest is copied too.
+    /// With [`BuildConfig::run_as_user`], every copy is `--chown` to that user.
     fn render_runtime_copies(&self) -> String {
-        match &self.config.include {
-            None => "COPY . .\n".to_owned(),
-            Some(paths) if paths.is_empty() => String::new(),
-            Some(paths) => {
-                let mut out = String::new();
+        let copy = match &self.config.run_as_user {
+            Some(user) => format!("COPY --chown={}", user.chown()),
+            None => "COPY".to_owned(),
+        };
+        let member = self
+            .project
+            .member_path()
+            .map(|path| path.to_string_lossy().replace('\\', "/"));
+        let mut out = String::new();
+        match (&self.config.include, &member) {
+            (None, None) => return format!("{copy} . .\n"),
+            (None, Some(member)) => {
+                // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+                let _ = writeln!(out, "{copy} {member}/ ./");
+            }
+            (Some(paths), _) => {
+                let prefix = match &member {
+                    Some(member) => format!("{member}/"),
+                    None => String::new(),
+                };
                 for path in paths {
                     if path.ends_with('/') {
                         let trimmed = path.trim_end_matches('/');
                         // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
-                        let _ = writeln!(out, "COPY {trimmed}/ ./{trimmed}/");
+                        let _ = writeln!(out, "{copy} {prefix}{trimmed}/ ./{trimmed}/");
                     } else {
                         // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
-                        let _ = writeln!(out, "COPY {path} ./{path}");
+                        let _ = writeln!(out, "{copy} {prefix}{path} ./{path}");
                     }
                 }
-                out
             }
         }
+        if self.config.bundle_manifest {
+            // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+            let _ = writeln!(out, "{copy} {MANIFEST_FILE} ./{MANIFEST_FILE}");
+        }
+        out
     }
+}
 
-    /// Generates ENV directives from `[build.env]`.
-    fn render_env_directives(&self) -> String {
-        if self.config.env.is_empty() {
-            return String::new();
+/// Generates ENV directives for `env`, sorted by key.
+fn render_env_directives(env: &HashMap<String, String>) -> String {
+    let mut keys: Vec<&String> = env.keys().collect();
+    keys.sort();
+
+    let mut out = String::new();
+    for key in keys {
+        let value = &env[key];
+        // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+        let _ = writeln!(out, "ENV {key}={value}");
+    }
+    out
+}
+
+/// The C library an image provides, as far as its name tells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ImageFamily {
+    /// Debian, Ubuntu, distroless `cc`/`base`, the official `rust` images.
+    Glibc,
+    /// Alpine and musl toolchain images.
+    Musl,
+    /// No C library at all: `scratch`, distroless `static`.
+    Static,
+    /// Nothing in the name says; such images are never rejected.
+    Unknown,
+}
+
+impl ImageFamily {
+    /// Classify an image reference by its repository name and tag.
+    ///
+    /// ```
+    /// use propel_build::dockerfile::ImageFamily;
+    ///
+    /// assert_eq!(ImageFamily::classify("rust:1.93-bookworm"), ImageFamily::Glibc);
+    /// assert_eq!(ImageFamily::classify("alpine:3.20"), ImageFamily::Musl);
+    /// assert_eq!(ImageFamily::classify("gcr.io/distroless/static-debian12"), ImageFamily::Static);
+    /// ```
+    pub fn classify(image: &str) -> Self {
+        let image = image.to_ascii_lowercase();
+        let image = image
+            .split_once('@')
+            .map_or(image.as_str(), |(image, _digest)| image);
+        let (repository, tag) = match image.rsplit_once(':') {
+            // A ':' before the last '/' is a registry port, not a tag.
+            Some((repository, tag)) if !tag.contains('/') => (repository, tag),
+            _ => (image, ""),
+        };
+        let name = repository
+            .rsplit_once('/')
+            .map_or(repository, |(_path, name)| name);
+        let words: Vec<&str> = name
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .chain(tag.split(|c: char| !c.is_ascii_alphanumeric()))
+            .collect();
+        let has = |prefix: &str| words.iter().any(|word| word.starts_with(prefix));
+
+        let distroless = repository.contains("distroless/") || repository.contains("chainguard/");
+        if name == "scratch" || (distroless && (name == "static" || name.starts_with("static-"))) {
+            Self::Static
+        } else if has("alpine") || has("musl") {
+            Self::Musl
+        } else if matches!(name, "rust" | "debian" | "ubuntu")
+            || distroless
+            || GLIBC_WORDS.iter().any(|word| has(word))
+        {
+            Self::Glibc
+        } else {
+            Self::Unknown
         }
+    }
 
-        let mut keys: Vec<&String> = self.config.env.keys().collect();
-        keys.sort();
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Glibc => "glibc",
+            Self::Musl => "musl",
+            Self::Static => "no libc",
+            Self::Unknown => "unknown",
+        }
+    }
+}
 
-        let mut out = String::new();
-        for key in keys {
-            let value = &self.config.env[key];
-            // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
-            let _ = writeln!(out, "ENV {key}={value}");
+/// Name and tag words that mark a glibc image: distributions and their
+/// release codenames.
+const GLIBC_WORDS: &[&str] = &[
+    "debian", "ubuntu", "bookworm", "bullseye", "buster", "trixie", "jammy", "noble", "focal",
+    "glibc",
+];
+
+/// A Rust toolchain version: `1.83` or `1.83.0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RustVersion {
+    pub major: u64,
+    pub minor: u64,
+    /// `None` for a tag such as `rust:1.83`, which tracks the newest
+    /// `1.83.x` release.
+    pub patch: Option<u64>,
+}
+
+impl RustVersion {
+    /// Parse `major.minor` or `major.minor.patch`; anything else,
+    /// including a bare major version, is `None`.
+    ///
+    /// ```
+    /// use propel_build::dockerfile::RustVersion;
+    ///
+    /// assert_eq!(RustVersion::parse("1.83").unwrap().patch, None);
+    /// assert_eq!(RustVersion::parse("1.83.1").unwrap().patch, Some(1));
+    /// assert_eq!(RustVersion::parse("1"), None);
+    /// ```
+    pub fn parse(version: &str) -> Option<Self> {
+        let number = |part: &str| -> Option<u64> {
+            if part.is_empty() || !part.chars().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+            // arch-lint: allow(no-silent-result-drop) reason="an out-of-range number is just not a version"
+            part.parse().ok()
+        };
+        let mut parts = version.split('.');
+        let major = number(parts.next()?)?;
+        let minor = number(parts.next()?)?;
+        let patch = match parts.next() {
+            Some(part) => Some(number(part)?),
+            None => None,
+        };
+        if parts.next().is_some() {
+            return None;
         }
-        out
+        Some(Self {
+            major,
+            minor,
+            patch,
+        })
+    }
+
+    /// The Rust version an image reference's tag names, if any.
+    ///
+    /// For images whose name mentions Rust (`rust`, `muslrust`,
+    /// `rust-chef`), the first version-shaped word of the tag counts:
+    /// `rust:1.93-bookworm`, `rust:1.81.0-slim`. For other images only a
+    /// version right after a `rust` word does, so the cargo-chef version in
+    /// `lukemathwalker/cargo-chef:0.1.73-rust-1.83-bookworm` is skipped.
+    ///
+    /// ```
+    /// use propel_build::dockerfile::RustVersion;
+    ///
+    /// let version = RustVersion::from_image("lukemathwalker/cargo-chef:0.1.73-rust-1.83-bookworm");
+    /// assert_eq!(version, RustVersion::parse("1.83"));
+    /// assert_eq!(RustVersion::from_image("rust:latest"), None);
+    /// ```
+    pub fn from_image(image: &str) -> Option<Self> {
+        let image = image
+            .split_once('@')
+            .map_or(image, |(image, _digest)| image);
+        let (repository, tag) = image
+            .rsplit_once(':')
+            .filter(|(_, tag)| !tag.contains('/'))?;
+        let name = repository
+            .rsplit_once('/')
+            .map_or(repository, |(_path, name)| name)
+            .to_ascii_lowercase();
+        let words: Vec<&str> = tag.split('-').collect();
+        let after_rust = words
+            .windows(2)
+            .find(|pair| pair[0].eq_ignore_ascii_case("rust"))
+            .and_then(|pair| Self::parse(pair[1]));
+        if after_rust.is_some() || !name.contains("rust") {
+            return after_rust;
+        }
+        words.iter().find_map(|word| Self::parse(word))
+    }
+
+    /// Whether a toolchain of this version can build a package with
+    /// `rust-version = msrv`. A version without a patch is the newest
+    /// patch release of its minor version.
+    pub fn satisfies(self, msrv: Self) -> bool {
+        let patch = self.patch.map_or(u64::MAX, |patch| patch);
+        (self.major, self.minor, patch) >= (msrv.major, msrv.minor, msrv.patch.map_or(0, |p| p))
+    }
+}
+
+impl fmt::Display for RustVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)?;
+        if let Some(patch) = self.patch {
+            write!(f, ".{patch}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Check that `builder` has a Rust toolchain at least as new as the
+/// package's `rust-version`.
+///
+/// Returns the builder's version, or `None` when its tag names none (a
+/// custom image, `rust:latest`) and nothing was checked. An unparseable
+/// `rust_version` is not checked either.
+///
+/// # Errors
+///
+/// Returns [`propel_core::Error::BuilderImageTooOld`] when the builder's
+/// Rust is older than `rust_version`.
+pub fn check_builder_rust_version(
+    builder: &str,
+    rust_version: &str,
+) -> propel_core::Result<Option<RustVersion>> {
+    let Some(builder_version) = RustVersion::from_image(builder) else {
+        return Ok(None);
+    };
+    match RustVersion::parse(rust_version) {
+        Some(msrv) if !builder_version.satisfies(msrv) => {
+            Err(propel_core::Error::BuilderImageTooOld {
+                image: builder.to_owned(),
+                builder_version: builder_version.to_string(),
+                rust_version: rust_version.to_owned(),
+            })
+        }
+        _ => Ok(Some(builder_version)),
+    }
+}
+
+/// Check that `runtime` can execute a binary built on `builder`.
+///
+/// A musl builder produces a static binary, which runs anywhere. A glibc
+/// builder links glibc dynamically, which only a glibc runtime provides.
+/// Images [`ImageFamily::classify`] cannot place are let through.
+///
+/// # Errors
+///
+/// Returns [`propel_core::Error::IncompatibleRuntimeImage`] for a glibc
+/// builder paired with a musl or empty runtime image.
+pub fn check_runtime_compat(builder: &str, runtime: &str) -> propel_core::Result<()> {
+    let runtime_family = ImageFamily::classify(runtime);
+    if ImageFamily::classify(builder) != ImageFamily::Glibc
+        || !matches!(runtime_family, ImageFamily::Musl | ImageFamily::Static)
+    {
+        return Ok(());
     }
+    Err(propel_core::Error::IncompatibleRuntimeImage {
+        runtime: runtime.to_owned(),
+        reason: format!(
+            "the builder image {builder:?} links the binary against glibc, which this \
+             {family} image lacks, so the container would exit at startup with a loader \
+             error (\"exec ...: no such file or directory\"). Use a glibc runtime image such \
+             as \"gcr.io/distroless/cc-debian12\" or \"debian:bookworm-slim\", or set \
+             [build].static_binary = true to build a static musl binary. If the image does \
+             provide glibc, set [build].runtime_image_check = false",
+            family = runtime_family.as_str(),
+        ),
+    })
 }
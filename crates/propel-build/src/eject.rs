@@ -1,9 +1,30 @@
 use std::path::Path;
 
+/// Written to `.propel/README.md` next to the ejected Dockerfile.
+pub const EJECT_README: &str = "\
+# .propel/
+
+`propel eject` wrote this directory. Commit it to git: deploys from CI
+only see files that are tracked.
+
+- `Dockerfile` — used by `propel deploy` instead of the generated one.
+  Settings in `propel.toml` that shape the Dockerfile (`[build]` images,
+  packages, `include`, `env`) no longer apply; edit this file instead.
+- `.gitignore` — keeps local build logs (`*.log`) out of git.
+
+`.propel-bundle/` is a different directory: deploy recreates it on every
+run, and it should stay ignored.
+
+To go back to the generated Dockerfile, delete `.propel/Dockerfile`. To
+regenerate it from the current `propel.toml`, delete it and run
+`propel eject` again.
+";
+
 /// Ejects build configuration files into the project directory.
 ///
 /// After ejecting, `propel deploy` will use `.propel/Dockerfile`
-/// instead of generating one.
+/// instead of generating one. A `.propel/README.md` explaining that is
+/// written alongside unless one already exists.
 pub fn eject(project_dir: &Path, dockerfile_content: &str) -> Result<(), EjectError> {
     let propel_dir = project_dir.join(".propel");
     std::fs::create_dir_all(&propel_dir).map_err(|e| EjectError::CreateDir {
@@ -21,6 +42,14 @@ pub fn eject(project_dir: &Path, dockerfile_content: &str) -> Result<(), EjectEr
         source: e,
     })?;
 
+    let readme_path = propel_dir.join("README.md");
+    if !readme_path.exists() {
+        std::fs::write(&readme_path, EJECT_README).map_err(|e| EjectError::Write {
+            path: readme_path,
+            source: e,
+        })?;
+    }
+
     Ok(())
 }
 
@@ -35,7 +64,91 @@ pub fn load_ejected_dockerfile(project_dir: &Path) -> Result<String, EjectError>
     std::fs::read_to_string(&path).map_err(|e| EjectError::Read { path, source: e })
 }
 
+/// Where a Dockerfile names the binary it builds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryReferenceKind {
+    /// `cargo build --bin <name>` (or `--bin=<name>`).
+    BinFlag,
+    /// A `target/release/<name>` path, as in the runtime stage's `COPY`.
+    ReleasePath,
+}
+
+/// A binary name found in a Dockerfile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryReference {
+    /// 1-based line number.
+    pub line: usize,
+    pub kind: BinaryReferenceKind,
+    pub name: String,
+}
+
+/// Cargo's own directories under `target/release/`, never a binary.
+const RELEASE_DIRS: &[&str] = &["build", "deps", "examples", "incremental"];
+
+/// Every `--bin <name>` and `target/release/<name>` in `dockerfile`, in
+/// order. Comment lines are skipped, and so are names built from
+/// variables or globs (`${BIN}`, `app*`), which cannot be checked.
+pub fn binary_references(dockerfile: &str) -> Vec<BinaryReference> {
+    let mut found = Vec::new();
+    for (index, line) in dockerfile.lines().enumerate() {
+        if line.trim_start().starts_with('#') {
+            continue;
+        }
+        let mut push = |kind, name: Option<&str>| {
+            if let Some(name) = name {
+                found.push(BinaryReference {
+                    line: index + 1,
+                    kind,
+                    name: name.to_owned(),
+                });
+            }
+        };
+        let mut tokens = line.split_whitespace();
+        while let Some(token) = tokens.next() {
+            if token == "--bin" {
+                push(
+                    BinaryReferenceKind::BinFlag,
+                    tokens.next().and_then(binary_name),
+                );
+            } else if let Some(name) = token.strip_prefix("--bin=") {
+                push(BinaryReferenceKind::BinFlag, binary_name(name));
+            }
+        }
+        for (start, _) in line.match_indices("target/release/") {
+            let name = binary_name(&line[start + "target/release/".len()..])
+                .filter(|name| !RELEASE_DIRS.contains(name));
+            push(BinaryReferenceKind::ReleasePath, name);
+        }
+    }
+    found
+}
+
+/// References in `dockerfile` to a binary other than `binary`, e.g. the
+/// old name after the crate was renamed.
+pub fn mismatched_binary_references(dockerfile: &str, binary: &str) -> Vec<BinaryReference> {
+    binary_references(dockerfile)
+        .into_iter()
+        .filter(|reference| reference.name != binary)
+        .collect()
+}
+
+/// The binary name at the start of `text`, when it ends where a name can:
+/// at the end, whitespace, a path separator, a quote, or a JSON-array
+/// delimiter.
+fn binary_name(text: &str) -> Option<&str> {
+    let end = match text.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-')) {
+        Some(end) => end,
+        None => text.len(),
+    };
+    let (name, rest) = text.split_at(end);
+    let ends_cleanly = rest.is_empty()
+        || rest.starts_with(char::is_whitespace)
+        || rest.starts_with(['/', '"', '\'', ',', ']', ';']);
+    (!name.is_empty() && ends_cleanly).then_some(name)
+}
+
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum EjectError {
     #[error("failed to create .propel directory at {path}")]
     CreateDir {
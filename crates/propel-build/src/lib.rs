@@ -8,6 +8,7 @@
 //!   2. Bundle      ── git ls-files → .propel-bundle/
 //!   3. Dockerfile   ── DockerfileGenerator::render()
 //!   4. Cloud Build  ── gcloud builds submit .propel-bundle/
+//!                      (with cloudbuild.yaml when build secrets are needed)
 //!   5. Cloud Run    ── gcloud run deploy
 //! ```
 //!
@@ -17,15 +18,44 @@
 //! - All tracked and untracked (non-ignored) files via `git ls-files`
 //! - `.gitignore`d paths are excluded automatically
 //! - `.propel-bundle/`, `.propel/`, `.git/` are always excluded
+//! - `.cargo/config.toml` is always included, even when ignored
+//!
+//! Outside a git repository the directory tree is walked instead, with
+//! the same `.gitignore` rules and exclusions.
 //!
 //! # Runtime content
 //!
 //! The generated Dockerfile's runtime stage varies based on `[build.include]`:
 //! - **Omitted**: `COPY . .` — full bundle goes into the container
 //! - **Specified**: individual `COPY` per path — selective runtime content
+//!
+//! With `bundle_manifest = true`, `bundle-manifest.json` (see [`manifest`])
+//! is written into the bundle and always reaches the runtime image.
+//!
+//! # Stability
+//!
+//! Tools embedding this crate can rely on these call patterns across
+//! minor releases (`tests/public_api.rs` compiles each of them):
+//!
+//! - [`DockerfileGenerator::with_options`] with [`DockerfileOptions`]
+//! - [`bundle::create_bundle_with`] and
+//!   [`bundle::create_bundle_with_progress`] with [`bundle::BundleOptions`]
+//! - [`bundle::is_dirty`], [`bundle::check_project_dir`],
+//!   [`eject::eject`], and [`CargoConfig::detect`]
+//!
+//! Inputs grow through setters on the options structs, never through new
+//! positional parameters. Error enums and [`dockerfile::ImageFamily`] are
+//! `#[non_exhaustive]`, so match them with a wildcard arm. Replaced
+//! functions stay for one minor release as `#[deprecated]` shims behind
+//! the default `deprecated-shims` feature; with `default-features = false`
+//! only the stable API above is compiled.
 
 pub mod bundle;
+pub mod cargo_config;
+pub mod cloudbuild;
 pub mod dockerfile;
 pub mod eject;
+pub mod manifest;
 
-pub use dockerfile::DockerfileGenerator;
+pub use cargo_config::CargoConfig;
+pub use dockerfile::{DockerfileGenerator, DockerfileOptions};
@@ -0,0 +1,205 @@
+//! Reproducibility manifest for a bundle.
+//!
+//! With `[build] bundle_manifest = true`, deploy writes
+//! `bundle-manifest.json` into the bundle: the relative path, size, and
+//! SHA-256 of every file, the Dockerfile hash, the git commit, and the
+//! propel version. The manifest travels into the runtime image, so the
+//! sources behind a running image can be proven after the fact, and its own
+//! hash identifies the whole bundle.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::bundle::{BundleError, bundle_entries};
+
+/// File name of the manifest, at the bundle root and in `/app` at runtime.
+pub const MANIFEST_FILE: &str = "bundle-manifest.json";
+
+/// One bundled file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ManifestEntry {
+    /// Path relative to the bundle root, `/`-separated.
+    pub path: String,
+    pub size: u64,
+    /// Lowercase hex SHA-256 of the contents.
+    pub sha256: String,
+}
+
+/// Contents of `bundle-manifest.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BundleManifest {
+    pub propel_version: String,
+    /// `HEAD` of the bundled repository, if known.
+    pub git_commit: Option<String>,
+    pub dockerfile_sha256: String,
+    /// Every file except the manifest itself, sorted by path.
+    pub files: Vec<ManifestEntry>,
+}
+
+impl BundleManifest {
+    /// Hash every file in `bundle_dir`, reading files in parallel.
+    ///
+    /// A manifest already in the bundle is left out, so rebuilding one over
+    /// an existing bundle gives the same result.
+    pub fn build(
+        bundle_dir: &Path,
+        git_commit: Option<&str>,
+        propel_version: &str,
+    ) -> Result<Self, ManifestError> {
+        let paths: Vec<PathBuf> = bundle_entries(bundle_dir)
+            .map_err(|e| ManifestError::List { source: e })?
+            .into_iter()
+            .filter(|p| p != Path::new(MANIFEST_FILE))
+            .collect();
+        let files = hash_files(bundle_dir, &paths)?;
+
+        let dockerfile_sha256 = match files.iter().find(|f| f.path == "Dockerfile") {
+            Some(entry) => entry.sha256.clone(),
+            None => {
+                return Err(ManifestError::MissingDockerfile {
+                    bundle_dir: bundle_dir.to_owned(),
+                });
+            }
+        };
+
+        Ok(Self {
+            propel_version: propel_version.to_owned(),
+            git_commit: git_commit.map(str::to_owned),
+            dockerfile_sha256,
+            files,
+        })
+    }
+
+    /// Write the manifest to `bundle_dir` and return its own SHA-256.
+    pub fn write(&self, bundle_dir: &Path) -> Result<String, ManifestError> {
+        let mut json =
+            serde_json::to_vec_pretty(self).map_err(|e| ManifestError::Serialize { source: e })?;
+        json.push(b'\n');
+        let path = bundle_dir.join(MANIFEST_FILE);
+        std::fs::write(&path, &json).map_err(|e| ManifestError::Write { path, source: e })?;
+        Ok(hex(&Sha256::digest(&json)))
+    }
+}
+
+/// Hash `paths` (relative to `root`) on up to one thread per CPU, keeping
+/// their order.
+fn hash_files(root: &Path, paths: &[PathBuf]) -> Result<Vec<ManifestEntry>, ManifestError> {
+    map_files(paths, |path| hash_file(root, path))
+}
+
+/// The `paths` whose copy under `bundle_dir` no longer matches the file
+/// under `source` — rewritten or removed since it was copied — in the order
+/// of `paths`. Hashed like [`BundleManifest::build`].
+pub(crate) fn changed_files(
+    source: &Path,
+    bundle_dir: &Path,
+    paths: &[PathBuf],
+) -> Result<Vec<PathBuf>, ManifestError> {
+    let changed = map_files(paths, |path| {
+        let copied = hash_file(bundle_dir, path)?;
+        let original = match hash_file(source, path) {
+            Ok(entry) => Some(entry),
+            Err(ManifestError::Read { source, .. })
+                if source.kind() == std::io::ErrorKind::NotFound =>
+            {
+                None
+            }
+            Err(e) => return Err(e),
+        };
+        Ok((original.as_ref() != Some(&copied)).then(|| path.to_path_buf()))
+    })?;
+    Ok(changed.into_iter().flatten().collect())
+}
+
+/// Run `f` over `paths` on up to one thread per CPU, keeping their order.
+fn map_files<T: Send>(
+    paths: &[PathBuf],
+    f: impl Fn(&Path) -> Result<T, ManifestError> + Sync,
+) -> Result<Vec<T>, ManifestError> {
+    if paths.is_empty() {
+        return Ok(Vec::new());
+    }
+    let threads = std::thread::available_parallelism().map_or(1, usize::from);
+    let chunk_size = paths.len().div_ceil(threads);
+    let f = &f;
+
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = paths
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|path| f(path))
+                        .collect::<Result<Vec<_>, _>>()
+                })
+            })
+            .collect();
+
+        let mut results = Vec::with_capacity(paths.len());
+        for worker in workers {
+            results.extend(worker.join().expect("hashing thread panicked")?);
+        }
+        Ok(results)
+    })
+}
+
+/// Stream one file through SHA-256 without reading it into memory whole.
+fn hash_file(root: &Path, relative_path: &Path) -> Result<ManifestEntry, ManifestError> {
+    let path = root.join(relative_path);
+    let read_error = |source| ManifestError::Read {
+        path: path.clone(),
+        source,
+    };
+
+    let mut file = std::fs::File::open(&path).map_err(read_error)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut size = 0u64;
+    loop {
+        let n = file.read(&mut buf).map_err(read_error)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        size += n as u64;
+    }
+
+    let components: Vec<_> = relative_path
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect();
+    Ok(ManifestEntry {
+        path: components.join("/"),
+        size,
+        sha256: hex(&hasher.finalize()),
+    })
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum ManifestError {
+    #[error("failed to list bundle files")]
+    List { source: BundleError },
+    #[error("failed to read {path}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("bundle {bundle_dir} has no Dockerfile")]
+    MissingDockerfile { bundle_dir: PathBuf },
+    #[error("failed to serialize bundle manifest")]
+    Serialize { source: serde_json::Error },
+    #[error("failed to write bundle manifest {path}")]
+    Write {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
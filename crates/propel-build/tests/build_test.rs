@@ -2,10 +2,24 @@ use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
-use propel_build::bundle::{create_bundle, is_dirty};
-use propel_build::dockerfile::DockerfileGenerator;
-use propel_build::eject::{eject, is_ejected, load_ejected_dockerfile};
-use propel_core::{BuildConfig, CargoBinary, CargoProject};
+use propel_build::bundle::{
+    BUNDLE_DIR, BundleError, BundleOptions, BundleSize, bundle_entries, bundle_files,
+    check_project_dir, create_bundle_with, create_bundle_with_progress, enclosing_bundle_root,
+    format_bundle_summary, format_size, is_dirty, is_ignored, is_tracked, lockfile_bundled,
+    short_head_commit, write_cloudbuild_config,
+};
+use propel_build::cargo_config::CargoConfig;
+use propel_build::cloudbuild::{CloudBuildYaml, DeployStep};
+use propel_build::dockerfile::{
+    DockerfileGenerator, DockerfileOptions, ImageFamily, RustVersion, check_builder_rust_version,
+    check_runtime_compat,
+};
+use propel_build::eject::{
+    BinaryReference, BinaryReferenceKind, EJECT_README, binary_references, eject, is_ejected,
+    load_ejected_dockerfile, mismatched_binary_references,
+};
+use propel_build::manifest::{BundleManifest, MANIFEST_FILE, ManifestError};
+use propel_core::{BuildConfig, CargoBinary, CargoProject, Platform, RunAsUser};
 use tempfile::TempDir;
 
 fn default_project() -> CargoProject {
@@ -28,7 +42,11 @@ fn init_git_project(dir: &Path) {
     std::fs::create_dir_all(dir.join("src")).unwrap();
     std::fs::write(dir.join("Cargo.toml"), "[package]\nname = \"test\"").unwrap();
     std::fs::write(dir.join("src/main.rs"), "fn main() {}").unwrap();
+    git_commit_all(dir);
+}
 
+/// `git init` `dir` and commit everything in it.
+fn git_commit_all(dir: &Path) {
     Command::new("git")
         .args(["init"])
         .current_dir(dir)
@@ -56,13 +74,22 @@ fn init_git_project(dir: &Path) {
         .unwrap();
 }
 
+/// `.cargo/config.toml` replacing crates.io with vendored sources.
+const VENDORED_CARGO_CONFIG: &str = r#"
+[source.crates-io]
+replace-with = "vendored-sources"
+
+[source.vendored-sources]
+directory = "vendor"
+"#;
+
 // ── Dockerfile Generation Tests ──
 
 #[test]
 fn dockerfile_contains_cargo_chef_stages() {
     let config = BuildConfig::default();
     let project = default_project();
-    let generator = DockerfileGenerator::new(&config, &project, 8080);
+    let generator = DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project));
     let output = generator.render();
 
     assert!(output.contains("Stage 1: Planner"));
@@ -82,13 +109,70 @@ fn dockerfile_uses_configured_images() {
         ..Default::default()
     };
     let project = default_project();
-    let generator = DockerfileGenerator::new(&config, &project, 8080);
+    let generator = DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project));
     let output = generator.render();
 
     assert!(output.contains("FROM rust:1.82-slim AS chef"));
     assert!(output.contains("FROM debian:bookworm-slim"));
 }
 
+#[test]
+fn dockerfile_installs_cargo_chef_by_default() {
+    let config = BuildConfig::default();
+    let output =
+        DockerfileGenerator::with_options(DockerfileOptions::new(&config, &default_project()))
+            .render();
+
+    assert!(output.contains("FROM rust:1.93-bookworm AS chef"));
+    assert!(output.contains("RUN cargo install cargo-chef --version 0.1.73 --locked"));
+}
+
+#[test]
+fn dockerfile_chef_preinstalled_skips_install() {
+    let config = BuildConfig {
+        chef_preinstalled: true,
+        ..Default::default()
+    };
+    let output =
+        DockerfileGenerator::with_options(DockerfileOptions::new(&config, &default_project()))
+            .render();
+
+    assert!(output.contains("FROM rust:1.93-bookworm AS chef\nWORKDIR /app"));
+    assert!(!output.contains("cargo install cargo-chef"));
+    assert!(output.contains("cargo chef cook --release"));
+}
+
+#[test]
+fn dockerfile_chef_image_replaces_chef_base() {
+    let config = BuildConfig {
+        chef_image: Some("registry.example.com/rust-chef:1.93".to_owned()),
+        ..Default::default()
+    };
+    let output =
+        DockerfileGenerator::with_options(DockerfileOptions::new(&config, &default_project()))
+            .render();
+
+    assert!(output.contains("FROM registry.example.com/rust-chef:1.93 AS chef"));
+    assert!(!output.contains("rust:1.93-bookworm"));
+    assert!(output.contains("RUN cargo install cargo-chef --version 0.1.73 --locked"));
+    assert!(output.contains("FROM chef AS builder"));
+}
+
+#[test]
+fn dockerfile_chef_image_with_chef_preinstalled() {
+    let config = BuildConfig {
+        chef_image: Some("registry.example.com/rust-chef:1.93".to_owned()),
+        chef_preinstalled: true,
+        ..Default::default()
+    };
+    let output =
+        DockerfileGenerator::with_options(DockerfileOptions::new(&config, &default_project()))
+            .render();
+
+    assert!(output.contains("FROM registry.example.com/rust-chef:1.93 AS chef\nWORKDIR /app"));
+    assert!(!output.contains("cargo install cargo-chef"));
+}
+
 #[test]
 fn dockerfile_includes_extra_packages() {
     let config = BuildConfig {
@@ -96,7 +180,7 @@ fn dockerfile_includes_extra_packages() {
         ..Default::default()
     };
     let project = default_project();
-    let generator = DockerfileGenerator::new(&config, &project, 8080);
+    let generator = DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project));
     let output = generator.render();
 
     assert!(output.contains("apt-get install -y libssl-dev pkg-config"));
@@ -106,12 +190,28 @@ fn dockerfile_includes_extra_packages() {
 fn dockerfile_no_extra_packages_when_empty() {
     let config = BuildConfig::default();
     let project = default_project();
-    let generator = DockerfileGenerator::new(&config, &project, 8080);
+    let generator = DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project));
     let output = generator.render();
 
     assert!(!output.contains("apt-get install"));
 }
 
+#[test]
+fn dockerfile_installs_runtime_packages_in_runtime_stage() {
+    let config = BuildConfig {
+        runtime_image: "debian:bookworm-slim".to_owned(),
+        runtime_packages: vec!["libpq5".to_owned()],
+        ..Default::default()
+    };
+    let project = default_project();
+    let generator = DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project));
+    let output = generator.render();
+
+    let (build_stages, runtime_section) = output.split_once("Stage 4: Runtime").unwrap();
+    assert!(runtime_section.contains("apt-get install -y --no-install-recommends libpq5"));
+    assert!(!build_stages.contains("libpq5"));
+}
+
 #[test]
 fn dockerfile_uses_custom_binary_name() {
     let config = BuildConfig::default();
@@ -127,7 +227,7 @@ fn dockerfile_uses_custom_binary_name() {
         }],
         default_binary: "custom-bin".to_owned(),
     };
-    let generator = DockerfileGenerator::new(&config, &project, 8080);
+    let generator = DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project));
     let output = generator.render();
 
     assert!(output.contains("--bin custom-bin"));
@@ -138,7 +238,7 @@ fn dockerfile_uses_custom_binary_name() {
 fn dockerfile_exposes_port_8080() {
     let config = BuildConfig::default();
     let project = default_project();
-    let generator = DockerfileGenerator::new(&config, &project, 8080);
+    let generator = DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project));
     let output = generator.render();
 
     assert!(output.contains("EXPOSE 8080"));
@@ -148,7 +248,8 @@ fn dockerfile_exposes_port_8080() {
 fn dockerfile_exposes_custom_port() {
     let config = BuildConfig::default();
     let project = default_project();
-    let generator = DockerfileGenerator::new(&config, &project, 3000);
+    let generator =
+        DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project).port(3000));
     let output = generator.render();
 
     assert!(output.contains("EXPOSE 3000"));
@@ -161,7 +262,7 @@ fn dockerfile_exposes_custom_port() {
 fn dockerfile_default_include_none_copies_all() {
     let config = BuildConfig::default();
     let project = default_project();
-    let generator = DockerfileGenerator::new(&config, &project, 8080);
+    let generator = DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project));
     let output = generator.render();
 
     // include=None → runtime gets COPY . .
@@ -176,7 +277,7 @@ fn dockerfile_include_some_copies_only_specified() {
         ..Default::default()
     };
     let project = default_project();
-    let generator = DockerfileGenerator::new(&config, &project, 8080);
+    let generator = DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project));
     let output = generator.render();
 
     // Should have individual COPY directives, not COPY . .
@@ -196,7 +297,7 @@ fn dockerfile_include_empty_vec_no_runtime_copy() {
         ..Default::default()
     };
     let project = default_project();
-    let generator = DockerfileGenerator::new(&config, &project, 8080);
+    let generator = DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project));
     let output = generator.render();
 
     let runtime_section = output.split("Stage 4: Runtime").nth(1).unwrap();
@@ -217,18 +318,42 @@ fn dockerfile_build_env_generates_env_directives() {
         ..Default::default()
     };
     let project = default_project();
-    let generator = DockerfileGenerator::new(&config, &project, 8080);
+    let generator = DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project));
     let output = generator.render();
 
     assert!(output.contains("ENV LUA_DIR=/app/lua"));
     assert!(output.contains("ENV TEMPLATE_DIR=/app/templates"));
 }
 
+#[test]
+fn dockerfile_build_env_goes_into_chef_base_not_runtime() {
+    let config = BuildConfig {
+        build_env: HashMap::from([
+            ("PROTO_DIR".to_owned(), "/app/proto".to_owned()),
+            ("BUILD_MODE".to_owned(), "ci".to_owned()),
+        ]),
+        env: HashMap::from([("APP_ENV".to_owned(), "production".to_owned())]),
+        ..Default::default()
+    };
+    let project = default_project();
+    let output =
+        DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project)).render();
+
+    let (chef, rest) = output.split_once("# === Stage 1: Planner ===").unwrap();
+    let (build_stages, runtime) = rest.split_once("# === Stage 4: Runtime ===").unwrap();
+    assert!(chef.contains("ENV BUILD_MODE=ci\nENV PROTO_DIR=/app/proto\n"));
+    assert!(!chef.contains("APP_ENV"));
+    assert!(!build_stages.contains("ENV "), "stages inherit from chef");
+    assert!(runtime.contains("ENV APP_ENV=production"));
+    assert!(!runtime.contains("PROTO_DIR"));
+    assert!(chef.find("ENV PROTO_DIR").unwrap() < chef.find("WORKDIR /app").unwrap());
+}
+
 #[test]
 fn dockerfile_no_env_when_empty() {
     let config = BuildConfig::default();
     let project = default_project();
-    let generator = DockerfileGenerator::new(&config, &project, 8080);
+    let generator = DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project));
     let output = generator.render();
 
     assert!(!output.contains("ENV "));
@@ -241,7 +366,7 @@ fn dockerfile_include_file_not_treated_as_directory() {
         ..Default::default()
     };
     let project = default_project();
-    let generator = DockerfileGenerator::new(&config, &project, 8080);
+    let generator = DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project));
     let output = generator.render();
 
     let runtime_section = output.split("Stage 4: Runtime").nth(1).unwrap();
@@ -268,7 +393,7 @@ fn dockerfile_include_mixed_files_and_dirs() {
         ..Default::default()
     };
     let project = default_project();
-    let generator = DockerfileGenerator::new(&config, &project, 8080);
+    let generator = DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project));
     let output = generator.render();
 
     let runtime_section = output.split("Stage 4: Runtime").nth(1).unwrap();
@@ -281,194 +406,1280 @@ fn dockerfile_include_mixed_files_and_dirs() {
     assert!(!runtime_section.contains("COPY . ."));
 }
 
-// ── Bundle Tests ──
+// ── Private Dependency Tests ──
 
 #[test]
-fn bundle_creates_expected_structure() {
-    let tmp = TempDir::new().unwrap();
-    let project = tmp.path();
-    init_git_project(project);
-
-    let bundle_dir = create_bundle(project, "FROM rust\n").unwrap();
+fn dockerfile_mounts_git_credentials_for_fetching_stages() {
+    let config = BuildConfig {
+        git_credentials_secret: Some("GIT_CREDENTIALS".to_owned()),
+        ..Default::default()
+    };
+    let project = default_project();
+    let output =
+        DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project)).render();
+
+    assert!(output.starts_with("# syntax=docker/dockerfile:1\n"));
+    assert!(output.contains("ENV CARGO_NET_GIT_FETCH_WITH_CLI=true"));
+    assert_eq!(
+        output
+            .matches("RUN --mount=type=secret,id=git_credentials")
+            .count(),
+        2,
+        "cacher and builder stages must both mount the secret"
+    );
+    assert!(output.contains("GIT_CONFIG_VALUE_0='store --file=/run/secrets/git_credentials'"));
+    assert!(output.contains("GIT_CONFIG_KEY_1=url.https://.insteadOf"));
+}
 
-    assert!(bundle_dir.join("Dockerfile").exists());
-    assert!(bundle_dir.join("Cargo.toml").exists());
-    assert!(bundle_dir.join("src/main.rs").exists());
+#[test]
+fn dockerfile_keeps_git_credentials_out_of_runtime_stage() {
+    let config = BuildConfig {
+        git_credentials_secret: Some("GIT_CREDENTIALS".to_owned()),
+        ..Default::default()
+    };
+    let project = default_project();
+    let output =
+        DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project)).render();
 
-    let dockerfile = std::fs::read_to_string(bundle_dir.join("Dockerfile")).unwrap();
-    assert_eq!(dockerfile, "FROM rust\n");
+    let runtime_section = output.split("Stage 4: Runtime").nth(1).unwrap();
+    assert!(!runtime_section.contains("git_credentials"));
+    assert!(!runtime_section.contains("GIT_CONFIG"));
+    assert!(!runtime_section.contains("CARGO_NET_GIT_FETCH_WITH_CLI"));
 }
 
 #[test]
-fn bundle_includes_additional_dirs() {
-    let tmp = TempDir::new().unwrap();
-    let project = tmp.path();
+fn dockerfile_without_git_credentials_has_no_secret_mounts() {
+    let config = BuildConfig::default();
+    let project = default_project();
+    let output =
+        DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project)).render();
 
-    // Create project with extra dirs
-    std::fs::create_dir_all(project.join("src")).unwrap();
-    std::fs::create_dir_all(project.join("migrations")).unwrap();
-    std::fs::create_dir_all(project.join("templates")).unwrap();
-    std::fs::write(project.join("Cargo.toml"), "[package]\nname = \"test\"").unwrap();
-    std::fs::write(project.join("src/main.rs"), "fn main() {}").unwrap();
-    std::fs::write(project.join("migrations/001.sql"), "CREATE TABLE t;").unwrap();
-    std::fs::write(project.join("templates/index.html"), "<h1>hello</h1>").unwrap();
+    assert!(!output.contains("--mount=type=secret"));
+    assert!(!output.contains("# syntax="));
+}
 
-    // Git init and commit all
-    Command::new("git")
-        .args(["init"])
-        .current_dir(project)
-        .output()
-        .unwrap();
-    Command::new("git")
-        .args(["config", "user.email", "test@test.com"])
-        .current_dir(project)
-        .output()
-        .unwrap();
-    Command::new("git")
-        .args(["config", "user.name", "Test"])
-        .current_dir(project)
-        .output()
-        .unwrap();
-    Command::new("git")
-        .args(["add", "."])
-        .current_dir(project)
-        .output()
-        .unwrap();
-    Command::new("git")
-        .args(["commit", "-m", "init"])
-        .current_dir(project)
-        .output()
-        .unwrap();
+// ── Locked / Offline Build Tests ──
 
-    let bundle_dir = create_bundle(project, "FROM rust\n").unwrap();
+#[test]
+fn dockerfile_locks_cook_and_build_when_lockfile_is_bundled() {
+    let config = BuildConfig::default();
+    let project = default_project();
+    let output = DockerfileGenerator::with_options(
+        DockerfileOptions::new(&config, &project).lockfile_bundled(true),
+    )
+    .render();
 
-    // Additional dirs should be in the bundle
-    assert!(bundle_dir.join("migrations/001.sql").exists());
-    assert!(bundle_dir.join("templates/index.html").exists());
+    assert!(output.contains("RUN cargo chef cook --release --locked --recipe-path recipe.json"));
+    assert!(output.contains("RUN cargo build --release --locked --bin my-service"));
 }
 
 #[test]
-fn bundle_respects_gitignore() {
-    let tmp = TempDir::new().unwrap();
-    let project = tmp.path();
+fn dockerfile_without_lockfile_is_not_locked() {
+    let config = BuildConfig::default();
+    let project = default_project();
+    let output =
+        DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project)).render();
 
-    std::fs::create_dir_all(project.join("src")).unwrap();
-    std::fs::create_dir_all(project.join("target")).unwrap();
-    std::fs::write(project.join("Cargo.toml"), "[package]\nname = \"test\"").unwrap();
-    std::fs::write(project.join("src/main.rs"), "fn main() {}").unwrap();
-    std::fs::write(project.join("target/debug"), "binary").unwrap();
-    std::fs::write(project.join(".gitignore"), "target/\n").unwrap();
+    assert!(!output.contains("--locked --"));
+}
 
-    Command::new("git")
-        .args(["init"])
-        .current_dir(project)
-        .output()
-        .unwrap();
-    Command::new("git")
-        .args(["config", "user.email", "test@test.com"])
-        .current_dir(project)
-        .output()
-        .unwrap();
-    Command::new("git")
-        .args(["config", "user.name", "Test"])
-        .current_dir(project)
-        .output()
-        .unwrap();
-    Command::new("git")
-        .args(["add", "."])
-        .current_dir(project)
-        .output()
-        .unwrap();
-    Command::new("git")
-        .args(["commit", "-m", "init"])
-        .current_dir(project)
-        .output()
-        .unwrap();
+#[test]
+fn dockerfile_locked_setting_overrides_lockfile_detection() {
+    let project = default_project();
 
-    let bundle_dir = create_bundle(project, "FROM rust\n").unwrap();
+    let unlocked = BuildConfig {
+        locked: Some(false),
+        ..Default::default()
+    };
+    let output = DockerfileGenerator::with_options(
+        DockerfileOptions::new(&unlocked, &project).lockfile_bundled(true),
+    )
+    .render();
+    assert!(!output.contains("--release --locked"));
 
-    // .gitignored files should NOT be in the bundle
-    assert!(!bundle_dir.join("target").exists());
-    // Tracked files should be
-    assert!(bundle_dir.join("src/main.rs").exists());
-    assert!(bundle_dir.join(".gitignore").exists());
+    let locked = BuildConfig {
+        locked: Some(true),
+        ..Default::default()
+    };
+    let output =
+        DockerfileGenerator::with_options(DockerfileOptions::new(&locked, &project)).render();
+    assert_eq!(output.matches("--release --locked").count(), 2);
 }
 
 #[test]
-fn bundle_excludes_propel_dirs() {
-    let tmp = TempDir::new().unwrap();
-    let project = tmp.path();
+fn dockerfile_locked_keeps_git_credentials_mount() {
+    let config = BuildConfig {
+        git_credentials_secret: Some("GIT_CREDENTIALS".to_owned()),
+        ..Default::default()
+    };
+    let project = default_project();
+    let output = DockerfileGenerator::with_options(
+        DockerfileOptions::new(&config, &project).lockfile_bundled(true),
+    )
+    .render();
 
-    std::fs::create_dir_all(project.join("src")).unwrap();
-    std::fs::create_dir_all(project.join(".propel")).unwrap();
-    std::fs::write(project.join("Cargo.toml"), "[package]\nname = \"test\"").unwrap();
-    std::fs::write(project.join("src/main.rs"), "fn main() {}").unwrap();
-    std::fs::write(project.join(".propel/Dockerfile"), "custom").unwrap();
+    assert!(output.contains("ssh://git@ \\\n    cargo chef cook --release --locked"));
+    assert!(output.contains("ssh://git@ \\\n    cargo build --release --locked"));
+}
 
-    Command::new("git")
-        .args(["init"])
-        .current_dir(project)
-        .output()
-        .unwrap();
-    Command::new("git")
-        .args(["config", "user.email", "test@test.com"])
-        .current_dir(project)
-        .output()
-        .unwrap();
-    Command::new("git")
-        .args(["config", "user.name", "Test"])
-        .current_dir(project)
-        .output()
-        .unwrap();
-    Command::new("git")
-        .args(["add", "."])
-        .current_dir(project)
-        .output()
-        .unwrap();
-    Command::new("git")
-        .args(["commit", "-m", "init"])
-        .current_dir(project)
-        .output()
-        .unwrap();
+#[test]
+fn dockerfile_offline_sets_cargo_net_offline_after_chef_install() {
+    let config = BuildConfig {
+        offline: true,
+        ..Default::default()
+    };
+    let project = default_project();
+    let output =
+        DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project)).render();
 
-    let bundle_dir = create_bundle(project, "FROM rust\n").unwrap();
+    let chef = output.split_once("Stage 1: Planner").unwrap().0;
+    let install = chef.find("RUN cargo install cargo-chef").unwrap();
+    let offline = chef.find("ENV CARGO_NET_OFFLINE=true").unwrap();
+    assert!(
+        install < offline,
+        "cargo-chef must install before going offline"
+    );
+    let runtime_section = output.split("Stage 4: Runtime").nth(1).unwrap();
+    assert!(!runtime_section.contains("CARGO_NET_OFFLINE"));
+}
 
-    // .propel/ should be excluded by PROPEL_EXCLUDES
-    assert!(!bundle_dir.join(".propel").exists());
-    assert!(bundle_dir.join("src/main.rs").exists());
+fn render_cargo_options(
+    features: &[&str],
+    no_default_features: bool,
+    rustflags: Option<&str>,
+) -> String {
+    let config = BuildConfig {
+        features: features.iter().map(|f| (*f).to_owned()).collect(),
+        no_default_features,
+        rustflags: rustflags.map(str::to_owned),
+        ..Default::default()
+    };
+    let project = default_project();
+    DockerfileGenerator::with_options(
+        DockerfileOptions::new(&config, &project).lockfile_bundled(true),
+    )
+    .render()
 }
 
 #[test]
-fn bundle_cleans_previous_bundle() {
-    let tmp = TempDir::new().unwrap();
-    let project = tmp.path();
-    init_git_project(project);
+fn dockerfile_passes_cargo_options_to_cook_and_build() {
+    // (features, no_default_features, flags after --release)
+    let cases: &[(&[&str], bool, &str)] = &[
+        (&[], false, " --locked"),
+        (&["cloud"], false, " --locked --features cloud"),
+        (&[], true, " --locked --no-default-features"),
+        (
+            &["cloud", "sqlx/postgres"],
+            true,
+            " --locked --no-default-features --features cloud,sqlx/postgres",
+        ),
+    ];
+    for (features, no_default_features, flags) in cases {
+        let output = render_cargo_options(features, *no_default_features, None);
+        assert!(
+            output.contains(&format!(
+                "RUN cargo chef cook --release{flags} --recipe-path recipe.json"
+            )),
+            "{features:?} {no_default_features}:\n{output}"
+        );
+        assert!(
+            output.contains(&format!(
+                "RUN cargo build --release{flags} --bin my-service"
+            )),
+            "{features:?} {no_default_features}:\n{output}"
+        );
+    }
+}
 
-    // Create first bundle
-    let bundle1 = create_bundle(project, "FROM rust:1\n").unwrap();
-    assert!(bundle1.join("Dockerfile").exists());
+#[test]
+fn dockerfile_sets_rustflags_for_cacher_and_builder_after_chef_install() {
+    let output = render_cargo_options(&["cloud"], true, Some("-C target-cpu=x86-64-v3"));
 
-    // Create second bundle — should overwrite
-    let bundle2 = create_bundle(project, "FROM rust:2\n").unwrap();
-    let content = std::fs::read_to_string(bundle2.join("Dockerfile")).unwrap();
-    assert_eq!(content, "FROM rust:2\n");
+    let chef = output.split_once("Stage 1: Planner").unwrap().0;
+    let install = chef.find("RUN cargo install cargo-chef").unwrap();
+    let rustflags = chef
+        .find("ENV RUSTFLAGS=\"-C target-cpu=x86-64-v3\"\n")
+        .unwrap();
+    assert!(
+        install < rustflags,
+        "cargo-chef must not be built for the target CPU"
+    );
+    // Set once, in the stage the cacher and builder both derive from.
+    assert_eq!(output.matches("RUSTFLAGS").count(), 1);
+    assert!(output.contains("FROM chef AS cacher"));
+    assert!(output.contains("FROM chef AS builder"));
+    assert!(
+        output
+            .contains("cargo chef cook --release --locked --no-default-features --features cloud")
+    );
 }
 
 #[test]
-fn bundle_copies_nested_src_dirs() {
-    let tmp = TempDir::new().unwrap();
-    let project = tmp.path();
+fn dockerfile_rustflags_with_offline_and_quotes() {
+    let config = BuildConfig {
+        offline: true,
+        rustflags: Some(r#"-C link-arg="-Wl,--as-needed" -C opt\level"#.to_owned()),
+        ..Default::default()
+    };
+    let project = default_project();
+    let output =
+        DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project)).render();
 
-    std::fs::create_dir_all(project.join("src/handlers")).unwrap();
-    std::fs::write(project.join("Cargo.toml"), "[package]\nname = \"test\"").unwrap();
-    std::fs::write(project.join("src/main.rs"), "fn main() {}").unwrap();
-    std::fs::write(project.join("src/handlers/mod.rs"), "pub fn handle() {}").unwrap();
+    assert!(output.contains(
+        "ENV CARGO_NET_OFFLINE=true\nENV RUSTFLAGS=\"-C link-arg=\\\"-Wl,--as-needed\\\" -C opt\\\\level\"\n"
+    ));
+    assert!(output.contains("RUN cargo build --release --bin my-service"));
+}
 
-    Command::new("git")
-        .args(["init"])
-        .current_dir(project)
-        .output()
-        .unwrap();
+#[test]
+fn dockerfile_without_cargo_options_has_no_rustflags() {
+    let output = render_cargo_options(&[], false, None);
+
+    assert!(!output.contains("RUSTFLAGS"));
+    assert!(!output.contains("--features"));
+    assert!(!output.contains("--no-default-features"));
+}
+
+#[test]
+fn dockerfile_without_offline_leaves_network_on() {
+    let config = BuildConfig::default();
+    let project = default_project();
+    let output =
+        DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project)).render();
+
+    assert!(!output.contains("CARGO_NET_OFFLINE"));
+}
+
+#[test]
+fn dockerfile_static_binary_builds_for_musl_on_a_static_runtime() {
+    let config = BuildConfig {
+        static_binary: true,
+        rustflags: Some("-C target-cpu=x86-64-v3".to_owned()),
+        ..Default::default()
+    };
+    let project = default_project();
+    let output = DockerfileGenerator::with_options(
+        DockerfileOptions::new(&config, &project).lockfile_bundled(true),
+    )
+    .render();
+
+    let chef = output.split_once("Stage 1: Planner").unwrap().0;
+    let install = chef.find("RUN cargo install cargo-chef").unwrap();
+    let rustup = chef
+        .find("RUN rustup target add x86_64-unknown-linux-musl\n")
+        .unwrap();
+    let musl_tools = chef.find("apt-get install -y musl-tools").unwrap();
+    let rustflags = chef.find("ENV RUSTFLAGS").unwrap();
+    assert!(install < rustup && rustup < musl_tools && musl_tools < rustflags);
+    assert!(output.contains(
+        "RUN cargo chef cook --release --locked --target x86_64-unknown-linux-musl --recipe-path recipe.json"
+    ));
+    assert!(output.contains(
+        "RUN cargo build --release --locked --target x86_64-unknown-linux-musl --bin my-service"
+    ));
+    assert!(output.contains("FROM gcr.io/distroless/static-debian12\n"));
+    assert!(output.contains(
+        "COPY --from=builder /app/target/x86_64-unknown-linux-musl/release/my-service /usr/local/bin/app"
+    ));
+    assert!(!output.contains("target/release/"));
+}
+
+#[test]
+fn dockerfile_target_keeps_an_explicit_runtime_image() {
+    let project = default_project();
+    let config = BuildConfig {
+        static_binary: true,
+        runtime_image: "scratch".to_owned(),
+        platform: Some(Platform::Arm64),
+        ..Default::default()
+    };
+    let generator = DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project));
+    generator.check_runtime_image().unwrap();
+    let output = generator.render();
+    assert!(output.contains("RUN rustup target add aarch64-unknown-linux-musl\n"));
+    assert!(output.contains("FROM scratch\n"));
+    assert!(output.contains("/app/target/aarch64-unknown-linux-musl/release/my-service "));
+
+    // A glibc target: rustup but no musl-tools, and the default runtime.
+    let config = BuildConfig {
+        target: Some("x86_64-unknown-linux-gnu".to_owned()),
+        ..Default::default()
+    };
+    let output =
+        DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project)).render();
+    assert!(output.contains("RUN rustup target add x86_64-unknown-linux-gnu\n"));
+    assert!(!output.contains("musl-tools"));
+    assert!(output.contains("FROM gcr.io/distroless/cc-debian12\n"));
+    assert!(output.contains("/app/target/x86_64-unknown-linux-gnu/release/my-service "));
+}
+
+#[test]
+fn dockerfile_without_target_has_no_rustup_or_musl_tools() {
+    let output = render_cargo_options(&[], false, None);
+
+    assert!(!output.contains("rustup"));
+    assert!(!output.contains("musl-tools"));
+    assert!(!output.contains("--target"));
+    assert!(output.contains("COPY --from=builder /app/target/release/my-service "));
+}
+
+#[test]
+fn dockerfile_cache_mounts_cook_and_build_and_copy_the_binary_out() {
+    let config = BuildConfig {
+        cache_mounts: true,
+        ..Default::default()
+    };
+    let project = default_project();
+    let output = DockerfileGenerator::with_options(
+        DockerfileOptions::new(&config, &project).lockfile_bundled(true),
+    )
+    .render();
+
+    let mounts = "--mount=type=cache,target=/usr/local/cargo/registry \
+                  --mount=type=cache,target=/usr/local/cargo/git \
+                  --mount=type=cache,target=/app/target \\\n    ";
+    assert!(output.starts_with("# syntax=docker/dockerfile:1\n"));
+    assert!(output.contains(&format!(
+        "RUN {mounts}cargo chef cook --release --locked --recipe-path recipe.json\n"
+    )));
+    assert!(output.contains(&format!(
+        "RUN {mounts}cargo build --release --locked --bin my-service \\\n    \
+         && cp target/release/my-service /usr/local/bin/app\n"
+    )));
+    assert!(!output.contains("COPY --from=cacher /app/target"));
+    assert!(output.contains("COPY --from=cacher /usr/local/cargo /usr/local/cargo"));
+    assert!(output.contains("COPY --from=builder /usr/local/bin/app /usr/local/bin/app\n"));
+    let binaries = binary_references(&output);
+    assert!(
+        binaries.iter().all(|r| r.name == "my-service"),
+        "{binaries:?}"
+    );
+}
+
+#[test]
+fn dockerfile_cache_mounts_with_git_credentials_and_static_target() {
+    let config = BuildConfig {
+        cache_mounts: true,
+        static_binary: true,
+        git_credentials_secret: Some("GIT_CREDENTIALS".to_owned()),
+        ..Default::default()
+    };
+    let project = default_project();
+    let output =
+        DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project)).render();
+
+    assert_eq!(output.matches("# syntax=docker/dockerfile:1").count(), 1);
+    // Every mount flag precedes the scoped git environment.
+    assert!(output.contains(
+        "--mount=type=cache,target=/app/target \\\n    --mount=type=secret,id=git_credentials \\\n"
+    ));
+    assert!(output.contains(
+        "&& cp target/x86_64-unknown-linux-musl/release/my-service /usr/local/bin/app\n"
+    ));
+}
+
+#[test]
+fn dockerfile_without_cache_mounts_copies_target_from_cacher() {
+    let output = render_cargo_options(&[], false, None);
+
+    assert!(!output.contains("type=cache"));
+    assert!(!output.contains("# syntax="));
+    assert!(output.contains("COPY --from=cacher /app/target target\n"));
+    assert!(!output.contains("&& cp "));
+}
+
+#[test]
+fn cloudbuild_config_wires_secret_into_docker_build() {
+    let yaml = CloudBuildYaml::new(
+        "my-project",
+        "us-central1-docker.pkg.dev/my-project/propel/svc:latest",
+    )
+    .git_credentials_secret("GIT_CREDENTIALS")
+    .render();
+
+    assert!(
+        yaml.contains("versionName: projects/my-project/secrets/GIT_CREDENTIALS/versions/latest")
+    );
+    assert!(yaml.contains("env: PROPEL_GIT_CREDENTIALS"));
+    assert!(yaml.contains("secretEnv:\n      - PROPEL_GIT_CREDENTIALS"));
+    assert!(yaml.contains("- DOCKER_BUILDKIT=1"));
+    assert!(yaml.contains(
+        "docker build --secret id=git_credentials,env=PROPEL_GIT_CREDENTIALS \
+         -t us-central1-docker.pkg.dev/my-project/propel/svc:latest ."
+    ));
+    assert!(yaml.contains("images:\n  - us-central1-docker.pkg.dev/my-project/propel/svc:latest"));
+    assert!(!yaml.contains("serviceAccount:"));
+}
+
+#[test]
+fn cloudbuild_arm64_registers_emulators_then_builds_with_buildx() {
+    let yaml = CloudBuildYaml::new("my-project", "img:latest")
+        .platform(Platform::Arm64)
+        .render();
+
+    let binfmt = yaml
+        .find("args: ['run', '--privileged', '--rm', 'tonistiigi/binfmt', '--install', 'arm64']")
+        .unwrap();
+    let build = yaml
+        .find("docker buildx build --platform linux/arm64 --load -t img:latest .")
+        .unwrap();
+    assert!(
+        binfmt < build,
+        "emulators must be registered before the build"
+    );
+    assert!(yaml.contains("images:\n  - img:latest"));
+}
+
+#[test]
+fn cloudbuild_amd64_uses_buildx_without_emulation() {
+    let yaml = CloudBuildYaml::new("my-project", "img:latest")
+        .platform(Platform::Amd64)
+        .render();
+
+    assert!(yaml.contains("docker buildx build --platform linux/amd64 --load -t img:latest ."));
+    assert!(!yaml.contains("binfmt"));
+}
+
+#[test]
+fn cloudbuild_platform_keeps_git_credentials_secret() {
+    let yaml = CloudBuildYaml::new("my-project", "img:latest")
+        .platform(Platform::Arm64)
+        .git_credentials_secret("GIT_CREDENTIALS")
+        .render();
+
+    assert!(yaml.contains(
+        "docker buildx build --platform linux/arm64 --load \
+         --secret id=git_credentials,env=PROPEL_GIT_CREDENTIALS -t img:latest ."
+    ));
+    assert!(yaml.contains("secretEnv:\n      - PROPEL_GIT_CREDENTIALS"));
+}
+
+#[test]
+fn cloudbuild_cache_mounts_builds_with_buildx() {
+    let yaml = CloudBuildYaml::new("my-project", "img:latest")
+        .cache_mounts()
+        .git_credentials_secret("GIT_CREDENTIALS")
+        .render();
+
+    assert!(yaml.contains(
+        "- docker buildx build --load \
+         --secret id=git_credentials,env=PROPEL_GIT_CREDENTIALS -t img:latest ."
+    ));
+    assert!(yaml.contains("DOCKER_BUILDKIT=1"));
+    assert!(!yaml.contains("binfmt"));
+
+    let yaml = CloudBuildYaml::new("my-project", "img:latest")
+        .cache_mounts()
+        .platform(Platform::Arm64)
+        .render();
+    assert!(yaml.contains("docker buildx build --platform linux/arm64 --load -t img:latest ."));
+}
+
+#[test]
+fn cloudbuild_without_platform_uses_plain_docker_build() {
+    let yaml = CloudBuildYaml::new("my-project", "img:latest").render();
+
+    assert!(yaml.contains("- docker build -t img:latest ."));
+    assert!(!yaml.contains("buildx"));
+}
+
+#[test]
+fn dockerfile_notes_target_platform() {
+    let config = BuildConfig {
+        platform: Some(Platform::Arm64),
+        ..Default::default()
+    };
+    let project = default_project();
+    let output =
+        DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project)).render();
+
+    assert!(output.starts_with("# Target platform: linux/arm64"));
+    assert!(
+        output.contains("COPY --from=builder /app/target/release/my-service /usr/local/bin/app")
+    );
+}
+
+#[test]
+fn image_family_classifies_common_images() {
+    for (image, family) in [
+        ("rust:1.93-bookworm", ImageFamily::Glibc),
+        ("rust:1.93", ImageFamily::Glibc),
+        ("rust:1.93-slim-bullseye", ImageFamily::Glibc),
+        ("debian:bookworm-slim", ImageFamily::Glibc),
+        ("ubuntu:24.04", ImageFamily::Glibc),
+        ("gcr.io/distroless/cc-debian12", ImageFamily::Glibc),
+        (
+            "gcr.io/distroless/base-debian12:nonroot",
+            ImageFamily::Glibc,
+        ),
+        ("cgr.dev/chainguard/glibc-dynamic", ImageFamily::Glibc),
+        ("alpine:3.20", ImageFamily::Musl),
+        ("rust:1.93-alpine3.20", ImageFamily::Musl),
+        ("clux/muslrust:stable", ImageFamily::Musl),
+        ("localhost:5000/alpine", ImageFamily::Musl),
+        ("scratch", ImageFamily::Static),
+        ("gcr.io/distroless/static-debian12", ImageFamily::Static),
+        ("cgr.dev/chainguard/static:latest", ImageFamily::Static),
+        ("registry.example.com/runtime:1.0", ImageFamily::Unknown),
+        ("busybox", ImageFamily::Unknown),
+    ] {
+        assert_eq!(ImageFamily::classify(image), family, "{image}");
+    }
+    assert_eq!(
+        ImageFamily::classify("Alpine@sha256:0123abcd"),
+        ImageFamily::Musl
+    );
+}
+
+#[test]
+fn runtime_compat_rejects_glibc_binary_on_musl_or_empty_runtime() {
+    for runtime in [
+        "alpine:3.20",
+        "scratch",
+        "gcr.io/distroless/static-debian12",
+    ] {
+        let err = check_runtime_compat("rust:1.93-bookworm", runtime).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains(runtime), "{message}");
+        assert!(message.contains("loader"), "{message}");
+        assert!(
+            message.contains("gcr.io/distroless/cc-debian12"),
+            "{message}"
+        );
+        assert!(message.contains("runtime_image_check = false"), "{message}");
+    }
+}
+
+#[test]
+fn runtime_compat_allows_matching_and_unknown_images() {
+    for (builder, runtime) in [
+        ("rust:1.93-bookworm", "gcr.io/distroless/cc-debian12"),
+        ("rust:1.93-bookworm", "debian:bookworm-slim"),
+        ("rust:1.93-bookworm", "registry.example.com/runtime:1.0"),
+        ("clux/muslrust:stable", "scratch"),
+        ("rust:1.93-alpine", "alpine:3.20"),
+        ("registry.example.com/builder:1.93", "alpine:3.20"),
+    ] {
+        check_runtime_compat(builder, runtime).unwrap();
+    }
+}
+
+#[test]
+fn rust_version_from_common_image_tags() {
+    let version = |v: &str| Some(RustVersion::parse(v).unwrap());
+    for (image, expected) in [
+        ("rust:1.93-bookworm", version("1.93")),
+        ("rust:1.81", version("1.81")),
+        ("rust:1.81.0-slim-bookworm", version("1.81.0")),
+        ("rust:1.83-alpine3.20", version("1.83")),
+        ("docker.io/library/rust:1.84.1", version("1.84.1")),
+        ("rust:1.82@sha256:0123abcd", version("1.82")),
+        ("localhost:5000/rust:1.80-bookworm", version("1.80")),
+        ("clux/muslrust:1.83.0-stable", version("1.83.0")),
+        ("registry.example.com/rust-chef:1.93", version("1.93")),
+        (
+            "lukemathwalker/cargo-chef:0.1.73-rust-1.83-bookworm",
+            version("1.83"),
+        ),
+        (
+            "lukemathwalker/cargo-chef:latest-rust-1.84.0",
+            version("1.84.0"),
+        ),
+        // No version in the tag: the check is skipped.
+        ("rust:latest", None),
+        ("rust:slim-bookworm", None),
+        ("rust:1-bookworm", None),
+        ("rust", None),
+        ("clux/muslrust:stable", None),
+        ("lukemathwalker/cargo-chef:latest-rust-1", None),
+        ("registry.example.com/builder:1.93", None),
+        ("localhost:5000/builder", None),
+    ] {
+        assert_eq!(RustVersion::from_image(image), expected, "{image}");
+    }
+}
+
+#[test]
+fn rust_version_parses_and_compares() {
+    for invalid in ["", "1", "1.", "1.x", "v1.83", "1.83.0.1", "1.83.0-beta"] {
+        assert_eq!(RustVersion::parse(invalid), None, "{invalid:?}");
+    }
+    let v = |v: &str| RustVersion::parse(v).unwrap();
+    assert_eq!(v("1.83.2").to_string(), "1.83.2");
+    assert_eq!(v("1.83").to_string(), "1.83");
+
+    // (builder, msrv, satisfied)
+    for (builder, msrv, satisfied) in [
+        ("1.83", "1.83.0", true),
+        ("1.83", "1.83.5", true),
+        ("1.84", "1.83.0", true),
+        ("1.83.0", "1.83.0", true),
+        ("1.83.0", "1.83.1", false),
+        ("1.83.0", "1.83", true),
+        ("1.81", "1.83.0", false),
+        ("1.81.9", "1.82", false),
+        ("2.0", "1.99", true),
+    ] {
+        assert_eq!(
+            v(builder).satisfies(v(msrv)),
+            satisfied,
+            "{builder} vs {msrv}"
+        );
+    }
+}
+
+#[test]
+fn builder_rust_version_check_rejects_older_builders() {
+    let err = check_builder_rust_version("rust:1.81-bookworm", "1.83.0").unwrap_err();
+    assert!(
+        matches!(err, propel_core::Error::BuilderImageTooOld { .. }),
+        "{err:?}"
+    );
+    let message = err.to_string();
+    assert!(message.contains("Rust 1.81"), "{message}");
+    assert!(message.contains("rust-version 1.83.0"), "{message}");
+    assert!(message.contains("rust_version_check = false"), "{message}");
+
+    assert_eq!(
+        check_builder_rust_version("rust:1.93-bookworm", "1.83.0").unwrap(),
+        RustVersion::parse("1.93")
+    );
+    // Nothing to compare: skipped, not rejected.
+    assert_eq!(
+        check_builder_rust_version("registry.example.com/builder:v3", "1.83.0").unwrap(),
+        None
+    );
+    assert_eq!(
+        check_builder_rust_version("rust:1.81", "nightly").unwrap(),
+        RustVersion::parse("1.81")
+    );
+}
+
+#[test]
+fn generator_runtime_check_uses_chef_image_and_can_be_disabled() {
+    let project = default_project();
+    let mut config = BuildConfig {
+        runtime_image: "alpine:3.20".to_owned(),
+        ..Default::default()
+    };
+    assert!(
+        DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project))
+            .check_runtime_image()
+            .is_err()
+    );
+
+    config.chef_image = Some("clux/muslrust:stable".to_owned());
+    DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project))
+        .check_runtime_image()
+        .unwrap();
+
+    config.chef_image = None;
+    config.runtime_image_check = false;
+    DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project))
+        .check_runtime_image()
+        .unwrap();
+}
+
+// ── Deploy via Cloud Build Tests ──
+
+fn deploy_args() -> Vec<String> {
+    [
+        "run",
+        "deploy",
+        "svc",
+        "--image",
+        "img:latest",
+        "--update-secrets",
+        "A=A:latest",
+    ]
+    .iter()
+    .map(|s| (*s).to_owned())
+    .collect()
+}
+
+#[test]
+fn cloudbuild_deploy_step_pushes_then_deploys() {
+    let args = deploy_args();
+    let yaml = CloudBuildYaml::new("my-project", "img:latest")
+        .deploy_step(DeployStep {
+            gcloud_args: &args,
+            service_account: "propel-build@my-project.iam.gserviceaccount.com",
+        })
+        .render();
+
+    let push = yaml.find("args: ['push', 'img:latest']").unwrap();
+    let deploy = yaml.find("entrypoint: gcloud").unwrap();
+    assert!(push < deploy, "image must be pushed before the deploy step");
+    assert!(yaml.contains("      - 'run'\n      - 'deploy'\n      - 'svc'"));
+    assert!(yaml.contains("      - 'A=A:latest'"));
+}
+
+#[test]
+fn cloudbuild_deploy_step_runs_as_build_service_account() {
+    let args = deploy_args();
+    let yaml = CloudBuildYaml::new("my-project", "img:latest")
+        .deploy_step(DeployStep {
+            gcloud_args: &args,
+            service_account: "propel-build@my-project.iam.gserviceaccount.com",
+        })
+        .render();
+
+    assert!(yaml.contains(
+        "serviceAccount: projects/my-project/serviceAccounts/propel-build@my-project.iam.gserviceaccount.com"
+    ));
+    assert!(yaml.contains("options:\n  logging: CLOUD_LOGGING_ONLY"));
+}
+
+#[test]
+fn cloudbuild_deploy_step_with_logs_bucket_skips_cloud_logging_only() {
+    let args = deploy_args();
+    let yaml = CloudBuildYaml::new("my-project", "img:latest")
+        .logs_bucket("gs://acme-logs/propel")
+        .deploy_step(DeployStep {
+            gcloud_args: &args,
+            service_account: "propel-build@my-project.iam.gserviceaccount.com",
+        })
+        .render();
+
+    assert!(yaml.contains("logsBucket: gs://acme-logs/propel"));
+    assert!(!yaml.contains("CLOUD_LOGGING_ONLY"));
+}
+
+#[test]
+fn write_cloudbuild_config_places_file_in_bundle() {
+    let tmp = TempDir::new().unwrap();
+    let path = write_cloudbuild_config(tmp.path(), "steps: []\n").unwrap();
+
+    assert_eq!(path, tmp.path().join("cloudbuild.yaml"));
+    assert_eq!(std::fs::read_to_string(path).unwrap(), "steps: []\n");
+}
+
+// ── Bundle Tests ──
+
+#[test]
+fn bundle_creates_expected_structure() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+    init_git_project(project);
+
+    let bundle_dir = create_bundle_with(&BundleOptions::new(project, "FROM rust\n")).unwrap();
+
+    assert!(bundle_dir.join("Dockerfile").exists());
+    assert!(bundle_dir.join("Cargo.toml").exists());
+    assert!(bundle_dir.join("src/main.rs").exists());
+
+    let dockerfile = std::fs::read_to_string(bundle_dir.join("Dockerfile")).unwrap();
+    assert_eq!(dockerfile, "FROM rust\n");
+}
+
+#[test]
+fn bundle_leaves_out_extra_excludes() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+    std::fs::create_dir_all(project.join("fixtures/large")).unwrap();
+    std::fs::write(project.join("fixtures/large/dump.sql"), "-- big").unwrap();
+    std::fs::write(project.join("fixtures/small.sql"), "-- small").unwrap();
+    std::fs::create_dir_all(project.join(".cargo")).unwrap();
+    std::fs::write(project.join(".cargo/config.toml"), "[net]\n").unwrap();
+    init_git_project(project);
+
+    let options = BundleOptions::new(project, "FROM rust\n")
+        .exclude("fixtures/large")
+        .exclude(".cargo");
+    let bundle_dir = create_bundle_with(&options).unwrap();
+
+    assert!(bundle_dir.join("fixtures/small.sql").exists());
+    assert!(!bundle_dir.join("fixtures/large").exists());
+    assert!(!bundle_dir.join(".cargo/config.toml").exists());
+    assert!(bundle_dir.join("src/main.rs").exists());
+}
+
+#[test]
+fn bundle_files_lists_what_the_bundle_copies_without_writing() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+    std::fs::create_dir_all(project.join("fixtures/large")).unwrap();
+    std::fs::write(project.join("fixtures/large/dump.sql"), "-- big").unwrap();
+    init_git_project(project);
+    let options = BundleOptions::new(project, "FROM rust\n").exclude("fixtures/large");
+
+    let mut files = bundle_files(&options).unwrap();
+    assert!(!project.join(BUNDLE_DIR).exists());
+
+    let bundle_dir = create_bundle_with(&options).unwrap();
+    let mut entries = bundle_entries(&bundle_dir).unwrap();
+    entries.retain(|entry| entry != Path::new("Dockerfile"));
+    files.sort();
+    assert_eq!(files, entries);
+}
+
+/// A project directory with `.gitignore` files but no git repository.
+fn plain_project(dir: &Path) {
+    for (path, content) in [
+        ("Cargo.toml", "[package]\nname = \"test\""),
+        ("src/main.rs", "fn main() {}"),
+        (".gitignore", "/target\n*.log\nsecrets/\n"),
+        ("target/release/test", "binary"),
+        ("debug.log", "log"),
+        ("secrets/key.pem", "key"),
+        ("assets/.gitignore", "*.tmp\n!keep.tmp\n"),
+        ("assets/logo.svg", "<svg/>"),
+        ("assets/scratch.tmp", "tmp"),
+        ("assets/keep.tmp", "tmp"),
+        (".cargo/config.toml", "[net]\n"),
+        (".propel/Dockerfile", "FROM ejected"),
+    ] {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+}
+
+#[test]
+fn bundle_outside_git_walks_the_tree_honoring_gitignore() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+    plain_project(project);
+
+    let bundle_dir = create_bundle_with(&BundleOptions::new(project, "FROM rust\n")).unwrap();
+
+    assert_eq!(
+        bundle_entries(&bundle_dir).unwrap(),
+        [
+            ".cargo/config.toml",
+            ".gitignore",
+            "Cargo.toml",
+            "Dockerfile",
+            "assets/.gitignore",
+            "assets/keep.tmp",
+            "assets/logo.svg",
+            "src/main.rs",
+        ]
+        .map(PathBuf::from)
+    );
+
+    // A second bundle does not pick up the first.
+    let bundle_dir = create_bundle_with(&BundleOptions::new(project, "FROM rust\n")).unwrap();
+    assert!(!bundle_dir.join(".propel-bundle").exists());
+}
+
+#[test]
+fn git_queries_outside_git() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+    plain_project(project);
+
+    assert!(!is_dirty(project).unwrap());
+    assert!(is_ignored(project, Path::new("debug.log")).unwrap());
+    assert!(is_ignored(project, Path::new("secrets")).unwrap());
+    assert!(is_ignored(project, Path::new("assets/scratch.tmp")).unwrap());
+    assert!(!is_ignored(project, Path::new("assets/keep.tmp")).unwrap());
+    assert!(!is_ignored(project, Path::new(".cargo/config.toml")).unwrap());
+    let err = short_head_commit(project).unwrap_err();
+    assert!(matches!(err, BundleError::NotARepo { .. }), "{err:?}");
+    assert!(err.to_string().contains("tag_strategy"), "{err}");
+}
+
+#[test]
+fn parallel_bundle_matches_sequential_copy() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+    for i in 0..3000 {
+        let path = project.join(format!("data/d{}/e{}/file{i}.txt", i % 17, i % 5));
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, format!("contents of {i}\n").repeat(i % 7 + 1)).unwrap();
+    }
+    init_git_project(project);
+
+    let mut reports = Vec::new();
+    let bundle_dir = create_bundle_with_progress(
+        &BundleOptions::new(project, "FROM rust\n"),
+        |copied, total| reports.push((copied, total)),
+    )
+    .unwrap();
+
+    // What copying the file list one by one produces.
+    let listed = Command::new("git")
+        .args(["ls-files", "--cached", "--others", "--exclude-standard"])
+        .current_dir(project)
+        .output()
+        .unwrap();
+    let expected = TempDir::new().unwrap();
+    let mut expected_entries = vec![PathBuf::from("Dockerfile")];
+    for line in String::from_utf8(listed.stdout).unwrap().lines() {
+        if line.starts_with(".propel-bundle/") {
+            continue;
+        }
+        let dst = expected.path().join(line);
+        std::fs::create_dir_all(dst.parent().unwrap()).unwrap();
+        std::fs::copy(project.join(line), dst).unwrap();
+        expected_entries.push(PathBuf::from(line));
+    }
+    expected_entries.sort();
+
+    let entries = bundle_entries(&bundle_dir).unwrap();
+    assert_eq!(entries, expected_entries);
+    for entry in entries.iter().filter(|e| *e != Path::new("Dockerfile")) {
+        assert_eq!(
+            std::fs::read(bundle_dir.join(entry)).unwrap(),
+            std::fs::read(expected.path().join(entry)).unwrap(),
+            "{}",
+            entry.display()
+        );
+    }
+
+    let copied = expected_entries.len() - 1;
+    assert_eq!(reports.len(), copied);
+    assert!(reports.iter().all(|&(_, total)| total == copied));
+    assert_eq!(reports.last(), Some(&(copied, copied)));
+}
+
+#[test]
+fn parallel_bundle_reports_the_first_failed_copy() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+    for i in 0..200 {
+        std::fs::write(project.join(format!("file{i:03}.txt")), "x").unwrap();
+    }
+    init_git_project(project);
+    // Tracked but gone from the working tree: git still lists them.
+    std::fs::remove_file(project.join("file150.txt")).unwrap();
+    std::fs::remove_file(project.join("file050.txt")).unwrap();
+
+    let err = create_bundle_with(&BundleOptions::new(project, "FROM rust\n")).unwrap_err();
+
+    match err {
+        BundleError::CopyFile { path, .. } => assert_eq!(path, project.join("file050.txt")),
+        other => panic!("expected CopyFile, got {other:?}"),
+    }
+}
+
+/// Bundle `options` with `verify` on, running `mutate` from the progress
+/// callback after `at` files are copied.
+fn bundle_mutated_at(
+    options: BundleOptions,
+    at: usize,
+    mutate: impl Fn(),
+) -> Result<PathBuf, BundleError> {
+    create_bundle_with_progress(&options.verify(true), |copied, _| {
+        if copied == at {
+            mutate();
+        }
+    })
+}
+
+fn changed_paths(err: BundleError) -> Vec<PathBuf> {
+    match err {
+        BundleError::ChangedDuringBundle { paths } => paths,
+        other => panic!("expected ChangedDuringBundle, got {other:?}"),
+    }
+}
+
+#[test]
+fn verify_catches_a_file_rewritten_after_its_copy() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+    init_git_project(project);
+    let rewrite = || std::fs::write(project.join("src/main.rs"), "fn main() { loop {} }").unwrap();
+
+    // Both files are copied once the progress count reaches 2.
+    let err =
+        bundle_mutated_at(BundleOptions::new(project, "FROM rust\n"), 2, rewrite).unwrap_err();
+
+    assert!(err.to_string().contains("  src/main.rs\n"), "{err}");
+    assert_eq!(changed_paths(err), [PathBuf::from("src/main.rs")]);
+}
+
+#[test]
+fn verify_catches_a_file_created_during_the_copy() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+    init_git_project(project);
+    let generate = || std::fs::write(project.join("src/generated.rs"), "// new").unwrap();
+
+    let err =
+        bundle_mutated_at(BundleOptions::new(project, "FROM rust\n"), 1, generate).unwrap_err();
+
+    assert_eq!(changed_paths(err), [PathBuf::from("src/generated.rs")]);
+}
+
+#[test]
+fn verify_catches_a_file_removed_after_its_copy() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+    init_git_project(project);
+    std::fs::write(project.join("notes.txt"), "untracked").unwrap();
+    let remove = || std::fs::remove_file(project.join("notes.txt")).unwrap();
+
+    let err = bundle_mutated_at(BundleOptions::new(project, "FROM rust\n"), 3, remove).unwrap_err();
+
+    assert_eq!(changed_paths(err), [PathBuf::from("notes.txt")]);
+}
+
+#[test]
+fn verify_passes_an_unchanged_dirty_tree() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+    init_git_project(project);
+    std::fs::write(project.join("src/main.rs"), "fn main() { todo!() }").unwrap();
+    std::fs::write(project.join("notes.txt"), "untracked").unwrap();
+
+    // A previous bundle is replaced; neither it nor the new one is a change.
+    for _ in 0..2 {
+        let bundle_dir =
+            create_bundle_with(&BundleOptions::new(project, "FROM rust\n").verify(true)).unwrap();
+        assert!(bundle_dir.join("notes.txt").is_file());
+    }
+}
+
+#[test]
+fn verify_ignores_changes_to_excluded_paths() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+    std::fs::create_dir_all(project.join("fixtures")).unwrap();
+    std::fs::write(project.join("fixtures/data.json"), "{}").unwrap();
+    init_git_project(project);
+    let touch = || {
+        std::fs::write(project.join("fixtures/data.json"), "[]").unwrap();
+        std::fs::create_dir_all(project.join(".propel")).unwrap();
+        std::fs::write(project.join(".propel/Dockerfile"), "FROM rust\n").unwrap();
+    };
+
+    let options = BundleOptions::new(project, "FROM rust\n").exclude("fixtures");
+    bundle_mutated_at(options, 2, touch).unwrap();
+}
+
+#[test]
+fn verify_reports_paths_relative_to_a_member_directory() {
+    let tmp = TempDir::new().unwrap();
+    let member = tmp.path().join("services/api");
+    init_git_project(&member);
+    std::fs::rename(member.join(".git"), tmp.path().join(".git")).unwrap();
+    Command::new("git")
+        .args(["add", "-A"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+    Command::new("git")
+        .args(["commit", "-m", "move into a member"])
+        .current_dir(tmp.path())
+        .output()
+        .unwrap();
+    let rewrite =
+        || std::fs::write(member.join("Cargo.toml"), "[package]\nname = \"api\"").unwrap();
+
+    let err =
+        bundle_mutated_at(BundleOptions::new(&member, "FROM rust\n"), 2, rewrite).unwrap_err();
+
+    assert_eq!(changed_paths(err), [PathBuf::from("Cargo.toml")]);
+}
+
+#[test]
+fn verify_compares_hashes_outside_git() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+    std::fs::write(project.join("a.txt"), "a").unwrap();
+    std::fs::write(project.join("b.txt"), "b").unwrap();
+    let rewrite = || std::fs::write(project.join("a.txt"), "changed").unwrap();
+
+    let err =
+        bundle_mutated_at(BundleOptions::new(project, "FROM rust\n"), 2, rewrite).unwrap_err();
+
+    assert_eq!(changed_paths(err), [PathBuf::from("a.txt")]);
+}
+
+#[test]
+fn bundle_without_verify_keeps_a_mid_copy_rewrite() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+    init_git_project(project);
+
+    let bundle_dir = create_bundle_with_progress(
+        &BundleOptions::new(project, "FROM rust\n"),
+        |copied, total| {
+            if copied == total {
+                std::fs::write(project.join("src/main.rs"), "fn main() { loop {} }").unwrap();
+            }
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(bundle_dir.join("src/main.rs")).unwrap(),
+        "fn main() {}"
+    );
+}
+
+#[test]
+fn bundle_includes_additional_dirs() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+
+    // Create project with extra dirs
+    std::fs::create_dir_all(project.join("src")).unwrap();
+    std::fs::create_dir_all(project.join("migrations")).unwrap();
+    std::fs::create_dir_all(project.join("templates")).unwrap();
+    std::fs::write(project.join("Cargo.toml"), "[package]\nname = \"test\"").unwrap();
+    std::fs::write(project.join("src/main.rs"), "fn main() {}").unwrap();
+    std::fs::write(project.join("migrations/001.sql"), "CREATE TABLE t;").unwrap();
+    std::fs::write(project.join("templates/index.html"), "<h1>hello</h1>").unwrap();
+
+    // Git init and commit all
+    Command::new("git")
+        .args(["init"])
+        .current_dir(project)
+        .output()
+        .unwrap();
+    Command::new("git")
+        .args(["config", "user.email", "test@test.com"])
+        .current_dir(project)
+        .output()
+        .unwrap();
+    Command::new("git")
+        .args(["config", "user.name", "Test"])
+        .current_dir(project)
+        .output()
+        .unwrap();
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(project)
+        .output()
+        .unwrap();
+    Command::new("git")
+        .args(["commit", "-m", "init"])
+        .current_dir(project)
+        .output()
+        .unwrap();
+
+    let bundle_dir = create_bundle_with(&BundleOptions::new(project, "FROM rust\n")).unwrap();
+
+    // Additional dirs should be in the bundle
+    assert!(bundle_dir.join("migrations/001.sql").exists());
+    assert!(bundle_dir.join("templates/index.html").exists());
+}
+
+#[test]
+fn bundle_respects_gitignore() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+
+    std::fs::create_dir_all(project.join("src")).unwrap();
+    std::fs::create_dir_all(project.join("target")).unwrap();
+    std::fs::write(project.join("Cargo.toml"), "[package]\nname = \"test\"").unwrap();
+    std::fs::write(project.join("src/main.rs"), "fn main() {}").unwrap();
+    std::fs::write(project.join("target/debug"), "binary").unwrap();
+    std::fs::write(project.join(".gitignore"), "target/\n").unwrap();
+
+    Command::new("git")
+        .args(["init"])
+        .current_dir(project)
+        .output()
+        .unwrap();
+    Command::new("git")
+        .args(["config", "user.email", "test@test.com"])
+        .current_dir(project)
+        .output()
+        .unwrap();
+    Command::new("git")
+        .args(["config", "user.name", "Test"])
+        .current_dir(project)
+        .output()
+        .unwrap();
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(project)
+        .output()
+        .unwrap();
+    Command::new("git")
+        .args(["commit", "-m", "init"])
+        .current_dir(project)
+        .output()
+        .unwrap();
+
+    let bundle_dir = create_bundle_with(&BundleOptions::new(project, "FROM rust\n")).unwrap();
+
+    // .gitignored files should NOT be in the bundle
+    assert!(!bundle_dir.join("target").exists());
+    // Tracked files should be
+    assert!(bundle_dir.join("src/main.rs").exists());
+    assert!(bundle_dir.join(".gitignore").exists());
+}
+
+#[test]
+fn bundle_excludes_propel_dirs() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+
+    std::fs::create_dir_all(project.join("src")).unwrap();
+    std::fs::create_dir_all(project.join(".propel")).unwrap();
+    std::fs::write(project.join("Cargo.toml"), "[package]\nname = \"test\"").unwrap();
+    std::fs::write(project.join("src/main.rs"), "fn main() {}").unwrap();
+    std::fs::write(project.join(".propel/Dockerfile"), "custom").unwrap();
+
+    Command::new("git")
+        .args(["init"])
+        .current_dir(project)
+        .output()
+        .unwrap();
+    Command::new("git")
+        .args(["config", "user.email", "test@test.com"])
+        .current_dir(project)
+        .output()
+        .unwrap();
+    Command::new("git")
+        .args(["config", "user.name", "Test"])
+        .current_dir(project)
+        .output()
+        .unwrap();
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(project)
+        .output()
+        .unwrap();
+    Command::new("git")
+        .args(["commit", "-m", "init"])
+        .current_dir(project)
+        .output()
+        .unwrap();
+
+    let bundle_dir = create_bundle_with(&BundleOptions::new(project, "FROM rust\n")).unwrap();
+
+    // .propel/ should be excluded by PROPEL_EXCLUDES
+    assert!(!bundle_dir.join(".propel").exists());
+    assert!(bundle_dir.join("src/main.rs").exists());
+}
+
+#[test]
+fn bundle_cleans_previous_bundle() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+    init_git_project(project);
+
+    // Create first bundle
+    let bundle1 = create_bundle_with(&BundleOptions::new(project, "FROM rust:1\n")).unwrap();
+    assert!(bundle1.join("Dockerfile").exists());
+
+    // Create second bundle — should overwrite
+    let bundle2 = create_bundle_with(&BundleOptions::new(project, "FROM rust:2\n")).unwrap();
+    let content = std::fs::read_to_string(bundle2.join("Dockerfile")).unwrap();
+    assert_eq!(content, "FROM rust:2\n");
+}
+
+#[test]
+fn bundle_copies_nested_src_dirs() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+
+    std::fs::create_dir_all(project.join("src/handlers")).unwrap();
+    std::fs::write(project.join("Cargo.toml"), "[package]\nname = \"test\"").unwrap();
+    std::fs::write(project.join("src/main.rs"), "fn main() {}").unwrap();
+    std::fs::write(project.join("src/handlers/mod.rs"), "pub fn handle() {}").unwrap();
+
+    Command::new("git")
+        .args(["init"])
+        .current_dir(project)
+        .output()
+        .unwrap();
     Command::new("git")
         .args(["config", "user.email", "test@test.com"])
         .current_dir(project)
@@ -479,20 +1690,661 @@ fn bundle_copies_nested_src_dirs() {
         .current_dir(project)
         .output()
         .unwrap();
-    Command::new("git")
-        .args(["add", "."])
-        .current_dir(project)
-        .output()
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(project)
+        .output()
+        .unwrap();
+    Command::new("git")
+        .args(["commit", "-m", "init"])
+        .current_dir(project)
+        .output()
+        .unwrap();
+
+    let bundle_dir = create_bundle_with(&BundleOptions::new(project, "FROM rust\n")).unwrap();
+
+    assert!(bundle_dir.join("src/handlers/mod.rs").exists());
+}
+
+#[test]
+fn bundle_includes_ignored_cargo_config_but_not_credentials() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+    std::fs::create_dir_all(project.join(".cargo")).unwrap();
+    std::fs::write(project.join(".cargo/config.toml"), VENDORED_CARGO_CONFIG).unwrap();
+    std::fs::write(project.join(".cargo/credentials.toml"), "token = \"x\"").unwrap();
+    std::fs::write(project.join(".gitignore"), ".cargo/\n").unwrap();
+    init_git_project(project);
+
+    assert!(is_ignored(project, Path::new(".cargo/config.toml")).unwrap());
+    let bundle_dir = create_bundle_with(&BundleOptions::new(project, "FROM rust\n")).unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(bundle_dir.join(".cargo/config.toml")).unwrap(),
+        VENDORED_CARGO_CONFIG
+    );
+    assert!(!bundle_dir.join(".cargo/credentials.toml").exists());
+}
+
+#[test]
+fn is_ignored_false_for_tracked_file() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+    init_git_project(project);
+
+    assert!(!is_ignored(project, Path::new("src/main.rs")).unwrap());
+}
+
+#[test]
+fn is_tracked_distinguishes_committed_and_untracked_files() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+    init_git_project(project);
+    eject(project, "FROM rust\n").unwrap();
+
+    assert!(is_tracked(project, Path::new("src/main.rs")).unwrap());
+    assert!(!is_tracked(project, Path::new(".propel/Dockerfile")).unwrap());
+
+    git_commit_all(project);
+    assert!(is_tracked(project, Path::new(".propel/Dockerfile")).unwrap());
+}
+
+#[test]
+fn is_tracked_outside_git_repo_is_error() {
+    let tmp = TempDir::new().unwrap();
+    std::fs::write(tmp.path().join("file"), "x").unwrap();
+
+    let err = is_tracked(tmp.path(), Path::new("file")).unwrap_err();
+
+    assert!(matches!(err, BundleError::NotARepo { .. }), "{err:?}");
+}
+
+// ── Nested Bundle Tests ──
+
+#[test]
+fn enclosing_bundle_root_cases() {
+    let cases: &[(&str, Option<&str>)] = &[
+        ("/home/u/proj", None),
+        ("/home/u/proj/.propel-bundle", Some("/home/u/proj")),
+        ("/home/u/proj/.propel-bundle/src/bin", Some("/home/u/proj")),
+        // A bundle inside a bundle: the outermost project is the real one.
+        (
+            "/home/u/proj/.propel-bundle/.propel-bundle",
+            Some("/home/u/proj"),
+        ),
+        ("/home/u/proj/.propel-bundle/..", None),
+        ("/home/u/proj/.propel-bundle/../src", None),
+        ("/home/u/proj/src/../.propel-bundle", Some("/home/u/proj")),
+        ("/home/u/proj/./.propel-bundle/./src", Some("/home/u/proj")),
+        ("/home/u/.propel-bundle-old/proj", None),
+        ("/home/u/proj/.propel", None),
+        ("/.propel-bundle", Some("/")),
+        (".propel-bundle", Some(".")),
+        ("./.propel-bundle/src", Some(".")),
+        ("proj/.propel-bundle", Some("proj")),
+        ("../proj/.propel-bundle", Some("../proj")),
+        ("..", None),
+    ];
+    for (dir, expected) in cases {
+        assert_eq!(
+            enclosing_bundle_root(Path::new(dir)),
+            expected.map(PathBuf::from),
+            "{dir}"
+        );
+    }
+}
+
+#[test]
+fn create_bundle_refuses_to_run_inside_a_bundle() {
+    let tmp = tempfile::tempdir().unwrap();
+    let inside = tmp.path().join(".propel-bundle");
+    std::fs::create_dir(&inside).unwrap();
+
+    let err = create_bundle_with(&BundleOptions::new(&inside, "FROM rust\n")).unwrap_err();
+
+    match err {
+        BundleError::InsideBundle { project_root, .. } => assert_eq!(project_root, tmp.path()),
+        other => panic!("expected InsideBundle, got {other:?}"),
+    }
+    assert!(!inside.join(".propel-bundle").exists());
+}
+
+#[test]
+fn check_project_dir_accepts_a_project() {
+    let tmp = tempfile::tempdir().unwrap();
+    std::fs::create_dir(tmp.path().join(".propel-bundle")).unwrap();
+
+    check_project_dir(tmp.path()).unwrap();
+}
+
+#[test]
+fn check_project_dir_inside_bundle_suggests_project_root() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path().canonicalize().unwrap();
+    let inside = root.join(".propel-bundle/src");
+    std::fs::create_dir_all(&inside).unwrap();
+
+    let err = check_project_dir(&inside.join("..")).unwrap_err();
+
+    assert!(matches!(err, BundleError::InsideBundle { .. }), "{err:?}");
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "{} is inside a propel bundle, not a project; run propel from {} instead",
+            root.join(".propel-bundle").display(),
+            root.display()
+        )
+    );
+}
+
+#[test]
+fn check_project_dir_below_a_deployed_project_suggests_its_root() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path().canonicalize().unwrap();
+    std::fs::create_dir(root.join(".propel-bundle")).unwrap();
+    let sub = root.join("src/handlers");
+    std::fs::create_dir_all(&sub).unwrap();
+
+    match check_project_dir(&sub).unwrap_err() {
+        BundleError::InsideDeployedProject { dir, project_root } => {
+            assert_eq!(dir, sub);
+            assert_eq!(project_root, root);
+        }
+        other => panic!("expected InsideDeployedProject, got {other:?}"),
+    }
+}
+
+#[test]
+fn check_project_dir_accepts_a_member_of_a_deployed_workspace() {
+    let tmp = tempfile::tempdir().unwrap();
+    let root = tmp.path();
+    std::fs::write(
+        root.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"api\"]\n",
+    )
+    .unwrap();
+    std::fs::create_dir(root.join(".propel-bundle")).unwrap();
+    std::fs::create_dir(root.join("api")).unwrap();
+
+    check_project_dir(&root.join("api")).unwrap();
+}
+
+#[test]
+fn check_project_dir_missing_directory_is_an_error() {
+    let tmp = tempfile::tempdir().unwrap();
+
+    let err = check_project_dir(&tmp.path().join("missing")).unwrap_err();
+
+    assert!(matches!(err, BundleError::ResolveProjectDir { .. }));
+}
+
+// ── Cargo Config Tests ──
+
+#[test]
+fn cargo_config_collects_vendored_directories() {
+    let config = CargoConfig::parse(".cargo/config.toml", VENDORED_CARGO_CONFIG).unwrap();
+
+    assert_eq!(config.vendor_dirs, vec!["vendor"]);
+    assert!(config.external_dirs.is_empty());
+}
+
+#[test]
+fn cargo_config_separates_sources_outside_project() {
+    let content = r#"
+[source.a]
+local-registry = "registry/"
+[source.b]
+directory = "../shared-vendor"
+[source.c]
+directory = "/opt/vendor"
+[registries.internal]
+index = "sparse+https://crates.example.com/index/"
+"#;
+    let config = CargoConfig::parse(".cargo/config.toml", content).unwrap();
+
+    assert_eq!(config.vendor_dirs, vec!["registry"]);
+    assert_eq!(
+        config.external_dirs,
+        vec!["../shared-vendor", "/opt/vendor"]
+    );
+}
+
+#[test]
+fn cargo_config_detect_reads_fixture_and_prefers_config_toml() {
+    let tmp = TempDir::new().unwrap();
+    assert_eq!(CargoConfig::detect(tmp.path()).unwrap(), None);
+
+    std::fs::create_dir_all(tmp.path().join(".cargo")).unwrap();
+    std::fs::write(tmp.path().join(".cargo/config"), "").unwrap();
+    std::fs::write(tmp.path().join(".cargo/config.toml"), VENDORED_CARGO_CONFIG).unwrap();
+
+    let config = CargoConfig::detect(tmp.path()).unwrap().unwrap();
+    assert_eq!(config.path, ".cargo/config.toml");
+    assert_eq!(config.vendor_dirs, vec!["vendor"]);
+}
+
+#[test]
+fn cargo_config_detect_rejects_malformed_toml() {
+    let tmp = TempDir::new().unwrap();
+    std::fs::create_dir_all(tmp.path().join(".cargo")).unwrap();
+    std::fs::write(tmp.path().join(".cargo/config.toml"), "[source").unwrap();
+
+    let err = CargoConfig::detect(tmp.path()).unwrap_err();
+    assert!(err.to_string().contains("config.toml"));
+}
+
+#[test]
+fn dockerfile_copies_cargo_config_before_cook() {
+    let build = BuildConfig::default();
+    let project = default_project();
+    let cargo_config = CargoConfig::parse(".cargo/config.toml", VENDORED_CARGO_CONFIG).unwrap();
+    let output = DockerfileGenerator::with_options(
+        DockerfileOptions::new(&build, &project).cargo_config(&cargo_config),
+    )
+    .render();
+
+    let cacher = output
+        .split_once("Stage 2: Cacher")
+        .unwrap()
+        .1
+        .split_once("Stage 3: Builder")
+        .unwrap()
+        .0;
+    let cook = cacher.find("cargo chef cook").unwrap();
+    let config_copy = cacher
+        .find("COPY .cargo/config.toml ./.cargo/config.toml")
+        .unwrap();
+    let vendor_copy = cacher.find("COPY vendor/ ./vendor/").unwrap();
+    assert!(config_copy < cook);
+    assert!(vendor_copy < cook);
+}
+
+#[test]
+fn dockerfile_without_cargo_config_copies_only_recipe_into_cacher() {
+    let build = BuildConfig::default();
+    let project = default_project();
+    let output =
+        DockerfileGenerator::with_options(DockerfileOptions::new(&build, &project)).render();
+
+    assert!(!output.contains(".cargo"));
+}
+
+#[test]
+fn bundle_entries_lists_files_relative_and_sorted() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+    init_git_project(project);
+
+    let bundle_dir = create_bundle_with(&BundleOptions::new(project, "FROM rust\n")).unwrap();
+
+    assert_eq!(
+        bundle_entries(&bundle_dir).unwrap(),
+        vec![
+            PathBuf::from("Cargo.toml"),
+            PathBuf::from("Dockerfile"),
+            PathBuf::from("src/main.rs"),
+        ]
+    );
+}
+
+#[test]
+fn bundle_summary_groups_by_top_level_dir() {
+    let entries: Vec<PathBuf> = [
+        "Cargo.toml",
+        "Dockerfile",
+        "migrations/0001_init.sql",
+        "migrations/0002_users.sql",
+        "migrations/0003_posts.sql",
+        "src/main.rs",
+        "src/routes/mod.rs",
+    ]
+    .into_iter()
+    .map(PathBuf::from)
+    .collect();
+
+    assert_eq!(
+        format_bundle_summary(&entries, 2),
+        "\
+Bundle: 7 file(s)
+  ./ (2)
+    Cargo.toml
+    Dockerfile
+  migrations/ (3)
+    migrations/0001_init.sql
+    migrations/0002_users.sql
+    ... 1 more
+  src/ (2)
+    src/main.rs
+    src/routes/mod.rs
+"
+    );
+}
+
+#[test]
+fn bundle_summary_of_empty_bundle() {
+    assert_eq!(format_bundle_summary(&[], 10), "Bundle: 0 file(s)\n");
+}
+
+#[test]
+fn bundle_size_orders_files_largest_first() {
+    let tmp = TempDir::new().unwrap();
+    std::fs::create_dir(tmp.path().join("data")).unwrap();
+    std::fs::write(tmp.path().join("Cargo.toml"), vec![b'x'; 10]).unwrap();
+    std::fs::write(tmp.path().join("data/big.bin"), vec![0; 3000]).unwrap();
+    std::fs::write(tmp.path().join("data/mid.bin"), vec![0; 2000]).unwrap();
+
+    let entries = bundle_entries(tmp.path()).unwrap();
+    let size = BundleSize::measure(tmp.path(), &entries).unwrap();
+
+    assert_eq!(size.total, 5010);
+    let order: Vec<&Path> = size.files.iter().map(|(p, _)| p.as_path()).collect();
+    assert_eq!(
+        order,
+        vec![
+            Path::new("data/big.bin"),
+            Path::new("data/mid.bin"),
+            Path::new("Cargo.toml"),
+        ]
+    );
+    size.check_limit(5010).unwrap();
+}
+
+#[test]
+fn bundle_over_limit_names_largest_files() {
+    let files: Vec<(PathBuf, u64)> = (1..=7)
+        .rev()
+        .map(|i| {
+            (
+                PathBuf::from(format!("assets/file{i}.bin")),
+                i * 1024 * 1024,
+            )
+        })
+        .collect();
+    let size = BundleSize {
+        total: files.iter().map(|(_, s)| s).sum(),
+        files,
+    };
+
+    let err = size.check_limit(10 * 1024 * 1024).unwrap_err();
+
+    assert!(matches!(err, BundleError::TooLarge { .. }));
+    let message = err.to_string();
+    assert!(
+        message.contains("bundle is 28.0 MiB, over the 10.0 MiB"),
+        "{message}"
+    );
+    assert!(message.contains("7.0 MiB  assets/file7.bin"), "{message}");
+    assert!(message.contains("assets/file3.bin"), "{message}");
+    assert!(!message.contains("assets/file2.bin"), "{message}");
+    assert!(message.contains(".gitignore"), "{message}");
+    assert!(message.contains("--force-large-bundle"), "{message}");
+}
+
+#[test]
+fn format_size_picks_a_readable_unit() {
+    assert_eq!(format_size(512), "512 B");
+    assert_eq!(format_size(1536), "1.5 KiB");
+    assert_eq!(format_size(3 * 1024 * 1024), "3.0 MiB");
+    assert_eq!(format_size(1536 * 1024 * 1024), "1.50 GiB");
+}
+
+// ── Workspace Lockfile Tests ──
+
+const WORKSPACE_LOCKFILE: &str = "# This file is automatically @generated by Cargo.\nversion = 4\n";
+
+/// A committed two-crate workspace: binary member `api` and library `shared`.
+/// Returns the `api` member directory.
+fn init_git_workspace(root: &Path, api_manifest: &str) -> PathBuf {
+    std::fs::write(
+        root.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"api\", \"shared\"]\nresolver = \"2\"\n\n\
+         [workspace.package]\nversion = \"0.2.0\"\n",
+    )
+    .unwrap();
+    let api = root.join("api");
+    std::fs::create_dir_all(api.join("src")).unwrap();
+    std::fs::write(api.join("Cargo.toml"), api_manifest).unwrap();
+    std::fs::write(api.join("src/main.rs"), "fn main() {}").unwrap();
+    std::fs::create_dir_all(root.join("shared/src")).unwrap();
+    std::fs::write(
+        root.join("shared/Cargo.toml"),
+        "[package]\nname = \"shared\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )
+    .unwrap();
+    std::fs::write(root.join("shared/src/lib.rs"), "").unwrap();
+    git_commit_all(root);
+    api
+}
+
+#[test]
+fn workspace_member_bundles_from_root_with_path_dependency() {
+    let tmp = TempDir::new().unwrap();
+    std::fs::write(tmp.path().join("Cargo.lock"), WORKSPACE_LOCKFILE).unwrap();
+    let api = init_git_workspace(
+        tmp.path(),
+        "[package]\nname = \"api\"\nversion.workspace = true\nedition = \"2021\"\n\n\
+         [dependencies]\nshared = { path = \"../shared\" }\n",
+    );
+    let project = CargoProject::discover(&api).unwrap();
+    assert_eq!(project.bundle_root(), project.workspace_root);
+
+    let config = BuildConfig::default();
+    let dockerfile = DockerfileGenerator::with_options(
+        DockerfileOptions::new(&config, &project).lockfile_bundled(lockfile_bundled(&project)),
+    )
+    .render();
+    let bundle_dir =
+        create_bundle_with(&BundleOptions::new(project.bundle_root(), &dockerfile)).unwrap();
+
+    assert_eq!(bundle_dir, project.workspace_root.join(BUNDLE_DIR));
+    let entries = bundle_entries(&bundle_dir).unwrap();
+    for expected in [
+        "Cargo.toml",
+        "Cargo.lock",
+        "Dockerfile",
+        "api/Cargo.toml",
+        "api/src/main.rs",
+        "shared/Cargo.toml",
+        "shared/src/lib.rs",
+    ] {
+        assert!(
+            entries.contains(&PathBuf::from(expected)),
+            "{expected}: {entries:?}"
+        );
+    }
+    let dockerfile = std::fs::read_to_string(bundle_dir.join("Dockerfile")).unwrap();
+    assert!(
+        dockerfile.contains("cargo chef cook --release --locked --package api --recipe-path"),
+        "{dockerfile}"
+    );
+    assert!(
+        dockerfile.contains("cargo build --release --locked --package api --bin api"),
+        "{dockerfile}"
+    );
+    let runtime = dockerfile
+        .split("# === Stage 4: Runtime ===")
+        .nth(1)
         .unwrap();
-    Command::new("git")
-        .args(["commit", "-m", "init"])
-        .current_dir(project)
-        .output()
+    assert!(
+        runtime.contains("COPY --from=builder /app/target/release/api /usr/local/bin/app\n"),
+        "{runtime}"
+    );
+    assert!(runtime.contains("COPY api/ ./\n"), "{runtime}");
+    assert!(!runtime.contains("COPY . ."), "{runtime}");
+
+    // The staged bundle neither dirties the tree nor blocks the next deploy.
+    assert!(!is_dirty(&api).unwrap());
+    check_project_dir(&api).unwrap();
+}
+
+#[test]
+fn workspace_member_include_paths_are_relative_to_the_member() {
+    let config = BuildConfig {
+        include: Some(vec!["migrations/".to_owned(), "config.toml".to_owned()]),
+        bundle_manifest: true,
+        ..Default::default()
+    };
+    let project = CargoProject {
+        package_dir: PathBuf::from("/ws/services/api"),
+        workspace_root: PathBuf::from("/ws"),
+        ..default_project()
+    };
+
+    let dockerfile =
+        DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project)).render();
+
+    assert!(
+        dockerfile.contains(
+            "WORKDIR /app\n\
+             COPY services/api/migrations/ ./migrations/\n\
+             COPY services/api/config.toml ./config.toml\n\
+             COPY bundle-manifest.json ./bundle-manifest.json\n"
+        ),
+        "{dockerfile}"
+    );
+    assert!(dockerfile.contains("--package my-service --bin my-service"));
+}
+
+#[test]
+fn lockfile_bundled_checks_package_and_workspace_root() {
+    let tmp = TempDir::new().unwrap();
+    let member = tmp.path().join("api");
+    std::fs::create_dir(&member).unwrap();
+    let project = CargoProject {
+        package_dir: member.clone(),
+        workspace_root: tmp.path().to_path_buf(),
+        ..default_project()
+    };
+    assert!(!lockfile_bundled(&project));
+
+    std::fs::write(tmp.path().join("Cargo.lock"), "version = 4\n").unwrap();
+    assert!(lockfile_bundled(&project));
+
+    let single = CargoProject {
+        package_dir: member.clone(),
+        workspace_root: member.clone(),
+        ..default_project()
+    };
+    assert!(!lockfile_bundled(&single));
+    std::fs::write(member.join("Cargo.lock"), "version = 4\n").unwrap();
+    assert!(lockfile_bundled(&single));
+}
+
+// ── Bundle Manifest Tests ──
+
+#[test]
+fn bundle_manifest_is_stable_across_runs() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+    init_git_project(project);
+    let bundle_dir = create_bundle_with(&BundleOptions::new(project, "FROM rust\n")).unwrap();
+
+    let first = BundleManifest::build(&bundle_dir, Some("abc123"), "0.3.0").unwrap();
+    let first_sha = first.write(&bundle_dir).unwrap();
+    // The manifest now sits in the bundle but is not hashed into itself.
+    let second = BundleManifest::build(&bundle_dir, Some("abc123"), "0.3.0").unwrap();
+    let second_sha = second.write(&bundle_dir).unwrap();
+
+    assert_eq!(first, second);
+    assert_eq!(first_sha, second_sha);
+    let paths: Vec<_> = first.files.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(paths, ["Cargo.toml", "Dockerfile", "src/main.rs"]);
+}
+
+#[test]
+fn bundle_manifest_records_sizes_and_hashes() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+    init_git_project(project);
+    let bundle_dir = create_bundle_with(&BundleOptions::new(project, "FROM rust\n")).unwrap();
+
+    let manifest = BundleManifest::build(&bundle_dir, None, "0.3.0").unwrap();
+
+    let main_rs = &manifest.files[2];
+    assert_eq!(main_rs.size, "fn main() {}".len() as u64);
+    assert_eq!(main_rs.sha256.len(), 64);
+    // sha256("FROM rust\n")
+    assert_eq!(
+        manifest.dockerfile_sha256,
+        "3ce20c6e6815100c70591056ceb25df5015d618d445b1219f5745449ec724e2f"
+    );
+    assert_eq!(manifest.files[1].sha256, manifest.dockerfile_sha256);
+}
+
+#[test]
+fn bundle_manifest_changes_with_a_single_byte() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+    init_git_project(project);
+    let bundle_dir = create_bundle_with(&BundleOptions::new(project, "FROM rust\n")).unwrap();
+    let before = BundleManifest::build(&bundle_dir, Some("abc123"), "0.3.0").unwrap();
+    let before_sha = before.write(&bundle_dir).unwrap();
+
+    std::fs::write(bundle_dir.join("src/main.rs"), "fn main() {;").unwrap();
+    let after = BundleManifest::build(&bundle_dir, Some("abc123"), "0.3.0").unwrap();
+    let after_sha = after.write(&bundle_dir).unwrap();
+
+    assert_eq!(before.files[0], after.files[0]);
+    assert_eq!(before.files[2].size, after.files[2].size);
+    assert_ne!(before.files[2].sha256, after.files[2].sha256);
+    assert_ne!(before_sha, after_sha);
+}
+
+#[test]
+fn bundle_manifest_is_written_as_json() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+    init_git_project(project);
+    let bundle_dir = create_bundle_with(&BundleOptions::new(project, "FROM rust\n")).unwrap();
+
+    BundleManifest::build(&bundle_dir, Some("abc123"), "0.3.0")
+        .unwrap()
+        .write(&bundle_dir)
         .unwrap();
 
-    let bundle_dir = create_bundle(project, "FROM rust\n").unwrap();
+    let json = std::fs::read_to_string(bundle_dir.join(MANIFEST_FILE)).unwrap();
+    assert!(json.contains("\"propel_version\": \"0.3.0\""));
+    assert!(json.contains("\"git_commit\": \"abc123\""));
+    assert!(json.contains("\"path\": \"src/main.rs\""));
+}
 
-    assert!(bundle_dir.join("src/handlers/mod.rs").exists());
+#[test]
+fn bundle_manifest_without_dockerfile_is_rejected() {
+    let tmp = TempDir::new().unwrap();
+    std::fs::write(tmp.path().join("Cargo.toml"), "[package]").unwrap();
+
+    let err = BundleManifest::build(tmp.path(), None, "0.3.0").unwrap_err();
+
+    assert!(matches!(err, ManifestError::MissingDockerfile { .. }));
+}
+
+#[test]
+fn dockerfile_include_copies_bundle_manifest() {
+    let config = BuildConfig {
+        include: Some(vec!["migrations/".to_owned()]),
+        bundle_manifest: true,
+        ..Default::default()
+    };
+    let project = default_project();
+    let output =
+        DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project)).render();
+
+    let runtime_section = output.split("Stage 4: Runtime").nth(1).unwrap();
+    assert!(runtime_section.contains("COPY migrations/ ./migrations/\n"));
+    assert!(runtime_section.contains("COPY bundle-manifest.json ./bundle-manifest.json\n"));
+}
+
+#[test]
+fn dockerfile_include_omits_manifest_when_disabled() {
+    let config = BuildConfig {
+        include: Some(vec!["migrations/".to_owned()]),
+        ..Default::default()
+    };
+    let project = default_project();
+    let output =
+        DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project)).render();
+
+    assert!(!output.contains(MANIFEST_FILE));
 }
 
 // ── Dirty Check Tests ──
@@ -534,6 +2386,20 @@ fn is_dirty_with_untracked_file() {
     assert!(is_dirty(project).unwrap());
 }
 
+#[test]
+fn is_dirty_ignores_bundle_directories() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+    init_git_project(project);
+
+    for dir in [".propel-bundle", "src/.propel-bundle"] {
+        std::fs::create_dir_all(project.join(dir)).unwrap();
+        std::fs::write(project.join(dir).join("Dockerfile"), "FROM rust\n").unwrap();
+    }
+
+    assert!(!is_dirty(project).unwrap());
+}
+
 // ── Eject Tests ──
 
 #[test]
@@ -561,6 +2427,34 @@ fn eject_preserves_dockerfile_content() {
     assert_eq!(loaded, content);
 }
 
+#[test]
+fn eject_writes_readme_explaining_propel_dir() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+
+    eject(project, "FROM rust\n").unwrap();
+
+    let readme = std::fs::read_to_string(project.join(".propel/README.md")).unwrap();
+    assert_eq!(readme, EJECT_README);
+    assert!(readme.contains("Commit it to git"));
+    assert!(readme.contains(".propel-bundle/"));
+}
+
+#[test]
+fn eject_keeps_existing_readme() {
+    let tmp = TempDir::new().unwrap();
+    let project = tmp.path();
+    std::fs::create_dir_all(project.join(".propel")).unwrap();
+    std::fs::write(project.join(".propel/README.md"), "our notes\n").unwrap();
+
+    eject(project, "FROM rust\n").unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(project.join(".propel/README.md")).unwrap(),
+        "our notes\n"
+    );
+}
+
 #[test]
 fn eject_fails_if_already_ejected() {
     let tmp = TempDir::new().unwrap();
@@ -579,3 +2473,153 @@ fn is_ejected_false_without_propel_dir() {
     let tmp = TempDir::new().unwrap();
     assert!(!is_ejected(tmp.path()));
 }
+
+#[test]
+fn generated_dockerfile_references_only_its_binary() {
+    let config = BuildConfig::default();
+    let project = default_project();
+    let generator = DockerfileGenerator::with_options(DockerfileOptions::new(&config, &project));
+    let output = generator.render();
+
+    let references = binary_references(&output);
+    assert!(
+        references
+            .iter()
+            .any(|r| r.kind == BinaryReferenceKind::BinFlag)
+    );
+    assert!(
+        references
+            .iter()
+            .any(|r| r.kind == BinaryReferenceKind::ReleasePath)
+    );
+    assert!(mismatched_binary_references(&output, &project.default_binary).is_empty());
+}
+
+#[test]
+fn renamed_binary_is_reported_on_every_reference() {
+    let output = DockerfileGenerator::with_options(DockerfileOptions::new(
+        &BuildConfig::default(),
+        &default_project(),
+    ))
+    .render();
+
+    let mismatched = mismatched_binary_references(&output, "api");
+    assert!(
+        mismatched
+            .iter()
+            .any(|r| r.kind == BinaryReferenceKind::BinFlag)
+    );
+    assert!(
+        mismatched
+            .iter()
+            .any(|r| r.kind == BinaryReferenceKind::ReleasePath)
+    );
+    assert!(mismatched.iter().all(|r| r.name == "my-service"));
+}
+
+#[test]
+fn binary_references_report_lines_and_kinds() {
+    let dockerfile = "FROM rust:1.85\n\
+                      RUN cargo build --release --bin=old-name\n\
+                      COPY --from=builder /app/target/release/old-name /usr/local/bin/app\n\
+                      ENTRYPOINT [\"/app/target/release/old-name\"]\n";
+
+    assert_eq!(
+        binary_references(dockerfile),
+        vec![
+            BinaryReference {
+                line: 2,
+                kind: BinaryReferenceKind::BinFlag,
+                name: "old-name".to_owned(),
+            },
+            BinaryReference {
+                line: 3,
+                kind: BinaryReferenceKind::ReleasePath,
+                name: "old-name".to_owned(),
+            },
+            BinaryReference {
+                line: 4,
+                kind: BinaryReferenceKind::ReleasePath,
+                name: "old-name".to_owned(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn binary_references_skip_comments_cargo_dirs_and_variables() {
+    let dockerfile = "# RUN cargo build --bin old-name\n\
+                      RUN cargo build --release --bin ${BIN}\n\
+                      RUN ls /app/target/release/deps\n\
+                      COPY --from=builder /app/target/release/* /out/\n\
+                      RUN cargo build --release --bin my-service\n";
+
+    let references = binary_references(dockerfile);
+    assert_eq!(references.len(), 1, "{references:?}");
+    assert_eq!(references[0].line, 5);
+    assert!(mismatched_binary_references(dockerfile, "my-service").is_empty());
+}
+
+// ── run_as_user Tests ──
+
+fn runtime_section(config: &BuildConfig) -> String {
+    let project = default_project();
+    let output =
+        DockerfileGenerator::with_options(DockerfileOptions::new(config, &project)).render();
+    output.split("Stage 4: Runtime").nth(1).unwrap().to_owned()
+}
+
+#[test]
+fn dockerfile_runs_as_root_by_default() {
+    let runtime = runtime_section(&BuildConfig::default());
+
+    assert!(!runtime.contains("USER"));
+    assert!(!runtime.contains("--chown"));
+}
+
+#[test]
+fn dockerfile_numeric_run_as_user_sets_uid_and_gid() {
+    let config = BuildConfig {
+        run_as_user: Some(RunAsUser::Uid(65532)),
+        ..Default::default()
+    };
+    let runtime = runtime_section(&config);
+
+    assert!(runtime.contains(
+        "COPY --chown=65532:65532 --from=builder /app/target/release/my-service /usr/local/bin/app"
+    ));
+    assert!(runtime.contains("COPY --chown=65532:65532 . ."));
+    assert!(runtime.contains("USER 65532:65532\nEXPOSE 8080"));
+}
+
+#[test]
+fn dockerfile_named_run_as_user_is_used_as_written() {
+    let config = BuildConfig {
+        run_as_user: Some(RunAsUser::Name("nonroot".to_owned())),
+        include: Some(vec!["migrations/".to_owned(), "seeds.txt".to_owned()]),
+        bundle_manifest: true,
+        ..Default::default()
+    };
+    let runtime = runtime_section(&config);
+
+    assert!(runtime.contains("COPY --chown=nonroot:nonroot --from=builder "));
+    assert!(runtime.contains("COPY --chown=nonroot:nonroot migrations/ ./migrations/"));
+    assert!(runtime.contains("COPY --chown=nonroot:nonroot seeds.txt ./seeds.txt"));
+    assert!(runtime.contains("COPY --chown=nonroot:nonroot bundle-manifest.json"));
+    assert!(runtime.contains("USER nonroot\nEXPOSE 8080"));
+}
+
+#[test]
+fn dockerfile_run_as_user_follows_runtime_package_install() {
+    let config = BuildConfig {
+        runtime_image: "debian:bookworm-slim".to_owned(),
+        runtime_packages: vec!["libpq5".to_owned()],
+        run_as_user: Some(RunAsUser::Uid(1000)),
+        ..Default::default()
+    };
+    let runtime = runtime_section(&config);
+
+    let install = runtime.find("apt-get install").unwrap();
+    let user = runtime.find("USER 1000:1000").unwrap();
+    assert!(install < user);
+}
@@ -0,0 +1,144 @@
+//! Call patterns the crate documents as stable (see the crate docs). Each
+//! test must keep compiling unchanged across minor releases; a failure
+//! here is a breaking change.
+
+use std::path::{Path, PathBuf};
+
+use propel_build::bundle::{self, BundleError, BundleFormat, BundleOptions};
+use propel_build::cargo_config::CargoConfigError;
+use propel_build::dockerfile::{DEFAULT_PORT, ImageFamily};
+use propel_build::eject::EjectError;
+use propel_build::manifest::ManifestError;
+use propel_build::{CargoConfig, DockerfileGenerator, DockerfileOptions};
+use propel_core::{BuildConfig, CargoBinary, CargoProject};
+
+fn project() -> CargoProject {
+    CargoProject {
+        name: "svc".to_owned(),
+        version: "0.1.0".to_owned(),
+        manifest_path: PathBuf::from("Cargo.toml"),
+        package_dir: PathBuf::from("."),
+        workspace_root: PathBuf::from("."),
+        binaries: vec![CargoBinary {
+            name: "svc".to_owned(),
+            src_path: PathBuf::from("src/main.rs"),
+        }],
+        default_binary: "svc".to_owned(),
+    }
+}
+
+#[test]
+fn dockerfile_from_options() {
+    let config = BuildConfig::default();
+    let project = project();
+    let cargo_config = CargoConfig::parse(".cargo/config.toml", "").unwrap();
+
+    let options: DockerfileOptions<'_> = DockerfileOptions::new(&config, &project)
+        .port(DEFAULT_PORT)
+        .cargo_config(&cargo_config)
+        .lockfile_bundled(true);
+    let generator = DockerfileGenerator::with_options(options);
+    let checked: propel_core::Result<()> = generator.check_runtime_image();
+    let dockerfile: String = generator.render();
+
+    checked.unwrap();
+    assert!(dockerfile.contains("EXPOSE 8080"));
+}
+
+#[test]
+#[cfg(feature = "deprecated-shims")]
+#[allow(deprecated)]
+fn deprecated_dockerfile_shims_match_options() {
+    let config = BuildConfig::default();
+    let project = project();
+
+    let old = DockerfileGenerator::new(&config, &project, 3000)
+        .lockfile_bundled(true)
+        .render();
+    let new = DockerfileGenerator::with_options(
+        DockerfileOptions::new(&config, &project)
+            .port(3000)
+            .lockfile_bundled(true),
+    )
+    .render();
+
+    assert_eq!(old, new);
+}
+
+/// Only has to compile: bundling needs a git repository.
+#[allow(dead_code)]
+fn bundle_from_options(source: &Path, dockerfile: &str) -> Result<PathBuf, BundleError> {
+    bundle::check_project_dir(source)?;
+    let _dirty: bool = bundle::is_dirty(source)?;
+    let options = BundleOptions::new(source, dockerfile)
+        .exclude("target")
+        .format(BundleFormat::Directory);
+    let _files: Vec<PathBuf> = bundle::bundle_files(&options)?;
+    let bundle_dir = bundle::create_bundle_with(&options)?;
+    let bundle_dir_again = bundle::create_bundle_with_progress(&options, |copied, total| {
+        let _: (usize, usize) = (copied, total);
+    })?;
+    assert_eq!(bundle_dir, bundle_dir_again);
+    Ok(bundle_dir)
+}
+
+/// Only has to compile: the deprecated shim keeps its signature.
+#[cfg(feature = "deprecated-shims")]
+#[allow(dead_code, deprecated)]
+fn deprecated_bundle_shim(source: &Path, dockerfile: &str) -> Result<PathBuf, BundleError> {
+    let bundle_dir = bundle::create_bundle(source, dockerfile)?;
+    Ok(bundle_dir)
+}
+
+#[test]
+fn errors_are_matched_with_a_wildcard_arm() {
+    fn describe_bundle(err: &BundleError) -> &'static str {
+        match err {
+            BundleError::GitNotFound => "install git",
+            BundleError::InsideBundle { .. } => "run from the project root",
+            _ => "other",
+        }
+    }
+    fn describe_eject(err: &EjectError) -> &'static str {
+        match err {
+            EjectError::AlreadyEjected(_) => "already ejected",
+            _ => "other",
+        }
+    }
+    fn describe_cargo_config(err: &CargoConfigError) -> &'static str {
+        match err {
+            CargoConfigError::Read { .. } => "unreadable",
+            _ => "other",
+        }
+    }
+    fn describe_manifest(err: &ManifestError) -> &'static str {
+        match err {
+            ManifestError::MissingDockerfile { .. } => "no Dockerfile",
+            _ => "other",
+        }
+    }
+    fn describe_family(family: ImageFamily) -> &'static str {
+        match family {
+            ImageFamily::Glibc => "glibc",
+            ImageFamily::Musl | ImageFamily::Static => "static binaries only",
+            _ => "unknown",
+        }
+    }
+
+    assert_eq!(describe_bundle(&BundleError::GitNotFound), "install git");
+    assert_eq!(
+        describe_eject(&EjectError::AlreadyEjected(PathBuf::from(".propel"))),
+        "already ejected"
+    );
+    let _: fn(&CargoConfigError) -> &'static str = describe_cargo_config;
+    assert_eq!(
+        describe_manifest(&ManifestError::MissingDockerfile {
+            bundle_dir: PathBuf::from(".propel-bundle"),
+        }),
+        "no Dockerfile"
+    );
+    assert_eq!(
+        describe_family(ImageFamily::classify("alpine:3.20")),
+        "static binaries only"
+    );
+}
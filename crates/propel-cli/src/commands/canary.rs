@@ -0,0 +1,263 @@
+//! `[cloud_run].canary`: deploy starts a canary, `propel promote` and
+//! `propel abort-canary` end it.
+
+use super::CliError;
+use super::ProjectContext;
+use super::history;
+use propel_cloud::{CanaryState, GcloudClient};
+use propel_core::{CanaryConfig, DeployEvent, DeployRecord};
+use std::path::PathBuf;
+
+/// Send all traffic to the canary revision released by `propel deploy`
+/// under `[cloud_run].canary`.
+pub async fn promote() -> Result<(), CliError> {
+    let project_dir = PathBuf::from(".");
+    let ctx = ProjectContext::load(&project_dir)?;
+    let project_id = ctx.gcp_project_id()?;
+    let service_name = ctx.service_name();
+    let region = ctx.region();
+
+    let client = GcloudClient::new();
+    let account =
+        super::announce_target(&client, &ctx.config, project_id, None, service_name).await?;
+
+    let canary = active_canary(&client, &ctx, project_id).await?;
+    check_promotable(&canary)?;
+
+    println!(
+        "Sending all traffic to {} (now {}%)...",
+        canary.revision, canary.percent
+    );
+    let url = client
+        .promote_canary(service_name, project_id, region)
+        .await?;
+
+    history::record(
+        &ctx,
+        &DeployRecord {
+            revision: Some(canary.revision.clone()),
+            percent: Some(canary.percent),
+            ..history::new_record(&ctx, DeployEvent::CanaryPromoted, account.as_deref())
+        },
+        |line| println!("{line}"),
+    );
+
+    println!();
+    println!("Promoted: {url} now serves {}", canary.revision);
+    Ok(())
+}
+
+/// Send all traffic back to the revision that served before the canary,
+/// and drop the canary tag.
+pub async fn abort_canary() -> Result<(), CliError> {
+    let project_dir = PathBuf::from(".");
+    let ctx = ProjectContext::load(&project_dir)?;
+    let project_id = ctx.gcp_project_id()?;
+    let service_name = ctx.service_name();
+    let region = ctx.region();
+
+    let client = GcloudClient::new();
+    let account =
+        super::announce_target(&client, &ctx.config, project_id, None, service_name).await?;
+
+    let canary = active_canary(&client, &ctx, project_id).await?;
+    let stable = abort_target(&canary)?;
+
+    println!(
+        "Sending all traffic back to {stable} (canary {} had {}%)...",
+        canary.revision, canary.percent
+    );
+    let url = client
+        .abort_canary(service_name, project_id, region, stable)
+        .await?;
+
+    history::record(
+        &ctx,
+        &DeployRecord {
+            revision: Some(canary.revision.clone()),
+            stable_revision: Some(stable.to_owned()),
+            percent: Some(canary.percent),
+            ..history::new_record(&ctx, DeployEvent::CanaryAborted, account.as_deref())
+        },
+        |line| println!("{line}"),
+    );
+
+    println!();
+    println!("Canary aborted: {url} now serves {stable}");
+    println!(
+        "Traffic stays pinned to {stable}: the next `propel deploy` with \
+         [cloud_run].canary or warmup_requests moves it again; otherwise run\n  \
+         gcloud run services update-traffic {service_name} --to-latest \
+         --project {project_id} --region {region}"
+    );
+    Ok(())
+}
+
+/// The canary in progress on the service, or an error saying there is
+/// none.
+async fn active_canary(
+    client: &GcloudClient,
+    ctx: &ProjectContext,
+    project_id: &str,
+) -> Result<CanaryState, CliError> {
+    let service_name = ctx.service_name();
+    let region = ctx.region();
+    match client.canary_state(service_name, project_id, region).await {
+        Ok(Some(canary)) => Ok(canary),
+        Ok(None) => Err(CliError::Deploy(anyhow::Error::msg(format!(
+            "no canary of {service_name} is in progress; \
+             set [cloud_run].canary and run `propel deploy` to start one"
+        )))),
+        Err(e) if e.is_service_not_found() => {
+            let message = match super::find_in_other_regions(
+                client,
+                project_id,
+                service_name,
+                region,
+            )
+            .await
+            {
+                Some(hint) => format!("{service_name} not found in {region}; {hint}"),
+                None => format!("{service_name} is not deployed in {region}"),
+            };
+            Err(CliError::Deploy(anyhow::Error::msg(message)))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Promoting follows the latest revision, so refuse when a newer one
+/// (e.g. from `propel update`) would get the traffic instead.
+fn check_promotable(canary: &CanaryState) -> Result<(), CliError> {
+    if canary.latest {
+        return Ok(());
+    }
+    Err(CliError::Deploy(anyhow::Error::msg(format!(
+        "canary {} is no longer the latest revision; run `propel abort-canary`, \
+         then deploy again",
+        canary.revision
+    ))))
+}
+
+/// The revision an abort sends all traffic to.
+fn abort_target(canary: &CanaryState) -> Result<&str, CliError> {
+    match &canary.stable_revision {
+        Some(revision) => Ok(revision),
+        None => Err(CliError::Deploy(anyhow::Error::msg(format!(
+            "canary {} already serves all traffic; use `propel rollback` to return \
+             to an earlier revision",
+            canary.revision
+        )))),
+    }
+}
+
+/// The `[cloud_run].canary` this deploy releases the new revision under:
+/// the setting, if the service already exists.
+///
+/// Fails while a canary is in progress, whatever the config says: a
+/// second deploy would leave the first canary's traffic split behind.
+pub(crate) async fn canary_applies(
+    client: &GcloudClient,
+    ctx: &ProjectContext,
+    project_id: &str,
+    mut note: impl FnMut(String),
+) -> Result<Option<CanaryConfig>, CliError> {
+    match client
+        .canary_state(ctx.service_name(), project_id, ctx.region())
+        .await
+    {
+        Ok(Some(canary)) => Err(CliError::Preflight(anyhow::Error::msg(format!(
+            "a canary of {} is in progress ({} receives {}% of traffic); \
+             run `propel promote` or `propel abort-canary` before deploying again",
+            ctx.service_name(),
+            canary.revision,
+            canary.percent
+        )))),
+        Ok(None) => Ok(ctx.config.cloud_run.canary),
+        Err(e) if e.is_service_not_found() => {
+            if ctx.config.cloud_run.canary.is_some() {
+                note(format!(
+                    "First deploy of {} — no previous revision to keep serving, \
+                     sending it all traffic instead of a canary share",
+                    ctx.service_name()
+                ));
+            }
+            Ok(None)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Warm up the canary revision at `tag_url` if `[cloud_run].warmup_requests`
+/// is set, then give it `canary.percent` of the traffic. Returns the
+/// service URL.
+pub(crate) async fn release_canary(
+    client: &GcloudClient,
+    ctx: &ProjectContext,
+    project_id: &str,
+    canary: CanaryConfig,
+    tag_url: &str,
+    mut note: impl FnMut(String),
+) -> Result<String, CliError> {
+    if ctx.config.cloud_run.warmup_requests > 0 {
+        super::warmup::warm_up_revision(client, ctx, tag_url, &mut note).await?;
+    }
+    note(format!(
+        "Sending {}% of traffic to the canary...",
+        canary.percent
+    ));
+    client
+        .split_canary_traffic(ctx.service_name(), project_id, ctx.region(), canary.percent)
+        .await?;
+    note(format!(
+        "Canary: {tag_url} ({}% of traffic)\n\
+         Run `propel promote` to send it all traffic, or `propel abort-canary` to revert",
+        canary.percent
+    ));
+    Ok(client
+        .service_url(ctx.service_name(), project_id, ctx.region())
+        .await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canary(stable_revision: Option<&str>, latest: bool) -> CanaryState {
+        CanaryState {
+            revision: "svc-00002".to_owned(),
+            percent: 10,
+            url: Some("https://canary---svc-abc.a.run.app".to_owned()),
+            stable_revision: stable_revision.map(str::to_owned),
+            latest,
+        }
+    }
+
+    #[test]
+    fn abort_returns_to_the_stable_revision() {
+        assert_eq!(
+            abort_target(&canary(Some("svc-00001"), true)).unwrap(),
+            "svc-00001"
+        );
+    }
+
+    #[test]
+    fn abort_without_a_stable_revision_points_to_rollback() {
+        let err = abort_target(&canary(None, true)).unwrap_err();
+
+        assert_eq!(err.exit_code(), 5);
+        assert!(err.to_string().contains("propel rollback"), "{err}");
+    }
+
+    #[test]
+    fn promote_requires_the_canary_to_be_latest() {
+        assert!(check_promotable(&canary(Some("svc-00001"), true)).is_ok());
+
+        let err = check_promotable(&canary(Some("svc-00001"), false)).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("svc-00002 is no longer the latest revision"),
+            "{err}"
+        );
+    }
+}
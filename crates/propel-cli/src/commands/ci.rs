@@ -1,3 +1,5 @@
+use super::CliError;
+use super::ProjectContext;
 use propel_cloud::GcloudClient;
 use propel_core::PropelConfig;
 use std::path::{Path, PathBuf};
@@ -8,7 +10,8 @@ use std::process::Stdio;
 /// - `run.admin` is required (not `run.developer`) because `--allow-unauthenticated`
 ///   needs `run.services.setIamPolicy`.
 /// - Secret access (secretAccessor) is granted per-secret at `propel secret set`
-///   time, so CI only needs viewer to list secret names for --update-secrets.
+///   time, so CI only needs viewer to list secret names for --update-secrets
+///   (dropped by [`ci_sa_roles`] with `[cloud_run].inject_secrets = false`).
 const CI_SA_ROLES: &[&str] = &[
     "roles/artifactregistry.writer",
     "roles/cloudbuild.builds.editor",
@@ -20,9 +23,41 @@ const CI_SA_ROLES: &[&str] = &[
     "roles/viewer",
 ];
 
-pub(super) const WIF_POOL_ID: &str = "propel-github";
-const WIF_PROVIDER_ID: &str = "github";
-pub(super) const CI_SA_ID: &str = "propel-deploy";
+/// IAM roles for the CI service account when `[ci].deploy_via_cloud_build`
+/// is set.
+///
+/// CI only submits the build; pushing the image and deploying to Cloud Run
+/// happen inside Cloud Build as [`BUILD_SA_ID`], so `run.admin`,
+/// `artifactregistry.writer`, and project-wide `iam.serviceAccountUser` are
+/// dropped. CI gets `serviceAccountUser` on the build service account only.
+const CI_SA_ROLES_VIA_CLOUD_BUILD: &[&str] = &[
+    "roles/cloudbuild.builds.editor",
+    "roles/secretmanager.viewer",
+    "roles/serviceusage.serviceUsageViewer",
+    "roles/storage.objectAdmin",
+    "roles/viewer",
+];
+
+/// IAM roles for the build service account that deploys from inside
+/// Cloud Build (`[ci].deploy_via_cloud_build`).
+///
+/// - `iam.serviceAccountUser` lets it deploy Cloud Run revisions running as
+///   the runtime service account (`[cloud_run].service_account`, else the
+///   default compute one).
+/// - `logging.logWriter` is required for builds under a user-specified
+///   service account with `CLOUD_LOGGING_ONLY`.
+const BUILD_SA_ROLES: &[&str] = &[
+    "roles/artifactregistry.writer",
+    "roles/iam.serviceAccountUser",
+    "roles/logging.logWriter",
+    "roles/run.admin",
+    "roles/storage.objectViewer",
+];
+
+/// Lets CI list secret names for `--update-secrets`.
+const SECRET_VIEWER_ROLE: &str = "roles/secretmanager.viewer";
+
+pub(super) const BUILD_SA_ID: &str = "propel-build";
 pub(super) const WORKFLOW_PATH: &str = ".github/workflows/propel-deploy.yml";
 
 /// GitHub Actions secret names managed by `ci init` / `destroy --include-ci`.
@@ -30,16 +65,17 @@ pub(super) const GH_SECRET_NAMES: &[&str] =
     &["GCP_PROJECT_ID", "WIF_PROVIDER", "WIF_SERVICE_ACCOUNT"];
 
 /// Set up GitHub Actions CI/CD pipeline.
-pub async fn ci_init() -> anyhow::Result<()> {
+pub async fn ci_init() -> Result<(), CliError> {
     let project_dir = PathBuf::from(".");
     let client = GcloudClient::new();
 
     // ── Guard: workflow already exists ──
     let workflow_path = Path::new(WORKFLOW_PATH);
     if workflow_path.exists() {
-        anyhow::bail!(
+        return Err(anyhow::anyhow!(
             "Workflow already exists at {WORKFLOW_PATH} — edit it directly, or delete it to re-run ci init"
-        );
+        )
+        .into());
     }
 
     // ── Prerequisites ──
@@ -68,12 +104,16 @@ pub async fn ci_init() -> anyhow::Result<()> {
     println!("  Repository: {github_repo}");
 
     // propel.toml + gcp_project_id
-    let config = PropelConfig::load(&project_dir)?;
-    let gcp_project_id = super::require_gcp_project_id(&config)?;
+    let ctx = ProjectContext::load(&project_dir)?;
+    let config = &ctx.config;
+    let gcp_project_id = ctx.gcp_project_id()?;
     println!("  GCP Project: {gcp_project_id}");
+    let resources = config.ci.resources(ctx.service_name());
+    let pool_id = resources.wif_pool_id.as_str();
+    let provider_id = resources.wif_provider_id.as_str();
 
     // Required GCP APIs check
-    check_required_apis(&client, gcp_project_id).await?;
+    check_required_apis(&client, config, gcp_project_id).await?;
     println!("  Required APIs: OK");
 
     println!();
@@ -82,20 +122,20 @@ pub async fn ci_init() -> anyhow::Result<()> {
 
     println!("Setting up Workload Identity Federation...");
 
-    let created = client.ensure_wif_pool(gcp_project_id, WIF_POOL_ID).await?;
+    let created = client.ensure_wif_pool(gcp_project_id, pool_id).await?;
     if created {
-        println!("  Created Identity Pool: {WIF_POOL_ID}");
+        println!("  Created Identity Pool: {pool_id}");
     } else {
-        println!("  Identity Pool already exists: {WIF_POOL_ID}");
+        println!("  Identity Pool already exists: {pool_id}");
     }
 
     let created = client
-        .ensure_oidc_provider(gcp_project_id, WIF_POOL_ID, WIF_PROVIDER_ID, &github_repo)
+        .ensure_oidc_provider(gcp_project_id, pool_id, provider_id, &github_repo)
         .await?;
     if created {
-        println!("  Created OIDC Provider: {WIF_PROVIDER_ID}");
+        println!("  Created OIDC Provider: {provider_id}");
     } else {
-        println!("  OIDC Provider already exists: {WIF_PROVIDER_ID}");
+        println!("  OIDC Provider already exists: {provider_id}");
     }
 
     println!();
@@ -104,10 +144,14 @@ pub async fn ci_init() -> anyhow::Result<()> {
 
     println!("Setting up Service Account...");
 
-    let sa_email = format!("{CI_SA_ID}@{gcp_project_id}.iam.gserviceaccount.com");
+    let sa_email = resources.service_account_email(gcp_project_id);
 
     let created = client
-        .ensure_service_account(gcp_project_id, CI_SA_ID, "Propel CI Deploy")
+        .ensure_service_account(
+            gcp_project_id,
+            &resources.service_account_id,
+            "Propel CI Deploy",
+        )
         .await?;
     if created {
         println!("  Created SA: {sa_email}");
@@ -115,21 +159,45 @@ pub async fn ci_init() -> anyhow::Result<()> {
         println!("  SA already exists: {sa_email}");
     }
 
+    let via_cloud_build = config.ci.deploy_via_cloud_build;
+    let ci_roles = ci_sa_roles(via_cloud_build, config.cloud_run.inject_secrets);
     println!("  Binding IAM roles...");
     client
-        .bind_iam_roles(gcp_project_id, &sa_email, CI_SA_ROLES)
+        .bind_iam_roles(gcp_project_id, &sa_email, &ci_roles)
         .await?;
-    for role in CI_SA_ROLES {
+    for role in &ci_roles {
         println!("    {role}");
     }
 
+    if via_cloud_build {
+        let build_sa = super::build_service_account(gcp_project_id);
+        let created = client
+            .ensure_service_account(gcp_project_id, BUILD_SA_ID, "Propel Cloud Build Deploy")
+            .await?;
+        if created {
+            println!("  Created build SA: {build_sa}");
+        } else {
+            println!("  Build SA already exists: {build_sa}");
+        }
+        client
+            .bind_iam_roles(gcp_project_id, &build_sa, BUILD_SA_ROLES)
+            .await?;
+        for role in BUILD_SA_ROLES {
+            println!("    {role} (build SA)");
+        }
+        client
+            .grant_service_account_user(gcp_project_id, &build_sa, &sa_email)
+            .await?;
+        println!("  Allowed CI SA to run builds as {build_sa}");
+    }
+
     // WIF → SA binding
     let project_number = client.get_project_number(gcp_project_id).await?;
     client
         .bind_wif_to_sa(
             gcp_project_id,
             &project_number,
-            WIF_POOL_ID,
+            pool_id,
             &sa_email,
             &github_repo,
         )
@@ -143,7 +211,7 @@ pub async fn ci_init() -> anyhow::Result<()> {
     println!("Configuring GitHub Secrets...");
 
     let wif_provider = format!(
-        "projects/{project_number}/locations/global/workloadIdentityPools/{WIF_POOL_ID}/providers/{WIF_PROVIDER_ID}"
+        "projects/{project_number}/locations/global/workloadIdentityPools/{pool_id}/providers/{provider_id}"
     );
 
     set_gh_secret("GCP_PROJECT_ID", gcp_project_id).await?;
@@ -157,12 +225,21 @@ pub async fn ci_init() -> anyhow::Result<()> {
 
     println!();
 
+    // ── Record resource ids ──
+
+    // destroy --include-ci and ci status read these back, so they keep
+    // targeting the same resources even if the service is renamed.
+    if !config.ci.resources_pinned() {
+        resources.save(&project_dir)?;
+        println!("Saved CI resource ids to propel.toml [ci]");
+    }
+
     // ── Generate workflow yaml ──
 
     if let Some(parent) = workflow_path.parent() {
         std::fs::create_dir_all(parent)?;
     }
-    std::fs::write(workflow_path, generate_workflow_yaml())?;
+    std::fs::write(workflow_path, generate_workflow_yaml(via_cloud_build))?;
     println!("Generated: {WORKFLOW_PATH}");
 
     println!();
@@ -171,8 +248,75 @@ pub async fn ci_init() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Show the CI resources this project's `[ci]` section resolves to.
+pub async fn ci_status() -> Result<(), CliError> {
+    let project_dir = PathBuf::from(".");
+    let ctx = ProjectContext::load(&project_dir)?;
+    let config = &ctx.config;
+    let gcp_project_id = ctx.gcp_project_id()?;
+    let resources = config.ci.resources(ctx.service_name());
+
+    let source = |pinned: bool| if pinned { "propel.toml" } else { "default" };
+    println!("CI resources for {gcp_project_id}:");
+    println!(
+        "  WIF pool:        {} ({})",
+        resources.wif_pool_id,
+        source(config.ci.wif_pool_id.is_some())
+    );
+    println!(
+        "  OIDC provider:   {} ({})",
+        resources.wif_provider_id,
+        source(config.ci.wif_provider_id.is_some())
+    );
+    println!(
+        "  Service account: {} ({})",
+        resources.service_account_email(gcp_project_id),
+        source(config.ci.service_account_id.is_some())
+    );
+    if config.ci.deploy_via_cloud_build {
+        println!(
+            "  Build SA:        {}",
+            super::build_service_account(gcp_project_id)
+        );
+    }
+
+    let workflow = if Path::new(WORKFLOW_PATH).exists() {
+        "present"
+    } else {
+        "missing — run `propel ci init`"
+    };
+    println!("  Workflow:        {WORKFLOW_PATH} ({workflow})");
+
+    if !config.ci.resources_pinned() && Path::new(WORKFLOW_PATH).exists() {
+        println!();
+        println!(
+            "Note: ids are not pinned in propel.toml [ci]. CI set up before they were \
+             configurable used pool 'propel-github' and SA 'propel-deploy'; set \
+             wif_pool_id / service_account_id to those to keep managing them."
+        );
+    }
+
+    Ok(())
+}
+
+/// IAM roles for the CI service account in the configured deploy mode.
+/// Without secret injection, CI never lists secrets, so the viewer role
+/// is dropped.
+fn ci_sa_roles(deploy_via_cloud_build: bool, inject_secrets: bool) -> Vec<&'static str> {
+    let roles = if deploy_via_cloud_build {
+        CI_SA_ROLES_VIA_CLOUD_BUILD
+    } else {
+        CI_SA_ROLES
+    };
+    roles
+        .iter()
+        .copied()
+        .filter(|role| inject_secrets || *role != SECRET_VIEWER_ROLE)
+        .collect()
+}
+
 /// Detect the GitHub owner/repo from the git remote origin URL.
-async fn detect_github_repo() -> anyhow::Result<String> {
+pub(super) async fn detect_github_repo() -> anyhow::Result<String> {
     let output = tokio::process::Command::new("git")
         .args(["remote", "get-url", "origin"])
         .stdout(Stdio::piped())
@@ -213,9 +357,11 @@ fn parse_github_repo(url: &str) -> Option<String> {
     None
 }
 
-/// Check that the required GCP APIs (Cloud Build, Cloud Run, Secret Manager) are enabled.
+/// Check that the GCP APIs deploy uses (Cloud Build, Cloud Run, Artifact Registry,
+/// Secret Manager unless this config never calls it) are enabled.
 async fn check_required_apis<E: propel_cloud::GcloudExecutor>(
     client: &GcloudClient<E>,
+    config: &PropelConfig,
     project_id: &str,
 ) -> anyhow::Result<()> {
     let output = client
@@ -223,10 +369,11 @@ async fn check_required_apis<E: propel_cloud::GcloudExecutor>(
         .await
         .map_err(|e| anyhow::anyhow!("{e}"))?;
 
-    if output.has_warnings() {
+    let disabled = super::preflight::DisabledApis::classify(config, output.disabled_apis, true);
+    if !disabled.required.is_empty() {
         anyhow::bail!(
             "Required APIs not enabled: {}",
-            output.disabled_apis.join(", ")
+            disabled.required.join(", ")
         );
     }
 
@@ -282,8 +429,19 @@ pub(super) async fn delete_gh_secret(name: &str) -> anyhow::Result<()> {
 }
 
 /// Generate the GitHub Actions workflow yaml content.
-fn generate_workflow_yaml() -> String {
-    r#"# Generated by: propel ci init
+///
+/// In `deploy_via_cloud_build` mode the deploy command is unchanged — the
+/// mode lives in propel.toml — but the step is labeled so readers of the
+/// workflow know CI never touches Cloud Run directly.
+fn generate_workflow_yaml(deploy_via_cloud_build: bool) -> String {
+    let deploy_step = if deploy_via_cloud_build {
+        "      # [ci].deploy_via_cloud_build: Cloud Build pushes the image and runs\n      \
+         # `gcloud run deploy` as the propel-build service account.\n      \
+         - name: Build and deploy via Cloud Build\n"
+    } else {
+        "      - name: Deploy\n"
+    };
+    let workflow = r#"# Generated by: propel ci init
 name: Deploy
 
 on:
@@ -325,10 +483,9 @@ jobs:
             cargo install propel-cli
           fi
 
-      - name: Deploy
-        run: propel deploy --allow-dirty
-"#
-    .to_owned()
+{deploy_step}        run: propel deploy --allow-dirty
+"#;
+    workflow.replace("{deploy_step}", deploy_step)
 }
 
 #[cfg(test)]
@@ -387,12 +544,57 @@ mod tests {
 
     #[test]
     fn workflow_yaml_contains_required_sections() {
-        let yaml = generate_workflow_yaml();
+        let yaml = generate_workflow_yaml(false);
         assert!(yaml.contains("workload_identity_provider"));
         assert!(yaml.contains("service_account"));
         assert!(yaml.contains("propel deploy --allow-dirty"));
         assert!(yaml.contains("id-token: write"));
         assert!(yaml.contains("cargo install propel-cli"));
+        assert!(yaml.contains("      - name: Deploy\n        run: propel deploy"));
+    }
+
+    #[test]
+    fn workflow_yaml_via_cloud_build_labels_deploy_step() {
+        let yaml = generate_workflow_yaml(true);
+        assert!(yaml.contains(
+            "      - name: Build and deploy via Cloud Build\n        run: propel deploy --allow-dirty"
+        ));
+        assert!(yaml.contains("propel-build service account"));
+        assert!(!yaml.contains("{deploy_step}"));
+    }
+
+    #[test]
+    fn ci_roles_default_include_run_admin() {
+        assert!(ci_sa_roles(false, true).contains(&"roles/run.admin"));
+    }
+
+    #[test]
+    fn ci_roles_list_secrets_only_when_injecting_them() {
+        for via_cloud_build in [false, true] {
+            assert!(ci_sa_roles(via_cloud_build, true).contains(&SECRET_VIEWER_ROLE));
+            let roles = ci_sa_roles(via_cloud_build, false);
+            assert!(!roles.contains(&SECRET_VIEWER_ROLE), "{roles:?}");
+            assert_eq!(
+                roles.len(),
+                ci_sa_roles(via_cloud_build, true).len() - 1,
+                "{roles:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn ci_roles_via_cloud_build_drop_deploy_permissions() {
+        let roles = ci_sa_roles(true, true);
+        assert!(roles.contains(&"roles/cloudbuild.builds.editor"));
+        assert!(!roles.contains(&"roles/run.admin"));
+        assert!(!roles.contains(&"roles/iam.serviceAccountUser"));
+        assert!(!roles.contains(&"roles/artifactregistry.writer"));
+    }
+
+    #[test]
+    fn build_sa_roles_cover_push_and_deploy() {
+        assert!(BUILD_SA_ROLES.contains(&"roles/run.admin"));
+        assert!(BUILD_SA_ROLES.contains(&"roles/artifactregistry.writer"));
     }
 
     mod proptests {
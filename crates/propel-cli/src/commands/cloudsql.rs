@@ -0,0 +1,205 @@
+//! Checks that a service using `[cloud_run].cloudsql_instances` can reach
+//! its instances: the Cloud SQL Admin API and the runtime service
+//! account's client role.
+
+use super::secret;
+use propel_cloud::{
+    CLOUD_SQL_ADMIN_API, CLOUD_SQL_CLIENT_ROLE, CheckResult, DoctorReport, GcloudClient,
+    GcloudExecutor,
+};
+use propel_core::PropelConfig;
+
+/// Doctor row checking that the Cloud SQL Admin API is enabled, or `None`
+/// when `[cloud_run].cloudsql_instances` is empty.
+pub(crate) async fn cloud_sql_doctor_check<E: GcloudExecutor>(
+    client: &GcloudClient<E>,
+    report: &DoctorReport,
+    project_id: Option<&str>,
+    config: &PropelConfig,
+) -> Option<CheckResult> {
+    if config.cloud_run.cloudsql_instances.is_empty() {
+        return None;
+    }
+    let Some(project_id) = project_id.filter(|_| report.project.passed) else {
+        return Some(CheckResult::skipped("project not accessible"));
+    };
+    Some(
+        match client.api_enabled(project_id, CLOUD_SQL_ADMIN_API).await {
+            Ok(true) => CheckResult::ok("Enabled"),
+            Ok(false) => CheckResult::fail(&format!(
+                "disabled; [cloud_run].cloudsql_instances needs it: \
+                 gcloud services enable {CLOUD_SQL_ADMIN_API} --project {project_id}"
+            )),
+            Err(e) => CheckResult::fail(&format!("{e}")),
+        },
+    )
+}
+
+/// Warnings for `[cloud_run].cloudsql_instances` that would keep the
+/// service from connecting: a disabled Cloud SQL Admin API, or a runtime
+/// service account without [`CLOUD_SQL_CLIENT_ROLE`]. Neither stops the
+/// deploy.
+///
+/// The role is not granted here: that takes
+/// `resourcemanager.projects.setIamPolicy`, which the deploying account
+/// should not need.
+pub(crate) async fn cloud_sql_warnings<E: GcloudExecutor>(
+    client: &GcloudClient<E>,
+    config: &PropelConfig,
+    project_id: &str,
+) -> Vec<String> {
+    let instances = &config.cloud_run.cloudsql_instances;
+    if instances.is_empty() {
+        return Vec::new();
+    }
+    let mut warnings = Vec::new();
+    match client.api_enabled(project_id, CLOUD_SQL_ADMIN_API).await {
+        Ok(true) => {}
+        Ok(false) => warnings.push(format!(
+            "{CLOUD_SQL_ADMIN_API} is disabled, so the service cannot connect to \
+             [cloud_run].cloudsql_instances; enable it with \
+             `gcloud services enable {CLOUD_SQL_ADMIN_API} --project {project_id}`"
+        )),
+        // arch-lint: allow(no-error-swallowing) reason="the check is advisory; the deploy itself reports a disabled API"
+        Err(e) => warnings.push(format!(
+            "could not check {CLOUD_SQL_ADMIN_API}: {:#}",
+            anyhow::Error::new(e)
+        )),
+    }
+
+    let account = match secret::runtime_service_account(client, config, project_id).await {
+        Ok(account) => account,
+        // arch-lint: allow(no-error-swallowing) reason="the check is advisory"
+        Err(e) => {
+            warnings.push(format!(
+                "could not check the runtime service account's Cloud SQL access: {e:#}"
+            ));
+            return warnings;
+        }
+    };
+    match client.project_iam_policy(project_id).await {
+        Ok(policy) if policy.grants_cloud_sql_connect(&account) => {}
+        Ok(_) => warnings.push(format!(
+            "{account} lacks {CLOUD_SQL_CLIENT_ROLE}, so the service cannot connect to {}; \
+             grant it with `gcloud projects add-iam-policy-binding {project_id} \
+             --member=serviceAccount:{account} --role={CLOUD_SQL_CLIENT_ROLE}`",
+            instances.join(", ")
+        )),
+        // arch-lint: allow(no-error-swallowing) reason="reading the policy needs resourcemanager.projects.getIamPolicy; the check is advisory"
+        Err(e) => warnings.push(format!(
+            "could not check that {account} has {CLOUD_SQL_CLIENT_ROLE}: {:#}",
+            anyhow::Error::new(e)
+        )),
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::scripted_gcloud::ScriptedGcloud;
+
+    /// gcloud answering `services list` with `sqladmin` when enabled, and
+    /// `projects get-iam-policy` with `policy` (`None`: denied).
+    fn cloud_sql_gcloud(sqladmin_enabled: bool, policy: Option<&'static str>) -> ScriptedGcloud {
+        let enabled = if sqladmin_enabled {
+            "sqladmin.googleapis.com\n"
+        } else {
+            ""
+        };
+        ScriptedGcloud::new(vec![
+            ("services list", Ok(enabled)),
+            ("get-iam-policy", policy.ok_or("PERMISSION_DENIED")),
+        ])
+    }
+
+    const API_SA_IS_CLIENT: &str = r#"{"bindings": [{"role": "roles/cloudsql.client",
+        "members": ["serviceAccount:api@proj.iam.gserviceaccount.com"]}]}"#;
+
+    fn cloud_sql_config() -> PropelConfig {
+        let mut config = PropelConfig::default();
+        config.cloud_run.cloudsql_instances = vec!["proj:us-central1:main".to_owned()];
+        config.cloud_run.service_account = Some("api@proj.iam.gserviceaccount.com".to_owned());
+        config
+    }
+
+    #[tokio::test]
+    async fn cloud_sql_doctor_check_needs_the_admin_api() {
+        let report = DoctorReport {
+            project: CheckResult::ok("proj"),
+            ..DoctorReport::default()
+        };
+        let check = |sqladmin_enabled, config: PropelConfig, project: Option<&'static str>| {
+            let report = &report;
+            async move {
+                let client = GcloudClient::with_executor(cloud_sql_gcloud(sqladmin_enabled, None));
+                cloud_sql_doctor_check(&client, report, project, &config).await
+            }
+        };
+
+        assert!(
+            check(true, PropelConfig::default(), Some("proj"))
+                .await
+                .is_none()
+        );
+        assert!(
+            check(true, cloud_sql_config(), Some("proj"))
+                .await
+                .unwrap()
+                .passed
+        );
+        let disabled = check(false, cloud_sql_config(), Some("proj"))
+            .await
+            .unwrap();
+        assert!(!disabled.passed && !disabled.skipped);
+        assert!(
+            disabled
+                .detail
+                .contains("gcloud services enable sqladmin.googleapis.com --project proj"),
+            "{}",
+            disabled.detail
+        );
+        assert!(
+            check(false, cloud_sql_config(), None)
+                .await
+                .unwrap()
+                .skipped
+        );
+    }
+
+    #[tokio::test]
+    async fn cloud_sql_warnings_cover_the_api_and_the_client_role() {
+        let warnings = |sqladmin_enabled, policy| async move {
+            let client = GcloudClient::with_executor(cloud_sql_gcloud(sqladmin_enabled, policy));
+            cloud_sql_warnings(&client, &cloud_sql_config(), "proj").await
+        };
+
+        assert!(warnings(true, Some(API_SA_IS_CLIENT)).await.is_empty());
+
+        let missing = warnings(true, Some(r#"{"bindings": []}"#)).await;
+        assert_eq!(missing.len(), 1, "{missing:?}");
+        assert!(
+            missing[0].contains(
+                "--member=serviceAccount:api@proj.iam.gserviceaccount.com \
+                 --role=roles/cloudsql.client"
+            ),
+            "{}",
+            missing[0]
+        );
+
+        let unchecked = warnings(false, None).await;
+        assert_eq!(unchecked.len(), 2, "{unchecked:?}");
+        assert!(unchecked[0].contains("sqladmin.googleapis.com is disabled"));
+        assert!(unchecked[1].starts_with("could not check that api@proj"));
+    }
+
+    #[tokio::test]
+    async fn cloud_sql_warnings_skip_services_without_instances() {
+        let client = GcloudClient::with_executor(cloud_sql_gcloud(false, None));
+        assert!(
+            cloud_sql_warnings(&client, &PropelConfig::default(), "proj")
+                .await
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,23 @@
+use super::CliError;
+use propel_core::PropelConfig;
+use std::path::PathBuf;
+
+pub async fn config_validate() -> Result<(), CliError> {
+    let project_dir = PathBuf::from(".");
+    if !project_dir.join("propel.toml").exists() {
+        return Err(CliError::config(
+            "propel.toml not found — run `propel init` to create one",
+        ));
+    }
+    let config = PropelConfig::load(&project_dir)?;
+    let warnings = super::config_warnings(&config, &project_dir);
+
+    for warning in &warnings {
+        println!("Warning: {warning}");
+    }
+    match warnings.len() {
+        0 => println!("propel.toml is valid"),
+        n => println!("propel.toml is valid ({n} warning(s))"),
+    }
+    Ok(())
+}
@@ -0,0 +1,682 @@
+use super::CliError;
+use propel_build::bundle::{self, BundleError};
+use propel_cloud::{GcloudClient, ManagedService};
+use propel_core::{CargoProject, DeployHistory, PropelConfig, TagStrategy};
+use std::path::{Path, PathBuf};
+
+/// Artifact Registry repository name used for container images.
+pub(crate) const ARTIFACT_REPO_NAME: &str = "propel";
+
+/// `propel.toml` and the Cargo package of one project directory, with the
+/// names every GCP-facing command derives from them.
+///
+/// `config` stays public so deploy can rewrite it (`--fix-config`, env
+/// templates) before anything is derived from it; call
+/// [`reload_config`](Self::reload_config) after changing `[project].name`.
+pub(crate) struct ProjectContext {
+    pub(crate) dir: PathBuf,
+    pub(crate) config: PropelConfig,
+    pub(crate) project: CargoProject,
+    service_name: String,
+}
+
+impl ProjectContext {
+    /// Load `propel.toml` and discover the Cargo package in `project_dir`.
+    ///
+    /// Every command that targets the deployed service (deploy, update,
+    /// status, logs, destroy, and the MCP tools) loads its context here, so
+    /// they all resolve the same service name.
+    pub(crate) fn load(project_dir: &Path) -> anyhow::Result<Self> {
+        let config = PropelConfig::load(project_dir)?;
+        let project = CargoProject::discover(project_dir)?;
+        Ok(Self::new(project_dir, config, project))
+    }
+
+    pub(crate) fn new(project_dir: &Path, config: PropelConfig, project: CargoProject) -> Self {
+        let service_name = resolve_service_name(&config, &project);
+        Self {
+            dir: project_dir.to_owned(),
+            config,
+            project,
+            service_name,
+        }
+    }
+
+    /// Re-read `propel.toml`, e.g. after `--fix-config` edited it.
+    pub(crate) fn reload_config(&mut self) -> anyhow::Result<()> {
+        self.config = PropelConfig::load(&self.dir)?;
+        self.service_name = resolve_service_name(&self.config, &self.project);
+        Ok(())
+    }
+
+    /// `[project].gcp_project_id`, required by every command that talks to GCP.
+    pub(crate) fn gcp_project_id(&self) -> Result<&str, CliError> {
+        require_gcp_project_id(&self.config)
+    }
+
+    /// Cloud Run service name: `[project].name`, or the Cargo package name
+    /// normalized for Cloud Run (`My.Service` → `my-service`).
+    pub(crate) fn service_name(&self) -> &str {
+        &self.service_name
+    }
+
+    pub(crate) fn region(&self) -> &str {
+        &self.config.project.region
+    }
+
+    /// The local deploy history of the project directory.
+    pub(crate) fn history(&self) -> DeployHistory {
+        DeployHistory::new(&self.dir)
+    }
+
+    /// Artifact Registry location of the image repository
+    /// ([`PropelConfig::artifact_location`]), which may differ from
+    /// [`region`](Self::region).
+    pub(crate) fn artifact_location(&self) -> &str {
+        self.config.artifact_location()
+    }
+
+    /// Artifact Registry image path, without a tag. The image name is the
+    /// service name, so it is lowercase and free of `.` and `_` too.
+    pub(crate) fn image_path(&self) -> anyhow::Result<String> {
+        Ok(format!(
+            "{location}-docker.pkg.dev/{project_id}/{ARTIFACT_REPO_NAME}/{service}",
+            location = self.artifact_location(),
+            project_id = self.gcp_project_id()?,
+            service = self.service_name(),
+        ))
+    }
+
+    /// The image deploy builds, pushes, and deploys, tagged by
+    /// `[build].tag_strategy`: the short commit SHA, with `-dirty` when
+    /// the tree has uncommitted changes, unless configured otherwise.
+    ///
+    /// Outside git there is no commit, so the SHA strategy falls back to
+    /// the package version, as `tag_strategy = "version"` would tag it.
+    pub(crate) fn image_tag(&self) -> anyhow::Result<String> {
+        let mut strategy = self.config.build.tag_strategy;
+        let mut commit = String::new();
+        if strategy.uses_commit() {
+            match bundle::short_head_commit(&self.dir) {
+                Ok(sha) => {
+                    commit = sha;
+                    if bundle::is_dirty(&self.dir)? {
+                        commit.push_str("-dirty");
+                    }
+                }
+                Err(BundleError::NotARepo { .. }) => {
+                    tracing::info!(
+                        version = %self.project.version,
+                        "not a git repository; tagging the image with the package version"
+                    );
+                    strategy = TagStrategy::Version;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        self.image_ref(&strategy.tag(&commit, &self.project.version))
+    }
+
+    /// [`image_path`](Self::image_path) with `tag`.
+    pub(crate) fn image_ref(&self, tag: &str) -> anyhow::Result<String> {
+        Ok(format!("{}:{tag}", self.image_path()?))
+    }
+}
+
+/// `[project].name` as written, or the package name through
+/// [`propel_core::derive_service_name`], logging the mapping when it
+/// changes the name.
+fn resolve_service_name(config: &PropelConfig, project: &CargoProject) -> String {
+    if let Some(name) = &config.project.name {
+        return name.clone();
+    }
+    match propel_core::derive_service_name(&project.name) {
+        Some(derived) if derived == project.name => derived,
+        Some(derived) => {
+            tracing::info!(
+                package = %project.name,
+                service = %derived,
+                "normalized package name for the Cloud Run service and image name; \
+                 set [project].name to choose another",
+            );
+            derived
+        }
+        // No letter to start a service name with; let gcloud report it.
+        None => project.name.clone(),
+    }
+}
+
+/// Extract `gcp_project_id` from config, returning a clear error if not set.
+///
+/// Only commands that talk to GCP call this; offline commands (`new`, `init`,
+/// `eject`) never construct a `GcloudClient`. Commands that only need
+/// `propel.toml` (`secret`) call it directly instead of loading a
+/// [`ProjectContext`].
+pub(crate) fn require_gcp_project_id(config: &PropelConfig) -> Result<&str, CliError> {
+    config.project.gcp_project_id.as_deref().ok_or_else(|| {
+        CliError::config(
+            "gcp_project_id not set in propel.toml — this command needs GCP access; \
+             set [project].gcp_project_id",
+        )
+    })
+}
+
+/// One-line summary of where a command is about to act.
+///
+/// ```text
+/// Project my-prod-proj (My Prod) · account alice@corp.com · region us-central1 · service my-api
+/// ```
+fn context_banner(
+    project_id: &str,
+    project_name: Option<&str>,
+    account: Option<&str>,
+    region: &str,
+    service: &str,
+) -> String {
+    let project = match project_name {
+        Some(name) if !name.is_empty() && name != project_id => format!("{project_id} ({name})"),
+        _ => project_id.to_owned(),
+    };
+    // arch-lint: allow(no-silent-result-drop) reason="Option: None = no active account, shown as such"
+    let account = account.unwrap_or("(none)");
+    format!("Project {project} · account {account} · region {region} · service {service}")
+}
+
+/// Fail unless the active account satisfies `[project].expected_account_suffix`.
+pub(crate) fn check_expected_account(
+    config: &PropelConfig,
+    account: Option<&str>,
+) -> anyhow::Result<()> {
+    let Some(suffix) = &config.project.expected_account_suffix else {
+        return Ok(());
+    };
+    match account {
+        Some(account) if config.project.account_matches(account) => Ok(()),
+        Some(account) => anyhow::bail!(
+            "active gcloud account {account} does not end with {suffix:?} \
+             ([project].expected_account_suffix)\n\
+             Switch accounts with: gcloud config set account <you>{suffix}"
+        ),
+        None => anyhow::bail!(
+            "no active gcloud account, but [project].expected_account_suffix = {suffix:?}\n\
+             Log in with: gcloud auth login"
+        ),
+    }
+}
+
+/// Print the [`context_banner`] and return the active account, for
+/// [`check_expected_account`] before destructive commands.
+///
+/// `project_name` comes from preflight when it already ran; otherwise it is
+/// looked up, and left out of the banner if that fails.
+pub(crate) async fn announce_target(
+    client: &GcloudClient,
+    config: &PropelConfig,
+    project_id: &str,
+    project_name: Option<&str>,
+    service: &str,
+) -> anyhow::Result<Option<String>> {
+    let account = client.active_account().await?;
+
+    let looked_up;
+    let project_name = match project_name {
+        Some(name) => Some(name),
+        None => match client.project_name(project_id).await {
+            Ok(name) => {
+                looked_up = name;
+                Some(looked_up.as_str())
+            }
+            Err(e) => {
+                tracing::debug!(error = %e, "could not look up project name for banner");
+                None
+            }
+        },
+    };
+
+    println!(
+        "{}",
+        context_banner(
+            project_id,
+            project_name,
+            account.as_deref(),
+            &config.project.region,
+            service
+        )
+    );
+    Ok(account)
+}
+
+/// Explain a service missing from the configured `region` that propel
+/// deployed under the same name elsewhere, e.g. before `[project].region`
+/// was changed. `None` when `found` has nothing outside `region`.
+fn cross_region_hint(service_name: &str, region: &str, found: &[ManagedService]) -> Option<String> {
+    let others: Vec<&str> = found
+        .iter()
+        .filter(|s| s.name == service_name)
+        .filter_map(|s| s.region.as_deref())
+        .filter(|r| *r != region)
+        .collect();
+    let first = others.first()?;
+    let mut hint = format!(
+        "found {service_name} in {} — your config says {region}\n\
+         To move it, run `propel deploy` to create it in {region}, then delete the old one:",
+        others.join(", ")
+    );
+    for other in &others {
+        hint.push_str(&format!("\n  propel destroy --region {other}"));
+    }
+    hint.push_str(&format!(
+        "\nTo keep it where it is, set [project].region = \"{first}\" in propel.toml."
+    ));
+    Some(hint)
+}
+
+/// [`cross_region_hint`] for the services found by
+/// [`GcloudClient::list_services_all_regions`]. A failed lookup only
+/// means no hint.
+pub(crate) async fn find_in_other_regions(
+    client: &GcloudClient,
+    project_id: &str,
+    service_name: &str,
+    region: &str,
+) -> Option<String> {
+    // arch-lint: allow(no-error-swallowing) reason="best-effort hint on top of an error the caller reports anyway"
+    match client
+        .list_services_all_regions(project_id, service_name)
+        .await
+    {
+        Ok(found) => cross_region_hint(service_name, region, &found),
+        Err(e) => {
+            tracing::debug!(error = %e, "could not search other regions for {service_name}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use propel_core::CargoBinary;
+
+    fn config_with_suffix(suffix: Option<&str>) -> PropelConfig {
+        let mut config = PropelConfig::default();
+        config.project.expected_account_suffix = suffix.map(str::to_owned);
+        config
+    }
+
+    fn context(configure: impl FnOnce(&mut PropelConfig)) -> ProjectContext {
+        package_context("cargo-name", configure)
+    }
+
+    fn package_context(package: &str, configure: impl FnOnce(&mut PropelConfig)) -> ProjectContext {
+        let mut config = PropelConfig::default();
+        configure(&mut config);
+        let project = CargoProject {
+            name: package.to_string(),
+            version: "0.1.0".to_string(),
+            manifest_path: PathBuf::from("Cargo.toml"),
+            package_dir: PathBuf::from("."),
+            workspace_root: PathBuf::from("."),
+            binaries: vec![CargoBinary {
+                name: package.to_string(),
+                src_path: PathBuf::from("src/main.rs"),
+            }],
+            default_binary: package.to_string(),
+        };
+        ProjectContext::new(Path::new("."), config, project)
+    }
+
+    #[test]
+    fn service_name_uses_config_override() {
+        let ctx = context(|c| c.project.name = Some("custom-name".to_string()));
+        assert_eq!(ctx.service_name(), "custom-name");
+    }
+
+    #[test]
+    fn service_name_falls_back_to_cargo() {
+        let ctx = context(|_| {});
+        assert_eq!(ctx.service_name(), "cargo-name");
+    }
+
+    #[test]
+    fn region_defaults_and_follows_config() {
+        assert_eq!(context(|_| {}).region(), "us-central1");
+        let ctx = context(|c| c.project.region = "asia-northeast1".to_string());
+        assert_eq!(ctx.region(), "asia-northeast1");
+    }
+
+    #[test]
+    fn gcp_project_id_missing() {
+        let err = context(|_| {}).gcp_project_id().unwrap_err();
+        assert!(err.to_string().contains("[project].gcp_project_id"));
+    }
+
+    #[test]
+    fn gcp_project_id_present() {
+        let ctx = context(|c| c.project.gcp_project_id = Some("my-project".to_string()));
+        assert_eq!(ctx.gcp_project_id().unwrap(), "my-project");
+    }
+
+    #[test]
+    fn image_path_and_tag() {
+        let ctx = context(|c| {
+            c.project.gcp_project_id = Some("my-project".to_string());
+            c.project.region = "europe-west1".to_string();
+            c.project.name = Some("api".to_string());
+        });
+        assert_eq!(
+            ctx.image_path().unwrap(),
+            "europe-west1-docker.pkg.dev/my-project/propel/api"
+        );
+        assert_eq!(
+            ctx.image_ref("latest").unwrap(),
+            "europe-west1-docker.pkg.dev/my-project/propel/api:latest"
+        );
+    }
+
+    #[test]
+    fn image_path_uses_the_artifact_location() {
+        let ctx = context(|c| {
+            c.project.gcp_project_id = Some("my-project".to_string());
+            c.project.region = "europe-west1".to_string();
+            c.project.name = Some("api".to_string());
+            c.build.artifact_location = Some("us".to_string());
+        });
+        assert_eq!(
+            ctx.image_path().unwrap(),
+            "us-docker.pkg.dev/my-project/propel/api"
+        );
+        assert_eq!(ctx.artifact_location(), "us");
+        assert_eq!(ctx.region(), "europe-west1");
+    }
+
+    #[test]
+    fn image_tag_follows_the_tag_strategy() {
+        let ctx = context(|c| {
+            c.project.gcp_project_id = Some("my-project".to_string());
+            c.build.tag_strategy = TagStrategy::Version;
+        });
+        assert_eq!(
+            ctx.image_tag().unwrap(),
+            "us-central1-docker.pkg.dev/my-project/propel/cargo-name:0.1.0"
+        );
+
+        let ctx = context(|c| {
+            c.project.gcp_project_id = Some("my-project".to_string());
+            c.build.tag_strategy = TagStrategy::Latest;
+        });
+        assert!(ctx.image_tag().unwrap().ends_with("/cargo-name:latest"));
+    }
+
+    #[test]
+    fn sha_tag_marks_a_dirty_tree() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let dir = tmp.path();
+        let git = |args: &[&str]| {
+            let output = std::process::Command::new("git")
+                .args(args)
+                .current_dir(dir)
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "git {args:?}");
+            String::from_utf8(output.stdout).unwrap().trim().to_owned()
+        };
+        git(&["init", "-q"]);
+        git(&[
+            "-c",
+            "user.email=t@t",
+            "-c",
+            "user.name=t",
+            "commit",
+            "-q",
+            "--allow-empty",
+            "-m",
+            "init",
+        ]);
+        let sha = git(&["rev-parse", "--short", "HEAD"]);
+        let ctx = ProjectContext {
+            dir: dir.to_owned(),
+            ..context(|c| c.project.gcp_project_id = Some("my-project".to_string()))
+        };
+
+        assert!(ctx.image_tag().unwrap().ends_with(&format!(":{sha}")));
+
+        std::fs::write(dir.join("main.rs"), "fn main() {}\n").unwrap();
+        assert!(ctx.image_tag().unwrap().ends_with(&format!(":{sha}-dirty")));
+    }
+
+    #[test]
+    fn sha_tag_outside_git_falls_back_to_the_version() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let ctx = ProjectContext {
+            dir: tmp.path().to_owned(),
+            ..context(|c| c.project.gcp_project_id = Some("my-project".to_string()))
+        };
+
+        assert!(ctx.image_tag().unwrap().ends_with("/cargo-name:0.1.0"));
+    }
+
+    #[test]
+    fn image_path_needs_gcp_project_id() {
+        assert!(context(|_| {}).image_path().is_err());
+    }
+
+    #[test]
+    fn package_name_is_normalized_for_service_and_image() {
+        let ctx = package_context("My.Service", |c| {
+            c.project.gcp_project_id = Some("my-project".to_string());
+        });
+        assert_eq!(ctx.service_name(), "my-service");
+        assert_eq!(
+            ctx.image_path().unwrap(),
+            "us-central1-docker.pkg.dev/my-project/propel/my-service"
+        );
+        assert_eq!(package_context("my_api", |_| {}).service_name(), "my-api");
+    }
+
+    #[test]
+    fn explicit_project_name_is_used_verbatim() {
+        let ctx = package_context("My.Service", |c| c.project.name = Some("api".to_string()));
+        assert_eq!(ctx.service_name(), "api");
+    }
+
+    /// Workspace member `my_api` with an inherited version and a `[[bin]]`
+    /// named differently from the package, plus `propel.toml`.
+    fn workspace_with_renamed_bin(propel_toml: &str) -> tempfile::TempDir {
+        let tmp = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            tmp.path().join("Cargo.toml"),
+            "[workspace]\nmembers = [\"api\"]\nresolver = \"2\"\n\n\
+             [workspace.package]\nversion = \"1.2.3\"\nedition = \"2024\"\n",
+        )
+        .unwrap();
+        let api = tmp.path().join("api");
+        std::fs::create_dir_all(api.join("src")).unwrap();
+        std::fs::write(
+            api.join("Cargo.toml"),
+            "[package]\nname = \"my_api\"\nversion.workspace = true\nedition.workspace = true\n\n\
+             [[bin]]\nname = \"server\"\npath = \"src/main.rs\"\n",
+        )
+        .unwrap();
+        std::fs::write(api.join("src/main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(api.join("propel.toml"), propel_toml).unwrap();
+        tmp
+    }
+
+    #[test]
+    fn renamed_bin_with_inherited_version_targets_the_package_service() {
+        let tmp = workspace_with_renamed_bin("[project]\ngcp_project_id = \"proj\"\n");
+        let ctx = ProjectContext::load(&tmp.path().join("api")).unwrap();
+
+        assert_eq!(ctx.project.version, "1.2.3");
+        assert_eq!(ctx.project.default_binary, "server");
+        // The service (and image) follow the package, not the binary.
+        assert_eq!(ctx.service_name(), "my-api");
+        assert!(ctx.image_path().unwrap().ends_with("/propel/my-api"));
+    }
+
+    #[test]
+    fn renamed_bin_with_name_override_targets_the_override() {
+        let tmp = workspace_with_renamed_bin("[project]\nname = \"edge\"\n");
+        let ctx = ProjectContext::load(&tmp.path().join("api")).unwrap();
+
+        assert_eq!(ctx.service_name(), "edge");
+    }
+
+    /// Artifact Registry Docker image path: lowercase region, project,
+    /// repository, and image name segments.
+    fn is_valid_image_path(path: &str) -> bool {
+        let segments: Vec<_> = path.split('/').collect();
+        segments.len() == 4
+            && segments[0].ends_with("-docker.pkg.dev")
+            && segments[1..].iter().all(|s| {
+                !s.is_empty()
+                    && s.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+                    && !s.ends_with(['-', '.', '_'])
+                    && s.chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "-._".contains(c))
+            })
+    }
+
+    mod proptests {
+        use super::*;
+        use proptest::prelude::*;
+
+        proptest! {
+            #[test]
+            fn crate_names_yield_valid_service_and_image(
+                package in "[A-Za-z][A-Za-z0-9._-]{0,63}",
+            ) {
+                let ctx = package_context(&package, |c| {
+                    c.project.gcp_project_id = Some("my-project".to_string());
+                });
+                let service = ctx.service_name();
+                prop_assert!(
+                    propel_core::validate_service_name(service).is_ok(),
+                    "{:?}",
+                    service
+                );
+
+                // Deploy pushes `image_ref(tag)`; destroy deletes `image_path()`.
+                let image_path = ctx.image_path().unwrap();
+                prop_assert!(is_valid_image_path(&image_path), "{:?}", image_path);
+                let suffix = format!("/{service}");
+                prop_assert!(image_path.ends_with(&suffix));
+                prop_assert_eq!(ctx.image_ref("latest").unwrap(), format!("{image_path}:latest"));
+            }
+        }
+    }
+
+    #[test]
+    fn context_banner_includes_every_field() {
+        assert_eq!(
+            context_banner(
+                "my-prod-proj",
+                Some("My Prod"),
+                Some("alice@corp.com"),
+                "us-central1",
+                "my-api"
+            ),
+            "Project my-prod-proj (My Prod) · account alice@corp.com · region us-central1 · service my-api"
+        );
+    }
+
+    #[test]
+    fn context_banner_omits_missing_or_redundant_name() {
+        let without = context_banner("proj", None, None, "asia-northeast1", "svc");
+        assert_eq!(
+            without,
+            "Project proj · account (none) · region asia-northeast1 · service svc"
+        );
+        assert_eq!(
+            context_banner("proj", Some("proj"), None, "asia-northeast1", "svc"),
+            without
+        );
+    }
+
+    #[test]
+    fn expected_account_unset_allows_anything() {
+        let config = config_with_suffix(None);
+        assert!(check_expected_account(&config, Some("me@gmail.com")).is_ok());
+        assert!(check_expected_account(&config, None).is_ok());
+    }
+
+    #[test]
+    fn expected_account_mismatch_fails_with_switch_hint() {
+        let config = config_with_suffix(Some("@corp.com"));
+        assert!(check_expected_account(&config, Some("alice@corp.com")).is_ok());
+
+        let err = check_expected_account(&config, Some("alice@gmail.com")).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("alice@gmail.com"));
+        assert!(message.contains("gcloud config set account"));
+
+        assert!(check_expected_account(&config, None).is_err());
+    }
+
+    fn deployed(name: &str, region: &str) -> ManagedService {
+        ManagedService {
+            name: name.to_owned(),
+            region: Some(region.to_owned()),
+            url: None,
+            propel_version: None,
+            config_hash: None,
+            image_size_bytes: None,
+        }
+    }
+
+    #[test]
+    fn cross_region_hint_names_old_region_and_both_fixes() {
+        let hint = cross_region_hint(
+            "my-api",
+            "europe-west1",
+            &[deployed("my-api", "us-central1")],
+        )
+        .unwrap();
+        assert_eq!(
+            hint,
+            "found my-api in us-central1 — your config says europe-west1\n\
+             To move it, run `propel deploy` to create it in europe-west1, then delete the old one:\n  \
+             propel destroy --region us-central1\n\
+             To keep it where it is, set [project].region = \"us-central1\" in propel.toml."
+        );
+    }
+
+    #[test]
+    fn cross_region_hint_lists_every_other_region() {
+        let hint = cross_region_hint(
+            "my-api",
+            "europe-west1",
+            &[
+                deployed("my-api", "us-central1"),
+                deployed("my-api", "europe-west1"),
+                deployed("my-api", "asia-northeast1"),
+            ],
+        )
+        .unwrap();
+        assert!(hint.starts_with("found my-api in us-central1, asia-northeast1 —"));
+        assert!(hint.contains("propel destroy --region us-central1\n"));
+        assert!(hint.contains("propel destroy --region asia-northeast1\n"));
+    }
+
+    #[test]
+    fn cross_region_hint_none_without_service_elsewhere() {
+        assert!(cross_region_hint("my-api", "europe-west1", &[]).is_none());
+        assert!(
+            cross_region_hint(
+                "my-api",
+                "europe-west1",
+                &[deployed("my-api", "europe-west1")]
+            )
+            .is_none()
+        );
+        assert!(
+            cross_region_hint(
+                "my-api",
+                "europe-west1",
+                &[deployed("other-api", "us-central1")]
+            )
+            .is_none()
+        );
+    }
+}
@@ -0,0 +1,35 @@
+use super::CliError;
+use propel_cloud::pricing::{self, PRICES_AS_OF};
+use propel_core::PropelConfig;
+use std::path::PathBuf;
+
+pub async fn costs() -> Result<(), CliError> {
+    let project_dir = PathBuf::from(".");
+    let config = PropelConfig::load(&project_dir)?;
+    let cloud_run = &config.cloud_run;
+    let region = &config.project.region;
+    let estimate = pricing::estimate_monthly_cost(cloud_run, region)?;
+
+    println!(
+        "Cloud Run cost estimate ({region}, {} prices)",
+        estimate.tier
+    );
+    println!(
+        "  Per instance: {} vCPU, {} ({:.2} GiB)",
+        cloud_run.cpu, cloud_run.memory, estimate.memory_gib
+    );
+    println!(
+        "  Idle:       ~${:.2}/month  (min_instances = {}, kept warm around the clock)",
+        estimate.idle_monthly, cloud_run.min_instances
+    );
+    println!(
+        "  Worst case: ~${:.2}/month  (max_instances = {}, all busy around the clock)",
+        estimate.max_monthly, cloud_run.max_instances
+    );
+    println!();
+    println!(
+        "Rough estimate from list prices as of {PRICES_AS_OF}, in USD. Excludes the free tier, \
+         request fees, networking, and other services; check your billing report for actual costs."
+    );
+    Ok(())
+}
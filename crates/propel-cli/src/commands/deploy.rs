@@ -1,102 +1,592 @@
-use propel_build::dockerfile::DockerfileGenerator;
-use propel_build::{bundle, eject as eject_mod};
-use propel_cloud::GcloudClient;
-use propel_core::{CargoProject, PropelConfig};
-use std::path::PathBuf;
-
-/// Execute the full deploy pipeline.
-pub async fn deploy(allow_dirty: bool) -> anyhow::Result<()> {
+use super::CliError;
+use super::ProjectContext;
+use propel_build::cloudbuild::CLOUDBUILD_FILE;
+use propel_build::manifest::MANIFEST_FILE;
+use propel_build::{CargoConfig, bundle, eject as eject_mod};
+use propel_cloud::{
+    API_ENABLEMENT_TIMEOUT, BuildOutput, CloudBuildError, GcloudClient, PreflightError,
+    PropelLabels, RecordingExecutor, SECRET_ACCESS_PROPAGATION_TIMEOUT, retry_secret_propagation,
+};
+use std::path::{Path, PathBuf};
+
+/// Files listed per top-level bundle directory with `--verbose`.
+const VERBOSE_FILES_PER_DIR: usize = 10;
+
+/// Execute the full deploy pipeline, or with `dry_run` print its plan
+/// (see [`print_plan`]).
+#[allow(
+    clippy::too_many_arguments,
+    reason = "one parameter per `propel deploy` flag"
+)]
+#[tracing::instrument(
+    name = "deploy",
+    level = "debug",
+    skip_all,
+    fields(project = tracing::field::Empty, service = tracing::field::Empty)
+)]
+pub async fn deploy(
+    allow_dirty: bool,
+    fix_config: bool,
+    verbose: bool,
+    build_log: &Path,
+    skip_iam_check: bool,
+    strict_preflight: bool,
+    force_large_bundle: bool,
+    strict_bundle: bool,
+    dry_run: bool,
+) -> Result<(), CliError> {
     let project_dir = PathBuf::from(".");
-    let client = GcloudClient::new();
+    bundle::check_project_dir(&project_dir)?;
 
     // Dirty check: refuse to deploy uncommitted changes unless --allow-dirty
     if !allow_dirty && bundle::is_dirty(&project_dir)? {
-        anyhow::bail!(
-            "uncommitted changes detected.\n\
-             Commit your changes, or use `propel deploy --allow-dirty` to deploy anyway."
-        );
+        return Err(CliError::DirtyTree);
     }
 
     // Load configuration
-    let config = PropelConfig::load(&project_dir)?;
-    let project = CargoProject::discover(&project_dir)?;
-
-    let gcp_project_id = super::require_gcp_project_id(&config)?;
-    let service_name = super::service_name(&config, &project);
-    let region = &config.project.region;
-    let image_tag = format!(
-        "{}:latest",
-        super::image_path(
-            region,
-            gcp_project_id,
-            super::ARTIFACT_REPO_NAME,
-            service_name
-        ),
-    );
+    let mut ctx = ProjectContext::load(&project_dir)?;
+    for warning in super::config_warnings(&ctx.config, &project_dir) {
+        println!("Warning: {warning}");
+    }
+    for warning in
+        super::include_check::unincluded_runtime_dirs(&ctx.config, &ctx.project, &project_dir)
+    {
+        println!("{warning}");
+    }
+    let client = super::preflight::preflight_client(&ctx.config);
+
+    // Dependency checks: catch missing -sys packages and private sources
+    // before Cloud Build does.
+    if let Some(metadata) = super::resolve_dependency_graph(&project_dir) {
+        super::check_private_dependencies(&metadata, &ctx.config.build, &ctx.project)?;
+        if let Some(found) =
+            propel_core::detect_deprecated_sdk(&metadata, &ctx.project.manifest_path)
+        {
+            println!("Warning: {found}");
+        }
+
+        // An ejected Dockerfile is the user's to maintain, so skip it there.
+        if !eject_mod::is_ejected(&project_dir)
+            && let Some(suggestion) =
+                super::native_package_suggestion(&metadata, &ctx.config.build, &ctx.project)
+        {
+            if fix_config && dry_run {
+                suggestion.apply_to(&mut ctx.config.build);
+                println!(
+                    "Would update propel.toml [build] for {} (dry run: not written):\n\n{}",
+                    suggestion.crates.join(", "),
+                    suggestion.toml_snippet(&ctx.config.build)
+                );
+            } else if fix_config {
+                suggestion.apply_to_config(&project_dir)?;
+                ctx.reload_config()?;
+                println!(
+                    "Updated propel.toml [build] for {} — commit the change to keep it",
+                    suggestion.crates.join(", ")
+                );
+            } else {
+                println!(
+                    "Warning: {} need(s) system packages missing from propel.toml.\n\
+                     Add the following, or re-run with `propel deploy --fix-config`:\n\n{}",
+                    suggestion.crates.join(", "),
+                    suggestion.toml_snippet(&ctx.config.build)
+                );
+            }
+        }
+    }
+
+    // Hash the config as written: resolved templates (e.g. the commit SHA)
+    // would give every deploy a different hash.
+    let labels = super::propel_labels(&ctx.config);
+    super::resolve_env_templates(&mut ctx)?;
+
+    let gcp_project_id = ctx.gcp_project_id()?;
+    let service_name = ctx.service_name();
+    let region = ctx.region();
+    let image_tag = ctx.image_tag()?;
+    tracing::Span::current()
+        .record("project", gcp_project_id)
+        .record("service", service_name);
+    let ProjectContext {
+        config, project, ..
+    } = &ctx;
+
+    if dry_run {
+        return print_plan(&ctx, &project_dir, &labels, &image_tag, build_log).await;
+    }
 
     // Pre-flight checks
     println!("Running pre-flight checks...");
     let report = client.check_prerequisites(gcp_project_id).await?;
+    // With deploy_via_cloud_build, gcloud runs in Cloud Build's SDK image,
+    // which ships every component.
+    if !config.ci.deploy_via_cloud_build
+        && let Some(notice) = super::check_run_command_group(&client, config).await?
+    {
+        println!("{notice}");
+    }
 
-    if report.has_warnings() {
-        println!("Warning: the following APIs are not enabled:");
-        for api in &report.disabled_apis {
-            println!("  - {api}");
+    if let Some(key_file) = &report.activated_key_file {
+        println!(
+            "No active gcloud account — activated service account key {} \
+             ([auth] activate_key_file)",
+            key_file.display()
+        );
+    }
+
+    let account = super::announce_target(
+        &client,
+        config,
+        gcp_project_id,
+        report.project_name.as_deref(),
+        service_name,
+    )
+    .await?;
+    super::check_expected_account(config, account.as_deref())?;
+    if !skip_iam_check
+        && let Some(warning) = super::iam::verify_deploy_permissions(
+            &client,
+            config,
+            gcp_project_id,
+            account.as_deref(),
+        )
+        .await?
+    {
+        println!("{warning}");
+    }
+
+    if report.billing_enabled.is_none() {
+        println!("Warning: could not verify billing status — continuing");
+    }
+
+    let disabled_apis =
+        super::preflight::DisabledApis::classify(config, report.disabled_apis, strict_preflight);
+    if let Some(warning) = disabled_apis.warning() {
+        println!("{warning}");
+    }
+    if !disabled_apis.required.is_empty() {
+        // Freshly enabled APIs take a while to show up as enabled; wait for
+        // those instead of failing immediately.
+        let pending = client
+            .pending_api_enablements(gcp_project_id, &disabled_apis.required)
+            .await?;
+        if pending.len() != disabled_apis.required.len() {
+            return Err(PreflightError::ApisNotEnabled {
+                project_id: gcp_project_id.to_owned(),
+                apis: disabled_apis.required,
+            }
+            .into());
         }
-        println!("Enable them with: gcloud services enable <api> --project {gcp_project_id}");
-        anyhow::bail!("required APIs not enabled");
+
+        println!(
+            "Waiting for recently enabled APIs to propagate (up to {}s)...",
+            API_ENABLEMENT_TIMEOUT.as_secs()
+        );
+        client
+            .wait_for_api_enablement(
+                gcp_project_id,
+                &pending,
+                API_ENABLEMENT_TIMEOUT,
+                |apis, elapsed| {
+                    println!(
+                        "  still waiting on {} ({}s elapsed)",
+                        apis.join(", "),
+                        elapsed.as_secs()
+                    );
+                },
+            )
+            .await?;
+        println!("  APIs enabled.");
     }
 
     // Ensure Artifact Registry repository
     println!("Ensuring Artifact Registry repository...");
     client
-        .ensure_artifact_repo(gcp_project_id, region, super::ARTIFACT_REPO_NAME)
+        .ensure_artifact_repo(
+            gcp_project_id,
+            ctx.artifact_location(),
+            super::ARTIFACT_REPO_NAME,
+        )
         .await?;
 
-    // Determine Dockerfile content
-    let dockerfile_content = if eject_mod::is_ejected(&project_dir) {
-        println!("Using ejected Dockerfile from .propel/Dockerfile");
-        eject_mod::load_ejected_dockerfile(&project_dir)?
-    } else {
-        let generator = DockerfileGenerator::new(&config.build, &project, config.cloud_run.port);
-        generator.render()
-    };
-
-    // Bundle source
-    println!("Bundling source...");
-    let bundle_dir = bundle::create_bundle(&project_dir, &dockerfile_content)?;
-
-    // Submit build
-    println!("Submitting build to Cloud Build...");
-    client
-        .submit_build(&bundle_dir, gcp_project_id, &image_tag)
-        .await?;
+    let dockerfile_content = deploy_dockerfile(&ctx, &project_dir, strict_preflight)?;
 
     // Discover secrets in Secret Manager and inject into Cloud Run.
     // IAM binding (secretAccessor) is granted at `propel secret set` time,
     // so deploy only needs secretmanager.viewer to list.
-    let secrets = client.list_secrets(gcp_project_id).await?;
-    if secrets.is_empty() {
+    // [secrets] entries are mounted as mapped; the rest are discovered.
+    // The git credentials secret is for the build only — never inject it,
+    // and [cloud_run].secrets narrows the set further.
+    let skipped = disabled_apis.secret_discovery_skipped(config);
+    let secrets = if skipped.is_some() {
+        Vec::new()
+    } else {
+        super::select_secrets(config, client.list_secrets(gcp_project_id).await?)?
+    };
+    if let Some(reason) = skipped {
+        println!("{reason}");
+    } else if secrets.is_empty() {
         println!("No secrets found in Secret Manager");
     } else {
         println!("Injecting {} secret(s) from Secret Manager", secrets.len());
     }
+    if config.cloud_run.check_secret_versions {
+        for warning in super::secret_version_warnings(&client, gcp_project_id, &secrets).await {
+            println!("Warning: {warning}");
+        }
+    }
+    for warning in super::cloudsql::cloud_sql_warnings(&client, config, gcp_project_id).await {
+        println!("Warning: {warning}");
+    }
 
-    // Deploy to Cloud Run
-    println!("Deploying to Cloud Run ({region})...");
-    let url = client
-        .deploy_to_cloud_run(
-            service_name,
-            &image_tag,
+    // Bundle source; a tree checked clean must still be clean once copied.
+    // A workspace member is bundled with its whole workspace.
+    if let Some(member) = project.member_path() {
+        println!(
+            "Bundling workspace {} for member {}",
+            project.workspace_root.display(),
+            member.display()
+        );
+    }
+    let bundle_dir = super::create_bundle_reporting(
+        &bundle::BundleOptions::new(
+            super::bundle_source(project, &project_dir),
+            dockerfile_content.as_str(),
+        )
+        .verify(strict_bundle || !allow_dirty),
+    )?;
+    if let Some(sha256) = super::write_bundle_manifest(config, &project_dir, &bundle_dir)? {
+        println!("Wrote {MANIFEST_FILE} (sha256 {sha256})");
+    }
+    if let Some(warning) = super::check_bundle_size(config, &bundle_dir, force_large_bundle)? {
+        println!("{warning}");
+    }
+    if verbose {
+        let entries = bundle::bundle_entries(&bundle_dir)?;
+        print!(
+            "{}",
+            bundle::format_bundle_summary(&entries, VERBOSE_FILES_PER_DIR)
+        );
+        println!("--- Dockerfile ---");
+        print!("{dockerfile_content}");
+        println!("--- end Dockerfile ---");
+    }
+    let build_config = match super::cloud_build_yaml(
+        config,
+        gcp_project_id,
+        &image_tag,
+        service_name,
+        &secrets,
+        &labels,
+    ) {
+        Some(yaml) => Some(bundle::write_cloudbuild_config(&bundle_dir, &yaml)?),
+        None => None,
+    };
+
+    // Submit build
+    if config.ci.deploy_via_cloud_build {
+        println!("Submitting build and Cloud Run deploy ({region}) to Cloud Build...");
+    } else {
+        println!("Submitting build to Cloud Build...");
+    }
+    super::ignore_build_logs(&project_dir, build_log)?;
+    let submitted = client
+        .submit_build(
+            &bundle_dir,
             gcp_project_id,
-            region,
-            &config.cloud_run,
-            &secrets,
+            &image_tag,
+            &config.cloud_build,
+            build_config.as_deref(),
+            &BuildOutput::Tee(build_log.to_path_buf()),
+        )
+        .await;
+    if let Err(e) = submitted {
+        println!("Full build log: {}", build_log.display());
+        // Org-policy failures are classified from stderr; for anything else,
+        // point at the usual culprits when they haven't been configured yet.
+        if matches!(e, CloudBuildError::Submit { .. })
+            && (config.cloud_build.region.is_none() || config.cloud_build.logs_bucket.is_none())
+        {
+            println!(
+                "Hint: if the output above mentions the logs bucket or \
+                 constraints/cloudbuild.allowedWorkerPools, set [cloud_build].logs_bucket \
+                 and/or [cloud_build].region in propel.toml"
+            );
+        }
+        return Err(e.into());
+    }
+    println!("Image: {image_tag}");
+    super::tag_latest(&client, &ctx, &image_tag, |line| println!("{line}")).await?;
+    let labels = match super::image_size::report_image_size(
+        &client,
+        config,
+        &image_tag,
+        service_name,
+        gcp_project_id,
+        region,
+        |line| println!("{line}"),
+    )
+    .await
+    {
+        Some(size) => labels.with_image_size(size),
+        None => labels,
+    };
+
+    // Deploy to Cloud Run (already done inside the build in deploy_via_cloud_build mode)
+    let (url, canary) = if config.ci.deploy_via_cloud_build {
+        let url = client
+            .service_url(service_name, gcp_project_id, region)
+            .await?;
+        (url, None)
+    } else {
+        println!("Deploying to Cloud Run ({region})...");
+        let canary =
+            super::canary::canary_applies(&client, &ctx, gcp_project_id, |line| println!("{line}"))
+                .await?;
+        let warm = canary.is_none()
+            && super::warmup::warmup_applies(&client, &ctx, gcp_project_id, |line| {
+                println!("{line}")
+            })
+            .await?;
+        // Secret access granted by a just-run `propel secret set` may not
+        // have propagated yet; Cloud Run rejects the revision until it has.
+        let deployed = retry_secret_propagation(
+            SECRET_ACCESS_PROPAGATION_TIMEOUT,
+            |delay, _| {
+                println!(
+                    "  Cloud Run cannot read the secrets yet — new secret access can take \
+                     up to a minute to propagate. Retrying in {}s...",
+                    delay.as_secs()
+                );
+            },
+            || async {
+                if canary.is_some() {
+                    client
+                        .deploy_canary_revision(
+                            service_name,
+                            &image_tag,
+                            gcp_project_id,
+                            region,
+                            &config.cloud_run,
+                            &secrets,
+                            &labels,
+                        )
+                        .await
+                } else if warm {
+                    client
+                        .deploy_revision_without_traffic(
+                            service_name,
+                            &image_tag,
+                            gcp_project_id,
+                            region,
+                            &config.cloud_run,
+                            &secrets,
+                            &labels,
+                        )
+                        .await
+                } else {
+                    client
+                        .deploy_to_cloud_run(
+                            service_name,
+                            &image_tag,
+                            gcp_project_id,
+                            region,
+                            &config.cloud_run,
+                            &secrets,
+                            &labels,
+                        )
+                        .await
+                }
+            },
         )
         .await?;
+        let url = if let Some(canary) = canary {
+            super::canary::release_canary(
+                &client,
+                &ctx,
+                gcp_project_id,
+                canary,
+                &deployed,
+                |line| println!("{line}"),
+            )
+            .await?
+        } else if warm {
+            super::warmup::warm_up_and_promote(&client, &ctx, gcp_project_id, &deployed, |line| {
+                println!("{line}")
+            })
+            .await?
+        } else {
+            deployed
+        };
+        (url, canary)
+    };
+    super::history::record(
+        &ctx,
+        &super::history::deploy_record(
+            &ctx,
+            &image_tag,
+            canary,
+            account.as_deref(),
+            report.project_number.as_deref(),
+        ),
+        |line| println!("{line}"),
+    );
 
     println!();
     println!("Deployed: {url}");
+    if let Some(notice) = super::private_service_notice(config) {
+        println!("{notice}");
+    }
+    if let Some(notice) = super::idle_cost_notice(config) {
+        println!("{notice}");
+    }
 
     Ok(())
 }
+
+/// The Dockerfile to build: the ejected one, else one rendered for the
+/// project and its cargo config.
+///
+/// An ejected Dockerfile naming another binary than the project builds is
+/// a warning, or an error with `strict`. A generated one is first checked
+/// against the package's `rust-version`.
+fn deploy_dockerfile(
+    ctx: &ProjectContext,
+    project_dir: &Path,
+    strict: bool,
+) -> Result<String, CliError> {
+    // Cargo reads its config from where it builds: the bundle root.
+    let bundle_root = super::bundle_source(&ctx.project, project_dir);
+    let cargo_config = CargoConfig::detect(bundle_root)?;
+    if let Some(cargo_config) = &cargo_config {
+        for warning in super::cargo_config_warnings(bundle_root, cargo_config)? {
+            println!("{warning}");
+        }
+    }
+
+    if eject_mod::is_ejected(project_dir) {
+        println!("Using ejected Dockerfile from .propel/Dockerfile");
+        if let Some(warning) = super::untracked_eject_warning(project_dir)? {
+            println!("{warning}");
+        }
+        let dockerfile = eject_mod::load_ejected_dockerfile(project_dir)?;
+        if let Some(warning) =
+            super::ejected_binary_warning(&dockerfile, &ctx.project.default_binary)
+        {
+            if strict {
+                return Err(CliError::Preflight(anyhow::anyhow!("{warning}")));
+            }
+            println!("Warning: {warning}");
+        }
+        Ok(dockerfile)
+    } else {
+        match super::check_builder_rust_version(&ctx.config, &ctx.project) {
+            Ok(Some(warning)) => println!("Warning: {warning}"),
+            Ok(None) => {}
+            Err(e @ propel_core::Error::BuilderImageTooOld { .. }) => {
+                return Err(CliError::Preflight(e.into()));
+            }
+            Err(e) => return Err(e.into()),
+        }
+        Ok(super::render_dockerfile(
+            &ctx.config,
+            &ctx.project,
+            cargo_config.as_ref(),
+        )?)
+    }
+}
+
+/// `propel deploy --dry-run`: print the Dockerfile, the files the bundle
+/// would hold, the image tag, and the gcloud commands the deploy would
+/// run. Nothing is written and gcloud is not called: the commands come
+/// from a [`GcloudClient`] whose [`RecordingExecutor`] only records them.
+async fn print_plan(
+    ctx: &ProjectContext,
+    project_dir: &Path,
+    labels: &PropelLabels,
+    image_tag: &str,
+    build_log: &Path,
+) -> Result<(), CliError> {
+    let config = &ctx.config;
+    let gcp_project_id = ctx.gcp_project_id()?;
+    let service_name = ctx.service_name();
+    let region = ctx.region();
+
+    let dockerfile_content = deploy_dockerfile(ctx, project_dir, false)?;
+    let bundle_root = super::bundle_source(&ctx.project, project_dir);
+    let files = bundle::bundle_files(&bundle::BundleOptions::new(
+        bundle_root,
+        dockerfile_content.as_str(),
+    ))?;
+    let size = bundle::BundleSize::measure(bundle_root, &files)?;
+
+    println!("Dry run: nothing is built, uploaded, or deployed.");
+    println!();
+    println!("--- Dockerfile ---");
+    print!("{dockerfile_content}");
+    println!("--- end Dockerfile ---");
+    print!(
+        "{}",
+        bundle::format_bundle_summary(&files, VERBOSE_FILES_PER_DIR)
+    );
+    println!(
+        "Bundle size: {} plus the generated Dockerfile",
+        bundle::format_size(size.total)
+    );
+    println!("Image: {image_tag}");
+
+    // Secrets are discovered in Secret Manager at deploy time, which is a
+    // gcloud call; the plan shows the commands without them.
+    let bundle_dir = bundle_root.join(bundle::BUNDLE_DIR);
+    let build_config =
+        super::cloud_build_yaml(config, gcp_project_id, image_tag, service_name, &[], labels)
+            .map(|yaml| (bundle_dir.join(CLOUDBUILD_FILE), yaml));
+    let client = GcloudClient::with_executor(RecordingExecutor::new());
+    client
+        .submit_build(
+            &bundle_dir,
+            gcp_project_id,
+            image_tag,
+            &config.cloud_build,
+            build_config.as_ref().map(|(path, _)| path.as_path()),
+            &BuildOutput::Tee(build_log.to_path_buf()),
+        )
+        .await?;
+    if !config.ci.deploy_via_cloud_build {
+        client
+            .deploy_to_cloud_run(
+                service_name,
+                image_tag,
+                gcp_project_id,
+                region,
+                &config.cloud_run,
+                &[],
+                labels,
+            )
+            .await?;
+    }
+
+    println!();
+    println!("Commands:");
+    for args in client.executor().commands() {
+        println!("  gcloud {}", super::shell_command(&args));
+    }
+    if let Some((_, yaml)) = &build_config {
+        println!("--- {CLOUDBUILD_FILE} ---");
+        print!("{yaml}");
+        println!("--- end {CLOUDBUILD_FILE} ---");
+    }
+    if config.cloud_run.inject_secrets {
+        println!("Secrets found in Secret Manager at deploy time are added with --update-secrets.");
+        for (env, secret) in &config.secrets {
+            println!("  {env}={}:{} ([secrets])", secret.name, secret.version);
+        }
+    }
+    if config.cloud_run.canary.is_some() || config.cloud_run.warmup_requests > 0 {
+        println!(
+            "If {service_name} already exists, the deploy adds --no-traffic and a tag, \
+             then moves traffic with `gcloud run services update-traffic`."
+        );
+    }
+    Ok(())
+}
@@ -1,6 +1,9 @@
+use super::CliError;
+use super::ProjectContext;
 use super::ci;
-use propel_cloud::GcloudClient;
-use propel_core::{CargoProject, PropelConfig};
+use propel_cloud::{GcloudClient, GcloudExecutor, SharedCiUsage};
+use propel_core::PropelConfig;
+use std::fmt::Write as _;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 
@@ -10,53 +13,247 @@ fn mask_name(name: &str) -> String {
     format!("{prefix}***")
 }
 
-/// Delete Cloud Run service, container image, and local bundle.
+/// What `propel destroy` found to delete.
+///
+/// Probed before the confirmation, so a never-deployed project is told
+/// there is nothing to destroy instead of being asked to confirm deletes
+/// that can only fail.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct DestroyPlan {
+    /// The Cloud Run service exists in the target region.
+    service: bool,
+    /// Artifact Registry holds images of the service.
+    image: bool,
+    /// Secrets under this config's `[project].secret_prefix`.
+    secrets: Vec<String>,
+    /// `propel ci init` ran here: the workflow file exists or `[ci]`
+    /// records the resource ids.
+    ci: bool,
+    /// A local `.propel-bundle/` is left over.
+    bundle: bool,
+}
+
+impl DestroyPlan {
+    /// Probe what exists. A probe that fails for a reason other than "not
+    /// found" counts the resource as present, so its delete still runs
+    /// and reports the actual error.
+    async fn discover<E: GcloudExecutor>(
+        client: &GcloudClient<E>,
+        config: &PropelConfig,
+        project_id: &str,
+        service_name: &str,
+        region: &str,
+        image_path: &str,
+        project_dir: &Path,
+    ) -> Self {
+        let service = assume_present(
+            "the Cloud Run service",
+            client
+                .service_exists(service_name, project_id, region)
+                .await,
+        );
+        let image = assume_present("container images", client.image_exists(image_path).await);
+
+        // Only secrets under [project].secret_prefix belong to this config.
+        // arch-lint: allow(no-error-swallowing) reason="pre-destroy discovery; empty fallback is safe since delete loop simply skips"
+        let mut secrets = match client.list_secrets(project_id).await {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("Warning: could not list secrets: {e}");
+                vec![]
+            }
+        };
+        let namespace = config.project.secret_namespace();
+        secrets.retain(|name| namespace.key(name).is_some());
+
+        Self {
+            service,
+            image,
+            secrets,
+            ci: project_dir.join(ci::WORKFLOW_PATH).exists() || config.ci.wif_pool_id.is_some(),
+            bundle: project_dir.join(".propel-bundle").exists(),
+        }
+    }
+
+    /// The part of the plan the flags ask to delete: secrets only with
+    /// `--include-secrets`, CI resources only with `--include-ci`.
+    fn selected(&self, include_secrets: bool, include_ci: bool) -> Self {
+        Self {
+            service: self.service,
+            image: self.image,
+            secrets: if include_secrets {
+                self.secrets.clone()
+            } else {
+                Vec::new()
+            },
+            ci: self.ci && include_ci,
+            bundle: self.bundle,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Confirmation lines, one per resource to delete; `ci_lines` lists the
+    /// CI resources when [`Self::ci`] is set.
+    fn summary(&self, service_name: &str, region: &str, ci_lines: &[String]) -> Vec<String> {
+        let mut lines = Vec::new();
+        if self.service {
+            lines.push(format!(
+                "  - Cloud Run service '{service_name}' in {region}"
+            ));
+        }
+        if self.image {
+            lines.push("  - Container images in Artifact Registry".to_owned());
+        }
+        if self.bundle {
+            lines.push("  - Local .propel-bundle/".to_owned());
+        }
+        if !self.secrets.is_empty() {
+            lines.push(format!(
+                "  - {} secret(s) from Secret Manager:",
+                self.secrets.len()
+            ));
+            lines.extend(
+                self.secrets
+                    .iter()
+                    .map(|s| format!("      {}", mask_name(s))),
+            );
+        }
+        if self.ci {
+            lines.extend(ci_lines.iter().map(|line| format!("  - {line}")));
+        }
+        lines
+    }
+}
+
+/// Whether a probed resource should be deleted: its probe result, or
+/// `true` when the probe itself failed.
+fn assume_present<E: std::fmt::Display>(what: &str, probed: Result<bool, E>) -> bool {
+    match probed {
+        Ok(exists) => exists,
+        // arch-lint: allow(no-error-swallowing) reason="a failed probe keeps the delete, which reports the real error"
+        Err(e) => {
+            eprintln!("Warning: could not check for {what} ({e}) — trying to delete anyway");
+            true
+        }
+    }
+}
+
+/// Delete the Cloud Run service, container image, and local bundle — and
+/// optionally secrets and CI resources — that actually exist.
 pub async fn destroy(
     skip_confirm: bool,
     include_secrets: bool,
     include_ci: bool,
-) -> anyhow::Result<()> {
+    force_shared: bool,
+    region: Option<String>,
+) -> Result<(), CliError> {
     let project_dir = PathBuf::from(".");
     let client = GcloudClient::new();
 
-    let config = PropelConfig::load(&project_dir)?;
-    let project = CargoProject::discover(&project_dir)?;
-
-    let gcp_project_id = super::require_gcp_project_id(&config)?;
-
-    let service_name = super::service_name(&config, &project);
-    let region = &config.project.region;
+    let mut ctx = ProjectContext::load(&project_dir)?;
+    // The service and its images live in the region they were deployed to,
+    // which may no longer be the one in propel.toml.
+    if let Some(region) = region {
+        ctx.config.project.region = region;
+    }
+    let config = &ctx.config;
+    let gcp_project_id = ctx.gcp_project_id()?;
+    let service_name = ctx.service_name();
+    let region = ctx.region();
+    let image_path = ctx.image_path()?;
+    let ci_resources = config.ci.resources(service_name);
+    let ci_sa_email = ci_resources.service_account_email(gcp_project_id);
+
+    let account =
+        super::announce_target(&client, config, gcp_project_id, None, service_name).await?;
+    super::check_expected_account(config, account.as_deref())?;
+
+    let found = DestroyPlan::discover(
+        &client,
+        config,
+        gcp_project_id,
+        service_name,
+        region,
+        &image_path,
+        &project_dir,
+    )
+    .await;
+    if !found.service
+        && let Some(hint) =
+            super::find_in_other_regions(&client, gcp_project_id, service_name, region).await
+    {
+        println!("{hint}");
+    }
+    let plan = found.selected(include_secrets, include_ci);
+    if plan.is_empty() {
+        println!("Nothing to destroy: service '{service_name}' has no resources in {region}.");
+        print_remaining(&found, include_secrets, include_ci);
+        return Ok(());
+    }
 
-    // Discover secrets for display / deletion
-    // arch-lint: allow(no-error-swallowing) reason="pre-destroy discovery; empty fallback is safe since delete loop simply skips"
-    let secrets = match client.list_secrets(gcp_project_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            eprintln!("Warning: could not list secrets: {e}");
-            vec![]
+    // Refuse to delete CI resources other repositories still depend on.
+    if plan.ci {
+        // arch-lint: allow(no-silent-result-drop) reason="Option: without an origin remote every WIF binding counts as foreign, which only makes the check stricter"
+        let github_repo = ci::detect_github_repo().await.ok();
+        let usage = client
+            .shared_ci_usage(
+                gcp_project_id,
+                &ci_resources.wif_pool_id,
+                &ci_resources.wif_provider_id,
+                &ci_sa_email,
+                github_repo.as_deref(),
+            )
+            .await;
+        match usage {
+            Ok(usage) if usage.is_shared() => {
+                let listing = shared_usage_listing(&usage);
+                if !force_shared {
+                    return Err(anyhow::anyhow!(
+                        "CI resources are shared with other repositories:\n{listing}\
+                         Deleting them would break their deploys. \
+                         Re-run with --force-shared to delete anyway."
+                    )
+                    .into());
+                }
+                println!("Warning: deleting CI resources shared with:\n{listing}");
+            }
+            Ok(_) => {}
+            // arch-lint: allow(no-error-swallowing) reason="--force-shared explicitly opts out of the sharing check"
+            Err(e) if force_shared => {
+                println!("Warning: could not check for shared CI resources ({e}) — continuing");
+            }
+            Err(e) => {
+                return Err(anyhow::Error::new(e)
+                    .context(
+                        "could not check whether CI resources are shared; \
+                     re-run with --force-shared to skip the check",
+                    )
+                    .into());
+            }
         }
-    };
+    }
 
     if !skip_confirm {
-        println!("This will delete:");
-        println!("  - Cloud Run service '{service_name}' in {region}");
-        println!("  - Container images in Artifact Registry");
-        println!("  - Local .propel-bundle/");
-
-        if include_secrets && !secrets.is_empty() {
-            println!("  - {} secret(s) from Secret Manager:", secrets.len());
-            for s in &secrets {
-                println!("      {}", mask_name(s));
-            }
+        let mut ci_lines = vec![
+            format!("Workload Identity Pool '{}'", ci_resources.wif_pool_id),
+            format!("Service Account '{ci_sa_email}'"),
+        ];
+        if config.ci.deploy_via_cloud_build {
+            ci_lines.push(format!(
+                "Service Account '{}'",
+                super::build_service_account(gcp_project_id)
+            ));
         }
+        ci_lines
+            .push("GitHub Secrets (GCP_PROJECT_ID, WIF_PROVIDER, WIF_SERVICE_ACCOUNT)".to_owned());
+        ci_lines.push(ci::WORKFLOW_PATH.to_owned());
 
-        if include_ci {
-            println!("  - Workload Identity Pool 'propel-github'");
-            println!(
-                "  - Service Account 'propel-deploy@{gcp_project_id}.iam.gserviceaccount.com'"
-            );
-            println!("  - GitHub Secrets (GCP_PROJECT_ID, WIF_PROVIDER, WIF_SERVICE_ACCOUNT)");
-            println!("  - {}", ci::WORKFLOW_PATH);
+        println!("This will delete:");
+        for line in plan.summary(service_name, region, &ci_lines) {
+            println!("{line}");
         }
 
         println!();
@@ -68,46 +265,43 @@ pub async fn destroy(
 
         if !matches!(input.trim(), "y" | "Y" | "yes" | "YES") {
             println!("Aborted.");
-            return Ok(());
+            return Err(CliError::Cancelled);
         }
     }
 
-    let image_tag = super::image_path(
-        region,
-        gcp_project_id,
-        super::ARTIFACT_REPO_NAME,
-        service_name,
-    );
-
     let mut cleanup_errors: Vec<String> = Vec::new();
 
     // 1. Delete Cloud Run service
-    println!("Deleting Cloud Run service '{service_name}'...");
-    match client
-        .delete_service(service_name, gcp_project_id, region)
-        .await
-    {
-        Ok(()) => println!("  Deleted."),
-        Err(e) => {
-            println!("  Skipped ({e})");
-            cleanup_errors.push(format!("Cloud Run service: {e}"));
+    if plan.service {
+        println!("Deleting Cloud Run service '{service_name}'...");
+        match client
+            .delete_service(service_name, gcp_project_id, region)
+            .await
+        {
+            Ok(()) => println!("  Deleted."),
+            Err(e) => {
+                println!("  Skipped ({e})");
+                cleanup_errors.push(format!("Cloud Run service: {e}"));
+            }
         }
     }
 
     // 2. Delete container image from Artifact Registry
-    println!("Deleting container image...");
-    match client.delete_image(&image_tag, gcp_project_id).await {
-        Ok(()) => println!("  Deleted."),
-        Err(e) => {
-            println!("  Skipped ({e})");
-            cleanup_errors.push(format!("container image: {e}"));
+    if plan.image {
+        println!("Deleting container image...");
+        match client.delete_image(&image_path, gcp_project_id).await {
+            Ok(()) => println!("  Deleted."),
+            Err(e) => {
+                println!("  Skipped ({e})");
+                cleanup_errors.push(format!("container image: {e}"));
+            }
         }
     }
 
     // 3. Delete secrets if requested
-    if include_secrets && !secrets.is_empty() {
-        println!("Deleting {} secret(s)...", secrets.len());
-        for s in &secrets {
+    if !plan.secrets.is_empty() {
+        println!("Deleting {} secret(s)...", plan.secrets.len());
+        for s in &plan.secrets {
             match client.delete_secret(gcp_project_id, s).await {
                 Ok(()) => println!("  Deleted {}", mask_name(s)),
                 Err(e) => {
@@ -119,15 +313,15 @@ pub async fn destroy(
     }
 
     // 4. Delete CI/CD resources if requested
-    if include_ci {
+    if plan.ci {
         println!("Deleting CI/CD resources...");
 
         // WIF Pool (providers are cascade-deleted)
         match client
-            .delete_wif_pool(gcp_project_id, ci::WIF_POOL_ID)
+            .delete_wif_pool(gcp_project_id, &ci_resources.wif_pool_id)
             .await
         {
-            Ok(()) => println!("  Deleted WIF Pool '{}'", ci::WIF_POOL_ID),
+            Ok(()) => println!("  Deleted WIF Pool '{}'", ci_resources.wif_pool_id),
             Err(e) => {
                 println!("  Skipped WIF Pool ({e})");
                 cleanup_errors.push(format!("WIF Pool: {e}"));
@@ -135,9 +329,8 @@ pub async fn destroy(
         }
 
         // Service Account
-        let sa_email = format!("{}@{gcp_project_id}.iam.gserviceaccount.com", ci::CI_SA_ID);
         match client
-            .delete_service_account(gcp_project_id, &sa_email)
+            .delete_service_account(gcp_project_id, &ci_sa_email)
             .await
         {
             Ok(()) => println!("  Deleted Service Account"),
@@ -147,6 +340,21 @@ pub async fn destroy(
             }
         }
 
+        // Build service account ([ci].deploy_via_cloud_build mode only)
+        if config.ci.deploy_via_cloud_build {
+            let build_sa = super::build_service_account(gcp_project_id);
+            match client
+                .delete_service_account(gcp_project_id, &build_sa)
+                .await
+            {
+                Ok(()) => println!("  Deleted build Service Account"),
+                Err(e) => {
+                    println!("  Skipped build Service Account ({e})");
+                    cleanup_errors.push(format!("build Service Account: {e}"));
+                }
+            }
+        }
+
         // GitHub Secrets (best-effort)
         for secret_name in ci::GH_SECRET_NAMES {
             match ci::delete_gh_secret(secret_name).await {
@@ -167,9 +375,8 @@ pub async fn destroy(
     }
 
     // 5. Clean local bundle
-    let bundle_dir = project_dir.join(".propel-bundle");
-    if bundle_dir.exists() {
-        std::fs::remove_dir_all(&bundle_dir)?;
+    if plan.bundle {
+        std::fs::remove_dir_all(project_dir.join(".propel-bundle"))?;
         println!("Removed local .propel-bundle/");
     }
 
@@ -183,29 +390,196 @@ pub async fn destroy(
 
     println!();
     println!("Destroy complete.");
+    print_remaining(&found, include_secrets, include_ci);
+
+    Ok(())
+}
 
-    // Show remaining resource hints
-    if !include_secrets && !secrets.is_empty() {
+/// Notes on found resources the flags left alone.
+fn print_remaining(found: &DestroyPlan, include_secrets: bool, include_ci: bool) {
+    if !include_secrets && !found.secrets.is_empty() {
         println!();
         println!(
             "Note: {} secret(s) remain in Secret Manager.",
-            secrets.len()
+            found.secrets.len()
         );
         println!("  To delete them: propel destroy --include-secrets");
     }
 
-    if !include_ci && Path::new(ci::WORKFLOW_PATH).exists() {
+    if !include_ci && found.ci {
         println!();
         println!("Note: CI/CD resources remain (WIF, Service Account, GitHub Secrets, workflow).");
         println!("  To delete them: propel destroy --include-ci");
     }
+}
 
-    Ok(())
+/// One line per foreign principal / provider, for the shared-resources prompt.
+fn shared_usage_listing(usage: &SharedCiUsage) -> String {
+    let mut out = String::new();
+    for principal in &usage.other_principals {
+        // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+        let _ = writeln!(out, "  - SA binding: {principal}");
+    }
+    for provider in &usage.other_providers {
+        // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+        let _ = writeln!(out, "  - WIF provider: {provider}");
+    }
+    out
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::commands::scripted_gcloud::{Reply, ScriptedGcloud};
+
+    const MISSING_SERVICE: Reply =
+        Err("ERROR: (gcloud.run.services.describe) Cannot find service [api]");
+    const MISSING_REPO: Reply =
+        Err("ERROR: (gcloud.artifacts.docker.images.list) NOT_FOUND: Repository not found");
+
+    async fn discover(
+        script: Vec<(&'static str, Reply)>,
+        config: &PropelConfig,
+        project_dir: &Path,
+    ) -> DestroyPlan {
+        let client = GcloudClient::with_executor(ScriptedGcloud::new(script));
+        DestroyPlan::discover(
+            &client,
+            config,
+            "proj",
+            "api",
+            "us-central1",
+            "us-central1-docker.pkg.dev/proj/propel/api",
+            project_dir,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn never_deployed_project_has_nothing_to_destroy() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let plan = discover(
+            vec![
+                ("describe", MISSING_SERVICE),
+                ("images", MISSING_REPO),
+                ("secrets", Ok("")),
+            ],
+            &PropelConfig::default(),
+            tmp.path(),
+        )
+        .await;
+
+        assert_eq!(plan, DestroyPlan::default());
+        assert!(plan.selected(true, true).is_empty());
+    }
+
+    #[tokio::test]
+    async fn partial_deploy_plans_only_what_exists() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(tmp.path().join(".propel-bundle")).unwrap();
+        let plan = discover(
+            vec![
+                ("describe", MISSING_SERVICE),
+                ("images", Ok("sha256:5f1c2a0de7b1\n")),
+                ("secrets", Ok("API_KEY\n")),
+            ],
+            &PropelConfig::default(),
+            tmp.path(),
+        )
+        .await;
+
+        let selected = plan.selected(false, true);
+        assert_eq!(
+            selected,
+            DestroyPlan {
+                image: true,
+                bundle: true,
+                ..DestroyPlan::default()
+            }
+        );
+        assert_eq!(
+            selected.summary("api", "us-central1", &[]),
+            [
+                "  - Container images in Artifact Registry",
+                "  - Local .propel-bundle/"
+            ]
+        );
+        // The secret stays, and destroy notes it.
+        assert_eq!(plan.secrets, ["API_KEY"]);
+    }
+
+    #[tokio::test]
+    async fn full_deploy_plans_everything_the_flags_select() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let workflow = tmp.path().join(ci::WORKFLOW_PATH);
+        std::fs::create_dir_all(workflow.parent().unwrap()).unwrap();
+        std::fs::write(&workflow, "on: push\n").unwrap();
+        let mut config = PropelConfig::default();
+        config.project.secret_prefix = Some("staging_".to_owned());
+        let plan = discover(
+            vec![
+                ("describe", Ok("api\n")),
+                ("images", Ok("sha256:5f1c2a0de7b1\n")),
+                ("secrets", Ok("staging_API_KEY\nprod_API_KEY\n")),
+            ],
+            &config,
+            tmp.path(),
+        )
+        .await;
+
+        let selected = plan.selected(true, true);
+        assert_eq!(
+            selected,
+            DestroyPlan {
+                service: true,
+                image: true,
+                secrets: vec!["staging_API_KEY".to_owned()],
+                ci: true,
+                bundle: false,
+            }
+        );
+        assert_eq!(
+            selected.summary("api", "us-central1", &["Service Account 'ci'".to_owned()]),
+            [
+                "  - Cloud Run service 'api' in us-central1",
+                "  - Container images in Artifact Registry",
+                "  - 1 secret(s) from Secret Manager:",
+                "      stagi***",
+                "  - Service Account 'ci'",
+            ]
+        );
+        assert!(!plan.selected(false, false).ci);
+    }
+
+    #[tokio::test]
+    async fn failed_probe_keeps_the_resource_in_the_plan() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let plan = discover(
+            vec![
+                ("describe", Err("PERMISSION_DENIED: run.services.get")),
+                ("images", MISSING_REPO),
+                ("secrets", Ok("")),
+            ],
+            &PropelConfig::default(),
+            tmp.path(),
+        )
+        .await;
+
+        assert!(plan.service);
+        assert!(!plan.image);
+    }
+
+    #[test]
+    fn shared_usage_listing_names_principals_and_providers() {
+        let usage = SharedCiUsage {
+            other_principals: vec!["principalSet://x/attribute.repository/acme/other".to_owned()],
+            other_providers: vec!["gitlab".to_owned()],
+        };
+        assert_eq!(
+            shared_usage_listing(&usage),
+            "  - SA binding: principalSet://x/attribute.repository/acme/other\n  - WIF provider: gitlab\n"
+        );
+    }
 
     #[test]
     fn mask_name_ascii_long() {
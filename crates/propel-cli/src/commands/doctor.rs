@@ -1,8 +1,34 @@
-use propel_cloud::GcloudClient;
+//! `propel doctor`.
+//!
+//! Every row carries a stable id, included in `--json` output and the MCP
+//! `doctor` tool's structured result so scripts can react to a specific
+//! failure. Labels may be reworded; ids may not. A new check must take a
+//! new id in [`DoctorReport::checks`](propel_cloud::DoctorReport::checks)
+//! and be added here.
+//!
+//! | id                            | row                                                              |
+//! |-------------------------------|------------------------------------------------------------------|
+//! | `gcloud.installed`            | gcloud CLI                                                       |
+//! | `gcloud.components`           | gcloud components                                                |
+//! | `gcp.account`                 | Authentication                                                   |
+//! | `gcp.project`                 | GCP Project                                                      |
+//! | `gcp.billing`                 | Billing                                                          |
+//! | `api.cloudbuild`              | Cloud Build API                                                  |
+//! | `api.run`                     | Cloud Run API                                                    |
+//! | `api.secretmanager`           | Secret Manager API                                               |
+//! | `api.artifactregistry`        | Artifact Registry API                                            |
+//! | `api.sqladmin`                | Cloud SQL Admin API (only with `[cloud_run].cloudsql_instances`) |
+//! | `iam.permissions`             | IAM permissions                                                  |
+//! | `iam.runtime_service_account` | Service account (only with `[cloud_run].service_account`)        |
+//! | `config.file`                 | propel.toml                                                      |
+//! | `rust.toolchain`              | Rust toolchain                                                   |
+//! | `git.installed`               | git                                                              |
+
+use super::CliError;
 use propel_core::PropelConfig;
 use std::path::Path;
 
-pub async fn doctor() -> anyhow::Result<()> {
+pub async fn doctor(skip_iam_check: bool, no_cache: bool, json: bool) -> Result<(), CliError> {
     let config = PropelConfig::load(Path::new("."));
     let project_id = config
         .as_ref()
@@ -10,22 +36,43 @@ pub async fn doctor() -> anyhow::Result<()> {
         .ok()
         .and_then(|c| c.project.gcp_project_id.as_deref());
 
-    let client = GcloudClient::new();
-    let mut report = client.doctor(project_id).await;
+    let client = super::doctor_client(Path::new("."), project_id, no_cache);
+    let required_components = match &config {
+        Ok(config) => super::required_components(config),
+        Err(_) => Vec::new(),
+    };
+    let mut report = client.doctor(project_id, &required_components).await;
+    report.iam =
+        Some(super::iam::iam_doctor_check(&client, &report, project_id, skip_iam_check).await);
 
-    // Config file check
-    let config_exists = Path::new("propel.toml").exists();
-    if config_exists {
-        report.config_file = propel_cloud::CheckResult::ok("Found");
-    } else {
-        report.config_file = propel_cloud::CheckResult::fail("Not found");
+    super::local_doctor_checks(&mut report, Path::new("."));
+    report
+        .notes
+        .extend(super::deprecated_sdk_note(Path::new(".")));
+    if let Ok(config) = &config {
+        report.runtime_service_account =
+            super::iam::runtime_sa_doctor_check(&client, &report, project_id, config).await;
+        report.cloud_sql_api =
+            super::cloudsql::cloud_sql_doctor_check(&client, &report, project_id, config).await;
+        report.notes.extend(
+            super::config_warnings(config, Path::new("."))
+                .iter()
+                .map(ToString::to_string),
+        );
+        report.notes.extend(super::private_service_notice(config));
     }
 
-    println!();
-    println!("{report}");
+    if json {
+        println!("{:#}", report.to_json());
+    } else {
+        println!();
+        println!("{report}");
+    }
 
     if !report.all_passed() {
-        anyhow::bail!("some checks failed — see above for details");
+        return Err(CliError::Preflight(anyhow::anyhow!(
+            "some checks failed — see above for details"
+        )));
     }
 
     Ok(())
@@ -1,18 +1,28 @@
-use propel_build::dockerfile::DockerfileGenerator;
-use propel_core::{CargoProject, PropelConfig};
+use super::CliError;
+use super::ProjectContext;
+use propel_build::CargoConfig;
 use std::path::PathBuf;
 
-pub async fn eject() -> anyhow::Result<()> {
+pub async fn eject() -> Result<(), CliError> {
     let project_dir = PathBuf::from(".");
-    let config = PropelConfig::load(&project_dir)?;
-    let project = CargoProject::discover(&project_dir)?;
+    let ProjectContext {
+        config, project, ..
+    } = ProjectContext::load(&project_dir)?;
 
-    let generator = DockerfileGenerator::new(&config.build, &project, config.cloud_run.port);
-    let dockerfile = generator.render();
+    let cargo_config = CargoConfig::detect(super::bundle_source(&project, &project_dir))?;
+    let dockerfile = super::render_dockerfile(&config, &project, cargo_config.as_ref())?;
 
     propel_build::eject::eject(&project_dir, &dockerfile)?;
 
+    if config.build.env.values().any(|v| v.contains("{{")) {
+        println!(
+            "Warning: [build.env] templates such as {{{{commit}}}} are written to the \
+             ejected Dockerfile literally — deploy does not resolve them there."
+        );
+    }
+
     println!("Ejected build config to .propel/Dockerfile");
     println!("You can now edit it directly. propel deploy will use this file.");
+    println!("Commit .propel/ so deploys from CI use it too (see .propel/README.md).");
     Ok(())
 }
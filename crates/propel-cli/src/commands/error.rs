@@ -0,0 +1,194 @@
+//! Errors returned by `propel` commands, and the exit code each maps to.
+//!
+//! Wrapper scripts branch on the exit code, so the codes are part of the
+//! CLI's interface: add kinds, but do not renumber them.
+
+use std::process::ExitCode;
+
+use propel_build::bundle::BundleError;
+use propel_cloud::{CloudBuildError, DeployError, PreflightError};
+
+/// Exit code table printed at the end of `propel --help`.
+pub(crate) const EXIT_CODES_HELP: &str = "\
+Exit codes:
+   0  success
+   1  any other error
+   2  usage error, or propel.toml missing, invalid, or incomplete
+   3  pre-flight check failed (gcloud, git, authentication, APIs, permissions)
+   4  Cloud Build failed
+   5  Cloud Run deploy (or another Cloud Run call) failed
+   6  uncommitted changes (use --allow-dirty)
+  10  cancelled at a confirmation prompt";
+
+/// Why a command failed.
+///
+/// Each kind wraps the error the user sees, so the message is unchanged;
+/// only the exit code depends on the kind. Errors that reach a command as
+/// [`anyhow::Error`] are classified by what they wrap (see the
+/// [`From<anyhow::Error>`](#impl-From<Error>-for-CliError) impl).
+#[derive(Debug, thiserror::Error)]
+pub enum CliError {
+    /// `propel.toml` is missing, unparsable, or lacks a required setting.
+    #[error(transparent)]
+    Config(anyhow::Error),
+    #[error(transparent)]
+    Preflight(anyhow::Error),
+    #[error(transparent)]
+    Build(anyhow::Error),
+    #[error(transparent)]
+    Deploy(anyhow::Error),
+    #[error(
+        "uncommitted changes detected.\n\
+         Commit your changes, or use `propel deploy --allow-dirty` to deploy anyway."
+    )]
+    DirtyTree,
+    /// The user declined a confirmation prompt (already reported on stdout).
+    #[error("cancelled")]
+    Cancelled,
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+impl CliError {
+    /// Shorthand for [`CliError::Config`] with a message.
+    pub(crate) fn config(message: impl Into<String>) -> Self {
+        Self::Config(anyhow::Error::msg(message.into()))
+    }
+
+    /// Process exit code, as listed in [`EXIT_CODES_HELP`].
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::Other(_) => 1,
+            Self::Config(_) => 2,
+            Self::Preflight(_) => 3,
+            Self::Build(_) => 4,
+            Self::Deploy(_) => 5,
+            Self::DirtyTree => 6,
+            Self::Cancelled => 10,
+        }
+    }
+
+    /// Print the error the way `main` returning [`anyhow::Result`] did
+    /// (`Error: ...` plus the cause chain) and return its exit code.
+    pub fn report(self) -> ExitCode {
+        match &self {
+            Self::Config(e)
+            | Self::Preflight(e)
+            | Self::Build(e)
+            | Self::Deploy(e)
+            | Self::Other(e) => {
+                eprintln!("Error: {e:?}");
+            }
+            Self::DirtyTree => eprintln!("Error: {self}"),
+            Self::Cancelled => {}
+        }
+        ExitCode::from(self.exit_code())
+    }
+}
+
+impl From<anyhow::Error> for CliError {
+    /// Keep a [`CliError`] that passed through an `anyhow` helper, and
+    /// classify the error types the kinds are named after.
+    fn from(error: anyhow::Error) -> Self {
+        let error = match error.downcast::<CliError>() {
+            Ok(cli) => return cli,
+            Err(error) => error,
+        };
+        if error.is::<propel_core::Error>()
+            || matches!(error.downcast_ref(), Some(BundleError::NotARepo { .. }))
+        {
+            Self::Config(error)
+        } else if error.is::<PreflightError>()
+            || matches!(error.downcast_ref(), Some(BundleError::GitNotFound))
+        {
+            Self::Preflight(error)
+        } else if error.is::<CloudBuildError>() {
+            Self::Build(error)
+        } else if error.is::<DeployError>() {
+            Self::Deploy(error)
+        } else {
+            Self::Other(error)
+        }
+    }
+}
+
+/// `From` impls sending a library error through the `anyhow::Error`
+/// classification, so `?` works on it in commands.
+macro_rules! from_via_anyhow {
+    ($($error:ty),* $(,)?) => {
+        $(
+            impl From<$error> for CliError {
+                fn from(error: $error) -> Self {
+                    anyhow::Error::from(error).into()
+                }
+            }
+        )*
+    };
+}
+
+from_via_anyhow!(
+    std::io::Error,
+    propel_core::Error,
+    propel_core::TemplateError,
+    BundleError,
+    propel_build::cargo_config::CargoConfigError,
+    propel_build::eject::EjectError,
+    propel_build::manifest::ManifestError,
+    PreflightError,
+    CloudBuildError,
+    DeployError,
+    propel_cloud::SecretError,
+    propel_cloud::WifError,
+    propel_cloud::gcloud::GcloudError,
+    propel_cloud::PricingError,
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn cli_error_survives_anyhow_helpers() {
+        let error = anyhow::Error::from(CliError::DirtyTree);
+        assert!(matches!(CliError::from(error), CliError::DirtyTree));
+
+        let error = anyhow::Error::from(CliError::config("missing"));
+        assert_eq!(CliError::from(error).exit_code(), 2);
+    }
+
+    #[test]
+    fn library_errors_are_classified_by_type() {
+        let cases: [(anyhow::Error, u8); 7] = [
+            (PreflightError::NotAuthenticated.into(), 3),
+            (BundleError::GitNotFound.into(), 3),
+            (BundleError::NotARepo { dir: ".".into() }.into(), 2),
+            (
+                BundleError::GitFailed {
+                    detail: "not a git repository".to_owned(),
+                }
+                .into(),
+                1,
+            ),
+            (CloudBuildError::InvalidPath("bundle".into()).into(), 4),
+            (
+                anyhow::Error::from(PreflightError::GcloudNotInstalled).context("checking"),
+                3,
+            ),
+            (anyhow::anyhow!("something else"), 1),
+        ];
+        for (error, code) in cases {
+            let message = format!("{error:#}");
+            assert_eq!(CliError::from(error).exit_code(), code, "{message}");
+        }
+    }
+
+    #[test]
+    fn classification_keeps_the_message() {
+        let error: CliError = Err::<(), _>(PreflightError::NotAuthenticated)
+            .context("pre-flight")
+            .unwrap_err()
+            .into();
+        assert!(error.to_string().starts_with("pre-flight"), "{error}");
+    }
+}
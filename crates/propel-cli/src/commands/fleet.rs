@@ -0,0 +1,93 @@
+use super::CliError;
+use propel_cloud::{GcloudClient, ManagedService};
+use propel_core::PropelConfig;
+use std::path::PathBuf;
+
+/// List the propel-managed Cloud Run services in the configured project.
+pub async fn fleet_list() -> Result<(), CliError> {
+    let project_dir = PathBuf::from(".");
+    let config = PropelConfig::load(&project_dir)?;
+    let project_id = super::require_gcp_project_id(&config)?;
+
+    let client = GcloudClient::new();
+    let services = client.list_managed_services(project_id).await?;
+    if services.is_empty() {
+        println!("No propel-managed Cloud Run services in {project_id}");
+        return Ok(());
+    }
+    print!("{}", fleet_table(&services));
+    Ok(())
+}
+
+/// Render services as aligned columns; missing labels show as `-`.
+fn fleet_table(services: &[ManagedService]) -> String {
+    let header = ["SERVICE", "REGION", "PROPEL", "CONFIG HASH"];
+    let rows: Vec<[&str; 4]> = services
+        .iter()
+        .map(|s| {
+            [
+                s.name.as_str(),
+                or_dash(&s.region),
+                or_dash(&s.propel_version),
+                or_dash(&s.config_hash),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let mut out = String::new();
+    for row in std::iter::once(&header).chain(&rows) {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+fn or_dash(value: &Option<String>) -> &str {
+    match value {
+        Some(v) => v,
+        None => "-",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(name: &str, version: Option<&str>) -> ManagedService {
+        ManagedService {
+            name: name.to_owned(),
+            region: Some("us-central1".to_owned()),
+            url: None,
+            propel_version: version.map(str::to_owned),
+            config_hash: version.map(|_| "0123456789ab".to_owned()),
+            image_size_bytes: None,
+        }
+    }
+
+    #[test]
+    fn fleet_table_aligns_columns_and_marks_missing_labels() {
+        let table = fleet_table(&[
+            service("billing", Some("0.4.3")),
+            service("legacy-worker", None),
+        ]);
+        assert_eq!(
+            table,
+            "SERVICE        REGION       PROPEL  CONFIG HASH\n\
+             billing        us-central1  0.4.3   0123456789ab\n\
+             legacy-worker  us-central1  -       -\n"
+        );
+    }
+}
@@ -0,0 +1,440 @@
+use super::CliError;
+use super::ProjectContext;
+use chrono::{DateTime, SecondsFormat, Utc};
+use propel_cloud::{GcloudClient, RevisionInfo};
+use propel_core::{CanaryConfig, DeployEvent, DeployRecord, ProjectConfig};
+use std::path::PathBuf;
+
+/// List the service's deploys, newest first: when each revision was
+/// created, which account deployed it, and the traffic it serves.
+///
+/// The revisions are read from Cloud Run, which records the deploying
+/// account on every revision. Canary promotes and aborts leave no revision
+/// behind, so they are listed after it from the local deploy history.
+pub async fn history(limit: usize) -> Result<(), CliError> {
+    let project_dir = PathBuf::from(".");
+    let ctx = ProjectContext::load(&project_dir)?;
+    let project_id = ctx.gcp_project_id()?;
+    let service_name = ctx.service_name();
+    let region = ctx.region();
+
+    let client = GcloudClient::new();
+    let account =
+        super::announce_target(&client, &ctx.config, project_id, None, service_name).await?;
+    let revisions = super::service_revisions(&client, project_id, service_name, region).await?;
+
+    match revisions.iter().find_map(|r| r.project_number.as_deref()) {
+        Some(number) => {
+            println!("Deploys of {service_name} ({region}, project {project_id} #{number}):")
+        }
+        None => println!("Deploys of {service_name} ({region}, project {project_id}):"),
+    }
+    print!(
+        "{}",
+        history_table(
+            &revisions,
+            limit,
+            account.as_deref(),
+            &ctx.config.project,
+            Utc::now()
+        )
+    );
+
+    let records = ctx.history().records()?;
+    let recorded = recorded_table(&records, service_name, region, limit, Utc::now());
+    if !recorded.is_empty() {
+        println!();
+        println!("Recorded by propel in {}:", propel_core::HISTORY_DIR);
+        print!("{recorded}");
+    }
+    Ok(())
+}
+
+/// A [`DeployRecord`] of `event` on the service by `account`, stamped
+/// now.
+pub(crate) fn new_record(
+    ctx: &ProjectContext,
+    event: DeployEvent,
+    account: Option<&str>,
+) -> DeployRecord {
+    DeployRecord {
+        account: account.map(str::to_owned),
+        ..DeployRecord::new(
+            Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            event,
+            ctx.service_name(),
+            ctx.region(),
+        )
+    }
+}
+
+/// The record of a deploy of `image_tag` by `account` to the project
+/// preflight found as `project_number`: a started canary when it went out
+/// under `canary`, else a plain deploy.
+pub(crate) fn deploy_record(
+    ctx: &ProjectContext,
+    image_tag: &str,
+    canary: Option<CanaryConfig>,
+    account: Option<&str>,
+    project_number: Option<&str>,
+) -> DeployRecord {
+    let record = match canary {
+        Some(canary) => DeployRecord {
+            percent: Some(canary.percent),
+            ..new_record(ctx, DeployEvent::CanaryStarted, account)
+        },
+        None => new_record(ctx, DeployEvent::Deploy, account),
+    };
+    DeployRecord {
+        image: Some(image_tag.to_owned()),
+        project_number: project_number.map(str::to_owned),
+        ..record
+    }
+}
+
+/// Append `record` to the project's deploy history. The command has done
+/// its work by then, so a failed write is passed to `say` as a warning.
+pub(crate) fn record(ctx: &ProjectContext, record: &DeployRecord, mut say: impl FnMut(String)) {
+    if let Err(e) = ctx.history().append(record) {
+        say(format!("Warning: could not update the deploy history: {e}"));
+    }
+}
+
+/// `Last deployed by bob@corp.com 2 days ago (svc-00004)` for `propel
+/// status`, from the newest revision.
+pub(crate) fn last_deploy_line(revisions: &[RevisionInfo], now: DateTime<Utc>) -> String {
+    let Some(latest) = revisions.first() else {
+        return "Last deploy: none".to_owned();
+    };
+    let when = ago(&latest.created, now);
+    match &latest.deployed_by {
+        Some(account) => format!("Last deployed by {account} {when} ({})", latest.name),
+        None => format!(
+            "Last deployed {when} ({}), by an unknown account",
+            latest.name
+        ),
+    }
+}
+
+/// The newest `limit` revisions, one per line. The deploying account is
+/// marked when it is the active one, and flagged when it does not satisfy
+/// `[project].expected_account_suffix`.
+fn history_table(
+    revisions: &[RevisionInfo],
+    limit: usize,
+    active_account: Option<&str>,
+    project: &ProjectConfig,
+    now: DateTime<Utc>,
+) -> String {
+    let shown = &revisions[..revisions.len().min(limit)];
+    let rows: Vec<[String; 4]> = shown
+        .iter()
+        .map(|revision| {
+            let deployer = match (
+                revision.deployed_by.as_deref(),
+                project.expected_account_suffix.as_deref(),
+            ) {
+                (None, _) => "unknown".to_owned(),
+                (Some(account), Some(suffix)) if !project.account_matches(account) => {
+                    format!("{account} (not {suffix})")
+                }
+                (Some(account), _)
+                    if active_account.is_some_and(|a| a.eq_ignore_ascii_case(account)) =>
+                {
+                    format!("{account} (you)")
+                }
+                (Some(account), _) => account.to_owned(),
+            };
+            let status = match (revision.traffic_percent, revision.ready) {
+                (0, true) => String::new(),
+                (0, false) => "not ready".to_owned(),
+                (percent, _) => format!("{percent}% traffic"),
+            };
+            [
+                revision.name.clone(),
+                ago(&revision.created, now),
+                deployer,
+                status,
+            ]
+        })
+        .collect();
+
+    let width = |column: usize| {
+        rows.iter()
+            .map(|row| row[column].len())
+            .max()
+            // arch-lint: allow(no-silent-result-drop) reason="Option: no revisions means an empty table"
+            .unwrap_or_default()
+    };
+    let (name_width, age_width, deployer_width) = (width(0), width(1), width(2));
+    let mut out = String::new();
+    for [name, age, deployer, status] in &rows {
+        let line =
+            format!("  {name:name_width$}  {age:age_width$}  {deployer:deployer_width$}  {status}");
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    if revisions.len() > limit {
+        out.push_str(&format!("  ... {} older\n", revisions.len() - limit));
+    }
+    out
+}
+
+/// The newest `limit` local records of `service_name` in `region`, one
+/// per line with the account and project number that made them; empty
+/// when there are none.
+fn recorded_table(
+    records: &[DeployRecord],
+    service_name: &str,
+    region: &str,
+    limit: usize,
+    now: DateTime<Utc>,
+) -> String {
+    let rows: Vec<[String; 4]> = records
+        .iter()
+        .rev()
+        .filter(|record| record.service == service_name && record.region == region)
+        .take(limit)
+        .map(|record| {
+            let account = match &record.account {
+                Some(account) => account.clone(),
+                None => "unknown".to_owned(),
+            };
+            let project = match &record.project_number {
+                Some(number) => format!("#{number}"),
+                None => String::new(),
+            };
+            [ago(&record.at, now), account, project, describe(record)]
+        })
+        .collect();
+
+    let width = |column: usize| {
+        rows.iter()
+            .map(|row| row[column].len())
+            .max()
+            // arch-lint: allow(no-silent-result-drop) reason="Option: no records means an empty table"
+            .unwrap_or_default()
+    };
+    let (age_width, account_width, project_width) = (width(0), width(1), width(2));
+    let mut out = String::new();
+    for [age, account, project, what] in &rows {
+        let line = format!(
+            "  {age:age_width$}  {account:account_width$}  {project:project_width$}  {what}"
+        );
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+/// `started a canary of img:abc123 (10% of traffic)`
+fn describe(record: &DeployRecord) -> String {
+    let field = |value: &Option<String>| match value {
+        Some(value) => value.clone(),
+        None => "unknown".to_owned(),
+    };
+    match record.event {
+        DeployEvent::Deploy => format!("deployed {}", field(&record.image)),
+        DeployEvent::CanaryStarted => match record.percent {
+            Some(percent) => format!(
+                "started a canary of {} ({percent}% of traffic)",
+                field(&record.image)
+            ),
+            None => format!("started a canary of {}", field(&record.image)),
+        },
+        DeployEvent::CanaryPromoted => {
+            format!("promoted canary {} to all traffic", field(&record.revision))
+        }
+        DeployEvent::CanaryAborted => format!(
+            "aborted canary {}, back to {}",
+            field(&record.revision),
+            field(&record.stable_revision)
+        ),
+    }
+}
+
+/// `3 hours ago` for an RFC 3339 timestamp, or the timestamp itself when
+/// it does not parse.
+fn ago(timestamp: &str, now: DateTime<Utc>) -> String {
+    let Ok(then) = DateTime::parse_from_rfc3339(timestamp) else {
+        return timestamp.to_owned();
+    };
+    let seconds = (now - then.with_timezone(&Utc)).num_seconds();
+    let (count, unit) = match seconds {
+        ..60 => return "just now".to_owned(),
+        60..3_600 => (seconds / 60, "minute"),
+        3_600..172_800 => (seconds / 3_600, "hour"),
+        _ => (seconds / 86_400, "day"),
+    };
+    let plural = if count == 1 { "" } else { "s" };
+    format!("{count} {unit}{plural} ago")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2026-03-10T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn record(at: &str, event: DeployEvent, service: &str) -> DeployRecord {
+        DeployRecord::new(at.to_owned(), event, service, "us-central1")
+    }
+
+    fn revision(name: &str, created: &str, deployed_by: Option<&str>) -> RevisionInfo {
+        RevisionInfo {
+            name: name.to_owned(),
+            created: created.to_owned(),
+            traffic_percent: 0,
+            image: "img:latest".to_owned(),
+            ready: true,
+            deployed_by: deployed_by.map(str::to_owned),
+            project_number: Some("123456789012".to_owned()),
+        }
+    }
+
+    #[test]
+    fn ago_picks_the_largest_whole_unit() {
+        for (created, expected) in [
+            ("2026-03-10T11:59:30Z", "just now"),
+            ("2026-03-10T11:59:00Z", "1 minute ago"),
+            ("2026-03-10T09:00:00Z", "3 hours ago"),
+            ("2026-03-09T00:00:00Z", "36 hours ago"),
+            ("2026-03-08T12:00:00Z", "2 days ago"),
+            ("2026-03-10T13:00:00+02:00", "1 hour ago"),
+            ("not a time", "not a time"),
+        ] {
+            assert_eq!(ago(created, now()), expected, "{created}");
+        }
+    }
+
+    #[test]
+    fn last_deploy_names_the_newest_revision_and_its_account() {
+        let revisions = [
+            revision("svc-00002", "2026-03-08T12:00:00Z", Some("bob@corp.com")),
+            revision("svc-00001", "2026-03-01T00:00:00Z", Some("alice@corp.com")),
+        ];
+        assert_eq!(
+            last_deploy_line(&revisions, now()),
+            "Last deployed by bob@corp.com 2 days ago (svc-00002)"
+        );
+
+        let unknown = [revision("svc-00003", "2026-03-10T09:00:00Z", None)];
+        assert_eq!(
+            last_deploy_line(&unknown, now()),
+            "Last deployed 3 hours ago (svc-00003), by an unknown account"
+        );
+        assert_eq!(last_deploy_line(&[], now()), "Last deploy: none");
+    }
+
+    #[test]
+    fn table_marks_the_active_account_and_unexpected_ones() {
+        let mut revisions = vec![
+            revision(
+                "svc-00004",
+                "2026-03-10T09:00:00Z",
+                Some("deploy@proj.iam.gserviceaccount.com"),
+            ),
+            revision("svc-00003", "2026-03-08T12:00:00Z", Some("Bob@corp.com")),
+            revision("svc-00002", "2026-03-05T12:00:00Z", None),
+            revision("svc-00001", "2026-03-01T12:00:00Z", Some("alice@corp.com")),
+        ];
+        revisions[0].traffic_percent = 100;
+        revisions[2].ready = false;
+        let project = ProjectConfig {
+            expected_account_suffix: Some("@corp.com".to_owned()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            history_table(&revisions, 3, Some("bob@corp.com"), &project, now()),
+            "  svc-00004  3 hours ago  deploy@proj.iam.gserviceaccount.com (not @corp.com)  100% traffic\n  \
+             svc-00003  2 days ago   Bob@corp.com (you)\n  \
+             svc-00002  5 days ago   unknown                                              not ready\n  \
+             ... 1 older\n"
+        );
+    }
+
+    #[test]
+    fn deploy_record_is_a_canary_start_under_canary() {
+        let project = propel_core::CargoProject {
+            name: "svc".to_owned(),
+            version: "0.1.0".to_owned(),
+            manifest_path: PathBuf::from("Cargo.toml"),
+            package_dir: PathBuf::from("."),
+            workspace_root: PathBuf::from("."),
+            binaries: Vec::new(),
+            default_binary: "svc".to_owned(),
+        };
+        let ctx = ProjectContext::new(
+            std::path::Path::new("."),
+            propel_core::PropelConfig::default(),
+            project,
+        );
+
+        let plain = deploy_record(
+            &ctx,
+            "img:abc123",
+            None,
+            Some("bob@corp.com"),
+            Some("123456789012"),
+        );
+        assert_eq!(plain.event, DeployEvent::Deploy);
+        assert_eq!((plain.service.as_str(), plain.percent), ("svc", None));
+        assert_eq!(plain.image.as_deref(), Some("img:abc123"));
+        assert_eq!(plain.account.as_deref(), Some("bob@corp.com"));
+        assert_eq!(plain.project_number.as_deref(), Some("123456789012"));
+        assert!(
+            DateTime::parse_from_rfc3339(&plain.at).is_ok(),
+            "{}",
+            plain.at
+        );
+
+        let canary = deploy_record(
+            &ctx,
+            "img:abc123",
+            Some(CanaryConfig { percent: 10 }),
+            None,
+            None,
+        );
+        assert_eq!(canary.event, DeployEvent::CanaryStarted);
+        assert_eq!(canary.percent, Some(10));
+    }
+
+    #[test]
+    fn recorded_table_lists_this_service_newest_first() {
+        let records = [
+            DeployRecord {
+                image: Some("img:aaa".to_owned()),
+                ..record("2026-03-05T12:00:00Z", DeployEvent::Deploy, "svc")
+            },
+            DeployRecord {
+                image: Some("img:bbb".to_owned()),
+                percent: Some(10),
+                account: Some("bob@corp.com".to_owned()),
+                project_number: Some("123456789012".to_owned()),
+                ..record("2026-03-08T12:00:00Z", DeployEvent::CanaryStarted, "svc")
+            },
+            record("2026-03-09T12:00:00Z", DeployEvent::Deploy, "other-svc"),
+            DeployRecord {
+                revision: Some("svc-00002".to_owned()),
+                stable_revision: Some("svc-00001".to_owned()),
+                account: Some("alice@corp.com".to_owned()),
+                ..record("2026-03-10T09:00:00Z", DeployEvent::CanaryAborted, "svc")
+            },
+        ];
+
+        assert_eq!(
+            recorded_table(&records, "svc", "us-central1", 2, now()),
+            "  3 hours ago  alice@corp.com                 aborted canary svc-00002, back to svc-00001\n  \
+             2 days ago   bob@corp.com    #123456789012  started a canary of img:bbb (10% of traffic)\n"
+        );
+        assert_eq!(
+            recorded_table(&records, "svc", "europe-west1", 5, now()),
+            ""
+        );
+    }
+}
@@ -0,0 +1,224 @@
+//! `propel iam grant-invoker`, and the IAM checks deploy and doctor run:
+//! the deploy permissions of the active account and the runtime service
+//! account.
+
+use super::CliError;
+use super::ProjectContext;
+use propel_cloud::{
+    CheckResult, DoctorReport, GcloudClient, PermissionCheck, PreflightError, RUN_INVOKER_ROLE,
+    account_member,
+};
+use propel_core::PropelConfig;
+use std::fmt::Write;
+use std::path::PathBuf;
+
+/// Let `member` call the deployed service, e.g. a service account of
+/// another service when `[cloud_run] allow_unauthenticated = false`.
+pub async fn iam_grant_invoker(member: &str) -> Result<(), CliError> {
+    let member = invoker_member(member)?;
+    let project_dir = PathBuf::from(".");
+    let ctx = ProjectContext::load(&project_dir)?;
+    let project_id = ctx.gcp_project_id()?;
+    let service_name = ctx.service_name();
+    let region = ctx.region();
+
+    let client = GcloudClient::new();
+    super::announce_target(&client, &ctx.config, project_id, None, service_name).await?;
+
+    println!("Granting {RUN_INVOKER_ROLE} on {service_name} to {member}...");
+    client
+        .grant_invoker(service_name, project_id, region, &member)
+        .await?;
+    println!("  Granted.");
+    if ctx.config.cloud_run.allow_unauthenticated {
+        println!(
+            "Note: the service also takes unauthenticated requests; set \
+             [cloud_run] allow_unauthenticated = false and redeploy to require this role"
+        );
+    }
+    Ok(())
+}
+
+/// IAM member for `member`: as given when it has a `type:` prefix, else
+/// `serviceAccount:` for a service account address and `user:` for any
+/// other email.
+fn invoker_member(member: &str) -> Result<String, CliError> {
+    let member = member.trim();
+    if member.contains(':') {
+        return Ok(member.to_owned());
+    }
+    if member.contains('@') {
+        return Ok(account_member(member));
+    }
+    Err(CliError::config(format!(
+        "{member:?} is not an IAM member; pass an email or a prefixed member such as \
+         serviceAccount:caller@my-project.iam.gserviceaccount.com or group:team@example.com"
+    )))
+}
+
+/// Permission secret discovery needs, one of [`propel_cloud::DEPLOY_PERMISSIONS`].
+const SECRET_LIST_PERMISSION: &str = "secretmanager.secrets.list";
+
+/// Verify that the active account holds the permissions deploy needs,
+/// before anything slow starts.
+///
+/// Returns a warning instead of failing when permissions look missing but
+/// the project grants roles in ways the check cannot evaluate. Listing
+/// secrets is not needed with `[cloud_run].inject_secrets = false`.
+pub(crate) async fn verify_deploy_permissions(
+    client: &GcloudClient,
+    config: &PropelConfig,
+    project_id: &str,
+    account: Option<&str>,
+) -> anyhow::Result<Option<String>> {
+    let Some(account) = account else {
+        return Ok(None);
+    };
+    let mut check = client.deploy_permissions(project_id, account).await?;
+    if !config.cloud_run.inject_secrets {
+        check
+            .missing
+            .retain(|p| p.permission != SECRET_LIST_PERMISSION);
+    }
+    if check.is_complete() {
+        return Ok(None);
+    }
+    if check.inconclusive {
+        return Ok(Some(format!(
+            "Warning: could not confirm that {account} has {} — the project grants \
+             roles through groups or custom roles; continuing",
+            permission_names(&check)
+        )));
+    }
+    Err(PreflightError::MissingPermissions {
+        account: account.to_owned(),
+        project_id: project_id.to_owned(),
+        missing: check.missing,
+    }
+    .into())
+}
+
+/// The doctor's IAM permissions row.
+///
+/// Needs the account and project rows to have passed; `skip` reflects
+/// `--skip-iam-check`.
+pub(crate) async fn iam_doctor_check(
+    client: &GcloudClient,
+    report: &DoctorReport,
+    project_id: Option<&str>,
+    skip: bool,
+) -> CheckResult {
+    if skip {
+        return CheckResult::skipped("--skip-iam-check");
+    }
+    let Some(project_id) = project_id.filter(|_| report.project.passed && report.account.passed)
+    else {
+        return CheckResult::skipped("needs an active account and accessible project");
+    };
+    match client
+        .deploy_permissions(project_id, &report.account.detail)
+        .await
+    {
+        Ok(check) => permission_check_result(&check),
+        Err(e) => CheckResult::fail(&e.to_string()),
+    }
+}
+
+/// Doctor row checking that `[cloud_run].service_account` exists, or
+/// `None` when none is configured.
+pub(crate) async fn runtime_sa_doctor_check(
+    client: &GcloudClient,
+    report: &DoctorReport,
+    project_id: Option<&str>,
+    config: &PropelConfig,
+) -> Option<CheckResult> {
+    let email = config.cloud_run.service_account.as_deref()?;
+    let Some(project_id) = project_id.filter(|_| report.project.passed && report.account.passed)
+    else {
+        return Some(CheckResult::skipped(
+            "needs an active account and accessible project",
+        ));
+    };
+    Some(
+        match client.service_account_exists(project_id, email).await {
+            Ok(true) => CheckResult::ok(email),
+            Ok(false) => CheckResult::fail(&format!(
+                "{email} not found in {project_id} ([cloud_run].service_account)"
+            )),
+            Err(e) => CheckResult::fail(&format!("{e}")),
+        },
+    )
+}
+
+/// Doctor row for a [`PermissionCheck`]: each missing permission with the
+/// roles that grant it.
+fn permission_check_result(check: &PermissionCheck) -> CheckResult {
+    if check.is_complete() {
+        return CheckResult::ok("deploy permissions granted");
+    }
+    let mut detail = String::from("missing:");
+    for permission in &check.missing {
+        // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+        let _ = write!(detail, "\n{permission}");
+    }
+    if check.inconclusive {
+        detail.push_str("\n(may be granted through groups or custom roles)");
+    }
+    CheckResult::fail(&detail)
+}
+
+fn permission_names(check: &PermissionCheck) -> String {
+    check
+        .missing
+        .iter()
+        .map(|p| p.permission)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bare_emails_get_a_member_type() {
+        assert_eq!(
+            invoker_member("caller@proj.iam.gserviceaccount.com").unwrap(),
+            "serviceAccount:caller@proj.iam.gserviceaccount.com"
+        );
+        assert_eq!(
+            invoker_member("alice@example.com").unwrap(),
+            "user:alice@example.com"
+        );
+        assert_eq!(
+            invoker_member("group:team@example.com").unwrap(),
+            "group:team@example.com"
+        );
+    }
+
+    #[test]
+    fn non_members_are_a_config_error() {
+        let err = invoker_member("caller").unwrap_err();
+        assert_eq!(err.exit_code(), 2);
+        assert!(err.to_string().contains("serviceAccount:"), "{err}");
+    }
+
+    #[test]
+    fn permission_check_row_lists_missing_with_roles() {
+        let check = PermissionCheck {
+            missing: vec![propel_cloud::DEPLOY_PERMISSIONS[0]],
+            inconclusive: true,
+        };
+
+        let row = permission_check_result(&check);
+
+        assert!(!row.passed);
+        assert_eq!(
+            row.detail,
+            "missing:\n\
+             run.services.update (grant roles/run.developer or roles/run.admin)\n\
+             (may be granted through groups or custom roles)"
+        );
+        assert!(permission_check_result(&PermissionCheck::default()).passed);
+    }
+}
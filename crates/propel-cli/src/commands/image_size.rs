@@ -0,0 +1,108 @@
+//! The image size line deploy prints after the build, compared with the
+//! size the last deploy recorded.
+
+use propel_build::bundle;
+use propel_cloud::GcloudClient;
+use propel_core::PropelConfig;
+
+/// Look up the size of the image just built and the size the last deploy
+/// recorded, and pass the [`image_size_report`] lines to `say`.
+///
+/// Returns the size for the new revision's labels. The report is advisory,
+/// so a failed lookup is logged and yields `None`.
+pub(crate) async fn report_image_size(
+    client: &GcloudClient,
+    config: &PropelConfig,
+    image_tag: &str,
+    service_name: &str,
+    project_id: &str,
+    region: &str,
+    mut say: impl FnMut(String),
+) -> Option<u64> {
+    let size = match client.image_size(image_tag).await {
+        Ok(Some(size)) => size,
+        Ok(None) => {
+            tracing::debug!(image_tag, "Artifact Registry lists no image size");
+            return None;
+        }
+        // arch-lint: allow(no-error-swallowing) reason="advisory report; the deploy goes on without it"
+        Err(e) => {
+            tracing::debug!(error = %e, "skipping image size report");
+            return None;
+        }
+    };
+    let previous = match client
+        .deployed_image_size(service_name, project_id, region)
+        .await
+    {
+        Ok(previous) => previous,
+        // arch-lint: allow(no-error-swallowing) reason="first deploy, or no earlier size to compare against"
+        Err(e) => {
+            tracing::debug!(error = %e, "no image size from the last deploy");
+            None
+        }
+    };
+    for line in image_size_report(config, size, previous) {
+        say(line);
+    }
+    Some(size)
+}
+
+/// `Image size 84.0 MiB (was 82.0 MiB last deploy)`, plus a warning when
+/// the image is over `[cloud_build].warn_image_mb`.
+fn image_size_report(config: &PropelConfig, size: u64, previous: Option<u64>) -> Vec<String> {
+    let mut lines = vec![match previous {
+        Some(previous) => format!(
+            "Image size {} (was {} last deploy)",
+            bundle::format_size(size),
+            bundle::format_size(previous)
+        ),
+        None => format!("Image size {}", bundle::format_size(size)),
+    }];
+    if let Some(limit) = config.cloud_build.warn_image_bytes()
+        && size > limit
+    {
+        lines.push(format!(
+            "Warning: the image is over [cloud_build].warn_image_mb ({}) — check that the \
+             runtime stage does not copy build output such as target/ (see [build].include)",
+            bundle::format_size(limit)
+        ));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_size_report_compares_with_the_last_deploy() {
+        let config = PropelConfig::default();
+        const MIB: u64 = 1024 * 1024;
+
+        assert_eq!(
+            image_size_report(&config, 84 * MIB, Some(82 * MIB)),
+            ["Image size 84.0 MiB (was 82.0 MiB last deploy)"]
+        );
+        assert_eq!(
+            image_size_report(&config, 84 * MIB, None),
+            ["Image size 84.0 MiB"]
+        );
+    }
+
+    #[test]
+    fn image_size_report_warns_over_the_threshold() {
+        let mut config = PropelConfig::default();
+        config.cloud_build.warn_image_mb = Some(100);
+        const MIB: u64 = 1024 * 1024;
+
+        assert_eq!(image_size_report(&config, 100 * MIB, None).len(), 1);
+        let lines = image_size_report(&config, 900 * MIB, Some(84 * MIB));
+        assert_eq!(lines.len(), 2);
+        assert!(
+            lines[1].contains("warn_image_mb (100.0 MiB)"),
+            "{}",
+            lines[1]
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! `[build].include_check`: runtime directories the source reads that
+//! `[build].include` leaves out of the image.
+
+use propel_build::bundle;
+use propel_core::{CargoProject, PropelConfig};
+use std::path::{Path, PathBuf};
+
+/// Warnings for top-level directories the source reads at runtime that
+/// `[build].include` leaves out of the image, one per directory.
+///
+/// Only directories git tracks count: anything else is not bundled either
+/// way. Nothing is checked without `include`, with `include_check = false`,
+/// or in an ejected project. The scan is advisory, so unreadable sources
+/// are logged and skipped.
+pub(crate) fn unincluded_runtime_dirs(
+    config: &PropelConfig,
+    project: &CargoProject,
+    project_dir: &Path,
+) -> Vec<String> {
+    let build = &config.build;
+    let Some(include) = &build.include else {
+        return Vec::new();
+    };
+    if !build.include_check || propel_build::eject::is_ejected(project_dir) {
+        return Vec::new();
+    }
+
+    let mut references: Vec<(String, String)> = Vec::new();
+    let mut sources = Vec::new();
+    collect_rust_sources(&project.package_dir.join("src"), &mut sources);
+    for path in sources {
+        let source = match std::fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(e) => {
+                tracing::debug!(path = %path.display(), error = %e, "skipping runtime path scan");
+                continue;
+            }
+        };
+        // arch-lint: allow(no-silent-result-drop) reason="display path: fall back to the full path"
+        let shown = path.strip_prefix(&project.package_dir).unwrap_or(&path);
+        for reference in propel_core::scan_source(&source) {
+            if let Some(dir) = propel_core::top_level_entry(&reference.path) {
+                references.push((
+                    dir.to_owned(),
+                    format!("{}:{}", shown.display(), reference.line),
+                ));
+            }
+        }
+    }
+    for (key, dir) in propel_core::env_path_references(&build.env) {
+        references.push((dir, format!("[build.env] {key}")));
+    }
+
+    let mut warned: Vec<String> = Vec::new();
+    let mut warnings = Vec::new();
+    for (dir, location) in references {
+        if warned.contains(&dir)
+            || propel_core::is_included(&dir, include)
+            || !project_dir.join(&dir).is_dir()
+        {
+            continue;
+        }
+        match bundle::is_tracked(project_dir, Path::new(&dir)) {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(e) => {
+                tracing::debug!(dir = %dir, error = %e, "skipping runtime path check");
+                continue;
+            }
+        }
+        warnings.push(format!(
+            "Warning: {location} reads {dir}/ at runtime, but [build].include does not copy \
+             it into the image — add \"{dir}/\" to include, or set [build].include_check = false \
+             if the service does not need it"
+        ));
+        warned.push(dir);
+    }
+    warnings
+}
+
+/// `.rs` files under `dir`, recursively, in a stable order.
+fn collect_rust_sources(dir: &Path, out: &mut Vec<PathBuf>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        // arch-lint: allow(no-error-swallowing) reason="advisory scan: a missing or unreadable directory has nothing to scan"
+        Err(e) => {
+            tracing::debug!(dir = %dir.display(), error = %e, "skipping runtime path scan");
+            return;
+        }
+    };
+    let mut paths: Vec<_> = entries
+        // arch-lint: allow(no-silent-result-drop) reason="advisory scan: an unreadable entry is skipped"
+        .filter_map(|entry| entry.ok())
+        .map(|e| e.path())
+        .collect();
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            collect_rust_sources(&path, out);
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            out.push(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runtime_dirs_missing_from_include_are_reported_once() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let dir = tmp.path();
+        for path in ["src", "templates", "migrations", "untracked"] {
+            std::fs::create_dir_all(dir.join(path)).unwrap();
+        }
+        std::fs::write(dir.join("templates/index.html"), "<html>").unwrap();
+        std::fs::write(dir.join("migrations/0001.sql"), "").unwrap();
+        std::fs::write(
+            dir.join("src/main.rs"),
+            "fn main() {\n\
+             let page = std::fs::read_to_string(\"templates/index.html\");\n\
+             let sql = std::fs::read_dir(\"migrations\");\n\
+             let more = std::fs::read(\"templates/other.html\");\n\
+             let local = std::fs::read(\"untracked/x\");\n\
+             }\n",
+        )
+        .unwrap();
+        let status = std::process::Command::new("git")
+            .args(["init", "-q"])
+            .current_dir(dir)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let status = std::process::Command::new("git")
+            .args(["add", "src", "templates", "migrations"])
+            .current_dir(dir)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let project = CargoProject {
+            name: "app".to_owned(),
+            version: "0.1.0".to_owned(),
+            manifest_path: dir.join("Cargo.toml"),
+            package_dir: dir.to_path_buf(),
+            workspace_root: dir.to_path_buf(),
+            binaries: Vec::new(),
+            default_binary: "app".to_owned(),
+        };
+        let mut config = PropelConfig::default();
+        config.build.include = Some(vec!["migrations/".to_owned()]);
+
+        let warnings = unincluded_runtime_dirs(&config, &project, dir);
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert!(
+            warnings[0].contains("src/main.rs:2 reads templates/"),
+            "{}",
+            warnings[0]
+        );
+
+        config.build.include_check = false;
+        assert!(unincluded_runtime_dirs(&config, &project, dir).is_empty());
+
+        config.build.include_check = true;
+        config.build.include = None;
+        assert!(unincluded_runtime_dirs(&config, &project, dir).is_empty());
+    }
+}
@@ -1,10 +1,34 @@
+//! `propel init`, and with `--guided` the prompts that fill in
+//! `[project].gcp_project_id` and `region`.
+//!
+//! [`choose_target`] asks through [`GuidedInitEnv`], so the prompt flow is
+//! tested with a scripted environment.
+
+use super::CliError;
+use propel_cloud::{GcloudClient, GcpProject};
+use propel_core::{ProjectConfig, PropelConfig};
+use std::io::Write;
 use std::path::Path;
 
-/// Initialize Propel in an existing Rust project.
-pub async fn init_project() -> anyhow::Result<()> {
+/// Initialize Propel in an existing Rust project. With `guided`, also pick
+/// the GCP project and region from what gcloud offers and write them to
+/// propel.toml.
+pub async fn init_project(guided: bool) -> Result<(), CliError> {
+    use std::io::IsTerminal;
+
     // Must be inside a Cargo project
     if !Path::new("Cargo.toml").exists() {
-        anyhow::bail!("Cargo.toml not found. Run this command from a Rust project root.");
+        return Err(anyhow::anyhow!(
+            "Cargo.toml not found. Run this command from a Rust project root."
+        )
+        .into());
+    }
+    if guided && !std::io::stdin().is_terminal() {
+        return Err(CliError::Config(anyhow::anyhow!(
+            "`propel init --guided` needs an interactive terminal. Run `propel init`, then set \
+             gcp_project_id and region under [project] in propel.toml \
+             (`gcloud projects list` and `gcloud run regions list` show the choices)."
+        )));
     }
 
     let mut created = Vec::new();
@@ -14,7 +38,7 @@ pub async fn init_project() -> anyhow::Result<()> {
     if propel_toml_path.exists() {
         eprintln!("propel.toml already exists, skipping");
     } else {
-        std::fs::write(propel_toml_path, super::PROPEL_TOML_TEMPLATE)?;
+        std::fs::write(propel_toml_path, super::template::propel_toml_template())?;
         created.push("propel.toml");
     }
 
@@ -31,6 +55,18 @@ SUPABASE_JWT_SECRET=your-jwt-secret
         created.push(".env.example");
     }
 
+    // .gitignore: keep the deploy staging directory out of git
+    let gitignore_path = Path::new(".gitignore");
+    let existing = if gitignore_path.exists() {
+        std::fs::read_to_string(gitignore_path)?
+    } else {
+        String::new()
+    };
+    if let Some(updated) = super::gitignore_with_propel_entries(&existing) {
+        std::fs::write(gitignore_path, updated)?;
+        created.push(".gitignore entry for .propel-bundle/");
+    }
+
     if created.is_empty() {
         println!("Nothing to create — already initialized.");
     } else {
@@ -39,6 +75,18 @@ SUPABASE_JWT_SECRET=your-jwt-secret
         }
     }
 
+    if guided {
+        let mut env = TerminalInit {
+            client: GcloudClient::new(),
+        };
+        let target = choose_target(&mut env).await?;
+        write_target(Path::new("."), &target)?;
+        println!(
+            "Wrote gcp_project_id = \"{}\" and region = \"{}\" to propel.toml",
+            target.project_id, target.region
+        );
+    }
+
     println!();
     println!("Next steps:");
     println!();
@@ -66,3 +114,344 @@ SUPABASE_JWT_SECRET=your-jwt-secret
 
     Ok(())
 }
+
+/// Where `propel init --guided` points the project.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct InitTarget {
+    pub(crate) project_id: String,
+    pub(crate) region: String,
+}
+
+/// Prompts and GCP queries for [`choose_target`].
+pub(crate) trait GuidedInitEnv {
+    async fn projects(&mut self) -> Result<Vec<GcpProject>, CliError>;
+    async fn billing_enabled(&mut self, project_id: &str) -> Result<bool, CliError>;
+    async fn regions(&mut self, project_id: &str) -> Result<Vec<String>, CliError>;
+    /// Ask for one of `options` by number; an empty answer takes `default`.
+    fn pick(
+        &mut self,
+        prompt: &str,
+        options: &[String],
+        default: Option<usize>,
+    ) -> Result<usize, CliError>;
+    /// Ask a yes/no question; no is the default.
+    fn confirm(&mut self, question: &str) -> Result<bool, CliError>;
+    fn say(&mut self, line: &str);
+}
+
+/// Ask for the GCP project, then the region to deploy to.
+///
+/// A project without billing is picked again unless the user keeps it;
+/// when billing cannot be read, the project is kept with a warning, as
+/// deploy does. The region list defaults to propel's default region.
+pub(crate) async fn choose_target(env: &mut impl GuidedInitEnv) -> Result<InitTarget, CliError> {
+    let projects = env.projects().await?;
+    if projects.is_empty() {
+        return Err(CliError::Preflight(anyhow::anyhow!(
+            "the active gcloud account sees no GCP projects — create one at \
+             https://console.cloud.google.com/projectcreate, or switch accounts with \
+             `gcloud auth login`"
+        )));
+    }
+    let labels: Vec<String> = projects.iter().map(project_label).collect();
+    let project = loop {
+        let project = &projects[env.pick("GCP project", &labels, None)?];
+        match env.billing_enabled(&project.id).await {
+            Ok(true) => break project,
+            Ok(false) => {
+                env.say(&format!(
+                    "Billing is not enabled for {0}, so deploys to it fail. Link a billing \
+                     account: https://console.cloud.google.com/billing/linkedaccount?project={0}",
+                    project.id
+                ));
+                if env.confirm(&format!("Use {} anyway?", project.id))? {
+                    break project;
+                }
+            }
+            // arch-lint: allow(no-error-swallowing) reason="reading billing needs extra permissions; deploy also continues without it"
+            Err(e) => {
+                env.say(&format!(
+                    "Could not check billing for {}: {e:#}",
+                    project.id
+                ));
+                break project;
+            }
+        }
+    };
+
+    let regions = env.regions(&project.id).await?;
+    if regions.is_empty() {
+        return Err(CliError::Preflight(anyhow::anyhow!(
+            "gcloud lists no Cloud Run regions for {}",
+            project.id
+        )));
+    }
+    let default_region = PropelConfig::default().project.region;
+    let default = regions.iter().position(|region| *region == default_region);
+    let region = regions[env.pick("Region", &regions, default)?].clone();
+
+    Ok(InitTarget {
+        project_id: project.id.clone(),
+        region,
+    })
+}
+
+/// `id (name)`, or the ID alone when the name adds nothing.
+fn project_label(project: &GcpProject) -> String {
+    if project.name.is_empty() || project.name == project.id {
+        project.id.clone()
+    } else {
+        format!("{} ({})", project.id, project.name)
+    }
+}
+
+/// Write `target` into `project_dir/propel.toml`, keeping its comments.
+fn write_target(project_dir: &Path, target: &InitTarget) -> propel_core::Result<()> {
+    ProjectConfig::save_gcp_project_id(project_dir, &target.project_id)?;
+    ProjectConfig::save_region(project_dir, &target.region)
+}
+
+/// [`GuidedInitEnv`] against the real gcloud and terminal.
+struct TerminalInit {
+    client: GcloudClient,
+}
+
+impl TerminalInit {
+    /// Read one trimmed line after `prompt`; end of input cancels.
+    fn read_line(prompt: &str) -> Result<String, CliError> {
+        print!("{prompt}");
+        std::io::stdout().flush()?;
+        let mut input = String::new();
+        if std::io::stdin().read_line(&mut input)? == 0 {
+            return Err(CliError::Cancelled);
+        }
+        Ok(input.trim().to_owned())
+    }
+}
+
+impl GuidedInitEnv for TerminalInit {
+    async fn projects(&mut self) -> Result<Vec<GcpProject>, CliError> {
+        Ok(self.client.list_projects().await?)
+    }
+
+    async fn billing_enabled(&mut self, project_id: &str) -> Result<bool, CliError> {
+        Ok(self.client.billing_enabled(project_id).await?)
+    }
+
+    async fn regions(&mut self, project_id: &str) -> Result<Vec<String>, CliError> {
+        Ok(self.client.list_run_regions(project_id).await?)
+    }
+
+    fn pick(
+        &mut self,
+        prompt: &str,
+        options: &[String],
+        default: Option<usize>,
+    ) -> Result<usize, CliError> {
+        println!();
+        println!("{prompt}:");
+        for (i, option) in options.iter().enumerate() {
+            println!("  {:>3}) {option}", i + 1);
+        }
+        let hint = match default {
+            Some(i) => format!("Choose 1-{} [{}]: ", options.len(), i + 1),
+            None => format!("Choose 1-{}: ", options.len()),
+        };
+        loop {
+            let answer = Self::read_line(&hint)?;
+            if answer.is_empty()
+                && let Some(i) = default
+            {
+                return Ok(i);
+            }
+            match answer.parse::<usize>() {
+                Ok(n) if (1..=options.len()).contains(&n) => return Ok(n - 1),
+                _ => println!("Enter a number from 1 to {}.", options.len()),
+            }
+        }
+    }
+
+    fn confirm(&mut self, question: &str) -> Result<bool, CliError> {
+        let answer = Self::read_line(&format!("{question} [y/N] "))?;
+        Ok(matches!(answer.as_str(), "y" | "Y" | "yes" | "YES"))
+    }
+
+    fn say(&mut self, line: &str) {
+        println!("{line}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// A [`GuidedInitEnv`] replaying scripted gcloud answers and choices.
+    #[derive(Default)]
+    struct ScriptedEnv {
+        projects: Vec<GcpProject>,
+        /// Projects whose billing is disabled; `None` fails the check.
+        unbilled: Option<Vec<&'static str>>,
+        regions: Vec<String>,
+        picks: VecDeque<usize>,
+        answers: VecDeque<bool>,
+        /// `(prompt, default)` of each pick.
+        asked: Vec<(String, Option<usize>)>,
+        output: Vec<String>,
+    }
+
+    impl GuidedInitEnv for ScriptedEnv {
+        async fn projects(&mut self) -> Result<Vec<GcpProject>, CliError> {
+            Ok(self.projects.clone())
+        }
+
+        async fn billing_enabled(&mut self, project_id: &str) -> Result<bool, CliError> {
+            match &self.unbilled {
+                Some(unbilled) => Ok(!unbilled.contains(&project_id)),
+                None => Err(CliError::Preflight(anyhow::anyhow!("permission denied"))),
+            }
+        }
+
+        async fn regions(&mut self, _project_id: &str) -> Result<Vec<String>, CliError> {
+            Ok(self.regions.clone())
+        }
+
+        fn pick(
+            &mut self,
+            prompt: &str,
+            _options: &[String],
+            default: Option<usize>,
+        ) -> Result<usize, CliError> {
+            self.asked.push((prompt.to_owned(), default));
+            Ok(self.picks.pop_front().expect("unexpected pick"))
+        }
+
+        fn confirm(&mut self, _question: &str) -> Result<bool, CliError> {
+            Ok(self.answers.pop_front().expect("unexpected question"))
+        }
+
+        fn say(&mut self, line: &str) {
+            self.output.push(line.to_owned());
+        }
+    }
+
+    fn env(picks: &[usize], answers: &[bool]) -> ScriptedEnv {
+        ScriptedEnv {
+            projects: vec![
+                GcpProject {
+                    id: "prod-api".to_owned(),
+                    name: "Prod API".to_owned(),
+                },
+                GcpProject {
+                    id: "sandbox".to_owned(),
+                    name: "sandbox".to_owned(),
+                },
+            ],
+            unbilled: Some(vec![]),
+            regions: vec!["asia-northeast1".to_owned(), "us-central1".to_owned()],
+            picks: picks.iter().copied().collect(),
+            answers: answers.iter().copied().collect(),
+            ..ScriptedEnv::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn picks_project_then_region_defaulting_to_us_central1() {
+        let mut env = env(&[1, 0], &[]);
+
+        let target = choose_target(&mut env).await.unwrap();
+
+        assert_eq!(
+            target,
+            InitTarget {
+                project_id: "sandbox".to_owned(),
+                region: "asia-northeast1".to_owned(),
+            }
+        );
+        assert_eq!(
+            env.asked,
+            [
+                ("GCP project".to_owned(), None),
+                ("Region".to_owned(), Some(1))
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn project_without_billing_is_picked_again_unless_kept() {
+        let mut env = env(&[0, 1, 1], &[false]);
+        env.unbilled = Some(vec!["prod-api"]);
+
+        let target = choose_target(&mut env).await.unwrap();
+
+        assert_eq!(target.project_id, "sandbox");
+        assert!(
+            env.output
+                .iter()
+                .any(|line| line.contains("Billing is not enabled for prod-api"))
+        );
+
+        let mut env = self::env(&[0, 1], &[true]);
+        env.unbilled = Some(vec!["prod-api"]);
+        assert_eq!(
+            choose_target(&mut env).await.unwrap().project_id,
+            "prod-api"
+        );
+    }
+
+    #[tokio::test]
+    async fn unreadable_billing_keeps_the_project_with_a_warning() {
+        let mut env = env(&[0, 1], &[]);
+        env.unbilled = None;
+
+        let target = choose_target(&mut env).await.unwrap();
+
+        assert_eq!(target.project_id, "prod-api");
+        assert!(env.output[0].contains("Could not check billing"));
+    }
+
+    #[tokio::test]
+    async fn no_projects_is_an_error() {
+        let mut env = ScriptedEnv::default();
+
+        let err = choose_target(&mut env).await.unwrap_err();
+
+        assert_eq!(err.exit_code(), 3);
+        assert!(err.to_string().contains("no GCP projects"), "{err}");
+    }
+
+    #[test]
+    fn project_label_names_the_project_when_it_differs() {
+        let env = env(&[], &[]);
+        let labels: Vec<String> = env.projects.iter().map(project_label).collect();
+        assert_eq!(labels, ["prod-api (Prod API)", "sandbox"]);
+    }
+
+    #[test]
+    fn write_target_keeps_the_template_comments() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            tmp.path().join("propel.toml"),
+            super::super::template::propel_toml_template(),
+        )
+        .unwrap();
+
+        write_target(
+            tmp.path(),
+            &InitTarget {
+                project_id: "prod-api".to_owned(),
+                region: "asia-northeast1".to_owned(),
+            },
+        )
+        .unwrap();
+
+        let content = std::fs::read_to_string(tmp.path().join("propel.toml")).unwrap();
+        assert!(
+            content.contains("# GCP region for Cloud Run deployment."),
+            "{content}"
+        );
+        let config = PropelConfig::load(tmp.path()).unwrap();
+        assert_eq!(config.project.gcp_project_id.as_deref(), Some("prod-api"));
+        assert_eq!(config.project.region, "asia-northeast1");
+    }
+}
@@ -1,22 +1,29 @@
+use super::CliError;
+use super::ProjectContext;
 use propel_cloud::GcloudClient;
-use propel_core::{CargoProject, PropelConfig};
 use std::path::PathBuf;
 
-pub async fn logs(follow: bool, tail: Option<u32>) -> anyhow::Result<()> {
+pub async fn logs(follow: bool, tail: Option<u32>) -> Result<(), CliError> {
     let project_dir = PathBuf::from(".");
-    let config = PropelConfig::load(&project_dir)?;
-    let project_id = config
-        .project
-        .gcp_project_id
-        .as_deref()
-        .ok_or_else(|| anyhow::anyhow!("gcp_project_id not set in propel.toml"))?;
-
-    let project = CargoProject::discover(&project_dir)?;
-    let service_name = super::service_name(&config, &project);
-    let region = &config.project.region;
+    let ctx = ProjectContext::load(&project_dir)?;
+    let project_id = ctx.gcp_project_id()?;
+    let service_name = ctx.service_name();
+    let region = ctx.region();
 
     let client = GcloudClient::new();
 
+    // Reading logs of a service that is not in `region` just prints
+    // nothing, so check where it lives first.
+    if let Err(e) = client.service_url(service_name, project_id, region).await
+        && e.is_service_not_found()
+        && let Some(hint) =
+            super::find_in_other_regions(&client, project_id, service_name, region).await
+    {
+        return Err(CliError::Deploy(anyhow::anyhow!(
+            "{service_name} not found in {region}; {hint}"
+        )));
+    }
+
     if follow {
         client.tail_logs(service_name, project_id, region).await?;
     } else {
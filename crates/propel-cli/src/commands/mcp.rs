@@ -3,14 +3,18 @@
 //! MCP Protocol (stdio) <-> propel-cloud / propel-core / propel-build
 //!
 //! Each tool is a thin wrapper around existing CLI logic.
-//! DoctorReport formatting (`Display` impl) is shared with the CLI.
+//! DoctorReport formatting (`Display` impl and JSON form) is shared with the CLI.
 
+use super::ProjectContext;
 use anyhow::Result;
 use clap::Args;
-use propel_build::dockerfile::DockerfileGenerator;
-use propel_build::{bundle, eject as eject_mod};
-use propel_cloud::GcloudClient;
-use propel_core::{CargoProject, PropelConfig};
+use propel_build::manifest::MANIFEST_FILE;
+use propel_build::{CargoConfig, bundle, eject as eject_mod};
+use propel_cloud::{
+    API_ENABLEMENT_TIMEOUT, BuildOutput, GcloudClient, PreflightError,
+    SECRET_ACCESS_PROPAGATION_TIMEOUT, retry_secret_propagation,
+};
+use propel_core::{CargoProject, PropelConfig, SecretBinding};
 use rmcp::{
     ErrorData as McpError, ServerHandler, ServiceExt,
     handler::server::{tool::ToolCallContext, tool::ToolRouter, wrapper::Parameters},
@@ -33,6 +37,46 @@ fn internal_err(e: impl std::fmt::Display) -> McpError {
     McpError::internal_error(format!("{e}"), None)
 }
 
+// =============================================================================
+// Response budgeting
+// =============================================================================
+
+/// Most of the captured Cloud Build log `deploy` returns, in bytes.
+const BUILD_LOG_BUDGET: usize = 32 * 1024;
+
+/// Most of the gcloud output `status` and `logs` return, in bytes.
+const OUTPUT_BUDGET: usize = 64 * 1024;
+
+/// `text` cut down to its last `budget` bytes, behind a marker line that
+/// says how much was dropped and where to find the rest (`note`).
+///
+/// The cut lands on a character boundary, and on a line boundary when the
+/// kept tail has one, so no partial character or line is returned.
+fn keep_tail(text: &str, budget: usize, note: &str) -> String {
+    if text.len() <= budget {
+        return text.to_owned();
+    }
+    let mut start = text.len() - budget;
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    if !text[..start].ends_with('\n')
+        && let Some(newline) = text[start..].find('\n')
+        && start + newline + 1 < text.len()
+    {
+        start += newline + 1;
+    }
+    format!(
+        "[truncated: first {start} bytes omitted; {note}]\n{}",
+        &text[start..]
+    )
+}
+
+/// Tool result carrying `text`, capped at [`OUTPUT_BUDGET`].
+fn budgeted_text(text: String, note: &str) -> CallToolResult {
+    CallToolResult::success(vec![Content::text(keep_tail(&text, OUTPUT_BUDGET, note))])
+}
+
 // =============================================================================
 // CLI entry point
 // =============================================================================
@@ -156,23 +200,15 @@ impl PropelMcpServer {
             .map_err(|e| McpError::internal_error(format!("Failed to load config: {e}"), None))
     }
 
-    fn load_project(project_path: &Path) -> Result<CargoProject, McpError> {
-        CargoProject::discover(project_path).map_err(|e| {
-            McpError::internal_error(format!("Failed to discover cargo project: {e}"), None)
-        })
+    /// Same loading (and so the same service name) as the CLI commands.
+    fn load_context(project_path: &Path) -> Result<ProjectContext, McpError> {
+        ProjectContext::load(project_path)
+            .map_err(|e| McpError::internal_error(format!("Failed to load project: {e}"), None))
     }
 
     fn require_project_id(config: &PropelConfig) -> Result<&str, McpError> {
-        config.project.gcp_project_id.as_deref().ok_or_else(|| {
-            McpError::invalid_request(
-                "gcp_project_id not set in propel.toml — set [project].gcp_project_id".to_string(),
-                None,
-            )
-        })
-    }
-
-    fn service_name<'a>(config: &'a PropelConfig, project: &'a CargoProject) -> &'a str {
-        super::service_name(config, project)
+        super::require_gcp_project_id(config)
+            .map_err(|e| McpError::invalid_request(e.to_string(), None))
     }
 
     /// Determine Dockerfile and bundle source into a temp directory.
@@ -180,18 +216,69 @@ impl PropelMcpServer {
         project_path: &Path,
         config: &PropelConfig,
         project: &CargoProject,
+        force_large_bundle: bool,
+        strict_preflight: bool,
+        verify_bundle: bool,
         steps: &mut Vec<String>,
     ) -> Result<PathBuf, McpError> {
+        let bundle_root = super::bundle_source(project, project_path);
+        let cargo_config = CargoConfig::detect(bundle_root).map_err(internal_err)?;
+        if let Some(cargo_config) = &cargo_config {
+            steps.extend(
+                super::cargo_config_warnings(bundle_root, cargo_config).map_err(internal_err)?,
+            );
+        }
+
         let dockerfile_content = if eject_mod::is_ejected(project_path) {
             steps.push("Using ejected Dockerfile".to_string());
-            eject_mod::load_ejected_dockerfile(project_path).map_err(internal_err)?
+            if let Some(warning) =
+                super::untracked_eject_warning(project_path).map_err(internal_err)?
+            {
+                steps.push(warning);
+            }
+            let dockerfile =
+                eject_mod::load_ejected_dockerfile(project_path).map_err(internal_err)?;
+            if let Some(warning) =
+                super::ejected_binary_warning(&dockerfile, &project.default_binary)
+            {
+                if strict_preflight {
+                    return Err(McpError::invalid_request(warning, None));
+                }
+                steps.push(format!("Warning: {warning}"));
+            }
+            dockerfile
         } else {
-            let generator = DockerfileGenerator::new(&config.build, project, config.cloud_run.port);
-            generator.render()
+            if let Some(warning) = super::check_builder_rust_version(config, project)
+                .map_err(|e| McpError::invalid_request(e.to_string(), None))?
+            {
+                steps.push(format!("Warning: {warning}"));
+            }
+            super::render_dockerfile(config, project, cargo_config.as_ref())
+                .map_err(|e| McpError::invalid_request(e.to_string(), None))?
         };
 
-        let bundle_dir =
-            bundle::create_bundle(project_path, &dockerfile_content).map_err(internal_err)?;
+        if let Some(member) = project.member_path() {
+            steps.push(format!(
+                "Bundling workspace {} for member {}",
+                project.workspace_root.display(),
+                member.display()
+            ));
+        }
+        let bundle_dir = bundle::create_bundle_with(
+            &bundle::BundleOptions::new(bundle_root, dockerfile_content.as_str())
+                .verify(verify_bundle),
+        )
+        .map_err(internal_err)?;
+        if let Some(sha256) =
+            super::write_bundle_manifest(config, project_path, &bundle_dir).map_err(internal_err)?
+        {
+            steps.push(format!("Wrote {MANIFEST_FILE} (sha256 {sha256})"));
+        }
+        if let Some(warning) = super::check_bundle_size(config, &bundle_dir, force_large_bundle)
+            .map_err(|e| McpError::invalid_request(e.to_string(), None))?
+        {
+            steps.push(warning);
+        }
         steps.push("Source bundled".to_string());
         Ok(bundle_dir)
     }
@@ -200,21 +287,29 @@ impl PropelMcpServer {
     ///
     /// Failure to list secrets is a hard error — deploying without expected
     /// secrets would cause the application to crash on startup.
+    /// Selection follows [`super::select_secrets`].
     async fn discover_secrets(
         project_id: &str,
         client: &GcloudClient,
+        config: &PropelConfig,
         steps: &mut Vec<String>,
-    ) -> Result<Vec<String>, McpError> {
-        let secrets = client
+    ) -> Result<Vec<SecretBinding>, McpError> {
+        let discovered = client
             .list_secrets(project_id)
             .await
             .map_err(internal_err)?;
+        let secrets = super::select_secrets(config, discovered).map_err(internal_err)?;
 
         if secrets.is_empty() {
             steps.push("No secrets found in Secret Manager".to_string());
         } else {
             steps.push(format!("{} secret(s) will be injected", secrets.len()));
         }
+        if config.cloud_run.check_secret_versions {
+            for warning in super::secret_version_warnings(client, project_id, &secrets).await {
+                steps.push(format!("Warning: {warning}"));
+            }
+        }
         Ok(secrets)
     }
 }
@@ -278,7 +373,18 @@ impl ServerHandler for PropelMcpServer {
 // =============================================================================
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
-struct McpDoctorRequest {}
+struct McpDoctorRequest {
+    #[schemars(
+        description = "Skip verifying the gcloud account's IAM permissions (default: false)"
+    )]
+    #[serde(default)]
+    pub skip_iam_check: bool,
+    #[schemars(
+        description = "Re-check API enablement instead of reusing a result from the last 10 minutes (default: false)"
+    )]
+    #[serde(default)]
+    pub no_cache: bool,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 struct McpStatusRequest {}
@@ -300,6 +406,26 @@ struct McpDeployRequest {
     #[schemars(description = "Allow deploying with uncommitted changes (default: false)")]
     #[serde(default)]
     pub allow_dirty: bool,
+    #[schemars(
+        description = "Skip verifying the gcloud account's IAM permissions, e.g. when it may not read the project IAM policy (default: false)"
+    )]
+    #[serde(default)]
+    pub skip_iam_check: bool,
+    #[schemars(
+        description = "Fail on any disabled API, including Secret Manager when no secrets are configured, and when the ejected Dockerfile names another binary than the project builds (default: false)"
+    )]
+    #[serde(default)]
+    pub strict_preflight: bool,
+    #[schemars(
+        description = "Upload the bundle even when it exceeds [cloud_build].max_bundle_mb (default: false)"
+    )]
+    #[serde(default)]
+    pub force_large_bundle: bool,
+    #[schemars(
+        description = "Fail when files change while the bundle is copied; always on unless allow_dirty is set (default: false)"
+    )]
+    #[serde(default)]
+    pub strict_bundle: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -313,7 +439,7 @@ struct McpEjectRequest {}
 impl PropelMcpServer {
     #[tool(
         name = "doctor",
-        description = "Check GCP setup and readiness for deployment. Verifies gcloud CLI, authentication, project, billing, required APIs, and propel.toml existence.",
+        description = "Check GCP setup and readiness for deployment. Verifies gcloud CLI, authentication, project, billing, required APIs, the account's deploy IAM permissions, propel.toml existence, the Rust toolchain, and git. The structured result lists each check with a stable id (e.g. gcp.billing, api.run).",
         annotations(
             read_only_hint = true,
             destructive_hint = false,
@@ -322,7 +448,7 @@ impl PropelMcpServer {
     )]
     async fn doctor(
         &self,
-        #[allow(unused_variables)] Parameters(_req): Parameters<McpDoctorRequest>,
+        Parameters(req): Parameters<McpDoctorRequest>,
         peer: rmcp::service::Peer<RoleServer>,
     ) -> Result<CallToolResult, McpError> {
         let project_path = self.project_path(&peer).await?;
@@ -336,19 +462,33 @@ impl PropelMcpServer {
             .ok()
             .and_then(|c| c.project.gcp_project_id.as_deref());
 
-        let client = GcloudClient::new();
-        let mut report = client.doctor(project_id).await;
+        let client = super::doctor_client(&project_path, project_id, req.no_cache);
+        let required_components = match &config {
+            Ok(config) => super::required_components(config),
+            Err(_) => Vec::new(),
+        };
+        let mut report = client.doctor(project_id, &required_components).await;
+        report.iam = Some(
+            super::iam::iam_doctor_check(&client, &report, project_id, req.skip_iam_check).await,
+        );
 
-        // Config file check
-        if project_path.join("propel.toml").exists() {
-            report.config_file = propel_cloud::CheckResult::ok("Found");
-        } else {
-            report.config_file = propel_cloud::CheckResult::fail("Not found");
+        super::local_doctor_checks(&mut report, &project_path);
+        if let Ok(config) = &config {
+            report.runtime_service_account =
+                super::iam::runtime_sa_doctor_check(&client, &report, project_id, config).await;
+            report.cloud_sql_api =
+                super::cloudsql::cloud_sql_doctor_check(&client, &report, project_id, config).await;
+            report.notes.extend(
+                super::config_warnings(config, &project_path)
+                    .iter()
+                    .map(ToString::to_string),
+            );
+            report.notes.extend(super::private_service_notice(config));
         }
 
-        Ok(CallToolResult::success(vec![Content::text(
-            report.to_string(),
-        )]))
+        let mut result = CallToolResult::success(vec![Content::text(report.to_string())]);
+        result.structured_content = Some(report.to_json());
+        Ok(result)
     }
 
     #[tool(
@@ -366,18 +506,36 @@ impl PropelMcpServer {
         peer: rmcp::service::Peer<RoleServer>,
     ) -> Result<CallToolResult, McpError> {
         let project_path = self.project_path(&peer).await?;
-        let config = Self::load_config(&project_path)?;
-        let project = Self::load_project(&project_path)?;
-        let project_id = Self::require_project_id(&config)?;
-        let service_name = Self::service_name(&config, &project);
+        let ctx = Self::load_context(&project_path)?;
+        let project_id = Self::require_project_id(&ctx.config)?;
+        let service_name = ctx.service_name();
+
+        let region = ctx.region();
 
         let client = GcloudClient::new();
-        let output = client
-            .describe_service(service_name, project_id, &config.project.region)
+        let output = match client
+            .describe_service(service_name, project_id, region)
             .await
-            .map_err(internal_err)?;
+        {
+            Ok(output) => output,
+            Err(e) if e.is_service_not_found() => {
+                let hint =
+                    super::find_in_other_regions(&client, project_id, service_name, region).await;
+                return Err(match hint {
+                    Some(hint) => {
+                        internal_err(format!("{service_name} not found in {region}; {hint}"))
+                    }
+                    None => internal_err(e),
+                });
+            }
+            Err(e) => return Err(internal_err(e)),
+        };
+        let access = super::access_line(&client, service_name, project_id, region).await;
 
-        Ok(CallToolResult::success(vec![Content::text(output)]))
+        Ok(budgeted_text(
+            format!("{output}\n{access}"),
+            "run `propel status` for the full output",
+        ))
     }
 
     #[tool(
@@ -395,27 +553,32 @@ impl PropelMcpServer {
         peer: rmcp::service::Peer<RoleServer>,
     ) -> Result<CallToolResult, McpError> {
         let project_path = self.project_path(&peer).await?;
-        let config = Self::load_config(&project_path)?;
-        let project = Self::load_project(&project_path)?;
-        let project_id = Self::require_project_id(&config)?;
-        let service_name = Self::service_name(&config, &project);
+        let ctx = Self::load_context(&project_path)?;
+        let project_id = Self::require_project_id(&ctx.config)?;
+        let service_name = ctx.service_name();
 
         // arch-lint: allow(no-silent-result-drop) reason="Option: None = MCP client omitted tail param; 100 is default limit"
         let limit = req.tail.unwrap_or(100).min(1000);
 
+        let region = ctx.region();
+
         let client = GcloudClient::new();
         let output = client
-            .read_logs_captured(service_name, project_id, &config.project.region, limit)
+            .read_logs_captured(service_name, project_id, region, limit)
             .await
             .map_err(internal_err)?;
 
-        let text = if output.trim().is_empty() {
-            "No log entries found".to_string()
-        } else {
+        let text = if !output.trim().is_empty() {
             output
+        } else if let Some(hint) =
+            super::find_in_other_regions(&client, project_id, service_name, region).await
+        {
+            format!("No log entries found in {region}; {hint}")
+        } else {
+            "No log entries found".to_string()
         };
 
-        Ok(CallToolResult::success(vec![Content::text(text)]))
+        Ok(budgeted_text(text, "request fewer entries with `tail`"))
     }
 
     #[tool(
@@ -437,10 +600,11 @@ impl PropelMcpServer {
         let project_id = Self::require_project_id(&config)?;
 
         let client = GcloudClient::new();
-        let secrets = client
+        let discovered = client
             .list_secrets(project_id)
             .await
             .map_err(internal_err)?;
+        let secrets = config.project.secret_namespace().keys(&discovered);
 
         let output = if secrets.is_empty() {
             "No secrets found".to_string()
@@ -452,7 +616,10 @@ impl PropelMcpServer {
             lines.join("\n")
         };
 
-        Ok(CallToolResult::success(vec![Content::text(output)]))
+        Ok(budgeted_text(
+            output,
+            "run `propel secret list` for every name",
+        ))
     }
 
     #[tool(
@@ -493,9 +660,11 @@ impl PropelMcpServer {
         peer: rmcp::service::Peer<RoleServer>,
     ) -> Result<CallToolResult, McpError> {
         let project_path = self.project_path(&peer).await?;
-        let client = GcloudClient::new();
         let mut steps = Vec::new();
 
+        bundle::check_project_dir(&project_path)
+            .map_err(|e| McpError::invalid_request(e.to_string(), None))?;
+
         // Dirty check
         if !req.allow_dirty && bundle::is_dirty(&project_path).map_err(internal_err)? {
             return Err(McpError::invalid_request(
@@ -507,74 +676,328 @@ impl PropelMcpServer {
         }
 
         // Load configuration
-        let config = Self::load_config(&project_path)?;
-        let project = Self::load_project(&project_path)?;
-        let gcp_project_id = Self::require_project_id(&config)?;
-        let service_name = Self::service_name(&config, &project);
-        let region = &config.project.region;
-        let image_tag = format!(
-            "{}:latest",
-            super::image_path(
-                region,
-                gcp_project_id,
-                super::ARTIFACT_REPO_NAME,
-                service_name
-            ),
+        let mut ctx = Self::load_context(&project_path)?;
+        steps.extend(
+            super::config_warnings(&ctx.config, &project_path)
+                .iter()
+                .map(|w| format!("Warning: {w}")),
         );
+        steps.extend(super::include_check::unincluded_runtime_dirs(
+            &ctx.config,
+            &ctx.project,
+            &project_path,
+        ));
+        let labels = super::propel_labels(&ctx.config);
+        super::resolve_env_templates(&mut ctx)
+            .map_err(|e| McpError::invalid_request(e.to_string(), None))?;
+        let client = super::preflight::preflight_client(&ctx.config);
+        let gcp_project_id = Self::require_project_id(&ctx.config)?;
+        let service_name = ctx.service_name();
+        let region = ctx.region();
+        let image_tag = ctx
+            .image_tag()
+            .map_err(|e| McpError::invalid_request(e.to_string(), None))?;
+        let ProjectContext {
+            config, project, ..
+        } = &ctx;
+
+        // Dependency checks
+        if let Some(metadata) = super::resolve_dependency_graph(&project_path) {
+            super::check_private_dependencies(&metadata, &config.build, project)
+                .map_err(|e| McpError::invalid_request(e.to_string(), None))?;
+            if let Some(found) =
+                propel_core::detect_deprecated_sdk(&metadata, &project.manifest_path)
+            {
+                steps.push(format!("Warning: {found}"));
+            }
+            if !eject_mod::is_ejected(&project_path)
+                && let Some(suggestion) =
+                    super::native_package_suggestion(&metadata, &config.build, project)
+            {
+                steps.push(format!(
+                    "Warning: {} need(s) system packages missing from propel.toml:\n{}",
+                    suggestion.crates.join(", "),
+                    suggestion.toml_snippet(&config.build)
+                ));
+            }
+        }
 
         // Pre-flight checks
         let report = client
             .check_prerequisites(gcp_project_id)
             .await
             .map_err(internal_err)?;
-        if report.has_warnings() {
-            let disabled = report.disabled_apis.join(", ");
-            return Err(McpError::internal_error(
-                format!(
-                    "Required APIs not enabled: {disabled}. \
-                     Enable them with: gcloud services enable <api> --project {gcp_project_id}"
-                ),
-                None,
+        if !config.ci.deploy_via_cloud_build
+            && let Some(notice) = super::check_run_command_group(&client, config)
+                .await
+                .map_err(internal_err)?
+        {
+            steps.push(notice);
+        }
+        if let Some(key_file) = &report.activated_key_file {
+            steps.push(format!(
+                "No active gcloud account — activated service account key {}",
+                key_file.display()
             ));
         }
+        let account = client.active_account().await.map_err(internal_err)?;
+        if !req.skip_iam_check
+            && let Some(warning) = super::iam::verify_deploy_permissions(
+                &client,
+                config,
+                gcp_project_id,
+                account.as_deref(),
+            )
+            .await
+            .map_err(internal_err)?
+        {
+            steps.push(warning);
+        }
+        if report.billing_enabled.is_none() {
+            steps.push("Warning: could not verify billing status".to_string());
+        }
+        let disabled_apis = super::preflight::DisabledApis::classify(
+            config,
+            report.disabled_apis,
+            req.strict_preflight,
+        );
+        if let Some(warning) = disabled_apis.warning() {
+            steps.push(warning);
+        }
+        if !disabled_apis.required.is_empty() {
+            let pending = client
+                .pending_api_enablements(gcp_project_id, &disabled_apis.required)
+                .await
+                .map_err(internal_err)?;
+            if pending.len() != disabled_apis.required.len() {
+                return Err(internal_err(PreflightError::ApisNotEnabled {
+                    project_id: gcp_project_id.to_owned(),
+                    apis: disabled_apis.required,
+                }));
+            }
+            client
+                .wait_for_api_enablement(
+                    gcp_project_id,
+                    &pending,
+                    API_ENABLEMENT_TIMEOUT,
+                    |_, _| {},
+                )
+                .await
+                .map_err(internal_err)?;
+            steps.push("Waited for recently enabled APIs to propagate".to_string());
+        }
         steps.push("Pre-flight checks passed".to_string());
 
         // Ensure Artifact Registry repository
         client
-            .ensure_artifact_repo(gcp_project_id, region, super::ARTIFACT_REPO_NAME)
+            .ensure_artifact_repo(
+                gcp_project_id,
+                ctx.artifact_location(),
+                super::ARTIFACT_REPO_NAME,
+            )
             .await
             .map_err(internal_err)?;
         steps.push("Artifact Registry repository ensured".to_string());
 
+        // Discover secrets (injected by the CLI deploy or the in-build deploy step)
+        let secrets = if let Some(reason) = disabled_apis.secret_discovery_skipped(config) {
+            steps.push(reason.to_string());
+            Vec::new()
+        } else {
+            Self::discover_secrets(gcp_project_id, &client, config, &mut steps).await?
+        };
+        for warning in super::cloudsql::cloud_sql_warnings(&client, config, gcp_project_id).await {
+            steps.push(format!("Warning: {warning}"));
+        }
+
         // Bundle source
-        let bundle_dir = Self::prepare_bundle(&project_path, &config, &project, &mut steps)?;
+        let bundle_dir = Self::prepare_bundle(
+            &project_path,
+            config,
+            project,
+            req.force_large_bundle,
+            req.strict_preflight,
+            req.strict_bundle || !req.allow_dirty,
+            &mut steps,
+        )?;
+        let build_config = match super::cloud_build_yaml(
+            config,
+            gcp_project_id,
+            &image_tag,
+            service_name,
+            &secrets,
+            &labels,
+        ) {
+            Some(yaml) => {
+                Some(bundle::write_cloudbuild_config(&bundle_dir, &yaml).map_err(internal_err)?)
+            }
+            None => None,
+        };
 
         // Submit build (captured for MCP response)
+        let build_log = project_path.join(super::DEFAULT_BUILD_LOG);
+        super::ignore_build_logs(&project_path, &build_log).map_err(internal_err)?;
         let build_output = client
-            .submit_build_captured(&bundle_dir, gcp_project_id, &image_tag)
+            .submit_build(
+                &bundle_dir,
+                gcp_project_id,
+                &image_tag,
+                &config.cloud_build,
+                build_config.as_deref(),
+                &BuildOutput::Capture(build_log),
+            )
+            .await
+            .map_err(|e| {
+                internal_err(format!(
+                    "{e} (full build log: {})",
+                    super::DEFAULT_BUILD_LOG
+                ))
+            })?;
+        steps.push(format!(
+            "Cloud Build completed (log: {})",
+            super::DEFAULT_BUILD_LOG
+        ));
+        steps.push(format!("Image: {image_tag}"));
+        super::tag_latest(&client, &ctx, &image_tag, |line| steps.push(line))
             .await
             .map_err(internal_err)?;
-        steps.push("Cloud Build completed".to_string());
+        let labels = match super::image_size::report_image_size(
+            &client,
+            config,
+            &image_tag,
+            service_name,
+            gcp_project_id,
+            region,
+            |line| steps.push(line),
+        )
+        .await
+        {
+            Some(size) => labels.with_image_size(size),
+            None => labels,
+        };
 
-        // Discover secrets & deploy to Cloud Run
-        let secrets = Self::discover_secrets(gcp_project_id, &client, &mut steps).await?;
-        let url = client
-            .deploy_to_cloud_run(
-                service_name,
-                &image_tag,
-                gcp_project_id,
-                region,
-                &config.cloud_run,
-                &secrets,
+        // Deploy to Cloud Run (already done inside the build in deploy_via_cloud_build mode)
+        let (url, canary) = if config.ci.deploy_via_cloud_build {
+            let url = client
+                .service_url(service_name, gcp_project_id, region)
+                .await
+                .map_err(internal_err)?;
+            (url, None)
+        } else {
+            let canary = super::canary::canary_applies(&client, &ctx, gcp_project_id, |line| {
+                steps.push(line)
+            })
+            .await
+            .map_err(internal_err)?;
+            let warm = canary.is_none()
+                && super::warmup::warmup_applies(&client, &ctx, gcp_project_id, |line| {
+                    steps.push(line)
+                })
+                .await
+                .map_err(internal_err)?;
+            let mut retries = 0;
+            let deployed = retry_secret_propagation(
+                SECRET_ACCESS_PROPAGATION_TIMEOUT,
+                |_, _| retries += 1,
+                || async {
+                    if canary.is_some() {
+                        client
+                            .deploy_canary_revision(
+                                service_name,
+                                &image_tag,
+                                gcp_project_id,
+                                region,
+                                &config.cloud_run,
+                                &secrets,
+                                &labels,
+                            )
+                            .await
+                    } else if warm {
+                        client
+                            .deploy_revision_without_traffic(
+                                service_name,
+                                &image_tag,
+                                gcp_project_id,
+                                region,
+                                &config.cloud_run,
+                                &secrets,
+                                &labels,
+                            )
+                            .await
+                    } else {
+                        client
+                            .deploy_to_cloud_run(
+                                service_name,
+                                &image_tag,
+                                gcp_project_id,
+                                region,
+                                &config.cloud_run,
+                                &secrets,
+                                &labels,
+                            )
+                            .await
+                    }
+                },
             )
             .await
             .map_err(internal_err)?;
+            if retries > 0 {
+                steps.push(format!(
+                    "Retried Cloud Run deploy {retries} time(s) while secret access propagated"
+                ));
+            }
+            let url = if let Some(canary) = canary {
+                super::canary::release_canary(
+                    &client,
+                    &ctx,
+                    gcp_project_id,
+                    canary,
+                    &deployed,
+                    |line| steps.push(line),
+                )
+                .await
+                .map_err(internal_err)?
+            } else if warm {
+                super::warmup::warm_up_and_promote(
+                    &client,
+                    &ctx,
+                    gcp_project_id,
+                    &deployed,
+                    |line| steps.push(line),
+                )
+                .await
+                .map_err(internal_err)?
+            } else {
+                deployed
+            };
+            (url, canary)
+        };
+        super::history::record(
+            &ctx,
+            &super::history::deploy_record(
+                &ctx,
+                &image_tag,
+                canary,
+                account.as_deref(),
+                report.project_number.as_deref(),
+            ),
+            |line| steps.push(line),
+        );
         steps.push(format!("Deployed: {url}"));
+        steps.extend(super::private_service_notice(config));
+        if let Some(notice) = super::idle_cost_notice(config) {
+            steps.push(notice);
+        }
 
         // Format response
         let mut text = steps.join("\n");
         if !build_output.is_empty() {
-            text.push_str(&format!("\n\n--- Cloud Build Log ---\n{build_output}"));
+            let build_log = keep_tail(
+                &build_output,
+                BUILD_LOG_BUDGET,
+                &format!("full log at {}", super::DEFAULT_BUILD_LOG),
+            );
+            text.push_str(&format!("\n\n--- Cloud Build Log ---\n{build_log}"));
         }
 
         Ok(CallToolResult::success(vec![Content::text(text)]))
@@ -596,17 +1019,21 @@ impl PropelMcpServer {
         peer: rmcp::service::Peer<RoleServer>,
     ) -> Result<CallToolResult, McpError> {
         let project_path = self.project_path(&peer).await?;
-        let config = Self::load_config(&project_path)?;
-        let project = Self::load_project(&project_path)?;
+        let ProjectContext {
+            config, project, ..
+        } = Self::load_context(&project_path)?;
 
-        let generator = DockerfileGenerator::new(&config.build, &project, config.cloud_run.port);
-        let dockerfile = generator.render();
+        let cargo_config = CargoConfig::detect(super::bundle_source(&project, &project_path))
+            .map_err(internal_err)?;
+        let dockerfile = super::render_dockerfile(&config, &project, cargo_config.as_ref())
+            .map_err(|e| McpError::invalid_request(e.to_string(), None))?;
 
         eject_mod::eject(&project_path, &dockerfile).map_err(internal_err)?;
 
         Ok(CallToolResult::success(vec![Content::text(
             "Ejected build config to .propel/Dockerfile\n\
-             You can now edit it directly. `propel deploy` will use this file.",
+             You can now edit it directly. `propel deploy` will use this file.\n\
+             Commit .propel/ so deploys from CI use it too (see .propel/README.md).",
         )]))
     }
 }
@@ -618,7 +1045,73 @@ impl PropelMcpServer {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use propel_core::CargoBinary;
+
+    #[test]
+    fn keep_tail_leaves_short_text_alone() {
+        assert_eq!(
+            keep_tail("line 1\nline 2\n", 14, "note"),
+            "line 1\nline 2\n"
+        );
+        assert_eq!(keep_tail("", 0, "note"), "");
+    }
+
+    #[test]
+    fn keep_tail_keeps_whole_last_lines_with_marker() {
+        let text = "step 1\nstep 2\nstep 3\n";
+        assert_eq!(
+            keep_tail(text, 10, "full log at .propel/last-build.log"),
+            "[truncated: first 14 bytes omitted; full log at .propel/last-build.log]\nstep 3\n"
+        );
+    }
+
+    #[test]
+    fn keep_tail_cut_at_line_start_keeps_that_line() {
+        let text = "step 1\nstep 2\n";
+        assert_eq!(
+            keep_tail(text, 7, "note"),
+            "[truncated: first 7 bytes omitted; note]\nstep 2\n"
+        );
+    }
+
+    #[test]
+    fn keep_tail_single_line_cuts_at_char_boundary() {
+        // 'é' is 2 bytes, '日' 3, '🦀' 4: every budget that splits one of
+        // them must move forward to the next boundary.
+        for text in ["aéééé", "a日日日日", "a🦀🦀🦀🦀"] {
+            for budget in 0..text.len() {
+                let kept = keep_tail(text, budget, "note");
+                let tail = kept.split_once('\n').unwrap().1;
+                assert!(tail.len() <= budget, "{text:?} budget {budget}: {tail:?}");
+                assert!(text.ends_with(tail));
+                assert!(kept.starts_with("[truncated: first "), "{kept:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn keep_tail_multibyte_lines() {
+        let text = "ビルド開始\nコンパイル中 🦀\n完了\n";
+        let kept = keep_tail(text, 12, "note");
+        assert_eq!(
+            kept,
+            format!(
+                "[truncated: first {} bytes omitted; note]\n完了\n",
+                text.len() - "完了\n".len()
+            )
+        );
+    }
+
+    #[test]
+    fn keep_tail_reports_omitted_bytes_consistently() {
+        let text = "x".repeat(100) + "\n" + &"y".repeat(50);
+        let kept = keep_tail(&text, 60, "note");
+        let (marker, tail) = kept.split_once('\n').unwrap();
+        assert_eq!(tail, "y".repeat(50));
+        assert_eq!(
+            marker,
+            format!("[truncated: first {} bytes omitted; note]", text.len() - 50)
+        );
+    }
 
     #[test]
     fn logs_request_default_tail() {
@@ -652,49 +1145,6 @@ mod tests {
         assert!(!info.server_info.version.is_empty());
     }
 
-    #[test]
-    fn service_name_uses_config_override() {
-        let mut config = PropelConfig::default();
-        config.project.name = Some("custom-name".to_string());
-        let project = CargoProject {
-            name: "cargo-name".to_string(),
-            version: "0.1.0".to_string(),
-            manifest_path: PathBuf::from("Cargo.toml"),
-            package_dir: PathBuf::from("."),
-            workspace_root: PathBuf::from("."),
-            binaries: vec![CargoBinary {
-                name: "cargo-name".to_string(),
-                src_path: PathBuf::from("src/main.rs"),
-            }],
-            default_binary: "cargo-name".to_string(),
-        };
-        assert_eq!(
-            PropelMcpServer::service_name(&config, &project),
-            "custom-name"
-        );
-    }
-
-    #[test]
-    fn service_name_falls_back_to_cargo() {
-        let config = PropelConfig::default();
-        let project = CargoProject {
-            name: "cargo-name".to_string(),
-            version: "0.1.0".to_string(),
-            manifest_path: PathBuf::from("Cargo.toml"),
-            package_dir: PathBuf::from("."),
-            workspace_root: PathBuf::from("."),
-            binaries: vec![CargoBinary {
-                name: "cargo-name".to_string(),
-                src_path: PathBuf::from("src/main.rs"),
-            }],
-            default_binary: "cargo-name".to_string(),
-        };
-        assert_eq!(
-            PropelMcpServer::service_name(&config, &project),
-            "cargo-name"
-        );
-    }
-
     #[test]
     fn require_project_id_missing() {
         let config = PropelConfig::default();
@@ -1,194 +1,1330 @@
+mod canary;
 mod ci;
+mod cloudsql;
+mod config;
+mod context;
+mod costs;
 mod deploy;
 mod destroy;
 mod doctor;
 mod eject;
+mod error;
+mod fleet;
+mod history;
+mod iam;
+mod image_size;
+mod include_check;
 mod init;
 mod logs;
 pub(crate) mod mcp;
 mod new;
+mod preflight;
+mod rollback;
+#[cfg(test)]
+mod scripted_gcloud;
 mod secret;
+mod setup;
 mod status;
+mod template;
+mod update;
+mod warmup;
 
-use propel_core::{CargoProject, PropelConfig};
+use cargo_metadata::Metadata;
+use propel_build::bundle::{self, BundleError};
+use propel_build::cloudbuild::{CloudBuildYaml, DeployStep};
+use propel_build::manifest::BundleManifest;
+use propel_build::{CargoConfig, DockerfileGenerator, DockerfileOptions};
+use propel_cloud::{
+    CheckResult, DoctorReport, GcloudClient, PreflightError, PropelLabels, RevisionInfo,
+    RunCommandGroup, SecretInfo,
+};
+use propel_core::{
+    BuildConfig, CargoProject, ConfigWarning, DiskCache, PackageSuggestion, ProjectFlags,
+    PropelConfig, SecretBinding, TagStrategy, TemplateContext,
+};
+use std::path::Path;
 
-/// Artifact Registry repository name used for container images.
-pub(crate) const ARTIFACT_REPO_NAME: &str = "propel";
+pub(crate) use context::{
+    ARTIFACT_REPO_NAME, ProjectContext, announce_target, check_expected_account,
+    find_in_other_regions, require_gcp_project_id,
+};
+pub use error::CliError;
+pub(crate) use error::EXIT_CODES_HELP;
 
-/// Resolve the Cloud Run service name: config override or Cargo package name.
-pub(crate) fn service_name<'a>(config: &'a PropelConfig, project: &'a CargoProject) -> &'a str {
-    // arch-lint: allow(no-silent-result-drop) reason="Option: None = user omitted [project].name; Cargo package name is intended fallback"
-    config.project.name.as_deref().unwrap_or(&project.name)
+/// `.gitignore` lines for propel's directories: the deploy staging
+/// directory is ignored, while `.propel/` (an ejected Dockerfile) must be
+/// committed and is deliberately not listed.
+pub(crate) const GITIGNORE_PROPEL_ENTRIES: &str = "# propel: deploy staging directory (commit .propel/, which holds an ejected Dockerfile)\n\
+     .propel-bundle/\n";
+
+/// `existing` `.gitignore` content with [`GITIGNORE_PROPEL_ENTRIES`]
+/// appended, or `None` when `.propel-bundle/` is already ignored.
+pub(crate) fn gitignore_with_propel_entries(existing: &str) -> Option<String> {
+    let ignored = existing
+        .lines()
+        .map(|line| line.trim().trim_start_matches('/').trim_end_matches('/'))
+        .any(|entry| entry == ".propel-bundle");
+    if ignored {
+        return None;
+    }
+    let mut updated = existing.to_owned();
+    if !updated.is_empty() && !updated.ends_with('\n') {
+        updated.push('\n');
+    }
+    updated.push_str(GITIGNORE_PROPEL_ENTRIES);
+    Some(updated)
 }
 
-/// Build the Artifact Registry image path (without tag).
-pub(crate) fn image_path(region: &str, project_id: &str, repo: &str, service: &str) -> String {
-    format!("{region}-docker.pkg.dev/{project_id}/{repo}/{service}")
+/// Where deploy writes the Cloud Build log unless `--build-log` says otherwise.
+pub(crate) const DEFAULT_BUILD_LOG: &str = ".propel/last-build.log";
+
+/// Resolve the full dependency graph for the pre-build dependency checks.
+///
+/// Failure is logged and yields `None` — these checks are advisory and
+/// must never be the reason a deploy cannot start.
+pub(crate) fn resolve_dependency_graph(project_dir: &Path) -> Option<Metadata> {
+    match propel_core::resolve_metadata(project_dir) {
+        Ok(m) => Some(m),
+        Err(e) => {
+            tracing::warn!(error = %e, "skipping dependency checks");
+            None
+        }
+    }
 }
 
-/// Initial `propel.toml` template with comprehensive documentation.
+/// Detect native `-sys` crates whose system packages are missing from `[build]`.
 ///
-/// This is the only configuration file users need to write after `propel new`
-/// or `propel init`, so every field is documented inline with defaults,
-/// valid values, and usage notes.
-pub(crate) const PROPEL_TOML_TEMPLATE: &str = r##"# ============================================================================
-# propel.toml — Propel configuration
-# ============================================================================
-#
-# This file controls how `propel deploy` builds and deploys your Rust
-# application to Google Cloud Run.
-#
-# All sections and fields are optional. Sensible defaults are provided
-# so a bare `propel.toml` (or even an empty file) is perfectly valid.
-#
-# Workflow:
-#   propel new <name>   — scaffold a new project (generates this file)
-#   propel init         — add Propel to an existing Rust project
-#   propel deploy       — build & deploy to Cloud Run
-#   propel eject        — take full control of the Dockerfile
-#
-# Escalation path for build customization:
-#   Zero config  →  [build] include/env  →  propel eject
-#   (all-in)        (selective)              (full Dockerfile control)
-
-# ── Project ─────────────────────────────────────────────────────────────────
-#
-# Identifies your project and its target GCP environment.
-
-[project]
-
-# GCP project ID (REQUIRED for deploy).
-#
-# Find yours with:  gcloud config get-value project
-# gcp_project_id = "your-project-id"
-
-# Service name override. Defaults to Cargo.toml [package].name.
-# Useful when the crate name differs from the desired Cloud Run service name.
-# name = "my-service"
-
-# GCP region for Cloud Run deployment.
-# See available regions: gcloud run regions list
-# Default: "us-central1"
-# region = "us-central1"
-
-# ── Build ───────────────────────────────────────────────────────────────────
-#
-# Controls Docker image generation.
-#
-# Propel generates a multi-stage Dockerfile using Cargo Chef for optimal
-# layer caching:
-#   Stage 1 (Planner)  — extract dependency recipe
-#   Stage 2 (Cacher)   — pre-build dependencies (cached between deploys)
-#   Stage 3 (Builder)  — compile the application
-#   Stage 4 (Runtime)  — minimal image with binary + runtime assets
-
-[build]
-
-# Rust builder image. Must be >= 1.85 for edition = "2024".
-# Default: "rust:1.93-bookworm"
-# base_image = "rust:1.93-bookworm"
-
-# Runtime base image. Distroless is recommended for minimal attack surface.
-# Default: "gcr.io/distroless/cc-debian12"
-#
-# Common alternatives:
-#   "debian:bookworm-slim"       — when you need a shell for debugging
-#   "gcr.io/distroless/cc-debian12"  — minimal, no shell (default)
-# runtime_image = "gcr.io/distroless/cc-debian12"
-
-# System packages to install via apt-get during the build stage.
-# Needed when your crate depends on C libraries (e.g. OpenSSL, libpq).
-#
-# Example:
-#   extra_packages = ["libssl-dev", "pkg-config"]
-# extra_packages = []
-
-# Cargo Chef version for dependency caching.
-# Default: "0.1.73"
-# cargo_chef_version = "0.1.73"
-
-# Paths to copy into the runtime image.
-#
-# By default (when omitted), the entire build context is copied into the
-# runtime container via `COPY . .`. This means migrations, templates,
-# Lua scripts, and any other files in your repo are available at runtime
-# with zero configuration.
-#
-# When specified, ONLY the listed paths (plus the compiled binary) are
-# copied into the runtime image. This produces a smaller, cleaner image.
-#
-# Convention:
-#   - Directories MUST end with `/`   →  COPY dir/ ./dir/
-#   - Files MUST NOT end with `/`     →  COPY file ./file
-#
-# Examples:
-#   include = ["migrations/"]
-#   include = ["lua/", "seeds.txt", "templates/"]
-#   include = []                # binary only, no extra files
-# include = ["migrations/", "templates/"]
-
-# Static environment variables baked into the container image.
-#
-# These become `ENV` directives in the generated Dockerfile and are
-# available to the application at runtime.
-#
-# For sensitive values (API keys, tokens), use Cloud Run environment
-# variables or Secret Manager via `propel secret set KEY=VALUE` instead.
-#
-# [build.env]
-# TEMPLATE_DIR = "/app/templates"
-# RUST_LOG = "info"
-
-# ── Cloud Run ───────────────────────────────────────────────────────────────
-#
-# Cloud Run service configuration. These map directly to `gcloud run deploy`
-# flags. See: https://cloud.google.com/run/docs/configuring/memory-limits
-
-[cloud_run]
-
-# Port the application listens on. Must match your code's bind address.
-# Default: 8080
-# port = 8080
-
-# Memory allocation per instance.
-# Valid: "128Mi", "256Mi", "512Mi", "1Gi", "2Gi", "4Gi", ...up to "32Gi"
-# Default: "512Mi"
-# memory = "512Mi"
-
-# CPU count per instance.
-# Valid: 1, 2, 4, 8
-# Default: 1
-# cpu = 1
-
-# Minimum number of instances to keep warm (avoids cold starts).
-# Default: 0 (scale to zero)
-# Billing note: min_instances > 0 incurs idle charges.
-# min_instances = 0
-
-# Maximum number of instances to scale up to.
-# Default: 10
-# max_instances = 10
-
-# Maximum concurrent requests per instance.
-# Default: 80
-# Higher values improve throughput; lower values improve per-request latency.
-# concurrency = 80
-"##;
-
-/// Extract `gcp_project_id` from config, returning a clear error if not set.
-fn require_gcp_project_id(config: &PropelConfig) -> anyhow::Result<&str> {
-    config.project.gcp_project_id.as_deref().ok_or_else(|| {
-        anyhow::anyhow!("gcp_project_id not set in propel.toml — set [project].gcp_project_id")
+/// Returns `None` when nothing is missing.
+pub(crate) fn native_package_suggestion(
+    metadata: &Metadata,
+    build: &BuildConfig,
+    project: &CargoProject,
+) -> Option<PackageSuggestion> {
+    let detected = propel_core::detect_native_dependencies(metadata, &project.manifest_path);
+    let suggestion = PackageSuggestion::for_config(&detected, build);
+    (!suggestion.is_empty()).then_some(suggestion)
+}
+
+/// Fail when dependencies need credentials but no git credentials secret is configured.
+pub(crate) fn check_private_dependencies(
+    metadata: &Metadata,
+    build: &BuildConfig,
+    project: &CargoProject,
+) -> Result<(), PreflightError> {
+    if build.git_credentials_secret.is_some() {
+        return Ok(());
+    }
+    let private = propel_core::detect_private_dependencies(metadata, &project.manifest_path);
+    if private.is_empty() {
+        return Ok(());
+    }
+    Err(PreflightError::MissingGitCredentials {
+        dependencies: private.iter().map(ToString::to_string).collect(),
     })
 }
 
-pub use ci::ci_init;
+/// `propel.toml` settings that have no effect in this project, e.g.
+/// `[build]` fields of an ejected project.
+pub(crate) fn config_warnings(config: &PropelConfig, project_dir: &Path) -> Vec<ConfigWarning> {
+    let flags = ProjectFlags {
+        ejected: propel_build::eject::is_ejected(project_dir),
+    };
+    propel_core::check_config(config, &flags)
+}
+
+/// Warning when `.propel/Dockerfile` exists but git does not track it: a
+/// deploy from a CI checkout would silently use the generated Dockerfile.
+///
+/// Outside git there is no CI checkout to miss it, so there is no warning.
+pub(crate) fn untracked_eject_warning(project_dir: &Path) -> anyhow::Result<Option<String>> {
+    if !propel_build::eject::is_ejected(project_dir) {
+        return Ok(None);
+    }
+    match bundle::is_tracked(project_dir, Path::new(".propel/Dockerfile")) {
+        Ok(true) | Err(BundleError::NotARepo { .. }) => return Ok(None),
+        Ok(false) => {}
+        Err(e) => return Err(e.into()),
+    }
+    Ok(Some(
+        "Warning: .propel/Dockerfile is not tracked by git — deploys from CI will not see \
+         it and use the generated Dockerfile instead. Commit .propel/ to keep it."
+            .to_owned(),
+    ))
+}
+
+/// Directory to bundle from: the workspace root for a workspace member
+/// ([`CargoProject::bundle_root`]), else `project_dir` as given, so a
+/// standalone package keeps its short relative paths in output.
+pub(crate) fn bundle_source<'a>(project: &'a CargoProject, project_dir: &'a Path) -> &'a Path {
+    match project.member_path() {
+        Some(_) => project.bundle_root(),
+        None => project_dir,
+    }
+}
+
+/// Keep a build log under `.propel/` out of git, so writing it does not
+/// trip the next deploy's dirty check. Logs elsewhere are the user's call.
+pub(crate) fn ignore_build_logs(project_dir: &Path, build_log: &Path) -> std::io::Result<()> {
+    let propel_dir = project_dir.join(".propel");
+    let relative = match build_log.strip_prefix(project_dir) {
+        Ok(relative) => relative,
+        Err(_) => build_log,
+    };
+    let first = relative
+        .components()
+        .find(|c| !matches!(c, std::path::Component::CurDir));
+    if first != Some(std::path::Component::Normal(".propel".as_ref())) {
+        return Ok(());
+    }
+    let ignore_file = propel_dir.join(".gitignore");
+    if ignore_file.exists() {
+        return Ok(());
+    }
+    std::fs::create_dir_all(&propel_dir)?;
+    std::fs::write(ignore_file, "# Written by propel\n*.log\n.gitignore\n")
+}
+
+/// Render the generated Dockerfile, honoring `.cargo/config.toml` when present.
+///
+/// # Errors
+///
+/// Returns [`propel_core::Error::ConflictingBuildOptions`] for
+/// `[build] offline = true` without vendored sources, and
+/// [`propel_core::Error::IncompatibleRuntimeImage`] for a runtime image
+/// that cannot run the binary.
+pub(crate) fn render_dockerfile(
+    config: &PropelConfig,
+    project: &CargoProject,
+    cargo_config: Option<&CargoConfig>,
+) -> propel_core::Result<String> {
+    config
+        .build
+        .validate_offline(cargo_config.is_some_and(|c| !c.vendor_dirs.is_empty()))?;
+    let mut options = DockerfileOptions::new(&config.build, project)
+        .port(config.cloud_run.port)
+        .lockfile_bundled(bundle::lockfile_bundled(project));
+    if let Some(cargo_config) = cargo_config {
+        options = options.cargo_config(cargo_config);
+    }
+    let generator = DockerfileGenerator::with_options(options);
+    generator.check_runtime_image()?;
+    Ok(generator.render())
+}
+
+/// Check the builder image's Rust against the package's `rust-version`,
+/// unless `[build] rust_version_check = false`. Returns a warning when the
+/// image tag names no Rust version, so the skipped check is visible.
+///
+/// # Errors
+///
+/// Returns [`propel_core::Error::BuilderImageTooOld`] for a builder older
+/// than the package's `rust-version`, and
+/// [`propel_core::Error::CargoMetadata`] when cargo cannot report it.
+pub(crate) fn check_builder_rust_version(
+    config: &PropelConfig,
+    project: &CargoProject,
+) -> propel_core::Result<Option<String>> {
+    if !config.build.rust_version_check {
+        return Ok(None);
+    }
+    let Some(rust_version) = project.rust_version()? else {
+        return Ok(None);
+    };
+    let builder = config.build.builder_image();
+    if propel_build::dockerfile::check_builder_rust_version(builder, &rust_version)?.is_some() {
+        return Ok(None);
+    }
+    Ok(Some(format!(
+        "the tag of builder image {builder:?} names no Rust version, so it was not \
+         checked against rust-version {rust_version}; set [build] rust_version_check = false \
+         to silence this"
+    )))
+}
+
+/// Write `bundle-manifest.json` into the bundle when `[build] bundle_manifest`
+/// is set, returning the manifest's own SHA-256.
+///
+/// The recorded commit carries a `-dirty` suffix when the tree has
+/// uncommitted changes, like `{{commit}}`; outside git none is recorded.
+pub(crate) fn write_bundle_manifest(
+    config: &PropelConfig,
+    project_dir: &Path,
+    bundle_dir: &Path,
+) -> anyhow::Result<Option<String>> {
+    if !config.build.bundle_manifest {
+        return Ok(None);
+    }
+    let commit = match bundle::head_commit(project_dir) {
+        Ok(mut commit) => {
+            if bundle::is_dirty(project_dir)? {
+                commit.push_str("-dirty");
+            }
+            Some(commit)
+        }
+        Err(BundleError::NotARepo { .. }) => None,
+        Err(e) => return Err(e.into()),
+    };
+    let manifest = BundleManifest::build(bundle_dir, commit.as_deref(), env!("CARGO_PKG_VERSION"))?;
+    Ok(Some(manifest.write(bundle_dir)?))
+}
+
+/// Width, in characters, of the bar [`create_bundle_reporting`] draws.
+const BUNDLE_PROGRESS_WIDTH: usize = 30;
+
+/// Bundle the source, drawing a progress bar while files are copied when
+/// stdout is a terminal.
+pub(crate) fn create_bundle_reporting(
+    options: &bundle::BundleOptions,
+) -> Result<std::path::PathBuf, BundleError> {
+    use std::io::{IsTerminal, Write as _};
+
+    if !std::io::stdout().is_terminal() {
+        println!("Bundling source...");
+        return bundle::create_bundle_with(options);
+    }
+    let mut shown = None;
+    let result = bundle::create_bundle_with_progress(options, |copied, total| {
+        let line = bundle_progress_line(copied, total, BUNDLE_PROGRESS_WIDTH);
+        if shown.as_ref() != Some(&line) {
+            print!("\r{line}");
+            // arch-lint: allow(no-silent-result-drop) reason="progress display only; a failed flush shows up with the next line"
+            let _ = std::io::stdout().flush();
+            shown = Some(line);
+        }
+    });
+    match shown {
+        Some(_) => println!(),
+        None => println!("Bundling source..."),
+    }
+    result
+}
+
+/// Progress line of [`create_bundle_reporting`], e.g.
+/// `Bundling source [#####     ]  50% of 2000 files`. The caller redraws
+/// only when it changes.
+fn bundle_progress_line(copied: usize, total: usize, width: usize) -> String {
+    let total = total.max(1);
+    let filled = copied.min(total) * width / total;
+    let percent = copied.min(total) * 100 / total;
+    format!(
+        "Bundling source [{}{}] {percent:>3}% of {total} files",
+        "#".repeat(filled),
+        " ".repeat(width - filled),
+    )
+}
+
+/// Stop before uploading a bundle larger than `[cloud_build].max_bundle_mb`.
+///
+/// With `force`, an oversized bundle yields a warning instead.
+pub(crate) fn check_bundle_size(
+    config: &PropelConfig,
+    bundle_dir: &Path,
+    force: bool,
+) -> Result<Option<String>, bundle::BundleError> {
+    let entries = bundle::bundle_entries(bundle_dir)?;
+    let size = bundle::BundleSize::measure(bundle_dir, &entries)?;
+    let limit = config.cloud_build.max_bundle_bytes();
+    if force && size.total > limit {
+        return Ok(Some(format!(
+            "Warning: uploading a {} bundle, over the {} limit (--force-large-bundle)",
+            bundle::format_size(size.total),
+            bundle::format_size(limit)
+        )));
+    }
+    size.check_limit(limit)?;
+    Ok(None)
+}
+
+/// Point `:latest` at the image just built, unless `[build].tag_strategy`
+/// already pushed it as `:latest`. Deploys use the immutable tag, so a
+/// failure only costs the alias and is passed to `say` as a warning.
+pub(crate) async fn tag_latest(
+    client: &GcloudClient,
+    ctx: &ProjectContext,
+    image_tag: &str,
+    mut say: impl FnMut(String),
+) -> anyhow::Result<()> {
+    if ctx.config.build.tag_strategy == TagStrategy::Latest {
+        return Ok(());
+    }
+    let latest = ctx.image_ref("latest")?;
+    if let Err(e) = client.add_image_tag(image_tag, &latest).await {
+        say(format!("Warning: could not tag the image as {latest}: {e}"));
+    }
+    Ok(())
+}
+
+/// Warnings about `.cargo/config.toml` sources the image build may not see.
+pub(crate) fn cargo_config_warnings(
+    project_dir: &Path,
+    cargo_config: &CargoConfig,
+) -> anyhow::Result<Vec<String>> {
+    let mut warnings = Vec::new();
+    if bundle::is_ignored(project_dir, Path::new(cargo_config.path))? {
+        warnings.push(format!(
+            "Warning: {} is gitignored — bundling it anyway so the image build uses it",
+            cargo_config.path
+        ));
+    }
+    for dir in &cargo_config.vendor_dirs {
+        if bundle::is_ignored(project_dir, Path::new(dir))? {
+            warnings.push(format!(
+                "Warning: vendored sources in {dir}/ are gitignored and will not be bundled; \
+                 the image build will fail to find them"
+            ));
+        }
+    }
+    for dir in &cargo_config.external_dirs {
+        warnings.push(format!(
+            "Warning: {} points at {dir}, outside the project; \
+             it cannot be bundled and the image build will not find it",
+            cargo_config.path
+        ));
+    }
+    Ok(warnings)
+}
+
+/// Resolve `{{…}}` templates in `[build.env]` and `[cloud_run.env]` in place.
+///
+/// `{{commit}}` gets a `-dirty` suffix when the tree has uncommitted changes.
+pub(crate) fn resolve_env_templates(ctx: &mut ProjectContext) -> anyhow::Result<()> {
+    let templated = ctx
+        .config
+        .build
+        .env
+        .values()
+        .chain(ctx.config.cloud_run.env.values())
+        .any(|v| v.contains("{{"));
+    if !templated {
+        return Ok(());
+    }
+
+    let mut commit = bundle::head_commit(&ctx.dir)?;
+    if bundle::is_dirty(&ctx.dir)? {
+        commit.push_str("-dirty");
+    }
+    let deploy_time = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    let service = ctx.service_name().to_owned();
+    let region = ctx.region().to_owned();
+    let ProjectContext {
+        config, project, ..
+    } = ctx;
+
+    config.build.env = propel_core::substitute_env(
+        "build.env",
+        &config.build.env,
+        &TemplateContext::build(&commit, &project.version),
+    )?;
+    config.cloud_run.env = propel_core::substitute_env(
+        "cloud_run.env",
+        &config.cloud_run.env,
+        &TemplateContext::runtime(&commit, &project.version, &service, &region, &deploy_time),
+    )?;
+    Ok(())
+}
+
+/// Check that the local gcloud has the component the `[cloud_run]`
+/// command group needs, and say so when that group is not plain `run`.
+pub(crate) async fn check_run_command_group(
+    client: &GcloudClient,
+    config: &PropelConfig,
+) -> Result<Option<String>, PreflightError> {
+    Ok(
+        match client.check_run_command_group(&config.cloud_run).await? {
+            RunCommandGroup::Ga => None,
+            RunCommandGroup::Beta => {
+                Some("[cloud_run] uses beta-only flags — running `gcloud beta run`".to_owned())
+            }
+        },
+    )
+}
+
+/// Client for the doctor: API checks come from the project's
+/// [`DiskCache`] unless `no_cache`, or without a GCP project to cache for
+/// (which also keeps `.propel/cache` out of non-project directories).
+pub(crate) fn doctor_client(
+    project_dir: &Path,
+    project_id: Option<&str>,
+    no_cache: bool,
+) -> GcloudClient {
+    let client = GcloudClient::new();
+    if no_cache || project_id.is_none() {
+        return client;
+    }
+    client.doctor_cache(DiskCache::new(project_dir))
+}
+
+/// gcloud components the doctor requires for `config`.
+pub(crate) fn required_components(config: &PropelConfig) -> Vec<&'static str> {
+    RunCommandGroup::for_config(&config.cloud_run)
+        .component()
+        .into_iter()
+        .collect()
+}
+
+/// Fill in the doctor rows that need no gcloud: `propel.toml`, the Rust
+/// toolchain, and git.
+pub(crate) fn local_doctor_checks(report: &mut DoctorReport, project_dir: &Path) {
+    report.config_file = config_file_check(project_dir);
+    report.toolchain = tool_version_check("cargo", "install: https://rustup.rs");
+    report.git = tool_version_check("git", "install: https://git-scm.com/downloads");
+}
+
+/// Doctor row for `propel.toml`: loads and validates it as deploy would,
+/// failing with the parse error (line and column included) or validation
+/// error, and listing keys propel ignores.
+fn config_file_check(project_dir: &Path) -> CheckResult {
+    if !project_dir.join("propel.toml").exists() {
+        return CheckResult::fail("Not found");
+    }
+    match PropelConfig::load_reporting_unknown_keys(project_dir) {
+        Ok((_, unknown_keys)) if unknown_keys.is_empty() => CheckResult::ok("Valid"),
+        Ok((_, unknown_keys)) => {
+            let mut detail = "Valid, but these keys are unknown and ignored:".to_owned();
+            for key in &unknown_keys {
+                detail.push_str(&format!("\n  {key}"));
+            }
+            CheckResult::ok(&detail)
+        }
+        Err(e) => CheckResult::fail(format!("{:#}", anyhow::Error::new(e)).trim_end()),
+    }
+}
+
+/// Doctor note on a `propel-sdk` dependency in the project at `project_dir`.
+///
+/// A directory without a Cargo project, or one cargo cannot resolve, gets
+/// no note — the other doctor rows report that.
+pub(crate) fn deprecated_sdk_note(project_dir: &Path) -> Option<String> {
+    let project = match CargoProject::discover(project_dir) {
+        Ok(project) => project,
+        Err(e) => {
+            tracing::debug!(error = %e, "skipping propel-sdk check");
+            return None;
+        }
+    };
+    let metadata = resolve_dependency_graph(project_dir)?;
+    propel_core::detect_deprecated_sdk(&metadata, &project.manifest_path)
+        .map(|found| found.to_string())
+}
+
+/// `<tool> --version` as a doctor row.
+fn tool_version_check(tool: &str, remediation: &str) -> CheckResult {
+    match std::process::Command::new(tool).arg("--version").output() {
+        Ok(output) if output.status.success() => {
+            CheckResult::ok(String::from_utf8_lossy(&output.stdout).trim())
+        }
+        Ok(output) => CheckResult::fail(&format!(
+            "`{tool} --version` failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            CheckResult::fail(&format!("{tool} not found — {remediation}"))
+        }
+        Err(e) => CheckResult::fail(&format!("could not run {tool}: {e}")),
+    }
+}
+
+/// Revisions of the service, newest first. A service missing from
+/// `region` is reported with the regions it was found in instead.
+pub(crate) async fn service_revisions(
+    client: &GcloudClient,
+    project_id: &str,
+    service_name: &str,
+    region: &str,
+) -> Result<Vec<RevisionInfo>, CliError> {
+    match client
+        .list_revisions(service_name, project_id, region)
+        .await
+    {
+        Ok(revisions) => Ok(revisions),
+        Err(e) if e.is_service_not_found() => {
+            let message =
+                match find_in_other_regions(client, project_id, service_name, region).await {
+                    Some(hint) => format!("{service_name} not found in {region}; {hint}"),
+                    None => format!("{service_name} is not deployed in {region}"),
+                };
+            Err(CliError::Deploy(anyhow::Error::msg(message)))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// `Access: public (allUsers may invoke) · ingress all` for `propel
+/// status`. The line is informational, so a failed lookup (e.g. no
+/// permission to read the service's IAM policy) is reported in it.
+pub(crate) async fn access_line(
+    client: &GcloudClient,
+    service_name: &str,
+    project_id: &str,
+    region: &str,
+) -> String {
+    match client
+        .service_access(service_name, project_id, region)
+        .await
+    {
+        Ok(access) => format!("Access: {access}"),
+        Err(e) => format!("Access: unknown ({e})"),
+    }
+}
+
+/// Note that the service is deployed private, or `None` for the default
+/// public service.
+pub(crate) fn private_service_notice(config: &PropelConfig) -> Option<String> {
+    if config.cloud_run.allow_unauthenticated {
+        return None;
+    }
+    Some(
+        "Note: [cloud_run] allow_unauthenticated = false — the service is private;          callers need roles/run.invoker (`propel iam grant-invoker MEMBER`)"
+            .to_owned(),
+    )
+}
+
+/// `args` joined for a shell: arguments with characters a shell treats
+/// specially are single-quoted.
+pub(crate) fn shell_command(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| {
+            let plain = !arg.is_empty()
+                && arg.chars().all(|c| {
+                    c.is_ascii_alphanumeric()
+                        || matches!(c, '-' | '_' | '.' | '/' | '=' | ':' | ',' | '@' | '%' | '+')
+                });
+            if plain {
+                arg.clone()
+            } else {
+                format!("'{}'", arg.replace('\'', r"'\''"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Deploy-summary warning about the idle cost of `min_instances`, or `None`
+/// when no instance is kept warm.
+pub(crate) fn idle_cost_notice(config: &PropelConfig) -> Option<String> {
+    let min_instances = config.cloud_run.min_instances;
+    if min_instances == 0 {
+        return None;
+    }
+    let cost = match propel_cloud::estimate_monthly_cost(&config.cloud_run, &config.project.region)
+    {
+        Ok(estimate) => format!("~${:.2}/month", estimate.idle_monthly),
+        Err(e) => {
+            tracing::warn!(error = %e, "skipping idle cost estimate");
+            "an unknown amount".to_owned()
+        }
+    };
+    Some(format!(
+        "Note: min_instances = {min_instances} keeps instance(s) running while idle, \
+         costing {cost} (rough estimate; see `propel costs`)."
+    ))
+}
+
+/// Secrets from Secret Manager to inject into the Cloud Run service.
+///
+/// Every `[secrets]` entry is mounted from the secret it names, and each
+/// must exist. The other secrets are discovered: only those under
+/// `[project].secret_prefix` are considered, each mounted under its key.
+/// The `[build].git_credentials_secret` is build-only and never injected,
+/// and neither is a secret `[secrets]` already mounts. With
+/// `[cloud_run].secrets` set, only the listed secrets are discovered, and
+/// each must exist.
+pub(crate) fn select_secrets(
+    config: &PropelConfig,
+    discovered: Vec<String>,
+) -> anyhow::Result<Vec<SecretBinding>> {
+    let missing: Vec<&str> = config
+        .secrets
+        .values()
+        .filter(|secret| !discovered.contains(&secret.name))
+        .map(|secret| secret.name.as_str())
+        .collect();
+    if !missing.is_empty() {
+        anyhow::bail!(
+            "[secrets] maps env vars to secret(s) not found in Secret Manager: {}\n\
+             Create them, or fix the names in propel.toml.",
+            missing.join(", ")
+        );
+    }
+    let mut bindings: Vec<SecretBinding> = config
+        .secrets
+        .iter()
+        .map(|(env, secret)| SecretBinding {
+            env: env.clone(),
+            secret: secret.name.clone(),
+            version: Some(secret.version.clone()),
+        })
+        .collect();
+
+    let namespace = config.project.secret_namespace();
+    let unmapped: Vec<String> = discovered
+        .into_iter()
+        .filter(|name| !config.secrets.values().any(|secret| &secret.name == name))
+        .collect();
+    let mut keys = namespace.keys(&unmapped);
+    keys.retain(|key| {
+        Some(key) != config.build.git_credentials_secret.as_ref()
+            && !config.secrets.contains_key(key)
+    });
+    if let Some(wanted) = &config.cloud_run.secrets {
+        let missing: Vec<String> = wanted
+            .names()
+            .filter(|name| {
+                !keys.iter().any(|key| key == name) && !config.secrets.contains_key(*name)
+            })
+            .map(|name| namespace.secret_name(name))
+            .collect();
+        if !missing.is_empty() {
+            anyhow::bail!(
+                "[cloud_run].secrets lists secret(s) not found in Secret Manager: {}\n\
+                 Create them with `propel secret set NAME=VALUE`, or remove them from propel.toml.",
+                missing.join(", ")
+            );
+        }
+        keys.retain(|key| wanted.contains(key));
+    }
+    bindings.extend(keys.iter().map(|key| namespace.binding(key)));
+    Ok(bindings)
+}
+
+/// Warnings for injected secrets without an enabled version, which make
+/// new revisions fail at startup (`[cloud_run].check_secret_versions`).
+/// A secret that cannot be described is warned about too; neither stops
+/// the deploy.
+pub(crate) async fn secret_version_warnings(
+    client: &GcloudClient,
+    project_id: &str,
+    secrets: &[SecretBinding],
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for binding in secrets {
+        match client.describe_secret(project_id, &binding.secret).await {
+            Ok(info) => warnings.extend(missing_version_warning(binding, &info)),
+            // arch-lint: allow(no-error-swallowing) reason="the check is advisory; the deploy itself reports unreadable secrets"
+            Err(e) => warnings.push(format!(
+                "could not check the versions of secret '{}': {:#}",
+                binding.secret,
+                anyhow::Error::new(e)
+            )),
+        }
+    }
+    warnings
+}
+
+/// The warning for `binding` when `info` shows no enabled version.
+fn missing_version_warning(binding: &SecretBinding, info: &SecretInfo) -> Option<String> {
+    if info.has_enabled_version() {
+        return None;
+    }
+    let fix = match binding.version {
+        // Discovered under its key, so `secret set` writes to it.
+        None => format!("propel secret set {}=...", binding.env),
+        Some(_) => format!(
+            "gcloud secrets versions add {} --data-file=-",
+            binding.secret
+        ),
+    };
+    Some(format!(
+        "secret '{}' ({}) has no enabled version, so new revisions will fail to start; \
+         add one with `{fix}`",
+        binding.secret, binding.env
+    ))
+}
+
+/// Warning when the ejected Dockerfile builds or copies a binary other
+/// than `binary` — typically the old name after the crate was renamed,
+/// which would otherwise fail deep inside the Docker build. Includes a
+/// `sed` command that rewrites the references.
+pub(crate) fn ejected_binary_warning(dockerfile: &str, binary: &str) -> Option<String> {
+    let mismatched = propel_build::eject::mismatched_binary_references(dockerfile, binary);
+    if mismatched.is_empty() {
+        return None;
+    }
+    let mut names: Vec<&str> = mismatched.iter().map(|r| r.name.as_str()).collect();
+    names.sort_unstable();
+    names.dedup();
+
+    let mut warning = format!(
+        ".propel/Dockerfile refers to binary {} but the project builds `{binary}`:",
+        names
+            .iter()
+            .map(|name| format!("`{name}`"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    for reference in &mismatched {
+        let text = match reference.kind {
+            propel_build::eject::BinaryReferenceKind::BinFlag => {
+                format!("--bin {}", reference.name)
+            }
+            propel_build::eject::BinaryReferenceKind::ReleasePath => {
+                format!("target/release/{}", reference.name)
+            }
+        };
+        warning.push_str(&format!("\n  line {}: {text}", reference.line));
+    }
+    let mut sed = String::from("sed -i");
+    for name in &names {
+        sed.push_str(&format!(
+            " -e 's/--bin\\([ =]\\){name}\\b/--bin\\1{binary}/' -e 's#target/release/{name}\\b#target/release/{binary}#'"
+        ));
+    }
+    warning.push_str(&format!("\nFix it with:\n  {sed} .propel/Dockerfile"));
+    Some(warning)
+}
+
+/// Email of the service account builds run as in `[ci].deploy_via_cloud_build` mode.
+pub(crate) fn build_service_account(project_id: &str) -> String {
+    format!("{}@{project_id}.iam.gserviceaccount.com", ci::BUILD_SA_ID)
+}
+
+/// Render the `cloudbuild.yaml` this deploy needs, or `None` when a plain
+/// `gcloud builds submit --tag` suffices.
+///
+/// An explicit config is needed for a git credentials secret, for a
+/// `[build].platform` or `[build].cache_mounts` (built with
+/// `docker buildx`), and for
+/// `[ci].deploy_via_cloud_build`, which appends the Cloud Run deploy
+/// (with `secrets` injected) as the final build step.
+pub(crate) fn cloud_build_yaml(
+    config: &PropelConfig,
+    project_id: &str,
+    image_tag: &str,
+    service_name: &str,
+    secrets: &[SecretBinding],
+    labels: &PropelLabels,
+) -> Option<String> {
+    let via_cloud_build = config.ci.deploy_via_cloud_build;
+    if config.build.git_credentials_secret.is_none()
+        && config.build.platform.is_none()
+        && !config.build.cache_mounts
+        && !via_cloud_build
+    {
+        return None;
+    }
+
+    let deploy_args;
+    let service_account;
+    let git_credentials_secret;
+    let mut yaml = CloudBuildYaml::new(project_id, image_tag);
+    if let Some(key) = &config.build.git_credentials_secret {
+        git_credentials_secret = config.project.secret_namespace().secret_name(key);
+        yaml = yaml.git_credentials_secret(&git_credentials_secret);
+    }
+    if let Some(bucket) = &config.cloud_build.logs_bucket {
+        yaml = yaml.logs_bucket(bucket);
+    }
+    if let Some(platform) = config.build.platform {
+        yaml = yaml.platform(platform);
+    }
+    if config.build.cache_mounts {
+        yaml = yaml.cache_mounts();
+    }
+
+    if via_cloud_build {
+        deploy_args = propel_cloud::cloud_run_deploy_args(
+            service_name,
+            image_tag,
+            project_id,
+            &config.project.region,
+            &config.cloud_run,
+            secrets,
+            labels,
+        );
+        service_account = build_service_account(project_id);
+        yaml = yaml.deploy_step(DeployStep {
+            gcloud_args: &deploy_args,
+            service_account: &service_account,
+        });
+    }
+    Some(yaml.render())
+}
+
+/// Labels identifying a deploy by propel version and
+/// [`PropelConfig::config_hash`]. Pass the config before env templates are
+/// resolved, so the hash only changes when `propel.toml` does.
+pub(crate) fn propel_labels(config: &PropelConfig) -> PropelLabels {
+    PropelLabels::new(env!("CARGO_PKG_VERSION"), &config.config_hash())
+}
+
+pub use canary::{abort_canary, promote};
+pub use ci::{ci_init, ci_status};
+pub use config::config_validate;
+pub use costs::costs;
 pub use deploy::deploy;
 pub use destroy::destroy;
 pub use doctor::doctor;
 pub use eject::eject;
+pub use fleet::fleet_list;
+pub use history::history;
+pub use iam::iam_grant_invoker;
 pub use init::init_project;
 pub use logs::logs;
 pub use new::new_project;
-pub use secret::{secret_delete, secret_list, secret_set};
+pub use rollback::rollback;
+pub use secret::{secret_delete, secret_import, secret_info, secret_list, secret_pull, secret_set};
+pub use setup::setup;
 pub use status::status;
+pub use update::update;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use propel_core::{SecretRef, SecretVersions};
+
+    fn config_row(toml: &str) -> CheckResult {
+        let tmp = tempfile::TempDir::new().unwrap();
+        std::fs::write(tmp.path().join("propel.toml"), toml).unwrap();
+        config_file_check(tmp.path())
+    }
+
+    #[test]
+    fn doctor_config_row_reports_parse_errors_with_position() {
+        let row = config_row("[cloud_run]\nmemory = 512Mi\n");
+
+        assert!(!row.passed);
+        assert!(
+            row.detail.contains("failed to parse config"),
+            "{}",
+            row.detail
+        );
+        assert!(row.detail.contains("line 2, column 13"), "{}", row.detail);
+    }
+
+    #[test]
+    fn doctor_config_row_reports_validation_failures() {
+        let row = config_row("[build]\ninclude = [\"\"]\n");
+
+        assert!(!row.passed);
+        assert!(
+            row.detail
+                .contains("path must not be empty or whitespace-only"),
+            "{}",
+            row.detail
+        );
+    }
+
+    #[test]
+    fn doctor_config_row_lists_unknown_keys() {
+        let row = config_row(
+            "[build]\nbase_imag = \"rust:1.93\"\n\n[build.env]\nANY_KEY = \"ok\"\n\n\
+             [cloud_run]\nmemroy = \"1Gi\"\n",
+        );
+
+        assert!(row.passed);
+        assert_eq!(
+            row.detail,
+            "Valid, but these keys are unknown and ignored:\n  build.base_imag\n  cloud_run.memroy"
+        );
+        assert_eq!(config_row("[build]\n").detail, "Valid");
+    }
+
+    #[test]
+    fn bundle_progress_line_fills_the_bar() {
+        assert_eq!(
+            bundle_progress_line(0, 2000, 10),
+            "Bundling source [          ]   0% of 2000 files"
+        );
+        assert_eq!(
+            bundle_progress_line(1000, 2000, 10),
+            "Bundling source [#####     ]  50% of 2000 files"
+        );
+        assert_eq!(
+            bundle_progress_line(2000, 2000, 10),
+            "Bundling source [##########] 100% of 2000 files"
+        );
+    }
+
+    #[test]
+    fn doctor_requires_beta_only_for_beta_only_flags() {
+        let mut config = PropelConfig::default();
+        assert!(required_components(&config).is_empty());
+
+        config.cloud_run.extra_flags = vec!["--gpu=1".to_owned()];
+        assert_eq!(required_components(&config), ["beta"]);
+    }
+
+    fn secrets(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| (*s).to_owned()).collect()
+    }
+
+    fn bindings(names: &[&str]) -> Vec<SecretBinding> {
+        names.iter().map(|s| (*s).into()).collect()
+    }
+
+    #[test]
+    fn cloud_build_yaml_only_when_needed() {
+        let mut config = PropelConfig::default();
+        let labels = propel_labels(&config);
+        assert!(cloud_build_yaml(&config, "proj", "img:latest", "svc", &[], &labels).is_none());
+
+        config.build.platform = Some(propel_core::Platform::Arm64);
+        let yaml = cloud_build_yaml(&config, "proj", "img:latest", "svc", &[], &labels).unwrap();
+        assert!(yaml.contains("docker buildx build --platform linux/arm64"));
+
+        config.build.platform = None;
+        config.build.cache_mounts = true;
+        let yaml = cloud_build_yaml(&config, "proj", "img:latest", "svc", &[], &labels).unwrap();
+        assert!(yaml.contains("docker buildx build --load -t img:latest ."));
+    }
+
+    #[test]
+    fn gitignore_gains_bundle_dir_but_not_propel_dir() {
+        let updated = gitignore_with_propel_entries("/target\n.env").unwrap();
+        assert!(updated.starts_with("/target\n.env\n# propel:"));
+        assert!(updated.ends_with(".propel-bundle/\n"));
+        assert!(!updated.lines().any(|l| l == ".propel/" || l == ".propel"));
+
+        assert_eq!(
+            gitignore_with_propel_entries("").unwrap(),
+            GITIGNORE_PROPEL_ENTRIES
+        );
+    }
+
+    #[test]
+    fn gitignore_already_ignoring_bundle_dir_is_left_alone() {
+        for existing in ["/target\n.propel-bundle/\n", "/.propel-bundle\n"] {
+            assert_eq!(
+                gitignore_with_propel_entries(existing),
+                None,
+                "{existing:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn select_secrets_defaults_to_all_but_git_credentials() {
+        let mut config = PropelConfig::default();
+        config.build.git_credentials_secret = Some("GIT_CREDENTIALS".to_owned());
+
+        let selected =
+            select_secrets(&config, secrets(&["API_KEY", "GIT_CREDENTIALS", "JWT"])).unwrap();
+
+        assert_eq!(selected, bindings(&["API_KEY", "JWT"]));
+    }
+
+    #[test]
+    fn select_secrets_keeps_only_configured() {
+        let mut config = PropelConfig::default();
+        config.cloud_run.secrets = Some(SecretVersions::from([("JWT", "3")]));
+
+        let selected = select_secrets(&config, secrets(&["API_KEY", "JWT"])).unwrap();
+
+        assert_eq!(selected, bindings(&["JWT"]));
+    }
+
+    #[test]
+    fn select_secrets_maps_prefixed_secrets_to_their_keys() {
+        let mut config = PropelConfig::default();
+        config.project.secret_prefix = Some("staging_".to_owned());
+        config.build.git_credentials_secret = Some("GIT_CREDENTIALS".to_owned());
+
+        let selected = select_secrets(
+            &config,
+            secrets(&["FOO", "prod_FOO", "staging_FOO", "staging_GIT_CREDENTIALS"]),
+        )
+        .unwrap();
+
+        assert_eq!(
+            selected,
+            [SecretBinding {
+                env: "FOO".to_owned(),
+                secret: "staging_FOO".to_owned(),
+                version: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn select_secrets_names_missing_prefixed_secrets() {
+        let mut config = PropelConfig::default();
+        config.project.secret_prefix = Some("staging_".to_owned());
+        config.cloud_run.secrets = Some(SecretVersions::from([("JWT", "latest")]));
+
+        // An unprefixed JWT belongs to another environment.
+        let err = select_secrets(&config, secrets(&["JWT"])).unwrap_err();
+
+        assert!(err.to_string().contains("staging_JWT"), "{err}");
+    }
+
+    fn secret_ref(value: &str) -> SecretRef {
+        SecretRef::try_from(value.to_owned()).unwrap()
+    }
+
+    #[test]
+    fn select_secrets_pins_mapped_secret_versions() {
+        let mut config = PropelConfig::default();
+        config
+            .secrets
+            .insert("DATABASE_URL".to_owned(), secret_ref("prod-db-url:3"));
+
+        let selected = select_secrets(&config, secrets(&["prod-db-url"])).unwrap();
+
+        assert_eq!(
+            selected,
+            [SecretBinding {
+                env: "DATABASE_URL".to_owned(),
+                secret: "prod-db-url".to_owned(),
+                version: Some("3".to_owned()),
+            }]
+        );
+    }
+
+    #[test]
+    fn select_secrets_discovers_around_mapped_entries() {
+        let mut config = PropelConfig::default();
+        config
+            .secrets
+            .insert("DATABASE_URL".to_owned(), secret_ref("prod-db-url"));
+        config
+            .secrets
+            .insert("JWT".to_owned(), secret_ref("shared-jwt:7"));
+
+        // JWT is mapped, so the discovered JWT secret is not mounted too.
+        let selected = select_secrets(
+            &config,
+            secrets(&["API_KEY", "JWT", "prod-db-url", "shared-jwt"]),
+        )
+        .unwrap();
+
+        let mut expected = vec![
+            SecretBinding {
+                env: "DATABASE_URL".to_owned(),
+                secret: "prod-db-url".to_owned(),
+                version: Some("latest".to_owned()),
+            },
+            SecretBinding {
+                env: "JWT".to_owned(),
+                secret: "shared-jwt".to_owned(),
+                version: Some("7".to_owned()),
+            },
+        ];
+        expected.extend(bindings(&["API_KEY"]));
+        assert_eq!(selected, expected);
+    }
+
+    #[test]
+    fn select_secrets_rejects_mapped_secret_missing_from_secret_manager() {
+        let mut config = PropelConfig::default();
+        config
+            .secrets
+            .insert("DATABASE_URL".to_owned(), secret_ref("prod-db-url:3"));
+
+        let err = select_secrets(&config, secrets(&["API_KEY"])).unwrap_err();
+
+        assert!(err.to_string().contains("[secrets]"), "{err}");
+        assert!(err.to_string().contains("prod-db-url"), "{err}");
+    }
+
+    #[test]
+    fn select_secrets_allowlist_may_name_mapped_env_vars() {
+        let mut config = PropelConfig::default();
+        config
+            .secrets
+            .insert("DATABASE_URL".to_owned(), secret_ref("prod-db-url"));
+        config.cloud_run.secrets = Some(SecretVersions::from([
+            ("DATABASE_URL", "latest"),
+            ("JWT", "latest"),
+        ]));
+
+        let selected =
+            select_secrets(&config, secrets(&["API_KEY", "JWT", "prod-db-url"])).unwrap();
+
+        let envs: Vec<&str> = selected.iter().map(|b| b.env.as_str()).collect();
+        assert_eq!(envs, ["DATABASE_URL", "JWT"]);
+    }
+
+    fn secret_info(states: &[propel_cloud::SecretVersionState]) -> SecretInfo {
+        SecretInfo {
+            name: "DB_URL".to_owned(),
+            create_time: "2025-01-02T03:04:05Z".to_owned(),
+            versions: states
+                .iter()
+                .enumerate()
+                .map(|(i, state)| propel_cloud::SecretVersionInfo {
+                    version: (states.len() - i).to_string(),
+                    state: *state,
+                    create_time: String::new(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn missing_version_warning_only_without_an_enabled_version() {
+        use propel_cloud::SecretVersionState::{Destroyed, Disabled, Enabled};
+        let binding = SecretBinding::from("DB_URL");
+
+        assert_eq!(
+            missing_version_warning(&binding, &secret_info(&[Disabled, Enabled])),
+            None
+        );
+        let warning =
+            missing_version_warning(&binding, &secret_info(&[Disabled, Destroyed])).unwrap();
+        assert!(warning.contains("no enabled version"), "{warning}");
+        assert!(
+            warning.contains("propel secret set DB_URL=..."),
+            "{warning}"
+        );
+        assert!(missing_version_warning(&binding, &secret_info(&[])).is_some());
+    }
+
+    #[test]
+    fn missing_version_warning_for_mapped_secret_names_the_secret() {
+        let binding = SecretBinding {
+            env: "DATABASE_URL".to_owned(),
+            secret: "prod-db-url".to_owned(),
+            version: Some("latest".to_owned()),
+        };
+
+        let warning = missing_version_warning(&binding, &secret_info(&[])).unwrap();
+
+        assert!(
+            warning.contains("gcloud secrets versions add prod-db-url"),
+            "{warning}"
+        );
+    }
+
+    #[test]
+    fn ejected_binary_warning_lists_references_and_a_fix() {
+        let dockerfile = "RUN cargo build --release --bin old\n\
+                          COPY --from=builder /app/target/release/old /usr/local/bin/app\n";
+
+        let warning = ejected_binary_warning(dockerfile, "api").unwrap();
+
+        assert!(warning.contains("binary `old`"), "{warning}");
+        assert!(warning.contains("line 1: --bin old"), "{warning}");
+        assert!(warning.contains("line 2: target/release/old"), "{warning}");
+        assert!(
+            warning.contains(r"s#target/release/old\b#target/release/api#"),
+            "{warning}"
+        );
+    }
+
+    #[test]
+    fn ejected_binary_warning_none_when_names_match() {
+        let dockerfile = "RUN cargo build --release --bin api\n\
+                          COPY --from=builder /app/target/release/api /usr/local/bin/app\n";
+
+        assert_eq!(ejected_binary_warning(dockerfile, "api"), None);
+    }
+
+    #[test]
+    fn cloud_build_yaml_reads_prefixed_git_credentials() {
+        let mut config = PropelConfig::default();
+        config.project.secret_prefix = Some("staging_".to_owned());
+        config.build.git_credentials_secret = Some("GIT_CREDENTIALS".to_owned());
+        let labels = propel_labels(&config);
+
+        let yaml = cloud_build_yaml(&config, "proj", "img:latest", "svc", &[], &labels).unwrap();
+
+        assert!(yaml.contains("secrets/staging_GIT_CREDENTIALS/"), "{yaml}");
+    }
+
+    #[test]
+    fn select_secrets_rejects_configured_secret_missing_from_secret_manager() {
+        let mut config = PropelConfig::default();
+        config.cloud_run.secrets = Some(SecretVersions::from([("JWT", "latest"), ("TYPO", "1")]));
+
+        let err = select_secrets(&config, secrets(&["JWT"])).unwrap_err();
+
+        assert!(err.to_string().contains("TYPO"), "{err}");
+    }
+
+    #[test]
+    fn build_log_under_propel_dir_stays_out_of_git() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let dir = tmp.path();
+        let git = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .args(args)
+                .current_dir(dir)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {args:?}");
+        };
+        git(&["init", "-q"]);
+        git(&[
+            "-c",
+            "user.email=t@t",
+            "-c",
+            "user.name=t",
+            "commit",
+            "-q",
+            "--allow-empty",
+            "-m",
+            "init",
+        ]);
+
+        let log = dir.join(DEFAULT_BUILD_LOG);
+        ignore_build_logs(dir, &log).unwrap();
+        std::fs::write(&log, "build output\n").unwrap();
+
+        assert!(!bundle::is_dirty(dir).unwrap());
+    }
+
+    #[test]
+    fn build_log_elsewhere_is_left_alone() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        ignore_build_logs(tmp.path(), &tmp.path().join("logs/build.log")).unwrap();
+        assert!(!tmp.path().join(".propel").exists());
+    }
+
+    #[test]
+    fn shell_command_quotes_only_special_arguments() {
+        let args = [
+            "run",
+            "deploy",
+            "--format",
+            "value(status.url)",
+            "--set-env-vars",
+            "GREETING=it's",
+            "",
+        ]
+        .map(str::to_owned);
+
+        assert_eq!(
+            shell_command(&args),
+            r#"run deploy --format 'value(status.url)' --set-env-vars 'GREETING=it'\''s' ''"#
+        );
+    }
+
+    #[test]
+    fn builder_rust_version_check_warns_when_skipped_and_can_be_disabled() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(tmp.path().join("src")).unwrap();
+        std::fs::write(
+            tmp.path().join("Cargo.toml"),
+            "[package]\nname = \"api\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\
+             rust-version = \"1.83\"\n",
+        )
+        .unwrap();
+        std::fs::write(tmp.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        let project = CargoProject::discover(tmp.path()).unwrap();
+        let mut config = PropelConfig::default();
+
+        assert_eq!(check_builder_rust_version(&config, &project).unwrap(), None);
+
+        config.build.base_image = "rust:1.81-bookworm".to_owned();
+        let err = check_builder_rust_version(&config, &project).unwrap_err();
+        assert!(
+            matches!(err, propel_core::Error::BuilderImageTooOld { .. }),
+            "{err:?}"
+        );
+
+        config.build.chef_image = Some("registry.example.com/builder:v3".to_owned());
+        let warning = check_builder_rust_version(&config, &project)
+            .unwrap()
+            .unwrap();
+        assert!(
+            warning.contains("registry.example.com/builder:v3"),
+            "{warning}"
+        );
+        assert!(warning.contains("rust-version 1.83.0"), "{warning}");
+
+        config.build.chef_image = None;
+        config.build.rust_version_check = false;
+        assert_eq!(check_builder_rust_version(&config, &project).unwrap(), None);
+    }
+}
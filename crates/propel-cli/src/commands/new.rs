@@ -1,10 +1,20 @@
+use super::CliError;
 use std::path::Path;
+use std::process::Command;
 
 /// Scaffold a new Propel project.
-pub async fn new_project(name: &str) -> anyhow::Result<()> {
+pub async fn new_project(name: &str) -> Result<(), CliError> {
+    validate_package_name(name)?;
+    let service_name = propel_core::derive_service_name(name).ok_or_else(|| {
+        anyhow::anyhow!(
+            "'{name}' has no letters to derive a Cloud Run service name from — \
+             choose a name that contains at least one ASCII letter"
+        )
+    })?;
+
     let project_dir = Path::new(name);
     if project_dir.exists() {
-        anyhow::bail!("directory '{}' already exists", name);
+        return Err(anyhow::anyhow!("directory '{name}' already exists").into());
     }
 
     std::fs::create_dir_all(project_dir.join("src"))?;
@@ -42,6 +52,10 @@ async fn main() {
 
     let app = Router::new()
         .route("/health", get(health))
+        // To warm up before traffic (e.g. open a DB pool), add `propel = "0.4"`
+        // to Cargo.toml, set [cloud_run].warmup_handler = true in propel.toml,
+        // and uncomment:
+        // .route(propel::WARMUP_PATH, propel::warmup_handler(|| async { pool.connect().await }))
         .route("/", get(hello));
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:8080")
@@ -55,13 +69,31 @@ async fn main() {
     std::fs::write(project_dir.join("src/main.rs"), main_rs)?;
 
     // propel.toml
-    std::fs::write(project_dir.join("propel.toml"), super::PROPEL_TOML_TEMPLATE)?;
+    // Cloud Run rejects names like `my_api`, so pin a valid one up front
+    // instead of failing at the end of the first deploy.
+    let propel_toml = if service_name == name {
+        super::template::propel_toml_template()
+    } else {
+        super::template::propel_toml_with_service_name(&service_name)
+    };
+    std::fs::write(project_dir.join("propel.toml"), propel_toml)?;
 
     // .gitignore
-    let gitignore = "/target\n.env\n.propel-bundle/\n";
+    let gitignore = format!("/target\n.env\n{}", super::GITIGNORE_PROPEL_ENTRIES);
     std::fs::write(project_dir.join(".gitignore"), gitignore)?;
 
+    let git_notice = git_init(project_dir)?;
+
     println!("Created project '{name}'");
+    if let Some(notice) = git_notice {
+        println!("Note: {notice}");
+    }
+    if service_name != name {
+        println!(
+            "Note: Cloud Run service names only allow lowercase letters, digits, and hyphens; \
+             set [project].name = \"{service_name}\" in propel.toml"
+        );
+    }
     println!();
     println!("  cd {name}");
     println!("  cargo run              # local development");
@@ -71,3 +103,61 @@ async fn main() {
 
     Ok(())
 }
+
+/// Make `project_dir` a git repository, as `cargo new` does, unless it is
+/// already inside one: deploy bundles the files git lists.
+///
+/// Without git on PATH the scaffold is still usable for local development,
+/// so the init is skipped and a notice returned instead.
+fn git_init(project_dir: &Path) -> anyhow::Result<Option<String>> {
+    let inside = Command::new("git")
+        .args(["rev-parse", "--is-inside-work-tree"])
+        .current_dir(project_dir)
+        .output();
+    match inside {
+        Ok(output) if output.status.success() => return Ok(None),
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(Some(
+                "git not found on PATH — skipped `git init`. Install git \
+                 (https://git-scm.com/downloads) and run `git init` before \
+                 `propel deploy`, which bundles the files git lists."
+                    .to_owned(),
+            ));
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    let output = Command::new("git")
+        .args(["init", "--quiet"])
+        .current_dir(project_dir)
+        .output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git init failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(None)
+}
+
+/// Cargo's package name rules: ASCII letters, digits, `-`, and `_`, not
+/// starting with a digit.
+fn validate_package_name(name: &str) -> anyhow::Result<()> {
+    if name.is_empty() {
+        anyhow::bail!("project name must not be empty");
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || *c == '-' || *c == '_'))
+    {
+        anyhow::bail!(
+            "invalid project name '{name}': {c:?} is not allowed — \
+             use ASCII letters, digits, '-', and '_'"
+        );
+    }
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        anyhow::bail!("invalid project name '{name}': must not start with a digit");
+    }
+    Ok(())
+}
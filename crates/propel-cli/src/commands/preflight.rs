@@ -0,0 +1,254 @@
+//! Which disabled APIs stop a deploy, and the gcloud client preflight
+//! checks run with.
+
+use propel_cloud::{GcloudClient, SECRET_MANAGER_API};
+use propel_core::PropelConfig;
+use std::path::Path;
+
+/// How a disabled API affects `propel deploy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ApiRequirement {
+    /// The deploy cannot work without it.
+    Required,
+    /// Unused by this config; a warning unless `--strict-preflight`.
+    Conditional,
+    /// Never called with this config; ignored, even with `--strict-preflight`.
+    Unused,
+}
+
+/// Classify `api` for this config.
+///
+/// Secret Manager is required only when secrets would be injected or
+/// configured: a `[build].git_credentials_secret`, a `[secrets]` entry,
+/// or a non-empty `[cloud_run].secrets` allowlist. Without either, a disabled API just
+/// means there is nothing to discover. With `[cloud_run].inject_secrets =
+/// false` and no git credentials secret, it is not used at all.
+pub(crate) fn api_requirement(config: &PropelConfig, api: &str) -> ApiRequirement {
+    if api != SECRET_MANAGER_API {
+        return ApiRequirement::Required;
+    }
+    if !config.cloud_run.inject_secrets {
+        return if config.build.git_credentials_secret.is_some() {
+            ApiRequirement::Required
+        } else {
+            ApiRequirement::Unused
+        };
+    }
+    let allowlisted = match &config.cloud_run.secrets {
+        Some(secrets) => !secrets.is_empty(),
+        None => false,
+    };
+    if allowlisted || !config.secrets.is_empty() || config.build.git_credentials_secret.is_some() {
+        ApiRequirement::Required
+    } else {
+        ApiRequirement::Conditional
+    }
+}
+
+/// Disabled APIs from preflight, split by [`ApiRequirement`].
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct DisabledApis {
+    /// Block the deploy (after waiting for any still propagating).
+    pub(crate) required: Vec<String>,
+    /// Reported as warnings; the deploy continues without them.
+    pub(crate) conditional: Vec<String>,
+}
+
+impl DisabledApis {
+    /// Classify `disabled`; with `strict`, every API this config uses is
+    /// required.
+    pub(crate) fn classify(config: &PropelConfig, disabled: Vec<String>, strict: bool) -> Self {
+        let (required, conditional) = disabled
+            .into_iter()
+            .filter(|api| api_requirement(config, api) != ApiRequirement::Unused)
+            .partition(|api| strict || api_requirement(config, api) == ApiRequirement::Required);
+        Self {
+            required,
+            conditional,
+        }
+    }
+
+    /// Whether secret discovery must be skipped.
+    pub(crate) fn skips_secret_manager(&self) -> bool {
+        self.conditional.iter().any(|api| api == SECRET_MANAGER_API)
+    }
+
+    /// Why deploy injects no secrets without asking Secret Manager, or
+    /// `None` when it should discover them.
+    pub(crate) fn secret_discovery_skipped(&self, config: &PropelConfig) -> Option<&'static str> {
+        if !config.cloud_run.inject_secrets {
+            Some("[cloud_run].inject_secrets = false — no secrets injected")
+        } else if self.skips_secret_manager() {
+            Some("Secret Manager API disabled — no secrets injected")
+        } else {
+            None
+        }
+    }
+
+    /// Warning about the conditional APIs, if any are disabled.
+    pub(crate) fn warning(&self) -> Option<String> {
+        if self.conditional.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "Warning: {} disabled — not needed by this config, continuing without \
+             secrets (--strict-preflight makes this fatal)",
+            self.conditional.join(", ")
+        ))
+    }
+}
+
+/// Client for commands that run preflight checks.
+///
+/// With `[auth] activate_key_file = true` and `GOOGLE_APPLICATION_CREDENTIALS`
+/// pointing at a key file, preflight activates that key when no gcloud
+/// account is active.
+pub(crate) fn preflight_client(config: &PropelConfig) -> GcloudClient {
+    let client = GcloudClient::new();
+    if !config.auth.activate_key_file {
+        return client;
+    }
+    match std::env::var_os("GOOGLE_APPLICATION_CREDENTIALS") {
+        Some(key_file) if Path::new(&key_file).is_file() => {
+            client.key_file_fallback(key_file.into())
+        }
+        _ => client,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use propel_core::SecretVersions;
+
+    fn disabled(apis: &[&str]) -> Vec<String> {
+        apis.iter().map(|s| (*s).to_owned()).collect()
+    }
+
+    #[test]
+    fn core_apis_are_always_required() {
+        let config = PropelConfig::default();
+        for api in [
+            "cloudbuild.googleapis.com",
+            "run.googleapis.com",
+            "artifactregistry.googleapis.com",
+        ] {
+            assert_eq!(api_requirement(&config, api), ApiRequirement::Required);
+        }
+    }
+
+    #[test]
+    fn secret_manager_requirement_follows_config() {
+        let mut config = PropelConfig::default();
+        // Default: discover whatever exists — nothing to discover is fine.
+        assert_eq!(
+            api_requirement(&config, SECRET_MANAGER_API),
+            ApiRequirement::Conditional
+        );
+
+        config.cloud_run.secrets = Some(SecretVersions::from([]));
+        assert_eq!(
+            api_requirement(&config, SECRET_MANAGER_API),
+            ApiRequirement::Conditional
+        );
+
+        config.cloud_run.secrets = Some(SecretVersions::from([("JWT", "latest")]));
+        assert_eq!(
+            api_requirement(&config, SECRET_MANAGER_API),
+            ApiRequirement::Required
+        );
+
+        let mut config = PropelConfig::default();
+        config.build.git_credentials_secret = Some("GIT_CREDENTIALS".to_owned());
+        assert_eq!(
+            api_requirement(&config, SECRET_MANAGER_API),
+            ApiRequirement::Required
+        );
+    }
+
+    #[test]
+    fn disabled_secret_manager_is_a_warning_without_secrets() {
+        let config = PropelConfig::default();
+
+        let apis = DisabledApis::classify(
+            &config,
+            disabled(&["run.googleapis.com", SECRET_MANAGER_API]),
+            false,
+        );
+
+        assert_eq!(apis.required, disabled(&["run.googleapis.com"]));
+        assert_eq!(apis.conditional, disabled(&[SECRET_MANAGER_API]));
+        assert!(apis.skips_secret_manager());
+        assert!(apis.warning().unwrap().contains("--strict-preflight"));
+    }
+
+    #[test]
+    fn secret_manager_is_unused_without_injection() {
+        let mut config = PropelConfig::default();
+        config.cloud_run.inject_secrets = false;
+        // An allowlist is inert without injection.
+        config.cloud_run.secrets = Some(SecretVersions::from([("JWT", "latest")]));
+        assert_eq!(
+            api_requirement(&config, SECRET_MANAGER_API),
+            ApiRequirement::Unused
+        );
+        for strict in [false, true] {
+            let apis = DisabledApis::classify(&config, disabled(&[SECRET_MANAGER_API]), strict);
+            assert_eq!(apis, DisabledApis::default(), "strict: {strict}");
+        }
+
+        // The build still reads the git credentials secret.
+        config.build.git_credentials_secret = Some("GIT_CREDENTIALS".to_owned());
+        assert_eq!(
+            api_requirement(&config, SECRET_MANAGER_API),
+            ApiRequirement::Required
+        );
+    }
+
+    #[test]
+    fn secret_discovery_is_skipped_by_config_or_disabled_api() {
+        let mut config = PropelConfig::default();
+        let enabled = DisabledApis::default();
+        let api_disabled = DisabledApis::classify(&config, disabled(&[SECRET_MANAGER_API]), false);
+
+        assert_eq!(enabled.secret_discovery_skipped(&config), None);
+        assert!(
+            api_disabled
+                .secret_discovery_skipped(&config)
+                .unwrap()
+                .contains("API disabled")
+        );
+
+        config.cloud_run.inject_secrets = false;
+        for apis in [&enabled, &api_disabled] {
+            assert!(
+                apis.secret_discovery_skipped(&config)
+                    .unwrap()
+                    .contains("inject_secrets = false")
+            );
+        }
+    }
+
+    #[test]
+    fn strict_preflight_requires_every_api() {
+        let config = PropelConfig::default();
+
+        let apis = DisabledApis::classify(&config, disabled(&[SECRET_MANAGER_API]), true);
+
+        assert_eq!(apis.required, disabled(&[SECRET_MANAGER_API]));
+        assert!(apis.conditional.is_empty());
+        assert!(!apis.skips_secret_manager());
+        assert_eq!(apis.warning(), None);
+    }
+
+    #[test]
+    fn disabled_secret_manager_blocks_when_secrets_configured() {
+        let mut config = PropelConfig::default();
+        config.cloud_run.secrets = Some(SecretVersions::from([("JWT", "3")]));
+
+        let apis = DisabledApis::classify(&config, disabled(&[SECRET_MANAGER_API]), false);
+
+        assert_eq!(apis.required, disabled(&[SECRET_MANAGER_API]));
+        assert!(!apis.skips_secret_manager());
+    }
+}
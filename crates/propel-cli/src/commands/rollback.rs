@@ -0,0 +1,209 @@
+use super::CliError;
+use super::ProjectContext;
+use propel_cloud::{GcloudClient, RevisionInfo, previous_revision};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Revisions listed before rolling back.
+const LISTED_REVISIONS: usize = 5;
+
+/// Send all traffic to an earlier revision of the service: `revision`, or
+/// the one before the revision serving traffic now (after confirmation,
+/// unless `skip_confirm`).
+pub async fn rollback(revision: Option<&str>, skip_confirm: bool) -> Result<(), CliError> {
+    let project_dir = PathBuf::from(".");
+    let ctx = ProjectContext::load(&project_dir)?;
+    let project_id = ctx.gcp_project_id()?;
+    let service_name = ctx.service_name();
+    let region = ctx.region();
+
+    let client = GcloudClient::new();
+    super::announce_target(&client, &ctx.config, project_id, None, service_name).await?;
+
+    let revisions = super::service_revisions(&client, project_id, service_name, region).await?;
+
+    println!("Recent revisions of {service_name} ({region}):");
+    print!("{}", revision_table(&revisions, LISTED_REVISIONS));
+
+    let target = rollback_target(&revisions, revision)?;
+    if target.traffic_percent == 100 {
+        println!("{} already serves all traffic", target.name);
+        return Ok(());
+    }
+
+    if revision.is_none() && !skip_confirm {
+        print!(
+            "Route 100% of traffic to {} (created {})? [y/N] ",
+            target.name, target.created
+        );
+        std::io::stdout().flush()?;
+
+        let mut input = String::new();
+        std::io::stdin().read_line(&mut input)?;
+
+        if !matches!(input.trim(), "y" | "Y" | "yes" | "YES") {
+            println!("Aborted.");
+            return Err(CliError::Cancelled);
+        }
+    }
+
+    println!("Routing all traffic to {}...", target.name);
+    let url = client
+        .route_all_traffic(service_name, project_id, region, &target.name)
+        .await?;
+
+    println!();
+    println!("Rolled back: {url} now serves {}", target.name);
+    println!(
+        "Traffic stays pinned to {}: newer revisions get none until you run\n  \
+         gcloud run services update-traffic {service_name} --to-latest \
+         --project {project_id} --region {region}",
+        target.name
+    );
+    Ok(())
+}
+
+/// The revision to roll back to: `requested` by name, else the one before
+/// the revision serving traffic now.
+fn rollback_target<'a>(
+    revisions: &'a [RevisionInfo],
+    requested: Option<&str>,
+) -> Result<&'a RevisionInfo, CliError> {
+    let fail = |message: String| Err(CliError::Deploy(anyhow::Error::msg(message)));
+
+    if let Some(name) = requested {
+        return match revisions.iter().find(|r| r.name == name) {
+            Some(revision) if revision.ready => Ok(revision),
+            Some(_) => fail(format!(
+                "revision {name} is not ready to serve traffic; pick another one"
+            )),
+            None => fail(format!(
+                "revision {name} not found; the service has: {}",
+                revisions
+                    .iter()
+                    .map(|r| r.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        };
+    }
+
+    match revisions {
+        [] => fail("the service has no revisions".to_owned()),
+        [only] => fail(format!(
+            "only one revision exists ({}); there is nothing to roll back to",
+            only.name
+        )),
+        _ => match previous_revision(revisions) {
+            Some(revision) => Ok(revision),
+            None => fail(
+                "no ready revision is older than the one serving traffic; \
+                 pass --revision NAME to choose one"
+                    .to_owned(),
+            ),
+        },
+    }
+}
+
+/// The newest `limit` revisions, one per line, with traffic and image.
+fn revision_table(revisions: &[RevisionInfo], limit: usize) -> String {
+    let width = revisions
+        .iter()
+        .take(limit)
+        .map(|r| r.name.len())
+        .max()
+        // arch-lint: allow(no-silent-result-drop) reason="Option: no revisions means an empty table"
+        .unwrap_or_default();
+    let mut out = String::new();
+    for revision in revisions.iter().take(limit) {
+        let status = match (revision.traffic_percent, revision.ready) {
+            (0, true) => String::new(),
+            (0, false) => "  not ready".to_owned(),
+            (percent, _) => format!("  {percent}% traffic"),
+        };
+        out.push_str(&format!(
+            "  {:width$}  {}  {}{status}\n",
+            revision.name, revision.created, revision.image
+        ));
+    }
+    if revisions.len() > limit {
+        out.push_str(&format!("  ... {} older\n", revisions.len() - limit));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn revision(name: &str, traffic_percent: u32, ready: bool) -> RevisionInfo {
+        RevisionInfo {
+            name: name.to_owned(),
+            created: "2026-03-01T00:00:00Z".to_owned(),
+            traffic_percent,
+            image: "img:latest".to_owned(),
+            ready,
+            deployed_by: None,
+            project_number: None,
+        }
+    }
+
+    #[test]
+    fn target_defaults_to_the_previous_revision() {
+        let revisions = [
+            revision("svc-00002", 100, true),
+            revision("svc-00001", 0, true),
+        ];
+
+        assert_eq!(rollback_target(&revisions, None).unwrap().name, "svc-00001");
+    }
+
+    #[test]
+    fn single_revision_is_reported_cleanly() {
+        let err = rollback_target(&[revision("svc-00001", 100, true)], None).unwrap_err();
+
+        assert_eq!(err.exit_code(), 5);
+        assert!(
+            err.to_string()
+                .contains("only one revision exists (svc-00001)"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn requested_revision_must_exist_and_be_ready() {
+        let revisions = [
+            revision("svc-00002", 100, true),
+            revision("svc-00001", 0, false),
+        ];
+
+        let err = rollback_target(&revisions, Some("svc-00009")).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("the service has: svc-00002, svc-00001"),
+            "{err}"
+        );
+        let err = rollback_target(&revisions, Some("svc-00001")).unwrap_err();
+        assert!(err.to_string().contains("not ready"), "{err}");
+        assert_eq!(
+            rollback_target(&revisions, Some("svc-00002")).unwrap().name,
+            "svc-00002"
+        );
+    }
+
+    #[test]
+    fn table_marks_traffic_and_failed_revisions() {
+        let revisions = [
+            revision("svc-00003", 0, false),
+            revision("svc-00002", 100, true),
+            revision("svc-00001", 0, true),
+        ];
+
+        assert_eq!(
+            revision_table(&revisions, 2),
+            "  svc-00003  2026-03-01T00:00:00Z  img:latest  not ready\n  \
+             svc-00002  2026-03-01T00:00:00Z  img:latest  100% traffic\n  \
+             ... 1 older\n"
+        );
+    }
+}
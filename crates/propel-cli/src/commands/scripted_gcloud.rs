@@ -0,0 +1,62 @@
+//! gcloud stand-in for command tests that only capture gcloud output.
+
+use propel_cloud::GcloudExecutor;
+use propel_cloud::gcloud::GcloudError;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A scripted reply: `Ok` output, or `Err` stderr of a failed command.
+pub(crate) type Reply = Result<&'static str, &'static str>;
+
+/// Answers `exec` from a script of `(needle, reply)` entries: the first
+/// entry whose space-separated needle words are all among the arguments
+/// wins, and `Err` replies fail with that stderr. Any other call panics.
+pub(crate) struct ScriptedGcloud {
+    script: Vec<(&'static str, Reply)>,
+    calls: AtomicUsize,
+}
+
+impl ScriptedGcloud {
+    pub(crate) fn new(script: Vec<(&'static str, Reply)>) -> Self {
+        Self {
+            script,
+            calls: AtomicUsize::new(0),
+        }
+    }
+
+    /// How many gcloud commands have been run.
+    pub(crate) fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+impl GcloudExecutor for ScriptedGcloud {
+    async fn exec(&self, args: &[String]) -> Result<String, GcloudError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let Some((_, reply)) = self
+            .script
+            .iter()
+            .find(|(needle, _)| needle.split(' ').all(|word| args.iter().any(|a| a == word)))
+        else {
+            panic!("unexpected gcloud call: {args:?}");
+        };
+        reply
+            .map(str::to_owned)
+            .map_err(|stderr| GcloudError::CommandFailed {
+                args: args.to_vec(),
+                stderr: stderr.to_owned(),
+            })
+    }
+
+    async fn exec_streaming(&self, args: &[String]) -> Result<(), GcloudError> {
+        unreachable!("scripted gcloud only captures output: {args:?}")
+    }
+
+    async fn exec_tee(&self, args: &[String], _: &Path) -> Result<(), GcloudError> {
+        unreachable!("scripted gcloud only captures output: {args:?}")
+    }
+
+    async fn exec_with_stdin(&self, args: &[String], _: &[u8]) -> Result<String, GcloudError> {
+        unreachable!("scripted gcloud only captures output: {args:?}")
+    }
+}
@@ -1,9 +1,10 @@
-use propel_cloud::GcloudClient;
+use super::CliError;
+use propel_cloud::{GcloudClient, GcloudExecutor, SecretVersionState};
 use propel_core::PropelConfig;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-pub async fn secret_set(key_value: &str) -> anyhow::Result<()> {
+pub async fn secret_set(key_value: &str) -> Result<(), CliError> {
     let (key, value) = key_value
         .split_once('=')
         .ok_or_else(|| anyhow::anyhow!("expected KEY=VALUE format"))?;
@@ -12,25 +13,208 @@ pub async fn secret_set(key_value: &str) -> anyhow::Result<()> {
     let project_id = super::require_gcp_project_id(&config)?;
 
     let client = GcloudClient::new();
-    client.set_secret(project_id, key, value).await?;
+    let (name, version) = store_secret(&client, &config, project_id, key, value).await?;
 
-    // Grant Cloud Run default SA access to read this secret.
+    println!("Secret '{name}' set to version {version} (runtime service account granted access)");
+    println!("Pin it with [cloud_run.secrets] {key} = \"{version}\" in propel.toml");
+    Ok(())
+}
+
+/// Store `value` as a new version of the secret for `key` and let the
+/// runtime service account read it. Returns the Secret Manager
+/// name and the new version.
+pub(crate) async fn store_secret(
+    client: &GcloudClient,
+    config: &PropelConfig,
+    project_id: &str,
+    key: &str,
+    value: &str,
+) -> anyhow::Result<(String, String)> {
+    let sa = runtime_service_account(client, config, project_id).await?;
+    store_secret_for(client, config, project_id, &sa, key, value).await
+}
+
+/// [`store_secret`] with the runtime service account already resolved,
+/// for storing several secrets in a row.
+async fn store_secret_for(
+    client: &GcloudClient,
+    config: &PropelConfig,
+    project_id: &str,
+    sa: &str,
+    key: &str,
+    value: &str,
+) -> anyhow::Result<(String, String)> {
+    let name = config.project.secret_namespace().secret_name(key);
+    let version = client.set_secret(project_id, &name, value).await?;
+
+    // Grant the runtime SA access to read this secret.
     // This runs locally where the user has admin permissions,
     // so deploy (CI) only needs secretmanager.viewer.
+    client.grant_secret_access(project_id, &name, sa).await?;
+    Ok((name, version))
+}
+
+/// The service account Cloud Run revisions run as, which reads the
+/// secrets: `[cloud_run].service_account`, else the project's default
+/// compute service account.
+pub(super) async fn runtime_service_account<E: GcloudExecutor>(
+    client: &GcloudClient<E>,
+    config: &PropelConfig,
+    project_id: &str,
+) -> anyhow::Result<String> {
+    if let Some(email) = &config.cloud_run.service_account {
+        return Ok(email.clone());
+    }
     let project_number = client.get_project_number(project_id).await?;
-    let sa = format!("{project_number}-compute@developer.gserviceaccount.com");
-    client.grant_secret_access(project_id, key, &sa).await?;
+    Ok(format!(
+        "{project_number}-compute@developer.gserviceaccount.com"
+    ))
+}
+
+/// What `propel secret import` does with one key of the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImportAction {
+    Create,
+    Update,
+    Skip,
+}
+
+impl ImportAction {
+    fn for_key(exists: bool, skip_existing: bool) -> Self {
+        match (exists, skip_existing) {
+            (false, _) => Self::Create,
+            (true, false) => Self::Update,
+            (true, true) => Self::Skip,
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Self::Create => "new",
+            Self::Update => "new version",
+            Self::Skip => "exists, skipped",
+        }
+    }
+}
+
+/// Store every entry of a `.env` file as a secret, like `propel secret set`
+/// for each. Existing secrets get a new version unless `skip_existing`.
+///
+/// Every key is attempted; the command fails afterwards if any failed.
+pub async fn secret_import(
+    file: &Path,
+    skip_existing: bool,
+    skip_confirm: bool,
+) -> Result<(), CliError> {
+    let content = std::fs::read_to_string(file)
+        .map_err(|e| anyhow::anyhow!("could not read {}: {e}", file.display()))?;
+    let entries =
+        parse_env_file(&content).map_err(|e| anyhow::anyhow!("{}: {e}", file.display()))?;
+    if entries.is_empty() {
+        return Err(anyhow::anyhow!("{} has no KEY=VALUE entries", file.display()).into());
+    }
+
+    let config = PropelConfig::load(&PathBuf::from("."))?;
+    let project_id = super::require_gcp_project_id(&config)?;
+
+    let client = GcloudClient::new();
+    let existing = config
+        .project
+        .secret_namespace()
+        .keys(&client.list_secrets(project_id).await?);
+    let planned: Vec<(&str, &str, ImportAction)> = entries
+        .iter()
+        .map(|(key, value)| {
+            let exists = existing.iter().any(|name| name == key);
+            (
+                key.as_str(),
+                value.as_str(),
+                ImportAction::for_key(exists, skip_existing),
+            )
+        })
+        .collect();
+
+    println!(
+        "{} secret(s) from {} (values hidden):",
+        planned.len(),
+        file.display()
+    );
+    for (key, _, action) in &planned {
+        println!("  {key}=******** ({})", action.describe());
+    }
+    if !skip_confirm {
+        print!("Store these in Secret Manager? [y/N] ");
+        std::io::stdout().flush()?;
+
+        let mut input = String::new();
+        std::io::stdin().read_line(&mut input)?;
+
+        if !matches!(input.trim(), "y" | "Y" | "yes" | "YES") {
+            println!("Aborted.");
+            return Err(CliError::Cancelled);
+        }
+    }
 
-    println!("Secret '{key}' set successfully (Cloud Run SA granted access)");
+    let sa = runtime_service_account(&client, &config, project_id).await?;
+    let (mut created, mut updated, mut skipped) = (0, 0, 0);
+    let mut failed = Vec::new();
+    for (key, value, action) in &planned {
+        if *action == ImportAction::Skip {
+            skipped += 1;
+            continue;
+        }
+        match store_secret_for(&client, &config, project_id, &sa, key, value).await {
+            Ok((name, version)) => {
+                println!("Secret '{name}' set to version {version}");
+                match action {
+                    ImportAction::Create => created += 1,
+                    _ => updated += 1,
+                }
+            }
+            Err(e) => {
+                eprintln!("Failed to set '{key}': {e:#}");
+                failed.push(*key);
+            }
+        }
+    }
+
+    println!();
+    println!(
+        "{created} created, {updated} updated, {skipped} skipped, {} failed",
+        failed.len()
+    );
+    if !failed.is_empty() {
+        return Err(anyhow::anyhow!("could not set secret(s): {}", failed.join(", ")).into());
+    }
+    println!("Runtime service account granted access to each secret set.");
     Ok(())
 }
 
-pub async fn secret_delete(key: &str, skip_confirm: bool) -> anyhow::Result<()> {
+/// `KEY=VALUE` entries of a `.env` file, in file order. Quotes, comments
+/// and `export` prefixes follow dotenv rules; a repeated key keeps its
+/// last value at its first position.
+fn parse_env_file(content: &str) -> anyhow::Result<Vec<(String, String)>> {
+    let mut entries: Vec<(String, String)> = Vec::new();
+    for entry in dotenvy::from_read_iter(content.as_bytes()) {
+        let (key, value) = entry?;
+        if !is_env_var_name(&key) {
+            anyhow::bail!("'{key}' is not a valid environment variable name");
+        }
+        match entries.iter_mut().find(|(existing, _)| *existing == key) {
+            Some(entry) => entry.1 = value,
+            None => entries.push((key, value)),
+        }
+    }
+    Ok(entries)
+}
+
+pub async fn secret_delete(key: &str, skip_confirm: bool) -> Result<(), CliError> {
     let config = PropelConfig::load(&PathBuf::from("."))?;
     let project_id = super::require_gcp_project_id(&config)?;
+    let name = config.project.secret_namespace().secret_name(key);
 
     if !skip_confirm {
-        print!("Delete secret '{key}'? [y/N] ");
+        print!("Delete secret '{name}'? [y/N] ");
         std::io::stdout().flush()?;
 
         let mut input = String::new();
@@ -38,32 +222,34 @@ pub async fn secret_delete(key: &str, skip_confirm: bool) -> anyhow::Result<()>
 
         if !matches!(input.trim(), "y" | "Y" | "yes" | "YES") {
             println!("Aborted.");
-            return Ok(());
+            return Err(CliError::Cancelled);
         }
     }
 
     let client = GcloudClient::new();
 
-    // Revoke Cloud Run SA's access before deleting the secret itself.
-    let project_number = client.get_project_number(project_id).await?;
-    let sa = format!("{project_number}-compute@developer.gserviceaccount.com");
+    // Revoke the runtime SA's access before deleting the secret itself.
+    let sa = runtime_service_account(&client, &config, project_id).await?;
     // arch-lint: allow(no-error-swallowing) reason="revoke is best-effort pre-cleanup; propagating would skip delete_secret, leaving both secret and binding"
-    if let Err(e) = client.revoke_secret_access(project_id, key, &sa).await {
-        eprintln!("Warning: could not revoke SA binding for '{key}': {e}");
+    if let Err(e) = client.revoke_secret_access(project_id, &name, &sa).await {
+        eprintln!("Warning: could not revoke SA binding for '{name}': {e}");
     }
 
-    client.delete_secret(project_id, key).await?;
+    client.delete_secret(project_id, &name).await?;
 
-    println!("Secret '{key}' deleted");
+    println!("Secret '{name}' deleted");
     Ok(())
 }
 
-pub async fn secret_list() -> anyhow::Result<()> {
+pub async fn secret_list() -> Result<(), CliError> {
     let config = PropelConfig::load(&PathBuf::from("."))?;
     let project_id = super::require_gcp_project_id(&config)?;
 
     let client = GcloudClient::new();
-    let secrets = client.list_secrets(project_id).await?;
+    let secrets = config
+        .project
+        .secret_namespace()
+        .keys(&client.list_secrets(project_id).await?);
 
     if secrets.is_empty() {
         println!("No secrets found");
@@ -74,3 +260,374 @@ pub async fn secret_list() -> anyhow::Result<()> {
     }
     Ok(())
 }
+
+/// Show when the secret for `key` was created and the state of its
+/// versions. Never prints the value.
+pub async fn secret_info(key: &str) -> Result<(), CliError> {
+    let config = PropelConfig::load(&PathBuf::from("."))?;
+    let project_id = super::require_gcp_project_id(&config)?;
+    let name = config.project.secret_namespace().secret_name(key);
+
+    let client = GcloudClient::new();
+    let info = client.describe_secret(project_id, &name).await?;
+
+    println!("Secret:   {}", info.name);
+    println!("Created:  {}", info.create_time);
+    match info.latest() {
+        Some(latest) => println!(
+            "Latest:   version {} ({}, created {})",
+            latest.version, latest.state, latest.create_time
+        ),
+        None => println!("Latest:   none"),
+    }
+    let enabled = info
+        .versions
+        .iter()
+        .filter(|version| version.state == SecretVersionState::Enabled)
+        .count();
+    println!("Enabled:  {enabled} of {} version(s)", info.versions.len());
+    if !info.has_enabled_version() {
+        println!();
+        println!(
+            "Cloud Run cannot start a revision that mounts this secret. \
+             Add a version with `propel secret set {key}=...`"
+        );
+    }
+    Ok(())
+}
+
+/// Write the latest values of the named secrets to a local `.env` file.
+///
+/// Names must be listed explicitly — there is deliberately no way to pull
+/// every secret at once.
+pub async fn secret_pull(
+    only: &[String],
+    output: &Path,
+    force: bool,
+    skip_confirm: bool,
+) -> Result<(), CliError> {
+    let mut names: Vec<&str> = Vec::new();
+    for name in only {
+        let name = name.trim();
+        if !is_env_var_name(name) {
+            return Err(
+                anyhow::anyhow!("'{name}' is not a valid environment variable name").into(),
+            );
+        }
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    if names.is_empty() {
+        return Err(anyhow::anyhow!(
+            "no secrets selected — pass the names to pull with --only A,B"
+        )
+        .into());
+    }
+
+    if output.exists() && !force {
+        return Err(anyhow::anyhow!(
+            "{} already exists — use --force to overwrite it",
+            output.display()
+        )
+        .into());
+    }
+
+    let config = PropelConfig::load(&PathBuf::from("."))?;
+    let project_id = super::require_gcp_project_id(&config)?;
+
+    if !skip_confirm {
+        println!(
+            "This will write the plaintext values of {} secret(s) to {}:",
+            names.len(),
+            output.display()
+        );
+        for name in &names {
+            println!("  {name}");
+        }
+        print!("Write secret values to disk? [y/N] ");
+        std::io::stdout().flush()?;
+
+        let mut input = String::new();
+        std::io::stdin().read_line(&mut input)?;
+
+        if !matches!(input.trim(), "y" | "Y" | "yes" | "YES") {
+            println!("Aborted.");
+            return Err(CliError::Cancelled);
+        }
+    }
+
+    let namespace = config.project.secret_namespace();
+    let client = GcloudClient::new();
+    let mut entries = Vec::with_capacity(names.len());
+    for name in &names {
+        let value = client
+            .access_secret(project_id, &namespace.secret_name(name))
+            .await?;
+        entries.push((*name, value));
+    }
+
+    write_private_file(output, &format_dotenv(project_id, &entries))?;
+
+    println!("Wrote {} secret(s) to {}", entries.len(), output.display());
+    println!("Keep it out of version control (add it to .gitignore).");
+    Ok(())
+}
+
+/// `[A-Za-z_][A-Za-z0-9_]*` — names that are usable as `.env` keys.
+fn is_env_var_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Render `.env` content with one `KEY=value` line per entry.
+fn format_dotenv(project_id: &str, entries: &[(&str, String)]) -> String {
+    let mut out = format!("# Pulled from Secret Manager ({project_id}) by `propel secret pull`\n");
+    for (key, value) in entries {
+        out.push_str(key);
+        out.push('=');
+        out.push_str(&dotenv_quote(value));
+        out.push('\n');
+    }
+    out
+}
+
+/// Quote a value for `.env`.
+///
+/// Values made only of characters no dotenv parser treats specially are
+/// written bare. Everything else is double-quoted with `\`, `"`, and `$`
+/// backslash-escaped (so nothing is interpolated) and newlines written as
+/// `\n`, keeping each entry on one line.
+fn dotenv_quote(value: &str) -> String {
+    let bare = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-.,:/@+".contains(c));
+    if bare {
+        return value.to_owned();
+    }
+
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '\\' | '"' | '$' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' => out.push_str("\\n"),
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Write `content` to `path`, readable and writable by the owner only.
+fn write_private_file(path: &Path, content: &str) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        let mut file = options.open(path)?;
+        // `mode` only applies on creation; tighten an overwritten file too.
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+        file.write_all(content.as_bytes())
+    }
+    #[cfg(not(unix))]
+    {
+        options.open(path)?.write_all(content.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::scripted_gcloud::ScriptedGcloud;
+
+    fn parse(content: &str) -> Vec<(String, String)> {
+        dotenvy::from_read_iter(content.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    fn round_trip(value: &str) -> String {
+        let content = format_dotenv("proj", &[("KEY", value.to_owned())]);
+        let parsed = parse(&content);
+        assert_eq!(parsed.len(), 1, "content: {content}");
+        parsed[0].1.clone()
+    }
+
+    #[test]
+    fn dotenv_quote_plain_value_is_bare() {
+        assert_eq!(
+            dotenv_quote("postgres://u@db:5432/app"),
+            "postgres://u@db:5432/app"
+        );
+    }
+
+    #[test]
+    fn dotenv_quote_empty_value_is_quoted() {
+        assert_eq!(dotenv_quote(""), "\"\"");
+        assert_eq!(round_trip(""), "");
+    }
+
+    #[test]
+    fn dotenv_quote_escapes_special_characters() {
+        assert_eq!(dotenv_quote(r#"a"b\c$d"#), r#""a\"b\\c\$d""#);
+    }
+
+    #[test]
+    fn dotenv_quote_multiline_stays_on_one_line() {
+        let quoted = dotenv_quote("-----BEGIN KEY-----\nabc\n-----END KEY-----\n");
+        assert!(!quoted.contains('\n'));
+        assert_eq!(quoted, r#""-----BEGIN KEY-----\nabc\n-----END KEY-----\n""#);
+    }
+
+    #[test]
+    fn dotenv_round_trips_multiline_value() {
+        let pem = "-----BEGIN KEY-----\nabc\ndef\n-----END KEY-----\n";
+        assert_eq!(round_trip(pem), pem);
+    }
+
+    #[test]
+    fn dotenv_round_trips_special_characters() {
+        for value in [
+            "p@ss w0rd",
+            "has # hash",
+            "quote \" and 'single'",
+            "back\\slash",
+            "$HOME and ${USER}",
+            "key=value&x=1",
+            "  padded  ",
+            "日本語",
+        ] {
+            assert_eq!(round_trip(value), value, "value: {value:?}");
+        }
+    }
+
+    #[test]
+    fn format_dotenv_writes_one_line_per_entry() {
+        let content = format_dotenv(
+            "proj",
+            &[("A", "1".to_owned()), ("B", "two words".to_owned())],
+        );
+        assert_eq!(
+            content,
+            "# Pulled from Secret Manager (proj) by `propel secret pull`\nA=1\nB=\"two words\"\n"
+        );
+        assert_eq!(
+            parse(&content),
+            [
+                ("A".to_owned(), "1".to_owned()),
+                ("B".to_owned(), "two words".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_env_file_handles_quotes_and_comments() {
+        let entries = parse_env_file(
+            "# database\nDATABASE_URL=postgres://u@db/app # inline\n\n\
+             export API_KEY='a b'\nPEM=\"line1\\nline2\"\nEMPTY=\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            entries,
+            [
+                ("DATABASE_URL".to_owned(), "postgres://u@db/app".to_owned()),
+                ("API_KEY".to_owned(), "a b".to_owned()),
+                ("PEM".to_owned(), "line1\nline2".to_owned()),
+                ("EMPTY".to_owned(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_env_file_keeps_the_last_value_of_a_repeated_key() {
+        let entries = parse_env_file("A=1\nB=2\nA=3\n").unwrap();
+
+        assert_eq!(
+            entries,
+            [
+                ("A".to_owned(), "3".to_owned()),
+                ("B".to_owned(), "2".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_env_file_rejects_malformed_lines() {
+        assert!(parse_env_file("NOT A PAIR\n").is_err());
+        assert!(parse_env_file("my-key=1\n").is_err());
+    }
+
+    #[test]
+    fn import_action_updates_or_skips_existing_keys() {
+        assert_eq!(ImportAction::for_key(false, false), ImportAction::Create);
+        assert_eq!(ImportAction::for_key(false, true), ImportAction::Create);
+        assert_eq!(ImportAction::for_key(true, false), ImportAction::Update);
+        assert_eq!(ImportAction::for_key(true, true), ImportAction::Skip);
+    }
+
+    #[test]
+    fn is_env_var_name_accepts_shell_identifiers() {
+        assert!(is_env_var_name("DATABASE_URL"));
+        assert!(is_env_var_name("_private"));
+        assert!(!is_env_var_name("1ST"));
+        assert!(!is_env_var_name("my-secret"));
+        assert!(!is_env_var_name(""));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_private_file_is_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join(".env");
+        std::fs::write(&path, "OLD=1\n").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_private_file(&path, "NEW=1\n").unwrap();
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "NEW=1\n");
+    }
+
+    fn project_number_gcloud() -> ScriptedGcloud {
+        ScriptedGcloud::new(vec![("projects describe", Ok("123456789\n"))])
+    }
+
+    #[tokio::test]
+    async fn secrets_are_granted_to_the_default_compute_account() {
+        let client = GcloudClient::with_executor(project_number_gcloud());
+
+        let sa = runtime_service_account(&client, &PropelConfig::default(), "proj")
+            .await
+            .unwrap();
+
+        assert_eq!(sa, "123456789-compute@developer.gserviceaccount.com");
+    }
+
+    #[tokio::test]
+    async fn secrets_are_granted_to_the_configured_service_account() {
+        let mut config = PropelConfig::default();
+        config.cloud_run.service_account = Some("api@proj.iam.gserviceaccount.com".to_owned());
+        let client = GcloudClient::with_executor(project_number_gcloud());
+
+        let sa = runtime_service_account(&client, &config, "proj")
+            .await
+            .unwrap();
+
+        assert_eq!(sa, "api@proj.iam.gserviceaccount.com");
+        assert_eq!(client.executor().calls(), 0);
+    }
+}
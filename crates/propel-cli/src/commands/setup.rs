@@ -0,0 +1,629 @@
+//! `propel setup`: from a fresh machine to a project ready for its first
+//! deploy.
+//!
+//! [`pending_steps`] decides what is missing from a [`SetupState`], and
+//! [`run_setup`] offers each step once, in order. All prompting and GCP
+//! access goes through [`SetupEnv`], so the flow itself is tested with a
+//! scripted environment. Every step re-inspects the project first, so an
+//! interrupted or skipped setup picks up where it left off when re-run.
+
+use super::CliError;
+use super::ProjectContext;
+use propel_cloud::{GcloudClient, SECRET_MANAGER_API};
+use propel_core::{ProjectConfig, PropelConfig, SecretBinding};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Secrets the Supabase middleware reads, as in `propel init`'s `.env.example`.
+pub(crate) const SUPABASE_SECRETS: [&str; 3] =
+    ["SUPABASE_URL", "SUPABASE_ANON_KEY", "SUPABASE_JWT_SECRET"];
+
+/// What setup found. `None` means not checked yet, because a step before it
+/// is still missing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct SetupState {
+    pub(crate) authenticated: bool,
+    pub(crate) gcp_project_id: Option<String>,
+    /// Whether the active account can see `gcp_project_id`.
+    pub(crate) project_accessible: Option<bool>,
+    /// Required APIs not enabled in the project.
+    pub(crate) disabled_apis: Option<Vec<String>>,
+    /// [`SUPABASE_SECRETS`] not in Secret Manager.
+    pub(crate) missing_secrets: Option<Vec<String>>,
+}
+
+impl SetupState {
+    /// Whether a later run may find more steps once the pending ones are done.
+    fn fully_checked(&self) -> bool {
+        self.disabled_apis.is_some() && self.missing_secrets.is_some()
+    }
+}
+
+/// One fix setup can offer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum SetupStep {
+    /// Log in to gcloud.
+    Login,
+    /// Write `[project].gcp_project_id`; `current` is the inaccessible one.
+    SetProjectId {
+        current: Option<String>,
+    },
+    EnableApis(Vec<String>),
+    CreateSecrets(Vec<String>),
+}
+
+impl SetupStep {
+    /// The question asked before running the step.
+    fn question(&self) -> String {
+        match self {
+            Self::Login => "gcloud is not logged in. Run `gcloud auth login` now?".to_owned(),
+            Self::SetProjectId { current: None } => {
+                "[project].gcp_project_id is not set. Set it in propel.toml now?".to_owned()
+            }
+            Self::SetProjectId {
+                current: Some(current),
+            } => format!(
+                "GCP project '{current}' is not accessible. Set another gcp_project_id now?"
+            ),
+            Self::EnableApis(apis) => format!("Enable {} now?", apis.join(", ")),
+            Self::CreateSecrets(keys) => {
+                format!("Secret Manager lacks {}. Set them now?", keys.join(", "))
+            }
+        }
+    }
+
+    /// Commands that do the step by hand.
+    fn manual_commands(&self, project_id: Option<&str>) -> Vec<String> {
+        // arch-lint: allow(no-silent-result-drop) reason="Option: without a project ID the command shows a placeholder"
+        let project_id = project_id.unwrap_or("<project-id>");
+        match self {
+            Self::Login => vec!["gcloud auth login".to_owned()],
+            Self::SetProjectId { .. } => vec![
+                "gcloud projects list".to_owned(),
+                "# then in propel.toml: [project] gcp_project_id = \"<project-id>\"".to_owned(),
+            ],
+            Self::EnableApis(apis) => vec![format!(
+                "gcloud services enable {} --project {project_id}",
+                apis.join(" ")
+            )],
+            Self::CreateSecrets(keys) => keys
+                .iter()
+                .map(|key| format!("propel secret set {key}=..."))
+                .collect(),
+        }
+    }
+
+    /// Whether `other` is the same kind of step, whatever it holds.
+    fn same_kind(&self, other: &Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+/// Steps still missing, in the order they must run.
+pub(crate) fn pending_steps(state: &SetupState) -> Vec<SetupStep> {
+    let mut steps = Vec::new();
+    if !state.authenticated {
+        steps.push(SetupStep::Login);
+    }
+    match (&state.gcp_project_id, state.project_accessible) {
+        (None, _) => steps.push(SetupStep::SetProjectId { current: None }),
+        (Some(current), Some(false)) => steps.push(SetupStep::SetProjectId {
+            current: Some(current.clone()),
+        }),
+        _ => {}
+    }
+    if let Some(apis) = state.disabled_apis.as_ref().filter(|apis| !apis.is_empty()) {
+        steps.push(SetupStep::EnableApis(apis.clone()));
+    }
+    if let Some(keys) = state
+        .missing_secrets
+        .as_ref()
+        .filter(|keys| !keys.is_empty())
+    {
+        steps.push(SetupStep::CreateSecrets(keys.clone()));
+    }
+    steps
+}
+
+/// Prompts and GCP access for [`run_setup`].
+pub(crate) trait SetupEnv {
+    /// Check the project, as `propel doctor` does.
+    async fn inspect(&mut self) -> Result<SetupState, CliError>;
+    /// Ask a yes/no question; no is the default.
+    fn confirm(&mut self, question: &str) -> Result<bool, CliError>;
+    /// Run `step`, prompting for any values it needs.
+    async fn apply(&mut self, step: &SetupStep) -> Result<(), CliError>;
+    /// What `propel deploy` would do now, without doing it.
+    async fn deploy_plan(&mut self) -> Result<Vec<String>, CliError>;
+    fn say(&mut self, line: &str);
+}
+
+/// Walk through [`pending_steps`], offering each kind of step once.
+///
+/// A declined or failed step is reported with its manual commands and the
+/// walk moves on. Non-interactive runs change nothing and print the manual
+/// commands for every pending step. Ends with the deploy plan once nothing
+/// is pending, and fails while something still is.
+pub(crate) async fn run_setup(env: &mut impl SetupEnv, interactive: bool) -> Result<(), CliError> {
+    let mut offered: Vec<SetupStep> = Vec::new();
+    let state = loop {
+        let state = env.inspect().await?;
+        let pending = pending_steps(&state);
+        if !interactive {
+            print_manual_steps(env, &state, &pending);
+            break state;
+        }
+        let Some(step) = pending
+            .into_iter()
+            .find(|step| !offered.iter().any(|o| o.same_kind(step)))
+        else {
+            break state;
+        };
+        offered.push(step.clone());
+
+        let project_id = state.gcp_project_id.as_deref();
+        if !env.confirm(&step.question())? {
+            env.say("Skipped. To do it yourself:");
+            say_commands(env, &step, project_id);
+            continue;
+        }
+        // arch-lint: allow(no-error-swallowing) reason="a failed step is reported with its manual commands; the next steps may still apply"
+        if let Err(e) = env.apply(&step).await {
+            env.say(&format!("Failed: {e:#}"));
+            env.say("To do it yourself:");
+            say_commands(env, &step, project_id);
+        }
+    };
+
+    let remaining = pending_steps(&state);
+    if remaining.is_empty() && state.fully_checked() {
+        env.say("");
+        for line in env.deploy_plan().await? {
+            env.say(&line);
+        }
+        return Ok(());
+    }
+    Err(CliError::Preflight(anyhow::anyhow!(
+        "setup incomplete — finish the steps above, then re-run `propel setup`"
+    )))
+}
+
+fn print_manual_steps(env: &mut impl SetupEnv, state: &SetupState, pending: &[SetupStep]) {
+    if pending.is_empty() {
+        return;
+    }
+    env.say("Remaining setup steps:");
+    for step in pending {
+        say_commands(env, step, state.gcp_project_id.as_deref());
+    }
+    if !state.fully_checked() {
+        env.say("Then re-run `propel setup` to check the APIs and secrets.");
+    }
+}
+
+fn say_commands(env: &mut impl SetupEnv, step: &SetupStep, project_id: Option<&str>) {
+    for command in step.manual_commands(project_id) {
+        env.say(&format!("  {command}"));
+    }
+}
+
+/// The deploy plan: where and what `propel deploy` would build and deploy.
+pub(crate) fn deploy_plan(
+    ctx: &ProjectContext,
+    ejected: bool,
+    secrets: &[SecretBinding],
+) -> anyhow::Result<Vec<String>> {
+    let config = &ctx.config;
+    let dockerfile = if ejected {
+        ".propel/Dockerfile (ejected)"
+    } else {
+        "generated by propel"
+    };
+    let secrets = if secrets.is_empty() {
+        "none".to_owned()
+    } else {
+        secrets
+            .iter()
+            .map(|s| s.env.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let mut lines = vec![
+        "Setup complete. `propel deploy` would (dry run, nothing deployed):".to_owned(),
+        format!(
+            "  build    {} with Cloud Build in {} (Dockerfile {dockerfile})",
+            ctx.project.name,
+            ctx.gcp_project_id()?
+        ),
+        format!("  push     {}", ctx.image_tag()?),
+        format!(
+            "  deploy   Cloud Run service {} in {}",
+            ctx.service_name(),
+            ctx.region()
+        ),
+        format!("  secrets  {secrets}"),
+    ];
+    lines.extend(super::idle_cost_notice(config));
+    Ok(lines)
+}
+
+/// `propel setup` against the real gcloud and terminal.
+pub async fn setup(non_interactive: bool) -> Result<(), CliError> {
+    use std::io::IsTerminal;
+
+    let interactive = !non_interactive && std::io::stdin().is_terminal();
+    let mut env = TerminalSetup {
+        project_dir: PathBuf::from("."),
+        client: GcloudClient::new(),
+        doctor_shown: false,
+    };
+    run_setup(&mut env, interactive).await
+}
+
+struct TerminalSetup {
+    project_dir: PathBuf,
+    client: GcloudClient,
+    doctor_shown: bool,
+}
+
+impl TerminalSetup {
+    fn config(&self) -> Result<PropelConfig, CliError> {
+        Ok(PropelConfig::load(&self.project_dir)?)
+    }
+
+    /// Read one trimmed line after `prompt`.
+    fn read_line(prompt: &str) -> std::io::Result<String> {
+        print!("{prompt}");
+        std::io::stdout().flush()?;
+        let mut input = String::new();
+        std::io::stdin().read_line(&mut input)?;
+        Ok(input.trim().to_owned())
+    }
+}
+
+impl SetupEnv for TerminalSetup {
+    async fn inspect(&mut self) -> Result<SetupState, CliError> {
+        let config = self.config()?;
+        let project_id = config.project.gcp_project_id.clone();
+        let mut report = self
+            .client
+            .doctor(project_id.as_deref(), &super::required_components(&config))
+            .await;
+        if !self.doctor_shown {
+            super::local_doctor_checks(&mut report, &self.project_dir);
+            println!("{report}");
+            self.doctor_shown = true;
+        }
+
+        let mut state = SetupState {
+            authenticated: report.account.passed,
+            gcp_project_id: project_id,
+            ..SetupState::default()
+        };
+        let Some(project_id) = state
+            .gcp_project_id
+            .as_deref()
+            .filter(|_| state.authenticated)
+        else {
+            return Ok(state);
+        };
+        state.project_accessible = Some(report.project.passed);
+        if !report.project.passed {
+            return Ok(state);
+        }
+
+        let disabled = self.client.disabled_preflight_apis(project_id).await?;
+        if !disabled.iter().any(|api| api == SECRET_MANAGER_API) {
+            let stored = config
+                .project
+                .secret_namespace()
+                .keys(&self.client.list_secrets(project_id).await?);
+            state.missing_secrets = Some(
+                SUPABASE_SECRETS
+                    .iter()
+                    .filter(|key| !stored.iter().any(|s| s == *key))
+                    .map(|key| (*key).to_owned())
+                    .collect(),
+            );
+        }
+        state.disabled_apis = Some(disabled);
+        Ok(state)
+    }
+
+    fn confirm(&mut self, question: &str) -> Result<bool, CliError> {
+        println!();
+        let answer = Self::read_line(&format!("{question} [y/N] "))?;
+        Ok(matches!(answer.as_str(), "y" | "Y" | "yes" | "YES"))
+    }
+
+    async fn apply(&mut self, step: &SetupStep) -> Result<(), CliError> {
+        match step {
+            SetupStep::Login => {
+                let status = tokio::process::Command::new("gcloud")
+                    .args(["auth", "login"])
+                    .status()
+                    .await?;
+                if !status.success() {
+                    return Err(CliError::Preflight(anyhow::anyhow!(
+                        "gcloud auth login exited with {status}"
+                    )));
+                }
+            }
+            SetupStep::SetProjectId { .. } => {
+                let project_id = Self::read_line("GCP project ID (empty to skip): ")?;
+                if project_id.is_empty() {
+                    println!("No project ID entered.");
+                    return Ok(());
+                }
+                ProjectConfig::save_gcp_project_id(&self.project_dir, &project_id)?;
+                println!("Wrote gcp_project_id = \"{project_id}\" to propel.toml");
+            }
+            SetupStep::EnableApis(apis) => {
+                let config = self.config()?;
+                let project_id = super::require_gcp_project_id(&config)?;
+                println!("Enabling {}...", apis.join(", "));
+                self.client.enable_apis(project_id, apis).await?;
+            }
+            SetupStep::CreateSecrets(keys) => {
+                let config = self.config()?;
+                let project_id = super::require_gcp_project_id(&config)?;
+                for key in keys {
+                    let value = Self::read_line(&format!("{key} (empty to skip): "))?;
+                    if value.is_empty() {
+                        continue;
+                    }
+                    let (name, version) =
+                        super::secret::store_secret(&self.client, &config, project_id, key, &value)
+                            .await?;
+                    println!("Secret '{name}' set to version {version}");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn deploy_plan(&mut self) -> Result<Vec<String>, CliError> {
+        let ctx = ProjectContext::load(&self.project_dir)?;
+        let project_id = ctx.gcp_project_id()?;
+        let discovered = self.client.list_secrets(project_id).await?;
+        let secrets = super::select_secrets(&ctx.config, discovered)?;
+        let ejected = propel_build::eject::is_ejected(Path::new(&self.project_dir));
+        Ok(deploy_plan(&ctx, ejected, &secrets)?)
+    }
+
+    fn say(&mut self, line: &str) {
+        println!("{line}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// A [`SetupEnv`] replaying scripted states and answers.
+    #[derive(Default)]
+    struct ScriptedEnv {
+        /// Returned by successive `inspect` calls; the last one repeats.
+        states: VecDeque<SetupState>,
+        answers: VecDeque<bool>,
+        failing: Option<SetupStep>,
+        applied: Vec<SetupStep>,
+        questions: Vec<String>,
+        output: Vec<String>,
+    }
+
+    impl ScriptedEnv {
+        fn new(states: impl IntoIterator<Item = SetupState>, answers: &[bool]) -> Self {
+            Self {
+                states: states.into_iter().collect(),
+                answers: answers.iter().copied().collect(),
+                ..Self::default()
+            }
+        }
+
+        fn said(&self, text: &str) -> bool {
+            self.output.iter().any(|line| line.contains(text))
+        }
+    }
+
+    impl SetupEnv for ScriptedEnv {
+        async fn inspect(&mut self) -> Result<SetupState, CliError> {
+            if self.states.len() > 1 {
+                Ok(self.states.pop_front().unwrap())
+            } else {
+                Ok(self.states[0].clone())
+            }
+        }
+
+        fn confirm(&mut self, question: &str) -> Result<bool, CliError> {
+            self.questions.push(question.to_owned());
+            Ok(self.answers.pop_front().expect("unexpected question"))
+        }
+
+        async fn apply(&mut self, step: &SetupStep) -> Result<(), CliError> {
+            self.applied.push(step.clone());
+            if self.failing.as_ref() == Some(step) {
+                return Err(CliError::Preflight(anyhow::anyhow!("permission denied")));
+            }
+            Ok(())
+        }
+
+        async fn deploy_plan(&mut self) -> Result<Vec<String>, CliError> {
+            Ok(vec!["PLAN".to_owned()])
+        }
+
+        fn say(&mut self, line: &str) {
+            self.output.push(line.to_owned());
+        }
+    }
+
+    fn fresh() -> SetupState {
+        SetupState::default()
+    }
+
+    fn ready() -> SetupState {
+        SetupState {
+            authenticated: true,
+            gcp_project_id: Some("proj".to_owned()),
+            project_accessible: Some(true),
+            disabled_apis: Some(vec![]),
+            missing_secrets: Some(vec![]),
+        }
+    }
+
+    fn with(edit: impl FnOnce(&mut SetupState)) -> SetupState {
+        let mut state = ready();
+        edit(&mut state);
+        state
+    }
+
+    #[test]
+    fn pending_steps_follow_dependency_order() {
+        assert_eq!(
+            pending_steps(&fresh()),
+            [SetupStep::Login, SetupStep::SetProjectId { current: None }]
+        );
+        let state = with(|s| {
+            s.disabled_apis = Some(vec![SECRET_MANAGER_API.to_owned()]);
+            s.missing_secrets = None;
+        });
+        assert_eq!(
+            pending_steps(&state),
+            [SetupStep::EnableApis(vec![SECRET_MANAGER_API.to_owned()])]
+        );
+        assert!(pending_steps(&ready()).is_empty());
+    }
+
+    #[test]
+    fn inaccessible_project_is_offered_for_replacement() {
+        let state = with(|s| {
+            s.project_accessible = Some(false);
+            s.disabled_apis = None;
+            s.missing_secrets = None;
+        });
+        assert_eq!(
+            pending_steps(&state),
+            [SetupStep::SetProjectId {
+                current: Some("proj".to_owned())
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn fresh_project_walks_every_step_then_plans() {
+        let secrets = vec!["SUPABASE_URL".to_owned()];
+        let mut env = ScriptedEnv::new(
+            [
+                fresh(),
+                SetupState {
+                    authenticated: true,
+                    ..fresh()
+                },
+                with(|s| {
+                    s.disabled_apis = Some(vec!["run.googleapis.com".to_owned()]);
+                    s.missing_secrets = None;
+                }),
+                with(|s| s.missing_secrets = Some(secrets.clone())),
+                ready(),
+            ],
+            &[true, true, true, true],
+        );
+
+        run_setup(&mut env, true).await.unwrap();
+
+        assert_eq!(
+            env.applied,
+            [
+                SetupStep::Login,
+                SetupStep::SetProjectId { current: None },
+                SetupStep::EnableApis(vec!["run.googleapis.com".to_owned()]),
+                SetupStep::CreateSecrets(secrets),
+            ]
+        );
+        assert_eq!(env.output.last().map(String::as_str), Some("PLAN"));
+    }
+
+    #[tokio::test]
+    async fn declined_step_prints_manual_commands_and_moves_on() {
+        let mut env = ScriptedEnv::new([fresh()], &[false, true]);
+
+        let err = run_setup(&mut env, true).await.unwrap_err();
+
+        assert_eq!(err.exit_code(), 3);
+        assert_eq!(env.questions.len(), 2, "{:?}", env.questions);
+        assert_eq!(env.applied, [SetupStep::SetProjectId { current: None }]);
+        assert!(env.said("gcloud auth login"));
+        assert!(!env.said("PLAN"));
+    }
+
+    #[tokio::test]
+    async fn each_step_is_offered_once_even_if_still_pending() {
+        // Login "succeeds" but gcloud still reports no account.
+        let mut env = ScriptedEnv::new([fresh()], &[true, false]);
+
+        assert!(run_setup(&mut env, true).await.is_err());
+        assert_eq!(env.questions.len(), 2, "{:?}", env.questions);
+    }
+
+    #[tokio::test]
+    async fn failed_step_is_reported_with_manual_commands() {
+        let apis = vec!["run.googleapis.com".to_owned()];
+        let mut env = ScriptedEnv::new(
+            [with(|s| {
+                s.disabled_apis = Some(apis.clone());
+                s.missing_secrets = None;
+            })],
+            &[true],
+        );
+        env.failing = Some(SetupStep::EnableApis(apis));
+
+        assert!(run_setup(&mut env, true).await.is_err());
+        assert!(env.said("Failed: permission denied"));
+        assert!(env.said("gcloud services enable run.googleapis.com --project proj"));
+    }
+
+    #[tokio::test]
+    async fn rerun_resumes_at_the_first_missing_step() {
+        let mut env = ScriptedEnv::new(
+            [
+                with(|s| s.missing_secrets = Some(vec!["SUPABASE_JWT_SECRET".to_owned()])),
+                ready(),
+            ],
+            &[true],
+        );
+
+        run_setup(&mut env, true).await.unwrap();
+
+        assert_eq!(env.questions.len(), 1);
+        assert!(env.questions[0].contains("SUPABASE_JWT_SECRET"));
+    }
+
+    #[tokio::test]
+    async fn ready_project_only_prints_the_plan() {
+        let mut env = ScriptedEnv::new([ready()], &[]);
+
+        run_setup(&mut env, true).await.unwrap();
+
+        assert!(env.questions.is_empty());
+        assert!(env.applied.is_empty());
+        assert!(env.said("PLAN"));
+    }
+
+    #[tokio::test]
+    async fn non_interactive_prints_commands_and_changes_nothing() {
+        let mut env = ScriptedEnv::new(
+            [with(|s| {
+                s.disabled_apis = Some(vec!["run.googleapis.com".to_owned()]);
+                s.missing_secrets = None;
+            })],
+            &[],
+        );
+
+        assert!(run_setup(&mut env, false).await.is_err());
+        assert!(env.questions.is_empty());
+        assert!(env.applied.is_empty());
+        assert!(env.said("gcloud services enable run.googleapis.com --project proj"));
+        assert!(env.said("re-run `propel setup`"));
+    }
+}
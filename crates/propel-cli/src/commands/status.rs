@@ -1,25 +1,48 @@
+use super::CliError;
+use super::ProjectContext;
 use propel_cloud::GcloudClient;
-use propel_core::{CargoProject, PropelConfig};
 use std::path::PathBuf;
 
-pub async fn status() -> anyhow::Result<()> {
+pub async fn status() -> Result<(), CliError> {
     let project_dir = PathBuf::from(".");
-    let config = PropelConfig::load(&project_dir)?;
-    let project_id = config
-        .project
-        .gcp_project_id
-        .as_deref()
-        .ok_or_else(|| anyhow::anyhow!("gcp_project_id not set in propel.toml"))?;
-
-    let project = CargoProject::discover(&project_dir)?;
-    let service_name = super::service_name(&config, &project);
-    let region = &config.project.region;
+    let ctx = ProjectContext::load(&project_dir)?;
+    let project_id = ctx.gcp_project_id()?;
+    let service_name = ctx.service_name();
+    let region = ctx.region();
 
     let client = GcloudClient::new();
-    let output = client
+    super::announce_target(&client, &ctx.config, project_id, None, service_name).await?;
+    let output = match client
         .describe_service(service_name, project_id, region)
-        .await?;
+        .await
+    {
+        Ok(output) => output,
+        Err(e) if e.is_service_not_found() => {
+            match super::find_in_other_regions(&client, project_id, service_name, region).await {
+                Some(hint) => {
+                    return Err(CliError::Deploy(anyhow::anyhow!(
+                        "{service_name} not found in {region}; {hint}"
+                    )));
+                }
+                None => return Err(e.into()),
+            }
+        }
+        Err(e) => return Err(e.into()),
+    };
 
     println!("{output}");
+    println!(
+        "{}",
+        super::access_line(&client, service_name, project_id, region).await
+    );
+    // Informational like the access line: a failed listing is reported in it.
+    let last_deploy = match client
+        .list_revisions(service_name, project_id, region)
+        .await
+    {
+        Ok(revisions) => super::history::last_deploy_line(&revisions, chrono::Utc::now()),
+        Err(e) => format!("Last deploy: unknown ({e})"),
+    };
+    println!("{last_deploy}");
     Ok(())
 }
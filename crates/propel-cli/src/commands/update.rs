@@ -0,0 +1,85 @@
+use super::CliError;
+use super::ProjectContext;
+use propel_cloud::{GcloudClient, SECRET_ACCESS_PROPAGATION_TIMEOUT, retry_secret_propagation};
+use std::path::PathBuf;
+
+/// Apply `[cloud_run]` settings, env vars, and secrets to the deployed
+/// service without bundling or building: the running image is kept.
+pub async fn update() -> Result<(), CliError> {
+    let project_dir = PathBuf::from(".");
+    let mut ctx = ProjectContext::load(&project_dir)?;
+    for warning in super::config_warnings(&ctx.config, &project_dir) {
+        println!("Warning: {warning}");
+    }
+
+    let labels = super::propel_labels(&ctx.config);
+    super::resolve_env_templates(&mut ctx)?;
+
+    let project_id = ctx.gcp_project_id()?;
+    let service_name = ctx.service_name();
+    let region = ctx.region();
+    let config = &ctx.config;
+
+    let client = GcloudClient::new();
+    let account = super::announce_target(&client, config, project_id, None, service_name).await?;
+    super::check_expected_account(config, account.as_deref())?;
+    if let Some(notice) = super::check_run_command_group(&client, config).await? {
+        println!("{notice}");
+    }
+
+    // There is no image to fall back on: the service must already exist.
+    if let Err(e) = client.service_url(service_name, project_id, region).await {
+        if !e.is_service_not_found() {
+            return Err(e.into());
+        }
+        let message =
+            match super::find_in_other_regions(&client, project_id, service_name, region).await {
+                Some(hint) => format!("{service_name} not found in {region}; {hint}"),
+                None => format!(
+                    "{service_name} is not deployed in {region} — run `propel deploy` first"
+                ),
+            };
+        return Err(CliError::Deploy(anyhow::Error::msg(message)));
+    }
+
+    let secrets = match super::preflight::DisabledApis::default().secret_discovery_skipped(config) {
+        Some(reason) => {
+            println!("{reason}");
+            Vec::new()
+        }
+        None => super::select_secrets(config, client.list_secrets(project_id).await?)?,
+    };
+    if !secrets.is_empty() {
+        println!("Injecting {} secret(s) from Secret Manager", secrets.len());
+    }
+
+    println!("Updating Cloud Run service {service_name} ({region}) without rebuilding...");
+    let url = retry_secret_propagation(
+        SECRET_ACCESS_PROPAGATION_TIMEOUT,
+        |delay, _| {
+            println!(
+                "  Cloud Run cannot read the secrets yet — new secret access can take \
+                 up to a minute to propagate. Retrying in {}s...",
+                delay.as_secs()
+            );
+        },
+        || {
+            client.update_cloud_run_service(
+                service_name,
+                project_id,
+                region,
+                &config.cloud_run,
+                &secrets,
+                &labels,
+            )
+        },
+    )
+    .await?;
+
+    println!();
+    println!("Updated: {url}");
+    if let Some(notice) = super::idle_cost_notice(config) {
+        println!("{notice}");
+    }
+    Ok(())
+}
@@ -0,0 +1,89 @@
+//! `[cloud_run].warmup_requests`: deploy the new revision without traffic,
+//! warm it up, then move traffic to it.
+
+use super::CliError;
+use super::ProjectContext;
+use propel_cloud::{DeployError, GcloudClient, ReqwestProber, Warmup, warm_up};
+
+/// Whether this deploy warms the new revision up before it takes traffic:
+/// `[cloud_run].warmup_requests` is set and the service already exists.
+///
+/// On a first deploy nothing else is serving (and Cloud Run rejects
+/// `--no-traffic` when creating a service), so `note` is told the warm-up
+/// is skipped.
+pub(crate) async fn warmup_applies(
+    client: &GcloudClient,
+    ctx: &ProjectContext,
+    project_id: &str,
+    mut note: impl FnMut(String),
+) -> Result<bool, DeployError> {
+    if ctx.config.cloud_run.warmup_requests == 0 {
+        return Ok(false);
+    }
+    match client
+        .service_url(ctx.service_name(), project_id, ctx.region())
+        .await
+    {
+        Ok(_) => Ok(true),
+        Err(e) if e.is_service_not_found() => {
+            note(format!(
+                "First deploy of {} — no previous revision to keep serving, skipping warm-up",
+                ctx.service_name()
+            ));
+            Ok(false)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Send the `[cloud_run].warmup_requests` to the untrafficked revision at
+/// `tag_url`, then move all traffic to it. Returns the service URL.
+///
+/// If the revision does not answer in time, traffic stays where it was.
+pub(crate) async fn warm_up_and_promote(
+    client: &GcloudClient,
+    ctx: &ProjectContext,
+    project_id: &str,
+    tag_url: &str,
+    mut note: impl FnMut(String),
+) -> Result<String, CliError> {
+    warm_up_revision(client, ctx, tag_url, &mut note).await?;
+    note("Shifting traffic to the new revision...".to_owned());
+    Ok(client
+        .promote_latest_revision(ctx.service_name(), project_id, ctx.region())
+        .await?)
+}
+
+/// Send the `[cloud_run].warmup_requests` to the revision at `tag_url`
+/// until enough succeed.
+pub(crate) async fn warm_up_revision(
+    client: &GcloudClient,
+    ctx: &ProjectContext,
+    tag_url: &str,
+    mut note: impl FnMut(String),
+) -> Result<(), CliError> {
+    let cloud_run = &ctx.config.cloud_run;
+    let url = format!(
+        "{}{}",
+        tag_url.trim_end_matches('/'),
+        cloud_run.warmup_path()
+    );
+    note(format!(
+        "Warming up the new revision with {} request(s) to {url}...",
+        cloud_run.warmup_requests
+    ));
+    // A private service answers 403 without the deployer's identity token.
+    let prober = if cloud_run.allow_unauthenticated {
+        ReqwestProber::new()
+    } else {
+        ReqwestProber::new().with_bearer_token(client.identity_token().await?)
+    };
+    warm_up(
+        &prober,
+        &url,
+        &Warmup::new(cloud_run.warmup_requests),
+        |outcome| note(format!("  not ready yet ({outcome}) — retrying")),
+    )
+    .await
+    .map_err(|e| CliError::Deploy(e.into()))
+}
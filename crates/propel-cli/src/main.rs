@@ -1,10 +1,14 @@
 mod commands;
 
+use std::process::ExitCode;
+
 use clap::{Parser, Subcommand};
+use commands::CliError;
+use tracing_subscriber::fmt::format::FmtSpan;
 
 #[derive(Parser)]
 #[command(name = "propel", about = "Deploy Rust apps to Cloud Run with Supabase")]
-#[command(version)]
+#[command(version, after_help = commands::EXIT_CODES_HELP)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
@@ -18,18 +22,77 @@ enum Commands {
         name: String,
     },
     /// Add Propel to an existing Rust project
-    Init,
+    Init {
+        /// Pick the GCP project and region from gcloud and write them to propel.toml
+        #[arg(long)]
+        guided: bool,
+    },
     /// Deploy to Google Cloud Run
     Deploy {
         /// Allow deploying with uncommitted changes
         #[arg(long)]
         allow_dirty: bool,
+        /// Add missing system packages for native dependencies to propel.toml
+        #[arg(long)]
+        fix_config: bool,
+        /// Print the bundled files and the Dockerfile before building, and
+        /// debug logs with step timings from propel (unless RUST_LOG is set)
+        #[arg(long, short = 'v')]
+        verbose: bool,
+        /// Also write the Cloud Build output to this file
+        #[arg(long, value_name = "PATH", default_value = commands::DEFAULT_BUILD_LOG)]
+        build_log: std::path::PathBuf,
+        /// Skip verifying the gcloud account's IAM permissions
+        #[arg(long)]
+        skip_iam_check: bool,
+        /// Fail on any disabled API, including Secret Manager when no
+        /// secrets are configured, and when the ejected Dockerfile names
+        /// another binary than the project builds
+        #[arg(long)]
+        strict_preflight: bool,
+        /// Upload the bundle even when it exceeds `[cloud_build].max_bundle_mb`
+        #[arg(long)]
+        force_large_bundle: bool,
+        /// Fail when files change while the bundle is copied (always on
+        /// without --allow-dirty)
+        #[arg(long)]
+        strict_bundle: bool,
+        /// Print the Dockerfile, bundled files, image tag, and gcloud
+        /// commands without building, deploying, or writing anything; with
+        /// --fix-config, plan with the fixed config but leave propel.toml as is
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Apply `[cloud_run]` settings, env vars, and secrets to the deployed service without rebuilding
+    Update,
+    /// Send all traffic to an earlier revision of the deployed service
+    Rollback {
+        /// Revision to route traffic to (default: the one before the revision serving now)
+        #[arg(long, value_name = "NAME")]
+        revision: Option<String>,
+        /// Skip confirmation prompt
+        #[arg(long, short = 'y')]
+        yes: bool,
+    },
+    /// Send all traffic to the canary revision started by `propel deploy`
+    Promote,
+    /// Send all traffic back to the revision serving before the canary
+    AbortCanary,
+    /// Manage who may call the deployed service
+    Iam {
+        #[command(subcommand)]
+        action: IamAction,
     },
     /// Manage secrets
     Secret {
         #[command(subcommand)]
         action: SecretAction,
     },
+    /// Inspect propel.toml
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
     /// Eject Dockerfile for manual customization
     Eject,
     /// Delete Cloud Run service, images, and local bundle
@@ -43,11 +106,46 @@ enum Commands {
         /// Also delete CI/CD resources (WIF, service account, GitHub Secrets, workflow)
         #[arg(long)]
         include_ci: bool,
+        /// With --include-ci, delete the WIF pool and service account even if other repositories use them
+        #[arg(long, requires = "include_ci")]
+        force_shared: bool,
+        /// Region to delete from instead of `[project].region` (e.g. after changing it)
+        #[arg(long)]
+        region: Option<String>,
+    },
+    /// Walk through first-time setup: gcloud login, project ID, APIs, and secrets
+    Setup {
+        /// Change nothing; print the commands for the remaining steps instead
+        #[arg(long)]
+        non_interactive: bool,
     },
     /// Check GCP setup and readiness
-    Doctor,
+    Doctor {
+        /// Skip verifying the gcloud account's IAM permissions
+        #[arg(long)]
+        skip_iam_check: bool,
+        /// Re-check API enablement instead of reusing a result from the last 10 minutes
+        #[arg(long)]
+        no_cache: bool,
+        /// Print the report as JSON, each check with a stable id
+        #[arg(long)]
+        json: bool,
+    },
     /// Show Cloud Run service status
     Status,
+    /// List recent deploys with the account that made each one
+    History {
+        /// Number of deploys to show
+        #[arg(long, short = 'n', default_value_t = 10)]
+        limit: usize,
+    },
+    /// Inspect every propel-managed service in the GCP project
+    Fleet {
+        #[command(subcommand)]
+        action: FleetAction,
+    },
+    /// Estimate monthly Cloud Run cost from `[cloud_run]` settings
+    Costs,
     /// Stream Cloud Run logs
     Logs {
         /// Tail logs in real-time
@@ -75,6 +173,38 @@ enum SecretAction {
     },
     /// List all secrets
     List,
+    /// Show a secret's creation time and version states (never its value)
+    Info {
+        /// Secret name
+        key: String,
+    },
+    /// Set every KEY=VALUE of a .env file as a secret
+    Import {
+        /// File to read
+        #[arg(long, default_value = ".env")]
+        file: std::path::PathBuf,
+        /// Leave secrets that already exist unchanged instead of adding a version
+        #[arg(long)]
+        skip_existing: bool,
+        /// Skip the confirmation prompt
+        #[arg(long, short = 'y')]
+        yes: bool,
+    },
+    /// Write selected secret values to a local .env file
+    Pull {
+        /// Comma-separated secret names to pull (required; there is no "pull all")
+        #[arg(long, value_delimiter = ',', required = true)]
+        only: Vec<String>,
+        /// File to write
+        #[arg(long, default_value = ".env")]
+        output: std::path::PathBuf,
+        /// Overwrite the output file if it exists
+        #[arg(long)]
+        force: bool,
+        /// Skip the confirmation prompt acknowledging values are written to disk
+        #[arg(long, short = 'y')]
+        yes: bool,
+    },
     /// Delete a secret
     Delete {
         /// Secret name
@@ -85,48 +215,151 @@ enum SecretAction {
     },
 }
 
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Check propel.toml and warn about settings that have no effect
+    Validate,
+}
+
+#[derive(Subcommand)]
+enum IamAction {
+    /// Grant roles/run.invoker on the service, e.g. to call a private service
+    GrantInvoker {
+        /// Email or IAM member (serviceAccount:..., user:..., group:...)
+        member: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum FleetAction {
+    /// List Cloud Run services deployed by propel, with their propel version and config hash
+    List,
+}
+
 #[derive(Subcommand)]
 enum CiAction {
     /// Set up GitHub Actions CI/CD pipeline (WIF + Service Account + GitHub Secrets + workflow)
     Init,
+    /// Show the WIF pool, provider, and service account this project uses
+    Status,
 }
 
+/// Log filter for `propel deploy --verbose` without `RUST_LOG`: debug
+/// events and span timings from propel's crates, warnings from the rest.
+const VERBOSE_LOG_FILTER: &str = "warn,propel=debug,propel_build=debug,propel_cloud=debug";
+
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let verbose = matches!(cli.command, Commands::Deploy { verbose: true, .. });
+    let default_filter = if verbose { VERBOSE_LOG_FILTER } else { "info" };
     let env_filter = match tracing_subscriber::EnvFilter::try_from_default_env() {
         Ok(filter) => filter,
         Err(e) => {
             // RUST_LOG set but malformed → warn the user
             if std::env::var("RUST_LOG").is_ok() {
-                eprintln!("Warning: invalid RUST_LOG value: {e}, falling back to 'info'");
+                eprintln!(
+                    "Warning: invalid RUST_LOG value: {e}, falling back to '{default_filter}'"
+                );
             }
-            tracing_subscriber::EnvFilter::new("info")
+            tracing_subscriber::EnvFilter::new(default_filter)
         }
     };
-    tracing_subscriber::fmt().with_env_filter(env_filter).init();
+    // Spans are at debug level, so their timings only show up with debug
+    // logging (`--verbose` or RUST_LOG).
+    tracing_subscriber::fmt()
+        .with_env_filter(env_filter)
+        .with_span_events(FmtSpan::CLOSE)
+        .init();
 
-    let cli = Cli::parse();
+    match run(cli.command).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => e.report(),
+    }
+}
 
-    match cli.command {
+async fn run(command: Commands) -> Result<(), CliError> {
+    match command {
         Commands::New { name } => commands::new_project(&name).await?,
-        Commands::Init => commands::init_project().await?,
-        Commands::Deploy { allow_dirty } => commands::deploy(allow_dirty).await?,
+        Commands::Init { guided } => commands::init_project(guided).await?,
+        Commands::Deploy {
+            allow_dirty,
+            fix_config,
+            verbose,
+            build_log,
+            skip_iam_check,
+            strict_preflight,
+            force_large_bundle,
+            strict_bundle,
+            dry_run,
+        } => {
+            commands::deploy(
+                allow_dirty,
+                fix_config,
+                verbose,
+                &build_log,
+                skip_iam_check,
+                strict_preflight,
+                force_large_bundle,
+                strict_bundle,
+                dry_run,
+            )
+            .await?
+        }
         Commands::Secret { action } => match action {
             SecretAction::Set { key_value } => commands::secret_set(&key_value).await?,
             SecretAction::List => commands::secret_list().await?,
+            SecretAction::Info { key } => commands::secret_info(&key).await?,
+            SecretAction::Import {
+                file,
+                skip_existing,
+                yes,
+            } => commands::secret_import(&file, skip_existing, yes).await?,
+            SecretAction::Pull {
+                only,
+                output,
+                force,
+                yes,
+            } => commands::secret_pull(&only, &output, force, yes).await?,
             SecretAction::Delete { key, yes } => commands::secret_delete(&key, yes).await?,
         },
+        Commands::Config { action } => match action {
+            ConfigAction::Validate => commands::config_validate().await?,
+        },
         Commands::Eject => commands::eject().await?,
         Commands::Destroy {
             yes,
             include_secrets,
             include_ci,
-        } => commands::destroy(yes, include_secrets, include_ci).await?,
-        Commands::Doctor => commands::doctor().await?,
+            force_shared,
+            region,
+        } => commands::destroy(yes, include_secrets, include_ci, force_shared, region).await?,
+        Commands::Setup { non_interactive } => commands::setup(non_interactive).await?,
+        Commands::Doctor {
+            skip_iam_check,
+            no_cache,
+            json,
+        } => commands::doctor(skip_iam_check, no_cache, json).await?,
+        Commands::Update => commands::update().await?,
+        Commands::Rollback { revision, yes } => {
+            commands::rollback(revision.as_deref(), yes).await?
+        }
+        Commands::Promote => commands::promote().await?,
+        Commands::AbortCanary => commands::abort_canary().await?,
+        Commands::Iam { action } => match action {
+            IamAction::GrantInvoker { member } => commands::iam_grant_invoker(&member).await?,
+        },
         Commands::Status => commands::status().await?,
+        Commands::History { limit } => commands::history(limit).await?,
+        Commands::Fleet { action } => match action {
+            FleetAction::List => commands::fleet_list().await?,
+        },
+        Commands::Costs => commands::costs().await?,
         Commands::Logs { follow, tail } => commands::logs(follow, tail).await?,
         Commands::Ci { action } => match action {
             CiAction::Init => commands::ci_init().await?,
+            CiAction::Status => commands::ci_status().await?,
         },
         Commands::Mcp(args) => commands::mcp::execute(args).await?,
     }
@@ -1,11 +1,34 @@
 use assert_cmd::cargo::cargo_bin_cmd;
 use predicates::prelude::*;
+use propel_testkit::{FakeGcloud, Script};
 use tempfile::TempDir;
 
 fn propel() -> assert_cmd::Command {
     cargo_bin_cmd!("propel")
 }
 
+/// Build a PATH directory exposing only the tools offline commands may use
+/// (cargo, rustc, git) — notably without `gcloud`.
+fn path_without_gcloud(dir: &std::path::Path) -> std::path::PathBuf {
+    path_with_only(dir, &["cargo", "rustc", "git"])
+}
+
+/// Build a PATH directory exposing only `tools`, never `gcloud`.
+fn path_with_only(dir: &std::path::Path, tools: &[&str]) -> std::path::PathBuf {
+    let bin_dir = dir.join("offline-bin");
+    std::fs::create_dir_all(&bin_dir).unwrap();
+    let search = std::env::var_os("PATH").unwrap();
+    for tool in tools {
+        let found = std::env::split_paths(&search)
+            .map(|p| p.join(tool))
+            .find(|p| p.is_file())
+            .unwrap_or_else(|| panic!("{tool} not found on PATH"));
+        std::os::unix::fs::symlink(found, bin_dir.join(tool)).unwrap();
+    }
+    assert!(!bin_dir.join("gcloud").exists());
+    bin_dir
+}
+
 // ── Help / Version ──
 
 #[test]
@@ -17,6 +40,16 @@ fn shows_help() {
         .stdout(predicate::str::contains("Deploy Rust apps to Cloud Run"));
 }
 
+#[test]
+fn help_documents_exit_codes() {
+    propel()
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Exit codes:"))
+        .stdout(predicate::str::contains("6  uncommitted changes"));
+}
+
 #[test]
 fn shows_version() {
     propel()
@@ -47,6 +80,81 @@ fn new_creates_project_structure() {
     assert!(project_dir.join(".gitignore").exists());
 }
 
+#[test]
+fn new_initializes_a_git_repository() {
+    let tmp = TempDir::new().unwrap();
+
+    propel()
+        .current_dir(tmp.path())
+        .args(["new", "with-git"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("git not found").not());
+
+    assert!(tmp.path().join("with-git/.git").is_dir());
+}
+
+#[test]
+fn new_gitignore_ignores_bundle_but_not_propel_dir() {
+    let tmp = TempDir::new().unwrap();
+
+    propel()
+        .current_dir(tmp.path())
+        .args(["new", "ignore-test"])
+        .assert()
+        .success();
+
+    let gitignore = std::fs::read_to_string(tmp.path().join("ignore-test/.gitignore")).unwrap();
+    assert!(gitignore.lines().any(|l| l == ".propel-bundle/"));
+    assert!(!gitignore.lines().any(|l| l == ".propel/" || l == ".propel"));
+}
+
+#[test]
+fn new_with_underscore_name_pins_cloud_run_service_name() {
+    let tmp = TempDir::new().unwrap();
+
+    propel()
+        .current_dir(tmp.path())
+        .args(["new", "my_api"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[project].name = \"my-api\""));
+
+    let cargo_toml = std::fs::read_to_string(tmp.path().join("my_api/Cargo.toml")).unwrap();
+    assert!(cargo_toml.contains("name = \"my_api\""));
+    let propel_toml = std::fs::read_to_string(tmp.path().join("my_api/propel.toml")).unwrap();
+    assert!(propel_toml.lines().any(|l| l == "name = \"my-api\""));
+}
+
+#[test]
+fn new_with_hyphen_name_keeps_template_name_commented() {
+    let tmp = TempDir::new().unwrap();
+
+    propel()
+        .current_dir(tmp.path())
+        .args(["new", "my-api"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Note:").not());
+
+    let propel_toml = std::fs::read_to_string(tmp.path().join("my-api/propel.toml")).unwrap();
+    assert!(propel_toml.contains("# name = \"my-service\""));
+}
+
+#[test]
+fn new_rejects_invalid_package_names() {
+    let tmp = TempDir::new().unwrap();
+
+    for name in ["1api", "my.api", "___"] {
+        propel()
+            .current_dir(tmp.path())
+            .args(["new", name])
+            .assert()
+            .failure();
+        assert!(!tmp.path().join(name).exists(), "{name}");
+    }
+}
+
 #[test]
 fn new_cargo_toml_contains_dependencies() {
     let tmp = TempDir::new().unwrap();
@@ -110,6 +218,28 @@ fn new_scaffold_does_not_require_supabase() {
     assert!(!cargo.contains("propel"));
 }
 
+#[test]
+fn init_suggests_propel_crate_not_deprecated_sdk() {
+    let tmp = TempDir::new().unwrap();
+    std::fs::write(
+        tmp.path().join("Cargo.toml"),
+        "[package]\nname = \"sdk-init\"\nversion = \"0.1.0\"\nedition = \"2024\"",
+    )
+    .unwrap();
+
+    propel()
+        .current_dir(tmp.path())
+        .arg("init")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("cargo add propel\n"))
+        .stdout(predicate::str::contains(
+            "use propel::{PropelState, PropelAuth};",
+        ))
+        .stdout(predicate::str::contains("propel-sdk").not())
+        .stdout(predicate::str::contains("propel_sdk").not());
+}
+
 // ── Eject Command ──
 
 #[test]
@@ -167,6 +297,217 @@ fn eject_fails_on_second_run() {
         .stderr(predicate::str::contains("already ejected"));
 }
 
+// ── Offline Commands (no gcloud on PATH) ──
+
+#[cfg(unix)]
+#[test]
+fn new_succeeds_without_gcloud() {
+    let tmp = TempDir::new().unwrap();
+    let path = path_without_gcloud(tmp.path());
+
+    propel()
+        .current_dir(tmp.path())
+        .env("PATH", &path)
+        .args(["new", "offline-new"])
+        .assert()
+        .success();
+}
+
+#[cfg(unix)]
+#[test]
+fn new_without_git_skips_git_init_with_a_notice() {
+    let tmp = TempDir::new().unwrap();
+    let path = path_with_only(tmp.path(), &["cargo", "rustc"]);
+
+    propel()
+        .current_dir(tmp.path())
+        .env("PATH", &path)
+        .args(["new", "no-git"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Created project"))
+        .stdout(predicate::str::contains(
+            "git not found on PATH — skipped `git init`",
+        ));
+
+    assert!(tmp.path().join("no-git/Cargo.toml").exists());
+    assert!(!tmp.path().join("no-git/.git").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn deploy_without_git_says_to_install_git() {
+    let tmp = TempDir::new().unwrap();
+    let path = path_with_only(tmp.path(), &["cargo", "rustc"]);
+
+    propel()
+        .current_dir(tmp.path())
+        .env("PATH", &path)
+        .arg("deploy")
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("git not found on PATH"))
+        .stderr(predicate::str::contains("install git"));
+}
+
+#[cfg(unix)]
+#[test]
+fn doctor_without_git_reports_the_git_row() {
+    let tmp = TempDir::new().unwrap();
+    let path = path_with_only(tmp.path(), &["cargo", "rustc"]);
+
+    propel()
+        .current_dir(tmp.path())
+        .env("PATH", &path)
+        .arg("doctor")
+        .assert()
+        .stdout(predicate::str::contains("git not found"));
+}
+
+#[cfg(unix)]
+#[test]
+fn doctor_json_identifies_failed_checks() {
+    let tmp = TempDir::new().unwrap();
+    let path = path_with_only(tmp.path(), &["cargo", "rustc"]);
+
+    let output = propel()
+        .current_dir(tmp.path())
+        .env("PATH", &path)
+        .args(["doctor", "--json"])
+        .assert()
+        .code(3)
+        .get_output()
+        .stdout
+        .clone();
+
+    let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(report["all_passed"], false);
+    let check = |id: &str| {
+        report["checks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|check| check["id"] == id)
+            .unwrap_or_else(|| panic!("no {id} check in {report}"))
+            .clone()
+    };
+    assert_eq!(check("git.installed")["passed"], false);
+    assert_eq!(check("gcloud.installed")["passed"], false);
+    assert_eq!(check("gcp.billing")["skipped"], true);
+}
+
+#[test]
+fn init_guided_needs_a_terminal() {
+    let tmp = TempDir::new().unwrap();
+    std::fs::write(
+        tmp.path().join("Cargo.toml"),
+        "[package]\nname = \"guided\"\nversion = \"0.1.0\"\nedition = \"2024\"",
+    )
+    .unwrap();
+
+    propel()
+        .current_dir(tmp.path())
+        .args(["init", "--guided"])
+        .write_stdin("")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("interactive terminal"))
+        .stderr(predicate::str::contains("gcp_project_id"));
+
+    assert!(!tmp.path().join("propel.toml").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn init_succeeds_without_gcloud() {
+    let tmp = TempDir::new().unwrap();
+    let path = path_without_gcloud(tmp.path());
+    std::fs::write(
+        tmp.path().join("Cargo.toml"),
+        "[package]\nname = \"offline-init\"\nversion = \"0.1.0\"\nedition = \"2024\"",
+    )
+    .unwrap();
+
+    propel()
+        .current_dir(tmp.path())
+        .env("PATH", &path)
+        .arg("init")
+        .assert()
+        .success();
+
+    assert!(tmp.path().join("propel.toml").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn eject_succeeds_without_gcloud() {
+    let tmp = TempDir::new().unwrap();
+    let path = path_without_gcloud(tmp.path());
+    std::fs::write(
+        tmp.path().join("Cargo.toml"),
+        "[package]\nname = \"offline-eject\"\nversion = \"0.1.0\"\nedition = \"2024\"",
+    )
+    .unwrap();
+    std::fs::create_dir(tmp.path().join("src")).unwrap();
+    std::fs::write(tmp.path().join("src/main.rs"), "fn main() {}").unwrap();
+
+    propel()
+        .current_dir(tmp.path())
+        .env("PATH", &path)
+        .arg("eject")
+        .assert()
+        .success();
+
+    assert!(tmp.path().join(".propel/Dockerfile").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn status_without_gcloud_says_gcloud_is_needed() {
+    let tmp = TempDir::new().unwrap();
+    let path = path_without_gcloud(tmp.path());
+    std::fs::write(
+        tmp.path().join("Cargo.toml"),
+        "[package]\nname = \"offline-status\"\nversion = \"0.1.0\"\nedition = \"2024\"",
+    )
+    .unwrap();
+    std::fs::write(
+        tmp.path().join("propel.toml"),
+        "[project]\ngcp_project_id = \"proj\"",
+    )
+    .unwrap();
+    std::fs::create_dir(tmp.path().join("src")).unwrap();
+    std::fs::write(tmp.path().join("src/main.rs"), "fn main() {}").unwrap();
+
+    propel()
+        .current_dir(tmp.path())
+        .env("PATH", &path)
+        .arg("status")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("gcloud"));
+}
+
+#[cfg(unix)]
+#[test]
+fn setup_non_interactive_prints_the_manual_steps() {
+    let tmp = TempDir::new().unwrap();
+    let path = path_without_gcloud(tmp.path());
+
+    propel()
+        .current_dir(tmp.path())
+        .env("PATH", &path)
+        .args(["setup", "--non-interactive"])
+        .assert()
+        .code(3)
+        .stdout(predicate::str::contains("gcloud auth login"))
+        .stdout(predicate::str::contains("gcp_project_id"))
+        .stdout(predicate::str::contains("re-run `propel setup`"))
+        .stderr(predicate::str::contains("setup incomplete"));
+
+    assert!(!tmp.path().join("propel.toml").exists());
+}
+
 // ── Deploy Command (no GCP) ──
 
 #[test]
@@ -187,14 +528,35 @@ fn deploy_fails_without_gcp_project_id() {
         .current_dir(tmp.path())
         .args(["deploy", "--allow-dirty"])
         .assert()
-        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("gcp_project_id"));
+}
+
+#[test]
+fn update_fails_without_gcp_project_id() {
+    let tmp = TempDir::new().unwrap();
+
+    std::fs::write(
+        tmp.path().join("Cargo.toml"),
+        "[package]\nname = \"no-gcp\"\nversion = \"0.1.0\"\nedition = \"2024\"",
+    )
+    .unwrap();
+    std::fs::write(tmp.path().join("propel.toml"), "").unwrap();
+    std::fs::create_dir(tmp.path().join("src")).unwrap();
+    std::fs::write(tmp.path().join("src/main.rs"), "fn main() {}").unwrap();
+
+    propel()
+        .current_dir(tmp.path())
+        .arg("update")
+        .assert()
+        .code(2)
         .stderr(predicate::str::contains("gcp_project_id"));
 }
 
 // ── Deploy: Dirty Check ──
 
 #[test]
-fn deploy_fails_on_non_git_directory() {
+fn deploy_outside_git_skips_the_dirty_check() {
     let tmp = TempDir::new().unwrap();
 
     std::fs::write(
@@ -205,12 +567,179 @@ fn deploy_fails_on_non_git_directory() {
     std::fs::create_dir(tmp.path().join("src")).unwrap();
     std::fs::write(tmp.path().join("src/main.rs"), "fn main() {}").unwrap();
 
+    // Not a repository, so not dirty: deploy gets as far as the config.
     propel()
         .current_dir(tmp.path())
         .arg("deploy")
         .assert()
-        .failure()
-        .stderr(predicate::str::contains("git"));
+        .code(2)
+        .stderr(predicate::str::contains("gcp_project_id not set"));
+}
+
+#[test]
+fn deploy_dry_run_prints_the_plan_without_gcloud() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path();
+
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        "[package]\nname = \"dry-run\"\nversion = \"0.1.0\"\nedition = \"2024\"",
+    )
+    .unwrap();
+    std::fs::create_dir(dir.join("src")).unwrap();
+    std::fs::write(dir.join("src/main.rs"), "fn main() {}").unwrap();
+    std::fs::write(
+        dir.join("propel.toml"),
+        "[project]\ngcp_project_id = \"my-proj\"\n\n[build]\ntag_strategy = \"latest\"\n",
+    )
+    .unwrap();
+    let tools = TempDir::new().unwrap();
+
+    // Outside git (no dirty check), and with no gcloud on PATH: the plan
+    // must not need it.
+    propel()
+        .current_dir(dir)
+        .env("PATH", path_without_gcloud(tools.path()))
+        .args(["deploy", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--- Dockerfile ---"))
+        .stdout(predicate::str::contains("    src/main.rs"))
+        .stdout(predicate::str::contains(
+            "Image: us-central1-docker.pkg.dev/my-proj/propel/dry-run:latest",
+        ))
+        .stdout(predicate::str::contains(
+            "gcloud builds submit ./.propel-bundle --project my-proj",
+        ))
+        .stdout(predicate::str::contains(
+            "gcloud run deploy dry-run --image",
+        ));
+
+    assert!(!dir.join(".propel-bundle").exists());
+}
+
+#[test]
+fn deploy_dry_run_with_fix_config_leaves_propel_toml_alone() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path();
+
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        "[package]\nname = \"native\"\nversion = \"0.1.0\"\nedition = \"2024\"\n\n\
+         [dependencies]\npq-sys = { path = \"pq-sys\" }\n",
+    )
+    .unwrap();
+    std::fs::create_dir(dir.join("src")).unwrap();
+    std::fs::write(dir.join("src/main.rs"), "fn main() {}").unwrap();
+    // A local stand-in: detection goes by crate name.
+    std::fs::create_dir_all(dir.join("pq-sys/src")).unwrap();
+    std::fs::write(
+        dir.join("pq-sys/Cargo.toml"),
+        "[package]\nname = \"pq-sys\"\nversion = \"0.6.0\"\nedition = \"2024\"\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("pq-sys/src/lib.rs"), "").unwrap();
+    let config = "# keep me\n[project]\ngcp_project_id = \"my-proj\"\n\n\
+                  [build]\ntag_strategy = \"latest\"\n";
+    std::fs::write(dir.join("propel.toml"), config).unwrap();
+    let tools = TempDir::new().unwrap();
+
+    propel()
+        .current_dir(dir)
+        .env("PATH", path_without_gcloud(tools.path()))
+        .args(["deploy", "--dry-run", "--fix-config"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Would update propel.toml [build] for pq-sys (dry run: not written)",
+        ))
+        .stdout(predicate::str::contains("libpq-dev"));
+
+    assert_eq!(
+        std::fs::read(dir.join("propel.toml")).unwrap(),
+        config.as_bytes()
+    );
+}
+
+#[test]
+fn deploy_dry_run_outside_git_tags_with_the_package_version() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path();
+
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        "[package]\nname = \"no-git\"\nversion = \"0.3.1\"\nedition = \"2024\"",
+    )
+    .unwrap();
+    std::fs::create_dir(dir.join("src")).unwrap();
+    std::fs::write(dir.join("src/main.rs"), "fn main() {}").unwrap();
+    std::fs::write(
+        dir.join("propel.toml"),
+        "[project]\ngcp_project_id = \"my-proj\"\n",
+    )
+    .unwrap();
+    let tools = TempDir::new().unwrap();
+
+    // Default tag_strategy = "sha", but there is no commit to tag with.
+    propel()
+        .current_dir(dir)
+        .env("PATH", path_without_gcloud(tools.path()))
+        .args(["deploy", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Image: us-central1-docker.pkg.dev/my-proj/propel/no-git:0.3.1",
+        ));
+}
+
+#[test]
+fn deploy_dry_run_of_a_workspace_member_bundles_the_workspace() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path();
+
+    std::fs::write(
+        root.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"api\", \"shared\"]\nresolver = \"2\"\n",
+    )
+    .unwrap();
+    std::fs::create_dir_all(root.join("shared/src")).unwrap();
+    std::fs::write(
+        root.join("shared/Cargo.toml"),
+        "[package]\nname = \"shared\"\nversion = \"0.1.0\"\nedition = \"2024\"\n",
+    )
+    .unwrap();
+    std::fs::write(root.join("shared/src/lib.rs"), "").unwrap();
+    let api = root.join("api");
+    std::fs::create_dir_all(api.join("src")).unwrap();
+    std::fs::write(
+        api.join("Cargo.toml"),
+        "[package]\nname = \"api\"\nversion = \"0.1.0\"\nedition = \"2024\"\n\n\
+         [dependencies]\nshared = { path = \"../shared\" }\n",
+    )
+    .unwrap();
+    std::fs::write(api.join("src/main.rs"), "fn main() {}").unwrap();
+    std::fs::write(
+        api.join("propel.toml"),
+        "[project]\ngcp_project_id = \"my-proj\"\n\n[build]\ntag_strategy = \"latest\"\n",
+    )
+    .unwrap();
+    let tools = TempDir::new().unwrap();
+
+    propel()
+        .current_dir(&api)
+        .env("PATH", path_without_gcloud(tools.path()))
+        .args(["deploy", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--package api --bin api\n"))
+        .stdout(predicate::str::contains("COPY api/ ./\n"))
+        .stdout(predicate::str::contains("shared/src/lib.rs"))
+        .stdout(predicate::str::contains(format!(
+            "gcloud builds submit {} --project my-proj",
+            root.join(".propel-bundle").display()
+        )));
+
+    assert!(!root.join(".propel-bundle").exists());
 }
 
 #[test]
@@ -260,7 +789,7 @@ fn deploy_dirty_repo_blocked_without_flag() {
         .current_dir(dir)
         .arg("deploy")
         .assert()
-        .failure()
+        .code(6)
         .stderr(predicate::str::contains("uncommitted changes"));
 }
 
@@ -288,3 +817,356 @@ fn secret_set_rejects_invalid_format() {
         .failure()
         .stderr(predicate::str::contains("KEY=VALUE"));
 }
+
+#[test]
+fn secret_pull_requires_explicit_names() {
+    let tmp = TempDir::new().unwrap();
+
+    propel()
+        .current_dir(tmp.path())
+        .args(["secret", "pull"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--only"));
+}
+
+#[test]
+fn secret_pull_refuses_to_overwrite_without_force() {
+    let tmp = TempDir::new().unwrap();
+    std::fs::write(tmp.path().join(".env"), "KEEP=1\n").unwrap();
+
+    propel()
+        .current_dir(tmp.path())
+        .args(["secret", "pull", "--only", "DB_URL", "--yes"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--force"));
+
+    assert_eq!(
+        std::fs::read_to_string(tmp.path().join(".env")).unwrap(),
+        "KEEP=1\n"
+    );
+}
+
+#[test]
+fn secret_import_rejects_an_invalid_env_file() {
+    let tmp = TempDir::new().unwrap();
+    std::fs::write(tmp.path().join("prod.env"), "my-key=1\n").unwrap();
+
+    propel()
+        .current_dir(tmp.path())
+        .args(["secret", "import", "--file", "prod.env", "--yes"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("prod.env"))
+        .stderr(predicate::str::contains("my-key"));
+}
+
+// ── Costs ──
+
+#[test]
+fn costs_estimates_without_gcloud() {
+    let tmp = TempDir::new().unwrap();
+    std::fs::write(
+        tmp.path().join("propel.toml"),
+        "[cloud_run]\nmin_instances = 1\nmax_instances = 3\n",
+    )
+    .unwrap();
+    let bin_dir = path_without_gcloud(tmp.path());
+
+    propel()
+        .current_dir(tmp.path())
+        .env("PATH", &bin_dir)
+        .arg("costs")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("min_instances = 1"))
+        .stdout(predicate::str::contains("max_instances = 3"))
+        .stdout(predicate::str::contains("Rough estimate"));
+}
+
+// ── Config validate ──
+
+#[test]
+fn config_validate_warns_about_build_fields_when_ejected() {
+    let tmp = TempDir::new().unwrap();
+    std::fs::write(
+        tmp.path().join("propel.toml"),
+        "[build]\nextra_packages = [\"libpq-dev\"]\n",
+    )
+    .unwrap();
+    std::fs::create_dir(tmp.path().join(".propel")).unwrap();
+    std::fs::write(tmp.path().join(".propel/Dockerfile"), "FROM scratch\n").unwrap();
+
+    propel()
+        .current_dir(tmp.path())
+        .args(["config", "validate"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "[build].extra_packages has no effect: project is ejected",
+        ))
+        .stdout(predicate::str::contains("1 warning(s)"));
+}
+
+#[test]
+fn config_validate_without_eject_is_clean() {
+    let tmp = TempDir::new().unwrap();
+    std::fs::write(
+        tmp.path().join("propel.toml"),
+        "[build]\nextra_packages = [\"libpq-dev\"]\n",
+    )
+    .unwrap();
+
+    propel()
+        .current_dir(tmp.path())
+        .args(["config", "validate"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("propel.toml is valid\n"))
+        .stdout(predicate::str::contains("no effect").not());
+}
+
+#[test]
+fn config_validate_reports_invalid_config() {
+    let tmp = TempDir::new().unwrap();
+    std::fs::write(
+        tmp.path().join("propel.toml"),
+        "[build]\nplatform = \"linux/riscv64\"\n",
+    )
+    .unwrap();
+
+    propel()
+        .current_dir(tmp.path())
+        .args(["config", "validate"])
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn config_validate_warns_about_empty_propel_toml() {
+    let tmp = TempDir::new().unwrap();
+    std::fs::write(tmp.path().join("propel.toml"), "").unwrap();
+
+    propel()
+        .current_dir(tmp.path())
+        .args(["config", "validate"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "propel.toml is empty; using defaults",
+        ));
+}
+
+#[test]
+fn config_validate_rejects_propel_toml_directory() {
+    let tmp = TempDir::new().unwrap();
+    std::fs::create_dir(tmp.path().join("propel.toml")).unwrap();
+
+    propel()
+        .current_dir(tmp.path())
+        .args(["config", "validate"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("is a directory, not a file"));
+}
+
+// ── End to end with a scripted gcloud ──
+
+const PREFLIGHT: &str = include_str!("fixtures/gcloud_preflight.toml");
+const DEPLOY: &str = include_str!("fixtures/gcloud_deploy.toml");
+const BUILD_FAILURE: &str = include_str!("fixtures/gcloud_build_failure.toml");
+const DESTROY: &str = include_str!("fixtures/gcloud_destroy.toml");
+
+/// Fixtures in the order their responses are tried.
+fn scripted_gcloud(dir: &std::path::Path, fixtures: &[&str]) -> FakeGcloud {
+    let script = fixtures
+        .iter()
+        .map(|fixture| Script::parse(fixture).unwrap())
+        .fold(Script::default(), Script::then);
+    FakeGcloud::install(dir, &script).unwrap()
+}
+
+/// A committed package `hello` deploying to GCP project `my-proj`.
+fn committed_project(dir: &std::path::Path) {
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        "[package]\nname = \"hello\"\nversion = \"0.1.0\"\nedition = \"2024\"\n",
+    )
+    .unwrap();
+    std::fs::create_dir(dir.join("src")).unwrap();
+    std::fs::write(dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+    std::fs::write(
+        dir.join("propel.toml"),
+        "[project]\ngcp_project_id = \"my-proj\"\n",
+    )
+    .unwrap();
+    for args in [
+        &["generate-lockfile", "--offline"][..],
+        &["init", "-q"],
+        &["add", "-A"],
+        &[
+            "-c",
+            "user.email=t@t.com",
+            "-c",
+            "user.name=T",
+            "commit",
+            "-q",
+            "-m",
+            "init",
+        ],
+    ] {
+        let program = if args[0] == "generate-lockfile" {
+            "cargo"
+        } else {
+            "git"
+        };
+        let status = std::process::Command::new(program)
+            .args(args)
+            .current_dir(dir)
+            .status()
+            .unwrap();
+        assert!(status.success(), "{program} {args:?}");
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn deploy_runs_the_pipeline_against_scripted_gcloud() {
+    let project = TempDir::new().unwrap();
+    committed_project(project.path());
+    let tools = TempDir::new().unwrap();
+    let gcloud = scripted_gcloud(tools.path(), &[PREFLIGHT, DEPLOY]);
+
+    propel()
+        .current_dir(project.path())
+        .env("PATH", gcloud.path())
+        .arg("deploy")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Project my-proj (My Project) · account dev@example.com · region us-central1 · service hello",
+        ))
+        .stdout(predicate::str::contains(
+            "Injecting 1 secret(s) from Secret Manager",
+        ))
+        .stdout(predicate::str::contains("[build] DONE"))
+        .stdout(predicate::str::contains(
+            "Image: us-central1-docker.pkg.dev/my-proj/propel/hello:",
+        ))
+        .stdout(predicate::str::contains("Image size 30.0 MiB"))
+        .stdout(predicate::str::contains(
+            "Deployed: https://hello-abc123-uc.a.run.app",
+        ));
+
+    assert_eq!(gcloud.unscripted().unwrap(), Vec::<String>::new());
+    let calls = gcloud.calls().unwrap();
+    let deploy = calls
+        .iter()
+        .find(|call| call.starts_with("run deploy hello "))
+        .unwrap_or_else(|| panic!("no run deploy in {calls:#?}"));
+    assert!(
+        deploy.contains("--update-secrets DATABASE_URL=DATABASE_URL:latest"),
+        "{deploy}"
+    );
+    let build_log = std::fs::read_to_string(project.path().join(".propel/last-build.log")).unwrap();
+    assert!(build_log.contains("DONE"), "{build_log}");
+}
+
+#[cfg(unix)]
+#[test]
+fn deploy_reports_a_failed_build_with_exit_code_4() {
+    let project = TempDir::new().unwrap();
+    committed_project(project.path());
+    let tools = TempDir::new().unwrap();
+    let gcloud = scripted_gcloud(tools.path(), &[PREFLIGHT, BUILD_FAILURE, DEPLOY]);
+
+    propel()
+        .current_dir(project.path())
+        .env("PATH", gcloud.path())
+        .arg("deploy")
+        .assert()
+        .code(4)
+        .stdout(predicate::str::contains(
+            "[build] Step #0: error[E0425]: cannot find value `x` in this scope",
+        ))
+        .stdout(predicate::str::contains(
+            "Full build log: .propel/last-build.log",
+        ))
+        .stdout(predicate::str::contains("Deployed:").not())
+        .stderr(predicate::str::contains("cloud build submission failed"))
+        .stderr(predicate::str::contains(
+            "completed with status \"FAILURE\"",
+        ));
+
+    assert_eq!(gcloud.unscripted().unwrap(), Vec::<String>::new());
+    let calls = gcloud.calls().unwrap();
+    assert!(
+        !calls.iter().any(|call| call.starts_with("run deploy")),
+        "{calls:#?}"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn doctor_passes_against_scripted_gcloud() {
+    let project = TempDir::new().unwrap();
+    committed_project(project.path());
+    let tools = TempDir::new().unwrap();
+    let gcloud = scripted_gcloud(tools.path(), &[PREFLIGHT]);
+
+    let output = propel()
+        .current_dir(project.path())
+        .env("PATH", gcloud.path())
+        .args(["doctor", "--json"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(report["all_passed"], true, "{report}");
+    let account = report["checks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|check| check["id"] == "gcp.account")
+        .unwrap_or_else(|| panic!("no gcp.account check in {report}"));
+    assert_eq!(account["detail"], "dev@example.com");
+    assert_eq!(gcloud.unscripted().unwrap(), Vec::<String>::new());
+}
+
+#[cfg(unix)]
+#[test]
+fn destroy_deletes_the_service_and_images() {
+    let project = TempDir::new().unwrap();
+    committed_project(project.path());
+    let tools = TempDir::new().unwrap();
+    let gcloud = scripted_gcloud(tools.path(), &[PREFLIGHT, DESTROY]);
+
+    propel()
+        .current_dir(project.path())
+        .env("PATH", gcloud.path())
+        .args(["destroy", "--yes"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Deleting Cloud Run service 'hello'...",
+        ))
+        .stdout(predicate::str::contains("Destroy complete."))
+        .stdout(predicate::str::contains(
+            "1 secret(s) remain in Secret Manager",
+        ))
+        .stdout(predicate::str::contains("cleanup step(s) failed").not());
+
+    assert_eq!(gcloud.unscripted().unwrap(), Vec::<String>::new());
+    let calls = gcloud.calls().unwrap();
+    for deletion in [
+        "run services delete hello --project my-proj --region us-central1 --quiet",
+        "artifacts docker images delete us-central1-docker.pkg.dev/my-proj/propel/hello \
+         --project my-proj --delete-tags --quiet",
+    ] {
+        assert!(calls.iter().any(|call| call == deletion), "{calls:#?}");
+    }
+}
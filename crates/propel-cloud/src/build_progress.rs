@@ -0,0 +1,221 @@
+//! Progress detection over streamed Cloud Build output.
+//!
+//! Recognizes the stages of propel's generated Dockerfile (see
+//! `propel_build::DockerfileGenerator`) in both the classic Docker builder
+//! output (`Step 5/17 : FROM chef AS planner`) and BuildKit output
+//! (`#9 [cacher 2/3] COPY ...`), and whether the `cargo chef cook` layer
+//! came from cache. An ejected Dockerfile with other stage names simply
+//! produces no events.
+
+use std::fmt;
+
+/// A stage of the generated Dockerfile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildStage {
+    Planner,
+    Cacher,
+    Builder,
+    Runtime,
+}
+
+impl BuildStage {
+    const ALL: [Self; 4] = [Self::Planner, Self::Cacher, Self::Builder, Self::Runtime];
+
+    /// Stage for a named `FROM ... AS <alias>`; `chef` (the shared base) has none.
+    fn from_alias(alias: &str) -> Option<Self> {
+        match alias {
+            "planner" => Some(Self::Planner),
+            "cacher" => Some(Self::Cacher),
+            "builder" => Some(Self::Builder),
+            _ => None,
+        }
+    }
+
+    fn number(self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|s| *s == self)
+            .map_or(0, |i| i + 1)
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::Planner => "planning dependencies (cargo chef prepare)",
+            Self::Cacher => "building dependencies (cargo chef cook)",
+            Self::Builder => "compiling the application",
+            Self::Runtime => "assembling the runtime image",
+        }
+    }
+}
+
+/// Something worth telling the user about while a build streams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildEvent {
+    StageStarted(BuildStage),
+    /// Whether the `cargo chef cook` layer was reused.
+    DependencyCache {
+        hit: bool,
+    },
+}
+
+impl fmt::Display for BuildEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StageStarted(stage) => write!(
+                f,
+                "Stage {}/{}: {}",
+                stage.number(),
+                BuildStage::ALL.len(),
+                stage.description()
+            ),
+            Self::DependencyCache { hit: true } => f.write_str("Dependencies: cached layer hit"),
+            Self::DependencyCache { hit: false } => {
+                f.write_str("Dependencies: cached layer miss — rebuilding dependencies")
+            }
+        }
+    }
+}
+
+/// The `cargo chef cook` step whose outcome is still unknown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PendingCook {
+    Classic,
+    /// BuildKit step id, e.g. `#10`.
+    BuildKit(String),
+}
+
+/// Line-by-line build log parser. Feed every output line to [`Self::observe`].
+#[derive(Debug, Default)]
+pub struct BuildProgress {
+    announced: Vec<BuildStage>,
+    pending_cook: Option<PendingCook>,
+}
+
+impl BuildProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Consume one line of build output, returning an event it completes.
+    pub fn observe(&mut self, line: &str) -> Option<BuildEvent> {
+        let line = strip_step_prefix(line.trim_end());
+        if let Some(instruction) = classic_instruction(line) {
+            return self.observe_classic_instruction(instruction);
+        }
+        if self.pending_cook == Some(PendingCook::Classic) {
+            let marker = line.trim_start();
+            if marker.starts_with("---> Using cache") {
+                self.pending_cook = None;
+                return Some(BuildEvent::DependencyCache { hit: true });
+            }
+            if marker.starts_with("---> Running in") {
+                self.pending_cook = None;
+                return Some(BuildEvent::DependencyCache { hit: false });
+            }
+        }
+        if let Some((id, rest)) = buildkit_line(line) {
+            return self.observe_buildkit(id, rest);
+        }
+        None
+    }
+
+    fn observe_classic_instruction(&mut self, instruction: &str) -> Option<BuildEvent> {
+        self.pending_cook = None;
+        if let Some(image) = strip_keyword(instruction, "FROM") {
+            let stage = match image
+                .split_once(" AS ")
+                .or_else(|| image.split_once(" as "))
+            {
+                Some((_, alias)) => BuildStage::from_alias(alias.trim())?,
+                None => BuildStage::Runtime,
+            };
+            return self.announce(stage);
+        }
+        if instruction.contains("cargo chef cook") {
+            self.pending_cook = Some(PendingCook::Classic);
+        }
+        None
+    }
+
+    fn observe_buildkit(&mut self, id: &str, rest: &str) -> Option<BuildEvent> {
+        if let Some(PendingCook::BuildKit(pending)) = &self.pending_cook
+            && pending == id
+            && !rest.starts_with('[')
+        {
+            let hit = rest == "CACHED";
+            self.pending_cook = None;
+            return Some(BuildEvent::DependencyCache { hit });
+        }
+
+        // `[cacher 3/3] RUN cargo chef cook ...`
+        let (header, instruction) = rest.strip_prefix('[')?.split_once("] ")?;
+        let (stage_name, _) = header.split_once(' ')?;
+        if instruction.contains("cargo chef cook") {
+            self.pending_cook = Some(PendingCook::BuildKit(id.to_owned()));
+        }
+        // BuildKit pulls every base image up front, so a stage only counts
+        // as started at its first instruction after FROM.
+        if strip_keyword(instruction, "FROM").is_some() {
+            return None;
+        }
+        let stage = match BuildStage::from_alias(stage_name) {
+            Some(stage) => stage,
+            None if stage_name.starts_with("stage-") => BuildStage::Runtime,
+            None => return None,
+        };
+        self.announce(stage)
+    }
+
+    /// Emit a stage once, however often its lines appear.
+    fn announce(&mut self, stage: BuildStage) -> Option<BuildEvent> {
+        if self.announced.contains(&stage) {
+            return None;
+        }
+        self.announced.push(stage);
+        Some(BuildEvent::StageStarted(stage))
+    }
+}
+
+/// Drop Cloud Build's `Step #0: ` / `Step #0 - "build": ` step prefix.
+fn strip_step_prefix(line: &str) -> &str {
+    let Some(rest) = line.strip_prefix("Step #") else {
+        return line;
+    };
+    let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+    if digits == 0 {
+        return line;
+    }
+    let rest = &rest[digits..];
+    if let Some(rest) = rest.strip_prefix(": ") {
+        return rest;
+    }
+    if let Some(rest) = rest.strip_prefix(" - \"")
+        && let Some((_, rest)) = rest.split_once("\": ")
+    {
+        return rest;
+    }
+    line
+}
+
+/// `Step 5/17 : FROM chef AS planner` → `FROM chef AS planner`.
+fn classic_instruction(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix("Step ")?;
+    let (counter, instruction) = rest.split_once(" : ")?;
+    let (current, total) = counter.split_once('/')?;
+    let numeric = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    (numeric(current) && numeric(total)).then_some(instruction)
+}
+
+/// `#10 [cacher 3/3] RUN ...` → (`#10`, `[cacher 3/3] RUN ...`).
+fn buildkit_line(line: &str) -> Option<(&str, &str)> {
+    let (id, rest) = line.split_once(' ')?;
+    let digits = id.strip_prefix('#')?;
+    (!digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())).then_some((id, rest))
+}
+
+/// `FROM rust AS chef` with keyword `FROM` → `rust AS chef`.
+fn strip_keyword<'a>(instruction: &'a str, keyword: &str) -> Option<&'a str> {
+    instruction
+        .strip_prefix(keyword)
+        .and_then(|rest| rest.strip_prefix(' '))
+}
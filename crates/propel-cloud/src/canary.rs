@@ -0,0 +1,109 @@
+//! Canary releases: a new revision is deployed tagged [`CANARY_TAG`]
+//! without traffic, then given a share of it
+//! ([`GcloudClient::deploy_canary_revision`](crate::GcloudClient::deploy_canary_revision),
+//! [`GcloudClient::split_canary_traffic`](crate::GcloudClient::split_canary_traffic))
+//! while the revision serving before keeps the rest. Promoting moves all
+//! traffic to the canary; aborting moves it back. Both drop the tag.
+//!
+//! The service itself records the canary: one is in progress exactly
+//! while a traffic target carries [`CANARY_TAG`], which
+//! [`parse_canary_state`] reads back.
+
+use serde::Deserialize;
+
+/// Traffic tag of the canary revision; its URL is
+/// `https://canary---<service host>`.
+pub const CANARY_TAG: &str = "canary";
+
+/// A canary in progress on a service.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CanaryState {
+    /// Revision tagged [`CANARY_TAG`].
+    pub revision: String,
+    /// Share of the traffic it receives, 0–100.
+    pub percent: u32,
+    /// Tag URL reaching the canary directly.
+    pub url: Option<String>,
+    /// Revision serving the rest of the traffic: the one an abort returns
+    /// to. `None` when the canary serves everything.
+    pub stable_revision: Option<String>,
+    /// Whether the canary is the service's latest ready revision.
+    /// Promoting follows the latest revision, so it must be.
+    pub latest: bool,
+}
+
+/// `gcloud run services describe --format json`, reduced to traffic.
+#[derive(Debug, Deserialize)]
+struct Service {
+    #[serde(default)]
+    status: ServiceStatus,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ServiceStatus {
+    #[serde(default)]
+    traffic: Vec<TrafficTarget>,
+    latest_ready_revision_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrafficTarget {
+    revision_name: Option<String>,
+    #[serde(default)]
+    percent: u32,
+    #[serde(default)]
+    latest_revision: bool,
+    tag: Option<String>,
+    url: Option<String>,
+}
+
+/// The canary in progress according to `gcloud run services describe
+/// --format json` output, or `None` when no traffic target is tagged
+/// [`CANARY_TAG`].
+///
+/// Cloud Run may list a revision's tag and its traffic share as separate
+/// targets, and a target that follows the latest revision counts toward
+/// `latestReadyRevisionName`.
+pub fn parse_canary_state(service_json: &str) -> Result<Option<CanaryState>, serde_json::Error> {
+    let service: Service = serde_json::from_str(service_json)?;
+    let latest_ready = service.status.latest_ready_revision_name;
+    let revision_of = |target: &TrafficTarget| match &target.revision_name {
+        Some(name) => Some(name.clone()),
+        None if target.latest_revision => latest_ready.clone(),
+        None => None,
+    };
+
+    let traffic = &service.status.traffic;
+    let Some(tagged) = traffic
+        .iter()
+        .find(|target| target.tag.as_deref() == Some(CANARY_TAG))
+    else {
+        return Ok(None);
+    };
+    let Some(revision) = revision_of(tagged) else {
+        return Ok(None);
+    };
+
+    let mut percent = 0;
+    let mut stable: Option<(String, u32)> = None;
+    for target in traffic {
+        let Some(name) = revision_of(target) else {
+            continue;
+        };
+        if name == revision {
+            percent += target.percent;
+        } else if target.percent > stable.as_ref().map_or(0, |(_, p)| *p) {
+            stable = Some((name, target.percent));
+        }
+    }
+
+    Ok(Some(CanaryState {
+        latest: latest_ready.as_deref() == Some(revision.as_str()),
+        url: tagged.url.clone(),
+        stable_revision: stable.map(|(name, _)| name),
+        percent,
+        revision,
+    }))
+}
@@ -1,19 +1,60 @@
+use crate::canary::{CANARY_TAG, CanaryState, parse_canary_state};
 use crate::executor::{GcloudExecutor, RealExecutor};
-use crate::gcloud::GcloudError;
-use propel_core::CloudRunConfig;
+use crate::gcloud::{GcloudError, GcloudVersion, MIN_GCLOUD_VERSION};
+use crate::iam::{
+    IamPolicy, PermissionCheck, RUN_INVOKER_ROLE, RequiredPermission, ServiceAccess, SharedCiUsage,
+    WifProvider, check_deploy_permissions,
+};
+use crate::labels::{ManagedService, PropelLabels, managed_filter, parse_managed_services};
+use crate::projects::{GcpProject, parse_projects};
+use crate::revisions::{RevisionInfo, parse_revisions};
+use crate::secrets::{SecretInfo, parse_secret_info};
+use crate::warmup::{WARMUP_TAG, tagged_revision_url};
+use propel_core::{CloudBuildConfig, CloudRunConfig, DiskCache, ProbeConfig, SecretBinding};
+use serde::Serialize;
 use std::fmt;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// APIs preflight checks before `propel deploy` runs.
+///
+/// Secret Manager is only needed when secrets are used; callers decide
+/// whether a disabled [`SECRET_MANAGER_API`] blocks the deploy.
+const PREFLIGHT_APIS: &[&str] = &[
+    "cloudbuild.googleapis.com",
+    "run.googleapis.com",
+    "artifactregistry.googleapis.com",
+    SECRET_MANAGER_API,
+];
+
+/// Service name of the Secret Manager API.
+pub const SECRET_MANAGER_API: &str = "secretmanager.googleapis.com";
+
+/// Service name of the Cloud SQL Admin API, which Cloud Run's Cloud SQL
+/// connections (`[cloud_run].cloudsql_instances`) go through.
+pub const CLOUD_SQL_ADMIN_API: &str = "sqladmin.googleapis.com";
+
+/// How long the doctor reuses API checks that found every API enabled
+/// (see [`GcloudClient::doctor_cache`]).
+pub const DOCTOR_API_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// How long to wait for a freshly enabled API to propagate before giving up.
+pub const API_ENABLEMENT_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// How long to keep retrying a Cloud Run deploy that cannot read its
+/// secrets yet, while fresh secret IAM bindings propagate.
+pub const SECRET_ACCESS_PROPAGATION_TIMEOUT: Duration = Duration::from_secs(60);
 
 /// GCP operations client, parameterized over the executor for testability.
 pub struct GcloudClient<E: GcloudExecutor = RealExecutor> {
     executor: E,
+    key_file_fallback: Option<PathBuf>,
+    doctor_cache: Option<DiskCache>,
 }
 
 impl GcloudClient<RealExecutor> {
     pub fn new() -> Self {
-        Self {
-            executor: RealExecutor,
-        }
+        Self::with_executor(RealExecutor)
     }
 }
 
@@ -25,11 +66,39 @@ impl Default for GcloudClient<RealExecutor> {
 
 impl<E: GcloudExecutor> GcloudClient<E> {
     pub fn with_executor(executor: E) -> Self {
-        Self { executor }
+        Self {
+            executor,
+            key_file_fallback: None,
+            doctor_cache: None,
+        }
+    }
+
+    /// The executor running this client's gcloud commands.
+    pub fn executor(&self) -> &E {
+        &self.executor
+    }
+
+    /// When preflight finds no active gcloud account, activate the service
+    /// account key at `key_file` instead of failing.
+    ///
+    /// Activation changes the active account of the user's gcloud
+    /// configuration, so callers should only enable this on request.
+    pub fn key_file_fallback(mut self, key_file: PathBuf) -> Self {
+        self.key_file_fallback = Some(key_file);
+        self
+    }
+
+    /// Let [`Self::doctor`] reuse API checks from `cache` that found every
+    /// API enabled less than [`DOCTOR_API_CACHE_TTL`] ago, and store new
+    /// ones there.
+    pub fn doctor_cache(mut self, cache: DiskCache) -> Self {
+        self.doctor_cache = Some(cache);
+        self
     }
 
     // ── Preflight ──
 
+    #[tracing::instrument(name = "preflight", level = "debug", skip_all, fields(project = project_id))]
     pub async fn check_prerequisites(
         &self,
         project_id: &str,
@@ -46,15 +115,22 @@ impl<E: GcloudExecutor> GcloudClient<E> {
             Err(_) => return Err(PreflightError::GcloudNotInstalled),
         }
 
-        // 2. Authenticated
-        match self
-            .executor
-            .exec(&args(["auth", "print-access-token", "--quiet"]))
-            .await
-        {
-            Ok(_) => report.authenticated = true,
-            Err(_) => return Err(PreflightError::NotAuthenticated),
+        // 2. Authenticated, falling back to the configured key file
+        let token_args = args(["auth", "print-access-token", "--quiet"]);
+        if let Err(e) = self.executor.exec(&token_args).await {
+            let error = self.auth_failure(e).await;
+            let (PreflightError::NotAuthenticated, Some(key_file)) =
+                (&error, &self.key_file_fallback)
+            else {
+                return Err(error);
+            };
+            self.activate_service_account(key_file).await?;
+            if let Err(e) = self.executor.exec(&token_args).await {
+                return Err(self.auth_failure(e).await);
+            }
+            report.activated_key_file = Some(key_file.clone());
         }
+        report.authenticated = true;
 
         // 3. Project accessible
         match self
@@ -64,20 +140,329 @@ impl<E: GcloudExecutor> GcloudClient<E> {
                 "describe",
                 project_id,
                 "--format",
-                "value(name)",
+                "value(name,projectNumber)",
             ]))
             .await
         {
-            Ok(name) => report.project_name = Some(name.trim().to_owned()),
+            Ok(output) => {
+                let output = output.trim();
+                match output.split_once('\t') {
+                    Some((name, number)) => {
+                        report.project_name = Some(name.to_owned());
+                        report.project_number = Some(number.to_owned());
+                    }
+                    None => report.project_name = Some(output.to_owned()),
+                }
+            }
             Err(_) => return Err(PreflightError::ProjectNotAccessible(project_id.to_owned())),
         }
 
-        // 4. Required APIs enabled
-        for api in &[
-            "cloudbuild.googleapis.com",
-            "run.googleapis.com",
-            "secretmanager.googleapis.com",
-        ] {
+        // 4. Billing enabled — a project without billing passes every other
+        // check and then fails deep inside Cloud Build.
+        match self.billing_enabled(project_id).await {
+            Ok(true) => report.billing_enabled = Some(true),
+            Ok(false) => return Err(PreflightError::BillingDisabled(project_id.to_owned())),
+            Err(e) => {
+                // Querying billing needs extra permissions (and the Cloud Billing API)
+                // that a CI service account may lack — don't block the deploy on that.
+                tracing::warn!(error = %e, "could not verify billing status");
+                report.billing_enabled = None;
+            }
+        }
+
+        // 5. Required APIs enabled
+        report.disabled_apis = self.disabled_apis(project_id, PREFLIGHT_APIS).await?;
+
+        Ok(report)
+    }
+
+    /// The doctor's API rows, from [`Self::doctor_cache`] when it holds a
+    /// fresh result. Only a fully enabled set is cached, so an API enabled
+    /// after a failing run shows up on the next one.
+    async fn doctor_api_checks(&self, project_id: &str) -> Vec<ApiCheck> {
+        let cache_key = format!("doctor-apis-{project_id}");
+        if let Some(cache) = &self.doctor_cache {
+            match cache.get::<Vec<String>>(&cache_key) {
+                Ok(Some(enabled))
+                    if DOCTOR_APIS
+                        .iter()
+                        .all(|(_, _, api)| enabled.iter().any(|e| e == api)) =>
+                {
+                    return DOCTOR_APIS
+                        .iter()
+                        .map(|(id, label, _)| ApiCheck {
+                            id,
+                            name: label.to_string(),
+                            result: CheckResult::ok("Enabled (cached)"),
+                        })
+                        .collect();
+                }
+                Ok(_) => {}
+                // arch-lint: allow(no-error-swallowing) reason="a cache miss only costs the live checks below"
+                Err(e) => tracing::debug!(error = %e, "doctor API cache unavailable"),
+            }
+        }
+
+        let mut checks = Vec::new();
+        for (id, label, api) in DOCTOR_APIS {
+            let result = self
+                .executor
+                .exec(&args([
+                    "services",
+                    "list",
+                    "--project",
+                    project_id,
+                    "--filter",
+                    &format!("config.name={api}"),
+                    "--format",
+                    "value(config.name)",
+                ]))
+                .await;
+
+            let check = match result {
+                Ok(out) if !out.trim().is_empty() => CheckResult::ok("Enabled"),
+                Ok(_) => CheckResult::fail("Not enabled"),
+                Err(e) => CheckResult::fail(&format!("Check failed: {e}")),
+            };
+
+            checks.push(ApiCheck {
+                id,
+                name: label.to_string(),
+                result: check,
+            });
+        }
+
+        if let Some(cache) = &self.doctor_cache
+            && checks.iter().all(|check| check.result.passed)
+        {
+            let enabled: Vec<&str> = DOCTOR_APIS.iter().map(|(_, _, api)| *api).collect();
+            // arch-lint: allow(no-error-swallowing) reason="caching is an optimization; the checks already ran"
+            if let Err(e) = cache.put(&cache_key, &enabled, DOCTOR_API_CACHE_TTL) {
+                tracing::debug!(error = %e, "could not cache doctor API checks");
+            }
+        }
+        checks
+    }
+
+    /// Check that the gcloud component the `[cloud_run]` command group
+    /// needs is installed, and return the group.
+    ///
+    /// Returns [`PreflightError::ComponentMissing`] naming the flags that
+    /// need it, before any build time is spent on a deploy gcloud would
+    /// reject. Runs nothing for [`RunCommandGroup::Ga`].
+    pub async fn check_run_command_group(
+        &self,
+        config: &CloudRunConfig,
+    ) -> Result<RunCommandGroup, PreflightError> {
+        let flags = cloud_run_flags(config, &[]);
+        let group = RunCommandGroup::for_flags(&flags);
+        let Some(component) = group.component() else {
+            return Ok(group);
+        };
+        let version = self
+            .executor
+            .exec(&args(["version"]))
+            .await
+            .map_err(|_| PreflightError::GcloudNotInstalled)?;
+        if GcloudVersion::parse(&version).has_component(component) {
+            return Ok(group);
+        }
+        Err(PreflightError::ComponentMissing {
+            component,
+            flags: RunCommandGroup::beta_only_flags(&flags)
+                .into_iter()
+                .map(str::to_owned)
+                .collect(),
+        })
+    }
+
+    /// Classify a failed access-token probe: with no credentialed account
+    /// gcloud is simply logged out, otherwise the account's credentials
+    /// exist but can't mint a token (expired login, revoked or disabled key).
+    async fn auth_failure(&self, token_error: GcloudError) -> PreflightError {
+        match self.credentialed_account().await {
+            Ok(Some(account)) => PreflightError::TokenUnavailable {
+                account,
+                source: token_error,
+            },
+            Ok(None) => PreflightError::NotAuthenticated,
+            Err(e) => {
+                tracing::debug!(error = %e, "could not list credentialed accounts");
+                PreflightError::NotAuthenticated
+            }
+        }
+    }
+
+    /// The active account gcloud holds credentials for, or `None` when no
+    /// credentialed account is active.
+    ///
+    /// Unlike [`active_account`](Self::active_account), which reads the
+    /// configured account name, this only reports an account that
+    /// `gcloud auth` has actually logged in or activated.
+    pub async fn credentialed_account(&self) -> Result<Option<String>, GcloudError> {
+        let output = self
+            .executor
+            .exec(&args([
+                "auth",
+                "list",
+                "--filter=status:ACTIVE",
+                "--format",
+                "value(account)",
+            ]))
+            .await?;
+        Ok(output
+            .lines()
+            .map(str::trim)
+            .find(|account| !account.is_empty())
+            .map(str::to_owned))
+    }
+
+    /// Make the service account in `key_file` gcloud's active account.
+    pub async fn activate_service_account(&self, key_file: &Path) -> Result<(), PreflightError> {
+        let key_file_arg = format!("--key-file={}", key_file.display());
+        self.executor
+            .exec(&args([
+                "auth",
+                "activate-service-account",
+                &key_file_arg,
+                "--quiet",
+            ]))
+            .await
+            .map_err(|e| PreflightError::KeyFileActivation {
+                key_file: key_file.to_owned(),
+                source: e,
+            })?;
+        Ok(())
+    }
+
+    /// Query whether billing is enabled for the project.
+    pub async fn billing_enabled(&self, project_id: &str) -> Result<bool, GcloudError> {
+        let output = self
+            .executor
+            .exec(&args([
+                "billing",
+                "projects",
+                "describe",
+                project_id,
+                "--format",
+                "value(billingEnabled)",
+            ]))
+            .await?;
+
+        Ok(output.trim().eq_ignore_ascii_case("true"))
+    }
+
+    /// Projects the active account can see, in gcloud's order.
+    pub async fn list_projects(&self) -> Result<Vec<GcpProject>, PreflightError> {
+        let output = self
+            .executor
+            .exec(&args(["projects", "list", "--format", "json"]))
+            .await
+            .map_err(|e| PreflightError::ProjectList { source: e })?;
+        parse_projects(&output).map_err(|e| PreflightError::ProjectListParse { source: e })
+    }
+
+    /// Regions Cloud Run can deploy to from `project_id`.
+    pub async fn list_run_regions(&self, project_id: &str) -> Result<Vec<String>, PreflightError> {
+        let output = self
+            .executor
+            .exec(&args([
+                "run",
+                "regions",
+                "list",
+                "--project",
+                project_id,
+                "--format",
+                "value(locationId)",
+            ]))
+            .await
+            .map_err(|e| PreflightError::RegionList { source: e })?;
+        Ok(output
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_owned)
+            .collect())
+    }
+
+    /// The active gcloud account, or `None` when none is set.
+    pub async fn active_account(&self) -> Result<Option<String>, GcloudError> {
+        let output = self
+            .executor
+            .exec(&args(["config", "get-value", "account"]))
+            .await?;
+        let account = output.trim();
+        Ok((!account.is_empty()).then(|| account.to_owned()))
+    }
+
+    /// Check the project IAM policy for the permissions deploy needs, as
+    /// granted to `account` (see [`check_deploy_permissions`]).
+    ///
+    /// # Errors
+    ///
+    /// [`PreflightError::IamPolicyUnreadable`] when the account may not read
+    /// the policy (`resourcemanager.projects.getIamPolicy`), and
+    /// [`PreflightError::IamPolicyParse`] for unexpected output.
+    pub async fn deploy_permissions(
+        &self,
+        project_id: &str,
+        account: &str,
+    ) -> Result<PermissionCheck, PreflightError> {
+        let policy = self.project_iam_policy(project_id).await?;
+        Ok(check_deploy_permissions(&policy, account))
+    }
+
+    /// The project IAM policy (`gcloud projects get-iam-policy`).
+    ///
+    /// # Errors
+    ///
+    /// As for [`Self::deploy_permissions`].
+    pub async fn project_iam_policy(&self, project_id: &str) -> Result<IamPolicy, PreflightError> {
+        let output = self
+            .executor
+            .exec(&args([
+                "projects",
+                "get-iam-policy",
+                project_id,
+                "--format",
+                "json",
+            ]))
+            .await
+            .map_err(|e| PreflightError::IamPolicyUnreadable {
+                project_id: project_id.to_owned(),
+                source: e,
+            })?;
+        serde_json::from_str(&output).map_err(|e| PreflightError::IamPolicyParse { source: e })
+    }
+
+    /// Display name of the project (`gcloud projects describe`).
+    pub async fn project_name(&self, project_id: &str) -> Result<String, GcloudError> {
+        let output = self
+            .executor
+            .exec(&args([
+                "projects",
+                "describe",
+                project_id,
+                "--format",
+                "value(name)",
+            ]))
+            .await?;
+        Ok(output.trim().to_owned())
+    }
+
+    /// Whether `api` (e.g. [`CLOUD_SQL_ADMIN_API`]) is enabled in the project.
+    pub async fn api_enabled(&self, project_id: &str, api: &str) -> Result<bool, PreflightError> {
+        Ok(self.disabled_apis(project_id, &[api]).await?.is_empty())
+    }
+
+    /// Return the subset of `apis` that are not listed as enabled.
+    async fn disabled_apis(
+        &self,
+        project_id: &str,
+        apis: &[&str],
+    ) -> Result<Vec<String>, PreflightError> {
+        let mut disabled = Vec::new();
+        for api in apis {
             let output = self
                 .executor
                 .exec(&args([
@@ -97,33 +482,151 @@ impl<E: GcloudExecutor> GcloudClient<E> {
                 })?;
 
             if output.trim().is_empty() {
-                report.disabled_apis.push((*api).to_owned());
+                disabled.push((*api).to_owned());
             }
         }
+        Ok(disabled)
+    }
 
-        Ok(report)
+    /// Return the subset of `apis` whose enablement is still propagating.
+    ///
+    /// An API that was just enabled is reported as `ENABLED` by
+    /// `gcloud services list --available` before it shows up in the
+    /// enabled list used by preflight. Those are worth waiting for;
+    /// APIs that were never enabled are not.
+    pub async fn pending_api_enablements(
+        &self,
+        project_id: &str,
+        apis: &[String],
+    ) -> Result<Vec<String>, PreflightError> {
+        let mut pending = Vec::new();
+        for api in apis {
+            let state = self
+                .executor
+                .exec(&args([
+                    "services",
+                    "list",
+                    "--available",
+                    "--project",
+                    project_id,
+                    "--filter",
+                    &format!("config.name={api}"),
+                    "--format",
+                    "value(state)",
+                ]))
+                .await
+                .map_err(|e| PreflightError::ApiCheckFailed {
+                    api: api.clone(),
+                    source: e,
+                })?;
+
+            if state.trim().eq_ignore_ascii_case("ENABLED") {
+                pending.push(api.clone());
+            }
+        }
+        Ok(pending)
+    }
+
+    /// Poll until every API in `apis` is listed as enabled, or `timeout` elapses.
+    ///
+    /// Uses exponential backoff starting at 2 seconds and capped at 15 seconds.
+    /// `on_wait` is called before each sleep with the APIs still disabled and
+    /// the time elapsed so far, so callers can report progress.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PreflightError::ApisNotEnabled`] listing the APIs still
+    /// disabled when the timeout is reached.
+    pub async fn wait_for_api_enablement(
+        &self,
+        project_id: &str,
+        apis: &[String],
+        timeout: Duration,
+        mut on_wait: impl FnMut(&[String], Duration),
+    ) -> Result<(), PreflightError> {
+        let start = tokio::time::Instant::now();
+        let mut delay = Duration::from_secs(2);
+        let api_refs: Vec<&str> = apis.iter().map(String::as_str).collect();
+
+        loop {
+            let still_disabled = self.disabled_apis(project_id, &api_refs).await?;
+            if still_disabled.is_empty() {
+                return Ok(());
+            }
+
+            let elapsed = start.elapsed();
+            if elapsed >= timeout {
+                return Err(PreflightError::ApisNotEnabled {
+                    project_id: project_id.to_owned(),
+                    apis: still_disabled,
+                });
+            }
+
+            on_wait(&still_disabled, elapsed);
+            tokio::time::sleep(delay.min(timeout - elapsed)).await;
+            delay = (delay * 2).min(Duration::from_secs(15));
+        }
+    }
+
+    /// APIs preflight requires that are not enabled in the project.
+    pub async fn disabled_preflight_apis(
+        &self,
+        project_id: &str,
+    ) -> Result<Vec<String>, PreflightError> {
+        self.disabled_apis(project_id, PREFLIGHT_APIS).await
+    }
+
+    /// Enable `apis` in the project. gcloud waits for the operation, though
+    /// the APIs may take a little longer to be usable everywhere.
+    pub async fn enable_apis(
+        &self,
+        project_id: &str,
+        apis: &[String],
+    ) -> Result<(), PreflightError> {
+        let mut cmd = args(["services", "enable"]);
+        cmd.extend(apis.iter().cloned());
+        cmd.extend(args(["--project", project_id, "--quiet"]));
+        self.executor
+            .exec(&cmd)
+            .await
+            .map_err(|e| PreflightError::ApiEnablement {
+                project_id: project_id.to_owned(),
+                apis: apis.to_vec(),
+                source: e,
+            })?;
+        Ok(())
     }
 
     // ── Doctor ──
 
     /// Run all diagnostic checks without early return.
     /// Returns a report with pass/fail for each check item.
-    pub async fn doctor(&self, project_id: Option<&str>) -> DoctorReport {
+    ///
+    /// `required_components` lists gcloud components that features enabled
+    /// in the project's configuration depend on (e.g. `"beta"`); the
+    /// components row fails when any of them is missing.
+    pub async fn doctor(
+        &self,
+        project_id: Option<&str>,
+        required_components: &[&str],
+    ) -> DoctorReport {
         let mut report = DoctorReport::default();
 
-        // 1. gcloud CLI
+        // 1. gcloud CLI — everything below needs a working gcloud, so a
+        // failure here skips the remaining rows instead of attempting them.
         match self.executor.exec(&args(["version"])).await {
             Ok(v) => {
-                // Parse "Google Cloud SDK X.Y.Z" from first line
-                let version = v
-                    .lines()
-                    .next()
-                    .and_then(|line| line.strip_prefix("Google Cloud SDK "))
-                    // arch-lint: allow(no-silent-result-drop) reason="Option: doctor() is diagnostic — raw output fallback is more informative than failing on format change"
-                    .unwrap_or(v.trim());
-                report.gcloud = CheckResult::ok(version.trim());
+                let parsed = GcloudVersion::parse(&v);
+                report.gcloud = gcloud_version_check(&parsed, &v);
+                report.components = components_check(&parsed, required_components);
+            }
+            Err(e) => {
+                report.gcloud = CheckResult::fail(&gcloud_unavailable_detail(&e));
+                report.components = CheckResult::skipped("gcloud unavailable");
+                report.account = CheckResult::skipped("gcloud unavailable");
+                report.skip_project_checks("gcloud unavailable");
+                return report;
             }
-            Err(e) => report.gcloud = CheckResult::fail(&e.to_string()),
         }
 
         // 2. Active account
@@ -138,6 +641,7 @@ impl<E: GcloudExecutor> GcloudClient<E> {
 
         // 3. Project
         let Some(pid) = project_id else {
+            report.skip_project_checks("gcp_project_id not set");
             report.project = CheckResult::fail("gcp_project_id not set in propel.toml");
             return report;
         };
@@ -157,63 +661,45 @@ impl<E: GcloudExecutor> GcloudClient<E> {
                 report.project = CheckResult::ok(&format!("{pid} ({name})", name = name.trim()))
             }
             Err(_) => {
+                report.skip_project_checks("project not accessible");
                 report.project = CheckResult::fail(&format!("{pid} — not accessible"));
                 return report;
             }
         }
 
         // 4. Billing
+        match self.billing_enabled(pid).await {
+            Ok(true) => report.billing = CheckResult::ok("Enabled"),
+            _ => report.billing = CheckResult::fail("Billing not enabled"),
+        }
+
+        // 5. Required APIs
+        report.apis = self.doctor_api_checks(pid).await;
+
+        // 6. Cloud Build org policy (informational — reading policies may be denied)
         match self
             .executor
             .exec(&args([
-                "billing",
-                "projects",
+                "resource-manager",
+                "org-policies",
                 "describe",
+                "constraints/cloudbuild.allowedWorkerPools",
+                "--project",
                 pid,
+                "--effective",
                 "--format",
-                "value(billingEnabled)",
+                "json",
             ]))
             .await
         {
-            Ok(v) if v.trim().eq_ignore_ascii_case("true") => {
-                report.billing = CheckResult::ok("Enabled");
-            }
-            _ => report.billing = CheckResult::fail("Billing not enabled"),
-        }
-
-        // 5. Required APIs
-        let required_apis = [
-            ("Cloud Build", "cloudbuild.googleapis.com"),
-            ("Cloud Run", "run.googleapis.com"),
-            ("Secret Manager", "secretmanager.googleapis.com"),
-            ("Artifact Registry", "artifactregistry.googleapis.com"),
-        ];
-
-        for (label, api) in &required_apis {
-            let result = self
-                .executor
-                .exec(&args([
-                    "services",
-                    "list",
-                    "--project",
-                    pid,
-                    "--filter",
-                    &format!("config.name={api}"),
-                    "--format",
-                    "value(config.name)",
-                ]))
-                .await;
-
-            let check = match result {
-                Ok(out) if !out.trim().is_empty() => CheckResult::ok("Enabled"),
-                Ok(_) => CheckResult::fail("Not enabled"),
-                Err(e) => CheckResult::fail(&format!("Check failed: {e}")),
-            };
-
-            report.apis.push(ApiCheck {
-                name: label.to_string(),
-                result: check,
-            });
+            Ok(out) if worker_pool_policy_restricted(&out) => report.notes.push(
+                "org policy constraints/cloudbuild.allowedWorkerPools is set — \
+                 builds may need [cloud_build].region (and logs_bucket) in propel.toml"
+                    .to_owned(),
+            ),
+            Ok(_) => {}
+            // arch-lint: allow(no-error-swallowing) reason="policy read needs orgpolicy.policies.get; absence of the note is the fallback"
+            Err(e) => tracing::debug!(error = %e, "could not read Cloud Build org policy"),
         }
 
         report
@@ -221,11 +707,12 @@ impl<E: GcloudExecutor> GcloudClient<E> {
 
     // ── Artifact Registry ──
 
-    /// Ensure the Artifact Registry Docker repository exists, creating it if needed.
+    /// Ensure the Artifact Registry Docker repository exists in `location`
+    /// (a region or multi-region such as `us`), creating it if needed.
     pub async fn ensure_artifact_repo(
         &self,
         project_id: &str,
-        region: &str,
+        location: &str,
         repo_name: &str,
     ) -> Result<(), DeployError> {
         let exists = self
@@ -238,7 +725,7 @@ impl<E: GcloudExecutor> GcloudClient<E> {
                 "--project",
                 project_id,
                 "--location",
-                region,
+                location,
             ]))
             .await
             .is_ok();
@@ -253,7 +740,7 @@ impl<E: GcloudExecutor> GcloudClient<E> {
                     "--project",
                     project_id,
                     "--location",
-                    region,
+                    location,
                     "--repository-format",
                     "docker",
                     "--quiet",
@@ -276,135 +763,668 @@ impl<E: GcloudExecutor> GcloudClient<E> {
                 image_tag,
                 "--project",
                 project_id,
-                "--delete-tags",
+                "--delete-tags",
+                "--quiet",
+            ]))
+            .await
+            .map_err(|e| DeployError::Deploy { source: e })?;
+
+        Ok(())
+    }
+
+    /// Point `alias_tag` (e.g. `IMAGE:latest`) at the image `image_tag`
+    /// names, moving it off the image it marked before.
+    pub async fn add_image_tag(&self, image_tag: &str, alias_tag: &str) -> Result<(), DeployError> {
+        self.executor
+            .exec(&args([
+                "artifacts",
+                "docker",
+                "tags",
+                "add",
+                image_tag,
+                alias_tag,
+                "--quiet",
+            ]))
+            .await
+            .map_err(|e| DeployError::ImageTag { source: e })?;
+
+        Ok(())
+    }
+
+    /// Size in bytes of `image_tag` in Artifact Registry, or `None` when
+    /// the registry lists no size for it.
+    pub async fn image_size(&self, image_tag: &str) -> Result<Option<u64>, DeployError> {
+        let (image, tag) = crate::image::split_image_tag(image_tag);
+        let output = self
+            .executor
+            .exec(&args([
+                "artifacts",
+                "docker",
+                "images",
+                "list",
+                image,
+                "--include-tags",
+                "--filter",
+                &format!("tags:{tag}"),
+                "--format",
+                "json",
+            ]))
+            .await
+            .map_err(|e| DeployError::ImageSize { source: e })?;
+
+        crate::image::parse_image_size(&output)
+            .map_err(|e| DeployError::ImageSizeParse { source: e })
+    }
+
+    /// Image size the last deploy of `service_name` recorded in its
+    /// [`IMAGE_SIZE_LABEL`](crate::labels::IMAGE_SIZE_LABEL) label, or
+    /// `None` when it recorded none.
+    pub async fn deployed_image_size(
+        &self,
+        service_name: &str,
+        project_id: &str,
+        region: &str,
+    ) -> Result<Option<u64>, DeployError> {
+        let output = self
+            .executor
+            .exec(&args([
+                "run",
+                "services",
+                "describe",
+                service_name,
+                "--project",
+                project_id,
+                "--region",
+                region,
+                "--format",
+                &format!("value(metadata.labels.{})", crate::labels::IMAGE_SIZE_LABEL),
+            ]))
+            .await
+            .map_err(|e| DeployError::Deploy { source: e })?;
+
+        // arch-lint: allow(no-silent-result-drop) reason="a malformed label is reported as unknown"
+        Ok(output.trim().parse().ok())
+    }
+
+    // ── Cloud Build ──
+
+    /// Submit a Cloud Build, handling its output as `output` says.
+    ///
+    /// With `build_config`, the build follows that `cloudbuild.yaml`
+    /// (which names the image itself) instead of `--tag image_tag`.
+    /// `settings` adds `--region` / `--gcs-log-dir` from `[cloud_build]`.
+    /// The command is the same for every [`BuildOutput`].
+    ///
+    /// Returns the output under [`BuildOutput::Capture`], else an empty
+    /// string. Org-policy failures are classified from gcloud's stderr,
+    /// which `Capture` and `Tee` keep; `Stream` leaves it on the terminal.
+    #[tracing::instrument(
+        name = "cloud_build",
+        level = "debug",
+        skip_all,
+        fields(project = project_id, image_tag = image_tag)
+    )]
+    pub async fn submit_build(
+        &self,
+        bundle_dir: &Path,
+        project_id: &str,
+        image_tag: &str,
+        settings: &CloudBuildConfig,
+        build_config: Option<&Path>,
+        output: &BuildOutput,
+    ) -> Result<String, CloudBuildError> {
+        let submit_args =
+            build_submit_args(bundle_dir, project_id, image_tag, settings, build_config)?;
+        match output {
+            BuildOutput::Stream => self
+                .executor
+                .exec_streaming(&submit_args)
+                .await
+                .map(|()| String::new()),
+            BuildOutput::Tee(build_log) => self
+                .executor
+                .exec_tee(&submit_args, build_log)
+                .await
+                .map(|()| String::new()),
+            BuildOutput::Capture(build_log) => {
+                let result = self.executor.exec(&submit_args).await;
+                let log = match &result {
+                    Ok(output) => output.as_str(),
+                    Err(GcloudError::CommandFailed { stderr, .. }) => stderr.as_str(),
+                    Err(_) => "",
+                };
+                // arch-lint: allow(no-error-swallowing) reason="the build log is a convenience copy; the build result is what callers need"
+                if let Err(e) = write_log_file(build_log, log) {
+                    tracing::warn!(error = %e, path = %build_log.display(), "could not write build log");
+                }
+                result
+            }
+        }
+        .map_err(submit_error)
+    }
+
+    // ── Cloud Run Deploy ──
+
+    #[allow(
+        clippy::too_many_arguments,
+        reason = "mirrors cloud_run_deploy_args, which callers also use directly"
+    )]
+    #[tracing::instrument(
+        name = "cloud_run_deploy",
+        level = "debug",
+        skip_all,
+        fields(
+            project = project_id,
+            service = service_name,
+            region = region,
+            image_tag = image_tag
+        )
+    )]
+    pub async fn deploy_to_cloud_run(
+        &self,
+        service_name: &str,
+        image_tag: &str,
+        project_id: &str,
+        region: &str,
+        config: &CloudRunConfig,
+        secrets: &[SecretBinding],
+        labels: &PropelLabels,
+    ) -> Result<String, DeployError> {
+        let cmd = cloud_run_deploy_args(
+            service_name,
+            image_tag,
+            project_id,
+            region,
+            config,
+            secrets,
+            labels,
+        );
+
+        let output = self
+            .executor
+            .exec(&cmd)
+            .await
+            .map_err(|e| DeployError::Deploy { source: e })?;
+
+        Ok(output.trim().to_owned())
+    }
+
+    /// Deploy `image_tag` as a new revision that receives no traffic,
+    /// tagged [`WARMUP_TAG`], and return the revision's tag URL.
+    ///
+    /// The service must already exist: Cloud Run rejects `--no-traffic`
+    /// when creating one.
+    #[allow(
+        clippy::too_many_arguments,
+        reason = "mirrors cloud_run_deploy_args, which callers also use directly"
+    )]
+    #[tracing::instrument(
+        name = "cloud_run_deploy",
+        level = "debug",
+        skip_all,
+        fields(
+            project = project_id,
+            service = service_name,
+            region = region,
+            image_tag = image_tag,
+            no_traffic = true
+        )
+    )]
+    pub async fn deploy_revision_without_traffic(
+        &self,
+        service_name: &str,
+        image_tag: &str,
+        project_id: &str,
+        region: &str,
+        config: &CloudRunConfig,
+        secrets: &[SecretBinding],
+        labels: &PropelLabels,
+    ) -> Result<String, DeployError> {
+        let cmd = cloud_run_deploy_args(
+            service_name,
+            image_tag,
+            project_id,
+            region,
+            config,
+            secrets,
+            labels,
+        );
+        self.deploy_tagged_revision(cmd, service_name, project_id, region, WARMUP_TAG)
+            .await
+    }
+
+    /// Deploy `image_tag` as a new revision that receives no traffic yet,
+    /// tagged [`CANARY_TAG`], and return the revision's tag URL. Give it its
+    /// share with [`Self::split_canary_traffic`].
+    ///
+    /// The service must already exist: Cloud Run rejects `--no-traffic`
+    /// when creating one.
+    #[allow(
+        clippy::too_many_arguments,
+        reason = "mirrors cloud_run_deploy_args, which callers also use directly"
+    )]
+    #[tracing::instrument(
+        name = "cloud_run_deploy",
+        level = "debug",
+        skip_all,
+        fields(
+            project = project_id,
+            service = service_name,
+            region = region,
+            image_tag = image_tag,
+            canary = true
+        )
+    )]
+    pub async fn deploy_canary_revision(
+        &self,
+        service_name: &str,
+        image_tag: &str,
+        project_id: &str,
+        region: &str,
+        config: &CloudRunConfig,
+        secrets: &[SecretBinding],
+        labels: &PropelLabels,
+    ) -> Result<String, DeployError> {
+        let cmd = cloud_run_deploy_args(
+            service_name,
+            image_tag,
+            project_id,
+            region,
+            config,
+            secrets,
+            labels,
+        );
+        self.deploy_tagged_revision(cmd, service_name, project_id, region, CANARY_TAG)
+            .await
+    }
+
+    /// Run the deploy command `cmd` with `--no-traffic --tag tag`, then
+    /// look up the tag URL.
+    async fn deploy_tagged_revision(
+        &self,
+        mut cmd: Vec<String>,
+        service_name: &str,
+        project_id: &str,
+        region: &str,
+        tag: &'static str,
+    ) -> Result<String, DeployError> {
+        cmd.extend(args(["--no-traffic", "--tag", tag]));
+
+        self.executor
+            .exec(&cmd)
+            .await
+            .map_err(|e| DeployError::Deploy { source: e })?;
+
+        let json = self
+            .describe_service_json(service_name, project_id, region)
+            .await?;
+        tagged_revision_url(&json, tag)
+            .map_err(|e| DeployError::DescribeParse { source: e })?
+            .ok_or(DeployError::TagNotFound { tag })
+    }
+
+    /// `gcloud run services describe --format json` output.
+    async fn describe_service_json(
+        &self,
+        service_name: &str,
+        project_id: &str,
+        region: &str,
+    ) -> Result<String, DeployError> {
+        self.executor
+            .exec(&args([
+                "run",
+                "services",
+                "describe",
+                service_name,
+                "--project",
+                project_id,
+                "--region",
+                region,
+                "--format",
+                "json",
+            ]))
+            .await
+            .map_err(|e| DeployError::Deploy { source: e })
+    }
+
+    /// The canary in progress on the service, if any (see
+    /// [`parse_canary_state`]).
+    pub async fn canary_state(
+        &self,
+        service_name: &str,
+        project_id: &str,
+        region: &str,
+    ) -> Result<Option<CanaryState>, DeployError> {
+        let json = self
+            .describe_service_json(service_name, project_id, region)
+            .await?;
+        parse_canary_state(&json).map_err(|e| DeployError::DescribeParse { source: e })
+    }
+
+    /// Send `percent` of the traffic to the revision tagged [`CANARY_TAG`].
+    /// The other revisions keep the rest.
+    pub async fn split_canary_traffic(
+        &self,
+        service_name: &str,
+        project_id: &str,
+        region: &str,
+        percent: u32,
+    ) -> Result<(), DeployError> {
+        self.executor
+            .exec(&args([
+                "run",
+                "services",
+                "update-traffic",
+                service_name,
+                "--project",
+                project_id,
+                "--region",
+                region,
+                "--to-tags",
+                &format!("{CANARY_TAG}={percent}"),
+                "--quiet",
+            ]))
+            .await
+            .map_err(|e| DeployError::Deploy { source: e })?;
+        Ok(())
+    }
+
+    /// Send all traffic to the latest revision, which must be the canary,
+    /// and drop [`CANARY_TAG`]. Returns the service URL.
+    pub async fn promote_canary(
+        &self,
+        service_name: &str,
+        project_id: &str,
+        region: &str,
+    ) -> Result<String, DeployError> {
+        self.executor
+            .exec(&args([
+                "run",
+                "services",
+                "update-traffic",
+                service_name,
+                "--project",
+                project_id,
+                "--region",
+                region,
+                "--to-latest",
+                "--remove-tags",
+                CANARY_TAG,
+                "--quiet",
+            ]))
+            .await
+            .map_err(|e| DeployError::Deploy { source: e })?;
+        self.service_url(service_name, project_id, region).await
+    }
+
+    /// Send all traffic back to `stable_revision` and drop [`CANARY_TAG`].
+    /// Returns the service URL.
+    ///
+    /// Like [`Self::route_all_traffic`], this pins traffic to the revision.
+    pub async fn abort_canary(
+        &self,
+        service_name: &str,
+        project_id: &str,
+        region: &str,
+        stable_revision: &str,
+    ) -> Result<String, DeployError> {
+        self.executor
+            .exec(&args([
+                "run",
+                "services",
+                "update-traffic",
+                service_name,
+                "--project",
+                project_id,
+                "--region",
+                region,
+                "--to-revisions",
+                &format!("{stable_revision}=100"),
+                "--remove-tags",
+                CANARY_TAG,
+                "--quiet",
+            ]))
+            .await
+            .map_err(|e| DeployError::Deploy { source: e })?;
+        self.service_url(service_name, project_id, region).await
+    }
+
+    /// Send all traffic to the latest revision and drop [`WARMUP_TAG`].
+    /// Returns the service URL.
+    pub async fn promote_latest_revision(
+        &self,
+        service_name: &str,
+        project_id: &str,
+        region: &str,
+    ) -> Result<String, DeployError> {
+        self.executor
+            .exec(&args([
+                "run",
+                "services",
+                "update-traffic",
+                service_name,
+                "--project",
+                project_id,
+                "--region",
+                region,
+                "--to-latest",
+                "--remove-tags",
+                WARMUP_TAG,
                 "--quiet",
             ]))
             .await
             .map_err(|e| DeployError::Deploy { source: e })?;
-
-        Ok(())
+        self.service_url(service_name, project_id, region).await
     }
 
-    // ── Cloud Build ──
-
-    /// Submit a Cloud Build with streaming output to stdout (CLI use).
-    pub async fn submit_build(
+    /// Revisions of a service, newest first, with the traffic each serves.
+    pub async fn list_revisions(
         &self,
-        bundle_dir: &Path,
+        service_name: &str,
         project_id: &str,
-        image_tag: &str,
-    ) -> Result<(), CloudBuildError> {
-        let bundle_str = bundle_dir
-            .to_str()
-            .ok_or_else(|| CloudBuildError::InvalidPath(bundle_dir.to_path_buf()))?;
-
-        self.executor
-            .exec_streaming(&args([
-                "builds",
-                "submit",
-                bundle_str,
+        region: &str,
+    ) -> Result<Vec<RevisionInfo>, DeployError> {
+        let revisions = self
+            .executor
+            .exec(&args([
+                "run",
+                "revisions",
+                "list",
+                "--service",
+                service_name,
                 "--project",
                 project_id,
-                "--tag",
-                image_tag,
-                "--quiet",
+                "--region",
+                region,
+                "--format",
+                "json",
+            ]))
+            .await
+            .map_err(|e| DeployError::Deploy { source: e })?;
+        let service = self
+            .executor
+            .exec(&args([
+                "run",
+                "services",
+                "describe",
+                service_name,
+                "--project",
+                project_id,
+                "--region",
+                region,
+                "--format",
+                "json",
             ]))
             .await
-            .map_err(|e| CloudBuildError::Submit { source: e })
+            .map_err(|e| DeployError::Deploy { source: e })?;
+        parse_revisions(&revisions, &service).map_err(|e| DeployError::RevisionsParse { source: e })
     }
 
-    /// Submit a Cloud Build with captured output (MCP / non-TTY use).
-    pub async fn submit_build_captured(
+    /// Send all traffic to `revision`, e.g. to roll back. Returns the
+    /// service URL.
+    ///
+    /// Traffic stays pinned to the revision: later revisions get none until
+    /// traffic is routed to them (see [`Self::promote_latest_revision`]).
+    pub async fn route_all_traffic(
         &self,
-        bundle_dir: &Path,
+        service_name: &str,
         project_id: &str,
-        image_tag: &str,
-    ) -> Result<String, CloudBuildError> {
-        let bundle_str = bundle_dir
-            .to_str()
-            .ok_or_else(|| CloudBuildError::InvalidPath(bundle_dir.to_path_buf()))?;
-
+        region: &str,
+        revision: &str,
+    ) -> Result<String, DeployError> {
         self.executor
             .exec(&args([
-                "builds",
-                "submit",
-                bundle_str,
+                "run",
+                "services",
+                "update-traffic",
+                service_name,
                 "--project",
                 project_id,
-                "--tag",
-                image_tag,
+                "--region",
+                region,
+                "--to-revisions",
+                &format!("{revision}=100"),
                 "--quiet",
             ]))
             .await
-            .map_err(|e| CloudBuildError::Submit { source: e })
+            .map_err(|e| DeployError::Deploy { source: e })?;
+        self.service_url(service_name, project_id, region).await
     }
 
-    // ── Cloud Run Deploy ──
-
-    pub async fn deploy_to_cloud_run(
+    /// Apply `[cloud_run]` settings, env vars, and secrets to a deployed
+    /// service without building a new image. Returns the service URL.
+    #[tracing::instrument(
+        name = "cloud_run_update",
+        level = "debug",
+        skip_all,
+        fields(project = project_id, service = service_name, region = region)
+    )]
+    pub async fn update_cloud_run_service(
         &self,
         service_name: &str,
-        image_tag: &str,
         project_id: &str,
         region: &str,
         config: &CloudRunConfig,
-        secrets: &[String],
+        secrets: &[SecretBinding],
+        labels: &PropelLabels,
     ) -> Result<String, DeployError> {
-        let cpu = config.cpu.to_string();
-        let min = config.min_instances.to_string();
-        let max = config.max_instances.to_string();
-        let concurrency = config.concurrency.to_string();
-        let port = config.port.to_string();
+        let cmd = cloud_run_update_args(service_name, project_id, region, config, secrets, labels);
 
-        // Build --update-secrets value: ENV_VAR=SECRET_NAME:latest,...
-        let secrets_flag = secrets
-            .iter()
-            .map(|s| format!("{s}={s}:latest"))
-            .collect::<Vec<_>>()
-            .join(",");
+        let output = self
+            .executor
+            .exec(&cmd)
+            .await
+            .map_err(|e| DeployError::Deploy { source: e })?;
+
+        Ok(output.trim().to_owned())
+    }
+
+    /// Fetch the URL of a deployed Cloud Run service.
+    pub async fn service_url(
+        &self,
+        service_name: &str,
+        project_id: &str,
+        region: &str,
+    ) -> Result<String, DeployError> {
+        let output = self
+            .executor
+            .exec(&args([
+                "run",
+                "services",
+                "describe",
+                service_name,
+                "--project",
+                project_id,
+                "--region",
+                region,
+                "--format",
+                "value(status.url)",
+            ]))
+            .await
+            .map_err(|e| DeployError::Deploy { source: e })?;
+
+        Ok(output.trim().to_owned())
+    }
 
-        let mut cmd = vec![
-            "run",
-            "deploy",
+    /// Ingress and invokers of a deployed Cloud Run service.
+    pub async fn service_access(
+        &self,
+        service_name: &str,
+        project_id: &str,
+        region: &str,
+    ) -> Result<ServiceAccess, DeployError> {
+        let location = args([
             service_name,
-            "--image",
-            image_tag,
             "--project",
             project_id,
             "--region",
             region,
-            "--platform",
-            "managed",
-            "--memory",
-            &config.memory,
-            "--cpu",
-            &cpu,
-            "--min-instances",
-            &min,
-            "--max-instances",
-            &max,
-            "--concurrency",
-            &concurrency,
-            "--port",
-            &port,
-            "--allow-unauthenticated",
-            "--quiet",
             "--format",
-            "value(status.url)",
-        ];
+            "json",
+        ]);
+        let run_services = |command: &str| {
+            let mut cmd = args(["run", "services", command]);
+            cmd.extend(location.iter().cloned());
+            cmd
+        };
+        let service = self
+            .executor
+            .exec(&run_services("describe"))
+            .await
+            .map_err(|e| DeployError::Deploy { source: e })?;
+        let policy = self
+            .executor
+            .exec(&run_services("get-iam-policy"))
+            .await
+            .map_err(|e| DeployError::Deploy { source: e })?;
 
-        if !secrets_flag.is_empty() {
-            cmd.push("--update-secrets");
-            cmd.push(&secrets_flag);
-        }
+        ServiceAccess::parse(&service, &policy)
+            .map_err(|e| DeployError::ServiceAccessParse { source: e })
+    }
+
+    /// Let `member` (e.g. `serviceAccount:caller@proj.iam.gserviceaccount.com`)
+    /// call a Cloud Run service, such as one deployed with
+    /// `allow_unauthenticated = false`.
+    pub async fn grant_invoker(
+        &self,
+        service_name: &str,
+        project_id: &str,
+        region: &str,
+        member: &str,
+    ) -> Result<(), DeployError> {
+        self.executor
+            .exec(&args([
+                "run",
+                "services",
+                "add-iam-policy-binding",
+                service_name,
+                "--project",
+                project_id,
+                "--region",
+                region,
+                "--member",
+                member,
+                "--role",
+                RUN_INVOKER_ROLE,
+                "--quiet",
+            ]))
+            .await
+            .map_err(|e| DeployError::GrantInvoker {
+                member: member.to_owned(),
+                source: e,
+            })?;
 
-        let cmd_owned: Vec<String> = cmd.iter().map(|s| (*s).to_owned()).collect();
+        Ok(())
+    }
 
+    /// Identity token of the active gcloud account, for requests to a
+    /// private Cloud Run service.
+    pub async fn identity_token(&self) -> Result<String, DeployError> {
         let output = self
             .executor
-            .exec(&cmd_owned)
+            .exec(&args(["auth", "print-identity-token"]))
             .await
-            .map_err(|e| DeployError::Deploy { source: e })?;
+            .map_err(|e| DeployError::IdentityToken { source: e })?;
 
         Ok(output.trim().to_owned())
     }
@@ -456,6 +1476,102 @@ impl<E: GcloudExecutor> GcloudClient<E> {
         Ok(())
     }
 
+    /// Whether the Cloud Run service exists in `region`.
+    pub async fn service_exists(
+        &self,
+        service_name: &str,
+        project_id: &str,
+        region: &str,
+    ) -> Result<bool, DeployError> {
+        let described = self
+            .executor
+            .exec(&args([
+                "run",
+                "services",
+                "describe",
+                service_name,
+                "--project",
+                project_id,
+                "--region",
+                region,
+                "--format",
+                "value(metadata.name)",
+            ]))
+            .await;
+        match described {
+            Ok(_) => Ok(true),
+            Err(e) if is_not_found(&e) || is_service_missing(&e) => Ok(false),
+            Err(e) => Err(DeployError::Deploy { source: e }),
+        }
+    }
+
+    /// Whether Artifact Registry holds any version of `image_path` (an
+    /// image without a tag). A missing repository counts as no image.
+    pub async fn image_exists(&self, image_path: &str) -> Result<bool, DeployError> {
+        let listed = self
+            .executor
+            .exec(&args([
+                "artifacts",
+                "docker",
+                "images",
+                "list",
+                image_path,
+                "--limit",
+                "1",
+                "--format",
+                "value(version)",
+            ]))
+            .await;
+        match listed {
+            Ok(output) => Ok(!output.trim().is_empty()),
+            Err(e) if is_not_found(&e) => Ok(false),
+            Err(e) => Err(DeployError::Deploy { source: e }),
+        }
+    }
+
+    /// Cloud Run services in the project deployed by propel, in every region.
+    pub async fn list_managed_services(
+        &self,
+        project_id: &str,
+    ) -> Result<Vec<ManagedService>, DeployError> {
+        self.list_services(project_id, &managed_filter()).await
+    }
+
+    /// Services named `service_name` deployed by propel, in every region —
+    /// e.g. one left behind after `[project].region` changed.
+    pub async fn list_services_all_regions(
+        &self,
+        project_id: &str,
+        service_name: &str,
+    ) -> Result<Vec<ManagedService>, DeployError> {
+        let filter = format!("metadata.name={service_name} AND {}", managed_filter());
+        self.list_services(project_id, &filter).await
+    }
+
+    /// `run services list` without `--region` lists every region.
+    async fn list_services(
+        &self,
+        project_id: &str,
+        filter: &str,
+    ) -> Result<Vec<ManagedService>, DeployError> {
+        let output = self
+            .executor
+            .exec(&args([
+                "run",
+                "services",
+                "list",
+                "--project",
+                project_id,
+                "--filter",
+                filter,
+                "--format",
+                "json",
+            ]))
+            .await
+            .map_err(|e| DeployError::List { source: e })?;
+        parse_managed_services(&output).map_err(|e| DeployError::ListParse { source: e })
+    }
+
     /// Read Cloud Run logs with streaming output to stdout (CLI use).
     pub async fn read_logs(
         &self,
@@ -534,12 +1650,14 @@ impl<E: GcloudExecutor> GcloudClient<E> {
 
     // ── Secret Manager ──
 
+    /// Store `secret_value` as a new version of `secret_name`, creating the
+    /// secret first if needed. Returns the new version number.
     pub async fn set_secret(
         &self,
         project_id: &str,
         secret_name: &str,
         secret_value: &str,
-    ) -> Result<(), SecretError> {
+    ) -> Result<String, SecretError> {
         let secret_exists = self
             .executor
             .exec(&args([
@@ -562,12 +1680,14 @@ impl<E: GcloudExecutor> GcloudClient<E> {
                     project_id,
                     "--replication-policy",
                     "automatic",
+                    "--quiet",
                 ]))
                 .await
                 .map_err(|e| SecretError::Create { source: e })?;
         }
 
-        self.executor
+        let output = self
+            .executor
             .exec_with_stdin(
                 &args([
                     "secrets",
@@ -578,13 +1698,18 @@ impl<E: GcloudExecutor> GcloudClient<E> {
                     project_id,
                     "--data-file",
                     "-",
+                    "--format",
+                    "value(name)",
+                    "--quiet",
                 ]),
                 secret_value.as_bytes(),
             )
             .await
             .map_err(|e| SecretError::AddVersion { source: e })?;
 
-        Ok(())
+        // projects/<number>/secrets/<name>/versions/<version>
+        let name = output.trim();
+        Ok(name.rsplit_once('/').map_or(name, |(_, v)| v).to_owned())
     }
 
     pub async fn get_project_number(&self, project_id: &str) -> Result<String, DeployError> {
@@ -603,6 +1728,35 @@ impl<E: GcloudExecutor> GcloudClient<E> {
         Ok(output.trim().to_owned())
     }
 
+    /// Whether the service account `email` exists in `project_id`.
+    pub async fn service_account_exists(
+        &self,
+        project_id: &str,
+        email: &str,
+    ) -> Result<bool, PreflightError> {
+        match self
+            .executor
+            .exec(&args([
+                "iam",
+                "service-accounts",
+                "describe",
+                email,
+                "--project",
+                project_id,
+                "--format",
+                "value(email)",
+            ]))
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) if is_not_found(&e) => Ok(false),
+            Err(e) => Err(PreflightError::ServiceAccountLookup {
+                email: email.to_owned(),
+                source: e,
+            }),
+        }
+    }
+
     pub async fn grant_secret_access(
         &self,
         project_id: &str,
@@ -621,6 +1775,7 @@ impl<E: GcloudExecutor> GcloudClient<E> {
                 &member,
                 "--role",
                 "roles/secretmanager.secretAccessor",
+                "--quiet",
             ]))
             .await
             .map_err(|e| SecretError::GrantAccess { source: e })?;
@@ -638,19 +1793,90 @@ impl<E: GcloudExecutor> GcloudClient<E> {
         self.executor
             .exec(&args([
                 "secrets",
-                "remove-iam-policy-binding",
+                "remove-iam-policy-binding",
+                secret_name,
+                "--project",
+                project_id,
+                "--member",
+                &member,
+                "--role",
+                "roles/secretmanager.secretAccessor",
+                "--quiet",
+            ]))
+            .await
+            .map_err(|e| SecretError::RevokeAccess { source: e })?;
+
+        Ok(())
+    }
+
+    /// Read the latest version of a secret.
+    ///
+    /// The payload is returned verbatim (no trailing newline is trimmed).
+    pub async fn access_secret(
+        &self,
+        project_id: &str,
+        secret_name: &str,
+    ) -> Result<String, SecretError> {
+        self.executor
+            .exec(&args([
+                "secrets",
+                "versions",
+                "access",
+                "latest",
+                "--secret",
+                secret_name,
+                "--project",
+                project_id,
+            ]))
+            .await
+            .map_err(|e| SecretError::Access {
+                name: secret_name.to_owned(),
+                source: e,
+            })
+    }
+
+    /// Creation time and versions of the secret `secret_name`; never its
+    /// value.
+    pub async fn describe_secret(
+        &self,
+        project_id: &str,
+        secret_name: &str,
+    ) -> Result<SecretInfo, SecretError> {
+        let describe_err = |e| SecretError::Describe {
+            name: secret_name.to_owned(),
+            source: e,
+        };
+        let secret = self
+            .executor
+            .exec(&args([
+                "secrets",
+                "describe",
+                secret_name,
+                "--project",
+                project_id,
+                "--format",
+                "json",
+            ]))
+            .await
+            .map_err(describe_err)?;
+        let versions = self
+            .executor
+            .exec(&args([
+                "secrets",
+                "versions",
+                "list",
                 secret_name,
                 "--project",
                 project_id,
-                "--member",
-                &member,
-                "--role",
-                "roles/secretmanager.secretAccessor",
+                "--format",
+                "json",
             ]))
             .await
-            .map_err(|e| SecretError::RevokeAccess { source: e })?;
-
-        Ok(())
+            .map_err(describe_err)?;
+        parse_secret_info(secret_name, &secret, &versions).map_err(|e| SecretError::DescribeParse {
+            name: secret_name.to_owned(),
+            source: e,
+        })
     }
 
     pub async fn list_secrets(&self, project_id: &str) -> Result<Vec<String>, SecretError> {
@@ -708,6 +1934,7 @@ impl<E: GcloudExecutor> GcloudClient<E> {
                 "global",
                 "--display-name",
                 "Propel GitHub Actions",
+                "--quiet",
             ]))
             .await
         {
@@ -748,6 +1975,7 @@ impl<E: GcloudExecutor> GcloudClient<E> {
             &attribute_condition,
             "--issuer-uri",
             "https://token.actions.githubusercontent.com",
+            "--quiet",
         ]
         .iter()
         .map(|s| (*s).to_owned())
@@ -779,6 +2007,7 @@ impl<E: GcloudExecutor> GcloudClient<E> {
                 project_id,
                 "--display-name",
                 display_name,
+                "--quiet",
             ]))
             .await
         {
@@ -818,6 +2047,38 @@ impl<E: GcloudExecutor> GcloudClient<E> {
         Ok(())
     }
 
+    /// Allow `member_sa` to act as `target_sa` (`roles/iam.serviceAccountUser`
+    /// on that one account, not project-wide).
+    pub async fn grant_service_account_user(
+        &self,
+        project_id: &str,
+        target_sa: &str,
+        member_sa: &str,
+    ) -> Result<(), WifError> {
+        let member = format!("serviceAccount:{member_sa}");
+        self.executor
+            .exec(&args([
+                "iam",
+                "service-accounts",
+                "add-iam-policy-binding",
+                target_sa,
+                "--project",
+                project_id,
+                "--role",
+                "roles/iam.serviceAccountUser",
+                "--member",
+                &member,
+                "--quiet",
+            ]))
+            .await
+            .map_err(|e| WifError::BindRole {
+                role: "roles/iam.serviceAccountUser".to_owned(),
+                source: e,
+            })?;
+
+        Ok(())
+    }
+
     /// Bind a WIF pool to a service account, scoped to a GitHub repository.
     pub async fn bind_wif_to_sa(
         &self,
@@ -843,6 +2104,7 @@ impl<E: GcloudExecutor> GcloudClient<E> {
                 "roles/iam.workloadIdentityUser",
                 "--member",
                 &member,
+                "--quiet",
             ]))
             .await
             .map_err(|e| WifError::BindWif { source: e })?;
@@ -850,6 +2112,93 @@ impl<E: GcloudExecutor> GcloudClient<E> {
         Ok(())
     }
 
+    /// Fetch the IAM policy attached to a service account.
+    ///
+    /// A service account that no longer exists has an empty policy.
+    pub async fn get_sa_iam_policy(
+        &self,
+        project_id: &str,
+        sa_email: &str,
+    ) -> Result<IamPolicy, WifError> {
+        let output = match self
+            .executor
+            .exec(&args([
+                "iam",
+                "service-accounts",
+                "get-iam-policy",
+                sa_email,
+                "--project",
+                project_id,
+                "--format",
+                "json",
+            ]))
+            .await
+        {
+            Ok(output) => output,
+            Err(ref e) if is_not_found(e) => return Ok(IamPolicy::default()),
+            Err(e) => return Err(WifError::GetIamPolicy { source: e }),
+        };
+
+        serde_json::from_str(&output).map_err(|e| WifError::ParseOutput { source: e })
+    }
+
+    /// List provider ids in a Workload Identity Pool.
+    ///
+    /// A pool that no longer exists has no providers.
+    pub async fn list_wif_providers(
+        &self,
+        project_id: &str,
+        pool_id: &str,
+    ) -> Result<Vec<String>, WifError> {
+        let output = match self
+            .executor
+            .exec(&args([
+                "iam",
+                "workload-identity-pools",
+                "providers",
+                "list",
+                "--workload-identity-pool",
+                pool_id,
+                "--project",
+                project_id,
+                "--location",
+                "global",
+                "--format",
+                "json",
+            ]))
+            .await
+        {
+            Ok(output) => output,
+            Err(ref e) if is_not_found(e) => return Ok(vec![]),
+            Err(e) => return Err(WifError::ListProviders { source: e }),
+        };
+
+        let providers: Vec<WifProvider> =
+            serde_json::from_str(&output).map_err(|e| WifError::ParseOutput { source: e })?;
+        Ok(providers.iter().map(|p| p.id().to_owned()).collect())
+    }
+
+    /// Find principals and providers besides `github_repo`'s that depend on
+    /// the CI pool and service account.
+    pub async fn shared_ci_usage(
+        &self,
+        project_id: &str,
+        pool_id: &str,
+        provider_id: &str,
+        sa_email: &str,
+        github_repo: Option<&str>,
+    ) -> Result<SharedCiUsage, WifError> {
+        let policy = self.get_sa_iam_policy(project_id, sa_email).await?;
+        let providers = self.list_wif_providers(project_id, pool_id).await?;
+        Ok(crate::iam::shared_ci_usage(
+            &policy,
+            &providers,
+            pool_id,
+            provider_id,
+            github_repo,
+        ))
+    }
+
     /// Delete a Workload Identity Pool (and its providers).
     pub async fn delete_wif_pool(&self, project_id: &str, pool_id: &str) -> Result<(), WifError> {
         self.executor
@@ -895,11 +2244,530 @@ impl<E: GcloudExecutor> GcloudClient<E> {
 
 // ── Helper ──
 
+/// Components worth mentioning in the doctor report even when not required.
+const NOTABLE_COMPONENTS: &[(&str, &str)] = &[
+    ("beta", "needed for beta-only Cloud Run flags"),
+    ("log-streaming", "needed for `propel logs --follow`"),
+];
+
+/// Build the gcloud CLI row: SDK version checked against [`MIN_GCLOUD_VERSION`].
+/// APIs the doctor checks, as `(check id, label, service name)`.
+const DOCTOR_APIS: &[(&str, &str, &str)] = &[
+    ("api.cloudbuild", "Cloud Build", "cloudbuild.googleapis.com"),
+    ("api.run", "Cloud Run", "run.googleapis.com"),
+    (
+        "api.secretmanager",
+        "Secret Manager",
+        "secretmanager.googleapis.com",
+    ),
+    (
+        "api.artifactregistry",
+        "Artifact Registry",
+        "artifactregistry.googleapis.com",
+    ),
+];
+
+/// Detail for a failed `gcloud version`, keeping gcloud's own stderr —
+/// a half-upgraded SDK explains itself there (e.g. a Python mismatch).
+fn gcloud_unavailable_detail(error: &GcloudError) -> String {
+    match error {
+        GcloudError::CommandFailed { stderr, .. } if !stderr.trim().is_empty() => {
+            format!("installed but failing:\n{}", stderr.trim())
+        }
+        GcloudError::CommandFailed { .. } => "installed but failing (no output)".to_owned(),
+        other => other.to_string(),
+    }
+}
+
+fn gcloud_version_check(parsed: &GcloudVersion, raw: &str) -> CheckResult {
+    let (major, minor, patch) = MIN_GCLOUD_VERSION;
+    match (parsed.sdk.as_deref(), parsed.meets_minimum()) {
+        (Some(v), Some(false)) => CheckResult::fail(&format!(
+            "{v} — propel requires >= {major}.{minor}.{patch}; run: gcloud components update"
+        )),
+        (Some(v), _) => CheckResult::ok(v),
+        // Unknown format: raw output is more informative than failing on a format change
+        (None, _) => CheckResult::ok(raw.trim()),
+    }
+}
+
+/// Build the components row from parsed `gcloud version` output.
+///
+/// Fails when any of `required` is missing, with the install command as
+/// remediation. Missing notable-but-optional components are noted in the
+/// detail without failing the check.
+pub fn components_check(parsed: &GcloudVersion, required: &[&str]) -> CheckResult {
+    let missing: Vec<&str> = required
+        .iter()
+        .copied()
+        .filter(|c| !parsed.has_component(c))
+        .collect();
+    if !missing.is_empty() {
+        return CheckResult::fail(&format!(
+            "missing {} — run: gcloud components install {}",
+            missing.join(", "),
+            missing.join(" ")
+        ));
+    }
+
+    let installed: Vec<&str> = NOTABLE_COMPONENTS
+        .iter()
+        .map(|(name, _)| *name)
+        .filter(|name| parsed.has_component(name))
+        .collect();
+    let mut detail = if installed.is_empty() {
+        "core".to_owned()
+    } else {
+        format!("core, {}", installed.join(", "))
+    };
+    for (name, why) in NOTABLE_COMPONENTS {
+        if !parsed.has_component(name) && !required.contains(name) {
+            detail.push_str(&format!(" ({name} not installed, {why})"));
+        }
+    }
+    CheckResult::ok(&detail)
+}
+
+/// Arguments for `gcloud run deploy`, shared by the CLI deploy and the
+/// in-build deploy step of [`CiConfig::deploy_via_cloud_build`](propel_core::CiConfig).
+pub fn cloud_run_deploy_args(
+    service_name: &str,
+    image_tag: &str,
+    project_id: &str,
+    region: &str,
+    config: &CloudRunConfig,
+    secrets: &[SecretBinding],
+    labels: &PropelLabels,
+) -> Vec<String> {
+    let flags = cloud_run_flags(config, secrets);
+    let mut cmd = RunCommandGroup::for_flags(&flags).args(["deploy"]);
+    cmd.extend(args([
+        service_name,
+        "--image",
+        image_tag,
+        "--project",
+        project_id,
+        "--region",
+        region,
+        "--platform",
+        "managed",
+    ]));
+    // Deploy only: `run services update` leaves access as it is.
+    cmd.push(if config.allow_unauthenticated {
+        "--allow-unauthenticated".to_owned()
+    } else {
+        "--no-allow-unauthenticated".to_owned()
+    });
+    cmd.extend(flags);
+    cmd.extend(labels_and_output_flags(labels));
+    cmd
+}
+
+/// Arguments for `gcloud run services update`: the flags of
+/// [`cloud_run_deploy_args`] against the image already deployed.
+pub fn cloud_run_update_args(
+    service_name: &str,
+    project_id: &str,
+    region: &str,
+    config: &CloudRunConfig,
+    secrets: &[SecretBinding],
+    labels: &PropelLabels,
+) -> Vec<String> {
+    let flags = cloud_run_flags(config, secrets);
+    let mut cmd = RunCommandGroup::for_flags(&flags).args(["services", "update"]);
+    cmd.extend(args([
+        service_name,
+        "--project",
+        project_id,
+        "--region",
+        region,
+        "--platform",
+        "managed",
+    ]));
+    cmd.extend(flags);
+    cmd.extend(labels_and_output_flags(labels));
+    cmd
+}
+
+/// Service settings from `[cloud_run]`, plus env vars and `secrets`, as
+/// flags both `run deploy` and `run services update` take.
+pub fn cloud_run_flags(config: &CloudRunConfig, secrets: &[SecretBinding]) -> Vec<String> {
+    let cpu = config.cpu.to_string();
+    let min = config.min_instances.to_string();
+    let max = config.max_instances.to_string();
+    let concurrency = config.concurrency.to_string();
+    let port = config.port.to_string();
+
+    let mut flags = args([
+        "--memory",
+        &config.memory,
+        "--cpu",
+        &cpu,
+        "--min-instances",
+        &min,
+        "--max-instances",
+        &max,
+        "--concurrency",
+        &concurrency,
+        "--port",
+        &port,
+    ]);
+    // Always explicit, so turning the setting off takes effect too.
+    flags.push(if config.cpu_always_allocated {
+        "--no-cpu-throttling".to_owned()
+    } else {
+        "--cpu-throttling".to_owned()
+    });
+    if let Some(seconds) = config.timeout_seconds {
+        flags.extend(args(["--timeout", &seconds.to_string()]));
+    }
+    if let Some(email) = &config.service_account {
+        flags.extend(args(["--service-account", email]));
+    }
+    if let Some(connector) = &config.vpc_connector {
+        flags.extend(args(["--vpc-connector", connector]));
+    }
+    if let Some(egress) = config.vpc_egress {
+        flags.extend(args(["--vpc-egress", egress.as_str()]));
+    }
+    if !config.cloudsql_instances.is_empty() {
+        flags.extend(args([
+            "--add-cloudsql-instances",
+            &config.cloudsql_instances.join(","),
+        ]));
+    }
+
+    if let Some(probe) = config.effective_startup_probe() {
+        flags.push(format!(
+            "--startup-probe={}",
+            probe_spec(&probe, config.port)
+        ));
+    }
+    if let Some(probe) = &config.liveness_probe {
+        flags.push(format!(
+            "--liveness-probe={}",
+            probe_spec(probe, config.port)
+        ));
+    }
+
+    // --set-env-vars, not --update-env-vars: a key dropped from
+    // [cloud_run.env] is removed from the service too. Secrets are a
+    // separate flag and are not cleared by it.
+    if !config.env.is_empty() {
+        flags.extend(args(["--set-env-vars", &env_vars_flag(&config.env)]));
+    }
+
+    // --update-secrets value: ENV_VAR=SECRET_NAME:VERSION,...
+    if !secrets.is_empty() {
+        let secrets_flag = secrets
+            .iter()
+            .map(|s| {
+                let version = match &s.version {
+                    Some(version) => version,
+                    None => config.secret_version(&s.env),
+                };
+                format!("{}={}:{version}", s.env, s.secret)
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        flags.extend(args(["--update-secrets", &secrets_flag]));
+    }
+    flags.extend(config.extra_flags.iter().cloned());
+    flags
+}
+
+/// Where [`GcloudClient::submit_build`] sends the build's output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildOutput {
+    /// Echo it to the terminal.
+    Stream,
+    /// Return it instead of printing it (MCP, where stdout carries the
+    /// protocol), and write it — stderr on failure — to the log file.
+    /// Failing to write the log is logged, not returned, so it never
+    /// masks the build result.
+    Capture(PathBuf),
+    /// Echo it to the terminal and copy it into the log file as it comes.
+    Tee(PathBuf),
+}
+
+/// `gcloud` command group a Cloud Run command runs under: `run`, or
+/// `beta run` when a flag needs it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunCommandGroup {
+    /// `gcloud run`
+    Ga,
+    /// `gcloud beta run`, from the `beta` component
+    Beta,
+}
+
+/// Cloud Run flags, by name, that only a pre-GA command group accepts.
+/// Any flag not listed runs under [`RunCommandGroup::Ga`]; drop an entry
+/// once gcloud promotes the flag.
+const RUN_FLAG_GROUPS: &[(&str, RunCommandGroup)] = &[
+    ("--gpu", RunCommandGroup::Beta),
+    ("--gpu-type", RunCommandGroup::Beta),
+    ("--gpu-zonal-redundancy", RunCommandGroup::Beta),
+    ("--no-gpu-zonal-redundancy", RunCommandGroup::Beta),
+    ("--mesh", RunCommandGroup::Beta),
+    ("--service-min-instances", RunCommandGroup::Beta),
+];
+
+impl RunCommandGroup {
+    /// The group every one of `flags` is accepted by — the only place
+    /// that decides between `run` and `beta run`.
+    pub fn for_flags(flags: &[String]) -> Self {
+        if Self::beta_only_flags(flags).is_empty() {
+            Self::Ga
+        } else {
+            Self::Beta
+        }
+    }
+
+    /// Group the `[cloud_run]` settings deploy and update with.
+    pub fn for_config(config: &CloudRunConfig) -> Self {
+        Self::for_flags(&cloud_run_flags(config, &[]))
+    }
+
+    /// Names of the flags in `flags` that need [`RunCommandGroup::Beta`],
+    /// in order, for error messages.
+    pub fn beta_only_flags(flags: &[String]) -> Vec<&str> {
+        flags
+            .iter()
+            .filter_map(|flag| flag.split('=').next())
+            .filter(|name| {
+                RUN_FLAG_GROUPS
+                    .iter()
+                    .any(|(flag, group)| flag == name && *group == Self::Beta)
+            })
+            .collect()
+    }
+
+    /// gcloud component that provides the group, if not part of core.
+    pub fn component(self) -> Option<&'static str> {
+        match self {
+            Self::Ga => None,
+            Self::Beta => Some("beta"),
+        }
+    }
+
+    /// `run <command...>`, prefixed with the group's component.
+    fn args<const N: usize>(self, command: [&str; N]) -> Vec<String> {
+        let mut cmd: Vec<String> = self.component().into_iter().map(str::to_owned).collect();
+        cmd.push("run".to_owned());
+        cmd.extend(args(command));
+        cmd
+    }
+}
+
+/// Trailing flags of deploy and update: propel's labels, no prompts, and
+/// the service URL as the only output.
+fn labels_and_output_flags(labels: &PropelLabels) -> Vec<String> {
+    // --update-labels, not --labels: keep labels the user set on the service.
+    args([
+        "--update-labels",
+        &labels.flag_value(),
+        "--quiet",
+        "--format",
+        "value(status.url)",
+    ])
+}
+
+/// Render env vars as the `--set-env-vars` value, sorted by key.
+///
+/// Values containing commas use gcloud's alternate delimiter syntax
+/// (`^DELIM^K=V DELIM K=V`), with a run of `@` that appears in no entry.
+fn env_vars_flag(env: &std::collections::HashMap<String, String>) -> String {
+    let mut pairs: Vec<String> = env.iter().map(|(k, v)| format!("{k}={v}")).collect();
+    pairs.sort();
+    if !pairs.iter().any(|p| p.contains(',')) {
+        return pairs.join(",");
+    }
+    let mut delim = String::from("@");
+    while pairs.iter().any(|p| p.contains(&delim)) {
+        delim.push('@');
+    }
+    format!("^{delim}^{}", pairs.join(&delim))
+}
+
+/// Render a probe as the `KEY=VALUE,...` spec `--startup-probe` and
+/// `--liveness-probe` take: HTTP GET when a path is set, TCP otherwise.
+fn probe_spec(probe: &ProbeConfig, port: u16) -> String {
+    let target = match &probe.path {
+        Some(path) => format!("httpGet.path={path},httpGet.port={port}"),
+        None => format!("tcpSocket.port={port}"),
+    };
+    format!(
+        "{target},initialDelaySeconds={},periodSeconds={},failureThreshold={},timeoutSeconds={}",
+        probe.initial_delay, probe.period, probe.failure_threshold, probe.timeout
+    )
+}
+
+fn build_submit_args(
+    bundle_dir: &Path,
+    project_id: &str,
+    image_tag: &str,
+    settings: &CloudBuildConfig,
+    build_config: Option<&Path>,
+) -> Result<Vec<String>, CloudBuildError> {
+    let bundle_str = bundle_dir
+        .to_str()
+        .ok_or_else(|| CloudBuildError::InvalidPath(bundle_dir.to_path_buf()))?;
+
+    let mut submit_args = args(["builds", "submit", bundle_str, "--project", project_id]);
+    match build_config {
+        Some(config) => {
+            let config_str = config
+                .to_str()
+                .ok_or_else(|| CloudBuildError::InvalidPath(config.to_path_buf()))?;
+            submit_args.extend(args(["--config", config_str]));
+        }
+        None => submit_args.extend(args(["--tag", image_tag])),
+    }
+    if let Some(region) = &settings.region {
+        submit_args.extend(args(["--region", region]));
+    }
+    if let Some(bucket) = &settings.logs_bucket {
+        submit_args.extend(args(["--gcs-log-dir", bucket]));
+    }
+    submit_args.push("--quiet".to_owned());
+    Ok(submit_args)
+}
+
+/// Org-policy restrictions that make `gcloud builds submit` fail with
+/// the default pool and logs bucket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloudBuildPolicyIssue {
+    /// The default logs bucket cannot be written or streamed from.
+    LogsBucket,
+    /// `constraints/cloudbuild.allowedWorkerPools` rejects the default pool.
+    WorkerPool,
+}
+
+/// Recognize the well-known org-policy failures in `builds submit` stderr.
+pub fn classify_cloud_build_error(stderr: &str) -> Option<CloudBuildPolicyIssue> {
+    let lower = stderr.to_lowercase();
+    if lower.contains("allowedworkerpools")
+        || (lower.contains("worker pool") && lower.contains("policy"))
+    {
+        Some(CloudBuildPolicyIssue::WorkerPool)
+    } else if lower.contains("logs bucket") || lower.contains("logs_bucket") {
+        Some(CloudBuildPolicyIssue::LogsBucket)
+    } else {
+        None
+    }
+}
+
+/// Whether a failed `gcloud run deploy` was rejected because the runtime
+/// service account cannot read a secret — right after `propel secret set`,
+/// usually because the new IAM binding has not propagated yet.
+pub fn secret_access_denied(stderr: &str) -> bool {
+    stderr.contains("Permission denied on secret")
+}
+
+/// Run `deploy` (typically [`GcloudClient::deploy_to_cloud_run`]), retrying
+/// while it fails with [`secret_access_denied`] until `timeout` elapses.
+///
+/// Uses exponential backoff starting at 5 seconds and capped at 20 seconds.
+/// `on_wait` is called before each sleep with the upcoming delay and the
+/// time elapsed so far. Other failures are returned immediately.
+///
+/// # Errors
+///
+/// Returns [`DeployError::SecretAccessDenied`] if the secret is still
+/// unreadable at the timeout, or the first non-secret deploy error.
+pub async fn retry_secret_propagation<F, Fut>(
+    timeout: Duration,
+    mut on_wait: impl FnMut(Duration, Duration),
+    mut deploy: F,
+) -> Result<String, DeployError>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<String, DeployError>>,
+{
+    let start = tokio::time::Instant::now();
+    let mut delay = Duration::from_secs(5);
+
+    loop {
+        let source = match deploy().await {
+            Err(DeployError::Deploy { source })
+                if matches!(&source, GcloudError::CommandFailed { stderr, .. }
+                    if secret_access_denied(stderr)) =>
+            {
+                source
+            }
+            other => return other,
+        };
+
+        let elapsed = start.elapsed();
+        if elapsed >= timeout {
+            return Err(DeployError::SecretAccessDenied {
+                waited: elapsed,
+                source,
+            });
+        }
+
+        let wait = delay.min(timeout - elapsed);
+        on_wait(wait, elapsed);
+        tokio::time::sleep(wait).await;
+        delay = (delay * 2).min(Duration::from_secs(20));
+    }
+}
+
+/// Whether `org-policies describe --effective` output for
+/// `constraints/cloudbuild.allowedWorkerPools` restricts the default pool.
+pub fn worker_pool_policy_restricted(describe_output: &str) -> bool {
+    describe_output.contains("allowedValues") || describe_output.contains("DENY")
+}
+
+fn submit_error(e: GcloudError) -> CloudBuildError {
+    let issue = match &e {
+        GcloudError::CommandFailed { stderr, .. } => classify_cloud_build_error(stderr),
+        _ => None,
+    };
+    match issue {
+        Some(CloudBuildPolicyIssue::LogsBucket) => {
+            CloudBuildError::LogsBucketRestricted { source: e }
+        }
+        Some(CloudBuildPolicyIssue::WorkerPool) => {
+            CloudBuildError::WorkerPoolRestricted { source: e }
+        }
+        None => CloudBuildError::Submit { source: e },
+    }
+}
+
+/// Write `content` to `path`, creating parent directories.
+fn write_log_file(path: &Path, content: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, content)
+}
+
 fn args<const N: usize>(a: [&str; N]) -> Vec<String> {
     a.iter().map(|s| (*s).to_owned()).collect()
 }
 
 /// Check whether a gcloud error indicates the resource already exists.
+fn is_not_found(e: &GcloudError) -> bool {
+    match e {
+        GcloudError::CommandFailed { stderr, .. } => {
+            stderr.contains("NOT_FOUND") || stderr.contains("not found")
+        }
+        _ => false,
+    }
+}
+
+/// `run services describe|delete` phrase a missing service as
+/// "Cannot find service [name]".
+fn is_service_missing(e: &GcloudError) -> bool {
+    match e {
+        GcloudError::CommandFailed { stderr, .. } => stderr.contains("Cannot find service"),
+        _ => false,
+    }
+}
+
 fn is_already_exists(e: &GcloudError) -> bool {
     match e {
         GcloudError::CommandFailed { stderr, .. } => {
@@ -916,7 +2784,13 @@ pub struct PreflightReport {
     pub gcloud_version: Option<String>,
     pub authenticated: bool,
     pub project_name: Option<String>,
+    /// The numeric project id, e.g. for the deploy history.
+    pub project_number: Option<String>,
+    /// `None` when the billing status could not be queried.
+    pub billing_enabled: Option<bool>,
     pub disabled_apis: Vec<String>,
+    /// Key file activated because no gcloud account was active.
+    pub activated_key_file: Option<PathBuf>,
 }
 
 impl PreflightReport {
@@ -930,14 +2804,129 @@ pub enum PreflightError {
     #[error("gcloud CLI not installed — https://cloud.google.com/sdk/docs/install")]
     GcloudNotInstalled,
 
-    #[error("not authenticated — run: gcloud auth login")]
+    #[error(
+        "not authenticated with gcloud. Either:\n  \
+         1. log in interactively: gcloud auth login\n  \
+         2. or, where a browser login is impossible (containers, non-GitHub CI), \
+         activate a service account key:\n       \
+         gcloud auth activate-service-account --key-file=/path/to/key.json\n     \
+         To do this automatically, point GOOGLE_APPLICATION_CREDENTIALS at the key \
+         and set [auth] activate_key_file = true in propel.toml"
+    )]
     NotAuthenticated,
 
+    #[error(
+        "gcloud account '{account}' is active but could not get an access token — its \
+         credentials may be expired, revoked, or disabled. Refresh them:\n  \
+         user account:    gcloud auth login {account}\n  \
+         service account: gcloud auth activate-service-account {account} \
+         --key-file=/path/to/key.json"
+    )]
+    TokenUnavailable {
+        account: String,
+        source: GcloudError,
+    },
+
+    #[error("failed to activate service account key {}", key_file.display())]
+    KeyFileActivation {
+        key_file: PathBuf,
+        source: GcloudError,
+    },
+
     #[error("GCP project '{0}' is not accessible — check project ID and permissions")]
     ProjectNotAccessible(String),
 
+    #[error(
+        "[cloud_run] sets {} — only `gcloud {component} run` accepts {}, and the \
+         gcloud '{component}' component is not installed.\n\
+         Install it with: gcloud components install {component}",
+        flags.join(", "),
+        if flags.len() == 1 { "it" } else { "them" }
+    )]
+    ComponentMissing {
+        component: &'static str,
+        flags: Vec<String>,
+    },
+
+    #[error(
+        "billing is not enabled for GCP project '{0}' — link a billing account: \
+         https://console.cloud.google.com/billing/linkedaccount?project={0}"
+    )]
+    BillingDisabled(String),
+
+    #[error(
+        "could not read the IAM policy of GCP project '{project_id}' to verify deploy \
+         permissions — re-run with --skip-iam-check to deploy without the check"
+    )]
+    IamPolicyUnreadable {
+        project_id: String,
+        source: GcloudError,
+    },
+
+    #[error("failed to parse project IAM policy")]
+    IamPolicyParse { source: serde_json::Error },
+
+    #[error("failed to list GCP projects")]
+    ProjectList { source: GcloudError },
+
+    #[error("unexpected output from `gcloud projects list`")]
+    ProjectListParse { source: serde_json::Error },
+
+    #[error("failed to list Cloud Run regions")]
+    RegionList { source: GcloudError },
+
+    #[error("failed to look up service account {email}")]
+    ServiceAccountLookup { email: String, source: GcloudError },
+
+    #[error(
+        "{account} lacks permissions deploy needs in GCP project '{project_id}':\n{}\n\
+         Grant the roles (e.g. gcloud projects add-iam-policy-binding {project_id} \
+         --member={} --role=ROLE), or re-run with --skip-iam-check \
+         if they are granted some other way.",
+        missing.iter().map(|p| format!("  - {p}")).collect::<Vec<_>>().join("\n"),
+        crate::iam::account_member(account)
+    )]
+    MissingPermissions {
+        account: String,
+        project_id: String,
+        missing: Vec<RequiredPermission>,
+    },
+
     #[error("failed to check API status for {api}")]
     ApiCheckFailed { api: String, source: GcloudError },
+
+    #[error(
+        "required APIs not enabled: {}\n\
+         Enable them with: gcloud services enable {} --project {project_id}\n\
+         If you just enabled them, propagation can take a few minutes — retry shortly.",
+        apis.join(", "),
+        apis.join(" ")
+    )]
+    ApisNotEnabled {
+        project_id: String,
+        apis: Vec<String>,
+    },
+
+    #[error(
+        "failed to enable {} in project '{project_id}' — enabling APIs needs \
+         serviceusage.services.enable (roles/serviceusage.serviceUsageAdmin)",
+        apis.join(", ")
+    )]
+    ApiEnablement {
+        project_id: String,
+        apis: Vec<String>,
+        source: GcloudError,
+    },
+
+    #[error(
+        "dependencies need credentials that Cloud Build does not have: {}\n\
+         Store git credentials (`https://<user>:<token>@<host>`) in Secret Manager with\n\
+         `propel secret set GIT_CREDENTIALS=...`, then set in propel.toml:\n\
+         [build]\n\
+         git_credentials_secret = \"GIT_CREDENTIALS\"",
+        dependencies.join(", ")
+    )]
+    MissingGitCredentials { dependencies: Vec<String> },
 }
 
 // ── Doctor types ──
@@ -945,58 +2934,147 @@ pub enum PreflightError {
 #[derive(Debug, Default)]
 pub struct DoctorReport {
     pub gcloud: CheckResult,
+    pub components: CheckResult,
     pub account: CheckResult,
     pub project: CheckResult,
     pub billing: CheckResult,
     pub apis: Vec<ApiCheck>,
+    /// Deploy permissions of the active account, filled in by the caller;
+    /// `None` when the check did not run.
+    pub iam: Option<CheckResult>,
+    /// Whether `[cloud_run].service_account` exists, filled in by the
+    /// caller; `None` when none is configured.
+    pub runtime_service_account: Option<CheckResult>,
+    /// Whether the Cloud SQL Admin API is enabled, filled in by the caller;
+    /// `None` when `[cloud_run].cloudsql_instances` is empty.
+    pub cloud_sql_api: Option<CheckResult>,
+    /// Local checks, filled in by the caller; they run even when gcloud
+    /// is unavailable.
     pub config_file: CheckResult,
+    pub toolchain: CheckResult,
+    pub git: CheckResult,
+    /// Informational findings that do not fail the report.
+    pub notes: Vec<String>,
 }
 
 impl DoctorReport {
     pub fn all_passed(&self) -> bool {
         self.gcloud.passed
+            && self.components.passed
             && self.account.passed
             && self.project.passed
             && self.billing.passed
             && self.config_file.passed
+            && self.toolchain.passed
+            && self.git.passed
             && self.apis.iter().all(|a| a.result.passed)
+            && self.iam.as_ref().is_none_or(|r| r.passed || r.skipped)
+            && self
+                .runtime_service_account
+                .as_ref()
+                .is_none_or(|r| r.passed || r.skipped)
+            && self
+                .cloud_sql_api
+                .as_ref()
+                .is_none_or(|r| r.passed || r.skipped)
+    }
+
+    /// Every row in display order, each with its stable id.
+    ///
+    /// Ids are what scripts and the MCP server match on, so they never
+    /// change once shipped; labels are for people and may be reworded. A
+    /// new row needs a new id here (see the registry in `propel doctor`'s
+    /// module docs).
+    pub fn checks(&self) -> Vec<DoctorCheck<'_>> {
+        let mut checks = vec![
+            DoctorCheck::new("gcloud.installed", "gcloud CLI", &self.gcloud),
+            DoctorCheck::new("gcloud.components", "gcloud components", &self.components),
+            DoctorCheck::new("gcp.account", "Authentication", &self.account),
+            DoctorCheck::new("gcp.project", "GCP Project", &self.project),
+            DoctorCheck::new("gcp.billing", "Billing", &self.billing),
+        ];
+        checks.extend(self.apis.iter().map(|api| DoctorCheck {
+            id: api.id,
+            label: format!("{} API", api.name),
+            result: &api.result,
+        }));
+        if let Some(cloud_sql) = &self.cloud_sql_api {
+            checks.push(DoctorCheck::new(
+                "api.sqladmin",
+                "Cloud SQL Admin API",
+                cloud_sql,
+            ));
+        }
+        if let Some(iam) = &self.iam {
+            checks.push(DoctorCheck::new("iam.permissions", "IAM permissions", iam));
+        }
+        if let Some(sa) = &self.runtime_service_account {
+            checks.push(DoctorCheck::new(
+                "iam.runtime_service_account",
+                "Service account",
+                sa,
+            ));
+        }
+        checks.extend([
+            DoctorCheck::new("config.file", "propel.toml", &self.config_file),
+            DoctorCheck::new("rust.toolchain", "Rust toolchain", &self.toolchain),
+            DoctorCheck::new("git.installed", "git", &self.git),
+        ]);
+        checks
+    }
+
+    /// The report as JSON: `all_passed`, the [`Self::checks`] rows, and
+    /// the notes. Shared by `propel doctor --json` and the MCP server.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "all_passed": self.all_passed(),
+            "checks": self.checks(),
+            "notes": self.notes,
+        })
+    }
+
+    /// Mark the project, billing, and API rows as skipped.
+    fn skip_project_checks(&mut self, reason: &str) {
+        self.project = CheckResult::skipped(reason);
+        self.billing = CheckResult::skipped(reason);
+        self.apis = DOCTOR_APIS
+            .iter()
+            .map(|(id, label, _)| ApiCheck {
+                id,
+                name: (*label).to_owned(),
+                result: CheckResult::skipped(reason),
+            })
+            .collect();
     }
 }
 
+/// Write one `label  icon  detail` row; continuation lines of a multi-line
+/// detail (such as captured gcloud stderr) are indented under the detail.
+fn write_doctor_row(f: &mut fmt::Formatter<'_>, label: &str, result: &CheckResult) -> fmt::Result {
+    let (first, rest) = match result.detail.split_once('\n') {
+        Some((first, rest)) => (first, rest),
+        None => (result.detail.as_str(), ""),
+    };
+    writeln!(f, "{:<22}{:<4}{}", label, result.icon(), first)?;
+    for line in rest.lines() {
+        writeln!(f, "{:<26}{}", "", line)?;
+    }
+    Ok(())
+}
+
 impl fmt::Display for DoctorReport {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "Propel Doctor")?;
         writeln!(f, "------------------------------")?;
 
-        let rows: [(&str, &CheckResult); 4] = [
-            ("gcloud CLI", &self.gcloud),
-            ("Authentication", &self.account),
-            ("GCP Project", &self.project),
-            ("Billing", &self.billing),
-        ];
-
-        for (label, result) in &rows {
-            writeln!(f, "{:<22}{:<4}{}", label, result.icon(), result.detail)?;
+        for check in self.checks() {
+            write_doctor_row(f, &check.label, check.result)?;
         }
 
-        for api in &self.apis {
-            writeln!(
-                f,
-                "{:<22}{:<4}{}",
-                format!("{} API", api.name),
-                api.result.icon(),
-                api.result.detail,
-            )?;
+        for note in &self.notes {
+            writeln!(f, "Note: {note}")?;
         }
 
-        writeln!(
-            f,
-            "{:<22}{:<4}{}",
-            "propel.toml",
-            self.config_file.icon(),
-            self.config_file.detail,
-        )?;
-
         writeln!(f, "------------------------------")?;
         if self.all_passed() {
             write!(f, "All checks passed!")?;
@@ -1008,9 +3086,31 @@ impl fmt::Display for DoctorReport {
     }
 }
 
-#[derive(Debug, Default, Clone)]
+/// One doctor row: its stable id, display label, and result.
+#[derive(Debug, Clone, Serialize)]
+pub struct DoctorCheck<'a> {
+    /// Stable identifier such as `gcp.billing`.
+    pub id: &'static str,
+    pub label: String,
+    #[serde(flatten)]
+    pub result: &'a CheckResult,
+}
+
+impl<'a> DoctorCheck<'a> {
+    fn new(id: &'static str, label: &str, result: &'a CheckResult) -> Self {
+        Self {
+            id,
+            label: label.to_owned(),
+            result,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
 pub struct CheckResult {
     pub passed: bool,
+    /// Not attempted because an earlier check it depends on failed.
+    pub skipped: bool,
     pub detail: String,
 }
 
@@ -1018,6 +3118,7 @@ impl CheckResult {
     pub fn ok(detail: &str) -> Self {
         Self {
             passed: true,
+            skipped: false,
             detail: detail.to_owned(),
         }
     }
@@ -1025,17 +3126,33 @@ impl CheckResult {
     pub fn fail(detail: &str) -> Self {
         Self {
             passed: false,
+            skipped: false,
             detail: detail.to_owned(),
         }
     }
 
+    /// A check that was not attempted; `reason` names the failed prerequisite.
+    pub fn skipped(reason: &str) -> Self {
+        Self {
+            passed: false,
+            skipped: true,
+            detail: format!("skipped ({reason})"),
+        }
+    }
+
     pub fn icon(&self) -> &'static str {
-        if self.passed { "OK" } else { "NG" }
+        match (self.passed, self.skipped) {
+            (true, _) => "OK",
+            (false, true) => "--",
+            (false, false) => "NG",
+        }
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct ApiCheck {
+    /// Stable identifier such as `api.run`.
+    pub id: &'static str,
     pub name: String,
     pub result: CheckResult,
 }
@@ -1047,6 +3164,22 @@ pub enum CloudBuildError {
 
     #[error("cloud build submission failed")]
     Submit { source: GcloudError },
+
+    #[error(
+        "Cloud Build cannot use the default logs bucket (blocked by org policy or permissions)\n\
+         Point it at a bucket you control in propel.toml:\n\
+         [cloud_build]\n\
+         logs_bucket = \"gs://<bucket>/logs\""
+    )]
+    LogsBucketRestricted { source: GcloudError },
+
+    #[error(
+        "org policy constraints/cloudbuild.allowedWorkerPools rejects the default Cloud Build pool\n\
+         Build in a region your organization allows, in propel.toml:\n\
+         [cloud_build]\n\
+         region = \"<region>\""
+    )]
+    WorkerPoolRestricted { source: GcloudError },
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -1054,8 +3187,66 @@ pub enum DeployError {
     #[error("cloud run deployment failed")]
     Deploy { source: GcloudError },
 
+    #[error(
+        "Cloud Run still cannot read the service's secrets after {}s\n\
+         Secret access granted by `propel secret set` can take a minute to propagate; \
+         retry the deploy shortly. If it keeps failing, check that the runtime service \
+         account has roles/secretmanager.secretAccessor on each secret.",
+        waited.as_secs()
+    )]
+    SecretAccessDenied {
+        waited: Duration,
+        source: GcloudError,
+    },
+
     #[error("failed to read logs")]
     Logs { source: GcloudError },
+
+    #[error("failed to list Cloud Run services")]
+    List { source: GcloudError },
+
+    #[error("unexpected output from `gcloud run services list`")]
+    ListParse { source: serde_json::Error },
+
+    #[error("unexpected output from `gcloud run services describe`")]
+    DescribeParse { source: serde_json::Error },
+
+    #[error("failed to look up the image size in Artifact Registry")]
+    ImageSize { source: GcloudError },
+
+    #[error("unexpected output from `gcloud artifacts docker images list`")]
+    ImageSizeParse { source: serde_json::Error },
+
+    #[error("unexpected output from `gcloud run revisions list`")]
+    RevisionsParse { source: serde_json::Error },
+
+    #[error("failed to tag the image in Artifact Registry")]
+    ImageTag { source: GcloudError },
+
+    #[error("unexpected output from `gcloud run services describe` or `get-iam-policy`")]
+    ServiceAccessParse { source: serde_json::Error },
+
+    #[error("failed to grant {RUN_INVOKER_ROLE} to {member}")]
+    GrantInvoker { member: String, source: GcloudError },
+
+    #[error("failed to get an identity token from gcloud")]
+    IdentityToken { source: GcloudError },
+
+    #[error("the new revision has no `{tag}` traffic tag after deploying")]
+    TagNotFound { tag: &'static str },
+}
+
+impl DeployError {
+    /// Whether gcloud reported that the Cloud Run service does not exist
+    /// (in the region asked about).
+    pub fn is_service_not_found(&self) -> bool {
+        match self {
+            Self::Deploy { source } | Self::Logs { source } => {
+                is_not_found(source) || is_service_missing(source)
+            }
+            _ => false,
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -1077,6 +3268,18 @@ pub enum SecretError {
 
     #[error("failed to delete secret")]
     Delete { source: GcloudError },
+
+    #[error("failed to access secret '{name}'")]
+    Access { name: String, source: GcloudError },
+
+    #[error("failed to describe secret '{name}'")]
+    Describe { name: String, source: GcloudError },
+
+    #[error("unexpected output describing secret '{name}'")]
+    DescribeParse {
+        name: String,
+        source: serde_json::Error,
+    },
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -1101,4 +3304,13 @@ pub enum WifError {
 
     #[error("failed to delete service account")]
     DeleteServiceAccount { source: GcloudError },
+
+    #[error("failed to read service account IAM policy")]
+    GetIamPolicy { source: GcloudError },
+
+    #[error("failed to list workload identity providers")]
+    ListProviders { source: GcloudError },
+
+    #[error("unexpected gcloud JSON output")]
+    ParseOutput { source: serde_json::Error },
 }
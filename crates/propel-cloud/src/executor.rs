@@ -1,4 +1,22 @@
+use crate::build_progress::BuildProgress;
 use crate::gcloud::GcloudError;
+use std::io::IsTerminal;
+use std::path::Path;
+use std::process::{ExitStatus, Output, Stdio};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::Mutex;
+
+/// Environment variable that makes gcloud answer every prompt with its
+/// default, like passing `--quiet` to every command.
+pub const DISABLE_PROMPTS_ENV: &str = "CLOUDSDK_CORE_DISABLE_PROMPTS";
+
+/// How long a captured gcloud call may go without writing anything before
+/// [`RealExecutor`] assumes it is waiting for input and kills it.
+///
+/// Generous on purpose: long operations such as `run deploy` print progress
+/// well within it.
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
 
 /// Abstraction over gcloud CLI execution for testability.
 ///
@@ -12,6 +30,14 @@ pub trait GcloudExecutor: Send + Sync {
     /// Execute a gcloud command, streaming output to the terminal.
     async fn exec_streaming(&self, args: &[String]) -> Result<(), GcloudError>;
 
+    /// Execute a Cloud Build submission, streaming its output to the
+    /// terminal as [`TeeEcho::Build`] lines while copying stdout and stderr
+    /// verbatim to `log_path`.
+    ///
+    /// On failure the captured stderr is returned in
+    /// [`GcloudError::CommandFailed`], unlike [`Self::exec_streaming`].
+    async fn exec_tee(&self, args: &[String], log_path: &Path) -> Result<(), GcloudError>;
+
     /// Execute a gcloud command with data piped to stdin.
     async fn exec_with_stdin(
         &self,
@@ -25,37 +51,16 @@ pub struct RealExecutor;
 
 impl GcloudExecutor for RealExecutor {
     async fn exec(&self, args: &[String]) -> Result<String, GcloudError> {
-        use std::process::Stdio;
-
         tracing::debug!(cmd = %format!("gcloud {}", args.join(" ")), "exec");
 
-        let output = tokio::process::Command::new("gcloud")
-            .args(args)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output()
-            .await
-            .map_err(|e| GcloudError::NotFound { source: e })?;
-
-        if output.status.success() {
-            String::from_utf8(output.stdout).map_err(|e| GcloudError::InvalidUtf8 { source: e })
-        } else {
-            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-            tracing::warn!(cmd = %format!("gcloud {}", args.join(" ")), %stderr, "command failed");
-            Err(GcloudError::CommandFailed {
-                args: args.to_vec(),
-                stderr,
-            })
-        }
+        let output = run_captured(&mut gcloud(args), None, IDLE_TIMEOUT).await?;
+        captured_stdout(args, output)
     }
 
     async fn exec_streaming(&self, args: &[String]) -> Result<(), GcloudError> {
-        use std::process::Stdio;
-
         tracing::debug!(cmd = %format!("gcloud {}", args.join(" ")), "exec_streaming");
 
-        let status = tokio::process::Command::new("gcloud")
-            .args(args)
+        let status = gcloud(args)
             .stdout(Stdio::inherit())
             .stderr(Stdio::inherit())
             .status()
@@ -73,53 +78,350 @@ impl GcloudExecutor for RealExecutor {
         }
     }
 
+    async fn exec_tee(&self, args: &[String], log_path: &Path) -> Result<(), GcloudError> {
+        tracing::debug!(
+            cmd = %format!("gcloud {}", args.join(" ")),
+            log = %log_path.display(),
+            "exec_tee"
+        );
+
+        let output = run_tee(&mut gcloud(args), log_path, TeeEcho::Build).await?;
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            tracing::warn!(cmd = %format!("gcloud {}", args.join(" ")), status = %output.status, "teed command failed");
+            Err(GcloudError::CommandFailed {
+                args: args.to_vec(),
+                stderr: output.stderr,
+            })
+        }
+    }
+
     async fn exec_with_stdin(
         &self,
         args: &[String],
         stdin_data: &[u8],
     ) -> Result<String, GcloudError> {
-        use std::process::Stdio;
-        use tokio::io::AsyncWriteExt;
-
         tracing::debug!(
             cmd = %format!("gcloud {}", args.join(" ")),
             stdin_bytes = stdin_data.len(),
             "exec_with_stdin"
         );
 
-        let mut child = tokio::process::Command::new("gcloud")
-            .args(args)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .map_err(|e| GcloudError::NotFound { source: e })?;
+        let output = run_captured(&mut gcloud(args), Some(stdin_data), IDLE_TIMEOUT).await?;
+        captured_stdout(args, output)
+    }
+}
 
-        if let Some(mut stdin) = child.stdin.take() {
-            stdin
-                .write_all(stdin_data)
-                .await
-                .map_err(|e| GcloudError::StdinWrite { source: e })?;
-            stdin
-                .shutdown()
-                .await
-                .map_err(|e| GcloudError::StdinWrite { source: e })?;
+/// Executor that runs nothing: it records each command's arguments and
+/// answers with empty output, so a [`GcloudClient`](crate::GcloudClient)
+/// can show what it would run (`propel deploy --dry-run`).
+///
+/// Calls whose output the client parses get nothing to parse, so only
+/// commands that need no answer are worth recording.
+#[derive(Debug, Default)]
+pub struct RecordingExecutor {
+    commands: std::sync::Mutex<Vec<Vec<String>>>,
+}
+
+impl RecordingExecutor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Arguments of every command so far, in call order.
+    pub fn commands(&self) -> Vec<Vec<String>> {
+        self.lock().clone()
+    }
+
+    fn record(&self, args: &[String]) {
+        tracing::debug!(cmd = %format!("gcloud {}", args.join(" ")), "recorded");
+        self.lock().push(args.to_vec());
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Vec<String>>> {
+        // A panic while recording leaves the list intact.
+        match self.commands.lock() {
+            Ok(commands) => commands,
+            Err(poisoned) => poisoned.into_inner(),
         }
+    }
+}
+
+impl GcloudExecutor for RecordingExecutor {
+    async fn exec(&self, args: &[String]) -> Result<String, GcloudError> {
+        self.record(args);
+        Ok(String::new())
+    }
 
-        let output = child
-            .wait_with_output()
+    async fn exec_streaming(&self, args: &[String]) -> Result<(), GcloudError> {
+        self.record(args);
+        Ok(())
+    }
+
+    async fn exec_tee(&self, args: &[String], _log_path: &Path) -> Result<(), GcloudError> {
+        self.record(args);
+        Ok(())
+    }
+
+    async fn exec_with_stdin(
+        &self,
+        args: &[String],
+        _stdin_data: &[u8],
+    ) -> Result<String, GcloudError> {
+        self.record(args);
+        Ok(String::new())
+    }
+}
+
+/// `gcloud <args>` with prompts disabled.
+fn gcloud(args: &[String]) -> tokio::process::Command {
+    let mut command = tokio::process::Command::new("gcloud");
+    command.args(args);
+    disable_prompts(&mut command);
+    command
+}
+
+/// Make gcloud run `command` non-interactively: set [`DISABLE_PROMPTS_ENV`].
+pub fn disable_prompts(command: &mut tokio::process::Command) -> &mut tokio::process::Command {
+    command.env(DISABLE_PROMPTS_ENV, "1")
+}
+
+/// Stdout of a successful captured call, or [`GcloudError::CommandFailed`]
+/// with its stderr.
+fn captured_stdout(args: &[String], output: Output) -> Result<String, GcloudError> {
+    if output.status.success() {
+        String::from_utf8(output.stdout).map_err(|e| GcloudError::InvalidUtf8 { source: e })
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        tracing::warn!(cmd = %format!("gcloud {}", args.join(" ")), %stderr, "command failed");
+        Err(GcloudError::CommandFailed {
+            args: args.to_vec(),
+            stderr,
+        })
+    }
+}
+
+/// Run `command` with stdout and stderr captured, feeding it `stdin_data`
+/// (or an empty stdin).
+///
+/// A command that writes nothing to either stream for `idle_timeout` is
+/// assumed to be stuck at a prompt nobody can answer: it is killed and
+/// [`GcloudError::WaitingForInput`] is returned. Spawn and pipe failures
+/// map to [`GcloudError::NotFound`], as with [`run_tee`].
+pub async fn run_captured(
+    command: &mut tokio::process::Command,
+    stdin_data: Option<&[u8]>,
+    idle_timeout: Duration,
+) -> Result<Output, GcloudError> {
+    let stdin = match stdin_data {
+        Some(_) => Stdio::piped(),
+        None => Stdio::null(),
+    };
+    let mut child = command
+        .stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| GcloudError::NotFound { source: e })?;
+
+    if let (Some(data), Some(mut stdin)) = (stdin_data, child.stdin.take()) {
+        stdin
+            .write_all(data)
             .await
-            .map_err(|e| GcloudError::NotFound { source: e })?;
+            .map_err(|e| GcloudError::StdinWrite { source: e })?;
+        stdin
+            .shutdown()
+            .await
+            .map_err(|e| GcloudError::StdinWrite { source: e })?;
+    }
 
-        if output.status.success() {
-            String::from_utf8(output.stdout).map_err(|e| GcloudError::InvalidUtf8 { source: e })
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let (mut out, mut err) = (Vec::new(), Vec::new());
+    let (mut out_buf, mut err_buf) = ([0u8; 8192], [0u8; 8192]);
+    let (mut out_open, mut err_open) = (true, true);
+    while out_open || err_open {
+        // A fresh sleep per iteration: any output restarts the idle clock.
+        tokio::select! {
+            read = stdout.read(&mut out_buf), if out_open => {
+                let n = read.map_err(|e| GcloudError::NotFound { source: e })?;
+                out.extend_from_slice(&out_buf[..n]);
+                out_open = n > 0;
+            }
+            read = stderr.read(&mut err_buf), if err_open => {
+                let n = read.map_err(|e| GcloudError::NotFound { source: e })?;
+                err.extend_from_slice(&err_buf[..n]);
+                err_open = n > 0;
+            }
+            () = tokio::time::sleep(idle_timeout) => {
+                if let Err(e) = child.kill().await {
+                    tracing::warn!(error = %e, "could not kill idle gcloud process");
+                }
+                return Err(GcloudError::WaitingForInput {
+                    args: command
+                        .as_std()
+                        .get_args()
+                        .map(|a| a.to_string_lossy().into_owned())
+                        .collect(),
+                    idle: idle_timeout,
+                });
+            }
+        }
+    }
+
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| GcloudError::NotFound { source: e })?;
+    Ok(Output {
+        status,
+        stdout: out,
+        stderr: err,
+    })
+}
+
+/// Result of [`run_tee`].
+#[derive(Debug)]
+pub struct TeeOutput {
+    pub status: ExitStatus,
+    /// Everything the command wrote to stderr (lossy UTF-8).
+    pub stderr: String,
+}
+
+/// How [`run_tee`] echoes output lines to the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TeeEcho {
+    /// Echo lines unchanged.
+    Raw,
+    /// Prefix each line with a dim `[build]` tag and interleave the
+    /// [`BuildProgress`] events detected in the output.
+    Build,
+}
+
+/// Tag in front of echoed Cloud Build lines.
+const BUILD_PREFIX: &str = "[build] ";
+/// `BUILD_PREFIX` in ANSI dim, for terminals.
+const BUILD_PREFIX_DIM: &str = "\x1b[2m[build]\x1b[0m ";
+
+/// Run `command`, echoing its stdout and stderr to ours line by line and
+/// copying both, interleaved and unmodified, into a fresh file at `log_path`.
+///
+/// Parent directories of `log_path` are created. A spawn failure maps to
+/// [`GcloudError::NotFound`]; any log or terminal I/O failure to
+/// [`GcloudError::LogFile`].
+pub async fn run_tee(
+    command: &mut tokio::process::Command,
+    log_path: &Path,
+    echo: TeeEcho,
+) -> Result<TeeOutput, GcloudError> {
+    let log_error = |source| GcloudError::LogFile {
+        path: log_path.to_owned(),
+        source,
+    };
+
+    if let Some(parent) = log_path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        tokio::fs::create_dir_all(parent).await.map_err(log_error)?;
+    }
+    let log = Mutex::new(tokio::fs::File::create(log_path).await.map_err(log_error)?);
+
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| GcloudError::NotFound { source: e })?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+
+    let echo = match echo {
+        TeeEcho::Raw => None,
+        TeeEcho::Build => Some(BuildEcho {
+            prefix: if std::io::stdout().is_terminal() {
+                BUILD_PREFIX_DIM
+            } else {
+                BUILD_PREFIX
+            },
+            progress: Mutex::new(BuildProgress::new()),
+        }),
+    };
+    let mut captured_stderr = Vec::new();
+    let (out, err) = tokio::join!(
+        pump(stdout, tokio::io::stdout(), &log, echo.as_ref(), None),
+        pump(
+            stderr,
+            tokio::io::stderr(),
+            &log,
+            echo.as_ref(),
+            Some(&mut captured_stderr)
+        ),
+    );
+    out.map_err(log_error)?;
+    err.map_err(log_error)?;
+    log.lock().await.flush().await.map_err(log_error)?;
+
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| GcloudError::NotFound { source: e })?;
+    Ok(TeeOutput {
+        status,
+        stderr: String::from_utf8_lossy(&captured_stderr).into_owned(),
+    })
+}
+
+/// Terminal decoration shared by the stdout and stderr pumps.
+struct BuildEcho {
+    prefix: &'static str,
+    progress: Mutex<BuildProgress>,
+}
+
+/// Copy `source` to `terminal` and `log` line by line until EOF,
+/// optionally keeping a copy in `capture`.
+///
+/// Only the terminal copy is decorated by `echo`; progress events go to
+/// stdout so they stay visible when stderr is redirected.
+async fn pump(
+    source: impl AsyncRead + Unpin,
+    mut terminal: impl AsyncWrite + Unpin,
+    log: &Mutex<tokio::fs::File>,
+    echo: Option<&BuildEcho>,
+    mut capture: Option<&mut Vec<u8>>,
+) -> std::io::Result<()> {
+    let mut source = BufReader::new(source);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if source.read_until(b'\n', &mut line).await? == 0 {
+            return Ok(());
+        }
+        if let Some(echo) = echo {
+            terminal.write_all(echo.prefix.as_bytes()).await?;
+            terminal.write_all(&line).await?;
+            if !line.ends_with(b"\n") {
+                terminal.write_all(b"\n").await?;
+            }
+            let event = echo
+                .progress
+                .lock()
+                .await
+                .observe(&String::from_utf8_lossy(&line));
+            if let Some(event) = event {
+                terminal.flush().await?;
+                let mut stdout = tokio::io::stdout();
+                stdout.write_all(format!("{event}\n").as_bytes()).await?;
+                stdout.flush().await?;
+            }
         } else {
-            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-            tracing::warn!(cmd = %format!("gcloud {}", args.join(" ")), %stderr, "command failed");
-            Err(GcloudError::CommandFailed {
-                args: args.to_vec(),
-                stderr,
-            })
+            terminal.write_all(&line).await?;
+        }
+        terminal.flush().await?;
+        log.lock().await.write_all(&line).await?;
+        if let Some(capture) = capture.as_deref_mut() {
+            capture.extend_from_slice(&line);
         }
     }
 }
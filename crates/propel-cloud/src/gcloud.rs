@@ -11,4 +11,85 @@ pub enum GcloudError {
 
     #[error("failed to write to gcloud stdin")]
     StdinWrite { source: std::io::Error },
+
+    #[error(
+        "gcloud appears to be waiting for input (no output for {}s): {args:?}\n\
+         Run the command yourself to see the prompt, then re-run propel.",
+        idle.as_secs()
+    )]
+    WaitingForInput {
+        args: Vec<String>,
+        idle: std::time::Duration,
+    },
+
+    #[error("failed to write log file {}", path.display())]
+    LogFile {
+        path: std::path::PathBuf,
+        source: std::io::Error,
+    },
+}
+
+/// Oldest Google Cloud SDK release propel is tested against.
+pub const MIN_GCLOUD_VERSION: (u32, u32, u32) = (460, 0, 0);
+
+/// Parsed `gcloud version` output: SDK version plus installed components.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GcloudVersion {
+    /// SDK version string, e.g. `"555.0.0"`.
+    pub sdk: Option<String>,
+    /// Installed components as `(name, version)` pairs, in output order.
+    pub components: Vec<(String, String)>,
+}
+
+impl GcloudVersion {
+    /// Parse the plain-text output of `gcloud version`.
+    ///
+    /// ```text
+    /// Google Cloud SDK 555.0.0
+    /// beta 2026.01.09
+    /// bq 2.1.26
+    /// core 2026.01.09
+    /// log-streaming 2025.11.07
+    /// ```
+    ///
+    /// Unrecognized lines (update notices, blank lines) are skipped.
+    pub fn parse(output: &str) -> Self {
+        let mut parsed = Self::default();
+        for line in output.lines().map(str::trim) {
+            if let Some(v) = line.strip_prefix("Google Cloud SDK ") {
+                parsed.sdk = Some(v.trim().to_owned());
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            if let (Some(name), Some(version), None) = (parts.next(), parts.next(), parts.next())
+                && version.starts_with(|c: char| c.is_ascii_digit())
+            {
+                parsed
+                    .components
+                    .push((name.to_owned(), version.to_owned()));
+            }
+        }
+        parsed
+    }
+
+    /// Whether the named component is installed.
+    pub fn has_component(&self, name: &str) -> bool {
+        self.components.iter().any(|(n, _)| n == name)
+    }
+
+    /// Numeric `(major, minor, patch)` of the SDK version, if parseable.
+    pub fn sdk_triple(&self) -> Option<(u32, u32, u32)> {
+        let mut nums = self.sdk.as_deref()?.split('.').map(str::parse::<u32>);
+        match (nums.next(), nums.next(), nums.next()) {
+            (Some(Ok(major)), Some(Ok(minor)), Some(Ok(patch))) => Some((major, minor, patch)),
+            _ => None,
+        }
+    }
+
+    /// Whether the SDK is at least [`MIN_GCLOUD_VERSION`].
+    ///
+    /// Returns `None` when the version could not be determined.
+    pub fn meets_minimum(&self) -> Option<bool> {
+        self.sdk_triple().map(|v| v >= MIN_GCLOUD_VERSION)
+    }
 }
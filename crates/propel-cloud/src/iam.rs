@@ -0,0 +1,294 @@
+use serde::Deserialize;
+
+/// Role granting a Workload Identity principal the right to impersonate a
+/// service account.
+pub const WORKLOAD_IDENTITY_USER_ROLE: &str = "roles/iam.workloadIdentityUser";
+
+/// IAM policy as printed by `gcloud ... get-iam-policy --format json`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct IamPolicy {
+    #[serde(default)]
+    pub bindings: Vec<IamBinding>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct IamBinding {
+    pub role: String,
+    #[serde(default)]
+    pub members: Vec<String>,
+}
+
+impl IamPolicy {
+    /// Members bound to `role`, in policy order.
+    pub fn members_with_role<'a>(&'a self, role: &'a str) -> impl Iterator<Item = &'a str> {
+        self.bindings
+            .iter()
+            .filter(move |b| b.role == role)
+            .flat_map(|b| b.members.iter().map(String::as_str))
+    }
+
+    /// Whether the service account `email` may connect to Cloud SQL
+    /// instances through a direct binding of [`CLOUD_SQL_CLIENT_ROLE`] or a
+    /// broader predefined role. Group bindings and custom roles are not
+    /// followed.
+    pub fn grants_cloud_sql_connect(&self, email: &str) -> bool {
+        let member = format!("serviceAccount:{email}");
+        CLOUD_SQL_CONNECT_ROLES.iter().any(|role| {
+            self.members_with_role(role)
+                .any(|m| m.eq_ignore_ascii_case(&member))
+        })
+    }
+}
+
+/// Role the runtime service account needs to connect to the Cloud SQL
+/// instances in `[cloud_run].cloudsql_instances`.
+pub const CLOUD_SQL_CLIENT_ROLE: &str = "roles/cloudsql.client";
+
+/// Predefined roles that include `cloudsql.instances.connect`.
+const CLOUD_SQL_CONNECT_ROLES: &[&str] = &[
+    CLOUD_SQL_CLIENT_ROLE,
+    "roles/cloudsql.editor",
+    "roles/cloudsql.admin",
+    "roles/owner",
+    "roles/editor",
+];
+
+/// Role that lets a principal call a Cloud Run service.
+pub const RUN_INVOKER_ROLE: &str = "roles/run.invoker";
+
+/// Member that makes a Cloud Run service public when it holds
+/// [`RUN_INVOKER_ROLE`].
+pub const ALL_USERS: &str = "allUsers";
+
+/// Who may reach a Cloud Run service: its ingress setting and the members
+/// holding [`RUN_INVOKER_ROLE`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceAccess {
+    /// `all`, `internal`, or `internal-and-cloud-load-balancing`.
+    pub ingress: String,
+    pub invokers: Vec<String>,
+}
+
+impl ServiceAccess {
+    /// From `gcloud run services describe --format json` and
+    /// `gcloud run services get-iam-policy --format json`. A service
+    /// without an ingress annotation accepts all traffic.
+    pub fn parse(service_json: &str, policy_json: &str) -> Result<Self, serde_json::Error> {
+        #[derive(Deserialize)]
+        struct Service {
+            #[serde(default)]
+            metadata: Metadata,
+        }
+        #[derive(Default, Deserialize)]
+        struct Metadata {
+            #[serde(default)]
+            annotations: std::collections::HashMap<String, String>,
+        }
+
+        let service: Service = serde_json::from_str(service_json)?;
+        let policy: IamPolicy = serde_json::from_str(policy_json)?;
+        Ok(Self {
+            ingress: service
+                .metadata
+                .annotations
+                .get("run.googleapis.com/ingress")
+                .map_or_else(|| "all".to_owned(), Clone::clone),
+            invokers: policy
+                .members_with_role(RUN_INVOKER_ROLE)
+                .map(str::to_owned)
+                .collect(),
+        })
+    }
+
+    /// Whether anyone may call the service (`allUsers` is an invoker).
+    pub fn is_public(&self) -> bool {
+        self.invokers.iter().any(|m| m == ALL_USERS)
+    }
+}
+
+impl std::fmt::Display for ServiceAccess {
+    /// `public (allUsers may invoke) · ingress all`, or
+    /// `private, invokers: serviceAccount:a@… · ingress internal`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_public() {
+            f.write_str("public (allUsers may invoke)")?;
+        } else if self.invokers.is_empty() {
+            f.write_str("private, no invokers")?;
+        } else {
+            write!(f, "private, invokers: {}", self.invokers.join(", "))?;
+        }
+        write!(f, " · ingress {}", self.ingress)
+    }
+}
+
+/// Entry of `gcloud iam workload-identity-pools providers list --format json`.
+#[derive(Debug, Deserialize)]
+pub(crate) struct WifProvider {
+    /// Full resource name, ending in `/providers/<id>`.
+    pub name: String,
+}
+
+impl WifProvider {
+    pub(crate) fn id(&self) -> &str {
+        match self.name.rsplit_once('/') {
+            Some((_, id)) => id,
+            None => &self.name,
+        }
+    }
+}
+
+/// Other users of the CI pool and service account `destroy --include-ci`
+/// is about to delete.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SharedCiUsage {
+    /// Workload Identity principals other than this repository's that may
+    /// impersonate the service account.
+    pub other_principals: Vec<String>,
+    /// Providers in the pool besides this project's.
+    pub other_providers: Vec<String>,
+}
+
+impl SharedCiUsage {
+    /// Whether deleting the resources would break someone else's CI.
+    pub fn is_shared(&self) -> bool {
+        !self.other_principals.is_empty() || !self.other_providers.is_empty()
+    }
+}
+
+/// Compare the service account policy and pool providers against what
+/// `propel ci init` created for `github_repo`.
+///
+/// Our principal is the `principalSet` for `pool_id` scoped to
+/// `attribute.repository/<github_repo>`. Without a known repository every
+/// Workload Identity principal counts as foreign.
+pub fn shared_ci_usage(
+    sa_policy: &IamPolicy,
+    providers: &[String],
+    pool_id: &str,
+    provider_id: &str,
+    github_repo: Option<&str>,
+) -> SharedCiUsage {
+    let ours = |member: &str| {
+        github_repo.is_some_and(|repo| {
+            member.starts_with("principalSet://")
+                && member.contains(&format!("/workloadIdentityPools/{pool_id}/"))
+                && member.ends_with(&format!("/attribute.repository/{repo}"))
+        })
+    };
+
+    SharedCiUsage {
+        other_principals: sa_policy
+            .members_with_role(WORKLOAD_IDENTITY_USER_ROLE)
+            .filter(|m| !ours(m))
+            .map(str::to_owned)
+            .collect(),
+        other_providers: providers
+            .iter()
+            .filter(|p| p.as_str() != provider_id)
+            .cloned()
+            .collect(),
+    }
+}
+
+/// A permission `propel deploy` needs, with the predefined roles that grant it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequiredPermission {
+    pub permission: &'static str,
+    /// Narrowest predefined roles first; any of them suffices.
+    pub roles: &'static [&'static str],
+}
+
+impl std::fmt::Display for RequiredPermission {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (grant {})", self.permission, self.roles.join(" or "))
+    }
+}
+
+/// Permissions deploy exercises against the project, beyond reading it.
+pub const DEPLOY_PERMISSIONS: &[RequiredPermission] = &[
+    RequiredPermission {
+        permission: "run.services.update",
+        roles: &["roles/run.developer", "roles/run.admin"],
+    },
+    RequiredPermission {
+        permission: "cloudbuild.builds.create",
+        roles: &["roles/cloudbuild.builds.editor"],
+    },
+    RequiredPermission {
+        permission: "artifactregistry.repositories.create",
+        roles: &["roles/artifactregistry.admin"],
+    },
+    RequiredPermission {
+        permission: "secretmanager.secrets.list",
+        roles: &["roles/secretmanager.viewer", "roles/secretmanager.admin"],
+    },
+];
+
+/// Basic roles that include every permission in [`DEPLOY_PERMISSIONS`].
+const BASIC_ROLES: &[&str] = &["roles/owner", "roles/editor"];
+
+/// IAM member string for a gcloud account: `serviceAccount:` for service
+/// account emails, `user:` otherwise.
+pub fn account_member(account: &str) -> String {
+    if account.ends_with(".gserviceaccount.com") {
+        format!("serviceAccount:{account}")
+    } else {
+        format!("user:{account}")
+    }
+}
+
+/// Outcome of [`check_deploy_permissions`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PermissionCheck {
+    /// Permissions none of the account's direct role bindings grant.
+    pub missing: Vec<RequiredPermission>,
+    /// The policy has bindings this check cannot evaluate — group or domain
+    /// members, or custom roles on the account — so `missing` may be
+    /// granted after all.
+    pub inconclusive: bool,
+}
+
+impl PermissionCheck {
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+/// Check which [`DEPLOY_PERMISSIONS`] `account` holds through its direct
+/// bindings in a project policy.
+///
+/// Only predefined roles are understood; conditions on bindings are ignored.
+pub fn check_deploy_permissions(policy: &IamPolicy, account: &str) -> PermissionCheck {
+    let is_account = |member: &str| {
+        member.split_once(':').is_some_and(|(kind, email)| {
+            matches!(kind, "user" | "serviceAccount") && email.eq_ignore_ascii_case(account)
+        })
+    };
+    let held: Vec<&str> = policy
+        .bindings
+        .iter()
+        .filter(|b| b.members.iter().any(|m| is_account(m)))
+        .map(|b| b.role.as_str())
+        .collect();
+
+    let missing = if held.iter().any(|r| BASIC_ROLES.contains(r)) {
+        Vec::new()
+    } else {
+        DEPLOY_PERMISSIONS
+            .iter()
+            .filter(|p| !p.roles.iter().any(|r| held.contains(r)))
+            .copied()
+            .collect()
+    };
+    let inconclusive = held.iter().any(|r| !r.starts_with("roles/"))
+        || policy
+            .bindings
+            .iter()
+            .flat_map(|b| &b.members)
+            .any(|m| m.starts_with("group:") || m.starts_with("domain:"));
+
+    PermissionCheck {
+        missing,
+        inconclusive,
+    }
+}
@@ -0,0 +1,49 @@
+//! Size of a built image in Artifact Registry.
+//!
+//! `gcloud artifacts docker images describe` only reports the digest, so
+//! the size comes from `gcloud artifacts docker images list`, whose
+//! entries carry `metadata.imageSizeBytes` (a decimal string).
+
+use serde::Deserialize;
+
+/// Split `REGISTRY/PATH/IMAGE:TAG` into the image path and the tag.
+///
+/// A reference without a tag is `latest`, as for `docker pull`.
+pub fn split_image_tag(image_tag: &str) -> (&str, &str) {
+    let name_start = image_tag.rfind('/').map_or(0, |i| i + 1);
+    match image_tag[name_start..].rfind(':') {
+        Some(colon) => (
+            &image_tag[..name_start + colon],
+            &image_tag[name_start + colon + 1..],
+        ),
+        None => (image_tag, "latest"),
+    }
+}
+
+/// Entry of `gcloud artifacts docker images list --format json`.
+#[derive(Debug, Deserialize)]
+struct ImageEntry {
+    #[serde(default)]
+    metadata: ImageMetadata,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ImageMetadata {
+    #[serde(rename = "imageSizeBytes")]
+    image_size_bytes: Option<serde_json::Value>,
+}
+
+/// Size in bytes of the first image in `gcloud artifacts docker images
+/// list --format json` output, or `None` when the list is empty or the
+/// entry has no size.
+pub fn parse_image_size(json: &str) -> Result<Option<u64>, serde_json::Error> {
+    let entries: Vec<ImageEntry> = serde_json::from_str(json)?;
+    Ok(entries
+        .into_iter()
+        .find_map(|entry| match entry.metadata.image_size_bytes? {
+            // arch-lint: allow(no-silent-result-drop) reason="a malformed size is reported as unknown"
+            serde_json::Value::String(s) => s.parse().ok(),
+            serde_json::Value::Number(n) => n.as_u64(),
+            _ => None,
+        }))
+}
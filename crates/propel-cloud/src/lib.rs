@@ -1,9 +1,44 @@
+pub mod build_progress;
+pub mod canary;
 pub mod client;
 pub mod executor;
 pub mod gcloud;
+pub mod iam;
+pub mod image;
+pub mod labels;
+pub mod pricing;
+pub mod projects;
+pub mod revisions;
+pub mod secrets;
+pub mod warmup;
 
+pub use build_progress::{BuildEvent, BuildProgress, BuildStage};
+pub use canary::{CANARY_TAG, CanaryState, parse_canary_state};
 pub use client::{
-    ApiCheck, CheckResult, CloudBuildError, DeployError, DoctorReport, GcloudClient,
-    PreflightError, PreflightReport, SecretError, WifError,
+    API_ENABLEMENT_TIMEOUT, ApiCheck, BuildOutput, CLOUD_SQL_ADMIN_API, CheckResult,
+    CloudBuildError, CloudBuildPolicyIssue, DOCTOR_API_CACHE_TTL, DeployError, DoctorCheck,
+    DoctorReport, GcloudClient, PreflightError, PreflightReport, RunCommandGroup,
+    SECRET_ACCESS_PROPAGATION_TIMEOUT, SECRET_MANAGER_API, SecretError, WifError,
+    classify_cloud_build_error, cloud_run_deploy_args, cloud_run_flags, cloud_run_update_args,
+    components_check, retry_secret_propagation, secret_access_denied,
+    worker_pool_policy_restricted,
+};
+pub use executor::{GcloudExecutor, RealExecutor, RecordingExecutor};
+pub use gcloud::{GcloudVersion, MIN_GCLOUD_VERSION};
+pub use iam::{
+    ALL_USERS, CLOUD_SQL_CLIENT_ROLE, DEPLOY_PERMISSIONS, IamBinding, IamPolicy, PermissionCheck,
+    RUN_INVOKER_ROLE, RequiredPermission, ServiceAccess, SharedCiUsage, account_member,
+    check_deploy_permissions, shared_ci_usage,
+};
+pub use image::{parse_image_size, split_image_tag};
+pub use labels::{
+    CONFIG_HASH_LABEL, IMAGE_SIZE_LABEL, MANAGED_LABEL, ManagedService, PropelLabels,
+    VERSION_LABEL, parse_managed_services,
+};
+pub use pricing::{CostEstimate, PriceTier, PricingError, estimate_monthly_cost};
+pub use projects::{GcpProject, parse_projects};
+pub use revisions::{CREATOR_ANNOTATION, RevisionInfo, parse_revisions, previous_revision};
+pub use secrets::{SecretInfo, SecretVersionInfo, SecretVersionState, parse_secret_info};
+pub use warmup::{
+    HttpProber, ReqwestProber, WARMUP_TAG, Warmup, WarmupError, tagged_revision_url, warm_up,
 };
-pub use executor::{GcloudExecutor, RealExecutor};
@@ -0,0 +1,166 @@
+//! Rough Cloud Run cost estimates from `[cloud_run]` settings.
+//!
+//! Prices are compiled in and will drift from Google's list prices. Update
+//! [`TIER_1_PRICES`], [`TIER_2_PRICES`], and [`TIER_1_REGIONS`] from
+//! <https://cloud.google.com/run/pricing> when they change, together with
+//! [`PRICES_AS_OF`].
+
+use propel_core::CloudRunConfig;
+
+/// When the compiled-in prices were last checked.
+pub const PRICES_AS_OF: &str = "2025-01";
+
+/// Seconds in the 730-hour month Google's pricing calculator uses.
+const SECONDS_PER_MONTH: f64 = 730.0 * 3600.0;
+
+/// Per-second USD prices for request-based billing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PriceTable {
+    /// vCPU-second while serving requests.
+    pub cpu_active: f64,
+    /// GiB-second while serving requests.
+    pub memory_active: f64,
+    /// vCPU-second for an idle minimum instance.
+    pub cpu_idle: f64,
+    /// GiB-second for an idle minimum instance.
+    pub memory_idle: f64,
+}
+
+pub const TIER_1_PRICES: PriceTable = PriceTable {
+    cpu_active: 0.000_024,
+    memory_active: 0.000_002_5,
+    cpu_idle: 0.000_002_5,
+    memory_idle: 0.000_002_5,
+};
+
+pub const TIER_2_PRICES: PriceTable = PriceTable {
+    cpu_active: 0.000_033_6,
+    memory_active: 0.000_003_5,
+    cpu_idle: 0.000_003_5,
+    memory_idle: 0.000_003_5,
+};
+
+/// Regions billed at Tier 1 prices; every other region is Tier 2.
+pub const TIER_1_REGIONS: &[&str] = &[
+    "asia-east1",
+    "asia-northeast1",
+    "asia-northeast2",
+    "europe-north1",
+    "europe-southwest1",
+    "europe-west1",
+    "europe-west4",
+    "europe-west8",
+    "europe-west9",
+    "me-west1",
+    "us-central1",
+    "us-east1",
+    "us-east4",
+    "us-east5",
+    "us-south1",
+    "us-west1",
+];
+
+/// Pricing tier of a Cloud Run region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriceTier {
+    Tier1,
+    Tier2,
+}
+
+impl PriceTier {
+    pub fn for_region(region: &str) -> Self {
+        if TIER_1_REGIONS.contains(&region) {
+            Self::Tier1
+        } else {
+            Self::Tier2
+        }
+    }
+
+    pub fn prices(self) -> &'static PriceTable {
+        match self {
+            Self::Tier1 => &TIER_1_PRICES,
+            Self::Tier2 => &TIER_2_PRICES,
+        }
+    }
+}
+
+impl std::fmt::Display for PriceTier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tier1 => f.write_str("Tier 1"),
+            Self::Tier2 => f.write_str("Tier 2"),
+        }
+    }
+}
+
+/// Monthly USD estimate for one service configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostEstimate {
+    pub tier: PriceTier,
+    /// Memory per instance, in GiB.
+    pub memory_gib: f64,
+    /// `min_instances` kept warm and idle around the clock (at active
+    /// prices with `cpu_always_allocated`).
+    pub idle_monthly: f64,
+    /// `max_instances` all serving requests around the clock.
+    pub max_monthly: f64,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PricingError {
+    #[error("cannot estimate cost for memory {value:?}: expected e.g. \"512Mi\" or \"2Gi\"")]
+    InvalidMemory { value: String },
+}
+
+/// Estimate monthly cost of `config` in `region`.
+///
+/// Ignores the free tier, request fees, and networking, and assumes the
+/// service never scales below `min_instances` — it is a rough upper bound
+/// on instance time, not a bill.
+pub fn estimate_monthly_cost(
+    config: &CloudRunConfig,
+    region: &str,
+) -> Result<CostEstimate, PricingError> {
+    let memory_gib =
+        parse_memory_gib(&config.memory).ok_or_else(|| PricingError::InvalidMemory {
+            value: config.memory.clone(),
+        })?;
+    let tier = PriceTier::for_region(region);
+    let prices = tier.prices();
+    let cpu = f64::from(config.cpu);
+
+    let active_per_instance = cpu * prices.cpu_active + memory_gib * prices.memory_active;
+    // With CPU always allocated, a warm instance is never billed as idle.
+    let idle_per_instance = if config.cpu_always_allocated {
+        active_per_instance
+    } else {
+        cpu * prices.cpu_idle + memory_gib * prices.memory_idle
+    };
+
+    Ok(CostEstimate {
+        tier,
+        memory_gib,
+        idle_monthly: f64::from(config.min_instances) * idle_per_instance * SECONDS_PER_MONTH,
+        max_monthly: f64::from(config.max_instances) * active_per_instance * SECONDS_PER_MONTH,
+    })
+}
+
+/// Parse a Cloud Run memory limit (`Mi`, `Gi`, `M`, `G`) into GiB.
+pub fn parse_memory_gib(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let (number, gib_per_unit) = if let Some(n) = value.strip_suffix("Gi") {
+        (n, 1.0)
+    } else if let Some(n) = value.strip_suffix("Mi") {
+        (n, 1.0 / 1024.0)
+    } else if let Some(n) = value.strip_suffix('G') {
+        (n, 1e9 / 1_073_741_824.0)
+    } else if let Some(n) = value.strip_suffix('M') {
+        (n, 1e6 / 1_073_741_824.0)
+    } else {
+        return None;
+    };
+    match number.parse::<f64>() {
+        Ok(n) if n > 0.0 => Some(n * gib_per_unit),
+        _ => None,
+    }
+}
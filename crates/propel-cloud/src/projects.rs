@@ -0,0 +1,41 @@
+//! GCP projects the active account can see, for `propel init --guided`.
+
+use serde::Deserialize;
+
+/// A GCP project from `gcloud projects list`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GcpProject {
+    /// Project ID, as written to `[project].gcp_project_id`.
+    pub id: String,
+    /// Display name; may equal the ID.
+    pub name: String,
+}
+
+/// Entry of `gcloud projects list --format json`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Project {
+    project_id: String,
+    #[serde(default)]
+    name: String,
+    lifecycle_state: Option<String>,
+}
+
+/// Projects in `gcloud projects list --format json` output, skipping any
+/// pending deletion.
+pub fn parse_projects(json: &str) -> Result<Vec<GcpProject>, serde_json::Error> {
+    let projects: Vec<Project> = serde_json::from_str(json)?;
+    Ok(projects
+        .into_iter()
+        .filter(|project| {
+            project
+                .lifecycle_state
+                .as_deref()
+                .is_none_or(|s| s == "ACTIVE")
+        })
+        .map(|project| GcpProject {
+            id: project.project_id,
+            name: project.name,
+        })
+        .collect())
+}
@@ -0,0 +1,121 @@
+//! What Secret Manager holds for a secret, without its value: when it was
+//! created and the state of each version.
+//!
+//! Cloud Run mounts a secret at startup, so a secret with no enabled
+//! version makes every new revision fail to start.
+
+use serde::Deserialize;
+use std::fmt;
+
+/// A secret and its versions, from `gcloud secrets describe` and
+/// `gcloud secrets versions list`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretInfo {
+    /// Secret Manager name.
+    pub name: String,
+    /// Creation time as reported by Secret Manager (RFC 3339, UTC).
+    pub create_time: String,
+    /// Every version, newest first.
+    pub versions: Vec<SecretVersionInfo>,
+}
+
+impl SecretInfo {
+    /// The newest version, whatever its state.
+    pub fn latest(&self) -> Option<&SecretVersionInfo> {
+        self.versions.first()
+    }
+
+    /// Whether any version can be read.
+    pub fn has_enabled_version(&self) -> bool {
+        self.versions
+            .iter()
+            .any(|version| version.state == SecretVersionState::Enabled)
+    }
+}
+
+/// One version of a secret.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretVersionInfo {
+    /// Version number, e.g. `"3"`.
+    pub version: String,
+    pub state: SecretVersionState,
+    /// Creation time as reported by Secret Manager (RFC 3339, UTC).
+    pub create_time: String,
+}
+
+/// Secret Manager's version states. Only enabled versions can be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SecretVersionState {
+    Enabled,
+    Disabled,
+    Destroyed,
+    #[serde(other)]
+    Unknown,
+}
+
+impl fmt::Display for SecretVersionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Enabled => "enabled",
+            Self::Disabled => "disabled",
+            Self::Destroyed => "destroyed",
+            Self::Unknown => "unknown",
+        })
+    }
+}
+
+/// `gcloud secrets describe --format json`, reduced to what is shown.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Secret {
+    #[serde(default)]
+    create_time: String,
+}
+
+/// Entry of `gcloud secrets versions list --format json`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Version {
+    /// `projects/<number>/secrets/<name>/versions/<version>`
+    name: String,
+    state: SecretVersionState,
+    #[serde(default)]
+    create_time: String,
+}
+
+/// Combine `gcloud secrets describe` and `gcloud secrets versions list`
+/// output (both `--format json`) for the secret `name`. Versions are
+/// ordered newest first, by number.
+pub fn parse_secret_info(
+    name: &str,
+    describe_json: &str,
+    versions_json: &str,
+) -> Result<SecretInfo, serde_json::Error> {
+    let secret: Secret = serde_json::from_str(describe_json)?;
+    let versions: Vec<Version> = serde_json::from_str(versions_json)?;
+
+    let mut versions: Vec<SecretVersionInfo> = versions
+        .into_iter()
+        .map(|version| SecretVersionInfo {
+            version: version
+                .name
+                .rsplit_once('/')
+                .map_or(version.name.as_str(), |(_, v)| v)
+                .to_owned(),
+            state: version.state,
+            create_time: version.create_time,
+        })
+        .collect();
+    // Numbers only; an unparsable one sorts last.
+    versions.sort_by_key(|version| match version.version.parse::<u64>() {
+        Ok(number) => std::cmp::Reverse(Some(number)),
+        Err(_) => std::cmp::Reverse(None),
+    });
+
+    Ok(SecretInfo {
+        name: name.to_owned(),
+        create_time: secret.create_time,
+        versions,
+    })
+}
@@ -0,0 +1,185 @@
+//! Warm-up of a new Cloud Run revision before it receives traffic.
+//!
+//! With `[cloud_run].warmup_requests`, deploy creates the revision with
+//! `--no-traffic --tag` [`WARMUP_TAG`]
+//! ([`GcloudClient::deploy_revision_without_traffic`](crate::GcloudClient::deploy_revision_without_traffic)),
+//! sends requests to the tag URL with [`warm_up`] until enough have
+//! succeeded, and only then moves traffic to it
+//! ([`GcloudClient::promote_latest_revision`](crate::GcloudClient::promote_latest_revision)).
+//! If the warm-up fails, traffic stays on the previous revision.
+
+use std::time::Duration;
+
+use serde::Deserialize;
+
+/// Traffic tag of the revision being warmed up; its URL is
+/// `https://propel-warmup---<service host>`.
+pub const WARMUP_TAG: &str = "propel-warmup";
+
+/// How long [`Warmup::new`] waits for the revision to answer.
+pub const WARMUP_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Pause after a failed warm-up request.
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// Timeout of one warm-up request in [`ReqwestProber`].
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Sends warm-up requests.
+///
+/// Production code uses [`ReqwestProber`]; tests may fake it.
+// Trait is consumed internally + tests only; Send bound on the trait itself suffices.
+#[allow(async_fn_in_trait)]
+pub trait HttpProber: Send + Sync {
+    /// `GET url`: the response status, or why there was none.
+    async fn get(&self, url: &str) -> Result<u16, String>;
+}
+
+/// [`HttpProber`] over `reqwest`.
+pub struct ReqwestProber {
+    client: reqwest::Client,
+    bearer_token: Option<String>,
+}
+
+impl ReqwestProber {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            bearer_token: None,
+        }
+    }
+
+    /// Send `Authorization: Bearer token` with every request, e.g. an
+    /// identity token for a service deployed with
+    /// `allow_unauthenticated = false`.
+    pub fn with_bearer_token(mut self, token: String) -> Self {
+        self.bearer_token = Some(token);
+        self
+    }
+}
+
+impl Default for ReqwestProber {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpProber for ReqwestProber {
+    async fn get(&self, url: &str) -> Result<u16, String> {
+        let mut request = self.client.get(url).timeout(REQUEST_TIMEOUT);
+        if let Some(token) = &self.bearer_token {
+            request = request.bearer_auth(token);
+        }
+        match request.send().await {
+            Ok(response) => Ok(response.status().as_u16()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+/// How many warm-up requests must succeed, and how long to try.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Warmup {
+    pub requests: u32,
+    pub timeout: Duration,
+    pub retry_delay: Duration,
+}
+
+impl Warmup {
+    /// `requests` successful requests within [`WARMUP_TIMEOUT`].
+    pub fn new(requests: u32) -> Self {
+        Self {
+            requests,
+            timeout: WARMUP_TIMEOUT,
+            retry_delay: RETRY_DELAY,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WarmupError {
+    #[error(
+        "new revision answered {succeeded} of {wanted} warm-up request(s) to {url} within {}s \
+         (last: {last}); traffic stays on the previous revision",
+        timeout.as_secs()
+    )]
+    TimedOut {
+        url: String,
+        succeeded: u32,
+        wanted: u32,
+        timeout: Duration,
+        last: String,
+    },
+}
+
+/// Send `GET url` one request at a time until `warmup.requests` have
+/// returned a 2xx status. A failed request (error or non-2xx) is reported
+/// to `on_retry` and retried after `warmup.retry_delay`.
+pub async fn warm_up<P: HttpProber>(
+    prober: &P,
+    url: &str,
+    warmup: &Warmup,
+    mut on_retry: impl FnMut(&str),
+) -> Result<(), WarmupError> {
+    let deadline = tokio::time::Instant::now() + warmup.timeout;
+    let mut succeeded = 0;
+    let mut last = String::from("no response yet");
+
+    while succeeded < warmup.requests {
+        let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+        if remaining.is_zero() {
+            return Err(WarmupError::TimedOut {
+                url: url.to_owned(),
+                succeeded,
+                wanted: warmup.requests,
+                timeout: warmup.timeout,
+                last,
+            });
+        }
+        let outcome = match tokio::time::timeout(remaining, prober.get(url)).await {
+            Ok(Ok(status)) if (200..300).contains(&status) => {
+                succeeded += 1;
+                continue;
+            }
+            Ok(Ok(status)) => format!("HTTP {status}"),
+            Ok(Err(e)) => e,
+            Err(_) => String::from("request still pending at the deadline"),
+        };
+        on_retry(&outcome);
+        last = outcome;
+        tokio::time::sleep(warmup.retry_delay.min(remaining)).await;
+    }
+    Ok(())
+}
+
+/// `gcloud run services describe --format json`, reduced to the traffic
+/// targets.
+#[derive(Debug, Deserialize)]
+struct ServiceTraffic {
+    #[serde(default)]
+    status: TrafficStatus,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct TrafficStatus {
+    #[serde(default)]
+    traffic: Vec<TrafficTarget>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TrafficTarget {
+    tag: Option<String>,
+    url: Option<String>,
+}
+
+/// URL of the traffic target tagged `tag` in `gcloud run services describe
+/// --format json` output, if there is one.
+pub fn tagged_revision_url(json: &str, tag: &str) -> Result<Option<String>, serde_json::Error> {
+    let service: ServiceTraffic = serde_json::from_str(json)?;
+    Ok(service
+        .status
+        .traffic
+        .into_iter()
+        .find(|target| target.tag.as_deref() == Some(tag))
+        .and_then(|target| target.url))
+}
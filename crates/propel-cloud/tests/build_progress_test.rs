@@ -0,0 +1,154 @@
+use propel_cloud::{BuildEvent, BuildProgress, BuildStage};
+
+fn events(log: &str) -> Vec<BuildEvent> {
+    let mut progress = BuildProgress::new();
+    log.lines()
+        .filter_map(|line| progress.observe(line))
+        .collect()
+}
+
+const CLASSIC_COLD: &str = "\
+Step 1/17 : FROM rust:1.85-bookworm AS chef
+ ---> 4b2f0a1c9d3e
+Step 2/17 : RUN cargo install cargo-chef --locked
+ ---> Running in 0c1d2e3f4a5b
+Step 4/17 : FROM chef AS planner
+ ---> 7a8b9c0d1e2f
+Step 6/17 : RUN cargo chef prepare --recipe-path recipe.json
+ ---> Running in 1a2b3c4d5e6f
+Step 7/17 : FROM chef AS cacher
+ ---> 7a8b9c0d1e2f
+Step 8/17 : COPY --from=planner /app/recipe.json recipe.json
+ ---> 2b3c4d5e6f7a
+Step 9/17 : RUN cargo chef cook --release --recipe-path recipe.json
+ ---> Running in 3c4d5e6f7a8b
+   Compiling serde v1.0.219
+Step 10/17 : FROM chef AS builder
+Step 13/17 : RUN cargo build --release
+ ---> Running in 4d5e6f7a8b9c
+Step 14/17 : FROM gcr.io/distroless/cc-debian12
+Step 15/17 : COPY --from=builder /app/target/release/app /app
+";
+
+#[test]
+fn classic_builder_cold_build() {
+    assert_eq!(
+        events(CLASSIC_COLD),
+        vec![
+            BuildEvent::StageStarted(BuildStage::Planner),
+            BuildEvent::StageStarted(BuildStage::Cacher),
+            BuildEvent::DependencyCache { hit: false },
+            BuildEvent::StageStarted(BuildStage::Builder),
+            BuildEvent::StageStarted(BuildStage::Runtime),
+        ]
+    );
+}
+
+#[test]
+fn classic_builder_with_cloud_build_step_prefix_and_cache_hit() {
+    let log = "\
+Step #0 - \"build\": Step 7/17 : FROM chef AS cacher
+Step #0 - \"build\": Step 9/17 : RUN cargo chef cook --release --recipe-path recipe.json
+Step #0 - \"build\":  ---> Using cache
+Step #0 - \"build\":  ---> 5e6f7a8b9c0d
+Step #0: Step 10/17 : FROM chef AS builder
+";
+    assert_eq!(
+        events(log),
+        vec![
+            BuildEvent::StageStarted(BuildStage::Cacher),
+            BuildEvent::DependencyCache { hit: true },
+            BuildEvent::StageStarted(BuildStage::Builder),
+        ]
+    );
+}
+
+#[test]
+fn buildkit_cached_dependencies() {
+    let log = "\
+#4 [chef 1/2] FROM docker.io/library/rust:1.85-bookworm@sha256:abc
+#5 [stage-4 1/2] FROM gcr.io/distroless/cc-debian12@sha256:def
+#7 [planner 2/3] COPY . .
+#7 DONE 0.2s
+#8 [planner 3/3] RUN cargo chef prepare --recipe-path recipe.json
+#9 [cacher 2/3] COPY --from=planner /app/recipe.json recipe.json
+#9 CACHED
+#10 [cacher 3/3] RUN --mount=type=secret,id=git_credentials cargo chef cook --release --recipe-path recipe.json
+#10 CACHED
+#11 [builder 3/4] COPY . .
+#12 [builder 4/4] RUN cargo build --release
+#12 3.101    Compiling app v0.1.0 (/app)
+#13 [stage-4 2/2] COPY --from=builder /app/target/release/app /app
+";
+    assert_eq!(
+        events(log),
+        vec![
+            BuildEvent::StageStarted(BuildStage::Planner),
+            BuildEvent::StageStarted(BuildStage::Cacher),
+            BuildEvent::DependencyCache { hit: true },
+            BuildEvent::StageStarted(BuildStage::Builder),
+            BuildEvent::StageStarted(BuildStage::Runtime),
+        ]
+    );
+}
+
+#[test]
+fn buildkit_cook_miss_waits_for_its_own_step() {
+    let log = "\
+#10 [cacher 3/3] RUN cargo chef cook --release --recipe-path recipe.json
+#9 DONE 0.1s
+#10 0.512    Compiling serde v1.0.219
+";
+    assert_eq!(
+        events(log),
+        vec![
+            BuildEvent::StageStarted(BuildStage::Cacher),
+            BuildEvent::DependencyCache { hit: false },
+        ]
+    );
+}
+
+#[test]
+fn ejected_single_stage_build_only_reports_runtime() {
+    let log = "\
+Creating temporary archive of 42 file(s) totalling 1.2 MiB before compression.
+Uploading tarball of [.] to [gs://proj_cloudbuild/source/123.tgz]
+Step #0: Already have image (with digest): gcr.io/cloud-builders/docker
+Step 1/3 : FROM debian:bookworm-slim
+ ---> Using cache
+DONE
+";
+    // gcloud chatter and `Step #0:` fetch lines are not build steps.
+    assert_eq!(
+        events(log),
+        vec![BuildEvent::StageStarted(BuildStage::Runtime)]
+    );
+}
+
+#[test]
+fn stages_are_announced_once() {
+    let log = "\
+#7 [planner 2/3] COPY . .
+#8 [planner 3/3] RUN cargo chef prepare --recipe-path recipe.json
+";
+    assert_eq!(
+        events(log),
+        vec![BuildEvent::StageStarted(BuildStage::Planner)]
+    );
+}
+
+#[test]
+fn event_messages() {
+    assert_eq!(
+        BuildEvent::StageStarted(BuildStage::Cacher).to_string(),
+        "Stage 2/4: building dependencies (cargo chef cook)"
+    );
+    assert_eq!(
+        BuildEvent::DependencyCache { hit: true }.to_string(),
+        "Dependencies: cached layer hit"
+    );
+    assert_eq!(
+        BuildEvent::DependencyCache { hit: false }.to_string(),
+        "Dependencies: cached layer miss — rebuilding dependencies"
+    );
+}
@@ -0,0 +1,67 @@
+use propel_cloud::canary::{CanaryState, parse_canary_state};
+
+#[test]
+fn no_canary_tag_means_no_canary() {
+    let json = r#"{"status": {
+        "latestReadyRevisionName": "svc-00001-abc",
+        "traffic": [{"percent": 100, "latestRevision": true}]
+    }}"#;
+
+    assert_eq!(parse_canary_state(json).unwrap(), None);
+}
+
+#[test]
+fn canary_share_and_stable_revision_are_read_from_traffic() {
+    let json = r#"{"status": {
+        "latestReadyRevisionName": "svc-00002-def",
+        "traffic": [
+            {"percent": 90, "revisionName": "svc-00001-abc"},
+            {"percent": 10, "revisionName": "svc-00002-def"},
+            {"revisionName": "svc-00002-def", "tag": "canary",
+             "url": "https://canary---svc-xyz.a.run.app"}
+        ]
+    }}"#;
+
+    assert_eq!(
+        parse_canary_state(json).unwrap(),
+        Some(CanaryState {
+            revision: "svc-00002-def".to_owned(),
+            percent: 10,
+            url: Some("https://canary---svc-xyz.a.run.app".to_owned()),
+            stable_revision: Some("svc-00001-abc".to_owned()),
+            latest: true,
+        })
+    );
+}
+
+#[test]
+fn tag_following_the_latest_revision_resolves_to_it() {
+    let json = r#"{"status": {
+        "latestReadyRevisionName": "svc-00003-ghi",
+        "traffic": [
+            {"percent": 75, "revisionName": "svc-00002-def"},
+            {"percent": 25, "latestRevision": true, "tag": "canary"}
+        ]
+    }}"#;
+
+    let canary = parse_canary_state(json).unwrap().unwrap();
+    assert_eq!(canary.revision, "svc-00003-ghi");
+    assert_eq!(canary.percent, 25);
+    assert_eq!(canary.stable_revision.as_deref(), Some("svc-00002-def"));
+    assert!(canary.latest);
+}
+
+#[test]
+fn canary_older_than_the_latest_revision_is_not_latest() {
+    let json = r#"{"status": {
+        "latestReadyRevisionName": "svc-00003-ghi",
+        "traffic": [
+            {"percent": 100, "revisionName": "svc-00002-def", "tag": "canary"}
+        ]
+    }}"#;
+
+    let canary = parse_canary_state(json).unwrap().unwrap();
+    assert_eq!(canary.percent, 100);
+    assert_eq!(canary.stable_revision, None);
+    assert!(!canary.latest);
+}
@@ -1,11 +1,17 @@
 use mockall::mock;
 use propel_cloud::client::{
-    CloudBuildError, DeployError, GcloudClient, PreflightError, SecretError, WifError,
+    BuildOutput, CloudBuildError, DeployError, GcloudClient, PreflightError, RunCommandGroup,
+    SecretError, WifError,
 };
 use propel_cloud::executor::GcloudExecutor;
 use propel_cloud::gcloud::GcloudError;
-use propel_core::CloudRunConfig;
-use std::path::PathBuf;
+use propel_cloud::labels::PropelLabels;
+use propel_cloud::secrets::SecretVersionState;
+use propel_core::{
+    CloudBuildConfig, CloudRunConfig, ProbeConfig, SecretBinding, SecretNamespace, SecretVersions,
+};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 mock! {
     Executor {}
@@ -13,6 +19,7 @@ mock! {
     impl GcloudExecutor for Executor {
         async fn exec(&self, args: &[String]) -> Result<String, GcloudError>;
         async fn exec_streaming(&self, args: &[String]) -> Result<(), GcloudError>;
+        async fn exec_tee(&self, args: &[String], log_path: &Path) -> Result<(), GcloudError>;
         async fn exec_with_stdin(
             &self,
             args: &[String],
@@ -21,6 +28,10 @@ mock! {
     }
 }
 
+fn labels() -> PropelLabels {
+    PropelLabels::new("0.4.3", "0123456789ab")
+}
+
 // ── Preflight Tests ──
 
 #[tokio::test]
@@ -40,9 +51,16 @@ async fn preflight_all_checks_pass() {
     // project describe
     mock.expect_exec()
         .withf(|args| {
-            args.contains(&"describe".to_owned()) && args.contains(&"projects".to_owned())
+            args.contains(&"describe".to_owned())
+                && args.contains(&"projects".to_owned())
+                && !args.contains(&"billing".to_owned())
         })
-        .returning(|_| Ok("my-project-name\n".to_owned()));
+        .returning(|_| Ok("my-project-name\t123456789012\n".to_owned()));
+
+    // billing
+    mock.expect_exec()
+        .withf(|args| args.contains(&"billing".to_owned()))
+        .returning(|_| Ok("True\n".to_owned()));
 
     // services list (3 API checks)
     mock.expect_exec()
@@ -65,6 +83,8 @@ async fn preflight_all_checks_pass() {
     assert_eq!(report.gcloud_version.as_deref(), Some("495.0.0"));
     assert!(report.authenticated);
     assert_eq!(report.project_name.as_deref(), Some("my-project-name"));
+    assert_eq!(report.project_number.as_deref(), Some("123456789012"));
+    assert_eq!(report.billing_enabled, Some(true));
     assert!(report.disabled_apis.is_empty());
     assert!(!report.has_warnings());
 }
@@ -106,12 +126,312 @@ async fn preflight_not_authenticated() {
             })
         });
 
+    // no credentialed account
+    expect_credentialed_account(&mut mock, "");
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client.check_prerequisites("test-project").await;
+
+    assert!(matches!(result, Err(PreflightError::NotAuthenticated)));
+}
+
+#[tokio::test]
+async fn preflight_active_account_without_token() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| args.contains(&"version".to_owned()))
+        .returning(|_| Ok("495.0.0\n".to_owned()));
+    mock.expect_exec()
+        .withf(|args| args.contains(&"print-access-token".to_owned()))
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "Reauthentication failed.".to_owned(),
+            })
+        });
+    expect_credentialed_account(&mut mock, "alice@corp.com\n");
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client.check_prerequisites("test-project").await;
+
+    assert!(matches!(
+        result,
+        Err(PreflightError::TokenUnavailable { ref account, .. }) if account == "alice@corp.com"
+    ));
+}
+
+#[tokio::test]
+async fn preflight_service_account_without_token_skips_key_file() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| args.contains(&"version".to_owned()))
+        .returning(|_| Ok("495.0.0\n".to_owned()));
+    mock.expect_exec()
+        .withf(|args| args.contains(&"print-access-token".to_owned()))
+        .times(1)
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "invalid_grant: account not found".to_owned(),
+            })
+        });
+    expect_credentialed_account(&mut mock, "ci@proj.iam.gserviceaccount.com\n");
+    mock.expect_exec()
+        .withf(|args| args.contains(&"activate-service-account".to_owned()))
+        .never();
+
+    let client =
+        GcloudClient::with_executor(mock).key_file_fallback(PathBuf::from("/keys/sa.json"));
+    let error = client
+        .check_prerequisites("test-project")
+        .await
+        .unwrap_err();
+
+    let PreflightError::TokenUnavailable { account, source } = &error else {
+        panic!("expected TokenUnavailable, got {error:?}");
+    };
+    assert_eq!(account, "ci@proj.iam.gserviceaccount.com");
+    assert!(source.to_string().contains("invalid_grant"));
+}
+
+#[tokio::test]
+async fn preflight_activated_key_file_without_token() {
+    let mut mock = MockExecutor::new();
+    let mut seq = mockall::Sequence::new();
+
+    mock.expect_exec()
+        .withf(|args| args.contains(&"version".to_owned()))
+        .returning(|_| Ok("495.0.0\n".to_owned()));
+    mock.expect_exec()
+        .withf(|args| args.contains(&"print-access-token".to_owned()))
+        .times(1)
+        .in_sequence(&mut seq)
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "no active account".to_owned(),
+            })
+        });
+    expect_credentialed_account_in(&mut mock, &mut seq, "");
+    mock.expect_exec()
+        .withf(|args| args.contains(&"activate-service-account".to_owned()))
+        .times(1)
+        .in_sequence(&mut seq)
+        .returning(|_| Ok(String::new()));
+    mock.expect_exec()
+        .withf(|args| args.contains(&"print-access-token".to_owned()))
+        .times(1)
+        .in_sequence(&mut seq)
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "service account is disabled".to_owned(),
+            })
+        });
+    expect_credentialed_account_in(&mut mock, &mut seq, "ci@proj.iam.gserviceaccount.com\n");
+
+    let client =
+        GcloudClient::with_executor(mock).key_file_fallback(PathBuf::from("/keys/sa.json"));
+    let result = client.check_prerequisites("test-project").await;
+
+    assert!(matches!(
+        result,
+        Err(PreflightError::TokenUnavailable { ref account, .. })
+            if account == "ci@proj.iam.gserviceaccount.com"
+    ));
+}
+
+#[tokio::test]
+async fn preflight_unlistable_accounts_report_not_authenticated() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| args.contains(&"version".to_owned()))
+        .returning(|_| Ok("495.0.0\n".to_owned()));
+    mock.expect_exec()
+        .withf(|args| args.contains(&"print-access-token".to_owned()))
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "not logged in".to_owned(),
+            })
+        });
+    mock.expect_exec()
+        .withf(|args| args.starts_with(&["auth".to_owned(), "list".to_owned()]))
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "config is corrupt".to_owned(),
+            })
+        });
+
     let client = GcloudClient::with_executor(mock);
     let result = client.check_prerequisites("test-project").await;
 
     assert!(matches!(result, Err(PreflightError::NotAuthenticated)));
 }
 
+/// Mock `gcloud auth list` for the active credentialed account.
+fn expect_credentialed_account(mock: &mut MockExecutor, output: &'static str) {
+    mock.expect_exec()
+        .withf(is_credentialed_account_query)
+        .returning(move |_| Ok(output.to_owned()));
+}
+
+/// [`expect_credentialed_account`], once, as the next call in `seq`.
+fn expect_credentialed_account_in(
+    mock: &mut MockExecutor,
+    seq: &mut mockall::Sequence,
+    output: &'static str,
+) {
+    mock.expect_exec()
+        .withf(is_credentialed_account_query)
+        .times(1)
+        .in_sequence(seq)
+        .returning(move |_| Ok(output.to_owned()));
+}
+
+fn is_credentialed_account_query(args: &[String]) -> bool {
+    args.starts_with(&["auth".to_owned(), "list".to_owned()])
+        && args.contains(&"--filter=status:ACTIVE".to_owned())
+}
+
+/// Mock the preflight checks after authentication as passing.
+fn expect_preflight_after_auth(mock: &mut MockExecutor) {
+    mock.expect_exec()
+        .withf(|args| {
+            args.contains(&"describe".to_owned())
+                && args.contains(&"projects".to_owned())
+                && !args.contains(&"billing".to_owned())
+        })
+        .returning(|_| Ok("my-project-name\n".to_owned()));
+    mock.expect_exec()
+        .withf(|args| args.contains(&"billing".to_owned()))
+        .returning(|_| Ok("True\n".to_owned()));
+    mock.expect_exec()
+        .withf(|args| args.contains(&"services".to_owned()) && args.contains(&"list".to_owned()))
+        .returning(|args| {
+            let api = args
+                .iter()
+                .find_map(|a| a.strip_prefix("config.name="))
+                .unwrap();
+            Ok(format!("{api}\n"))
+        });
+}
+
+#[tokio::test]
+async fn preflight_activates_key_file_when_not_authenticated() {
+    let mut mock = MockExecutor::new();
+    let mut seq = mockall::Sequence::new();
+
+    mock.expect_exec()
+        .withf(|args| args.contains(&"version".to_owned()))
+        .returning(|_| Ok("495.0.0\n".to_owned()));
+    mock.expect_exec()
+        .withf(|args| args.contains(&"print-access-token".to_owned()))
+        .times(1)
+        .in_sequence(&mut seq)
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "no active account".to_owned(),
+            })
+        });
+    expect_credentialed_account_in(&mut mock, &mut seq, "");
+    mock.expect_exec()
+        .withf(|args| {
+            args.starts_with(&["auth".to_owned(), "activate-service-account".to_owned()])
+                && args.contains(&"--key-file=/keys/sa.json".to_owned())
+        })
+        .times(1)
+        .in_sequence(&mut seq)
+        .returning(|_| Ok(String::new()));
+    mock.expect_exec()
+        .withf(|args| args.contains(&"print-access-token".to_owned()))
+        .times(1)
+        .in_sequence(&mut seq)
+        .returning(|_| Ok("ya29.token\n".to_owned()));
+    expect_preflight_after_auth(&mut mock);
+
+    let client =
+        GcloudClient::with_executor(mock).key_file_fallback(PathBuf::from("/keys/sa.json"));
+    let report = client.check_prerequisites("test-project").await.unwrap();
+
+    assert!(report.authenticated);
+    assert_eq!(
+        report.activated_key_file.as_deref(),
+        Some(std::path::Path::new("/keys/sa.json"))
+    );
+}
+
+#[tokio::test]
+async fn preflight_skips_activation_when_already_authenticated() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| args.contains(&"version".to_owned()))
+        .returning(|_| Ok("495.0.0\n".to_owned()));
+    mock.expect_exec()
+        .withf(|args| args.contains(&"print-access-token".to_owned()))
+        .times(1)
+        .returning(|_| Ok("ya29.token\n".to_owned()));
+    mock.expect_exec()
+        .withf(|args| args.contains(&"activate-service-account".to_owned()))
+        .never();
+    expect_preflight_after_auth(&mut mock);
+
+    let client =
+        GcloudClient::with_executor(mock).key_file_fallback(PathBuf::from("/keys/sa.json"));
+    let report = client.check_prerequisites("test-project").await.unwrap();
+
+    assert!(report.activated_key_file.is_none());
+}
+
+#[tokio::test]
+async fn preflight_key_file_activation_failure() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| args.contains(&"version".to_owned()))
+        .returning(|_| Ok("495.0.0\n".to_owned()));
+    mock.expect_exec()
+        .withf(|args| args.contains(&"print-access-token".to_owned()))
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "no active account".to_owned(),
+            })
+        });
+    expect_credentialed_account(&mut mock, "");
+    mock.expect_exec()
+        .withf(|args| args.contains(&"activate-service-account".to_owned()))
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "invalid key file".to_owned(),
+            })
+        });
+
+    let client =
+        GcloudClient::with_executor(mock).key_file_fallback(PathBuf::from("/keys/sa.json"));
+    let result = client.check_prerequisites("test-project").await;
+
+    assert!(matches!(
+        result,
+        Err(PreflightError::KeyFileActivation { ref key_file, .. }) if key_file == std::path::Path::new("/keys/sa.json")
+    ));
+}
+
+#[test]
+fn not_authenticated_message_covers_login_and_key_file() {
+    let msg = PreflightError::NotAuthenticated.to_string();
+    assert!(msg.contains("gcloud auth login"));
+    assert!(msg.contains("gcloud auth activate-service-account --key-file"));
+    assert!(msg.contains("GOOGLE_APPLICATION_CREDENTIALS"));
+}
+
 #[tokio::test]
 async fn preflight_project_not_accessible() {
     let mut mock = MockExecutor::new();
@@ -158,10 +478,17 @@ async fn preflight_disabled_apis_reported() {
 
     mock.expect_exec()
         .withf(|args| {
-            args.contains(&"describe".to_owned()) && args.contains(&"projects".to_owned())
+            args.contains(&"describe".to_owned())
+                && args.contains(&"projects".to_owned())
+                && !args.contains(&"billing".to_owned())
         })
         .returning(|_| Ok("my-project\n".to_owned()));
 
+    // billing
+    mock.expect_exec()
+        .withf(|args| args.contains(&"billing".to_owned()))
+        .returning(|_| Ok("True\n".to_owned()));
+
     // All API checks return empty (disabled)
     mock.expect_exec()
         .withf(|args| args.contains(&"services".to_owned()) && args.contains(&"list".to_owned()))
@@ -171,7 +498,7 @@ async fn preflight_disabled_apis_reported() {
     let report = client.check_prerequisites("test-project").await.unwrap();
 
     assert!(report.has_warnings());
-    assert_eq!(report.disabled_apis.len(), 3);
+    assert_eq!(report.disabled_apis.len(), 4);
     assert!(
         report
             .disabled_apis
@@ -182,6 +509,11 @@ async fn preflight_disabled_apis_reported() {
             .disabled_apis
             .contains(&"run.googleapis.com".to_owned())
     );
+    assert!(
+        report
+            .disabled_apis
+            .contains(&"artifactregistry.googleapis.com".to_owned())
+    );
     assert!(
         report
             .disabled_apis
@@ -204,10 +536,17 @@ async fn preflight_api_check_gcloud_failure_propagates_error() {
 
     mock.expect_exec()
         .withf(|args| {
-            args.contains(&"describe".to_owned()) && args.contains(&"projects".to_owned())
+            args.contains(&"describe".to_owned())
+                && args.contains(&"projects".to_owned())
+                && !args.contains(&"billing".to_owned())
         })
         .returning(|_| Ok("my-project\n".to_owned()));
 
+    // billing
+    mock.expect_exec()
+        .withf(|args| args.contains(&"billing".to_owned()))
+        .returning(|_| Ok("True\n".to_owned()));
+
     // API check: gcloud command itself fails (network error, auth expired, etc.)
     mock.expect_exec()
         .withf(|args| args.contains(&"services".to_owned()) && args.contains(&"list".to_owned()))
@@ -228,335 +567,511 @@ async fn preflight_api_check_gcloud_failure_propagates_error() {
     );
 }
 
-// ── Cloud Build Tests ──
-
 #[tokio::test]
-async fn submit_build_success() {
+async fn preflight_billing_disabled() {
     let mut mock = MockExecutor::new();
 
-    mock.expect_exec_streaming()
+    mock.expect_exec()
+        .withf(|args| args.contains(&"version".to_owned()))
+        .returning(|_| Ok("495.0.0\n".to_owned()));
+
+    mock.expect_exec()
+        .withf(|args| args.contains(&"print-access-token".to_owned()))
+        .returning(|_| Ok("ya29.token\n".to_owned()));
+
+    mock.expect_exec()
         .withf(|args| {
-            args.contains(&"builds".to_owned())
-                && args.contains(&"submit".to_owned())
-                && args.contains(&"--tag".to_owned())
+            args.contains(&"describe".to_owned())
+                && args.contains(&"projects".to_owned())
+                && !args.contains(&"billing".to_owned())
         })
-        .returning(|_| Ok(()));
+        .returning(|_| Ok("my-project\n".to_owned()));
+
+    mock.expect_exec()
+        .withf(|args| args.contains(&"billing".to_owned()))
+        .returning(|_| Ok("False\n".to_owned()));
+
+    // API checks must not run once billing is known to be disabled
+    mock.expect_exec()
+        .withf(|args| args.contains(&"services".to_owned()))
+        .never();
 
     let client = GcloudClient::with_executor(mock);
-    let result = client
-        .submit_build(
-            &PathBuf::from("/tmp/bundle"),
-            "my-project",
-            "gcr.io/my-project/my-service:latest",
-        )
-        .await;
+    let result = client.check_prerequisites("no-billing").await;
 
-    assert!(result.is_ok());
+    assert!(matches!(
+        result,
+        Err(PreflightError::BillingDisabled(ref p)) if p == "no-billing"
+    ));
+    let msg = result.unwrap_err().to_string();
+    assert!(
+        msg.contains("console.cloud.google.com/billing"),
+        "got: {msg}"
+    );
 }
 
 #[tokio::test]
-async fn submit_build_failure() {
+async fn preflight_billing_query_failure_is_not_fatal() {
     let mut mock = MockExecutor::new();
 
-    mock.expect_exec_streaming().returning(|_| {
-        Err(GcloudError::CommandFailed {
-            args: vec![],
-            stderr: "build failed".to_owned(),
-        })
-    });
-
-    let client = GcloudClient::with_executor(mock);
-    let result = client
-        .submit_build(&PathBuf::from("/tmp/bundle"), "proj", "tag")
-        .await;
+    mock.expect_exec()
+        .withf(|args| args.contains(&"version".to_owned()))
+        .returning(|_| Ok("495.0.0\n".to_owned()));
 
-    assert!(matches!(result, Err(CloudBuildError::Submit { .. })));
+    mock.expect_exec()
+        .withf(|args| args.contains(&"print-access-token".to_owned()))
+        .returning(|_| Ok("ya29.token\n".to_owned()));
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.contains(&"describe".to_owned())
+                && args.contains(&"projects".to_owned())
+                && !args.contains(&"billing".to_owned())
+        })
+        .returning(|_| Ok("my-project\n".to_owned()));
+
+    mock.expect_exec()
+        .withf(|args| args.contains(&"billing".to_owned()))
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "PERMISSION_DENIED".to_owned(),
+            })
+        });
+
+    mock.expect_exec()
+        .withf(|args| args.contains(&"services".to_owned()) && args.contains(&"list".to_owned()))
+        .returning(|args| {
+            let filter_arg = args.iter().find(|a| a.starts_with("config.name="));
+            Ok(filter_arg
+                .and_then(|f| f.strip_prefix("config.name="))
+                .unwrap_or("")
+                .to_owned())
+        });
+
+    let client = GcloudClient::with_executor(mock);
+    let report = client.check_prerequisites("test-project").await.unwrap();
+
+    assert!(report.billing_enabled.is_none());
+    assert!(!report.has_warnings());
 }
 
-#[tokio::test]
-async fn submit_build_captured_returns_output() {
+// ── API Enablement Wait Tests ──
+
+#[tokio::test(start_paused = true)]
+async fn wait_for_api_enablement_succeeds_after_propagation() {
     let mut mock = MockExecutor::new();
 
+    // Disabled for the first two polls, enabled on the third.
+    let mut calls = 0;
     mock.expect_exec()
-        .withf(|args| {
-            args.contains(&"builds".to_owned())
-                && args.contains(&"submit".to_owned())
-                && args.contains(&"--tag".to_owned())
-        })
-        .returning(|_| Ok("BUILD OK\nImage: gcr.io/proj/svc:latest\n".to_owned()));
+        .withf(|args| args.contains(&"services".to_owned()) && args.contains(&"list".to_owned()))
+        .times(3)
+        .returning(move |_| {
+            calls += 1;
+            if calls < 3 {
+                Ok(String::new())
+            } else {
+                Ok("run.googleapis.com\n".to_owned())
+            }
+        });
 
     let client = GcloudClient::with_executor(mock);
-    let output = client
-        .submit_build_captured(
-            &PathBuf::from("/tmp/bundle"),
-            "my-project",
-            "gcr.io/my-project/my-service:latest",
+    let mut waits = Vec::new();
+    let result = client
+        .wait_for_api_enablement(
+            "proj",
+            &["run.googleapis.com".to_owned()],
+            Duration::from_secs(90),
+            |apis, elapsed| waits.push((apis.to_vec(), elapsed)),
         )
-        .await
-        .unwrap();
+        .await;
 
-    assert!(output.contains("BUILD OK"));
+    assert!(result.is_ok());
+    assert_eq!(waits.len(), 2);
+    assert_eq!(waits[0].0, vec!["run.googleapis.com".to_owned()]);
+    // Backoff doubles: first wait at 0s, second after the 2s sleep.
+    assert_eq!(waits[0].1, Duration::ZERO);
+    assert_eq!(waits[1].1, Duration::from_secs(2));
 }
 
-#[tokio::test]
-async fn submit_build_captured_failure() {
+#[tokio::test(start_paused = true)]
+async fn wait_for_api_enablement_times_out() {
     let mut mock = MockExecutor::new();
 
-    mock.expect_exec().returning(|_| {
-        Err(GcloudError::CommandFailed {
-            args: vec![],
-            stderr: "build failed".to_owned(),
-        })
-    });
+    mock.expect_exec()
+        .withf(|args| args.contains(&"services".to_owned()) && args.contains(&"list".to_owned()))
+        .returning(|_| Ok(String::new()));
 
     let client = GcloudClient::with_executor(mock);
     let result = client
-        .submit_build_captured(&PathBuf::from("/tmp/bundle"), "proj", "tag")
+        .wait_for_api_enablement(
+            "proj",
+            &["run.googleapis.com".to_owned()],
+            Duration::from_secs(90),
+            |_, _| {},
+        )
         .await;
 
-    assert!(matches!(result, Err(CloudBuildError::Submit { .. })));
+    match result {
+        Err(PreflightError::ApisNotEnabled { apis, project_id }) => {
+            assert_eq!(apis, vec!["run.googleapis.com".to_owned()]);
+            assert_eq!(project_id, "proj");
+        }
+        other => panic!("expected ApisNotEnabled, got: {other:?}"),
+    }
 }
 
-// ── Cloud Run Deploy Tests ──
-
 #[tokio::test]
-async fn deploy_to_cloud_run_returns_url() {
+async fn pending_api_enablements_returns_only_enabled_state() {
     let mut mock = MockExecutor::new();
 
     mock.expect_exec()
-        .withf(|args| args.contains(&"run".to_owned()) && args.contains(&"deploy".to_owned()))
-        .returning(|_| Ok("https://my-service-abc123-uc.a.run.app\n".to_owned()));
+        .withf(|args| args.contains(&"--available".to_owned()))
+        .returning(|args| {
+            if args.contains(&"config.name=run.googleapis.com".to_owned()) {
+                Ok("ENABLED\n".to_owned())
+            } else {
+                Ok("DISABLED\n".to_owned())
+            }
+        });
 
     let client = GcloudClient::with_executor(mock);
-    let url = client
-        .deploy_to_cloud_run(
-            "my-service",
-            "gcr.io/proj/svc:latest",
+    let pending = client
+        .pending_api_enablements(
             "proj",
-            "us-central1",
-            &CloudRunConfig::default(),
-            &[],
+            &[
+                "run.googleapis.com".to_owned(),
+                "cloudbuild.googleapis.com".to_owned(),
+            ],
         )
         .await
         .unwrap();
 
-    assert_eq!(url, "https://my-service-abc123-uc.a.run.app");
+    assert_eq!(pending, vec!["run.googleapis.com".to_owned()]);
 }
 
 #[tokio::test]
-async fn deploy_to_cloud_run_failure() {
+async fn disabled_preflight_apis_lists_the_disabled_ones() {
     let mut mock = MockExecutor::new();
-
     mock.expect_exec()
-        .withf(|args| args.contains(&"run".to_owned()) && args.contains(&"deploy".to_owned()))
-        .returning(|_| {
-            Err(GcloudError::CommandFailed {
-                args: vec![],
-                stderr: "permission denied".to_owned(),
-            })
+        .withf(|args| args.contains(&"services".to_owned()) && args.contains(&"list".to_owned()))
+        .returning(|args| {
+            if args.contains(&"config.name=run.googleapis.com".to_owned()) {
+                Ok(String::new())
+            } else {
+                let api = args
+                    .iter()
+                    .find_map(|a| a.strip_prefix("config.name="))
+                    .unwrap();
+                Ok(format!("{api}\n"))
+            }
         });
 
     let client = GcloudClient::with_executor(mock);
-    let result = client
-        .deploy_to_cloud_run(
-            "svc",
-            "tag",
-            "proj",
-            "us-central1",
-            &CloudRunConfig::default(),
-            &[],
-        )
-        .await;
+    let disabled = client.disabled_preflight_apis("proj").await.unwrap();
 
-    assert!(matches!(result, Err(DeployError::Deploy { .. })));
+    assert_eq!(disabled, vec!["run.googleapis.com".to_owned()]);
 }
 
 #[tokio::test]
-async fn deploy_to_cloud_run_with_secrets() {
+async fn enable_apis_passes_every_api_to_one_command() {
     let mut mock = MockExecutor::new();
-
     mock.expect_exec()
-        .withf(|args| {
-            args.contains(&"--update-secrets".to_owned())
-                && args
-                    .contains(&"SUPABASE_URL=SUPABASE_URL:latest,API_KEY=API_KEY:latest".to_owned())
+        .withf(|args: &[String]| {
+            args == [
+                "services",
+                "enable",
+                "run.googleapis.com",
+                "cloudbuild.googleapis.com",
+                "--project",
+                "proj",
+                "--quiet",
+            ]
         })
-        .returning(|_| Ok("https://svc-abc123-uc.a.run.app\n".to_owned()));
+        .times(1)
+        .returning(|_| Ok(String::new()));
 
     let client = GcloudClient::with_executor(mock);
-    let secrets = vec!["SUPABASE_URL".to_owned(), "API_KEY".to_owned()];
-    let url = client
-        .deploy_to_cloud_run(
-            "svc",
-            "gcr.io/proj/svc:latest",
+    client
+        .enable_apis(
             "proj",
-            "us-central1",
-            &CloudRunConfig::default(),
-            &secrets,
+            &[
+                "run.googleapis.com".to_owned(),
+                "cloudbuild.googleapis.com".to_owned(),
+            ],
         )
         .await
         .unwrap();
-
-    assert_eq!(url, "https://svc-abc123-uc.a.run.app");
 }
 
-// ── Secret Manager Tests ──
-
 #[tokio::test]
-async fn set_secret_creates_new_secret() {
+async fn enable_apis_failure_names_the_permission() {
     let mut mock = MockExecutor::new();
+    mock.expect_exec().returning(|_| {
+        Err(GcloudError::CommandFailed {
+            args: vec![],
+            stderr: "PERMISSION_DENIED".to_owned(),
+        })
+    });
 
-    // describe → not found (secret doesn't exist)
-    mock.expect_exec()
-        .withf(|args| args.contains(&"describe".to_owned()) && args.contains(&"secrets".to_owned()))
-        .returning(|_| {
-            Err(GcloudError::CommandFailed {
-                args: vec![],
-                stderr: "NOT_FOUND".to_owned(),
-            })
-        });
+    let client = GcloudClient::with_executor(mock);
+    let err = client
+        .enable_apis("proj", &["run.googleapis.com".to_owned()])
+        .await
+        .unwrap_err();
 
-    // create
-    mock.expect_exec()
-        .withf(|args| args.contains(&"create".to_owned()) && args.contains(&"secrets".to_owned()))
-        .returning(|_| Ok(String::new()));
+    assert!(matches!(err, PreflightError::ApiEnablement { .. }));
+    assert!(err.to_string().contains("serviceusage.services.enable"));
+}
 
-    // versions add
-    mock.expect_exec_with_stdin()
-        .withf(|args, data| {
-            args.contains(&"versions".to_owned())
-                && args.contains(&"add".to_owned())
-                && data == b"super-secret-value"
+#[test]
+fn apis_not_enabled_error_includes_remediation() {
+    let err = PreflightError::ApisNotEnabled {
+        project_id: "proj".to_owned(),
+        apis: vec![
+            "run.googleapis.com".to_owned(),
+            "cloudbuild.googleapis.com".to_owned(),
+        ],
+    };
+    let msg = err.to_string();
+    assert!(msg.contains(
+        "gcloud services enable run.googleapis.com cloudbuild.googleapis.com --project proj"
+    ));
+    assert!(msg.contains("propagation"));
+}
+
+#[test]
+fn missing_git_credentials_error_names_dependencies_and_config() {
+    let err = PreflightError::MissingGitCredentials {
+        dependencies: vec![
+            "internal-auth (git+ssh://git@github.com/acme/internal-auth.git)".to_owned(),
+        ],
+    };
+    let msg = err.to_string();
+    assert!(msg.contains("internal-auth"));
+    assert!(msg.contains("git_credentials_secret = \"GIT_CREDENTIALS\""));
+    assert!(msg.contains("propel secret set GIT_CREDENTIALS="));
+}
+
+// ── Cloud Build Tests ──
+
+#[tokio::test]
+async fn submit_build_success() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec_tee()
+        .withf(|args, log| {
+            log == Path::new(".propel/last-build.log")
+                && args.contains(&"builds".to_owned())
+                && args.contains(&"submit".to_owned())
+                && args.contains(&"--tag".to_owned())
         })
-        .returning(|_, _| Ok(String::new()));
+        .returning(|_, _| Ok(()));
 
     let client = GcloudClient::with_executor(mock);
     let result = client
-        .set_secret("proj", "MY_SECRET", "super-secret-value")
+        .submit_build(
+            &PathBuf::from("/tmp/bundle"),
+            "my-project",
+            "gcr.io/my-project/my-service:latest",
+            &CloudBuildConfig::default(),
+            None,
+            &BuildOutput::Tee(PathBuf::from(".propel/last-build.log")),
+        )
         .await;
 
     assert!(result.is_ok());
 }
 
 #[tokio::test]
-async fn set_secret_updates_existing() {
+async fn submit_build_with_config_uses_config_instead_of_tag() {
     let mut mock = MockExecutor::new();
 
-    // describe → exists
-    mock.expect_exec()
-        .withf(|args| args.contains(&"describe".to_owned()) && args.contains(&"secrets".to_owned()))
-        .returning(|_| Ok("secret exists".to_owned()));
-
-    // No create call expected — goes straight to versions add
-    mock.expect_exec_with_stdin()
-        .withf(|args, _| args.contains(&"versions".to_owned()) && args.contains(&"add".to_owned()))
-        .returning(|_, _| Ok(String::new()));
+    mock.expect_exec_tee()
+        .withf(|args, log| {
+            log == Path::new(".propel/last-build.log")
+                && args
+                    .windows(2)
+                    .any(|w| w[0] == "--config" && w[1] == "/tmp/bundle/cloudbuild.yaml")
+                && !args.contains(&"--tag".to_owned())
+        })
+        .returning(|_, _| Ok(()));
 
     let client = GcloudClient::with_executor(mock);
-    let result = client.set_secret("proj", "EXISTING", "new-value").await;
+    let result = client
+        .submit_build(
+            &PathBuf::from("/tmp/bundle"),
+            "my-project",
+            "gcr.io/my-project/my-service:latest",
+            &CloudBuildConfig::default(),
+            Some(&PathBuf::from("/tmp/bundle/cloudbuild.yaml")),
+            &BuildOutput::Tee(PathBuf::from(".propel/last-build.log")),
+        )
+        .await;
 
     assert!(result.is_ok());
 }
 
 #[tokio::test]
-async fn set_secret_create_fails() {
+async fn submit_build_failure() {
     let mut mock = MockExecutor::new();
 
-    mock.expect_exec()
-        .withf(|args| args.contains(&"describe".to_owned()))
-        .returning(|_| {
-            Err(GcloudError::CommandFailed {
-                args: vec![],
-                stderr: "NOT_FOUND".to_owned(),
-            })
-        });
-
-    mock.expect_exec()
-        .withf(|args| args.contains(&"create".to_owned()))
-        .returning(|_| {
-            Err(GcloudError::CommandFailed {
-                args: vec![],
-                stderr: "permission denied".to_owned(),
-            })
-        });
+    mock.expect_exec_tee().returning(|_, _| {
+        Err(GcloudError::CommandFailed {
+            args: vec![],
+            stderr: "build failed".to_owned(),
+        })
+    });
 
     let client = GcloudClient::with_executor(mock);
-    let result = client.set_secret("proj", "SECRET", "val").await;
+    let result = client
+        .submit_build(
+            &PathBuf::from("/tmp/bundle"),
+            "proj",
+            "tag",
+            &CloudBuildConfig::default(),
+            None,
+            &BuildOutput::Tee(PathBuf::from(".propel/last-build.log")),
+        )
+        .await;
 
-    assert!(matches!(result, Err(SecretError::Create { .. })));
+    assert!(matches!(result, Err(CloudBuildError::Submit { .. })));
 }
 
 #[tokio::test]
-async fn list_secrets_returns_names() {
+async fn submit_build_capture_returns_output() {
     let mut mock = MockExecutor::new();
 
     mock.expect_exec()
         .withf(|args| {
-            args.contains(&"secrets".to_owned())
-                && args.contains(&"list".to_owned())
-                && args.contains(&"value(name)".to_owned())
+            args.contains(&"builds".to_owned())
+                && args.contains(&"submit".to_owned())
+                && args.contains(&"--tag".to_owned())
         })
-        .returning(|_| Ok("SUPABASE_URL\nSUPABASE_KEY\nJWT_SECRET\n".to_owned()));
+        .returning(|_| Ok("BUILD OK\nImage: gcr.io/proj/svc:latest\n".to_owned()));
 
+    let tmp = tempfile::TempDir::new().unwrap();
+    let log_path = tmp.path().join("last-build.log");
     let client = GcloudClient::with_executor(mock);
-    let secrets = client.list_secrets("proj").await.unwrap();
+    let output = client
+        .submit_build(
+            &PathBuf::from("/tmp/bundle"),
+            "my-project",
+            "gcr.io/my-project/my-service:latest",
+            &CloudBuildConfig::default(),
+            None,
+            &BuildOutput::Capture(log_path.clone()),
+        )
+        .await
+        .unwrap();
 
-    assert_eq!(secrets, vec!["SUPABASE_URL", "SUPABASE_KEY", "JWT_SECRET"]);
+    assert!(output.contains("BUILD OK"));
 }
 
 #[tokio::test]
-async fn list_secrets_empty() {
+async fn submit_build_capture_failure() {
     let mut mock = MockExecutor::new();
 
-    mock.expect_exec()
-        .withf(|args| args.contains(&"secrets".to_owned()) && args.contains(&"list".to_owned()))
-        .returning(|_| Ok(String::new()));
+    mock.expect_exec().returning(|_| {
+        Err(GcloudError::CommandFailed {
+            args: vec![],
+            stderr: "build failed".to_owned(),
+        })
+    });
 
+    let tmp = tempfile::TempDir::new().unwrap();
+    let log_path = tmp.path().join("last-build.log");
     let client = GcloudClient::with_executor(mock);
-    let secrets = client.list_secrets("proj").await.unwrap();
+    let result = client
+        .submit_build(
+            &PathBuf::from("/tmp/bundle"),
+            "proj",
+            "tag",
+            &CloudBuildConfig::default(),
+            None,
+            &BuildOutput::Capture(log_path.clone()),
+        )
+        .await;
 
-    assert!(secrets.is_empty());
+    assert!(matches!(result, Err(CloudBuildError::Submit { .. })));
 }
 
 #[tokio::test]
-async fn get_project_number_returns_number() {
+async fn submit_build_passes_region_and_logs_dir() {
     let mut mock = MockExecutor::new();
 
-    mock.expect_exec()
-        .withf(|args| {
-            args.contains(&"projects".to_owned())
-                && args.contains(&"describe".to_owned())
-                && args.contains(&"value(projectNumber)".to_owned())
+    mock.expect_exec_tee()
+        .withf(|args, log| {
+            log == Path::new(".propel/last-build.log")
+                && args
+                    == [
+                        "builds",
+                        "submit",
+                        "/tmp/bundle",
+                        "--project",
+                        "proj",
+                        "--tag",
+                        "tag",
+                        "--region",
+                        "europe-west1",
+                        "--gcs-log-dir",
+                        "gs://acme-logs/propel",
+                        "--quiet",
+                    ]
         })
-        .returning(|_| Ok("123456789\n".to_owned()));
+        .returning(|_, _| Ok(()));
 
     let client = GcloudClient::with_executor(mock);
-    let number = client.get_project_number("my-project").await.unwrap();
+    let settings = CloudBuildConfig {
+        region: Some("europe-west1".to_owned()),
+        logs_bucket: Some("gs://acme-logs/propel".to_owned()),
+        ..Default::default()
+    };
+    let result = client
+        .submit_build(
+            &PathBuf::from("/tmp/bundle"),
+            "proj",
+            "tag",
+            &settings,
+            None,
+            &BuildOutput::Tee(PathBuf::from(".propel/last-build.log")),
+        )
+        .await;
 
-    assert_eq!(number, "123456789");
+    assert!(result.is_ok());
 }
 
 #[tokio::test]
-async fn grant_secret_access_calls_add_iam_policy_binding() {
+async fn submit_build_omits_region_and_logs_dir_by_default() {
     let mut mock = MockExecutor::new();
 
-    mock.expect_exec()
-        .withf(|args| {
-            args.contains(&"add-iam-policy-binding".to_owned())
-                && args.contains(&"MY_SECRET".to_owned())
-                && args.contains(
-                    &"serviceAccount:123-compute@developer.gserviceaccount.com".to_owned(),
-                )
-                && args.contains(&"roles/secretmanager.secretAccessor".to_owned())
+    mock.expect_exec_tee()
+        .withf(|args, log| {
+            log == Path::new(".propel/last-build.log")
+                && args
+                    == [
+                        "builds",
+                        "submit",
+                        "/tmp/bundle",
+                        "--project",
+                        "proj",
+                        "--tag",
+                        "tag",
+                        "--quiet",
+                    ]
         })
-        .returning(|_| Ok(String::new()));
+        .returning(|_, _| Ok(()));
 
     let client = GcloudClient::with_executor(mock);
     let result = client
-        .grant_secret_access(
+        .submit_build(
+            &PathBuf::from("/tmp/bundle"),
             "proj",
-            "MY_SECRET",
-            "123-compute@developer.gserviceaccount.com",
+            "tag",
+            &CloudBuildConfig::default(),
+            None,
+            &BuildOutput::Tee(PathBuf::from(".propel/last-build.log")),
         )
         .await;
 
@@ -564,666 +1079,4185 @@ async fn grant_secret_access_calls_add_iam_policy_binding() {
 }
 
 #[tokio::test]
-async fn grant_secret_access_failure() {
+async fn submit_build_capture_classifies_logs_bucket_policy_error() {
     let mut mock = MockExecutor::new();
 
-    mock.expect_exec()
-        .withf(|args| args.contains(&"add-iam-policy-binding".to_owned()))
-        .returning(|_| {
-            Err(GcloudError::CommandFailed {
-                args: vec![],
-                stderr: "permission denied".to_owned(),
-            })
-        });
+    mock.expect_exec().returning(|_| {
+        Err(GcloudError::CommandFailed {
+            args: vec![],
+            stderr: "ERROR: (gcloud.builds.submit) The build is running, and logs are being \
+                     written to the default logs bucket. This tool can only stream logs if you \
+                     are Viewer/Owner of the project and, if applicable, allowed by your VPC-SC \
+                     security perimeter."
+                .to_owned(),
+        })
+    });
 
+    let tmp = tempfile::TempDir::new().unwrap();
+    let log_path = tmp.path().join("last-build.log");
     let client = GcloudClient::with_executor(mock);
-    let result = client
-        .grant_secret_access("proj", "SECRET", "sa@example.com")
-        .await;
+    let err = client
+        .submit_build(
+            &PathBuf::from("/tmp/bundle"),
+            "proj",
+            "tag",
+            &CloudBuildConfig::default(),
+            None,
+            &BuildOutput::Capture(log_path.clone()),
+        )
+        .await
+        .unwrap_err();
 
-    assert!(matches!(result, Err(SecretError::GrantAccess { .. })));
+    assert!(matches!(err, CloudBuildError::LogsBucketRestricted { .. }));
+    assert!(err.to_string().contains("[cloud_build]"));
+    assert!(err.to_string().contains("logs_bucket"));
 }
 
 #[tokio::test]
-async fn revoke_secret_access_calls_remove_iam_policy_binding() {
+async fn submit_build_capture_classifies_worker_pool_policy_error() {
     let mut mock = MockExecutor::new();
 
-    mock.expect_exec()
-        .withf(|args| {
-            args.contains(&"remove-iam-policy-binding".to_owned())
-                && args.contains(&"MY_SECRET".to_owned())
-                && args.contains(
-                    &"serviceAccount:123-compute@developer.gserviceaccount.com".to_owned(),
-                )
-                && args.contains(&"roles/secretmanager.secretAccessor".to_owned())
+    mock.expect_exec().returning(|_| {
+        Err(GcloudError::CommandFailed {
+            args: vec![],
+            stderr:
+                "ERROR: (gcloud.builds.submit) FAILED_PRECONDITION: generic::failed_precondition: \
+                     Constraint constraints/cloudbuild.allowedWorkerPools violated"
+                    .to_owned(),
         })
-        .returning(|_| Ok(String::new()));
+    });
 
+    let tmp = tempfile::TempDir::new().unwrap();
+    let log_path = tmp.path().join("last-build.log");
     let client = GcloudClient::with_executor(mock);
-    let result = client
-        .revoke_secret_access(
+    let err = client
+        .submit_build(
+            &PathBuf::from("/tmp/bundle"),
             "proj",
-            "MY_SECRET",
-            "123-compute@developer.gserviceaccount.com",
+            "tag",
+            &CloudBuildConfig::default(),
+            None,
+            &BuildOutput::Capture(log_path.clone()),
         )
-        .await;
+        .await
+        .unwrap_err();
 
-    assert!(result.is_ok());
+    assert!(matches!(err, CloudBuildError::WorkerPoolRestricted { .. }));
+    assert!(err.to_string().contains("region"));
 }
 
 #[tokio::test]
-async fn revoke_secret_access_failure() {
+async fn submit_build_classifies_policy_error_from_teed_stderr() {
     let mut mock = MockExecutor::new();
 
-    mock.expect_exec()
-        .withf(|args| args.contains(&"remove-iam-policy-binding".to_owned()))
-        .returning(|_| {
-            Err(GcloudError::CommandFailed {
-                args: vec![],
-                stderr: "not found".to_owned(),
-            })
-        });
+    mock.expect_exec_tee().returning(|_, _| {
+        Err(GcloudError::CommandFailed {
+            args: vec![],
+            stderr: "Constraint constraints/cloudbuild.allowedWorkerPools violated".to_owned(),
+        })
+    });
 
     let client = GcloudClient::with_executor(mock);
-    let result = client
-        .revoke_secret_access("proj", "SECRET", "sa@example.com")
-        .await;
+    let err = client
+        .submit_build(
+            &PathBuf::from("/tmp/bundle"),
+            "proj",
+            "tag",
+            &CloudBuildConfig::default(),
+            None,
+            &BuildOutput::Tee(PathBuf::from(".propel/last-build.log")),
+        )
+        .await
+        .unwrap_err();
 
-    assert!(matches!(result, Err(SecretError::RevokeAccess { .. })));
+    assert!(matches!(err, CloudBuildError::WorkerPoolRestricted { .. }));
 }
 
 #[tokio::test]
-async fn delete_secret_success() {
+async fn submit_build_capture_writes_output_to_build_log() {
     let mut mock = MockExecutor::new();
-
     mock.expect_exec()
-        .withf(|args| {
-            args.contains(&"secrets".to_owned())
-                && args.contains(&"delete".to_owned())
-                && args.contains(&"MY_SECRET".to_owned())
-                && args.contains(&"--quiet".to_owned())
+        .returning(|_| Ok("Step #0: Compiling app\nDONE\n".to_owned()));
+
+    let tmp = tempfile::TempDir::new().unwrap();
+    let log_path = tmp.path().join(".propel/last-build.log");
+    let client = GcloudClient::with_executor(mock);
+    client
+        .submit_build(
+            &PathBuf::from("/tmp/bundle"),
+            "proj",
+            "tag",
+            &CloudBuildConfig::default(),
+            None,
+            &BuildOutput::Capture(log_path.clone()),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(&log_path).unwrap(),
+        "Step #0: Compiling app\nDONE\n"
+    );
+}
+
+#[tokio::test]
+async fn submit_build_capture_writes_stderr_to_build_log_on_failure() {
+    let mut mock = MockExecutor::new();
+    mock.expect_exec().returning(|_| {
+        Err(GcloudError::CommandFailed {
+            args: vec![],
+            stderr: "error[E0425]: cannot find value `x`\n".to_owned(),
         })
-        .returning(|_| Ok(String::new()));
+    });
 
+    let tmp = tempfile::TempDir::new().unwrap();
+    let log_path = tmp.path().join("last-build.log");
     let client = GcloudClient::with_executor(mock);
-    let result = client.delete_secret("proj", "MY_SECRET").await;
+    let result = client
+        .submit_build(
+            &PathBuf::from("/tmp/bundle"),
+            "proj",
+            "tag",
+            &CloudBuildConfig::default(),
+            None,
+            &BuildOutput::Capture(log_path.clone()),
+        )
+        .await;
 
-    assert!(result.is_ok());
+    assert!(matches!(result, Err(CloudBuildError::Submit { .. })));
+    assert!(
+        std::fs::read_to_string(&log_path)
+            .unwrap()
+            .contains("E0425")
+    );
 }
 
 #[tokio::test]
-async fn delete_secret_failure() {
+async fn submit_build_streams_without_a_log() {
     let mut mock = MockExecutor::new();
+    mock.expect_exec_streaming()
+        .withf(|args| args.starts_with(&["builds".to_owned(), "submit".to_owned()]))
+        .times(1)
+        .returning(|_| Ok(()));
 
-    mock.expect_exec()
-        .withf(|args| args.contains(&"delete".to_owned()) && args.contains(&"secrets".to_owned()))
-        .returning(|_| {
-            Err(GcloudError::CommandFailed {
-                args: vec![],
-                stderr: "NOT_FOUND".to_owned(),
-            })
-        });
+    let client = GcloudClient::with_executor(mock);
+    let output = client
+        .submit_build(
+            &PathBuf::from("/tmp/bundle"),
+            "proj",
+            "tag",
+            &CloudBuildConfig::default(),
+            None,
+            &BuildOutput::Stream,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(output, "");
+}
+
+#[tokio::test]
+async fn submit_build_runs_the_same_command_for_every_output() {
+    let submitted = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let mut mock = MockExecutor::new();
+    let record = std::sync::Arc::clone(&submitted);
+    mock.expect_exec_streaming().returning(move |args| {
+        record.lock().unwrap().push(args.to_vec());
+        Ok(())
+    });
+    let record = std::sync::Arc::clone(&submitted);
+    mock.expect_exec().returning(move |args| {
+        record.lock().unwrap().push(args.to_vec());
+        Ok(String::new())
+    });
+    let record = std::sync::Arc::clone(&submitted);
+    mock.expect_exec_tee().returning(move |args, _| {
+        record.lock().unwrap().push(args.to_vec());
+        Ok(())
+    });
 
+    let tmp = tempfile::TempDir::new().unwrap();
+    let log_path = tmp.path().join("last-build.log");
+    let settings = CloudBuildConfig {
+        region: Some("europe-west1".to_owned()),
+        logs_bucket: Some("gs://acme-logs/propel".to_owned()),
+        ..Default::default()
+    };
     let client = GcloudClient::with_executor(mock);
-    let result = client.delete_secret("proj", "GONE").await;
+    for output in [
+        BuildOutput::Stream,
+        BuildOutput::Capture(log_path.clone()),
+        BuildOutput::Tee(log_path.clone()),
+    ] {
+        client
+            .submit_build(
+                &PathBuf::from("/tmp/bundle"),
+                "proj",
+                "tag",
+                &settings,
+                Some(Path::new("/tmp/bundle/cloudbuild.yaml")),
+                &output,
+            )
+            .await
+            .unwrap();
+    }
 
-    assert!(matches!(result, Err(SecretError::Delete { .. })));
+    let submitted = submitted.lock().unwrap();
+    assert_eq!(submitted.len(), 3);
+    assert!(
+        submitted.iter().all(|args| *args == submitted[0]),
+        "{submitted:?}"
+    );
 }
 
-// ── WIF Pool Tests ──
+#[test]
+fn classify_cloud_build_error_ignores_unrelated_failures() {
+    assert_eq!(
+        propel_cloud::classify_cloud_build_error("error: could not compile `app`"),
+        None
+    );
+}
+
+#[test]
+fn worker_pool_policy_restricted_detects_list_policy() {
+    let restricted = r#"{"constraint": "constraints/cloudbuild.allowedWorkerPools",
+        "listPolicy": {"allowedValues": ["projects/p/locations/europe-west1/workerPools/pool"]}}"#;
+    let unrestricted =
+        r#"{"constraint": "constraints/cloudbuild.allowedWorkerPools", "etag": "BwX="}"#;
+
+    assert!(propel_cloud::worker_pool_policy_restricted(restricted));
+    assert!(!propel_cloud::worker_pool_policy_restricted(unrestricted));
+}
+
+// ── Cloud Run Deploy Tests ──
+
+#[test]
+fn cloud_run_deploy_args_include_secrets_when_present() {
+    let cmd = propel_cloud::cloud_run_deploy_args(
+        "svc",
+        "img:latest",
+        "proj",
+        "us-central1",
+        &CloudRunConfig::default(),
+        &["DB_URL".into()],
+        &labels(),
+    );
+
+    assert_eq!(&cmd[..3], ["run", "deploy", "svc"]);
+    assert!(
+        cmd.windows(2)
+            .any(|w| w[0] == "--update-secrets" && w[1] == "DB_URL=DB_URL:latest")
+    );
+}
+
+#[test]
+fn cloud_run_deploy_args_follow_allow_unauthenticated() {
+    let deploy_args = |allow_unauthenticated| {
+        let config = CloudRunConfig {
+            allow_unauthenticated,
+            ..CloudRunConfig::default()
+        };
+        propel_cloud::cloud_run_deploy_args(
+            "svc",
+            "img:latest",
+            "proj",
+            "us-central1",
+            &config,
+            &[],
+            &labels(),
+        )
+    };
+
+    let public = deploy_args(true);
+    assert!(public.contains(&"--allow-unauthenticated".to_owned()));
+    assert!(!public.contains(&"--no-allow-unauthenticated".to_owned()));
+
+    let private = deploy_args(false);
+    assert!(private.contains(&"--no-allow-unauthenticated".to_owned()));
+    assert!(!private.contains(&"--allow-unauthenticated".to_owned()));
+}
 
 #[tokio::test]
-async fn ensure_wif_pool_creates_new() {
+async fn deploy_to_cloud_run_of_a_private_service_disallows_unauthenticated() {
     let mut mock = MockExecutor::new();
-
-    // create succeeds
     mock.expect_exec()
         .withf(|args| {
-            args.contains(&"workload-identity-pools".to_owned())
-                && args.contains(&"create".to_owned())
-                && args.contains(&"propel-github".to_owned())
-                && args.contains(&"global".to_owned())
+            args.starts_with(&["run".to_owned(), "deploy".to_owned()])
+                && args.contains(&"--no-allow-unauthenticated".to_owned())
+                && !args.contains(&"--allow-unauthenticated".to_owned())
         })
-        .returning(|_| Ok(String::new()));
+        .times(1)
+        .returning(|_| Ok("https://svc-abc123-uc.a.run.app\n".to_owned()));
 
+    let config = CloudRunConfig {
+        allow_unauthenticated: false,
+        ..CloudRunConfig::default()
+    };
     let client = GcloudClient::with_executor(mock);
-    let created = client
-        .ensure_wif_pool("proj", "propel-github")
+    client
+        .deploy_to_cloud_run(
+            "svc",
+            "img:0123abc",
+            "proj",
+            "us-central1",
+            &config,
+            &[],
+            &labels(),
+        )
         .await
         .unwrap();
-
-    assert!(created);
 }
 
 #[tokio::test]
-async fn ensure_wif_pool_already_exists() {
+async fn ensure_artifact_repo_creates_the_repository_in_its_location() {
     let mut mock = MockExecutor::new();
+    let mut seq = mockall::Sequence::new();
+    let has_location = |args: &[String]| {
+        args.windows(2)
+            .any(|w| w[0] == "--location" && w[1] == "us")
+    };
 
-    // create fails with ALREADY_EXISTS
     mock.expect_exec()
-        .withf(|args| {
-            args.contains(&"workload-identity-pools".to_owned())
-                && args.contains(&"create".to_owned())
+        .withf(move |args| {
+            args.starts_with(&[
+                "artifacts".to_owned(),
+                "repositories".to_owned(),
+                "describe".to_owned(),
+            ]) && has_location(args)
         })
+        .times(1)
+        .in_sequence(&mut seq)
         .returning(|_| {
             Err(GcloudError::CommandFailed {
                 args: vec![],
-                stderr: "ALREADY_EXISTS: resource already exists".to_owned(),
+                stderr: "NOT_FOUND: Repository not found".to_owned(),
             })
         });
-
-    let client = GcloudClient::with_executor(mock);
-    let created = client
-        .ensure_wif_pool("proj", "propel-github")
+    mock.expect_exec()
+        .withf(move |args| {
+            args.starts_with(&[
+                "artifacts".to_owned(),
+                "repositories".to_owned(),
+                "create".to_owned(),
+            ]) && has_location(args)
+        })
+        .times(1)
+        .in_sequence(&mut seq)
+        .returning(|_| Ok(String::new()));
+
+    let client = GcloudClient::with_executor(mock);
+    client
+        .ensure_artifact_repo("proj", "us", "propel")
         .await
         .unwrap();
-
-    assert!(!created);
 }
 
 #[tokio::test]
-async fn ensure_wif_pool_create_fails() {
+async fn deploy_from_a_multi_region_repository_uses_the_service_region() {
     let mut mock = MockExecutor::new();
-
-    // create fails with non-ALREADY_EXISTS error
     mock.expect_exec()
-        .withf(|args| args.contains(&"create".to_owned()))
-        .returning(|_| {
-            Err(GcloudError::CommandFailed {
-                args: vec![],
-                stderr: "permission denied".to_owned(),
-            })
-        });
+        .withf(|args| {
+            args.starts_with(&["run".to_owned(), "deploy".to_owned()])
+                && args
+                    .windows(2)
+                    .any(|w| w[0] == "--image" && w[1] == "us-docker.pkg.dev/proj/propel/svc:abc")
+                && args
+                    .windows(2)
+                    .any(|w| w[0] == "--region" && w[1] == "europe-west1")
+        })
+        .times(1)
+        .returning(|_| Ok("https://svc-abc123-ew.a.run.app\n".to_owned()));
 
     let client = GcloudClient::with_executor(mock);
-    let result = client.ensure_wif_pool("proj", "propel-github").await;
-
-    assert!(matches!(result, Err(WifError::CreatePool { .. })));
+    client
+        .deploy_to_cloud_run(
+            "svc",
+            "us-docker.pkg.dev/proj/propel/svc:abc",
+            "proj",
+            "europe-west1",
+            &CloudRunConfig::default(),
+            &[],
+            &labels(),
+        )
+        .await
+        .unwrap();
 }
 
-// ── OIDC Provider Tests ──
-
 #[tokio::test]
-async fn ensure_oidc_provider_creates_new() {
+async fn grant_invoker_binds_run_invoker_on_the_service() {
     let mut mock = MockExecutor::new();
-
-    // create-oidc succeeds
     mock.expect_exec()
         .withf(|args| {
-            args.contains(&"create-oidc".to_owned())
-                && args.contains(&"github".to_owned())
-                && args
-                    .iter()
-                    .any(|a| a.contains("token.actions.githubusercontent.com"))
-                && args
-                    .iter()
-                    .any(|a| a.contains("assertion.repository == 'owner/repo'"))
+            args == [
+                "run",
+                "services",
+                "add-iam-policy-binding",
+                "svc",
+                "--project",
+                "proj",
+                "--region",
+                "us-central1",
+                "--member",
+                "serviceAccount:caller@proj.iam.gserviceaccount.com",
+                "--role",
+                "roles/run.invoker",
+                "--quiet",
+            ]
         })
+        .times(1)
         .returning(|_| Ok(String::new()));
 
     let client = GcloudClient::with_executor(mock);
-    let created = client
-        .ensure_oidc_provider("proj", "propel-github", "github", "owner/repo")
+    client
+        .grant_invoker(
+            "svc",
+            "proj",
+            "us-central1",
+            "serviceAccount:caller@proj.iam.gserviceaccount.com",
+        )
         .await
         .unwrap();
-
-    assert!(created);
 }
 
 #[tokio::test]
-async fn ensure_oidc_provider_already_exists() {
+async fn service_access_reads_the_service_and_its_policy() {
     let mut mock = MockExecutor::new();
-
-    // create-oidc fails with ALREADY_EXISTS
     mock.expect_exec()
-        .withf(|args| args.contains(&"create-oidc".to_owned()))
+        .withf(|args| {
+            args.starts_with(&[
+                "run".to_owned(),
+                "services".to_owned(),
+                "describe".to_owned(),
+            ])
+        })
+        .times(1)
+        .returning(|_| Ok(include_str!("fixtures/run_services_describe.json").to_owned()));
+    mock.expect_exec()
+        .withf(|args| {
+            args.starts_with(&[
+                "run".to_owned(),
+                "services".to_owned(),
+                "get-iam-policy".to_owned(),
+            ])
+        })
+        .times(1)
         .returning(|_| {
-            Err(GcloudError::CommandFailed {
-                args: vec![],
-                stderr: "ALREADY_EXISTS: provider already exists".to_owned(),
-            })
+            Ok(
+                r#"{"bindings": [{"role": "roles/run.invoker", "members": ["allUsers"]}]}"#
+                    .to_owned(),
+            )
         });
 
     let client = GcloudClient::with_executor(mock);
-    let created = client
-        .ensure_oidc_provider("proj", "propel-github", "github", "owner/repo")
+    let access = client
+        .service_access("orders-api", "proj", "us-central1")
         .await
         .unwrap();
 
-    assert!(!created);
+    assert!(access.is_public());
+    assert_eq!(access.ingress, "internal-and-cloud-load-balancing");
 }
 
-// ── Service Account Tests ──
+#[test]
+fn cloud_run_deploy_args_use_pinned_secret_versions() {
+    let config = CloudRunConfig {
+        secrets: Some(SecretVersions::from([("JWT", "3"), ("API_KEY", "latest")])),
+        ..CloudRunConfig::default()
+    };
+    let cmd = propel_cloud::cloud_run_deploy_args(
+        "svc",
+        "img:latest",
+        "proj",
+        "us-central1",
+        &config,
+        &["API_KEY".into(), "JWT".into()],
+        &labels(),
+    );
+
+    assert!(
+        cmd.windows(2)
+            .any(|w| w[0] == "--update-secrets" && w[1] == "API_KEY=API_KEY:latest,JWT=JWT:3")
+    );
+}
+
+#[test]
+fn cloud_run_deploy_args_mount_prefixed_secrets_under_their_key() {
+    let config = CloudRunConfig {
+        secrets: Some(SecretVersions::from([("JWT", "3")])),
+        ..CloudRunConfig::default()
+    };
+    let namespace = SecretNamespace::new("staging_");
+    let cmd = propel_cloud::cloud_run_deploy_args(
+        "svc",
+        "img:latest",
+        "proj",
+        "us-central1",
+        &config,
+        &[namespace.binding("FOO"), namespace.binding("JWT")],
+        &labels(),
+    );
+
+    assert!(
+        cmd.windows(2)
+            .any(|w| w[0] == "--update-secrets"
+                && w[1] == "FOO=staging_FOO:latest,JWT=staging_JWT:3")
+    );
+}
+
+#[test]
+fn cloud_run_deploy_args_use_binding_versions_over_cloud_run_pins() {
+    let config = CloudRunConfig {
+        secrets: Some(SecretVersions::from([("DATABASE_URL", "1")])),
+        ..CloudRunConfig::default()
+    };
+    let mapped = SecretBinding {
+        env: "DATABASE_URL".to_owned(),
+        secret: "prod-db-url".to_owned(),
+        version: Some("4".to_owned()),
+    };
+    let cmd = propel_cloud::cloud_run_deploy_args(
+        "svc",
+        "img:latest",
+        "proj",
+        "us-central1",
+        &config,
+        &[mapped, "API_KEY".into()],
+        &labels(),
+    );
+
+    assert!(cmd.windows(2).any(|w| w[0] == "--update-secrets"
+        && w[1] == "DATABASE_URL=prod-db-url:4,API_KEY=API_KEY:latest"));
+}
+
+#[test]
+fn cloud_run_deploy_args_omit_secrets_flag_when_empty() {
+    let cmd = propel_cloud::cloud_run_deploy_args(
+        "svc",
+        "img:latest",
+        "proj",
+        "us-central1",
+        &CloudRunConfig::default(),
+        &[],
+        &labels(),
+    );
+
+    assert!(!cmd.contains(&"--update-secrets".to_owned()));
+}
+
+#[test]
+fn cloud_run_deploy_args_update_propel_labels() {
+    let cmd = propel_cloud::cloud_run_deploy_args(
+        "svc",
+        "img:latest",
+        "proj",
+        "us-central1",
+        &CloudRunConfig::default(),
+        &[],
+        &labels(),
+    );
+
+    assert!(cmd.windows(2).any(|w| w[0] == "--update-labels"
+        && w[1] == "propel-managed=true,propel-version=0_4_3,propel-config-hash=0123456789ab"));
+    // --labels would drop labels set outside propel.
+    assert!(!cmd.contains(&"--labels".to_owned()));
+}
 
 #[tokio::test]
-async fn ensure_service_account_creates_new() {
+async fn list_managed_services_filters_by_label() {
     let mut mock = MockExecutor::new();
 
-    // create succeeds
     mock.expect_exec()
         .withf(|args| {
-            args.contains(&"service-accounts".to_owned())
-                && args.contains(&"create".to_owned())
-                && args.contains(&"propel-deploy".to_owned())
+            args.starts_with(&["run".to_owned(), "services".to_owned(), "list".to_owned()])
+                && args
+                    .windows(2)
+                    .any(|w| w[0] == "--filter" && w[1] == "metadata.labels.propel-managed=true")
+                && args
+                    .windows(2)
+                    .any(|w| w[0] == "--format" && w[1] == "json")
         })
-        .returning(|_| Ok(String::new()));
+        .returning(|_| Ok(include_str!("fixtures/run_services_list.json").to_owned()));
 
     let client = GcloudClient::with_executor(mock);
-    let created = client
-        .ensure_service_account("proj", "propel-deploy", "Propel CI Deploy")
-        .await
-        .unwrap();
+    let services = client.list_managed_services("proj").await.unwrap();
 
-    assert!(created);
+    let names: Vec<&str> = services.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, ["billing", "legacy-worker", "orders-api"]);
 }
 
 #[tokio::test]
-async fn ensure_service_account_already_exists() {
+async fn list_managed_services_reports_unparsable_output() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .returning(|_| Ok("Listed 0 items.".to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    let err = client.list_managed_services("proj").await.unwrap_err();
+    assert!(matches!(err, DeployError::ListParse { .. }));
+}
+
+#[tokio::test]
+async fn service_url_reads_status_url() {
     let mut mock = MockExecutor::new();
 
-    // create fails with already exists
     mock.expect_exec()
         .withf(|args| {
-            args.contains(&"service-accounts".to_owned()) && args.contains(&"create".to_owned())
+            args.starts_with(&[
+                "run".to_owned(),
+                "services".to_owned(),
+                "describe".to_owned(),
+            ]) && args.contains(&"value(status.url)".to_owned())
         })
-        .returning(|_| {
-            Err(GcloudError::CommandFailed {
-                args: vec![],
-                stderr: "Service account already exists".to_owned(),
-            })
-        });
+        .returning(|_| Ok("https://svc-abc.a.run.app\n".to_owned()));
 
     let client = GcloudClient::with_executor(mock);
-    let created = client
-        .ensure_service_account("proj", "propel-deploy", "Propel CI Deploy")
+    let url = client
+        .service_url("svc", "proj", "us-central1")
         .await
         .unwrap();
 
-    assert!(!created);
+    assert_eq!(url, "https://svc-abc.a.run.app");
 }
 
-// ── IAM Role Binding Tests ──
-
 #[tokio::test]
-async fn bind_iam_roles_success() {
+async fn image_size_lists_the_tagged_image() {
     let mut mock = MockExecutor::new();
 
     mock.expect_exec()
         .withf(|args| {
-            args.contains(&"add-iam-policy-binding".to_owned())
-                && args.contains(&"projects".to_owned())
+            args.starts_with(&[
+                "artifacts".to_owned(),
+                "docker".to_owned(),
+                "images".to_owned(),
+                "list".to_owned(),
+                "us-central1-docker.pkg.dev/proj/propel/api".to_owned(),
+            ]) && args
+                .windows(2)
+                .any(|w| w[0] == "--filter" && w[1] == "tags:latest")
+                && args.contains(&"--include-tags".to_owned())
         })
-        .times(2)
-        .returning(|_| Ok(String::new()));
+        .returning(|_| Ok(include_str!("fixtures/artifacts_docker_images_list.json").to_owned()));
 
     let client = GcloudClient::with_executor(mock);
-    let result = client
-        .bind_iam_roles(
-            "proj",
-            "sa@proj.iam.gserviceaccount.com",
-            &["roles/run.admin", "roles/cloudbuild.builds.editor"],
-        )
-        .await;
+    let size = client
+        .image_size("us-central1-docker.pkg.dev/proj/propel/api:latest")
+        .await
+        .unwrap();
 
-    assert!(result.is_ok());
+    assert_eq!(size, Some(88_080_384));
 }
 
 #[tokio::test]
-async fn bind_iam_roles_partial_failure() {
+async fn image_size_rejects_unexpected_output() {
     let mut mock = MockExecutor::new();
-
-    // First role succeeds
-    mock.expect_exec()
-        .withf(|args| args.contains(&"roles/run.admin".to_owned()))
-        .returning(|_| Ok(String::new()));
-
-    // Second role fails
     mock.expect_exec()
-        .withf(|args| args.contains(&"roles/cloudbuild.builds.editor".to_owned()))
-        .returning(|_| {
-            Err(GcloudError::CommandFailed {
-                args: vec![],
-                stderr: "permission denied".to_owned(),
-            })
-        });
+        .returning(|_| Ok("Listed 0 items.".to_owned()));
 
     let client = GcloudClient::with_executor(mock);
-    let result = client
-        .bind_iam_roles(
-            "proj",
-            "sa@proj.iam.gserviceaccount.com",
-            &["roles/run.admin", "roles/cloudbuild.builds.editor"],
-        )
-        .await;
+    let err = client.image_size("r/p/api:latest").await.unwrap_err();
 
-    assert!(matches!(
-        result,
-        Err(WifError::BindRole { ref role, .. }) if role == "roles/cloudbuild.builds.editor"
-    ));
+    assert!(matches!(err, DeployError::ImageSizeParse { .. }));
 }
 
-// ── WIF → SA Binding Tests ──
-
 #[tokio::test]
-async fn bind_wif_to_sa_success() {
+async fn deployed_image_size_reads_the_service_label() {
     let mut mock = MockExecutor::new();
 
     mock.expect_exec()
         .withf(|args| {
-            args.contains(&"add-iam-policy-binding".to_owned())
-                && args.contains(&"roles/iam.workloadIdentityUser".to_owned())
-                && args
-                    .iter()
-                    .any(|a| a.contains("attribute.repository/owner/repo"))
+            args.starts_with(&[
+                "run".to_owned(),
+                "services".to_owned(),
+                "describe".to_owned(),
+                "svc".to_owned(),
+            ]) && args.contains(&"value(metadata.labels.propel-image-size)".to_owned())
         })
-        .returning(|_| Ok(String::new()));
+        .returning(|_| Ok("88080384\n".to_owned()));
 
     let client = GcloudClient::with_executor(mock);
-    let result = client
-        .bind_wif_to_sa(
-            "proj",
-            "123456",
-            "propel-github",
-            "sa@proj.iam.gserviceaccount.com",
-            "owner/repo",
-        )
-        .await;
+    let size = client
+        .deployed_image_size("svc", "proj", "us-central1")
+        .await
+        .unwrap();
 
-    assert!(result.is_ok());
+    assert_eq!(size, Some(88_080_384));
 }
 
 #[tokio::test]
-async fn bind_wif_to_sa_failure() {
+async fn deployed_image_size_without_label_is_none() {
     let mut mock = MockExecutor::new();
+    mock.expect_exec().returning(|_| Ok("\n".to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    let size = client
+        .deployed_image_size("svc", "proj", "us-central1")
+        .await
+        .unwrap();
+
+    assert_eq!(size, None);
+}
 
+#[tokio::test]
+async fn service_exists_treats_a_missing_service_as_absent() {
+    let mut mock = MockExecutor::new();
     mock.expect_exec()
-        .withf(|args| args.contains(&"add-iam-policy-binding".to_owned()))
-        .returning(|_| {
+        .withf(|args| args.contains(&"svc".to_owned()))
+        .returning(|args| Ok(args[3].clone()));
+    mock.expect_exec()
+        .withf(|args| args.contains(&"gone".to_owned()))
+        .returning(|args| {
             Err(GcloudError::CommandFailed {
-                args: vec![],
-                stderr: "failed".to_owned(),
+                args: args.to_vec(),
+                stderr: "ERROR: (gcloud.run.services.describe) Cannot find service [gone]"
+                    .to_owned(),
             })
         });
 
     let client = GcloudClient::with_executor(mock);
-    let result = client
-        .bind_wif_to_sa("proj", "123456", "pool", "sa@example.com", "owner/repo")
-        .await;
 
-    assert!(matches!(result, Err(WifError::BindWif { .. })));
+    assert!(
+        client
+            .service_exists("svc", "proj", "us-central1")
+            .await
+            .unwrap()
+    );
+    assert!(
+        !client
+            .service_exists("gone", "proj", "us-central1")
+            .await
+            .unwrap()
+    );
 }
 
-// ── Delete WIF Pool Tests ──
-
 #[tokio::test]
-async fn delete_wif_pool_success() {
+async fn service_exists_propagates_other_failures() {
     let mut mock = MockExecutor::new();
-
-    mock.expect_exec()
-        .withf(|args| {
-            args.contains(&"workload-identity-pools".to_owned())
-                && args.contains(&"delete".to_owned())
-                && args.contains(&"propel-github".to_owned())
-                && args.contains(&"--quiet".to_owned())
+    mock.expect_exec().returning(|args| {
+        Err(GcloudError::CommandFailed {
+            args: args.to_vec(),
+            stderr: "PERMISSION_DENIED: run.services.get".to_owned(),
         })
-        .returning(|_| Ok(String::new()));
+    });
 
     let client = GcloudClient::with_executor(mock);
-    let result = client.delete_wif_pool("proj", "propel-github").await;
 
-    assert!(result.is_ok());
+    assert!(
+        client
+            .service_exists("svc", "proj", "us-central1")
+            .await
+            .is_err()
+    );
 }
 
 #[tokio::test]
-async fn delete_wif_pool_failure() {
+async fn image_exists_checks_for_any_version() {
     let mut mock = MockExecutor::new();
-
     mock.expect_exec()
-        .withf(|args| {
-            args.contains(&"workload-identity-pools".to_owned())
-                && args.contains(&"delete".to_owned())
-        })
-        .returning(|_| {
+        .withf(|args| args.contains(&"r/p/api".to_owned()) && args.contains(&"--limit".to_owned()))
+        .returning(|_| Ok("sha256:5f1c2a0de7b1\n".to_owned()));
+    mock.expect_exec()
+        .withf(|args| args.contains(&"r/p/empty".to_owned()))
+        .returning(|_| Ok(String::new()));
+    mock.expect_exec()
+        .withf(|args| args.contains(&"r/missing/api".to_owned()))
+        .returning(|args| {
             Err(GcloudError::CommandFailed {
-                args: vec![],
-                stderr: "NOT_FOUND".to_owned(),
+                args: args.to_vec(),
+                stderr: "NOT_FOUND: Repository \"missing\" not found".to_owned(),
             })
         });
 
     let client = GcloudClient::with_executor(mock);
-    let result = client.delete_wif_pool("proj", "propel-github").await;
 
-    assert!(matches!(result, Err(WifError::DeletePool { .. })));
+    assert!(client.image_exists("r/p/api").await.unwrap());
+    assert!(!client.image_exists("r/p/empty").await.unwrap());
+    assert!(!client.image_exists("r/missing/api").await.unwrap());
 }
 
-// ── Delete Service Account Tests ──
-
 #[tokio::test]
-async fn delete_service_account_success() {
+async fn deploy_to_cloud_run_returns_url() {
     let mut mock = MockExecutor::new();
 
     mock.expect_exec()
-        .withf(|args| {
-            args.contains(&"service-accounts".to_owned())
-                && args.contains(&"delete".to_owned())
-                && args.contains(&"--quiet".to_owned())
-        })
-        .returning(|_| Ok(String::new()));
+        .withf(|args| args.contains(&"run".to_owned()) && args.contains(&"deploy".to_owned()))
+        .returning(|_| Ok("https://my-service-abc123-uc.a.run.app\n".to_owned()));
 
     let client = GcloudClient::with_executor(mock);
-    let result = client
-        .delete_service_account("proj", "sa@proj.iam.gserviceaccount.com")
-        .await;
+    let url = client
+        .deploy_to_cloud_run(
+            "my-service",
+            "gcr.io/proj/svc:latest",
+            "proj",
+            "us-central1",
+            &CloudRunConfig::default(),
+            &[],
+            &labels(),
+        )
+        .await
+        .unwrap();
 
-    assert!(result.is_ok());
+    assert_eq!(url, "https://my-service-abc123-uc.a.run.app");
 }
 
 #[tokio::test]
-async fn delete_service_account_failure() {
+async fn commit_tagged_image_reaches_build_and_deploy() {
+    const IMAGE: &str = "us-central1-docker.pkg.dev/proj/propel/svc:0123abc-dirty";
     let mut mock = MockExecutor::new();
-
+    mock.expect_exec_tee()
+        .withf(|args, _| args.windows(2).any(|w| w[0] == "--tag" && w[1] == IMAGE))
+        .times(1)
+        .returning(|_, _| Ok(()));
     mock.expect_exec()
         .withf(|args| {
-            args.contains(&"service-accounts".to_owned()) && args.contains(&"delete".to_owned())
+            args.starts_with(&["run".to_owned(), "deploy".to_owned()])
+                && args.windows(2).any(|w| w[0] == "--image" && w[1] == IMAGE)
         })
-        .returning(|_| {
-            Err(GcloudError::CommandFailed {
-                args: vec![],
-                stderr: "NOT_FOUND".to_owned(),
-            })
-        });
+        .times(1)
+        .returning(|_| Ok("https://svc-abc123-uc.a.run.app\n".to_owned()));
 
     let client = GcloudClient::with_executor(mock);
-    let result = client
-        .delete_service_account("proj", "sa@proj.iam.gserviceaccount.com")
-        .await;
-
-    assert!(matches!(result, Err(WifError::DeleteServiceAccount { .. })));
+    client
+        .submit_build(
+            &PathBuf::from("/tmp/bundle"),
+            "proj",
+            IMAGE,
+            &CloudBuildConfig::default(),
+            None,
+            &BuildOutput::Tee(PathBuf::from(".propel/last-build.log")),
+        )
+        .await
+        .unwrap();
+    client
+        .deploy_to_cloud_run(
+            "svc",
+            IMAGE,
+            "proj",
+            "us-central1",
+            &CloudRunConfig::default(),
+            &[],
+            &labels(),
+        )
+        .await
+        .unwrap();
 }
 
-// ── Logs Tests ──
-
 #[tokio::test]
-async fn read_logs_with_custom_limit() {
+async fn add_image_tag_points_the_alias_at_the_image() {
     let mut mock = MockExecutor::new();
-
-    mock.expect_exec_streaming()
+    mock.expect_exec()
         .withf(|args| {
-            args.contains(&"logs".to_owned())
-                && args.contains(&"read".to_owned())
-                && args.contains(&"50".to_owned())
+            args == [
+                "artifacts",
+                "docker",
+                "tags",
+                "add",
+                "r/p/svc:0123abc",
+                "r/p/svc:latest",
+                "--quiet",
+            ]
         })
-        .returning(|_| Ok(()));
+        .times(1)
+        .returning(|_| Ok(String::new()));
 
     let client = GcloudClient::with_executor(mock);
-    let result = client.read_logs("my-svc", "proj", "us-central1", 50).await;
-
-    assert!(result.is_ok());
+    client
+        .add_image_tag("r/p/svc:0123abc", "r/p/svc:latest")
+        .await
+        .unwrap();
 }
 
-#[tokio::test]
-async fn read_logs_failure() {
-    let mut mock = MockExecutor::new();
+/// Formatted tracing output of a test, with span close events.
+#[derive(Clone, Default)]
+struct CapturedLog(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
 
-    mock.expect_exec_streaming()
-        .withf(|args| args.contains(&"read".to_owned()))
-        .returning(|_| {
-            Err(GcloudError::CommandFailed {
-                args: vec![],
-                stderr: "not found".to_owned(),
-            })
-        });
+impl std::io::Write for CapturedLog {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
 
-    let client = GcloudClient::with_executor(mock);
-    let result = client.read_logs("svc", "proj", "us-central1", 100).await;
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
 
-    assert!(matches!(result, Err(DeployError::Logs { .. })));
+impl CapturedLog {
+    /// Capture debug output on this thread until the guard is dropped.
+    fn install(&self) -> tracing::subscriber::DefaultGuard {
+        let log = self.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE)
+            .with_ansi(false)
+            .with_writer(move || log.clone())
+            .finish();
+        tracing::subscriber::set_default(subscriber)
+    }
+
+    fn text(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
 }
 
 #[tokio::test]
-async fn read_logs_captured_returns_output() {
+async fn deploy_steps_emit_spans_with_fields() {
     let mut mock = MockExecutor::new();
-
+    mock.expect_exec_tee().returning(|_, _| Ok(()));
     mock.expect_exec()
-        .withf(|args| {
-            args.contains(&"logs".to_owned())
-                && args.contains(&"read".to_owned())
-                && args.contains(&"50".to_owned())
-        })
-        .returning(|_| Ok("2024-01-01 INFO request served\n".to_owned()));
-
+        .withf(|args| args.contains(&"run".to_owned()) && args.contains(&"deploy".to_owned()))
+        .returning(|_| Ok("https://svc.a.run.app\n".to_owned()));
     let client = GcloudClient::with_executor(mock);
-    let output = client
-        .read_logs_captured("my-svc", "proj", "us-central1", 50)
+
+    let log = CapturedLog::default();
+    let _guard = log.install();
+    client
+        .submit_build(
+            &PathBuf::from("/tmp/bundle"),
+            "proj",
+            "img:latest",
+            &CloudBuildConfig::default(),
+            None,
+            &BuildOutput::Tee(PathBuf::from(".propel/last-build.log")),
+        )
+        .await
+        .unwrap();
+    client
+        .deploy_to_cloud_run(
+            "svc",
+            "img:latest",
+            "proj",
+            "us-central1",
+            &CloudRunConfig::default(),
+            &[],
+            &labels(),
+        )
         .await
         .unwrap();
 
-    assert!(output.contains("request served"));
+    let text = log.text();
+    for expected in [
+        "cloud_build{project=\"proj\" image_tag=\"img:latest\"}: ",
+        "cloud_run_deploy{project=\"proj\" service=\"svc\" region=\"us-central1\" image_tag=\"img:latest\"}: ",
+    ] {
+        let closed = text
+            .lines()
+            .any(|line| line.contains(expected) && line.contains("close time.busy"));
+        assert!(closed, "no close event for {expected:?} in:\n{text}");
+    }
 }
 
-#[tokio::test]
-async fn read_logs_captured_failure() {
+const SECRET_DENIED_STDERR: &str = "ERROR: (gcloud.run.deploy) spec.template.spec.containers[0].env[0].value_from.secret_key_ref.name: \
+Permission denied on secret: projects/123/secrets/API_KEY/versions/latest for Revision service account \
+123-compute@developer.gserviceaccount.com.";
+
+fn secret_denied() -> GcloudError {
+    GcloudError::CommandFailed {
+        args: vec![],
+        stderr: SECRET_DENIED_STDERR.to_owned(),
+    }
+}
+
+async fn deploy_with_secret_retry(
+    client: &GcloudClient<MockExecutor>,
+    waits: &mut Vec<Duration>,
+) -> Result<String, DeployError> {
+    let config = CloudRunConfig::default();
+    let secrets = ["API_KEY".into()];
+    let labels = labels();
+    propel_cloud::retry_secret_propagation(
+        propel_cloud::SECRET_ACCESS_PROPAGATION_TIMEOUT,
+        |delay, _| waits.push(delay),
+        || {
+            client.deploy_to_cloud_run(
+                "svc",
+                "img:latest",
+                "proj",
+                "us-central1",
+                &config,
+                &secrets,
+                &labels,
+            )
+        },
+    )
+    .await
+}
+
+#[test]
+fn secret_access_denied_matches_propagation_signature() {
+    assert!(propel_cloud::secret_access_denied(SECRET_DENIED_STDERR));
+    assert!(!propel_cloud::secret_access_denied(
+        "ERROR: (gcloud.run.deploy) PERMISSION_DENIED: Permission 'run.services.get' denied"
+    ));
+}
+
+#[tokio::test(start_paused = true)]
+async fn deploy_retries_while_secret_access_propagates() {
     let mut mock = MockExecutor::new();
 
+    // Denied twice, then the binding has propagated.
+    let mut calls = 0;
     mock.expect_exec()
-        .withf(|args| args.contains(&"read".to_owned()))
-        .returning(|_| {
-            Err(GcloudError::CommandFailed {
-                args: vec![],
-                stderr: "not found".to_owned(),
-            })
+        .withf(|args| args.starts_with(&["run".to_owned(), "deploy".to_owned()]))
+        .times(3)
+        .returning(move |_| {
+            calls += 1;
+            if calls < 3 {
+                Err(secret_denied())
+            } else {
+                Ok("https://svc.a.run.app\n".to_owned())
+            }
         });
 
     let client = GcloudClient::with_executor(mock);
-    let result = client
-        .read_logs_captured("svc", "proj", "us-central1", 100)
-        .await;
+    let mut waits = Vec::new();
+    let url = deploy_with_secret_retry(&client, &mut waits).await.unwrap();
 
-    assert!(matches!(result, Err(DeployError::Logs { .. })));
+    assert_eq!(url, "https://svc.a.run.app");
+    assert_eq!(waits, vec![Duration::from_secs(5), Duration::from_secs(10)]);
 }
 
-#[tokio::test]
-async fn tail_logs_success() {
+#[tokio::test(start_paused = true)]
+async fn deploy_secret_retry_gives_up_at_timeout() {
     let mut mock = MockExecutor::new();
 
-    mock.expect_exec_streaming()
-        .withf(|args| {
-            args.contains(&"logs".to_owned())
-                && args.contains(&"tail".to_owned())
-                && args.contains(&"my-svc".to_owned())
-        })
-        .returning(|_| Ok(()));
+    mock.expect_exec()
+        .withf(|args| args.starts_with(&["run".to_owned(), "deploy".to_owned()]))
+        .returning(|_| Err(secret_denied()));
 
     let client = GcloudClient::with_executor(mock);
-    let result = client.tail_logs("my-svc", "proj", "us-central1").await;
-
-    assert!(result.is_ok());
+    let mut waits = Vec::new();
+    let err = deploy_with_secret_retry(&client, &mut waits)
+        .await
+        .unwrap_err();
+
+    // 5 + 10 + 20 + 20, then the 5s remaining before the 60s timeout.
+    assert_eq!(waits.iter().sum::<Duration>(), Duration::from_secs(60));
+    assert_eq!(waits.len(), 5);
+    match err {
+        DeployError::SecretAccessDenied { waited, .. } => {
+            assert_eq!(waited, Duration::from_secs(60));
+        }
+        other => panic!("expected SecretAccessDenied, got: {other:?}"),
+    }
 }
 
-#[tokio::test]
-async fn tail_logs_failure() {
+#[tokio::test(start_paused = true)]
+async fn deploy_secret_retry_passes_other_errors_through() {
     let mut mock = MockExecutor::new();
 
-    mock.expect_exec_streaming()
-        .withf(|args| args.contains(&"tail".to_owned()))
+    mock.expect_exec()
+        .withf(|args| args.starts_with(&["run".to_owned(), "deploy".to_owned()]))
+        .times(1)
         .returning(|_| {
             Err(GcloudError::CommandFailed {
                 args: vec![],
-                stderr: "not found".to_owned(),
+                stderr: "ERROR: image not found".to_owned(),
             })
         });
 
     let client = GcloudClient::with_executor(mock);
-    let result = client.tail_logs("svc", "proj", "us-central1").await;
+    let mut waits = Vec::new();
+    let err = deploy_with_secret_retry(&client, &mut waits)
+        .await
+        .unwrap_err();
 
-    assert!(matches!(result, Err(DeployError::Logs { .. })));
+    assert!(waits.is_empty());
+    assert!(matches!(err, DeployError::Deploy { .. }));
 }
 
-// ── DoctorReport Display ──
+#[test]
+fn cloud_run_deploy_args_omit_probe_flags_by_default() {
+    let cmd = propel_cloud::cloud_run_deploy_args(
+        "svc",
+        "img:latest",
+        "proj",
+        "us-central1",
+        &CloudRunConfig::default(),
+        &[],
+        &labels(),
+    );
+
+    assert!(!cmd.iter().any(|a| a.starts_with("--startup-probe")));
+    assert!(!cmd.iter().any(|a| a.starts_with("--liveness-probe")));
+}
 
 #[test]
-fn doctor_report_display_all_passed() {
-    let report = propel_cloud::DoctorReport {
-        gcloud: propel_cloud::CheckResult::ok("1.0.0"),
-        account: propel_cloud::CheckResult::ok("user@example.com"),
-        project: propel_cloud::CheckResult::ok("my-project"),
-        billing: propel_cloud::CheckResult::ok("Enabled"),
-        apis: vec![propel_cloud::ApiCheck {
-            name: "run.googleapis.com".to_string(),
-            result: propel_cloud::CheckResult::ok("Enabled"),
-        }],
-        config_file: propel_cloud::CheckResult::ok("Found"),
+fn cloud_run_deploy_args_probe_warmup_handler() {
+    let config = CloudRunConfig {
+        warmup_handler: true,
+        ..CloudRunConfig::default()
     };
+    let cmd = propel_cloud::cloud_run_deploy_args(
+        "svc",
+        "img",
+        "proj",
+        "us-central1",
+        &config,
+        &[],
+        &labels(),
+    );
 
-    let output = report.to_string();
-    assert!(output.contains("Propel Doctor"));
-    assert!(output.contains("gcloud CLI"));
-    assert!(output.contains("OK"));
-    assert!(output.contains("All checks passed!"));
-    assert!(!output.contains("NG"));
+    assert!(
+        cmd.iter()
+            .any(|a| a.starts_with("--startup-probe=httpGet.path=/warmup,httpGet.port=8080,")),
+        "{cmd:?}"
+    );
 }
 
 #[test]
-fn doctor_report_display_with_failures() {
-    let report = propel_cloud::DoctorReport {
-        gcloud: propel_cloud::CheckResult::ok("1.0.0"),
-        account: propel_cloud::CheckResult::ok("user@example.com"),
-        project: propel_cloud::CheckResult::fail("Not set"),
-        billing: propel_cloud::CheckResult::fail("Unknown"),
-        apis: vec![],
-        config_file: propel_cloud::CheckResult::fail("Not found"),
+fn cloud_run_deploy_args_tcp_startup_probe_without_path() {
+    let config = CloudRunConfig {
+        port: 3000,
+        startup_probe: Some(ProbeConfig {
+            initial_delay: 30,
+            ..ProbeConfig::default()
+        }),
+        ..CloudRunConfig::default()
     };
+    let cmd = propel_cloud::cloud_run_deploy_args(
+        "svc",
+        "img",
+        "proj",
+        "us-central1",
+        &config,
+        &[],
+        &labels(),
+    );
 
-    let output = report.to_string();
-    assert!(output.contains("NG"));
-    assert!(output.contains("Not set"));
-    assert!(output.contains("Not found"));
-    assert!(output.contains("Some checks failed"));
-    assert!(!output.contains("All checks passed!"));
+    assert!(cmd.contains(
+        &"--startup-probe=tcpSocket.port=3000,initialDelaySeconds=30,periodSeconds=10,failureThreshold=3,timeoutSeconds=1"
+            .to_owned()
+    ));
 }
 
 #[test]
-fn doctor_report_display_apis_shown() {
-    let report = propel_cloud::DoctorReport {
-        gcloud: propel_cloud::CheckResult::ok("1.0.0"),
-        account: propel_cloud::CheckResult::ok("user@example.com"),
-        project: propel_cloud::CheckResult::ok("proj"),
-        billing: propel_cloud::CheckResult::ok("Enabled"),
-        apis: vec![
-            propel_cloud::ApiCheck {
-                name: "run.googleapis.com".to_string(),
-                result: propel_cloud::CheckResult::ok("Enabled"),
-            },
-            propel_cloud::ApiCheck {
-                name: "cloudbuild.googleapis.com".to_string(),
-                result: propel_cloud::CheckResult::fail("Disabled"),
-            },
-        ],
-        config_file: propel_cloud::CheckResult::ok("Found"),
+fn cloud_run_deploy_args_update_env_vars_sorted() {
+    let config = CloudRunConfig {
+        env: [("RUST_LOG", "info"), ("GIT_SHA", "abc123")]
+            .into_iter()
+            .map(|(k, v)| (k.to_owned(), v.to_owned()))
+            .collect(),
+        ..CloudRunConfig::default()
     };
+    let cmd = propel_cloud::cloud_run_deploy_args(
+        "svc",
+        "img",
+        "proj",
+        "us-central1",
+        &config,
+        &[],
+        &labels(),
+    );
 
-    let output = report.to_string();
-    assert!(output.contains("run.googleapis.com API"));
-    assert!(output.contains("cloudbuild.googleapis.com API"));
-    assert!(output.contains("Disabled"));
+    // Exactly one env flag, and the replacing one.
+    let env_flags: Vec<(&str, &str)> = cmd
+        .windows(2)
+        .filter(|w| w[0].ends_with("-env-vars"))
+        .map(|w| (w[0].as_str(), w[1].as_str()))
+        .collect();
+    assert_eq!(
+        env_flags,
+        [("--set-env-vars", "GIT_SHA=abc123,RUST_LOG=info")]
+    );
+}
+
+#[test]
+fn cloud_run_deploy_args_env_vars_with_commas_use_alternate_delimiter() {
+    let config = CloudRunConfig {
+        env: [("ORIGINS", "a.dev,b.dev"), ("CONTACT", "ops@a.dev")]
+            .into_iter()
+            .map(|(k, v)| (k.to_owned(), v.to_owned()))
+            .collect(),
+        ..CloudRunConfig::default()
+    };
+    let cmd = propel_cloud::cloud_run_deploy_args(
+        "svc",
+        "img",
+        "proj",
+        "us-central1",
+        &config,
+        &[],
+        &labels(),
+    );
+
+    assert!(
+        cmd.windows(2)
+            .any(|w| w[0] == "--set-env-vars"
+                && w[1] == "^@@^CONTACT=ops@a.dev@@ORIGINS=a.dev,b.dev")
+    );
+}
+
+#[tokio::test]
+async fn deploy_to_cloud_run_keeps_env_vars_and_secrets_in_separate_flags() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            let value_of = |flag: &str| {
+                args.windows(2)
+                    .find(|w| w[0] == flag)
+                    .map(|w| w[1].as_str())
+            };
+            args.starts_with(&["run".to_owned(), "deploy".to_owned()])
+                && value_of("--set-env-vars")
+                    == Some("^@^DATABASE_OPTS=sslmode=require,pool=5@RUST_LOG=info")
+                && value_of("--update-secrets") == Some("API_KEY=API_KEY:latest")
+                && !args.contains(&"--update-env-vars".to_owned())
+        })
+        .times(1)
+        .returning(|_| Ok("https://svc.a.run.app\n".to_owned()));
+
+    let config = CloudRunConfig {
+        env: [
+            ("RUST_LOG", "info"),
+            ("DATABASE_OPTS", "sslmode=require,pool=5"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_owned(), v.to_owned()))
+        .collect(),
+        ..CloudRunConfig::default()
+    };
+    let client = GcloudClient::with_executor(mock);
+    client
+        .deploy_to_cloud_run(
+            "svc",
+            "img:latest",
+            "proj",
+            "us-central1",
+            &config,
+            &["API_KEY".into()],
+            &labels(),
+        )
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn deploy_to_cloud_run_passes_http_probes() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.starts_with(&["run".to_owned(), "deploy".to_owned()])
+                && args.contains(
+                    &"--startup-probe=httpGet.path=/health,httpGet.port=8080,initialDelaySeconds=5,periodSeconds=5,failureThreshold=24,timeoutSeconds=2"
+                        .to_owned(),
+                )
+                && args.contains(
+                    &"--liveness-probe=httpGet.path=/live,httpGet.port=8080,initialDelaySeconds=0,periodSeconds=30,failureThreshold=3,timeoutSeconds=1"
+                        .to_owned(),
+                )
+        })
+        .returning(|_| Ok("https://svc.a.run.app\n".to_owned()));
+
+    let config = CloudRunConfig {
+        startup_probe: Some(ProbeConfig {
+            path: Some("/health".to_owned()),
+            initial_delay: 5,
+            period: 5,
+            failure_threshold: 24,
+            timeout: 2,
+        }),
+        liveness_probe: Some(ProbeConfig {
+            path: Some("/live".to_owned()),
+            period: 30,
+            ..ProbeConfig::default()
+        }),
+        ..CloudRunConfig::default()
+    };
+    let client = GcloudClient::with_executor(mock);
+    let url = client
+        .deploy_to_cloud_run(
+            "svc",
+            "img:latest",
+            "proj",
+            "us-central1",
+            &config,
+            &[],
+            &labels(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(url, "https://svc.a.run.app");
+}
+
+#[tokio::test]
+async fn deploy_revision_without_traffic_returns_tag_url() {
+    let mut mock = MockExecutor::new();
+    let mut seq = mockall::Sequence::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.starts_with(&["run".to_owned(), "deploy".to_owned()])
+                && args.ends_with(&[
+                    "--no-traffic".to_owned(),
+                    "--tag".to_owned(),
+                    "propel-warmup".to_owned(),
+                ])
+        })
+        .times(1)
+        .in_sequence(&mut seq)
+        .returning(|_| Ok(String::new()));
+    mock.expect_exec()
+        .withf(|args| args.contains(&"describe".to_owned()) && args.ends_with(&["json".to_owned()]))
+        .times(1)
+        .in_sequence(&mut seq)
+        .returning(|_| {
+            Ok(r#"{"status": {"traffic": [
+                {"percent": 100, "revisionName": "svc-00001-abc"},
+                {"tag": "propel-warmup", "latestRevision": true,
+                 "url": "https://propel-warmup---svc-abc.a.run.app"}
+            ]}}"#
+                .to_owned())
+        });
+
+    let client = GcloudClient::with_executor(mock);
+    let url = client
+        .deploy_revision_without_traffic(
+            "svc",
+            "tag",
+            "proj",
+            "us-central1",
+            &CloudRunConfig::default(),
+            &[],
+            &labels(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(url, "https://propel-warmup---svc-abc.a.run.app");
+}
+
+#[tokio::test]
+async fn deploy_revision_without_traffic_missing_tag() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| args.contains(&"deploy".to_owned()))
+        .returning(|_| Ok(String::new()));
+    mock.expect_exec()
+        .withf(|args| args.contains(&"describe".to_owned()))
+        .returning(|_| Ok(r#"{"status": {"traffic": [{"percent": 100}]}}"#.to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    let err = client
+        .deploy_revision_without_traffic(
+            "svc",
+            "tag",
+            "proj",
+            "us-central1",
+            &CloudRunConfig::default(),
+            &[],
+            &labels(),
+        )
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, DeployError::TagNotFound { .. }), "{err}");
+}
+
+#[tokio::test]
+async fn promote_latest_revision_moves_traffic_and_drops_tag() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.starts_with(&[
+                "run".to_owned(),
+                "services".to_owned(),
+                "update-traffic".to_owned(),
+                "svc".to_owned(),
+            ]) && args.contains(&"--to-latest".to_owned())
+                && args
+                    .windows(2)
+                    .any(|w| w[0] == "--remove-tags" && w[1] == "propel-warmup")
+        })
+        .times(1)
+        .returning(|_| Ok(String::new()));
+    mock.expect_exec()
+        .withf(|args| args.contains(&"value(status.url)".to_owned()))
+        .returning(|_| Ok("https://svc-abc.a.run.app\n".to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    let url = client
+        .promote_latest_revision("svc", "proj", "us-central1")
+        .await
+        .unwrap();
+
+    assert_eq!(url, "https://svc-abc.a.run.app");
+}
+
+#[tokio::test]
+async fn deploy_canary_revision_tags_it_canary() {
+    let mut mock = MockExecutor::new();
+    let mut seq = mockall::Sequence::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.starts_with(&["run".to_owned(), "deploy".to_owned()])
+                && args.ends_with(&[
+                    "--no-traffic".to_owned(),
+                    "--tag".to_owned(),
+                    "canary".to_owned(),
+                ])
+        })
+        .times(1)
+        .in_sequence(&mut seq)
+        .returning(|_| Ok(String::new()));
+    mock.expect_exec()
+        .withf(|args| args.contains(&"describe".to_owned()))
+        .times(1)
+        .in_sequence(&mut seq)
+        .returning(|_| {
+            Ok(r#"{"status": {"traffic": [
+                {"percent": 100, "revisionName": "svc-00001-abc"},
+                {"tag": "canary", "revisionName": "svc-00002-def",
+                 "url": "https://canary---svc-abc.a.run.app"}
+            ]}}"#
+                .to_owned())
+        });
+
+    let client = GcloudClient::with_executor(mock);
+    let url = client
+        .deploy_canary_revision(
+            "svc",
+            "tag",
+            "proj",
+            "us-central1",
+            &CloudRunConfig::default(),
+            &[],
+            &labels(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(url, "https://canary---svc-abc.a.run.app");
+}
+
+#[tokio::test]
+async fn split_canary_traffic_routes_the_share_to_the_tag() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.starts_with(&[
+                "run".to_owned(),
+                "services".to_owned(),
+                "update-traffic".to_owned(),
+                "svc".to_owned(),
+            ]) && args
+                .windows(2)
+                .any(|w| w[0] == "--to-tags" && w[1] == "canary=10")
+        })
+        .times(1)
+        .returning(|_| Ok(String::new()));
+
+    let client = GcloudClient::with_executor(mock);
+    client
+        .split_canary_traffic("svc", "proj", "us-central1", 10)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn canary_state_reads_the_service_traffic() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| args.contains(&"describe".to_owned()) && args.ends_with(&["json".to_owned()]))
+        .times(1)
+        .returning(|_| {
+            Ok(r#"{"status": {
+                "latestReadyRevisionName": "svc-00002-def",
+                "traffic": [
+                    {"percent": 90, "revisionName": "svc-00001-abc"},
+                    {"percent": 10, "revisionName": "svc-00002-def", "tag": "canary"}
+                ]
+            }}"#
+            .to_owned())
+        });
+
+    let client = GcloudClient::with_executor(mock);
+    let canary = client
+        .canary_state("svc", "proj", "us-central1")
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(canary.revision, "svc-00002-def");
+    assert_eq!(canary.percent, 10);
+    assert_eq!(canary.stable_revision.as_deref(), Some("svc-00001-abc"));
+}
+
+#[tokio::test]
+async fn canary_state_of_missing_service_is_not_found() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec().returning(|_| {
+        Err(GcloudError::CommandFailed {
+            args: vec![],
+            stderr: "ERROR: (gcloud.run.services.describe) Cannot find service [svc]".to_owned(),
+        })
+    });
+
+    let client = GcloudClient::with_executor(mock);
+    let err = client
+        .canary_state("svc", "proj", "us-central1")
+        .await
+        .unwrap_err();
+
+    assert!(err.is_service_not_found(), "{err}");
+}
+
+#[tokio::test]
+async fn promote_canary_moves_traffic_to_latest_and_drops_tag() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.contains(&"update-traffic".to_owned())
+                && args.contains(&"--to-latest".to_owned())
+                && args
+                    .windows(2)
+                    .any(|w| w[0] == "--remove-tags" && w[1] == "canary")
+        })
+        .times(1)
+        .returning(|_| Ok(String::new()));
+    mock.expect_exec()
+        .withf(|args| args.contains(&"value(status.url)".to_owned()))
+        .returning(|_| Ok("https://svc-abc.a.run.app\n".to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    let url = client
+        .promote_canary("svc", "proj", "us-central1")
+        .await
+        .unwrap();
+
+    assert_eq!(url, "https://svc-abc.a.run.app");
+}
+
+#[tokio::test]
+async fn abort_canary_returns_traffic_to_the_stable_revision() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.contains(&"update-traffic".to_owned())
+                && args
+                    .windows(2)
+                    .any(|w| w[0] == "--to-revisions" && w[1] == "svc-00001-abc=100")
+                && args
+                    .windows(2)
+                    .any(|w| w[0] == "--remove-tags" && w[1] == "canary")
+        })
+        .times(1)
+        .returning(|_| Ok(String::new()));
+    mock.expect_exec()
+        .withf(|args| args.contains(&"value(status.url)".to_owned()))
+        .returning(|_| Ok("https://svc-abc.a.run.app\n".to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    let url = client
+        .abort_canary("svc", "proj", "us-central1", "svc-00001-abc")
+        .await
+        .unwrap();
+
+    assert_eq!(url, "https://svc-abc.a.run.app");
+}
+
+#[tokio::test]
+async fn deploy_to_cloud_run_failure() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| args.contains(&"run".to_owned()) && args.contains(&"deploy".to_owned()))
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "permission denied".to_owned(),
+            })
+        });
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client
+        .deploy_to_cloud_run(
+            "svc",
+            "tag",
+            "proj",
+            "us-central1",
+            &CloudRunConfig::default(),
+            &[],
+            &labels(),
+        )
+        .await;
+
+    assert!(matches!(result, Err(DeployError::Deploy { .. })));
+}
+
+#[tokio::test]
+async fn deploy_to_cloud_run_with_secrets() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.contains(&"--update-secrets".to_owned())
+                && args
+                    .contains(&"SUPABASE_URL=SUPABASE_URL:latest,API_KEY=API_KEY:latest".to_owned())
+        })
+        .returning(|_| Ok("https://svc-abc123-uc.a.run.app\n".to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    let secrets = vec!["SUPABASE_URL".into(), "API_KEY".into()];
+    let url = client
+        .deploy_to_cloud_run(
+            "svc",
+            "gcr.io/proj/svc:latest",
+            "proj",
+            "us-central1",
+            &CloudRunConfig::default(),
+            &secrets,
+            &labels(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(url, "https://svc-abc123-uc.a.run.app");
+}
+
+// ── Secret Manager Tests ──
+
+#[tokio::test]
+async fn set_secret_creates_new_secret() {
+    let mut mock = MockExecutor::new();
+
+    // describe → not found (secret doesn't exist)
+    mock.expect_exec()
+        .withf(|args| args.contains(&"describe".to_owned()) && args.contains(&"secrets".to_owned()))
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "NOT_FOUND".to_owned(),
+            })
+        });
+
+    // create
+    mock.expect_exec()
+        .withf(|args| args.contains(&"create".to_owned()) && args.contains(&"secrets".to_owned()))
+        .returning(|_| Ok(String::new()));
+
+    // versions add
+    mock.expect_exec_with_stdin()
+        .withf(|args, data| {
+            args.contains(&"versions".to_owned())
+                && args.contains(&"add".to_owned())
+                && data == b"super-secret-value"
+        })
+        .returning(|_, _| Ok(String::new()));
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client
+        .set_secret("proj", "MY_SECRET", "super-secret-value")
+        .await;
+
+    assert!(result.is_ok());
+}
+
+#[tokio::test]
+async fn set_secret_updates_existing() {
+    let mut mock = MockExecutor::new();
+
+    // describe → exists
+    mock.expect_exec()
+        .withf(|args| args.contains(&"describe".to_owned()) && args.contains(&"secrets".to_owned()))
+        .returning(|_| Ok("secret exists".to_owned()));
+
+    // No create call expected — goes straight to versions add
+    mock.expect_exec_with_stdin()
+        .withf(|args, _| args.contains(&"versions".to_owned()) && args.contains(&"add".to_owned()))
+        .returning(|_, _| Ok("projects/123/secrets/EXISTING/versions/4\n".to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client.set_secret("proj", "EXISTING", "new-value").await;
+
+    assert_eq!(result.unwrap(), "4");
+}
+
+#[tokio::test]
+async fn set_secret_create_fails() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| args.contains(&"describe".to_owned()))
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "NOT_FOUND".to_owned(),
+            })
+        });
+
+    mock.expect_exec()
+        .withf(|args| args.contains(&"create".to_owned()))
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "permission denied".to_owned(),
+            })
+        });
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client.set_secret("proj", "SECRET", "val").await;
+
+    assert!(matches!(result, Err(SecretError::Create { .. })));
+}
+
+#[tokio::test]
+async fn list_secrets_returns_names() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.contains(&"secrets".to_owned())
+                && args.contains(&"list".to_owned())
+                && args.contains(&"value(name)".to_owned())
+        })
+        .returning(|_| Ok("SUPABASE_URL\nSUPABASE_KEY\nJWT_SECRET\n".to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    let secrets = client.list_secrets("proj").await.unwrap();
+
+    assert_eq!(secrets, vec!["SUPABASE_URL", "SUPABASE_KEY", "JWT_SECRET"]);
+}
+
+#[tokio::test]
+async fn list_secrets_empty() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| args.contains(&"secrets".to_owned()) && args.contains(&"list".to_owned()))
+        .returning(|_| Ok(String::new()));
+
+    let client = GcloudClient::with_executor(mock);
+    let secrets = client.list_secrets("proj").await.unwrap();
+
+    assert!(secrets.is_empty());
+}
+
+#[tokio::test]
+async fn access_secret_returns_payload_verbatim() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.starts_with(&[
+                "secrets".to_owned(),
+                "versions".to_owned(),
+                "access".to_owned(),
+                "latest".to_owned(),
+            ]) && args.contains(&"DB_URL".to_owned())
+        })
+        .returning(|_| Ok("line1\nline2\n".to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    let value = client.access_secret("proj", "DB_URL").await.unwrap();
+
+    assert_eq!(value, "line1\nline2\n");
+}
+
+#[tokio::test]
+async fn access_secret_failure_names_secret() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec().returning(|_| {
+        Err(GcloudError::CommandFailed {
+            args: vec![],
+            stderr: "PERMISSION_DENIED".to_owned(),
+        })
+    });
+
+    let client = GcloudClient::with_executor(mock);
+    let err = client.access_secret("proj", "DB_URL").await.unwrap_err();
+
+    assert!(matches!(err, SecretError::Access { ref name, .. } if name == "DB_URL"));
+}
+
+#[tokio::test]
+async fn get_project_number_returns_number() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.contains(&"projects".to_owned())
+                && args.contains(&"describe".to_owned())
+                && args.contains(&"value(projectNumber)".to_owned())
+        })
+        .returning(|_| Ok("123456789\n".to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    let number = client.get_project_number("my-project").await.unwrap();
+
+    assert_eq!(number, "123456789");
+}
+
+#[tokio::test]
+async fn list_projects_parses_active_projects() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args: &[String]| args == ["projects", "list", "--format", "json"])
+        .returning(|_| {
+            Ok(r#"[
+                {"projectId": "prod-api", "name": "Prod API", "lifecycleState": "ACTIVE"},
+                {"projectId": "old-api", "name": "Old", "lifecycleState": "DELETE_REQUESTED"},
+                {"projectId": "sandbox"}
+            ]"#
+            .to_owned())
+        });
+
+    let client = GcloudClient::with_executor(mock);
+    let projects = client.list_projects().await.unwrap();
+
+    assert_eq!(
+        projects,
+        [
+            propel_cloud::GcpProject {
+                id: "prod-api".to_owned(),
+                name: "Prod API".to_owned(),
+            },
+            propel_cloud::GcpProject {
+                id: "sandbox".to_owned(),
+                name: String::new(),
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn list_projects_reports_failure() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec().returning(|args| {
+        Err(GcloudError::CommandFailed {
+            args: args.to_vec(),
+            stderr: "not logged in".to_owned(),
+        })
+    });
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client.list_projects().await;
+
+    assert!(matches!(result, Err(PreflightError::ProjectList { .. })));
+}
+
+#[tokio::test]
+async fn list_run_regions_reads_location_ids() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args: &[String]| {
+            args == [
+                "run",
+                "regions",
+                "list",
+                "--project",
+                "prod-api",
+                "--format",
+                "value(locationId)",
+            ]
+        })
+        .returning(|_| Ok("asia-northeast1\nus-central1\n\n".to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    let regions = client.list_run_regions("prod-api").await.unwrap();
+
+    assert_eq!(regions, ["asia-northeast1", "us-central1"]);
+}
+
+#[tokio::test]
+async fn billing_enabled_reads_billing_status() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| args.contains(&"billing".to_owned()) && args.contains(&"prod-api".to_owned()))
+        .returning(|_| Ok("False\n".to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+
+    assert!(!client.billing_enabled("prod-api").await.unwrap());
+}
+
+#[tokio::test]
+async fn describe_secret_reads_creation_time_and_versions() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args: &[String]| {
+            args == [
+                "secrets",
+                "describe",
+                "staging_DB_URL",
+                "--project",
+                "proj",
+                "--format",
+                "json",
+            ]
+        })
+        .returning(|_| {
+            Ok(r#"{"name": "projects/123/secrets/staging_DB_URL",
+                   "createTime": "2025-01-02T03:04:05.000000Z",
+                   "replication": {"automatic": {}}}"#
+                .to_owned())
+        });
+    mock.expect_exec()
+        .withf(|args: &[String]| {
+            args == [
+                "secrets",
+                "versions",
+                "list",
+                "staging_DB_URL",
+                "--project",
+                "proj",
+                "--format",
+                "json",
+            ]
+        })
+        .returning(|_| {
+            Ok(r#"[
+                {"name": "projects/123/secrets/staging_DB_URL/versions/2",
+                 "state": "ENABLED", "createTime": "2025-02-01T00:00:00Z"},
+                {"name": "projects/123/secrets/staging_DB_URL/versions/10",
+                 "state": "DISABLED", "createTime": "2025-03-01T00:00:00Z"},
+                {"name": "projects/123/secrets/staging_DB_URL/versions/1",
+                 "state": "DESTROYED", "createTime": "2025-01-02T03:04:05Z"}
+            ]"#
+            .to_owned())
+        });
+
+    let client = GcloudClient::with_executor(mock);
+    let info = client
+        .describe_secret("proj", "staging_DB_URL")
+        .await
+        .unwrap();
+
+    assert_eq!(info.name, "staging_DB_URL");
+    assert_eq!(info.create_time, "2025-01-02T03:04:05.000000Z");
+    let versions: Vec<(&str, SecretVersionState)> = info
+        .versions
+        .iter()
+        .map(|v| (v.version.as_str(), v.state))
+        .collect();
+    assert_eq!(
+        versions,
+        [
+            ("10", SecretVersionState::Disabled),
+            ("2", SecretVersionState::Enabled),
+            ("1", SecretVersionState::Destroyed),
+        ]
+    );
+    assert_eq!(info.latest().unwrap().create_time, "2025-03-01T00:00:00Z");
+    assert!(info.has_enabled_version());
+}
+
+#[tokio::test]
+async fn describe_secret_without_versions_has_none_enabled() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| args.contains(&"describe".to_owned()))
+        .returning(|_| Ok(r#"{"createTime": "2025-01-02T03:04:05Z"}"#.to_owned()));
+    mock.expect_exec()
+        .withf(|args| args.contains(&"versions".to_owned()))
+        .returning(|_| Ok("[]".to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    let info = client.describe_secret("proj", "EMPTY").await.unwrap();
+
+    assert!(info.latest().is_none());
+    assert!(!info.has_enabled_version());
+}
+
+#[tokio::test]
+async fn describe_secret_reports_missing_secret() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec().returning(|args| {
+        Err(GcloudError::CommandFailed {
+            args: args.to_vec(),
+            stderr: "NOT_FOUND: Secret [projects/123/secrets/NOPE] not found".to_owned(),
+        })
+    });
+
+    let client = GcloudClient::with_executor(mock);
+    let err = client.describe_secret("proj", "NOPE").await.unwrap_err();
+
+    assert!(
+        matches!(&err, SecretError::Describe { name, .. } if name == "NOPE"),
+        "{err:?}"
+    );
+}
+
+#[tokio::test]
+async fn describe_secret_reports_unexpected_output() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec().returning(|_| Ok("not json".to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    let err = client.describe_secret("proj", "DB").await.unwrap_err();
+
+    assert!(matches!(err, SecretError::DescribeParse { .. }), "{err:?}");
+}
+
+#[tokio::test]
+async fn grant_secret_access_calls_add_iam_policy_binding() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.contains(&"add-iam-policy-binding".to_owned())
+                && args.contains(&"MY_SECRET".to_owned())
+                && args.contains(
+                    &"serviceAccount:123-compute@developer.gserviceaccount.com".to_owned(),
+                )
+                && args.contains(&"roles/secretmanager.secretAccessor".to_owned())
+        })
+        .returning(|_| Ok(String::new()));
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client
+        .grant_secret_access(
+            "proj",
+            "MY_SECRET",
+            "123-compute@developer.gserviceaccount.com",
+        )
+        .await;
+
+    assert!(result.is_ok());
+}
+
+#[tokio::test]
+async fn grant_secret_access_failure() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| args.contains(&"add-iam-policy-binding".to_owned()))
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "permission denied".to_owned(),
+            })
+        });
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client
+        .grant_secret_access("proj", "SECRET", "sa@example.com")
+        .await;
+
+    assert!(matches!(result, Err(SecretError::GrantAccess { .. })));
+}
+
+#[tokio::test]
+async fn revoke_secret_access_calls_remove_iam_policy_binding() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.contains(&"remove-iam-policy-binding".to_owned())
+                && args.contains(&"MY_SECRET".to_owned())
+                && args.contains(
+                    &"serviceAccount:123-compute@developer.gserviceaccount.com".to_owned(),
+                )
+                && args.contains(&"roles/secretmanager.secretAccessor".to_owned())
+        })
+        .returning(|_| Ok(String::new()));
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client
+        .revoke_secret_access(
+            "proj",
+            "MY_SECRET",
+            "123-compute@developer.gserviceaccount.com",
+        )
+        .await;
+
+    assert!(result.is_ok());
+}
+
+#[tokio::test]
+async fn revoke_secret_access_failure() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| args.contains(&"remove-iam-policy-binding".to_owned()))
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "not found".to_owned(),
+            })
+        });
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client
+        .revoke_secret_access("proj", "SECRET", "sa@example.com")
+        .await;
+
+    assert!(matches!(result, Err(SecretError::RevokeAccess { .. })));
+}
+
+#[tokio::test]
+async fn delete_secret_success() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.contains(&"secrets".to_owned())
+                && args.contains(&"delete".to_owned())
+                && args.contains(&"MY_SECRET".to_owned())
+                && args.contains(&"--quiet".to_owned())
+        })
+        .returning(|_| Ok(String::new()));
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client.delete_secret("proj", "MY_SECRET").await;
+
+    assert!(result.is_ok());
+}
+
+#[tokio::test]
+async fn delete_secret_failure() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| args.contains(&"delete".to_owned()) && args.contains(&"secrets".to_owned()))
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "NOT_FOUND".to_owned(),
+            })
+        });
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client.delete_secret("proj", "GONE").await;
+
+    assert!(matches!(result, Err(SecretError::Delete { .. })));
+}
+
+// ── WIF Pool Tests ──
+
+#[tokio::test]
+async fn ensure_wif_pool_creates_new() {
+    let mut mock = MockExecutor::new();
+
+    // create succeeds
+    mock.expect_exec()
+        .withf(|args| {
+            args.contains(&"workload-identity-pools".to_owned())
+                && args.contains(&"create".to_owned())
+                && args.contains(&"propel-github".to_owned())
+                && args.contains(&"global".to_owned())
+        })
+        .returning(|_| Ok(String::new()));
+
+    let client = GcloudClient::with_executor(mock);
+    let created = client
+        .ensure_wif_pool("proj", "propel-github")
+        .await
+        .unwrap();
+
+    assert!(created);
+}
+
+#[tokio::test]
+async fn ensure_wif_pool_already_exists() {
+    let mut mock = MockExecutor::new();
+
+    // create fails with ALREADY_EXISTS
+    mock.expect_exec()
+        .withf(|args| {
+            args.contains(&"workload-identity-pools".to_owned())
+                && args.contains(&"create".to_owned())
+        })
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "ALREADY_EXISTS: resource already exists".to_owned(),
+            })
+        });
+
+    let client = GcloudClient::with_executor(mock);
+    let created = client
+        .ensure_wif_pool("proj", "propel-github")
+        .await
+        .unwrap();
+
+    assert!(!created);
+}
+
+#[tokio::test]
+async fn ensure_wif_pool_create_fails() {
+    let mut mock = MockExecutor::new();
+
+    // create fails with non-ALREADY_EXISTS error
+    mock.expect_exec()
+        .withf(|args| args.contains(&"create".to_owned()))
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "permission denied".to_owned(),
+            })
+        });
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client.ensure_wif_pool("proj", "propel-github").await;
+
+    assert!(matches!(result, Err(WifError::CreatePool { .. })));
+}
+
+// ── OIDC Provider Tests ──
+
+#[tokio::test]
+async fn ensure_oidc_provider_creates_new() {
+    let mut mock = MockExecutor::new();
+
+    // create-oidc succeeds
+    mock.expect_exec()
+        .withf(|args| {
+            args.contains(&"create-oidc".to_owned())
+                && args.contains(&"github".to_owned())
+                && args
+                    .iter()
+                    .any(|a| a.contains("token.actions.githubusercontent.com"))
+                && args
+                    .iter()
+                    .any(|a| a.contains("assertion.repository == 'owner/repo'"))
+        })
+        .returning(|_| Ok(String::new()));
+
+    let client = GcloudClient::with_executor(mock);
+    let created = client
+        .ensure_oidc_provider("proj", "propel-github", "github", "owner/repo")
+        .await
+        .unwrap();
+
+    assert!(created);
+}
+
+#[tokio::test]
+async fn ensure_oidc_provider_already_exists() {
+    let mut mock = MockExecutor::new();
+
+    // create-oidc fails with ALREADY_EXISTS
+    mock.expect_exec()
+        .withf(|args| args.contains(&"create-oidc".to_owned()))
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "ALREADY_EXISTS: provider already exists".to_owned(),
+            })
+        });
+
+    let client = GcloudClient::with_executor(mock);
+    let created = client
+        .ensure_oidc_provider("proj", "propel-github", "github", "owner/repo")
+        .await
+        .unwrap();
+
+    assert!(!created);
+}
+
+// ── Service Account Tests ──
+
+#[tokio::test]
+async fn ensure_service_account_creates_new() {
+    let mut mock = MockExecutor::new();
+
+    // create succeeds
+    mock.expect_exec()
+        .withf(|args| {
+            args.contains(&"service-accounts".to_owned())
+                && args.contains(&"create".to_owned())
+                && args.contains(&"propel-deploy".to_owned())
+        })
+        .returning(|_| Ok(String::new()));
+
+    let client = GcloudClient::with_executor(mock);
+    let created = client
+        .ensure_service_account("proj", "propel-deploy", "Propel CI Deploy")
+        .await
+        .unwrap();
+
+    assert!(created);
+}
+
+#[tokio::test]
+async fn ensure_service_account_already_exists() {
+    let mut mock = MockExecutor::new();
+
+    // create fails with already exists
+    mock.expect_exec()
+        .withf(|args| {
+            args.contains(&"service-accounts".to_owned()) && args.contains(&"create".to_owned())
+        })
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "Service account already exists".to_owned(),
+            })
+        });
+
+    let client = GcloudClient::with_executor(mock);
+    let created = client
+        .ensure_service_account("proj", "propel-deploy", "Propel CI Deploy")
+        .await
+        .unwrap();
+
+    assert!(!created);
+}
+
+// ── IAM Role Binding Tests ──
+
+#[tokio::test]
+async fn bind_iam_roles_success() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.contains(&"add-iam-policy-binding".to_owned())
+                && args.contains(&"projects".to_owned())
+        })
+        .times(2)
+        .returning(|_| Ok(String::new()));
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client
+        .bind_iam_roles(
+            "proj",
+            "sa@proj.iam.gserviceaccount.com",
+            &["roles/run.admin", "roles/cloudbuild.builds.editor"],
+        )
+        .await;
+
+    assert!(result.is_ok());
+}
+
+#[tokio::test]
+async fn bind_iam_roles_partial_failure() {
+    let mut mock = MockExecutor::new();
+
+    // First role succeeds
+    mock.expect_exec()
+        .withf(|args| args.contains(&"roles/run.admin".to_owned()))
+        .returning(|_| Ok(String::new()));
+
+    // Second role fails
+    mock.expect_exec()
+        .withf(|args| args.contains(&"roles/cloudbuild.builds.editor".to_owned()))
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "permission denied".to_owned(),
+            })
+        });
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client
+        .bind_iam_roles(
+            "proj",
+            "sa@proj.iam.gserviceaccount.com",
+            &["roles/run.admin", "roles/cloudbuild.builds.editor"],
+        )
+        .await;
+
+    assert!(matches!(
+        result,
+        Err(WifError::BindRole { ref role, .. }) if role == "roles/cloudbuild.builds.editor"
+    ));
+}
+
+// ── WIF → SA Binding Tests ──
+
+#[tokio::test]
+async fn bind_wif_to_sa_success() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.contains(&"add-iam-policy-binding".to_owned())
+                && args.contains(&"roles/iam.workloadIdentityUser".to_owned())
+                && args
+                    .iter()
+                    .any(|a| a.contains("attribute.repository/owner/repo"))
+        })
+        .returning(|_| Ok(String::new()));
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client
+        .bind_wif_to_sa(
+            "proj",
+            "123456",
+            "propel-github",
+            "sa@proj.iam.gserviceaccount.com",
+            "owner/repo",
+        )
+        .await;
+
+    assert!(result.is_ok());
+}
+
+#[tokio::test]
+async fn bind_wif_to_sa_failure() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| args.contains(&"add-iam-policy-binding".to_owned()))
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "failed".to_owned(),
+            })
+        });
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client
+        .bind_wif_to_sa("proj", "123456", "pool", "sa@example.com", "owner/repo")
+        .await;
+
+    assert!(matches!(result, Err(WifError::BindWif { .. })));
+}
+
+#[tokio::test]
+async fn grant_service_account_user_binds_on_target_sa() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.starts_with(&[
+                "iam".to_owned(),
+                "service-accounts".to_owned(),
+                "add-iam-policy-binding".to_owned(),
+                "propel-build@proj.iam.gserviceaccount.com".to_owned(),
+            ]) && args.contains(&"roles/iam.serviceAccountUser".to_owned())
+                && args.contains(
+                    &"serviceAccount:propel-deploy@proj.iam.gserviceaccount.com".to_owned(),
+                )
+        })
+        .returning(|_| Ok(String::new()));
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client
+        .grant_service_account_user(
+            "proj",
+            "propel-build@proj.iam.gserviceaccount.com",
+            "propel-deploy@proj.iam.gserviceaccount.com",
+        )
+        .await;
+
+    assert!(result.is_ok());
+}
+
+// ── Delete WIF Pool Tests ──
+
+#[tokio::test]
+async fn delete_wif_pool_success() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.contains(&"workload-identity-pools".to_owned())
+                && args.contains(&"delete".to_owned())
+                && args.contains(&"propel-github".to_owned())
+                && args.contains(&"--quiet".to_owned())
+        })
+        .returning(|_| Ok(String::new()));
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client.delete_wif_pool("proj", "propel-github").await;
+
+    assert!(result.is_ok());
+}
+
+#[tokio::test]
+async fn delete_wif_pool_failure() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.contains(&"workload-identity-pools".to_owned())
+                && args.contains(&"delete".to_owned())
+        })
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "NOT_FOUND".to_owned(),
+            })
+        });
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client.delete_wif_pool("proj", "propel-github").await;
+
+    assert!(matches!(result, Err(WifError::DeletePool { .. })));
+}
+
+// ── Delete Service Account Tests ──
+
+#[tokio::test]
+async fn delete_service_account_success() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.contains(&"service-accounts".to_owned())
+                && args.contains(&"delete".to_owned())
+                && args.contains(&"--quiet".to_owned())
+        })
+        .returning(|_| Ok(String::new()));
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client
+        .delete_service_account("proj", "sa@proj.iam.gserviceaccount.com")
+        .await;
+
+    assert!(result.is_ok());
+}
+
+#[tokio::test]
+async fn delete_service_account_failure() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.contains(&"service-accounts".to_owned()) && args.contains(&"delete".to_owned())
+        })
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "NOT_FOUND".to_owned(),
+            })
+        });
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client
+        .delete_service_account("proj", "sa@proj.iam.gserviceaccount.com")
+        .await;
+
+    assert!(matches!(result, Err(WifError::DeleteServiceAccount { .. })));
+}
+
+// ── Logs Tests ──
+
+#[tokio::test]
+async fn read_logs_with_custom_limit() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec_streaming()
+        .withf(|args| {
+            args.contains(&"logs".to_owned())
+                && args.contains(&"read".to_owned())
+                && args.contains(&"50".to_owned())
+        })
+        .returning(|_| Ok(()));
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client.read_logs("my-svc", "proj", "us-central1", 50).await;
+
+    assert!(result.is_ok());
+}
+
+#[tokio::test]
+async fn read_logs_failure() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec_streaming()
+        .withf(|args| args.contains(&"read".to_owned()))
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "not found".to_owned(),
+            })
+        });
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client.read_logs("svc", "proj", "us-central1", 100).await;
+
+    assert!(matches!(result, Err(DeployError::Logs { .. })));
+}
+
+#[tokio::test]
+async fn read_logs_captured_returns_output() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.contains(&"logs".to_owned())
+                && args.contains(&"read".to_owned())
+                && args.contains(&"50".to_owned())
+        })
+        .returning(|_| Ok("2024-01-01 INFO request served\n".to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    let output = client
+        .read_logs_captured("my-svc", "proj", "us-central1", 50)
+        .await
+        .unwrap();
+
+    assert!(output.contains("request served"));
+}
+
+#[tokio::test]
+async fn read_logs_captured_failure() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| args.contains(&"read".to_owned()))
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "not found".to_owned(),
+            })
+        });
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client
+        .read_logs_captured("svc", "proj", "us-central1", 100)
+        .await;
+
+    assert!(matches!(result, Err(DeployError::Logs { .. })));
+}
+
+#[tokio::test]
+async fn tail_logs_success() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec_streaming()
+        .withf(|args| {
+            args.contains(&"logs".to_owned())
+                && args.contains(&"tail".to_owned())
+                && args.contains(&"my-svc".to_owned())
+        })
+        .returning(|_| Ok(()));
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client.tail_logs("my-svc", "proj", "us-central1").await;
+
+    assert!(result.is_ok());
+}
+
+#[tokio::test]
+async fn tail_logs_failure() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec_streaming()
+        .withf(|args| args.contains(&"tail".to_owned()))
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "not found".to_owned(),
+            })
+        });
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client.tail_logs("svc", "proj", "us-central1").await;
+
+    assert!(matches!(result, Err(DeployError::Logs { .. })));
+}
+
+// ── DoctorReport Display ──
+
+#[test]
+fn doctor_report_display_all_passed() {
+    let report = propel_cloud::DoctorReport {
+        gcloud: propel_cloud::CheckResult::ok("1.0.0"),
+        components: propel_cloud::CheckResult::ok("core"),
+        account: propel_cloud::CheckResult::ok("user@example.com"),
+        project: propel_cloud::CheckResult::ok("my-project"),
+        billing: propel_cloud::CheckResult::ok("Enabled"),
+        apis: vec![propel_cloud::ApiCheck {
+            id: "api.run",
+            name: "run.googleapis.com".to_string(),
+            result: propel_cloud::CheckResult::ok("Enabled"),
+        }],
+        iam: None,
+        runtime_service_account: None,
+        cloud_sql_api: None,
+        config_file: propel_cloud::CheckResult::ok("Found"),
+        toolchain: propel_cloud::CheckResult::ok("cargo 1.85.0"),
+        git: propel_cloud::CheckResult::ok("git version 2.43.0"),
+        notes: vec![],
+    };
+
+    let output = report.to_string();
+    assert!(output.contains("Propel Doctor"));
+    assert!(output.contains("gcloud CLI"));
+    assert!(output.contains("OK"));
+    assert!(output.contains("All checks passed!"));
+    assert!(!output.contains("NG"));
+}
+
+#[test]
+fn doctor_report_display_with_failures() {
+    let report = propel_cloud::DoctorReport {
+        gcloud: propel_cloud::CheckResult::ok("1.0.0"),
+        components: propel_cloud::CheckResult::ok("core"),
+        account: propel_cloud::CheckResult::ok("user@example.com"),
+        project: propel_cloud::CheckResult::fail("Not set"),
+        billing: propel_cloud::CheckResult::fail("Unknown"),
+        apis: vec![],
+        iam: None,
+        runtime_service_account: None,
+        cloud_sql_api: None,
+        config_file: propel_cloud::CheckResult::fail("Not found"),
+        toolchain: propel_cloud::CheckResult::ok("cargo 1.85.0"),
+        git: propel_cloud::CheckResult::ok("git version 2.43.0"),
+        notes: vec![],
+    };
+
+    let output = report.to_string();
+    assert!(output.contains("NG"));
+    assert!(output.contains("Not set"));
+    assert!(output.contains("Not found"));
+    assert!(output.contains("Some checks failed"));
+    assert!(!output.contains("All checks passed!"));
+}
+
+#[test]
+fn doctor_report_display_apis_shown() {
+    let report = propel_cloud::DoctorReport {
+        gcloud: propel_cloud::CheckResult::ok("1.0.0"),
+        components: propel_cloud::CheckResult::ok("core"),
+        account: propel_cloud::CheckResult::ok("user@example.com"),
+        project: propel_cloud::CheckResult::ok("proj"),
+        billing: propel_cloud::CheckResult::ok("Enabled"),
+        apis: vec![
+            propel_cloud::ApiCheck {
+                id: "api.run",
+                name: "run.googleapis.com".to_string(),
+                result: propel_cloud::CheckResult::ok("Enabled"),
+            },
+            propel_cloud::ApiCheck {
+                id: "api.cloudbuild",
+                name: "cloudbuild.googleapis.com".to_string(),
+                result: propel_cloud::CheckResult::fail("Disabled"),
+            },
+        ],
+        iam: None,
+        runtime_service_account: None,
+        cloud_sql_api: None,
+        config_file: propel_cloud::CheckResult::ok("Found"),
+        toolchain: propel_cloud::CheckResult::ok("cargo 1.85.0"),
+        git: propel_cloud::CheckResult::ok("git version 2.43.0"),
+        notes: vec![],
+    };
+
+    let output = report.to_string();
+    assert!(output.contains("run.googleapis.com API"));
+    assert!(output.contains("cloudbuild.googleapis.com API"));
+    assert!(output.contains("Disabled"));
+}
+
+#[test]
+fn doctor_report_display_shows_notes_without_failing() {
+    let report = propel_cloud::DoctorReport {
+        gcloud: propel_cloud::CheckResult::ok("1.0.0"),
+        components: propel_cloud::CheckResult::ok("core"),
+        account: propel_cloud::CheckResult::ok("user@example.com"),
+        project: propel_cloud::CheckResult::ok("my-project"),
+        billing: propel_cloud::CheckResult::ok("Enabled"),
+        apis: vec![],
+        iam: None,
+        runtime_service_account: None,
+        cloud_sql_api: None,
+        config_file: propel_cloud::CheckResult::ok("Found"),
+        toolchain: propel_cloud::CheckResult::ok("cargo 1.85.0"),
+        git: propel_cloud::CheckResult::ok("git version 2.43.0"),
+        notes: vec!["org policy constraints/cloudbuild.allowedWorkerPools is set".to_owned()],
+    };
+
+    let output = report.to_string();
+    assert!(output.contains("Note: org policy constraints/cloudbuild.allowedWorkerPools"));
+    assert!(report.all_passed());
+}
+
+#[test]
+fn doctor_report_iam_row_counts_unless_skipped() {
+    let mut report = propel_cloud::DoctorReport {
+        gcloud: propel_cloud::CheckResult::ok("1.0.0"),
+        components: propel_cloud::CheckResult::ok("core"),
+        account: propel_cloud::CheckResult::ok("user@example.com"),
+        project: propel_cloud::CheckResult::ok("my-project"),
+        billing: propel_cloud::CheckResult::ok("Enabled"),
+        apis: vec![],
+        iam: Some(propel_cloud::CheckResult::fail(
+            "missing:\nrun.services.update (grant roles/run.developer or roles/run.admin)",
+        )),
+        runtime_service_account: None,
+        cloud_sql_api: None,
+        config_file: propel_cloud::CheckResult::ok("Found"),
+        toolchain: propel_cloud::CheckResult::ok("cargo 1.85.0"),
+        git: propel_cloud::CheckResult::ok("git version 2.43.0"),
+        notes: vec![],
+    };
+
+    let output = report.to_string();
+    assert!(output.contains("IAM permissions"));
+    assert!(output.contains("run.services.update"));
+    assert!(!report.all_passed());
+
+    report.iam = Some(propel_cloud::CheckResult::skipped("--skip-iam-check"));
+    assert!(report.all_passed());
+}
+
+#[tokio::test]
+async fn doctor_check_ids_are_stable() {
+    let mut mock = MockExecutor::new();
+    mock.expect_exec().returning(|args| {
+        Err(GcloudError::CommandFailed {
+            args: args.to_vec(),
+            stderr: String::new(),
+        })
+    });
+    let client = GcloudClient::with_executor(mock);
+    let mut report = client.doctor(Some("proj"), &[]).await;
+    report.iam = Some(propel_cloud::CheckResult::skipped("--skip-iam-check"));
+    report.runtime_service_account = Some(propel_cloud::CheckResult::skipped("test"));
+    report.cloud_sql_api = Some(propel_cloud::CheckResult::skipped("test"));
+
+    // Scripts and MCP clients match on these: renaming one is a breaking change.
+    let ids: Vec<&str> = report.checks().iter().map(|check| check.id).collect();
+    assert_eq!(
+        ids,
+        [
+            "gcloud.installed",
+            "gcloud.components",
+            "gcp.account",
+            "gcp.project",
+            "gcp.billing",
+            "api.cloudbuild",
+            "api.run",
+            "api.secretmanager",
+            "api.artifactregistry",
+            "api.sqladmin",
+            "iam.permissions",
+            "iam.runtime_service_account",
+            "config.file",
+            "rust.toolchain",
+            "git.installed",
+        ]
+    );
+}
+
+#[test]
+fn doctor_report_json_carries_check_ids() {
+    let report = propel_cloud::DoctorReport {
+        billing: propel_cloud::CheckResult::fail("Disabled"),
+        notes: vec!["a note".to_owned()],
+        ..propel_cloud::DoctorReport::default()
+    };
+
+    let json = report.to_json();
+
+    assert_eq!(json["all_passed"], false);
+    assert_eq!(json["notes"][0], "a note");
+    let billing = &json["checks"][4];
+    assert_eq!(billing["id"], "gcp.billing");
+    assert_eq!(billing["label"], "Billing");
+    assert_eq!(billing["passed"], false);
+    assert_eq!(billing["skipped"], false);
+    assert_eq!(billing["detail"], "Disabled");
+}
+
+// ── Doctor Skip Cascade ──
+
+const PYTHON_MISMATCH_STDERR: &str = "\
+ERROR: gcloud failed to load: No module named 'imp'
+This usually indicates corruption in your gcloud installation.";
+
+fn local_rows_ok(report: &mut propel_cloud::DoctorReport) {
+    report.config_file = propel_cloud::CheckResult::ok("Found");
+    report.toolchain = propel_cloud::CheckResult::ok("cargo 1.85.0");
+    report.git = propel_cloud::CheckResult::ok("git version 2.43.0");
+}
+
+/// Billing and every API row were skipped for `reason` (the project row is
+/// asserted by each test, since it is only skipped when gcloud is broken).
+fn assert_project_rows_skipped(report: &propel_cloud::DoctorReport, reason: &str) {
+    let expected = format!("skipped ({reason})");
+    assert!(report.billing.skipped);
+    assert_eq!(report.billing.detail, expected);
+    assert_eq!(report.apis.len(), 4);
+    for api in &report.apis {
+        assert!(api.result.skipped, "{} not skipped", api.name);
+        assert_eq!(api.result.detail, expected);
+    }
+}
+
+#[tokio::test]
+async fn doctor_broken_gcloud_skips_every_gcloud_row() {
+    let mut mock = MockExecutor::new();
+    mock.expect_exec()
+        .withf(|args: &[String]| args == ["version"])
+        .times(1)
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec!["version".to_owned()],
+                stderr: PYTHON_MISMATCH_STDERR.to_owned(),
+            })
+        });
+    // Any further gcloud call would hit this expectation and fail the test.
+    mock.expect_exec().times(0);
+
+    let client = GcloudClient::with_executor(mock);
+    let mut report = client.doctor(Some("proj"), &[]).await;
+    local_rows_ok(&mut report);
+
+    assert!(!report.gcloud.passed);
+    assert!(!report.gcloud.skipped);
+    assert!(report.gcloud.detail.contains("No module named 'imp'"));
+    assert!(report.components.skipped);
+    assert!(report.account.skipped);
+    assert_project_rows_skipped(&report, "gcloud unavailable");
+    assert!(report.project.skipped);
+    assert!(report.notes.is_empty());
+    assert!(!report.all_passed());
+
+    let output = report.to_string();
+    assert!(output.contains("skipped (gcloud unavailable)"));
+    assert!(output.contains("This usually indicates corruption"));
+    assert!(output.contains("Rust toolchain"));
+    assert!(!output.contains("Check failed"));
+}
+
+#[tokio::test]
+async fn doctor_missing_gcloud_reports_install_hint() {
+    let mut mock = MockExecutor::new();
+    mock.expect_exec().times(1).returning(|_| {
+        Err(GcloudError::NotFound {
+            source: std::io::Error::from(std::io::ErrorKind::NotFound),
+        })
+    });
+
+    let client = GcloudClient::with_executor(mock);
+    let report = client.doctor(Some("proj"), &[]).await;
+
+    assert!(report.gcloud.detail.contains("install"));
+    assert!(report.account.skipped);
+    assert_project_rows_skipped(&report, "gcloud unavailable");
+}
+
+#[tokio::test]
+async fn doctor_without_project_id_skips_project_rows() {
+    let mut mock = MockExecutor::new();
+    mock.expect_exec()
+        .withf(|args: &[String]| args == ["version"])
+        .returning(|_| Ok("Google Cloud SDK 555.0.0\ncore 2026.01.09\n".to_owned()));
+    mock.expect_exec()
+        .withf(|args: &[String]| args.starts_with(&["config".to_owned()]))
+        .returning(|_| Ok("user@example.com\n".to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    let report = client.doctor(None, &[]).await;
+
+    assert!(report.gcloud.passed);
+    assert!(report.account.passed);
+    assert!(!report.project.skipped);
+    assert!(report.project.detail.contains("gcp_project_id not set"));
+    assert_project_rows_skipped(&report, "gcp_project_id not set");
+}
+
+#[tokio::test]
+async fn doctor_inaccessible_project_skips_billing_and_apis() {
+    let mut mock = MockExecutor::new();
+    mock.expect_exec()
+        .withf(|args: &[String]| args == ["version"])
+        .returning(|_| Ok("Google Cloud SDK 555.0.0\ncore 2026.01.09\n".to_owned()));
+    mock.expect_exec()
+        .withf(|args: &[String]| args.starts_with(&["config".to_owned()]))
+        .returning(|_| Ok("user@example.com\n".to_owned()));
+    mock.expect_exec()
+        .withf(|args: &[String]| args.starts_with(&["projects".to_owned()]))
+        .times(1)
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "PERMISSION_DENIED".to_owned(),
+            })
+        });
+
+    let client = GcloudClient::with_executor(mock);
+    let report = client.doctor(Some("proj"), &[]).await;
+
+    assert!(!report.project.passed);
+    assert!(!report.project.skipped);
+    assert_project_rows_skipped(&report, "project not accessible");
+}
+
+/// Mock for a doctor run against `proj`: every call succeeds, and each
+/// `services list` reports its API enabled and counts toward `api_calls`.
+fn doctor_mock(api_calls: usize) -> MockExecutor {
+    let is_api_check = |args: &[String]| args.starts_with(&["services".to_owned()]);
+    let mut mock = MockExecutor::new();
+    mock.expect_exec()
+        .withf(move |args: &[String]| is_api_check(args))
+        .times(api_calls)
+        .returning(|args| Ok(args[5].trim_start_matches("config.name=").to_owned()));
+    mock.expect_exec()
+        .withf(move |args: &[String]| !is_api_check(args))
+        .returning(|_| Ok("ok\n".to_owned()));
+    mock
+}
+
+#[tokio::test]
+async fn doctor_reuses_cached_api_checks() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = propel_core::DiskCache::new(dir.path());
+
+    let client = GcloudClient::with_executor(doctor_mock(4)).doctor_cache(cache.clone());
+    let report = client.doctor(Some("proj"), &[]).await;
+    assert!(report.apis.iter().all(|api| api.result.detail == "Enabled"));
+
+    let client = GcloudClient::with_executor(doctor_mock(0)).doctor_cache(cache);
+    let report = client.doctor(Some("proj"), &[]).await;
+    assert_eq!(report.apis.len(), 4);
+    assert!(
+        report
+            .apis
+            .iter()
+            .all(|api| api.result.passed && api.result.detail == "Enabled (cached)")
+    );
+}
+
+#[tokio::test]
+async fn doctor_does_not_cache_a_disabled_api() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = propel_core::DiskCache::new(dir.path());
+
+    let mut mock = MockExecutor::new();
+    mock.expect_exec()
+        .withf(|args: &[String]| args.starts_with(&["services".to_owned()]))
+        .times(4)
+        .returning(|_| Ok(String::new()));
+    mock.expect_exec().returning(|_| Ok("ok\n".to_owned()));
+    let client = GcloudClient::with_executor(mock).doctor_cache(cache.clone());
+    let report = client.doctor(Some("proj"), &[]).await;
+    assert!(report.apis.iter().all(|api| !api.result.passed));
+
+    // The next run checks again and sees the APIs enabled since.
+    let client = GcloudClient::with_executor(doctor_mock(4)).doctor_cache(cache);
+    let report = client.doctor(Some("proj"), &[]).await;
+    assert!(report.apis.iter().all(|api| api.result.detail == "Enabled"));
+}
+
+#[test]
+fn skipped_check_has_distinct_icon() {
+    let skipped = propel_cloud::CheckResult::skipped("gcloud unavailable");
+    assert_eq!(skipped.icon(), "--");
+    assert!(!skipped.passed);
+    assert_eq!(propel_cloud::CheckResult::fail("x").icon(), "NG");
+}
+
+// ── Shared CI Resource Tests ──
+
+const OUR_PRINCIPAL: &str = "principalSet://iam.googleapis.com/projects/123/locations/global/workloadIdentityPools/svc-github/attribute.repository/acme/svc";
+const OTHER_PRINCIPAL: &str = "principalSet://iam.googleapis.com/projects/123/locations/global/workloadIdentityPools/svc-github/attribute.repository/acme/other";
+
+fn sa_policy_json(members: &[&str]) -> String {
+    let members: Vec<String> = members.iter().map(|m| format!("\"{m}\"")).collect();
+    format!(
+        r#"{{
+  "bindings": [
+    {{
+      "members": [{}],
+      "role": "roles/iam.workloadIdentityUser"
+    }},
+    {{
+      "members": ["user:admin@acme.dev"],
+      "role": "roles/iam.serviceAccountUser"
+    }}
+  ],
+  "etag": "BwYz",
+  "version": 1
+}}"#,
+        members.join(", ")
+    )
+}
+
+fn providers_json(ids: &[&str]) -> String {
+    let entries: Vec<String> = ids
+        .iter()
+        .map(|id| {
+            format!(
+                r#"{{"name": "projects/123/locations/global/workloadIdentityPools/svc-github/providers/{id}", "state": "ACTIVE"}}"#
+            )
+        })
+        .collect();
+    format!("[{}]", entries.join(", "))
+}
+
+fn mock_ci_usage(policy: String, providers: String) -> MockExecutor {
+    let mut mock = MockExecutor::new();
+    mock.expect_exec()
+        .withf(|args| args.contains(&"get-iam-policy".to_owned()))
+        .returning(move |_| Ok(policy.clone()));
+    mock.expect_exec()
+        .withf(|args| {
+            args.starts_with(&[
+                "iam".to_owned(),
+                "workload-identity-pools".to_owned(),
+                "providers".to_owned(),
+                "list".to_owned(),
+            ])
+        })
+        .returning(move |_| Ok(providers.clone()));
+    mock
+}
+
+#[tokio::test]
+async fn get_sa_iam_policy_parses_bindings() {
+    let mut mock = MockExecutor::new();
+    mock.expect_exec()
+        .withf(|args| {
+            args.contains(&"get-iam-policy".to_owned()) && args.contains(&"json".to_owned())
+        })
+        .returning(|_| Ok(sa_policy_json(&[OUR_PRINCIPAL])));
+
+    let client = GcloudClient::with_executor(mock);
+    let policy = client
+        .get_sa_iam_policy("proj", "svc-deploy@proj.iam.gserviceaccount.com")
+        .await
+        .unwrap();
+
+    let members: Vec<&str> = policy
+        .members_with_role("roles/iam.workloadIdentityUser")
+        .collect();
+    assert_eq!(members, [OUR_PRINCIPAL]);
+}
+
+#[tokio::test]
+async fn get_sa_iam_policy_missing_account_is_empty() {
+    let mut mock = MockExecutor::new();
+    mock.expect_exec().returning(|_| {
+        Err(GcloudError::CommandFailed {
+            args: vec![],
+            stderr: "ERROR: (gcloud.iam.service-accounts.get-iam-policy) NOT_FOUND: Unknown service account".to_owned(),
+        })
+    });
+
+    let client = GcloudClient::with_executor(mock);
+    let policy = client
+        .get_sa_iam_policy("proj", "gone@proj.iam.gserviceaccount.com")
+        .await
+        .unwrap();
+
+    assert!(policy.bindings.is_empty());
+}
+
+#[tokio::test]
+async fn get_sa_iam_policy_rejects_malformed_json() {
+    let mut mock = MockExecutor::new();
+    mock.expect_exec().returning(|_| Ok("not json".to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    let result = client
+        .get_sa_iam_policy("proj", "svc-deploy@proj.iam.gserviceaccount.com")
+        .await;
+
+    assert!(matches!(result, Err(WifError::ParseOutput { .. })));
+}
+
+#[tokio::test]
+async fn list_wif_providers_returns_ids() {
+    let mut mock = MockExecutor::new();
+    mock.expect_exec()
+        .withf(|args| args.contains(&"--workload-identity-pool".to_owned()))
+        .returning(|_| Ok(providers_json(&["github", "gitlab"])));
+
+    let client = GcloudClient::with_executor(mock);
+    let providers = client
+        .list_wif_providers("proj", "svc-github")
+        .await
+        .unwrap();
+
+    assert_eq!(providers, ["github", "gitlab"]);
+}
+
+#[tokio::test]
+async fn shared_ci_usage_unshared_when_only_our_repo() {
+    let mock = mock_ci_usage(
+        sa_policy_json(&[OUR_PRINCIPAL]),
+        providers_json(&["github"]),
+    );
+
+    let client = GcloudClient::with_executor(mock);
+    let usage = client
+        .shared_ci_usage(
+            "proj",
+            "svc-github",
+            "github",
+            "svc-deploy@proj.iam.gserviceaccount.com",
+            Some("acme/svc"),
+        )
+        .await
+        .unwrap();
+
+    assert!(!usage.is_shared());
+}
+
+#[tokio::test]
+async fn shared_ci_usage_flags_other_repo_binding() {
+    let mock = mock_ci_usage(
+        sa_policy_json(&[OUR_PRINCIPAL, OTHER_PRINCIPAL]),
+        providers_json(&["github"]),
+    );
+
+    let client = GcloudClient::with_executor(mock);
+    let usage = client
+        .shared_ci_usage(
+            "proj",
+            "svc-github",
+            "github",
+            "svc-deploy@proj.iam.gserviceaccount.com",
+            Some("acme/svc"),
+        )
+        .await
+        .unwrap();
+
+    assert!(usage.is_shared());
+    assert_eq!(usage.other_principals, [OTHER_PRINCIPAL]);
+    assert!(usage.other_providers.is_empty());
+}
+
+#[tokio::test]
+async fn shared_ci_usage_flags_extra_provider() {
+    let mock = mock_ci_usage(
+        sa_policy_json(&[OUR_PRINCIPAL]),
+        providers_json(&["github", "gitlab"]),
+    );
+
+    let client = GcloudClient::with_executor(mock);
+    let usage = client
+        .shared_ci_usage(
+            "proj",
+            "svc-github",
+            "github",
+            "svc-deploy@proj.iam.gserviceaccount.com",
+            Some("acme/svc"),
+        )
+        .await
+        .unwrap();
+
+    assert!(usage.is_shared());
+    assert_eq!(usage.other_providers, ["gitlab"]);
+}
+
+#[tokio::test]
+async fn shared_ci_usage_unknown_repo_treats_all_bindings_as_foreign() {
+    let mock = mock_ci_usage(
+        sa_policy_json(&[OUR_PRINCIPAL]),
+        providers_json(&["github"]),
+    );
+
+    let client = GcloudClient::with_executor(mock);
+    let usage = client
+        .shared_ci_usage(
+            "proj",
+            "svc-github",
+            "github",
+            "svc-deploy@proj.iam.gserviceaccount.com",
+            None,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(usage.other_principals, [OUR_PRINCIPAL]);
+}
+
+// ── Deploy Permissions ──
+
+#[tokio::test]
+async fn deploy_permissions_reads_project_policy() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.starts_with(&["projects".to_owned(), "get-iam-policy".to_owned()])
+                && args.contains(&"proj".to_owned())
+        })
+        .returning(|_| Ok(include_str!("fixtures/project_iam_policy.json").to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    let check = client
+        .deploy_permissions("proj", "viewer@example.com")
+        .await
+        .unwrap();
+
+    assert_eq!(check.missing.len(), propel_cloud::DEPLOY_PERMISSIONS.len());
+}
+
+#[tokio::test]
+async fn deploy_permissions_unreadable_policy() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| args.contains(&"get-iam-policy".to_owned()))
+        .returning(|_| {
+            Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "PERMISSION_DENIED: resourcemanager.projects.getIamPolicy".to_owned(),
+            })
+        });
+
+    let client = GcloudClient::with_executor(mock);
+    let err = client
+        .deploy_permissions("proj", "dev@example.com")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, PreflightError::IamPolicyUnreadable { .. }));
+    assert!(err.to_string().contains("--skip-iam-check"));
+}
+
+#[test]
+fn missing_permissions_error_names_roles_and_member() {
+    let err = PreflightError::MissingPermissions {
+        account: "ci@proj.iam.gserviceaccount.com".to_owned(),
+        project_id: "proj".to_owned(),
+        missing: vec![propel_cloud::DEPLOY_PERMISSIONS[2]],
+    };
+
+    let message = err.to_string();
+    assert!(
+        message.contains(
+            "  - artifactregistry.repositories.create (grant roles/artifactregistry.admin)"
+        )
+    );
+    assert!(message.contains("--member=serviceAccount:ci@proj.iam.gserviceaccount.com"));
+}
+
+// ── Active account ──
+
+#[tokio::test]
+async fn active_account_trims_output() {
+    let mut mock = MockExecutor::new();
+    mock.expect_exec()
+        .withf(|args: &[String]| args == ["config", "get-value", "account"])
+        .returning(|_| Ok("alice@corp.com\n".to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    assert_eq!(
+        client.active_account().await.unwrap().as_deref(),
+        Some("alice@corp.com")
+    );
+}
+
+#[tokio::test]
+async fn active_account_empty_is_none() {
+    let mut mock = MockExecutor::new();
+    mock.expect_exec().returning(|_| Ok("\n".to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    assert_eq!(client.active_account().await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn credentialed_account_reads_active_auth_entry() {
+    let mut mock = MockExecutor::new();
+    mock.expect_exec()
+        .withf(is_credentialed_account_query)
+        .returning(|_| Ok("\nalice@corp.com\n".to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    assert_eq!(
+        client.credentialed_account().await.unwrap().as_deref(),
+        Some("alice@corp.com")
+    );
+}
+
+#[test]
+fn token_unavailable_message_names_the_account() {
+    let msg = PreflightError::TokenUnavailable {
+        account: "alice@corp.com".to_owned(),
+        source: GcloudError::CommandFailed {
+            args: vec![],
+            stderr: "Reauthentication failed.".to_owned(),
+        },
+    }
+    .to_string();
+    assert!(msg.contains("'alice@corp.com' is active"));
+    assert!(msg.contains("gcloud auth login alice@corp.com"));
+    assert!(msg.contains("gcloud auth activate-service-account alice@corp.com"));
+}
+
+// ── Non-interactive flags ──
+
+#[tokio::test]
+async fn mutating_commands_pass_quiet() {
+    let calls = std::sync::Arc::new(std::sync::Mutex::new(Vec::<Vec<String>>::new()));
+    let mut mock = MockExecutor::new();
+
+    let recorded = calls.clone();
+    mock.expect_exec().returning(move |args| {
+        recorded.lock().unwrap().push(args.to_vec());
+        if args.contains(&"describe".to_owned()) {
+            return Err(GcloudError::CommandFailed {
+                args: vec![],
+                stderr: "NOT_FOUND".to_owned(),
+            });
+        }
+        Ok(String::new())
+    });
+    let recorded = calls.clone();
+    mock.expect_exec_with_stdin().returning(move |args, _| {
+        recorded.lock().unwrap().push(args.to_vec());
+        Ok("projects/1/secrets/S/versions/1".to_owned())
+    });
+
+    let client = GcloudClient::with_executor(mock);
+    client.set_secret("proj", "S", "value").await.unwrap();
+    client
+        .grant_secret_access("proj", "S", "sa@proj.iam.gserviceaccount.com")
+        .await
+        .unwrap();
+    client
+        .revoke_secret_access("proj", "S", "sa@proj.iam.gserviceaccount.com")
+        .await
+        .unwrap();
+    client.ensure_wif_pool("proj", "pool").await.unwrap();
+    client
+        .ensure_oidc_provider("proj", "pool", "github", "owner/repo")
+        .await
+        .unwrap();
+    client
+        .ensure_service_account("proj", "deployer", "Deployer")
+        .await
+        .unwrap();
+    client
+        .grant_service_account_user("proj", "a@proj.iam", "b@proj.iam")
+        .await
+        .unwrap();
+    client
+        .bind_wif_to_sa("proj", "123", "pool", "b@proj.iam", "owner/repo")
+        .await
+        .unwrap();
+
+    let calls = calls.lock().unwrap();
+    let mutating: Vec<&Vec<String>> = calls
+        .iter()
+        .filter(|args| !args.contains(&"describe".to_owned()))
+        .collect();
+    assert_eq!(mutating.len(), 9);
+    for args in mutating {
+        assert!(args.contains(&"--quiet".to_owned()), "{args:?}");
+    }
+}
+
+// ── Cross-region lookup ──
+
+#[tokio::test]
+async fn list_services_all_regions_filters_by_name_and_label_without_region() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.starts_with(&["run".to_owned(), "services".to_owned(), "list".to_owned()])
+                && args.windows(2).any(|w| {
+                    w[0] == "--filter"
+                        && w[1] == "metadata.name=my-api AND metadata.labels.propel-managed=true"
+                })
+                && !args.contains(&"--region".to_owned())
+        })
+        .returning(|_| Ok(include_str!("fixtures/run_services_list_by_name.json").to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    let services = client
+        .list_services_all_regions("proj", "my-api")
+        .await
+        .unwrap();
+
+    assert_eq!(services.len(), 1);
+    assert_eq!(services[0].name, "my-api");
+    assert_eq!(services[0].region.as_deref(), Some("us-central1"));
+}
+
+#[tokio::test]
+async fn list_services_all_regions_failure_is_list_error() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec().returning(|_| {
+        Err(GcloudError::CommandFailed {
+            args: vec![],
+            stderr: "PERMISSION_DENIED".to_owned(),
+        })
+    });
+
+    let client = GcloudClient::with_executor(mock);
+    let err = client
+        .list_services_all_regions("proj", "my-api")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, DeployError::List { .. }));
+}
+
+#[tokio::test]
+async fn describe_missing_service_is_service_not_found() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec().returning(|_| {
+        Err(GcloudError::CommandFailed {
+            args: vec![],
+            stderr: "ERROR: (gcloud.run.services.describe) Cannot find service [my-api]".to_owned(),
+        })
+    });
+
+    let client = GcloudClient::with_executor(mock);
+    let err = client
+        .describe_service("my-api", "proj", "europe-west1")
+        .await
+        .unwrap_err();
+    assert!(err.is_service_not_found());
+}
+
+#[test]
+fn other_deploy_errors_are_not_service_not_found() {
+    let err = DeployError::Deploy {
+        source: GcloudError::CommandFailed {
+            args: vec![],
+            stderr: "PERMISSION_DENIED: run.services.get".to_owned(),
+        },
+    };
+    assert!(!err.is_service_not_found());
+}
+
+// ── Config-only updates ──
+
+#[test]
+fn cloud_run_flags_cover_service_settings() {
+    let config = CloudRunConfig {
+        memory: "1Gi".to_owned(),
+        env: [("RUST_LOG".to_owned(), "info".to_owned())].into(),
+        ..CloudRunConfig::default()
+    };
+    let flags = propel_cloud::cloud_run_flags(&config, &["DB_URL".into()]);
+
+    for (flag, value) in [
+        ("--memory", "1Gi"),
+        ("--cpu", "1"),
+        ("--port", "8080"),
+        ("--set-env-vars", "RUST_LOG=info"),
+        ("--update-secrets", "DB_URL=DB_URL:latest"),
+    ] {
+        assert!(
+            flags.windows(2).any(|w| w[0] == flag && w[1] == value),
+            "{flag} {value} missing from {flags:?}"
+        );
+    }
+    // Target and output flags belong to the deploy/update commands.
+    for flag in ["--image", "--project", "--region", "--quiet", "--format"] {
+        assert!(!flags.contains(&flag.to_owned()), "{flag}");
+    }
+}
+
+#[test]
+fn cloud_run_flags_set_cpu_throttling_explicitly() {
+    let default = propel_cloud::cloud_run_flags(&CloudRunConfig::default(), &[]);
+    assert!(
+        default.contains(&"--cpu-throttling".to_owned()),
+        "{default:?}"
+    );
+    assert!(!default.contains(&"--no-cpu-throttling".to_owned()));
+
+    let config = CloudRunConfig {
+        cpu_always_allocated: true,
+        concurrency: 1,
+        ..CloudRunConfig::default()
+    };
+    let flags = propel_cloud::cloud_run_flags(&config, &[]);
+    assert!(
+        flags.contains(&"--no-cpu-throttling".to_owned()),
+        "{flags:?}"
+    );
+    assert!(!flags.contains(&"--cpu-throttling".to_owned()));
+    assert!(
+        flags.windows(2).any(|w| w == ["--concurrency", "1"]),
+        "{flags:?}"
+    );
+}
+
+#[test]
+fn cloud_run_flags_set_timeout_only_when_configured() {
+    let default = propel_cloud::cloud_run_flags(&CloudRunConfig::default(), &[]);
+    assert!(!default.contains(&"--timeout".to_owned()), "{default:?}");
+
+    let config = CloudRunConfig {
+        timeout_seconds: Some(900),
+        ..CloudRunConfig::default()
+    };
+    let flags = propel_cloud::cloud_run_flags(&config, &[]);
+    assert!(
+        flags.windows(2).any(|w| w == ["--timeout", "900"]),
+        "{flags:?}"
+    );
+}
+
+#[test]
+fn cloud_run_flags_set_service_account_only_when_configured() {
+    let default = propel_cloud::cloud_run_flags(&CloudRunConfig::default(), &[]);
+    assert!(
+        !default.contains(&"--service-account".to_owned()),
+        "{default:?}"
+    );
+
+    let config = CloudRunConfig {
+        service_account: Some("api@proj.iam.gserviceaccount.com".to_owned()),
+        ..CloudRunConfig::default()
+    };
+    let flags = propel_cloud::cloud_run_flags(&config, &[]);
+    assert!(
+        flags
+            .windows(2)
+            .any(|w| w == ["--service-account", "api@proj.iam.gserviceaccount.com"]),
+        "{flags:?}"
+    );
+}
+
+#[tokio::test]
+async fn service_account_exists_reads_not_found_as_false() {
+    let mut mock = MockExecutor::new();
+    mock.expect_exec()
+        .withf(|args| args.contains(&"found@proj.iam.gserviceaccount.com".to_owned()))
+        .returning(|_| Ok("found@proj.iam.gserviceaccount.com\n".to_owned()));
+    mock.expect_exec()
+        .withf(|args| args.contains(&"missing@proj.iam.gserviceaccount.com".to_owned()))
+        .returning(|args| {
+            Err(GcloudError::CommandFailed {
+                args: args.to_vec(),
+                stderr: "ERROR: (gcloud.iam.service-accounts.describe) NOT_FOUND: Unknown \
+                         service account"
+                    .to_owned(),
+            })
+        });
+    mock.expect_exec().returning(|args| {
+        Err(GcloudError::CommandFailed {
+            args: args.to_vec(),
+            stderr: "PERMISSION_DENIED: iam.serviceAccounts.get".to_owned(),
+        })
+    });
+    let client = GcloudClient::with_executor(mock);
+
+    assert!(
+        client
+            .service_account_exists("proj", "found@proj.iam.gserviceaccount.com")
+            .await
+            .unwrap()
+    );
+    assert!(
+        !client
+            .service_account_exists("proj", "missing@proj.iam.gserviceaccount.com")
+            .await
+            .unwrap()
+    );
+    let err = client
+        .service_account_exists("proj", "denied@proj.iam.gserviceaccount.com")
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, PreflightError::ServiceAccountLookup { .. }),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn doctor_report_fails_on_missing_runtime_service_account() {
+    let mut report = propel_cloud::DoctorReport {
+        runtime_service_account: Some(propel_cloud::CheckResult::fail(
+            "api@proj.iam.gserviceaccount.com not found in proj",
+        )),
+        ..propel_cloud::DoctorReport::default()
+    };
+    report.config_file = propel_cloud::CheckResult::ok("Found");
+
+    assert!(report.to_string().contains("Service account"));
+    assert!(!report.all_passed());
+    report.runtime_service_account = Some(propel_cloud::CheckResult::skipped("no project"));
+    assert!(
+        report
+            .checks()
+            .iter()
+            .any(|check| check.id == "iam.runtime_service_account")
+    );
+}
+
+#[test]
+fn cloud_run_flags_set_vpc_connector_and_egress() {
+    let default = propel_cloud::cloud_run_flags(&CloudRunConfig::default(), &[]);
+    assert!(
+        !default.contains(&"--vpc-connector".to_owned()),
+        "{default:?}"
+    );
+    assert!(!default.contains(&"--vpc-egress".to_owned()), "{default:?}");
+
+    for (egress, value) in [
+        (propel_core::VpcEgress::AllTraffic, "all-traffic"),
+        (
+            propel_core::VpcEgress::PrivateRangesOnly,
+            "private-ranges-only",
+        ),
+    ] {
+        let config = CloudRunConfig {
+            vpc_connector: Some("db-connector".to_owned()),
+            vpc_egress: Some(egress),
+            ..CloudRunConfig::default()
+        };
+        let flags = propel_cloud::cloud_run_flags(&config, &[]);
+        assert!(
+            flags
+                .windows(2)
+                .any(|w| w == ["--vpc-connector", "db-connector"]),
+            "{flags:?}"
+        );
+        assert!(
+            flags.windows(2).any(|w| w == ["--vpc-egress", value]),
+            "{flags:?}"
+        );
+    }
+}
+
+#[test]
+fn cloud_run_flags_add_cloudsql_instances() {
+    let default = propel_cloud::cloud_run_flags(&CloudRunConfig::default(), &[]);
+    assert!(
+        !default.contains(&"--add-cloudsql-instances".to_owned()),
+        "{default:?}"
+    );
+
+    let config = CloudRunConfig {
+        cloudsql_instances: vec![
+            "proj:us-central1:main".to_owned(),
+            "proj:us-central1:replica".to_owned(),
+        ],
+        ..CloudRunConfig::default()
+    };
+    let flags = propel_cloud::cloud_run_flags(&config, &[]);
+    assert!(
+        flags.windows(2).any(|w| w
+            == [
+                "--add-cloudsql-instances",
+                "proj:us-central1:main,proj:us-central1:replica"
+            ]),
+        "{flags:?}"
+    );
+}
+
+#[tokio::test]
+async fn api_enabled_checks_one_service() {
+    let mut mock = MockExecutor::new();
+    mock.expect_exec()
+        .withf(|args| {
+            args.starts_with(&["services".to_owned(), "list".to_owned()])
+                && args.contains(&"config.name=sqladmin.googleapis.com".to_owned())
+        })
+        .times(2)
+        .returning({
+            let mut calls = 0;
+            move |_| {
+                calls += 1;
+                Ok(if calls == 1 {
+                    "sqladmin.googleapis.com\n".to_owned()
+                } else {
+                    String::new()
+                })
+            }
+        });
+    let client = GcloudClient::with_executor(mock);
+
+    assert!(
+        client
+            .api_enabled("proj", propel_cloud::CLOUD_SQL_ADMIN_API)
+            .await
+            .unwrap()
+    );
+    assert!(
+        !client
+            .api_enabled("proj", propel_cloud::CLOUD_SQL_ADMIN_API)
+            .await
+            .unwrap()
+    );
+}
+
+#[tokio::test]
+async fn project_iam_policy_parses_and_reports_denial() {
+    let mut mock = MockExecutor::new();
+    mock.expect_exec()
+        .withf(|args| args.starts_with(&["projects".to_owned(), "get-iam-policy".to_owned()]))
+        .returning(|args| {
+            if args.contains(&"denied".to_owned()) {
+                return Err(GcloudError::CommandFailed {
+                    args: args.to_vec(),
+                    stderr: "PERMISSION_DENIED".to_owned(),
+                });
+            }
+            Ok(r#"{"bindings": [{"role": "roles/cloudsql.client", "members": ["serviceAccount:api@proj.iam.gserviceaccount.com"]}]}"#.to_owned())
+        });
+    let client = GcloudClient::with_executor(mock);
+
+    let policy = client.project_iam_policy("proj").await.unwrap();
+    assert!(policy.grants_cloud_sql_connect("api@proj.iam.gserviceaccount.com"));
+    let err = client.project_iam_policy("denied").await.unwrap_err();
+    assert!(
+        matches!(err, PreflightError::IamPolicyUnreadable { .. }),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn doctor_report_fails_on_disabled_cloud_sql_api() {
+    let mut report = propel_cloud::DoctorReport {
+        cloud_sql_api: Some(propel_cloud::CheckResult::fail("disabled")),
+        ..propel_cloud::DoctorReport::default()
+    };
+    report.config_file = propel_cloud::CheckResult::ok("Found");
+
+    assert!(report.to_string().contains("Cloud SQL Admin API"));
+    assert!(!report.all_passed());
+    report.cloud_sql_api = Some(propel_cloud::CheckResult::skipped("no project"));
+    assert!(
+        report
+            .checks()
+            .iter()
+            .any(|check| check.id == "api.sqladmin" && check.result.skipped)
+    );
+}
+
+#[test]
+fn deploy_and_update_share_cloud_run_flags() {
+    let config = CloudRunConfig {
+        startup_probe: Some(ProbeConfig::default()),
+        ..CloudRunConfig::default()
+    };
+    let secrets = ["API_KEY".into()];
+    let flags = propel_cloud::cloud_run_flags(&config, &secrets);
+    let deploy = propel_cloud::cloud_run_deploy_args(
+        "svc",
+        "img",
+        "proj",
+        "us-central1",
+        &config,
+        &secrets,
+        &labels(),
+    );
+    let update = propel_cloud::cloud_run_update_args(
+        "svc",
+        "proj",
+        "us-central1",
+        &config,
+        &secrets,
+        &labels(),
+    );
+
+    for cmd in [&deploy, &update] {
+        assert!(cmd.windows(flags.len()).any(|w| w == flags), "{cmd:?}");
+    }
+}
+
+#[test]
+fn cloud_run_update_args_keep_deployed_image() {
+    let cmd = propel_cloud::cloud_run_update_args(
+        "svc",
+        "proj",
+        "us-central1",
+        &CloudRunConfig::default(),
+        &[],
+        &labels(),
+    );
+
+    assert_eq!(&cmd[..4], ["run", "services", "update", "svc"]);
+    assert!(!cmd.contains(&"--image".to_owned()));
+    assert!(cmd.contains(&"--quiet".to_owned()));
+    assert!(cmd.windows(2).any(|w| w[0] == "--update-labels"
+        && w[1] == "propel-managed=true,propel-version=0_4_3,propel-config-hash=0123456789ab"));
+}
+
+#[tokio::test]
+async fn update_cloud_run_service_returns_url() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.starts_with(&["run".to_owned(), "services".to_owned(), "update".to_owned()])
+        })
+        .returning(|_| Ok("https://svc-abc123-uc.a.run.app\n".to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    let url = client
+        .update_cloud_run_service(
+            "svc",
+            "proj",
+            "us-central1",
+            &CloudRunConfig::default(),
+            &[],
+            &labels(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(url, "https://svc-abc123-uc.a.run.app");
+}
+
+// ── Beta command group ──
+
+fn extra_flags(flags: &[&str]) -> CloudRunConfig {
+    CloudRunConfig {
+        extra_flags: flags.iter().map(|f| (*f).to_owned()).collect(),
+        ..CloudRunConfig::default()
+    }
+}
+
+const GCLOUD_WITH_BETA: &str = "Google Cloud SDK 555.0.0\nbeta 2026.01.09\ncore 2026.01.09\n";
+const GCLOUD_CORE_ONLY: &str = "Google Cloud SDK 555.0.0\ncore 2026.01.09\n";
+
+#[test]
+fn ga_extra_flags_keep_the_run_group() {
+    let config = extra_flags(&["--execution-environment=gen2", "--session-affinity"]);
+    let deploy = propel_cloud::cloud_run_deploy_args(
+        "svc",
+        "img",
+        "proj",
+        "us-central1",
+        &config,
+        &[],
+        &labels(),
+    );
+
+    assert_eq!(RunCommandGroup::for_config(&config), RunCommandGroup::Ga);
+    assert_eq!(&deploy[..3], ["run", "deploy", "svc"]);
+    assert!(deploy.contains(&"--execution-environment=gen2".to_owned()));
+    assert!(deploy.contains(&"--session-affinity".to_owned()));
+}
+
+#[test]
+fn beta_only_flag_switches_deploy_and_update_to_beta_run() {
+    let config = extra_flags(&["--gpu=1", "--gpu-type=nvidia-l4"]);
+    let deploy = propel_cloud::cloud_run_deploy_args(
+        "svc",
+        "img",
+        "proj",
+        "us-central1",
+        &config,
+        &[],
+        &labels(),
+    );
+    let update =
+        propel_cloud::cloud_run_update_args("svc", "proj", "us-central1", &config, &[], &labels());
+
+    assert_eq!(&deploy[..4], ["beta", "run", "deploy", "svc"]);
+    assert_eq!(&update[..5], ["beta", "run", "services", "update", "svc"]);
+    assert!(deploy.contains(&"--gpu-type=nvidia-l4".to_owned()));
+}
+
+#[test]
+fn flag_names_match_exactly() {
+    let flags = ["--gpu-type-hint=x".to_owned(), "--no-gpu".to_owned()];
+    assert_eq!(RunCommandGroup::for_flags(&flags), RunCommandGroup::Ga);
+
+    let flags = [
+        "--memory".to_owned(),
+        "--no-gpu-zonal-redundancy".to_owned(),
+    ];
+    assert_eq!(RunCommandGroup::for_flags(&flags), RunCommandGroup::Beta);
+    assert_eq!(
+        RunCommandGroup::beta_only_flags(&flags),
+        ["--no-gpu-zonal-redundancy"]
+    );
+}
+
+#[tokio::test]
+async fn deploy_runs_beta_run_deploy_for_gpu_flags() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| args.starts_with(&["beta".to_owned(), "run".to_owned(), "deploy".to_owned()]))
+        .times(1)
+        .returning(|_| Ok("https://svc-abc123-uc.a.run.app\n".to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    let url = client
+        .deploy_to_cloud_run(
+            "svc",
+            "img",
+            "proj",
+            "us-central1",
+            &extra_flags(&["--gpu=1"]),
+            &[],
+            &labels(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(url, "https://svc-abc123-uc.a.run.app");
+}
+
+#[tokio::test]
+async fn ga_command_group_needs_no_component_check() {
+    let mut mock = MockExecutor::new();
+    mock.expect_exec().never();
+
+    let client = GcloudClient::with_executor(mock);
+    let group = client
+        .check_run_command_group(&CloudRunConfig::default())
+        .await
+        .unwrap();
+
+    assert_eq!(group, RunCommandGroup::Ga);
+}
+
+#[tokio::test]
+async fn beta_command_group_passes_with_beta_installed() {
+    let mut mock = MockExecutor::new();
+    mock.expect_exec()
+        .withf(|args| args == ["version"])
+        .times(1)
+        .returning(|_| Ok(GCLOUD_WITH_BETA.to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    let group = client
+        .check_run_command_group(&extra_flags(&["--gpu=1"]))
+        .await
+        .unwrap();
+
+    assert_eq!(group, RunCommandGroup::Beta);
+}
+
+#[tokio::test]
+async fn missing_beta_component_names_flags_and_install_command() {
+    let mut mock = MockExecutor::new();
+    mock.expect_exec()
+        .withf(|args| args == ["version"])
+        .returning(|_| Ok(GCLOUD_CORE_ONLY.to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    let err = client
+        .check_run_command_group(&extra_flags(&["--gpu=1", "--gpu-type=nvidia-l4"]))
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        &err,
+        PreflightError::ComponentMissing { component: "beta", flags }
+            if flags == &["--gpu", "--gpu-type"]
+    ));
+    let message = err.to_string();
+    assert!(message.contains("--gpu, --gpu-type"), "{message}");
+    assert!(
+        message.contains("gcloud components install beta"),
+        "{message}"
+    );
+}
+
+// ── Rollback ──
+
+#[tokio::test]
+async fn list_revisions_joins_revisions_and_service_traffic() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.starts_with(&["run".to_owned(), "revisions".to_owned(), "list".to_owned()])
+                && args
+                    .windows(2)
+                    .any(|w| w[0] == "--service" && w[1] == "orders-api")
+                && args
+                    .windows(2)
+                    .any(|w| w[0] == "--format" && w[1] == "json")
+        })
+        .times(1)
+        .returning(|_| Ok(include_str!("fixtures/run_revisions_list.json").to_owned()));
+    mock.expect_exec()
+        .withf(|args| {
+            args.starts_with(&[
+                "run".to_owned(),
+                "services".to_owned(),
+                "describe".to_owned(),
+            ]) && args
+                .windows(2)
+                .any(|w| w[0] == "--format" && w[1] == "json")
+        })
+        .times(1)
+        .returning(|_| Ok(include_str!("fixtures/run_services_describe.json").to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    let revisions = client
+        .list_revisions("orders-api", "proj", "us-central1")
+        .await
+        .unwrap();
+
+    assert_eq!(revisions.len(), 4);
+    assert_eq!(revisions[1].name, "orders-api-00003-kzw");
+    assert_eq!(revisions[1].traffic_percent, 100);
+}
+
+#[tokio::test]
+async fn list_revisions_reports_a_missing_service() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec().returning(|args| {
+        Err(GcloudError::CommandFailed {
+            args: args.to_vec(),
+            stderr: "ERROR: (gcloud.run.revisions.list) Cannot find service [svc]".to_owned(),
+        })
+    });
+
+    let client = GcloudClient::with_executor(mock);
+    let err = client
+        .list_revisions("svc", "proj", "us-central1")
+        .await
+        .unwrap_err();
+
+    assert!(err.is_service_not_found(), "{err:?}");
+}
+
+#[tokio::test]
+async fn list_revisions_rejects_unexpected_output() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .returning(|_| Ok("Listed 0 items.".to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    let err = client
+        .list_revisions("svc", "proj", "us-central1")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, DeployError::RevisionsParse { .. }), "{err:?}");
+}
+
+#[tokio::test]
+async fn route_all_traffic_pins_the_revision_and_returns_url() {
+    let mut mock = MockExecutor::new();
+
+    mock.expect_exec()
+        .withf(|args| {
+            args.starts_with(&[
+                "run".to_owned(),
+                "services".to_owned(),
+                "update-traffic".to_owned(),
+                "orders-api".to_owned(),
+            ]) && args
+                .windows(2)
+                .any(|w| w[0] == "--to-revisions" && w[1] == "orders-api-00002-q7t=100")
+                && args.contains(&"--quiet".to_owned())
+        })
+        .times(1)
+        .returning(|_| Ok(String::new()));
+    mock.expect_exec()
+        .withf(|args| args.contains(&"value(status.url)".to_owned()))
+        .times(1)
+        .returning(|_| Ok("https://orders-api-abc123-uc.a.run.app\n".to_owned()));
+
+    let client = GcloudClient::with_executor(mock);
+    let url = client
+        .route_all_traffic("orders-api", "proj", "us-central1", "orders-api-00002-q7t")
+        .await
+        .unwrap();
+
+    assert_eq!(url, "https://orders-api-abc123-uc.a.run.app");
 }
@@ -0,0 +1,247 @@
+use propel_cloud::executor::{RecordingExecutor, TeeEcho, disable_prompts, run_captured, run_tee};
+use propel_cloud::gcloud::GcloudError;
+use propel_cloud::{BuildOutput, GcloudClient};
+use propel_core::CloudBuildConfig;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+use tokio::process::Command;
+
+/// A stand-in for gcloud that writes known output to both streams.
+fn fake_child(script: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.args(["-c", script]);
+    command
+}
+
+#[tokio::test]
+async fn tee_copies_stdout_and_stderr_to_log() {
+    let tmp = TempDir::new().unwrap();
+    let log_path = tmp.path().join("build.log");
+
+    let output = run_tee(
+        &mut fake_child("echo 'Step #0: fetch'; echo 'warning: slow' >&2; echo 'DONE'"),
+        &log_path,
+        TeeEcho::Raw,
+    )
+    .await
+    .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(output.stderr, "warning: slow\n");
+    let log = std::fs::read_to_string(&log_path).unwrap();
+    for line in ["Step #0: fetch\n", "warning: slow\n", "DONE\n"] {
+        assert!(log.contains(line), "{line:?} missing from {log:?}");
+    }
+    assert_eq!(log.len(), "Step #0: fetch\nwarning: slow\nDONE\n".len());
+}
+
+#[tokio::test]
+async fn tee_keeps_log_and_stderr_of_failed_command() {
+    let tmp = TempDir::new().unwrap();
+    let log_path = tmp.path().join("nested/dir/build.log");
+
+    let output = run_tee(
+        &mut fake_child("echo 'compiling'; echo 'error: build step failed' >&2; exit 3"),
+        &log_path,
+        TeeEcho::Raw,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(output.stderr, "error: build step failed\n");
+    let log = std::fs::read_to_string(&log_path).unwrap();
+    assert!(log.contains("compiling\n"));
+    assert!(log.contains("error: build step failed\n"));
+}
+
+#[tokio::test]
+async fn tee_truncates_previous_log() {
+    let tmp = TempDir::new().unwrap();
+    let log_path = tmp.path().join("build.log");
+    std::fs::write(&log_path, "old build output\n").unwrap();
+
+    run_tee(&mut fake_child("echo new"), &log_path, TeeEcho::Raw)
+        .await
+        .unwrap();
+
+    assert_eq!(std::fs::read_to_string(&log_path).unwrap(), "new\n");
+}
+
+#[tokio::test]
+async fn tee_missing_program_is_not_found() {
+    let tmp = TempDir::new().unwrap();
+    let mut command = Command::new("propel-test-no-such-program");
+
+    let err = run_tee(&mut command, &tmp.path().join("build.log"), TeeEcho::Raw)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, GcloudError::NotFound { .. }));
+}
+
+#[tokio::test]
+async fn tee_unwritable_log_is_log_file_error() {
+    let tmp = TempDir::new().unwrap();
+    // A directory where the log file should be.
+    let log_path = tmp.path().join("build.log");
+    std::fs::create_dir(&log_path).unwrap();
+
+    let err = run_tee(&mut fake_child("echo hi"), &log_path, TeeEcho::Raw)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, GcloudError::LogFile { .. }));
+}
+
+#[tokio::test]
+async fn tee_build_echo_keeps_log_unprefixed() {
+    let tmp = TempDir::new().unwrap();
+    let log_path = tmp.path().join("build.log");
+
+    let output = run_tee(
+        &mut fake_child("echo 'Step 5/17 : FROM chef AS planner'; printf 'no newline'"),
+        &log_path,
+        TeeEcho::Build,
+    )
+    .await
+    .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        std::fs::read_to_string(&log_path).unwrap(),
+        "Step 5/17 : FROM chef AS planner\nno newline"
+    );
+}
+
+#[tokio::test]
+async fn captured_returns_both_streams() {
+    let output = run_captured(
+        &mut fake_child("echo out; echo err >&2; exit 2"),
+        None,
+        Duration::from_secs(10),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(output.stdout, b"out\n");
+    assert_eq!(output.stderr, b"err\n");
+}
+
+#[tokio::test]
+async fn captured_feeds_stdin() {
+    let output = run_captured(
+        &mut fake_child("cat"),
+        Some(b"s3cret"),
+        Duration::from_secs(10),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(output.stdout, b"s3cret");
+}
+
+#[tokio::test]
+async fn captured_stdin_is_empty_without_data() {
+    // A prompt reading stdin sees EOF instead of blocking.
+    let output = run_captured(
+        &mut fake_child("read answer; echo \"got:$answer\""),
+        None,
+        Duration::from_secs(10),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(output.stdout, b"got:\n");
+}
+
+#[tokio::test]
+async fn captured_kills_silent_process() {
+    let started = Instant::now();
+
+    let err = run_captured(
+        &mut fake_child("sleep 30"),
+        None,
+        Duration::from_millis(200),
+    )
+    .await
+    .unwrap_err();
+
+    assert!(started.elapsed() < Duration::from_secs(10));
+    match err {
+        GcloudError::WaitingForInput { args, idle } => {
+            assert_eq!(args, ["-c", "sleep 30"]);
+            assert_eq!(idle, Duration::from_millis(200));
+        }
+        other => panic!("expected WaitingForInput, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn captured_output_resets_idle_timeout() {
+    // Runs longer than the timeout in total, but never goes quiet for long.
+    let output = run_captured(
+        &mut fake_child("for i in 1 2 3 4 5; do echo $i >&2; sleep 0.1; done; echo done"),
+        None,
+        Duration::from_millis(400),
+    )
+    .await
+    .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"done\n");
+}
+
+#[test]
+fn waiting_for_input_message_names_command() {
+    let err = GcloudError::WaitingForInput {
+        args: vec!["services".to_owned(), "enable".to_owned()],
+        idle: Duration::from_secs(600),
+    };
+    let message = err.to_string();
+    assert!(message.starts_with("gcloud appears to be waiting for input (no output for 600s)"));
+    assert!(message.contains("\"services\", \"enable\""));
+}
+
+#[tokio::test]
+async fn disable_prompts_sets_cloudsdk_env() {
+    let mut command = fake_child("echo \"$CLOUDSDK_CORE_DISABLE_PROMPTS\"");
+    disable_prompts(&mut command);
+
+    let output = run_captured(&mut command, None, Duration::from_secs(10))
+        .await
+        .unwrap();
+
+    assert_eq!(output.stdout, b"1\n");
+}
+
+#[tokio::test]
+async fn recording_executor_records_client_commands_without_running_them() {
+    let tmp = TempDir::new().unwrap();
+    let log_path = tmp.path().join("build.log");
+    let client = GcloudClient::with_executor(RecordingExecutor::new());
+
+    client
+        .submit_build(
+            &tmp.path().join(".propel-bundle"),
+            "proj",
+            "img:tag",
+            &CloudBuildConfig::default(),
+            None,
+            &BuildOutput::Tee(log_path.clone()),
+        )
+        .await
+        .unwrap();
+    let url = client
+        .service_url("svc", "proj", "us-central1")
+        .await
+        .unwrap();
+
+    let commands = client.executor().commands();
+    assert_eq!(commands.len(), 2);
+    assert_eq!(commands[0][..2], ["builds", "submit"]);
+    assert_eq!(commands[1][..3], ["run", "services", "describe"]);
+    assert_eq!(url, "");
+    assert!(!log_path.exists());
+}
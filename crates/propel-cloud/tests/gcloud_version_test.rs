@@ -0,0 +1,115 @@
+use propel_cloud::{GcloudVersion, components_check};
+
+const GCLOUD_555: &str = "\
+Google Cloud SDK 555.0.0
+beta 2026.01.09
+bq 2.1.26
+core 2026.01.09
+gcloud-crc32c 1.0.0
+gsutil 5.35
+log-streaming 2025.11.07
+";
+
+const GCLOUD_470_MINIMAL: &str = "\
+Google Cloud SDK 470.0.0
+bq 2.1.1
+core 2024.03.22
+gsutil 5.27
+";
+
+const GCLOUD_400_WITH_UPDATE_NOTICE: &str = "\
+Google Cloud SDK 400.0.0
+bq 2.0.75
+core 2022.08.19
+gsutil 5.12
+
+Updates are available for some Google Cloud CLI components.  To install them,
+please run:
+  $ gcloud components update
+";
+
+// ── Parsing ──
+
+#[test]
+fn parse_full_output_with_components() {
+    let v = GcloudVersion::parse(GCLOUD_555);
+
+    assert_eq!(v.sdk.as_deref(), Some("555.0.0"));
+    assert_eq!(v.sdk_triple(), Some((555, 0, 0)));
+    assert!(v.has_component("beta"));
+    assert!(v.has_component("log-streaming"));
+    assert!(v.has_component("core"));
+    assert_eq!(v.components.len(), 6);
+}
+
+#[test]
+fn parse_minimal_install() {
+    let v = GcloudVersion::parse(GCLOUD_470_MINIMAL);
+
+    assert_eq!(v.sdk.as_deref(), Some("470.0.0"));
+    assert!(v.has_component("core"));
+    assert!(!v.has_component("beta"));
+    assert!(!v.has_component("log-streaming"));
+}
+
+#[test]
+fn parse_skips_update_notice() {
+    let v = GcloudVersion::parse(GCLOUD_400_WITH_UPDATE_NOTICE);
+
+    assert_eq!(v.sdk.as_deref(), Some("400.0.0"));
+    assert_eq!(v.components.len(), 3);
+    assert!(!v.has_component("$"));
+}
+
+#[test]
+fn parse_unrecognized_output() {
+    let v = GcloudVersion::parse("something unexpected\n");
+
+    assert!(v.sdk.is_none());
+    assert!(v.components.is_empty());
+    assert_eq!(v.meets_minimum(), None);
+}
+
+// ── Version minimum ──
+
+#[test]
+fn minimum_version_checks() {
+    assert_eq!(GcloudVersion::parse(GCLOUD_555).meets_minimum(), Some(true));
+    assert_eq!(
+        GcloudVersion::parse(GCLOUD_470_MINIMAL).meets_minimum(),
+        Some(true)
+    );
+    assert_eq!(
+        GcloudVersion::parse(GCLOUD_400_WITH_UPDATE_NOTICE).meets_minimum(),
+        Some(false)
+    );
+}
+
+// ── Components check ──
+
+#[test]
+fn components_check_passes_without_requirements() {
+    let result = components_check(&GcloudVersion::parse(GCLOUD_470_MINIMAL), &[]);
+
+    assert!(result.passed);
+    assert!(result.detail.contains("log-streaming not installed"));
+}
+
+#[test]
+fn components_check_fails_on_missing_required() {
+    let result = components_check(&GcloudVersion::parse(GCLOUD_470_MINIMAL), &["beta"]);
+
+    assert!(!result.passed);
+    assert!(result.detail.contains("gcloud components install beta"));
+}
+
+#[test]
+fn components_check_lists_installed_notable() {
+    let result = components_check(
+        &GcloudVersion::parse(GCLOUD_555),
+        &["beta", "log-streaming"],
+    );
+
+    assert!(result.passed);
+    assert_eq!(result.detail, "core, beta, log-streaming");
+}
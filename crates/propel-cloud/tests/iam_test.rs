@@ -0,0 +1,164 @@
+use propel_cloud::{
+    CLOUD_SQL_CLIENT_ROLE, DEPLOY_PERMISSIONS, IamPolicy, ServiceAccess, account_member,
+    check_deploy_permissions,
+};
+
+fn fixture_policy() -> IamPolicy {
+    serde_json::from_str(include_str!("fixtures/project_iam_policy.json")).unwrap()
+}
+
+fn missing(policy: &IamPolicy, account: &str) -> Vec<&'static str> {
+    check_deploy_permissions(policy, account)
+        .missing
+        .iter()
+        .map(|p| p.permission)
+        .collect()
+}
+
+#[test]
+fn predefined_roles_cover_every_deploy_permission() {
+    let check = check_deploy_permissions(&fixture_policy(), "deploy@proj.iam.gserviceaccount.com");
+    assert!(check.is_complete());
+    assert!(!check.inconclusive);
+}
+
+#[test]
+fn viewer_only_account_misses_everything() {
+    assert_eq!(
+        missing(&fixture_policy(), "viewer@example.com"),
+        [
+            "run.services.update",
+            "cloudbuild.builds.create",
+            "artifactregistry.repositories.create",
+            "secretmanager.secrets.list",
+        ]
+    );
+}
+
+#[test]
+fn partial_grant_lists_only_missing_permissions() {
+    assert_eq!(
+        missing(&fixture_policy(), "dev@example.com"),
+        [
+            "run.services.update",
+            "artifactregistry.repositories.create",
+            "secretmanager.secrets.list",
+        ]
+    );
+}
+
+#[test]
+fn basic_owner_role_grants_everything_case_insensitively() {
+    assert!(check_deploy_permissions(&fixture_policy(), "admin@example.com").is_complete());
+}
+
+#[test]
+fn unknown_account_misses_everything() {
+    assert_eq!(
+        missing(&fixture_policy(), "stranger@example.com").len(),
+        DEPLOY_PERMISSIONS.len()
+    );
+}
+
+#[test]
+fn group_bindings_make_the_check_inconclusive() {
+    let policy: IamPolicy = serde_json::from_str(
+        r#"{"bindings": [{"role": "roles/run.admin", "members": ["group:devs@example.com"]}]}"#,
+    )
+    .unwrap();
+
+    let check = check_deploy_permissions(&policy, "dev@example.com");
+
+    assert!(!check.is_complete());
+    assert!(check.inconclusive);
+}
+
+#[test]
+fn custom_role_on_the_account_makes_the_check_inconclusive() {
+    let policy: IamPolicy = serde_json::from_str(
+        r#"{"bindings": [
+            {"role": "projects/proj/roles/deployer", "members": ["user:dev@example.com"]},
+            {"role": "organizations/1/roles/other", "members": ["user:someone@example.com"]}
+        ]}"#,
+    )
+    .unwrap();
+
+    assert!(check_deploy_permissions(&policy, "dev@example.com").inconclusive);
+    assert!(!check_deploy_permissions(&policy, "viewer@example.com").inconclusive);
+}
+
+#[test]
+fn missing_permission_suggests_roles() {
+    let run = DEPLOY_PERMISSIONS[0];
+    assert_eq!(
+        run.to_string(),
+        "run.services.update (grant roles/run.developer or roles/run.admin)"
+    );
+}
+
+#[test]
+fn account_member_kinds() {
+    assert_eq!(account_member("dev@example.com"), "user:dev@example.com");
+    assert_eq!(
+        account_member("deploy@proj.iam.gserviceaccount.com"),
+        "serviceAccount:deploy@proj.iam.gserviceaccount.com"
+    );
+}
+
+#[test]
+fn service_access_reads_ingress_and_invokers() {
+    let access = ServiceAccess::parse(
+        include_str!("fixtures/run_services_describe.json"),
+        r#"{"bindings": [
+            {"role": "roles/run.invoker", "members": ["serviceAccount:caller@proj.iam.gserviceaccount.com"]},
+            {"role": "roles/run.admin", "members": ["allUsers"]}
+        ]}"#,
+    )
+    .unwrap();
+
+    assert!(!access.is_public());
+    assert_eq!(
+        access.to_string(),
+        "private, invokers: serviceAccount:caller@proj.iam.gserviceaccount.com \
+         · ingress internal-and-cloud-load-balancing"
+    );
+}
+
+#[test]
+fn service_access_defaults_to_all_ingress() {
+    let access = ServiceAccess::parse(
+        r#"{"metadata": {"name": "svc"}}"#,
+        r#"{"bindings": [{"role": "roles/run.invoker", "members": ["allUsers"]}]}"#,
+    )
+    .unwrap();
+
+    assert!(access.is_public());
+    assert_eq!(
+        access.to_string(),
+        "public (allUsers may invoke) · ingress all"
+    );
+    assert_eq!(
+        ServiceAccess::parse(r#"{}"#, r#"{"etag": "BwY"}"#)
+            .unwrap()
+            .to_string(),
+        "private, no invokers · ingress all"
+    );
+}
+
+#[test]
+fn cloud_sql_connect_needs_a_direct_binding_of_a_connecting_role() {
+    let policy: IamPolicy = serde_json::from_str(&format!(
+        r#"{{"bindings": [
+            {{"role": "{CLOUD_SQL_CLIENT_ROLE}", "members": ["serviceAccount:API@proj.iam.gserviceaccount.com"]}},
+            {{"role": "roles/editor", "members": ["serviceAccount:worker@proj.iam.gserviceaccount.com"]}},
+            {{"role": "roles/cloudsql.viewer", "members": ["serviceAccount:viewer@proj.iam.gserviceaccount.com"]}},
+            {{"role": "{CLOUD_SQL_CLIENT_ROLE}", "members": ["user:api@proj.iam.gserviceaccount.com", "group:db@example.com"]}}
+        ]}}"#
+    ))
+    .unwrap();
+
+    assert!(policy.grants_cloud_sql_connect("api@proj.iam.gserviceaccount.com"));
+    assert!(policy.grants_cloud_sql_connect("worker@proj.iam.gserviceaccount.com"));
+    assert!(!policy.grants_cloud_sql_connect("viewer@proj.iam.gserviceaccount.com"));
+    assert!(!policy.grants_cloud_sql_connect("other@proj.iam.gserviceaccount.com"));
+}
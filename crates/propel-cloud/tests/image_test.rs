@@ -0,0 +1,49 @@
+use propel_cloud::image::{parse_image_size, split_image_tag};
+
+const IMAGES_LIST: &str = include_str!("fixtures/artifacts_docker_images_list.json");
+
+#[test]
+fn split_image_tag_separates_the_tag() {
+    assert_eq!(
+        split_image_tag("us-central1-docker.pkg.dev/proj/propel/api:latest"),
+        ("us-central1-docker.pkg.dev/proj/propel/api", "latest")
+    );
+}
+
+#[test]
+fn split_image_tag_ignores_a_registry_port() {
+    assert_eq!(
+        split_image_tag("localhost:5000/proj/api"),
+        ("localhost:5000/proj/api", "latest")
+    );
+    assert_eq!(
+        split_image_tag("localhost:5000/proj/api:v2"),
+        ("localhost:5000/proj/api", "v2")
+    );
+}
+
+#[test]
+fn parse_fixture_reads_image_size_bytes() {
+    assert_eq!(parse_image_size(IMAGES_LIST).unwrap(), Some(88_080_384));
+}
+
+#[test]
+fn parse_accepts_a_numeric_size() {
+    let json = r#"[{"metadata": {"imageSizeBytes": 1024}}]"#;
+    assert_eq!(parse_image_size(json).unwrap(), Some(1024));
+}
+
+#[test]
+fn parse_without_a_size_is_none() {
+    assert_eq!(parse_image_size("[]").unwrap(), None);
+    assert_eq!(parse_image_size(r#"[{"package": "p"}]"#).unwrap(), None);
+    assert_eq!(
+        parse_image_size(r#"[{"metadata": {"imageSizeBytes": "n/a"}}]"#).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn parse_rejects_non_json() {
+    assert!(parse_image_size("Listed 0 items.").is_err());
+}
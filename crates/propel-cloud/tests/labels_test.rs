@@ -0,0 +1,113 @@
+use propel_cloud::labels::{
+    CONFIG_HASH_LABEL, IMAGE_SIZE_LABEL, MANAGED_LABEL, ManagedService, PropelLabels,
+    VERSION_LABEL, decode_version, encode_label_value, managed_filter, parse_managed_services,
+};
+
+const SERVICES_LIST: &str = include_str!("fixtures/run_services_list.json");
+
+/// Cloud Run's label rules: lowercase letters, digits, `_`, `-`; at most 63.
+fn is_valid_label(s: &str) -> bool {
+    !s.is_empty()
+        && s.len() <= 63
+        && s.chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+}
+
+#[test]
+fn labels_flag_value() {
+    let labels = PropelLabels::new("0.4.3", "0123456789ab");
+    assert_eq!(
+        labels.flag_value(),
+        "propel-managed=true,propel-version=0_4_3,propel-config-hash=0123456789ab"
+    );
+}
+
+#[test]
+fn labels_flag_value_records_image_size() {
+    let labels = PropelLabels::new("0.4.3", "0123456789ab").with_image_size(88_080_384);
+    assert_eq!(
+        labels.flag_value(),
+        "propel-managed=true,propel-version=0_4_3,propel-config-hash=0123456789ab,\
+         propel-image-size=88080384"
+    );
+}
+
+#[test]
+fn label_keys_and_values_are_valid_for_cloud_run() {
+    let labels = PropelLabels::new("1.0.0-RC.1+build.7", "0123456789ab").with_image_size(u64::MAX);
+    for (key, value) in labels.pairs() {
+        assert!(is_valid_label(key), "{key}");
+        assert!(is_valid_label(value), "{key}={value}");
+    }
+    for key in [
+        MANAGED_LABEL,
+        VERSION_LABEL,
+        CONFIG_HASH_LABEL,
+        IMAGE_SIZE_LABEL,
+    ] {
+        assert!(!key.contains('.'), "{key}");
+    }
+}
+
+#[test]
+fn encode_label_value_cases() {
+    assert_eq!(encode_label_value("0.4.3"), "0_4_3");
+    assert_eq!(encode_label_value("1.0.0-RC.1"), "1_0_0-rc_1");
+    assert_eq!(encode_label_value(&"a".repeat(80)).len(), 63);
+}
+
+#[test]
+fn version_round_trips_through_label() {
+    for version in ["0.4.3", "0.10.0", "1.0.0-rc.1"] {
+        assert_eq!(decode_version(&encode_label_value(version)), version);
+    }
+}
+
+#[test]
+fn managed_filter_uses_label_key() {
+    assert_eq!(managed_filter(), "metadata.labels.propel-managed=true");
+}
+
+#[test]
+fn parse_fixture_keeps_managed_services_sorted_by_name() {
+    let services = parse_managed_services(SERVICES_LIST).unwrap();
+    assert_eq!(
+        services,
+        [
+            ManagedService {
+                name: "billing".to_owned(),
+                region: Some("europe-west1".to_owned()),
+                url: Some("https://billing-abc123-ew.a.run.app".to_owned()),
+                propel_version: Some("0.4.3".to_owned()),
+                config_hash: Some("0123456789ab".to_owned()),
+                image_size_bytes: Some(88_080_384),
+            },
+            ManagedService {
+                name: "legacy-worker".to_owned(),
+                region: Some("us-central1".to_owned()),
+                url: None,
+                propel_version: None,
+                config_hash: None,
+                image_size_bytes: None,
+            },
+            ManagedService {
+                name: "orders-api".to_owned(),
+                region: Some("us-central1".to_owned()),
+                url: Some("https://orders-api-abc123-uc.a.run.app".to_owned()),
+                propel_version: Some("0.3.2".to_owned()),
+                config_hash: Some("9f2c41d07a5e".to_owned()),
+                image_size_bytes: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn parse_empty_list() {
+    assert!(parse_managed_services("[]").unwrap().is_empty());
+}
+
+#[test]
+fn parse_rejects_non_json() {
+    assert!(parse_managed_services("Listed 0 items.").is_err());
+}
@@ -0,0 +1,105 @@
+use propel_cloud::pricing::{PriceTier, parse_memory_gib};
+use propel_cloud::{PricingError, estimate_monthly_cost};
+use propel_core::CloudRunConfig;
+
+fn config(cpu: u32, memory: &str, min_instances: u32, max_instances: u32) -> CloudRunConfig {
+    CloudRunConfig {
+        cpu,
+        memory: memory.to_owned(),
+        min_instances,
+        max_instances,
+        ..CloudRunConfig::default()
+    }
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 0.01,
+        "expected ~{expected}, got {actual}"
+    );
+}
+
+// ── Memory parsing ──
+
+#[test]
+fn parses_binary_units() {
+    assert_eq!(parse_memory_gib("2Gi"), Some(2.0));
+    assert_eq!(parse_memory_gib("512Mi"), Some(0.5));
+}
+
+#[test]
+fn parses_decimal_units() {
+    assert_close(parse_memory_gib("1G").unwrap(), 0.931);
+    assert_close(parse_memory_gib("512M").unwrap(), 0.477);
+}
+
+#[test]
+fn rejects_unparseable_memory() {
+    for value in ["", "512", "Gi", "-1Gi", "0Mi", "1Ti", "abcMi"] {
+        assert_eq!(parse_memory_gib(value), None, "{value:?}");
+    }
+}
+
+// ── Tiers ──
+
+#[test]
+fn known_region_is_tier_1() {
+    assert_eq!(PriceTier::for_region("us-central1"), PriceTier::Tier1);
+    assert_eq!(PriceTier::for_region("asia-northeast1"), PriceTier::Tier1);
+}
+
+#[test]
+fn unlisted_region_is_tier_2() {
+    assert_eq!(PriceTier::for_region("europe-west2"), PriceTier::Tier2);
+    assert_eq!(PriceTier::for_region("not-a-region"), PriceTier::Tier2);
+}
+
+// ── Estimates ──
+
+#[test]
+fn no_min_instances_has_no_idle_cost() {
+    let estimate = estimate_monthly_cost(&config(1, "512Mi", 0, 10), "us-central1").unwrap();
+    assert_eq!(estimate.idle_monthly, 0.0);
+    assert!(estimate.max_monthly > 0.0);
+}
+
+#[test]
+fn idle_cost_scales_with_min_instances() {
+    // 1 vCPU + 1 GiB idle in Tier 1: (0.0000025 + 0.0000025) * 2_628_000 s
+    let one = estimate_monthly_cost(&config(1, "1Gi", 1, 10), "us-central1").unwrap();
+    assert_close(one.idle_monthly, 13.14);
+
+    let two = estimate_monthly_cost(&config(1, "1Gi", 2, 10), "us-central1").unwrap();
+    assert_close(two.idle_monthly, 26.28);
+}
+
+#[test]
+fn worst_case_uses_active_prices_for_max_instances() {
+    // 2 vCPU + 4 GiB active in Tier 1: (2 * 0.000024 + 4 * 0.0000025) * 2_628_000 s
+    let estimate = estimate_monthly_cost(&config(2, "4Gi", 0, 3), "us-central1").unwrap();
+    assert_close(estimate.max_monthly, 3.0 * 152.424);
+}
+
+#[test]
+fn always_allocated_cpu_bills_idle_instances_at_active_prices() {
+    let mut cfg = config(2, "4Gi", 1, 3);
+    cfg.cpu_always_allocated = true;
+    let estimate = estimate_monthly_cost(&cfg, "us-central1").unwrap();
+    assert_close(estimate.idle_monthly, 152.424);
+}
+
+#[test]
+fn tier_2_region_costs_more() {
+    let cfg = config(1, "512Mi", 1, 5);
+    let tier1 = estimate_monthly_cost(&cfg, "us-central1").unwrap();
+    let tier2 = estimate_monthly_cost(&cfg, "europe-west2").unwrap();
+    assert_eq!(tier2.tier, PriceTier::Tier2);
+    assert!(tier2.idle_monthly > tier1.idle_monthly);
+    assert!(tier2.max_monthly > tier1.max_monthly);
+}
+
+#[test]
+fn invalid_memory_is_an_error() {
+    let err = estimate_monthly_cost(&config(1, "lots", 1, 5), "us-central1").unwrap_err();
+    assert!(matches!(err, PricingError::InvalidMemory { ref value } if value == "lots"));
+}
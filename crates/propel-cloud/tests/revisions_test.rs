@@ -0,0 +1,136 @@
+use propel_cloud::revisions::{RevisionInfo, parse_revisions, previous_revision};
+
+const REVISIONS_LIST: &str = include_str!("fixtures/run_revisions_list.json");
+const SERVICE_DESCRIBE: &str = include_str!("fixtures/run_services_describe.json");
+
+fn revision(name: &str, traffic_percent: u32, ready: bool) -> RevisionInfo {
+    RevisionInfo {
+        name: name.to_owned(),
+        created: String::new(),
+        traffic_percent,
+        image: String::new(),
+        ready,
+        deployed_by: None,
+        project_number: None,
+    }
+}
+
+#[test]
+fn parse_fixture_sorts_newest_first_with_traffic() {
+    let revisions = parse_revisions(REVISIONS_LIST, SERVICE_DESCRIBE).unwrap();
+
+    let summary: Vec<(&str, u32, bool)> = revisions
+        .iter()
+        .map(|r| (r.name.as_str(), r.traffic_percent, r.ready))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("orders-api-00004-bad", 0, false),
+            ("orders-api-00003-kzw", 100, true),
+            ("orders-api-00002-q7t", 0, true),
+            ("orders-api-00001-abc", 0, true),
+        ]
+    );
+    assert_eq!(revisions[1].created, "2026-03-03T17:40:02.551218Z");
+    assert_eq!(
+        revisions[3].image,
+        "us-central1-docker.pkg.dev/proj/propel/orders-api:v1"
+    );
+}
+
+#[test]
+fn parse_fixture_reads_deployer_and_project_number() {
+    let revisions = parse_revisions(REVISIONS_LIST, SERVICE_DESCRIBE).unwrap();
+
+    let deployers: Vec<Option<&str>> = revisions.iter().map(|r| r.deployed_by.as_deref()).collect();
+    assert_eq!(
+        deployers,
+        [
+            Some("bob@corp.com"),
+            Some("propel-deploy@proj.iam.gserviceaccount.com"),
+            None,
+            None,
+        ]
+    );
+    assert!(
+        revisions
+            .iter()
+            .all(|r| r.project_number.as_deref() == Some("123456789012"))
+    );
+}
+
+#[test]
+fn project_number_is_only_a_numeric_namespace() {
+    let revisions = r#"[
+        {"metadata": {"name": "a", "namespace": "my-project"}},
+        {"metadata": {"name": "b", "annotations": {"serving.knative.dev/creator": ""}}}
+    ]"#;
+    let revisions = parse_revisions(revisions, r#"{"status": {}}"#).unwrap();
+
+    assert!(revisions.iter().all(|r| r.project_number.is_none()));
+    assert!(revisions.iter().all(|r| r.deployed_by.is_none()));
+}
+
+#[test]
+fn latest_revision_target_without_a_name_counts_for_latest_ready() {
+    let service = r#"{"status": {
+        "latestReadyRevisionName": "svc-00002",
+        "traffic": [
+            {"latestRevision": true, "percent": 90},
+            {"revisionName": "svc-00001", "percent": 10, "tag": "canary"}
+        ]
+    }}"#;
+    let revisions = r#"[
+        {"metadata": {"name": "svc-00001", "creationTimestamp": "2026-01-01T00:00:00Z"}},
+        {"metadata": {"name": "svc-00002", "creationTimestamp": "2026-01-02T00:00:00Z"}}
+    ]"#;
+
+    let parsed = parse_revisions(revisions, service).unwrap();
+
+    assert_eq!(parsed[0].traffic_percent, 90);
+    assert_eq!(parsed[1].traffic_percent, 10);
+}
+
+#[test]
+fn previous_revision_is_the_one_before_the_serving_revision() {
+    let revisions = parse_revisions(REVISIONS_LIST, SERVICE_DESCRIBE).unwrap();
+
+    assert_eq!(
+        previous_revision(&revisions).map(|r| r.name.as_str()),
+        Some("orders-api-00002-q7t")
+    );
+}
+
+#[test]
+fn previous_revision_skips_revisions_that_are_not_ready() {
+    let revisions = [
+        revision("svc-00004", 100, true),
+        revision("svc-00003", 0, false),
+        revision("svc-00002", 0, true),
+    ];
+
+    assert_eq!(
+        previous_revision(&revisions).map(|r| r.name.as_str()),
+        Some("svc-00002")
+    );
+}
+
+#[test]
+fn previous_revision_is_none_without_an_older_ready_revision() {
+    assert!(previous_revision(&[revision("svc-00001", 100, true)]).is_none());
+    assert!(
+        previous_revision(&[
+            revision("svc-00002", 100, true),
+            revision("svc-00001", 0, false)
+        ])
+        .is_none()
+    );
+    assert!(
+        previous_revision(&[
+            revision("svc-00002", 0, true),
+            revision("svc-00001", 0, true)
+        ])
+        .is_none()
+    );
+}
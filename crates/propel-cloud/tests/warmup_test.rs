@@ -0,0 +1,126 @@
+use propel_cloud::{ReqwestProber, Warmup, WarmupError, tagged_revision_url, warm_up};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Local HTTP server answering `503` to the first `cold` requests (a
+/// revision still starting) and `200` after that. Returns its URL and the
+/// number of requests served so far.
+async fn slow_then_ready(cold: u32) -> (String, Arc<AtomicU32>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/health", listener.local_addr().unwrap());
+    let served = Arc::new(AtomicU32::new(0));
+    let counter = Arc::clone(&served);
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 1024];
+            let _ = socket.read(&mut request).await;
+            let n = counter.fetch_add(1, Ordering::SeqCst);
+            let status = if n < cold {
+                "503 Service Unavailable"
+            } else {
+                "200 OK"
+            };
+            let response =
+                format!("HTTP/1.1 {status}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n");
+            let _ = socket.write_all(response.as_bytes()).await;
+        }
+    });
+    (url, served)
+}
+
+fn quick(requests: u32, timeout: Duration) -> Warmup {
+    Warmup {
+        requests,
+        timeout,
+        retry_delay: Duration::from_millis(10),
+    }
+}
+
+#[tokio::test]
+async fn warm_up_retries_until_revision_is_ready() {
+    let (url, served) = slow_then_ready(3).await;
+    let mut retries = Vec::new();
+
+    warm_up(
+        &ReqwestProber::new(),
+        &url,
+        &quick(5, Duration::from_secs(10)),
+        |outcome| retries.push(outcome.to_owned()),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(retries, ["HTTP 503", "HTTP 503", "HTTP 503"]);
+    assert_eq!(served.load(Ordering::SeqCst), 8);
+}
+
+#[tokio::test]
+async fn warm_up_times_out_on_a_revision_that_never_answers() {
+    let (url, _) = slow_then_ready(u32::MAX).await;
+
+    let err = warm_up(
+        &ReqwestProber::new(),
+        &url,
+        &quick(2, Duration::from_millis(200)),
+        |_| {},
+    )
+    .await
+    .unwrap_err();
+
+    let WarmupError::TimedOut {
+        succeeded, last, ..
+    } = &err;
+    assert_eq!(*succeeded, 0);
+    assert_eq!(last, "HTTP 503");
+    assert!(
+        err.to_string()
+            .contains("traffic stays on the previous revision")
+    );
+}
+
+#[tokio::test]
+async fn warm_up_reports_connection_errors() {
+    // Bind and drop a listener so the port is (almost certainly) closed.
+    let port = TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let mut retries = 0;
+
+    let err = warm_up(
+        &ReqwestProber::new(),
+        &format!("http://127.0.0.1:{port}/"),
+        &quick(1, Duration::from_millis(100)),
+        |_| retries += 1,
+    )
+    .await
+    .unwrap_err();
+
+    assert!(retries > 0);
+    assert!(matches!(err, WarmupError::TimedOut { succeeded: 0, .. }));
+}
+
+#[test]
+fn tagged_revision_url_finds_the_tag() {
+    let json = r#"{"status": {"traffic": [
+        {"percent": 100, "revisionName": "svc-00001-abc"},
+        {"tag": "other", "url": "https://other---svc.a.run.app"},
+        {"tag": "propel-warmup", "url": "https://propel-warmup---svc.a.run.app"}
+    ]}}"#;
+
+    assert_eq!(
+        tagged_revision_url(json, "propel-warmup")
+            .unwrap()
+            .as_deref(),
+        Some("https://propel-warmup---svc.a.run.app")
+    );
+    assert_eq!(tagged_revision_url(json, "missing").unwrap(), None);
+    assert_eq!(tagged_revision_url("{}", "propel-warmup").unwrap(), None);
+    assert!(tagged_revision_url("not json", "propel-warmup").is_err());
+}
@@ -184,6 +184,56 @@ impl CargoProject {
         })
     }
 
+    /// The package directory relative to the workspace root, for a
+    /// workspace member below it (`api` in a workspace with `members =
+    /// ["api", "shared"]`). `None` for a standalone package, the root
+    /// package of a workspace, and a member outside the root directory.
+    pub fn member_path(&self) -> Option<&Path> {
+        match self.package_dir.strip_prefix(&self.workspace_root) {
+            Ok(relative) if !relative.as_os_str().is_empty() => Some(relative),
+            _ => None,
+        }
+    }
+
+    /// Directory the deploy bundle is built from: the workspace root for a
+    /// [member](Self::member_path), so the root manifest, its `Cargo.lock`,
+    /// and sibling path dependencies reach the build; the package directory
+    /// otherwise.
+    pub fn bundle_root(&self) -> &Path {
+        match self.member_path() {
+            Some(_) => &self.workspace_root,
+            None => &self.package_dir,
+        }
+    }
+
+    /// The package's `rust-version` (MSRV), resolved by cargo so that
+    /// `rust-version.workspace = true` is followed. Cargo reports it with
+    /// all three components: `rust-version = "1.83"` comes back as
+    /// `"1.83.0"`.
+    ///
+    /// Runs `cargo metadata --no-deps` again rather than being a field, so
+    /// that constructing a [`CargoProject`] directly keeps compiling.
+    ///
+    /// # Errors
+    ///
+    /// [`crate::Error::CargoMetadata`] if `cargo metadata` fails.
+    pub fn rust_version(&self) -> crate::Result<Option<String>> {
+        let metadata = MetadataCommand::new()
+            .manifest_path(&self.manifest_path)
+            .no_deps()
+            .exec()
+            .map_err(|e| crate::Error::CargoMetadata {
+                manifest_path: self.manifest_path.clone(),
+                detail: e.to_string(),
+            })?;
+        Ok(metadata
+            .packages
+            .iter()
+            .find(|p| p.manifest_path.as_std_path() == self.manifest_path)
+            .and_then(|p| p.rust_version.as_ref())
+            .map(ToString::to_string))
+    }
+
     /// Select the binary to use for deployment.
     ///
     /// Priority:
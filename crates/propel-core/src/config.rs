@@ -1,6 +1,14 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 
-use serde::{Deserialize, Serialize};
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::secret_namespace::SecretNamespace;
+
+/// Bytes of SHA-256 kept by [`PropelConfig::config_hash`] (12 hex digits).
+const CONFIG_HASH_BYTES: usize = 6;
 
 /// Top-level `propel.toml` configuration.
 ///
@@ -29,6 +37,24 @@ pub struct PropelConfig {
     pub build: BuildConfig,
     #[serde(default)]
     pub cloud_run: CloudRunConfig,
+    #[serde(default)]
+    pub cloud_build: CloudBuildConfig,
+    #[serde(default)]
+    pub ci: CiConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    /// Env vars mounted from Secret Manager secrets of another name,
+    /// optionally pinned to a version. Names are used as written, without
+    /// `[project].secret_prefix`. Secrets not mapped here are still
+    /// discovered and mounted under their own names.
+    ///
+    /// ```toml
+    /// [secrets]
+    /// DATABASE_URL = "prod-db-url:3"
+    /// API_KEY = "shared-api-key"
+    /// ```
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub secrets: BTreeMap<String, SecretRef>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,6 +66,72 @@ pub struct ProjectConfig {
     pub region: String,
     /// GCP project ID
     pub gcp_project_id: Option<String>,
+    /// Required ending of the active gcloud account (e.g. `"@corp.com"`).
+    /// Deploy and destroy refuse to run under any other account.
+    pub expected_account_suffix: Option<String>,
+    /// Prefix of every Secret Manager secret this config manages (e.g.
+    /// `"staging_"`), so environments sharing a GCP project keep separate
+    /// secrets. See [`SecretNamespace`].
+    pub secret_prefix: Option<String>,
+}
+
+impl ProjectConfig {
+    /// Whether `account` satisfies [`expected_account_suffix`](Self::expected_account_suffix).
+    ///
+    /// Always true when no suffix is configured. Comparison ignores ASCII
+    /// case, since account emails are case-insensitive.
+    pub fn account_matches(&self, account: &str) -> bool {
+        let Some(suffix) = &self.expected_account_suffix else {
+            return true;
+        };
+        let account = account.trim().to_ascii_lowercase();
+        account.ends_with(&suffix.trim().to_ascii_lowercase())
+    }
+
+    /// Write `project_id` as `[project].gcp_project_id` in
+    /// `project_dir/propel.toml`, preserving the rest of the file.
+    pub fn save_gcp_project_id(
+        project_dir: &std::path::Path,
+        project_id: &str,
+    ) -> crate::Result<()> {
+        edit_config(project_dir, "project", |project| {
+            set_value(project, "gcp_project_id", project_id);
+        })
+    }
+
+    /// Write `region` as `[project].region` in `project_dir/propel.toml`,
+    /// preserving the rest of the file.
+    pub fn save_region(project_dir: &std::path::Path, region: &str) -> crate::Result<()> {
+        edit_config(project_dir, "project", |project| {
+            set_value(project, "region", region);
+        })
+    }
+
+    /// Secret Manager names under [`secret_prefix`](Self::secret_prefix).
+    pub fn secret_namespace(&self) -> SecretNamespace<'_> {
+        match &self.secret_prefix {
+            Some(prefix) => SecretNamespace::new(prefix),
+            None => SecretNamespace::default(),
+        }
+    }
+
+    /// Reject a `secret_prefix` that can't start a Secret Manager secret ID
+    /// (letters, digits, `_` and `-`).
+    fn validate_secret_prefix(&self) -> crate::Result<()> {
+        let Some(prefix) = &self.secret_prefix else {
+            return Ok(());
+        };
+        let valid = !prefix.is_empty()
+            && prefix
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid {
+            return Err(crate::Error::InvalidSecretPrefix {
+                value: prefix.clone(),
+            });
+        }
+        Ok(())
+    }
 }
 
 /// Build configuration under `[build]`.
@@ -89,9 +181,34 @@ pub struct BuildConfig {
     /// Additional system packages to install via `apt-get` during build.
     #[serde(default)]
     pub extra_packages: Vec<String>,
+    /// System packages to install via `apt-get` in the runtime stage.
+    ///
+    /// Needed when a `-sys` crate links a shared library (e.g. `libpq5`
+    /// for `pq-sys`). Requires a Debian-based `runtime_image` with a
+    /// shell and `apt-get` — distroless images have neither, so set
+    /// `runtime_image = "debian:bookworm-slim"` alongside this.
+    #[serde(default)]
+    pub runtime_packages: Vec<String>,
     /// Cargo Chef version for dependency caching.
     #[serde(default = "default_cargo_chef_version")]
     pub cargo_chef_version: String,
+    /// The builder image already ships `cargo-chef`, so the generated
+    /// Dockerfile skips `cargo install cargo-chef` (no network access in
+    /// the chef stage). Conflicts with a non-default `cargo_chef_version`,
+    /// which would otherwise be silently ignored.
+    #[serde(default)]
+    pub chef_preinstalled: bool,
+    /// Image for the chef base stage, which the planner, cacher, and
+    /// builder stages all derive from. Overrides `base_image` in the
+    /// generated Dockerfile.
+    ///
+    /// ```toml
+    /// [build]
+    /// chef_image = "registry.example.com/rust-chef:1.93"
+    /// chef_preinstalled = true
+    /// ```
+    #[serde(default)]
+    pub chef_image: Option<String>,
     /// Paths to copy into the runtime image.
     ///
     /// When `None`, the entire bundle is copied (`COPY . .`).
@@ -103,18 +220,618 @@ pub struct BuildConfig {
     /// ```
     #[serde(default)]
     pub include: Option<Vec<String>>,
+    /// Warn at deploy when the source reads a top-level directory at
+    /// runtime (`fs::read_to_string("templates/...")`, `ServeDir::new`,
+    /// a path in `[build.env]`) that `include` leaves out. The scan is a
+    /// heuristic; set `false` to silence it.
+    #[serde(default = "default_include_check")]
+    pub include_check: bool,
     /// Static environment variables baked into the container image.
     ///
     /// These become `ENV` directives in the generated Dockerfile.
     /// For runtime-configurable values (API keys, secrets), use
-    /// Cloud Run environment variables or Secret Manager instead.
+    /// [`CloudRunConfig::env`] or Secret Manager instead.
+    ///
+    /// Values may use `{{commit}}` and `{{version}}`, resolved at deploy
+    /// time (see [`crate::env_template`]).
     ///
     /// ```toml
     /// [build.env]
     /// TEMPLATE_DIR = "/app/templates"
+    /// GIT_SHA = "{{commit}}"
     /// ```
     #[serde(default)]
     pub env: HashMap<String, String>,
+    /// Compile-time environment variables for the dependency and
+    /// application builds, e.g. a directory `build.rs` reads.
+    ///
+    /// These become `ENV` directives in the chef base stage, so
+    /// `cargo chef cook` and `cargo build` see them; they do not reach the
+    /// runtime image. Use [`Self::env`] for values the running service needs.
+    ///
+    /// ```toml
+    /// [build.build_env]
+    /// PROTO_DIR = "/app/proto"
+    /// ```
+    #[serde(default)]
+    pub build_env: HashMap<String, String>,
+    /// Secret Manager secret holding git credentials for private dependencies.
+    ///
+    /// The secret contains `git-credentials` lines such as
+    /// `https://<user>:<token>@github.com`. It is exposed to the dependency
+    /// and build stages as a BuildKit secret mount — never written to an
+    /// image layer — and `ssh://git@` URLs are rewritten to `https://` so
+    /// the same credentials cover SSH-style `git =` dependencies and
+    /// git-index registries.
+    ///
+    /// ```toml
+    /// [build]
+    /// git_credentials_secret = "GIT_CREDENTIALS"
+    /// ```
+    #[serde(default)]
+    pub git_credentials_secret: Option<String>,
+    /// Write `bundle-manifest.json` — path, size, and SHA-256 of every
+    /// bundled file, plus the git commit and propel version — into the
+    /// bundle and the runtime image (`/app/bundle-manifest.json`).
+    #[serde(default)]
+    pub bundle_manifest: bool,
+    /// Target platform of the image. When set, Cloud Build runs a
+    /// `docker buildx build --platform` step (emulating arm64 on the
+    /// amd64 build workers), so `base_image` and `runtime_image` must
+    /// publish a variant for it.
+    ///
+    /// ```toml
+    /// [build]
+    /// platform = "linux/arm64"
+    /// ```
+    #[serde(default)]
+    pub platform: Option<Platform>,
+    /// Pass `--locked` to `cargo chef cook` and `cargo build`, so the image
+    /// build fails instead of updating `Cargo.lock`.
+    ///
+    /// Unset means locked whenever the bundle carries a `Cargo.lock` (the
+    /// package's own, or the workspace root's for a member).
+    #[serde(default)]
+    pub locked: Option<bool>,
+    /// Set `CARGO_NET_OFFLINE=true` for the cacher and builder stages, so
+    /// the build never touches the network for crates.
+    ///
+    /// Only works with vendored sources: `.cargo/config.toml` must replace
+    /// crates.io with a `directory` or `local-registry` source inside the
+    /// project. Deploy and `propel eject` refuse the setting otherwise.
+    ///
+    /// ```toml
+    /// [build]
+    /// offline = true
+    /// ```
+    #[serde(default)]
+    pub offline: bool,
+    /// Cargo features for `cargo chef cook` and `cargo build`
+    /// (`--features a,b`). Names may be `feature` or `dependency/feature`.
+    ///
+    /// ```toml
+    /// [build]
+    /// features = ["cloud"]
+    /// no_default_features = true
+    /// ```
+    #[serde(default)]
+    pub features: Vec<String>,
+    /// Pass `--no-default-features` to `cargo chef cook` and `cargo build`.
+    #[serde(default)]
+    pub no_default_features: bool,
+    /// `RUSTFLAGS` for the dependency and application builds, set in the
+    /// chef base stage after cargo-chef is installed, so the cached
+    /// dependencies are compiled with the same flags as the binary.
+    ///
+    /// ```toml
+    /// [build]
+    /// rustflags = "-C target-cpu=x86-64-v3"
+    /// ```
+    #[serde(default)]
+    pub rustflags: Option<String>,
+    /// Rust target triple to compile for. The chef base stage runs
+    /// `rustup target add` (and installs `musl-tools` for a musl target),
+    /// `cargo chef cook` and `cargo build` get `--target`, and the binary
+    /// is copied from `target/<target>/release/`.
+    ///
+    /// ```toml
+    /// [build]
+    /// target = "x86_64-unknown-linux-musl"
+    /// ```
+    #[serde(default)]
+    pub target: Option<String>,
+    /// Build a static musl binary: `target` defaults to the musl target of
+    /// `platform` (`x86_64-unknown-linux-musl`, or
+    /// `aarch64-unknown-linux-musl` for `linux/arm64`), and `runtime_image`,
+    /// when left at its default, becomes
+    /// [`BuildConfig::STATIC_RUNTIME_IMAGE`].
+    ///
+    /// Crates that link a C library through `-sys` bindings must build it
+    /// statically for musl (e.g. `rustls` instead of `native-tls`).
+    #[serde(default)]
+    pub static_binary: bool,
+    /// Run `cargo chef cook` and `cargo build` with BuildKit cache mounts
+    /// for the cargo registry, git checkouts, and `target/`, and build
+    /// with `docker buildx` on Cloud Build.
+    ///
+    /// A cache lives on the Docker host that built the image, so repeated
+    /// builds there (e.g. local builds of an ejected Dockerfile) skip
+    /// downloads and finished crates. Cloud Build starts every build on a
+    /// fresh worker with an empty cache.
+    #[serde(default)]
+    pub cache_mounts: bool,
+    /// User the runtime container runs as: a name from the runtime image
+    /// (`"nonroot"`) or a numeric UID (`65532`). A UID renders as
+    /// `USER 65532:65532`, which org policies enforcing runAsNonRoot can
+    /// verify without reading `/etc/passwd`. The binary and included files
+    /// are copied `--chown` to the same user.
+    ///
+    /// Distroless images define `nonroot` as UID and GID 65532
+    /// ([`RunAsUser::DISTROLESS_NONROOT_UID`]).
+    ///
+    /// ```toml
+    /// [build]
+    /// run_as_user = 65532
+    /// ```
+    #[serde(default)]
+    pub run_as_user: Option<RunAsUser>,
+    /// Tag deploy pushes the image under and deploys from. With `sha` or
+    /// `version`, `:latest` is added as an alias after the build, so every
+    /// deploy keeps its own image to roll back to.
+    ///
+    /// ```toml
+    /// [build]
+    /// tag_strategy = "version"
+    /// ```
+    #[serde(default)]
+    pub tag_strategy: TagStrategy,
+    /// Refuse to render a Dockerfile whose runtime image cannot run the
+    /// binary, such as an Alpine or `scratch` runtime for a glibc build.
+    /// The check goes by image names; set `false` for an image propel
+    /// misjudges (e.g. Alpine with a glibc compatibility layer).
+    #[serde(default = "default_runtime_image_check")]
+    pub runtime_image_check: bool,
+    /// Refuse to deploy when the builder image's Rust, read from its tag
+    /// (`rust:1.81-bookworm`), is older than the package's `rust-version`.
+    /// Deploy warns that the check is skipped when the tag names no
+    /// version; set `false` to turn it off.
+    #[serde(default = "default_rust_version_check")]
+    pub rust_version_check: bool,
+    /// Artifact Registry location of the `propel` repository that images
+    /// are pushed to: a multi-region (`us`, `europe`, `asia`) or a region.
+    /// Unset keeps images in `[project].region`. A multi-region lets
+    /// services in several regions share one copy of each image.
+    ///
+    /// ```toml
+    /// [build]
+    /// artifact_location = "us"
+    /// ```
+    #[serde(default)]
+    pub artifact_location: Option<String>,
+}
+
+/// Runtime user selected by `[build].run_as_user`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RunAsUser {
+    /// Numeric UID, also used as the GID.
+    Uid(u32),
+    /// User name defined by the runtime image.
+    Name(String),
+}
+
+impl RunAsUser {
+    /// UID and GID of the `nonroot` user in distroless images.
+    pub const DISTROLESS_NONROOT_UID: u32 = 65532;
+
+    /// Largest UID accepted: Kubernetes-style `runAsUser` fields, which
+    /// Cloud Run follows, are signed 32-bit.
+    pub const MAX_UID: u32 = i32::MAX as u32;
+
+    /// Argument of the Dockerfile `USER` instruction: `65532:65532` or `nonroot`.
+    pub fn user_instruction(&self) -> String {
+        match self {
+            Self::Uid(uid) => format!("{uid}:{uid}"),
+            Self::Name(name) => name.clone(),
+        }
+    }
+
+    /// Value of `COPY --chown`: `65532:65532` or `nonroot:nonroot`.
+    pub fn chown(&self) -> String {
+        match self {
+            Self::Uid(uid) => format!("{uid}:{uid}"),
+            Self::Name(name) => format!("{name}:{name}"),
+        }
+    }
+
+    fn validate(&self) -> crate::Result<()> {
+        let reason = match self {
+            Self::Uid(0) => "UID 0 is root; use a non-root UID such as 65532",
+            Self::Uid(uid) if *uid > Self::MAX_UID => "UIDs above 2147483647 are not accepted",
+            Self::Uid(_) => return Ok(()),
+            Self::Name(name) if name == "root" => {
+                "root is not a non-root user; use a name such as \"nonroot\" or a UID"
+            }
+            Self::Name(name)
+                if name.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+                    && name.chars().all(|c| {
+                        c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-'
+                    }) =>
+            {
+                return Ok(());
+            }
+            Self::Name(_) => {
+                "use a user name of lowercase letters, digits, '_' and '-', or a numeric UID"
+            }
+        };
+        Err(crate::Error::InvalidRunAsUser {
+            value: self.to_string(),
+            reason,
+        })
+    }
+}
+
+impl std::fmt::Display for RunAsUser {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Uid(uid) => write!(f, "{uid}"),
+            Self::Name(name) => write!(f, "{name:?}"),
+        }
+    }
+}
+
+/// Image platform selected by `[build].platform`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Platform {
+    #[serde(rename = "linux/amd64")]
+    Amd64,
+    #[serde(rename = "linux/arm64")]
+    Arm64,
+}
+
+impl Platform {
+    /// Docker platform string, e.g. `linux/arm64`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Amd64 => "linux/amd64",
+            Self::Arm64 => "linux/arm64",
+        }
+    }
+
+    /// Architecture name as used by `--platform` and binfmt (`arm64`).
+    pub fn arch(self) -> &'static str {
+        match self {
+            Self::Amd64 => "amd64",
+            Self::Arm64 => "arm64",
+        }
+    }
+
+    /// Rust musl target triple for this platform.
+    pub fn musl_target(self) -> &'static str {
+        match self {
+            Self::Amd64 => "x86_64-unknown-linux-musl",
+            Self::Arm64 => "aarch64-unknown-linux-musl",
+        }
+    }
+}
+
+impl std::fmt::Display for Platform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Image tag selected by `[build].tag_strategy`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TagStrategy {
+    /// Short commit SHA of `HEAD`, with `-dirty` when the working tree has
+    /// uncommitted changes.
+    #[default]
+    Sha,
+    /// Always `latest`, replacing the previous deploy's image.
+    Latest,
+    /// The Cargo package version.
+    Version,
+}
+
+impl TagStrategy {
+    /// Longest tag Docker accepts.
+    pub const MAX_TAG_LEN: usize = 128;
+
+    /// Whether [`Self::tag`] needs the commit.
+    pub fn uses_commit(self) -> bool {
+        self == Self::Sha
+    }
+
+    /// Image tag for `commit` (the short SHA, already suffixed `-dirty`
+    /// for a dirty tree) and the package `version`.
+    ///
+    /// Characters Docker does not allow in a tag (`+` in `1.0.0+build`)
+    /// become `-`, a tag that would start with `.` or `-` gets a `v`
+    /// prefix, and the tag is cut to [`Self::MAX_TAG_LEN`].
+    pub fn tag(self, commit: &str, version: &str) -> String {
+        let raw = match self {
+            Self::Sha => commit,
+            Self::Latest => return "latest".to_owned(),
+            Self::Version => version,
+        };
+        let mut tag: String = raw
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-') {
+                    c
+                } else {
+                    '-'
+                }
+            })
+            .collect();
+        if tag.is_empty() || tag.starts_with(['.', '-']) {
+            tag.insert(0, 'v');
+        }
+        tag.truncate(Self::MAX_TAG_LEN);
+        tag
+    }
+}
+
+/// Cloud Build settings under `[cloud_build]`.
+///
+/// `region` and `logs_bucket` are unset by default, which submits to the
+/// global default pool and logs to the default logs bucket. Organizations
+/// whose policies block either need to override them:
+///
+/// ```toml
+/// [cloud_build]
+/// region = "europe-west1"
+/// logs_bucket = "gs://my-build-logs/propel"
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CloudBuildConfig {
+    /// Region to run the build in (`gcloud builds submit --region`).
+    #[serde(default)]
+    pub region: Option<String>,
+    /// Cloud Storage location for build logs (`--gcs-log-dir`), as a
+    /// `gs://bucket/path` URL.
+    #[serde(default)]
+    pub logs_bucket: Option<String>,
+    /// Largest bundle, in MiB, that deploy uploads without
+    /// `--force-large-bundle` (default: [`Self::DEFAULT_MAX_BUNDLE_MB`]).
+    ///
+    /// Uploads of a few GB fail inside gcloud after minutes of uploading,
+    /// so deploy stops before the upload instead.
+    #[serde(default)]
+    pub max_bundle_mb: Option<u64>,
+    /// Image size, in MiB, above which deploy warns after the build —
+    /// e.g. when build output such as `target/` ends up in the runtime
+    /// image. Unset means no limit; the size is reported either way.
+    #[serde(default)]
+    pub warn_image_mb: Option<u64>,
+}
+
+/// gcloud authentication settings under `[auth]`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuthConfig {
+    /// When no gcloud account is active, activate the service account key
+    /// named by `GOOGLE_APPLICATION_CREDENTIALS`
+    /// (`gcloud auth activate-service-account --key-file`).
+    ///
+    /// Off by default because activation changes the active account of
+    /// the machine's gcloud configuration. Meant for containers and CI
+    /// systems where `gcloud auth login` is impossible.
+    ///
+    /// ```toml
+    /// [auth]
+    /// activate_key_file = true
+    /// ```
+    #[serde(default)]
+    pub activate_key_file: bool,
+}
+
+/// CI/CD settings under `[ci]`, used by `propel ci init` and deploy.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CiConfig {
+    /// Run the Cloud Run deploy as the last step of the Cloud Build job,
+    /// under a dedicated build service account, instead of calling
+    /// `gcloud run deploy` from the CLI.
+    ///
+    /// The CI service account then only submits builds and no longer
+    /// needs `roles/run.admin`.
+    ///
+    /// ```toml
+    /// [ci]
+    /// deploy_via_cloud_build = true
+    /// ```
+    #[serde(default)]
+    pub deploy_via_cloud_build: bool,
+    /// Workload Identity pool id. Defaults to `<service>-github`.
+    #[serde(default)]
+    pub wif_pool_id: Option<String>,
+    /// OIDC provider id inside the pool. Defaults to `github`.
+    #[serde(default)]
+    pub wif_provider_id: Option<String>,
+    /// Account id of the CI deploy service account. Defaults to
+    /// `<service>-deploy`.
+    #[serde(default)]
+    pub service_account_id: Option<String>,
+}
+
+/// GCP resource ids `propel ci init` creates and `destroy --include-ci`
+/// deletes, resolved from [`CiConfig`].
+///
+/// Defaults are derived from the service name so repositories sharing a
+/// GCP project never share (or delete each other's) pool and service
+/// account. `ci init` writes the resolved ids back to `[ci]`, so a later
+/// rename of the service does not orphan them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CiResources {
+    pub wif_pool_id: String,
+    pub wif_provider_id: String,
+    pub service_account_id: String,
+}
+
+/// Workload Identity pool ids are at most 32 characters.
+const WIF_POOL_ID_MAX_LEN: usize = 32;
+/// Service account ids are at most 30 characters.
+const SERVICE_ACCOUNT_ID_MAX_LEN: usize = 30;
+const DEFAULT_WIF_PROVIDER_ID: &str = "github";
+
+impl CiConfig {
+    /// Resolve resource ids, filling unset ones with per-service defaults.
+    pub fn resources(&self, service_name: &str) -> CiResources {
+        let mut resources = CiResources::defaults(service_name);
+        if let Some(id) = &self.wif_pool_id {
+            resources.wif_pool_id.clone_from(id);
+        }
+        if let Some(id) = &self.wif_provider_id {
+            resources.wif_provider_id.clone_from(id);
+        }
+        if let Some(id) = &self.service_account_id {
+            resources.service_account_id.clone_from(id);
+        }
+        resources
+    }
+
+    /// Whether all resource ids are pinned in `propel.toml`.
+    pub fn resources_pinned(&self) -> bool {
+        self.wif_pool_id.is_some()
+            && self.wif_provider_id.is_some()
+            && self.service_account_id.is_some()
+    }
+}
+
+impl CiResources {
+    /// Default ids derived from the Cloud Run service name.
+    pub fn defaults(service_name: &str) -> Self {
+        Self {
+            wif_pool_id: derive_resource_id(service_name, "github", WIF_POOL_ID_MAX_LEN),
+            wif_provider_id: DEFAULT_WIF_PROVIDER_ID.to_owned(),
+            service_account_id: derive_resource_id(
+                service_name,
+                "deploy",
+                SERVICE_ACCOUNT_ID_MAX_LEN,
+            ),
+        }
+    }
+
+    /// Email of the CI service account in `project_id`.
+    pub fn service_account_email(&self, project_id: &str) -> String {
+        format!(
+            "{}@{project_id}.iam.gserviceaccount.com",
+            self.service_account_id
+        )
+    }
+
+    /// Write these ids to `[ci]` in `project_dir/propel.toml`, preserving
+    /// the rest of the file.
+    pub fn save(&self, project_dir: &std::path::Path) -> crate::Result<()> {
+        edit_config(project_dir, "ci", |ci| {
+            ci.insert("wif_pool_id", toml_edit::value(&self.wif_pool_id));
+            ci.insert("wif_provider_id", toml_edit::value(&self.wif_provider_id));
+            ci.insert(
+                "service_account_id",
+                toml_edit::value(&self.service_account_id),
+            );
+        })
+    }
+}
+
+/// `<service>-<suffix>`, lowercased, with characters GCP ids reject
+/// replaced by `-` and the service part truncated to fit `max_len`.
+fn derive_resource_id(service_name: &str, suffix: &str, max_len: usize) -> String {
+    let mut base: String = service_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    base.truncate(max_len - suffix.len() - 1);
+    let base = base.trim_matches('-');
+    if base.is_empty() {
+        format!("propel-{suffix}")
+    } else {
+        format!("{base}-{suffix}")
+    }
+}
+
+/// Contents of `propel.toml` at `path`, or `None` when there is none.
+///
+/// A directory in its place, or a file propel may not read, is an error
+/// naming the path and the fix rather than a bare io error.
+fn read_config_file(path: &std::path::Path) -> crate::Result<Option<String>> {
+    let metadata = match std::fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(config_read_error(path, e)),
+    };
+    if metadata.is_dir() {
+        return Err(crate::Error::ConfigIsDirectory {
+            path: path.to_owned(),
+        });
+    }
+    tracing::debug!(path = %path.display(), "loading propel.toml");
+    std::fs::read_to_string(path)
+        .map(Some)
+        .map_err(|e| config_read_error(path, e))
+}
+
+fn config_read_error(path: &std::path::Path, source: std::io::Error) -> crate::Error {
+    let path = path.to_owned();
+    if source.kind() == std::io::ErrorKind::PermissionDenied {
+        crate::Error::ConfigPermissionDenied { path, source }
+    } else {
+        crate::Error::ConfigLoad { path, source }
+    }
+}
+
+/// Set `key` in `table` to `value`. An existing entry keeps its comments,
+/// both above the key and after the value.
+fn set_value(table: &mut dyn toml_edit::TableLike, key: &str, value: &str) {
+    match table.get_mut(key).and_then(toml_edit::Item::as_value_mut) {
+        Some(existing) => {
+            let decor = existing.decor().clone();
+            *existing = value.into();
+            *existing.decor_mut() = decor;
+        }
+        None => {
+            table.insert(key, toml_edit::value(value));
+        }
+    }
+}
+
+/// Apply `edit` to table `section` of `project_dir/propel.toml` and write
+/// the file back, keeping comments and formatting intact.
+pub(crate) fn edit_config(
+    project_dir: &std::path::Path,
+    section: &str,
+    edit: impl FnOnce(&mut dyn toml_edit::TableLike),
+) -> crate::Result<()> {
+    let path = project_dir.join("propel.toml");
+    // arch-lint: allow(no-silent-result-drop) reason="Option: a missing propel.toml is an empty document"
+    let content = read_config_file(&path)?.unwrap_or_default();
+    let mut doc: toml_edit::DocumentMut =
+        content
+            .parse()
+            .map_err(|e: toml_edit::TomlError| crate::Error::ConfigEdit {
+                path: path.clone(),
+                detail: e.to_string(),
+            })?;
+
+    let table = doc
+        .entry(section)
+        .or_insert_with(toml_edit::table)
+        .as_table_like_mut()
+        .ok_or_else(|| crate::Error::ConfigEdit {
+            path: path.clone(),
+            detail: format!("`{section}` is not a table"),
+        })?;
+    edit(table);
+
+    std::fs::write(&path, doc.to_string())
+        .map_err(|e| crate::Error::ConfigWrite { path, source: e })
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -137,6 +854,386 @@ pub struct CloudRunConfig {
     /// Port the application listens on
     #[serde(default = "default_port")]
     pub port: u16,
+    /// Keep CPU allocated outside of requests (`--no-cpu-throttling`), so
+    /// work started after a response is sent keeps running. Instances are
+    /// then billed for their whole lifetime, not per request.
+    #[serde(default)]
+    pub cpu_always_allocated: bool,
+    /// Request timeout in seconds (`--timeout`), 1–3600. Unset leaves the
+    /// service's current timeout, Cloud Run's 300 for a new service.
+    #[serde(default)]
+    pub timeout_seconds: Option<u32>,
+    /// Email of the service account revisions run as
+    /// (`--service-account`). Unset runs them as the project's default
+    /// compute service account. `propel secret set` grants this account
+    /// access to each secret it stores.
+    #[serde(default)]
+    pub service_account: Option<String>,
+    /// Serverless VPC Access connector (`--vpc-connector`), e.g. for
+    /// Cloud SQL over private IP: its name, or
+    /// `projects/<project>/locations/<region>/connectors/<name>`.
+    #[serde(default)]
+    pub vpc_connector: Option<String>,
+    /// Which outbound traffic goes through the VPC (`--vpc-egress`).
+    /// Unset keeps Cloud Run's default, `private-ranges-only`.
+    #[serde(default)]
+    pub vpc_egress: Option<VpcEgress>,
+    /// Cloud SQL instances attached to the service
+    /// (`--add-cloudsql-instances`), as `project:region:instance`
+    /// connection names. The app connects through the Unix socket at
+    /// `/cloudsql/<connection name>`; the runtime service account needs
+    /// `roles/cloudsql.client`.
+    ///
+    /// ```toml
+    /// [cloud_run]
+    /// cloudsql_instances = ["my-project:us-central1:main-db"]
+    /// ```
+    #[serde(default)]
+    pub cloudsql_instances: Vec<String>,
+    /// Startup probe under `[cloud_run.startup_probe]`. Unset keeps Cloud
+    /// Run's default TCP probe, unless [`Self::warmup_handler`] is set.
+    #[serde(default)]
+    pub startup_probe: Option<ProbeConfig>,
+    /// The app serves `propel::warmup_handler` at [`WARMUP_HANDLER_PATH`]:
+    /// the startup probe then GETs it, with the defaults of
+    /// [`ProbeConfig`] when `[cloud_run.startup_probe]` is unset and as
+    /// its path when the table sets none.
+    #[serde(default)]
+    pub warmup_handler: bool,
+    /// Liveness probe under `[cloud_run.liveness_probe]`. Unset means none.
+    #[serde(default)]
+    pub liveness_probe: Option<ProbeConfig>,
+    /// Environment variables set on the Cloud Run service
+    /// (`--set-env-vars`), without rebuilding the image. They replace the
+    /// service's plain env vars, so a removed key goes away; an empty
+    /// table leaves them alone.
+    ///
+    /// Values may use `{{commit}}`, `{{version}}`, `{{service}}`,
+    /// `{{region}}`, and `{{deploy_time}}` (see [`crate::env_template`]).
+    ///
+    /// ```toml
+    /// [cloud_run.env]
+    /// RUST_LOG = "info"
+    /// DEPLOYED_AT = "{{deploy_time}}"
+    /// ```
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Secret Manager secrets to inject (`--update-secrets`) and the version
+    /// each is pinned to. Unset injects every discovered secret at `latest`.
+    /// See [`SecretVersions`] for the accepted forms.
+    #[serde(default)]
+    pub secrets: Option<SecretVersions>,
+    /// Discover Secret Manager secrets at deploy and inject them. `false`
+    /// skips the discovery and `--update-secrets`, for services configured
+    /// only through `env`; deploy then needs no Secret Manager access.
+    #[serde(default = "default_inject_secrets")]
+    pub inject_secrets: bool,
+    /// Before deploying, check that each injected secret has an enabled
+    /// version and warn about those that don't: Cloud Run revisions fail
+    /// to start without one. Costs two gcloud calls per secret.
+    #[serde(default)]
+    pub check_secret_versions: bool,
+    /// Let anyone call the service (`--allow-unauthenticated`). `false`
+    /// deploys with `--no-allow-unauthenticated`, so only principals with
+    /// `roles/run.invoker` can, e.g. for internal APIs.
+    ///
+    /// ```toml
+    /// [cloud_run]
+    /// allow_unauthenticated = false
+    /// ```
+    #[serde(default = "default_allow_unauthenticated")]
+    pub allow_unauthenticated: bool,
+    /// Requests a new revision must answer (at [`Self::warmup_path`])
+    /// before traffic moves to it. `0` moves traffic as soon as Cloud Run
+    /// reports the revision ready.
+    #[serde(default)]
+    pub warmup_requests: u32,
+    /// Release new revisions as a canary: each deploy gives the new
+    /// revision [`CanaryConfig::percent`] of the traffic and leaves the
+    /// rest on the revision serving now, until `propel promote` or
+    /// `propel abort-canary`.
+    ///
+    /// ```toml
+    /// [cloud_run]
+    /// canary = { percent = 10 }
+    /// ```
+    #[serde(default)]
+    pub canary: Option<CanaryConfig>,
+    /// Flags passed as-is to `gcloud run deploy` and `run services update`
+    /// after propel's own, for settings without a field here — e.g.
+    /// `["--gpu=1", "--gpu-type=nvidia-l4"]`. Each entry is one
+    /// `--flag=value` or `--flag`.
+    ///
+    /// Flags only `gcloud beta run` accepts switch both commands to the
+    /// beta group (see `propel_cloud::RunCommandGroup`).
+    #[serde(default)]
+    pub extra_flags: Vec<String>,
+}
+
+/// `[cloud_run].vpc_egress`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VpcEgress {
+    /// All outbound traffic goes through the VPC.
+    AllTraffic,
+    /// Only traffic to private IP ranges does.
+    PrivateRangesOnly,
+}
+
+impl VpcEgress {
+    /// Value gcloud's `--vpc-egress` takes.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::AllTraffic => "all-traffic",
+            Self::PrivateRangesOnly => "private-ranges-only",
+        }
+    }
+}
+
+/// `[cloud_run].secrets`: secret name → version (`"latest"` or a version
+/// number).
+///
+/// Accepts an allowlist, where every secret tracks `latest`:
+///
+/// ```toml
+/// [cloud_run]
+/// secrets = ["SUPABASE_JWT_SECRET", "API_KEY"]
+/// ```
+///
+/// or a table pinning versions:
+///
+/// ```toml
+/// [cloud_run.secrets]
+/// SUPABASE_JWT_SECRET = "3"
+/// API_KEY = "latest"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct SecretVersions(BTreeMap<String, String>);
+
+impl SecretVersions {
+    /// Version a secret resolves to when it is not pinned.
+    pub const LATEST: &'static str = "latest";
+
+    /// Whether `name` is listed.
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    /// Whether no secret is listed (`secrets = []`).
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Listed secret names, sorted.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+
+    /// Version `name` is pinned to, if listed.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+}
+
+impl<const N: usize> From<[(&str, &str); N]> for SecretVersions {
+    fn from(entries: [(&str, &str); N]) -> Self {
+        Self(
+            entries
+                .into_iter()
+                .map(|(name, version)| (name.to_owned(), version.to_owned()))
+                .collect(),
+        )
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretVersions {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(SecretVersionsVisitor)
+    }
+}
+
+struct SecretVersionsVisitor;
+
+impl<'de> Visitor<'de> for SecretVersionsVisitor {
+    type Value = SecretVersions;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a list of secret names or a table of secret name = version")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut versions = BTreeMap::new();
+        while let Some(name) = seq.next_element::<String>()? {
+            versions.insert(name, SecretVersions::LATEST.to_owned());
+        }
+        Ok(SecretVersions(versions))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut versions = BTreeMap::new();
+        while let Some((name, version)) = map.next_entry::<String, SecretVersion>()? {
+            versions.insert(name, version.0);
+        }
+        Ok(SecretVersions(versions))
+    }
+}
+
+/// Whether `version` names a secret version: `"latest"` or a number from 1.
+fn is_secret_version(version: &str) -> bool {
+    version == SecretVersions::LATEST
+        || (!version.is_empty() && version.bytes().all(|b| b.is_ascii_digit()) && version != "0")
+}
+
+/// One pinned version: `"latest"`, or a version number as string or integer.
+struct SecretVersion(String);
+
+impl<'de> Deserialize<'de> for SecretVersion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct VersionVisitor;
+
+        impl Visitor<'_> for VersionVisitor {
+            type Value = SecretVersion;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("\"latest\" or a secret version number")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                if is_secret_version(v) {
+                    Ok(SecretVersion(v.to_owned()))
+                } else {
+                    Err(E::invalid_value(de::Unexpected::Str(v), &self))
+                }
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                if v >= 1 {
+                    Ok(SecretVersion(v.to_string()))
+                } else {
+                    Err(E::invalid_value(de::Unexpected::Signed(v), &self))
+                }
+            }
+        }
+
+        deserializer.deserialize_any(VersionVisitor)
+    }
+}
+
+/// A `[secrets]` value: the Secret Manager secret an env var is mounted
+/// from and its version, written `"name"` (version `latest`) or
+/// `"name:version"`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct SecretRef {
+    /// Secret Manager secret name.
+    pub name: String,
+    /// `"latest"` or a version number.
+    pub version: String,
+}
+
+impl TryFrom<String> for SecretRef {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let (name, version) = match value.split_once(':') {
+            Some((name, version)) => (name, version),
+            None => (value.as_str(), SecretVersions::LATEST),
+        };
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(format!(
+                "invalid secret {value:?}: the name may only contain letters, digits, '_' and '-'"
+            ));
+        }
+        if !is_secret_version(version) {
+            return Err(format!(
+                "invalid secret {value:?}: the version must be \"latest\" or a version number"
+            ));
+        }
+        Ok(Self {
+            name: name.to_owned(),
+            version: version.to_owned(),
+        })
+    }
+}
+
+impl From<SecretRef> for String {
+    fn from(secret: SecretRef) -> Self {
+        format!("{}:{}", secret.name, secret.version)
+    }
+}
+
+/// `[cloud_run].canary`: how a new revision is rolled out before it is
+/// promoted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CanaryConfig {
+    /// Share of the traffic the new revision receives, 1–99.
+    pub percent: u32,
+}
+
+/// Cloud Run health check probe. All durations are in seconds.
+///
+/// With `path` the probe is an HTTP GET against the service port; without
+/// it, a TCP connect (startup probes only — liveness probes need a path).
+///
+/// ```toml
+/// [cloud_run.startup_probe]
+/// path = "/health"
+/// initial_delay = 10
+/// period = 5
+/// failure_threshold = 24
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProbeConfig {
+    /// HTTP path to GET, starting with `/`.
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Seconds to wait after the container starts before the first probe.
+    #[serde(default)]
+    pub initial_delay: u32,
+    /// Seconds between probes.
+    #[serde(default = "default_probe_period")]
+    pub period: u32,
+    /// Consecutive failures before the container is considered unhealthy.
+    #[serde(default = "default_probe_failure_threshold")]
+    pub failure_threshold: u32,
+    /// Seconds before a probe attempt times out. At most `period`.
+    #[serde(default = "default_probe_timeout")]
+    pub timeout: u32,
+}
+
+/// Path `propel::warmup_handler` is mounted at (`propel::WARMUP_PATH`),
+/// probed under `[cloud_run].warmup_handler`.
+pub const WARMUP_HANDLER_PATH: &str = "/warmup";
+
+/// Which probe a [`ProbeConfig`] configures; the two have different limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProbeKind {
+    Startup,
+    Liveness,
+}
+
+impl ProbeKind {
+    /// Table name in `propel.toml`.
+    pub fn table(self) -> &'static str {
+        match self {
+            Self::Startup => "cloud_run.startup_probe",
+            Self::Liveness => "cloud_run.liveness_probe",
+        }
+    }
+
+    /// Upper bound Cloud Run accepts for `initial_delay`, `period`, and
+    /// `timeout`.
+    fn max_seconds(self) -> u32 {
+        match self {
+            Self::Startup => 240,
+            Self::Liveness => 3600,
+        }
+    }
 }
 
 impl Default for ProjectConfig {
@@ -145,6 +1242,8 @@ impl Default for ProjectConfig {
             name: None,
             region: default_region(),
             gcp_project_id: None,
+            expected_account_suffix: None,
+            secret_prefix: None,
         }
     }
 }
@@ -155,9 +1254,30 @@ impl Default for BuildConfig {
             base_image: default_builder_image(),
             runtime_image: default_runtime_image(),
             extra_packages: Vec::new(),
+            runtime_packages: Vec::new(),
             cargo_chef_version: default_cargo_chef_version(),
+            chef_preinstalled: false,
+            chef_image: None,
             include: None,
+            include_check: true,
             env: HashMap::new(),
+            build_env: HashMap::new(),
+            git_credentials_secret: None,
+            bundle_manifest: false,
+            platform: None,
+            locked: None,
+            offline: false,
+            features: Vec::new(),
+            no_default_features: false,
+            rustflags: None,
+            target: None,
+            static_binary: false,
+            cache_mounts: false,
+            run_as_user: None,
+            tag_strategy: TagStrategy::default(),
+            runtime_image_check: true,
+            rust_version_check: true,
+            artifact_location: None,
         }
     }
 }
@@ -171,40 +1291,341 @@ impl Default for CloudRunConfig {
             max_instances: default_max_instances(),
             concurrency: default_concurrency(),
             port: default_port(),
+            cpu_always_allocated: false,
+            timeout_seconds: None,
+            service_account: None,
+            vpc_connector: None,
+            vpc_egress: None,
+            cloudsql_instances: Vec::new(),
+            startup_probe: None,
+            warmup_handler: false,
+            liveness_probe: None,
+            env: HashMap::new(),
+            secrets: None,
+            inject_secrets: default_inject_secrets(),
+            check_secret_versions: false,
+            allow_unauthenticated: default_allow_unauthenticated(),
+            warmup_requests: 0,
+            canary: None,
+            extra_flags: Vec::new(),
+        }
+    }
+}
+
+impl Default for ProbeConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            initial_delay: 0,
+            period: default_probe_period(),
+            failure_threshold: default_probe_failure_threshold(),
+            timeout: default_probe_timeout(),
         }
     }
 }
 
 impl PropelConfig {
+    /// Artifact Registry location images are pushed to:
+    /// `[build].artifact_location`, else `[project].region`.
+    pub fn artifact_location(&self) -> &str {
+        match &self.build.artifact_location {
+            Some(location) => location,
+            None => &self.project.region,
+        }
+    }
+
+    /// Short, stable digest of the effective config (defaults filled in),
+    /// used to tell deployed services apart by configuration.
+    ///
+    /// Tables are serialized with sorted keys, so the digest does not
+    /// depend on `HashMap` order or on how `propel.toml` is formatted.
+    pub fn config_hash(&self) -> String {
+        // Round-trip through `toml::Value`, whose tables are sorted maps.
+        let canonical = toml::Value::try_from(self)
+            .and_then(|value| toml::to_string(&value))
+            .expect("PropelConfig always serializes to TOML");
+        let digest = Sha256::digest(canonical.as_bytes());
+        digest[..CONFIG_HASH_BYTES]
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
     /// Load from propel.toml at the given path, or return defaults if not found.
     pub fn load(project_dir: &std::path::Path) -> crate::Result<Self> {
+        Self::load_reporting_unknown_keys(project_dir).map(|(config, _)| config)
+    }
+
+    /// [`load`](Self::load), also returning the dotted paths of keys
+    /// propel does not know (`build.base_imag`), which loading ignores.
+    /// Keys under free-form tables such as `[build.env]` are never
+    /// unknown.
+    pub fn load_reporting_unknown_keys(
+        project_dir: &std::path::Path,
+    ) -> crate::Result<(Self, Vec<String>)> {
         let config_path = project_dir.join("propel.toml");
-        if config_path.exists() {
-            tracing::debug!(path = %config_path.display(), "loading propel.toml");
-            let content =
-                std::fs::read_to_string(&config_path).map_err(|e| crate::Error::ConfigLoad {
-                    path: config_path.clone(),
-                    source: e,
-                })?;
-            let config: Self = toml::from_str(&content).map_err(|e| crate::Error::ConfigParse {
+        let mut unknown_keys = Vec::new();
+        if let Some(content) = read_config_file(&config_path)? {
+            if content.trim().is_empty() {
+                tracing::warn!(
+                    path = %config_path.display(),
+                    "propel.toml is empty; using defaults"
+                );
+            }
+            let deserializer = toml::Deserializer::new(&content);
+            let config: Self = serde_ignored::deserialize(deserializer, |path| {
+                unknown_keys.push(dotted_key(&path));
+            })
+            .map_err(|e| crate::Error::ConfigParse {
                 path: config_path,
                 source: e,
             })?;
+            config.project.validate_secret_prefix()?;
             config.build.validate_include_paths()?;
+            config.build.validate_platform()?;
+            config.build.validate_chef()?;
+            config.build.validate_artifact_location()?;
+            config.build.validate_cargo_flags()?;
+            config.build.validate_target()?;
+            if let Some(user) = &config.build.run_as_user {
+                user.validate()?;
+            }
+            config.cloud_build.validate()?;
+            config.cloud_run.validate_probes()?;
+            config.cloud_run.validate_extra_flags()?;
+            config.cloud_run.validate_canary()?;
+            config.cloud_run.validate_timeout()?;
+            config.cloud_run.validate_service_account()?;
+            config.cloud_run.validate_cloudsql_instances()?;
+            if !config.secrets.is_empty() && !config.cloud_run.inject_secrets {
+                return Err(crate::Error::SecretsWithoutInjection);
+            }
+            if config.cloud_run.canary.is_some() && config.ci.deploy_via_cloud_build {
+                return Err(crate::Error::CanaryWithCloudBuildDeploy);
+            }
             tracing::debug!(
                 region = %config.project.region,
                 port = config.cloud_run.port,
+                unknown_keys = ?unknown_keys,
                 "config loaded"
             );
-            Ok(config)
+            Ok((config, unknown_keys))
         } else {
             tracing::debug!(path = %config_path.display(), "propel.toml not found, using defaults");
-            Ok(Self::default())
+            Ok((Self::default(), unknown_keys))
         }
     }
 }
 
 impl BuildConfig {
+    /// Image the chef base stage starts `FROM`: `chef_image` when set,
+    /// otherwise `base_image`.
+    pub fn builder_image(&self) -> &str {
+        let chef_image = self.chef_image.as_deref();
+        // arch-lint: allow(no-silent-result-drop) reason="Option: None = no chef_image override; base_image is the intended default"
+        chef_image.unwrap_or(&self.base_image)
+    }
+
+    /// Reject chef options that contradict each other.
+    fn validate_chef(&self) -> crate::Result<()> {
+        if self.chef_preinstalled && self.cargo_chef_version != default_cargo_chef_version() {
+            return Err(crate::Error::ConflictingBuildOptions {
+                reason: format!(
+                    "chef_preinstalled = true skips installing cargo-chef, so \
+                     cargo_chef_version = {:?} would be ignored; set only one",
+                    self.cargo_chef_version
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// Whether the image build passes `--locked`: `locked` when set,
+    /// otherwise whether the bundle carries a `Cargo.lock`.
+    pub fn is_locked(&self, lockfile_bundled: bool) -> bool {
+        match self.locked {
+            Some(locked) => locked,
+            None => lockfile_bundled,
+        }
+    }
+
+    /// Reject `offline = true` for a project without vendored sources,
+    /// where the cacher stage could not fetch a single crate.
+    ///
+    /// Needs the project's `.cargo/config.toml`, so callers run this
+    /// before generating a Dockerfile rather than at load time.
+    pub fn validate_offline(&self, vendored_sources: bool) -> crate::Result<()> {
+        if self.offline && !vendored_sources {
+            return Err(crate::Error::ConflictingBuildOptions {
+                reason: "offline = true needs vendored sources — run `cargo vendor` and \
+                         point .cargo/config.toml at the vendor directory inside the project"
+                    .to_owned(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Cargo flags of `cargo chef cook` and `cargo build`: `--locked`
+    /// (see [`is_locked`](Self::is_locked)), `--target` (see
+    /// [`rust_target`](Self::rust_target)), `--no-default-features`, and
+    /// `--features`, each with a leading space.
+    pub fn cargo_flags(&self, lockfile_bundled: bool) -> String {
+        let mut flags = String::new();
+        if self.is_locked(lockfile_bundled) {
+            flags.push_str(" --locked");
+        }
+        if let Some(target) = self.rust_target() {
+            flags.push_str(" --target ");
+            flags.push_str(target);
+        }
+        if self.no_default_features {
+            flags.push_str(" --no-default-features");
+        }
+        if !self.features.is_empty() {
+            flags.push_str(" --features ");
+            flags.push_str(&self.features.join(","));
+        }
+        flags
+    }
+
+    /// Reject feature names and `rustflags` that would break the
+    /// generated Dockerfile, and a second source of `RUSTFLAGS`.
+    fn validate_cargo_flags(&self) -> crate::Result<()> {
+        if let Some(feature) = self.features.iter().find(|feature| {
+            feature.is_empty()
+                || feature.starts_with('/')
+                || feature.ends_with('/')
+                || !feature
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+' | '.' | '/'))
+        }) {
+            return Err(crate::Error::InvalidFeature {
+                value: feature.clone(),
+            });
+        }
+        let Some(rustflags) = &self.rustflags else {
+            return Ok(());
+        };
+        let reason = if rustflags.contains(['\n', '\r']) {
+            format!("rustflags = {rustflags:?} spans several lines; write the flags on one")
+        } else if self.build_env.contains_key("RUSTFLAGS") {
+            "rustflags and [build.build_env].RUSTFLAGS both set RUSTFLAGS; set only one".to_owned()
+        } else {
+            return Ok(());
+        };
+        Err(crate::Error::ConflictingBuildOptions { reason })
+    }
+
+    /// Runtime image of a static build whose `runtime_image` is left at
+    /// its default: no libc, but CA certificates, tzdata, and a `nonroot`
+    /// user.
+    pub const STATIC_RUNTIME_IMAGE: &'static str = "gcr.io/distroless/static-debian12";
+
+    /// Target triple passed to `--target`: `target` when set, otherwise the
+    /// musl target of `platform` when `static_binary` is on.
+    pub fn rust_target(&self) -> Option<&str> {
+        match (&self.target, self.platform) {
+            (Some(target), _) => Some(target),
+            (None, _) if !self.static_binary => None,
+            // Cloud Build workers are amd64, the default platform.
+            (None, None) => Some(Platform::Amd64.musl_target()),
+            (None, Some(platform)) => Some(platform.musl_target()),
+        }
+    }
+
+    /// Whether the binary is linked statically, i.e. built for a musl
+    /// target, so it runs on any runtime image.
+    pub fn is_static(&self) -> bool {
+        self.rust_target()
+            .is_some_and(|target| target.contains("-musl"))
+    }
+
+    /// Image the runtime stage starts `FROM`: `runtime_image`, or
+    /// [`STATIC_RUNTIME_IMAGE`](Self::STATIC_RUNTIME_IMAGE) for a static
+    /// build that leaves `runtime_image` at its default.
+    pub fn effective_runtime_image(&self) -> &str {
+        if self.is_static() && self.runtime_image == default_runtime_image() {
+            Self::STATIC_RUNTIME_IMAGE
+        } else {
+            &self.runtime_image
+        }
+    }
+
+    /// Reject a `target` that is not a target triple, and a non-musl
+    /// `target` alongside `static_binary`.
+    fn validate_target(&self) -> crate::Result<()> {
+        let Some(target) = &self.target else {
+            return Ok(());
+        };
+        if target.is_empty()
+            || !target
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        {
+            return Err(crate::Error::InvalidTarget {
+                value: target.clone(),
+            });
+        }
+        if self.static_binary && !self.is_static() {
+            return Err(crate::Error::ConflictingBuildOptions {
+                reason: format!(
+                    "static_binary = true builds for a musl target, but target = {target:?} \
+                     is not one; set a musl target or drop static_binary"
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// Reject platform combinations the generated build cannot produce.
+    ///
+    /// musl/static builder images (Alpine, `muslrust`, …) are not yet
+    /// supported for arm64.
+    fn validate_platform(&self) -> crate::Result<()> {
+        if self.platform != Some(Platform::Arm64) {
+            return Ok(());
+        }
+        let image = self.builder_image().to_ascii_lowercase();
+        if image.contains("musl") || image.contains("alpine") {
+            return Err(crate::Error::UnsupportedPlatform {
+                platform: Platform::Arm64.as_str(),
+                reason: format!(
+                    "musl/static builds are not supported yet (builder image {:?})",
+                    self.builder_image()
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// Artifact Registry multi-regions accepted by `artifact_location`.
+    pub const ARTIFACT_MULTI_REGIONS: &'static [&'static str] = &["us", "europe", "asia"];
+
+    /// Accept a known multi-region, or a name shaped like a region
+    /// (`us-central1`, `europe-west1`).
+    fn validate_artifact_location(&self) -> crate::Result<()> {
+        let Some(location) = &self.artifact_location else {
+            return Ok(());
+        };
+        if Self::ARTIFACT_MULTI_REGIONS.contains(&location.as_str()) {
+            return Ok(());
+        }
+        let region_like = location.split_once('-').is_some_and(|(area, rest)| {
+            !area.is_empty()
+                && area.chars().all(|c| c.is_ascii_lowercase())
+                && rest.ends_with(|c: char| c.is_ascii_digit())
+                && rest
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        });
+        if region_like {
+            return Ok(());
+        }
+        Err(crate::Error::InvalidArtifactLocation {
+            value: location.clone(),
+        })
+    }
+
     /// Validate `include` paths, rejecting empty or whitespace-only entries.
     fn validate_include_paths(&self) -> crate::Result<()> {
         let paths = match &self.include {
@@ -234,6 +1655,35 @@ fn default_region() -> String {
     "us-central1".to_owned()
 }
 
+fn default_include_check() -> bool {
+    true
+}
+
+/// `build.base_imag` for an ignored key, leaving out the `Option` and
+/// newtype layers serde_ignored records along the way.
+fn dotted_key(path: &serde_ignored::Path<'_>) -> String {
+    use serde_ignored::Path;
+    match path {
+        Path::Root => String::new(),
+        Path::Map { parent, key } => match dotted_key(parent) {
+            prefix if prefix.is_empty() => key.clone(),
+            prefix => format!("{prefix}.{key}"),
+        },
+        Path::Seq { parent, index } => format!("{}[{index}]", dotted_key(parent)),
+        Path::Some { parent }
+        | Path::NewtypeStruct { parent }
+        | Path::NewtypeVariant { parent } => dotted_key(parent),
+    }
+}
+
+fn default_runtime_image_check() -> bool {
+    true
+}
+
+fn default_rust_version_check() -> bool {
+    true
+}
+
 fn default_builder_image() -> String {
     "rust:1.93-bookworm".to_owned()
 }
@@ -265,3 +1715,226 @@ fn default_concurrency() -> u32 {
 fn default_port() -> u16 {
     8080
 }
+
+fn default_inject_secrets() -> bool {
+    true
+}
+
+fn default_allow_unauthenticated() -> bool {
+    true
+}
+
+fn default_probe_period() -> u32 {
+    10
+}
+
+fn default_probe_failure_threshold() -> u32 {
+    3
+}
+
+fn default_probe_timeout() -> u32 {
+    1
+}
+
+impl CloudRunConfig {
+    /// Longest request timeout Cloud Run accepts, in seconds.
+    pub const MAX_TIMEOUT_SECONDS: u32 = 3600;
+
+    /// Startup probe to deploy: `[cloud_run.startup_probe]`, pointed at
+    /// [`WARMUP_HANDLER_PATH`] under [`Self::warmup_handler`] unless it
+    /// names a path itself.
+    pub fn effective_startup_probe(&self) -> Option<ProbeConfig> {
+        match (&self.startup_probe, self.warmup_handler) {
+            (Some(probe), true) if probe.path.is_none() => Some(ProbeConfig {
+                path: Some(WARMUP_HANDLER_PATH.to_owned()),
+                ..probe.clone()
+            }),
+            (None, true) => Some(ProbeConfig {
+                path: Some(WARMUP_HANDLER_PATH.to_owned()),
+                ..ProbeConfig::default()
+            }),
+            (probe, _) => probe.clone(),
+        }
+    }
+
+    /// Path warm-up requests go to: the startup probe's HTTP path, else the
+    /// liveness probe's, else `/`.
+    pub fn warmup_path(&self) -> &str {
+        if self.warmup_handler
+            && self
+                .startup_probe
+                .as_ref()
+                .is_none_or(|probe| probe.path.is_none())
+        {
+            return WARMUP_HANDLER_PATH;
+        }
+        [&self.startup_probe, &self.liveness_probe]
+            .into_iter()
+            .flatten()
+            .find_map(|probe| probe.path.as_deref())
+            // arch-lint: allow(no-silent-result-drop) reason="Option: None = no probe path configured; / is the documented default"
+            .unwrap_or("/")
+    }
+
+    /// Version of `secret` to mount: its pin in [`Self::secrets`], or `latest`.
+    pub fn secret_version(&self, secret: &str) -> &str {
+        match self
+            .secrets
+            .as_ref()
+            .and_then(|versions| versions.get(secret))
+        {
+            Some(version) => version,
+            None => SecretVersions::LATEST,
+        }
+    }
+
+    /// Validate probe settings against Cloud Run's limits.
+    fn validate_probes(&self) -> crate::Result<()> {
+        if let Some(probe) = &self.startup_probe {
+            probe.validate(ProbeKind::Startup)?;
+        }
+        if let Some(probe) = &self.liveness_probe {
+            probe.validate(ProbeKind::Liveness)?;
+        }
+        Ok(())
+    }
+
+    /// Check `timeout_seconds` against the range Cloud Run accepts.
+    fn validate_timeout(&self) -> crate::Result<()> {
+        match self.timeout_seconds {
+            Some(seconds) if !(1..=Self::MAX_TIMEOUT_SECONDS).contains(&seconds) => {
+                Err(crate::Error::InvalidRequestTimeout { seconds })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that `service_account` is an email, not an account id.
+    fn validate_service_account(&self) -> crate::Result<()> {
+        match &self.service_account {
+            Some(email) if email.split_once('@').is_none_or(|(id, _)| id.is_empty()) => {
+                Err(crate::Error::InvalidServiceAccount {
+                    value: email.clone(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that each `cloudsql_instances` entry is a
+    /// `project:region:instance` connection name, not a bare instance name.
+    fn validate_cloudsql_instances(&self) -> crate::Result<()> {
+        match self.cloudsql_instances.iter().find(|name| {
+            let parts: Vec<&str> = name.split(':').collect();
+            parts.len() != 3 || parts.iter().any(|part| part.is_empty())
+        }) {
+            Some(name) => Err(crate::Error::InvalidCloudSqlInstance {
+                value: name.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Check that a canary takes some, but not all, of the traffic.
+    fn validate_canary(&self) -> crate::Result<()> {
+        match self.canary {
+            Some(CanaryConfig { percent }) if !(1..=99).contains(&percent) => {
+                Err(crate::Error::InvalidCanaryPercent { percent })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Reject `extra_flags` entries gcloud would misread: a value split
+    /// into its own entry, or a flag and value joined by a space.
+    fn validate_extra_flags(&self) -> crate::Result<()> {
+        for flag in &self.extra_flags {
+            let reason = if !flag.starts_with("--") {
+                "each entry must start with \"--\"; write a value as \"--flag=value\""
+            } else if flag
+                .split('=')
+                .next()
+                .is_some_and(|name| name.contains(char::is_whitespace))
+            {
+                "write a value as \"--flag=value\", not \"--flag value\""
+            } else {
+                continue;
+            };
+            return Err(crate::Error::InvalidExtraFlag {
+                flag: flag.clone(),
+                reason,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl ProbeConfig {
+    /// Check ranges for a probe of the given kind.
+    pub fn validate(&self, kind: ProbeKind) -> crate::Result<()> {
+        let invalid = |reason: String| crate::Error::InvalidProbe {
+            table: kind.table(),
+            reason,
+        };
+        let max = kind.max_seconds();
+
+        match &self.path {
+            Some(path) if !path.starts_with('/') => {
+                return Err(invalid(format!("path {path:?} must start with '/'")));
+            }
+            None if kind == ProbeKind::Liveness => {
+                return Err(invalid(
+                    "path is required (liveness probes must be HTTP)".to_owned(),
+                ));
+            }
+            _ => {}
+        }
+        if self.initial_delay > max {
+            return Err(invalid(format!("initial_delay must be at most {max}")));
+        }
+        if !(1..=max).contains(&self.period) {
+            return Err(invalid(format!("period must be between 1 and {max}")));
+        }
+        if self.failure_threshold == 0 {
+            return Err(invalid("failure_threshold must be at least 1".to_owned()));
+        }
+        if !(1..=self.period).contains(&self.timeout) {
+            return Err(invalid(format!(
+                "timeout must be between 1 and period ({})",
+                self.period
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl CloudBuildConfig {
+    /// Bundle size limit when `max_bundle_mb` is unset: 1.5 GiB.
+    pub const DEFAULT_MAX_BUNDLE_MB: u64 = 1536;
+
+    /// Bundle size limit in bytes.
+    pub fn max_bundle_bytes(&self) -> u64 {
+        let mb = match self.max_bundle_mb {
+            Some(mb) => mb,
+            None => Self::DEFAULT_MAX_BUNDLE_MB,
+        };
+        mb.saturating_mul(1024 * 1024)
+    }
+
+    /// Image size warning threshold in bytes, when `warn_image_mb` is set.
+    pub fn warn_image_bytes(&self) -> Option<u64> {
+        self.warn_image_mb.map(|mb| mb.saturating_mul(1024 * 1024))
+    }
+
+    /// Validate `logs_bucket`, which gcloud only accepts as a `gs://` URL.
+    fn validate(&self) -> crate::Result<()> {
+        if let Some(bucket) = &self.logs_bucket
+            && bucket.strip_prefix("gs://").is_none_or(str::is_empty)
+        {
+            return Err(crate::Error::InvalidLogsBucket {
+                value: bucket.clone(),
+            });
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,155 @@
+//! Local log of what `propel deploy`, `propel promote`, and
+//! `propel abort-canary` did, under `.propel/history/`.
+//!
+//! Cloud Run keeps one revision per deploy but forgets traffic moves, so
+//! this log is where a promoted or aborted canary is remembered. Records
+//! are JSON lines appended to one file; a line that does not parse (an
+//! interrupted write, a record from a newer propel) is skipped on read.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// History directory, relative to the project directory.
+pub const HISTORY_DIR: &str = ".propel/history";
+
+/// The record file inside [`HISTORY_DIR`].
+const HISTORY_FILE: &str = "deploys.jsonl";
+
+/// Keeps the history out of git, and so out of the dirty check and bundle.
+const GITIGNORE: &str = "# Written by propel\n*\n";
+
+/// What a [`DeployRecord`] records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeployEvent {
+    /// A new revision took all traffic.
+    Deploy,
+    /// A new revision took `[cloud_run].canary` percent of traffic.
+    CanaryStarted,
+    /// `propel promote` sent all traffic to the canary.
+    CanaryPromoted,
+    /// `propel abort-canary` sent all traffic back to the stable revision.
+    CanaryAborted,
+}
+
+/// One line of the deploy history.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeployRecord {
+    /// When it happened, RFC 3339 in UTC.
+    pub at: String,
+    pub event: DeployEvent,
+    pub service: String,
+    pub region: String,
+    /// The canary revision, for promote and abort.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<String>,
+    /// The revision an abort sent all traffic back to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stable_revision: Option<String>,
+    /// Image deployed, for deploys.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// Traffic share of the canary when the event happened.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub percent: Option<u32>,
+    /// Email of the active gcloud account. Only the email is kept, never
+    /// a credential.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+    /// Number of the GCP project, from the deploy's preflight.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_number: Option<String>,
+}
+
+impl DeployRecord {
+    /// A record of `event` on `service` in `region` at `at`, with the
+    /// optional fields unset.
+    pub fn new(at: String, event: DeployEvent, service: &str, region: &str) -> Self {
+        Self {
+            at,
+            event,
+            service: service.to_owned(),
+            region: region.to_owned(),
+            revision: None,
+            stable_revision: None,
+            image: None,
+            percent: None,
+            account: None,
+            project_number: None,
+        }
+    }
+}
+
+/// The deploy history of one project directory.
+#[derive(Debug, Clone)]
+pub struct DeployHistory {
+    dir: PathBuf,
+}
+
+impl DeployHistory {
+    /// History of the project at `project_dir`. Nothing is created until
+    /// the first [`append`](Self::append).
+    pub fn new(project_dir: &Path) -> Self {
+        Self {
+            dir: project_dir.join(HISTORY_DIR),
+        }
+    }
+
+    /// Add `record` at the end of the history.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::HistoryWrite`](crate::Error::HistoryWrite) when the
+    /// directory or record cannot be written.
+    pub fn append(&self, record: &DeployRecord) -> crate::Result<()> {
+        let path = self.dir.join(HISTORY_FILE);
+        let write_error = |source| crate::Error::HistoryWrite {
+            path: path.clone(),
+            source,
+        };
+        std::fs::create_dir_all(&self.dir).map_err(write_error)?;
+        let gitignore = self.dir.join(".gitignore");
+        if !gitignore.exists() {
+            std::fs::write(&gitignore, GITIGNORE).map_err(write_error)?;
+        }
+
+        let mut line =
+            serde_json::to_string(record).map_err(|e| write_error(std::io::Error::other(e)))?;
+        line.push('\n');
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(write_error)
+    }
+
+    /// Every readable record, oldest first. A missing history is empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::HistoryRead`](crate::Error::HistoryRead) when the
+    /// history file exists but cannot be read.
+    pub fn records(&self) -> crate::Result<Vec<DeployRecord>> {
+        let path = self.dir.join(HISTORY_FILE);
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(source) => return Err(crate::Error::HistoryRead { path, source }),
+        };
+        Ok(content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(record) => Some(record),
+                // arch-lint: allow(no-error-swallowing) reason="a torn or newer record costs one line, not the history"
+                Err(e) => {
+                    tracing::debug!(path = %path.display(), error = %e, "skipping unreadable history record");
+                    None
+                }
+            })
+            .collect())
+    }
+}
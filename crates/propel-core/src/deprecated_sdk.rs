@@ -0,0 +1,78 @@
+//! Detection of the deprecated `propel-sdk` crate.
+//!
+//! `propel-sdk` was merged into `propel` and only re-exports the original
+//! middleware, so a project still depending on it misses newer additions
+//! such as server keys and `AuthIdentity` — and the docs it reads no
+//! longer match its imports. Finding the dependency in the project's
+//! manifest lets deploy and `propel doctor` print the migration.
+
+use std::path::Path;
+
+use cargo_metadata::Metadata;
+
+/// Name of the deprecated crate on crates.io.
+pub const DEPRECATED_SDK_CRATE: &str = "propel-sdk";
+
+/// `propel` version to migrate to: the release line this CLI ships with.
+const PROPEL_VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION_MAJOR"),
+    ".",
+    env!("CARGO_PKG_VERSION_MINOR")
+);
+
+/// A direct dependency on [`DEPRECATED_SDK_CRATE`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeprecatedSdkDependency {
+    /// Version requirement as resolved by Cargo (e.g. `^0.2`).
+    pub requirement: String,
+}
+
+impl DeprecatedSdkDependency {
+    /// `Cargo.toml` and import changes that move the project to `propel`.
+    pub fn migration_snippet(&self) -> String {
+        format!(
+            "# Cargo.toml\n\
+             - {DEPRECATED_SDK_CRATE} = \"{requirement}\"\n\
+             + propel = \"{PROPEL_VERSION}\"\n\
+             \n\
+             // src/*.rs\n\
+             - use propel_sdk::{{PropelAuth, PropelState}};\n\
+             + use propel::{{PropelAuth, PropelState}};",
+            requirement = self.requirement.trim_start_matches('^'),
+        )
+    }
+}
+
+impl std::fmt::Display for DeprecatedSdkDependency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{DEPRECATED_SDK_CRATE} {} is deprecated and missing features such as server keys \
+             and AuthIdentity — switch to the propel crate:\n\n{}",
+            self.requirement,
+            self.migration_snippet()
+        )
+    }
+}
+
+/// Find a dependency on [`DEPRECATED_SDK_CRATE`] declared by the package at
+/// `manifest_path`.
+///
+/// Only the package's own manifest is consulted: a transitive `propel-sdk`
+/// is another crate's to migrate. Works on metadata with or without
+/// `--no-deps`.
+pub fn detect_deprecated_sdk(
+    metadata: &Metadata,
+    manifest_path: &Path,
+) -> Option<DeprecatedSdkDependency> {
+    metadata
+        .packages
+        .iter()
+        .find(|p| p.manifest_path.as_std_path() == manifest_path)?
+        .dependencies
+        .iter()
+        .find(|d| d.name == DEPRECATED_SDK_CRATE)
+        .map(|d| DeprecatedSdkDependency {
+            requirement: d.req.to_string(),
+        })
+}
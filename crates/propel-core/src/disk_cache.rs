@@ -0,0 +1,166 @@
+//! Small JSON cache under `.propel/cache/` for results that are slow to
+//! fetch and rarely change, such as the doctor's API checks.
+//!
+//! Each key is one file holding the value and when it expires. A missing,
+//! expired, or unreadable entry is a miss: a corrupted file (an interrupted
+//! write, a manual edit) costs one refetch, never an error. Writes go to a
+//! temporary file renamed into place, so concurrent writers leave one
+//! complete entry behind.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// Cache directory, relative to the project directory.
+pub const CACHE_DIR: &str = ".propel/cache";
+
+/// Longest accepted key.
+const MAX_KEY_LEN: usize = 100;
+
+/// Keeps the cache out of git, and so out of the dirty check and bundle.
+const GITIGNORE: &str = "# Written by propel\n*\n";
+
+/// Distinguishes temporary files of writers in the same process.
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// JSON entries with a time-to-live, one file per key under
+/// [`CACHE_DIR`].
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    project_dir: PathBuf,
+    dir: PathBuf,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry<T> {
+    /// Seconds since the Unix epoch.
+    expires_at: u64,
+    value: T,
+}
+
+impl DiskCache {
+    /// Cache of the project at `project_dir`. Nothing is created until the
+    /// first [`put`](Self::put).
+    pub fn new(project_dir: &Path) -> Self {
+        Self {
+            project_dir: project_dir.to_path_buf(),
+            dir: project_dir.join(CACHE_DIR),
+        }
+    }
+
+    /// The cached value for `key`, unless it is missing, expired, or
+    /// unreadable.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidCacheKey`](crate::Error::InvalidCacheKey)
+    /// for a key that is not a plain file name.
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> crate::Result<Option<T>> {
+        let path = self.entry_path(key)?;
+        let content = match std::fs::read(&path) {
+            Ok(content) => content,
+            Err(e) => {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    tracing::debug!(path = %path.display(), error = %e, "cache entry unreadable");
+                }
+                return Ok(None);
+            }
+        };
+        let entry: Entry<T> = match serde_json::from_slice(&content) {
+            Ok(entry) => entry,
+            Err(e) => {
+                tracing::debug!(path = %path.display(), error = %e, "ignoring corrupt cache entry");
+                return Ok(None);
+            }
+        };
+        if unix_now() >= entry.expires_at {
+            return Ok(None);
+        }
+        Ok(Some(entry.value))
+    }
+
+    /// Store `value` under `key` for `ttl`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidCacheKey`](crate::Error::InvalidCacheKey)
+    /// for a key that is not a plain file name,
+    /// [`Error::CacheOutsideProject`](crate::Error::CacheOutsideProject)
+    /// when `.propel/cache` resolves outside the project (e.g. through a
+    /// symlink), and [`Error::CacheWrite`](crate::Error::CacheWrite) when
+    /// the entry cannot be written.
+    pub fn put<T: Serialize>(&self, key: &str, value: &T, ttl: Duration) -> crate::Result<()> {
+        let path = self.entry_path(key)?;
+        self.create_dir()?;
+
+        let entry = Entry {
+            expires_at: unix_now().saturating_add(ttl.as_secs()),
+            value,
+        };
+        let json = serde_json::to_vec(&entry).map_err(|e| crate::Error::CacheWrite {
+            path: path.clone(),
+            source: std::io::Error::other(e),
+        })?;
+
+        let tmp = self.dir.join(format!(
+            ".{key}.{}.{}.tmp",
+            std::process::id(),
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let written = std::fs::write(&tmp, json).and_then(|()| std::fs::rename(&tmp, &path));
+        if let Err(e) = written {
+            // arch-lint: allow(no-silent-result-drop) reason="best-effort cleanup; the write error is returned"
+            let _ = std::fs::remove_file(&tmp);
+            return Err(crate::Error::CacheWrite { path, source: e });
+        }
+        Ok(())
+    }
+
+    /// Path of `key`'s entry. Keys are plain file names, so every entry
+    /// stays inside the cache directory.
+    fn entry_path(&self, key: &str) -> crate::Result<PathBuf> {
+        let valid = !key.is_empty()
+            && key.len() <= MAX_KEY_LEN
+            && !key.starts_with('.')
+            && key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if !valid {
+            return Err(crate::Error::InvalidCacheKey {
+                key: key.to_owned(),
+            });
+        }
+        Ok(self.dir.join(format!("{key}.json")))
+    }
+
+    /// Create the cache directory with its `.gitignore`, and check it
+    /// resolves inside the project.
+    fn create_dir(&self) -> crate::Result<()> {
+        let write_error = |source| crate::Error::CacheWrite {
+            path: self.dir.clone(),
+            source,
+        };
+        std::fs::create_dir_all(&self.dir).map_err(write_error)?;
+
+        let dir = std::fs::canonicalize(&self.dir).map_err(write_error)?;
+        let project = std::fs::canonicalize(&self.project_dir).map_err(write_error)?;
+        if !dir.starts_with(&project) {
+            return Err(crate::Error::CacheOutsideProject { dir });
+        }
+
+        let gitignore = self.dir.join(".gitignore");
+        if !gitignore.exists() {
+            std::fs::write(&gitignore, GITIGNORE).map_err(write_error)?;
+        }
+        Ok(())
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
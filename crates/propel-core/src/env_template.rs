@@ -0,0 +1,144 @@
+//! `{{variable}}` substitution in `[build.env]` and `[cloud_run.env]` values.
+//!
+//! Values are resolved at deploy time so an app can report the commit and
+//! version it was deployed from. The variable set is deliberately small:
+//!
+//! | Variable          | `[build.env]` | `[cloud_run.env]` |
+//! |-------------------|---------------|-------------------|
+//! | `{{commit}}`      | yes           | yes               |
+//! | `{{version}}`     | yes           | yes               |
+//! | `{{service}}`     | —             | yes               |
+//! | `{{region}}`      | —             | yes               |
+//! | `{{deploy_time}}` | —             | yes               |
+//!
+//! `[build.env]` is baked into the image, so it only gets values that are
+//! fixed by the source tree; anything else would bust the layer cache or
+//! tie an image to one deployment.
+
+use std::collections::HashMap;
+
+/// Template variables known to propel.
+pub const TEMPLATE_VARIABLES: &[&str] = &["commit", "version", "service", "region", "deploy_time"];
+
+/// Values available for substitution. `None` fields are known variables
+/// that the current table does not support.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateContext<'a> {
+    /// Full git commit SHA of the deployed tree.
+    pub commit: Option<&'a str>,
+    /// Cargo package version.
+    pub version: Option<&'a str>,
+    /// Cloud Run service name.
+    pub service: Option<&'a str>,
+    /// Cloud Run region.
+    pub region: Option<&'a str>,
+    /// Deploy start time, RFC 3339 UTC.
+    pub deploy_time: Option<&'a str>,
+}
+
+impl<'a> TemplateContext<'a> {
+    /// Context for `[build.env]`: source-tree values only.
+    pub fn build(commit: &'a str, version: &'a str) -> Self {
+        Self {
+            commit: Some(commit),
+            version: Some(version),
+            ..Self::default()
+        }
+    }
+
+    /// Context for `[cloud_run.env]`: everything known before deploy.
+    pub fn runtime(
+        commit: &'a str,
+        version: &'a str,
+        service: &'a str,
+        region: &'a str,
+        deploy_time: &'a str,
+    ) -> Self {
+        Self {
+            commit: Some(commit),
+            version: Some(version),
+            service: Some(service),
+            region: Some(region),
+            deploy_time: Some(deploy_time),
+        }
+    }
+
+    /// `Ok(None)` for a known variable this context lacks.
+    fn lookup(&self, name: &str) -> Result<Option<&'a str>, TemplateError> {
+        match name {
+            "commit" => Ok(self.commit),
+            "version" => Ok(self.version),
+            "service" => Ok(self.service),
+            "region" => Ok(self.region),
+            "deploy_time" => Ok(self.deploy_time),
+            "url" => Err(TemplateError::UrlUnavailable),
+            _ => Err(TemplateError::Unknown {
+                name: name.to_owned(),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TemplateError {
+    #[error("unknown template variable {{{{{name}}}}} (supported: {})", TEMPLATE_VARIABLES.join(", "))]
+    Unknown { name: String },
+
+    #[error("{{{{{name}}}}} is not available here (only in [cloud_run.env])")]
+    Unavailable { name: String },
+
+    #[error(
+        "{{{{url}}}} is not supported: the service URL is only known after the deploy that sets the env"
+    )]
+    UrlUnavailable,
+
+    #[error("unterminated `{{{{` in {value:?}")]
+    Unterminated { value: String },
+}
+
+/// Replace every `{{name}}` (whitespace inside the braces is allowed) in
+/// `value` with its value from `ctx`.
+pub fn substitute(value: &str, ctx: &TemplateContext<'_>) -> Result<String, TemplateError> {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            return Err(TemplateError::Unterminated {
+                value: value.to_owned(),
+            });
+        };
+        let name = after[..end].trim();
+        match ctx.lookup(name)? {
+            Some(resolved) => out.push_str(resolved),
+            None => {
+                return Err(TemplateError::Unavailable {
+                    name: name.to_owned(),
+                });
+            }
+        }
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Substitute every value of an env table, naming the offending key on error.
+pub fn substitute_env(
+    table: &'static str,
+    env: &HashMap<String, String>,
+    ctx: &TemplateContext<'_>,
+) -> crate::Result<HashMap<String, String>> {
+    env.iter()
+        .map(|(key, value)| {
+            substitute(value, ctx)
+                .map(|resolved| (key.clone(), resolved))
+                .map_err(|e| crate::Error::EnvTemplate {
+                    table,
+                    key: key.clone(),
+                    source: e,
+                })
+        })
+        .collect()
+}
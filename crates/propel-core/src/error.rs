@@ -10,15 +10,160 @@ pub enum Error {
         source: std::io::Error,
     },
 
+    #[error(
+        "{path} is a directory, not a file; remove it (`rmdir {path}`) or replace it with \
+         a propel.toml file"
+    )]
+    ConfigIsDirectory { path: PathBuf },
+
+    #[error("permission denied reading {path}; make it readable (`chmod u+r {path}`)")]
+    ConfigPermissionDenied {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
     #[error("failed to parse config at {path}")]
     ConfigParse {
         path: PathBuf,
         source: toml::de::Error,
     },
 
+    #[error("failed to write config to {path}")]
+    ConfigWrite {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("failed to edit config at {path}: {detail}")]
+    ConfigEdit { path: PathBuf, detail: String },
+
     #[error("invalid include path {path:?}: {reason}")]
     InvalidIncludePath { path: String, reason: &'static str },
 
+    #[error("[build].platform = {platform:?}: {reason}")]
+    UnsupportedPlatform {
+        platform: &'static str,
+        reason: String,
+    },
+
+    #[error("[build].run_as_user = {value}: {reason}")]
+    InvalidRunAsUser { value: String, reason: &'static str },
+
+    #[error("[build].runtime_image = {runtime:?} cannot run the binary: {reason}")]
+    IncompatibleRuntimeImage { runtime: String, reason: String },
+
+    #[error(
+        "builder image {image:?} has Rust {builder_version}, older than the package's \
+         rust-version {rust_version}, so cargo would refuse to build it. Use a newer \
+         [build].base_image (or chef_image), or set [build].rust_version_check = false"
+    )]
+    BuilderImageTooOld {
+        image: String,
+        builder_version: String,
+        rust_version: String,
+    },
+
+    #[error("conflicting [build] options: {reason}")]
+    ConflictingBuildOptions { reason: String },
+
+    #[error(
+        "invalid [build].features entry {value:?}: use a feature name such as \"cloud\" \
+         or \"dependency/feature\""
+    )]
+    InvalidFeature { value: String },
+
+    #[error(
+        "invalid [build].target {value:?}: use a Rust target triple such as \
+         \"x86_64-unknown-linux-musl\""
+    )]
+    InvalidTarget { value: String },
+
+    #[error("invalid Cloud Run service name {name:?}: {reason}")]
+    InvalidServiceName { name: String, reason: &'static str },
+
+    #[error(
+        "invalid [project].secret_prefix {value:?}: use letters, digits, '_' and '-' \
+         (e.g. \"staging_\")"
+    )]
+    InvalidSecretPrefix { value: String },
+
+    #[error(
+        "invalid [build].artifact_location {value:?}: use a multi-region (us, europe, asia) \
+         or a region such as us-central1"
+    )]
+    InvalidArtifactLocation { value: String },
+
+    #[error("invalid [cloud_build].logs_bucket {value:?}: expected a gs://bucket/path URL")]
+    InvalidLogsBucket { value: String },
+
+    #[error("invalid [{table}]: {reason}")]
+    InvalidProbe { table: &'static str, reason: String },
+
+    #[error("[cloud_run].timeout_seconds must be between 1 and 3600, got {seconds}")]
+    InvalidRequestTimeout { seconds: u32 },
+
+    #[error(
+        "invalid [cloud_run].service_account {value:?}: expected an email such as \
+         name@project.iam.gserviceaccount.com"
+    )]
+    InvalidServiceAccount { value: String },
+
+    #[error(
+        "invalid [cloud_run].cloudsql_instances entry {value:?}: expected a connection \
+         name such as my-project:us-central1:my-instance \
+         (`gcloud sql instances describe <instance> --format='value(connectionName)'`)"
+    )]
+    InvalidCloudSqlInstance { value: String },
+
+    #[error("[cloud_run].canary percent must be between 1 and 99, got {percent}")]
+    InvalidCanaryPercent { percent: u32 },
+
+    #[error(
+        "[cloud_run].canary needs propel to deploy the revision itself; \
+         it cannot be combined with [ci].deploy_via_cloud_build = true"
+    )]
+    CanaryWithCloudBuildDeploy,
+
+    #[error(
+        "[secrets] maps env vars to secrets, but [cloud_run].inject_secrets = false \
+         mounts none; remove one of them"
+    )]
+    SecretsWithoutInjection,
+
+    #[error("[cloud_run].extra_flags entry {flag:?}: {reason}")]
+    InvalidExtraFlag { flag: String, reason: &'static str },
+
+    #[error("invalid cache key {key:?}: use ASCII letters, digits, '-', '_', and '.'")]
+    InvalidCacheKey { key: String },
+
+    #[error("cache directory {dir} resolves outside the project; remove it")]
+    CacheOutsideProject { dir: PathBuf },
+
+    #[error("failed to write cache entry {path}")]
+    CacheWrite {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("failed to write deploy history {path}")]
+    HistoryWrite {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("failed to read deploy history {path}")]
+    HistoryRead {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("[{table}].{key}: {source}")]
+    EnvTemplate {
+        table: &'static str,
+        key: String,
+        source: crate::env_template::TemplateError,
+    },
+
     // ── Cargo project discovery ──
     #[error("cargo metadata failed for {manifest_path}: {detail}")]
     CargoMetadata {
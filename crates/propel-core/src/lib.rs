@@ -5,8 +5,37 @@
 
 pub mod cargo;
 pub mod config;
+pub mod deploy_history;
+pub mod deprecated_sdk;
+pub mod disk_cache;
+pub mod env_template;
 pub mod error;
+pub mod lint;
+pub mod native;
+pub mod private_deps;
+pub mod runtime_paths;
+pub mod secret_namespace;
+pub mod service_name;
 
 pub use cargo::{CargoBinary, CargoProject};
-pub use config::{BuildConfig, CloudRunConfig, ProjectConfig, PropelConfig};
+pub use config::{
+    AuthConfig, BuildConfig, CanaryConfig, CiConfig, CiResources, CloudBuildConfig, CloudRunConfig,
+    Platform, ProbeConfig, ProbeKind, ProjectConfig, PropelConfig, RunAsUser, SecretRef,
+    SecretVersions, TagStrategy, VpcEgress, WARMUP_HANDLER_PATH,
+};
+pub use deploy_history::{DeployEvent, DeployHistory, DeployRecord, HISTORY_DIR};
+pub use deprecated_sdk::{DEPRECATED_SDK_CRATE, DeprecatedSdkDependency, detect_deprecated_sdk};
+pub use disk_cache::{CACHE_DIR, DiskCache};
+pub use env_template::{TemplateContext, TemplateError, substitute, substitute_env};
 pub use error::{Error, Result};
+pub use lint::{ConfigWarning, ProjectFlags, WarningKind, check_config};
+pub use native::{
+    DEBIAN_RUNTIME_IMAGE, KNOWN_NATIVE_DEPENDENCIES, NativeDependency, PackageSuggestion,
+    detect_native_dependencies, resolve_metadata,
+};
+pub use private_deps::{PrivateDependency, detect_private_dependencies};
+pub use runtime_paths::{
+    PathReference, env_path_references, is_included, scan_source, top_level_entry,
+};
+pub use secret_namespace::{SecretBinding, SecretNamespace};
+pub use service_name::{MAX_SERVICE_NAME_LEN, derive_service_name, validate_service_name};
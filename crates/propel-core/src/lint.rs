@@ -0,0 +1,210 @@
+//! Warnings for `propel.toml` settings that parse fine but do nothing, that
+//! cost more than they might seem to, or that contradict each other.
+//!
+//! Each rule is a pure function over the parsed config and a few facts
+//! about the project directory ([`ProjectFlags`]); [`check_config`] runs
+//! every rule in [`RULES`]. Adding a case means adding a rule there.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::config::{BuildConfig, PropelConfig};
+
+/// Facts about the project directory that decide whether a setting applies.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProjectFlags {
+    /// `.propel/Dockerfile` exists, so no Dockerfile is generated.
+    pub ejected: bool,
+}
+
+/// What is wrong with a setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningKind {
+    /// The setting is ignored.
+    NoEffect,
+    /// The setting works, but adds to the bill in a non-obvious way.
+    Cost,
+    /// The setting disagrees with another one.
+    Conflict,
+}
+
+/// A setting in `propel.toml` worth a second look.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigWarning {
+    /// Dotted key, e.g. `[build].extra_packages`.
+    pub field: &'static str,
+    pub kind: WarningKind,
+    /// Why the setting is inert, what it costs, or what it conflicts with.
+    pub reason: Cow<'static, str>,
+}
+
+impl fmt::Display for ConfigWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            WarningKind::NoEffect => write!(f, "{} has no effect: {}", self.field, self.reason),
+            WarningKind::Cost => write!(f, "{} adds cost: {}", self.field, self.reason),
+            WarningKind::Conflict => write!(f, "{} conflicts: {}", self.field, self.reason),
+        }
+    }
+}
+
+/// One check over the parsed config.
+pub type ConfigRule = fn(&PropelConfig, &ProjectFlags) -> Vec<ConfigWarning>;
+
+/// Every rule [`check_config`] runs, in output order.
+pub const RULES: &[ConfigRule] = &[
+    ejected_build_fields,
+    always_allocated_min_instances,
+    secrets_without_injection,
+    warmup_via_cloud_build,
+    build_env_differs_from_env,
+];
+
+/// Run every rule in [`RULES`].
+pub fn check_config(config: &PropelConfig, flags: &ProjectFlags) -> Vec<ConfigWarning> {
+    RULES.iter().flat_map(|rule| rule(config, flags)).collect()
+}
+
+/// Whether a `[build]` field differs from the default (second argument).
+type IsSet = fn(&BuildConfig, &BuildConfig) -> bool;
+
+/// `[build]` fields that only shape the generated Dockerfile, with a check
+/// for "set to something other than the default".
+///
+/// `git_credentials_secret`, `platform`, and `cache_mounts` are absent:
+/// Cloud Build still applies them to an ejected Dockerfile. `bundle_manifest` is absent
+/// because the manifest is still written into the bundle.
+const DOCKERFILE_ONLY_FIELDS: &[(&str, IsSet)] = &[
+    ("[build].base_image", |b, d| b.base_image != d.base_image),
+    ("[build].runtime_image", |b, d| {
+        b.runtime_image != d.runtime_image
+    }),
+    ("[build].extra_packages", |b, _| {
+        !b.extra_packages.is_empty()
+    }),
+    ("[build].runtime_packages", |b, _| {
+        !b.runtime_packages.is_empty()
+    }),
+    ("[build].cargo_chef_version", |b, d| {
+        b.cargo_chef_version != d.cargo_chef_version
+    }),
+    ("[build].chef_preinstalled", |b, _| b.chef_preinstalled),
+    ("[build].chef_image", |b, _| b.chef_image.is_some()),
+    ("[build].include", |b, _| b.include.is_some()),
+    ("[build.env]", |b, _| !b.env.is_empty()),
+    ("[build].build_env", |b, _| !b.build_env.is_empty()),
+    ("[build].locked", |b, _| b.locked.is_some()),
+    ("[build].offline", |b, _| b.offline),
+    ("[build].features", |b, _| !b.features.is_empty()),
+    ("[build].no_default_features", |b, _| b.no_default_features),
+    ("[build].rustflags", |b, _| b.rustflags.is_some()),
+    ("[build].target", |b, _| b.target.is_some()),
+    ("[build].static_binary", |b, _| b.static_binary),
+    ("[build].run_as_user", |b, _| b.run_as_user.is_some()),
+];
+
+/// An ejected project builds `.propel/Dockerfile` as written, so settings
+/// that only feed the generated Dockerfile are ignored.
+pub fn ejected_build_fields(config: &PropelConfig, flags: &ProjectFlags) -> Vec<ConfigWarning> {
+    if !flags.ejected {
+        return Vec::new();
+    }
+    let defaults = BuildConfig::default();
+    DOCKERFILE_ONLY_FIELDS
+        .iter()
+        .filter(|(_, is_set)| is_set(&config.build, &defaults))
+        .map(|(field, _)| ConfigWarning {
+            field,
+            kind: WarningKind::NoEffect,
+            reason: "project is ejected (.propel/Dockerfile is used as written)".into(),
+        })
+        .collect()
+}
+
+/// Minimum instances with always-allocated CPU are billed at the full
+/// (active) rate around the clock, instead of the reduced idle rate.
+pub fn always_allocated_min_instances(
+    config: &PropelConfig,
+    _flags: &ProjectFlags,
+) -> Vec<ConfigWarning> {
+    let cloud_run = &config.cloud_run;
+    if !cloud_run.cpu_always_allocated || cloud_run.min_instances == 0 {
+        return Vec::new();
+    }
+    vec![ConfigWarning {
+        field: "[cloud_run].cpu_always_allocated",
+        kind: WarningKind::Cost,
+        reason: "with min_instances > 0, idle instances are billed at the full CPU and \
+                 memory rate around the clock, not the idle rate"
+            .into(),
+    }]
+}
+
+/// `[cloud_run].secrets` picks among discovered secrets, and
+/// `check_secret_versions` checks the injected ones; nothing is discovered
+/// with `inject_secrets = false`.
+pub fn secrets_without_injection(
+    config: &PropelConfig,
+    _flags: &ProjectFlags,
+) -> Vec<ConfigWarning> {
+    let cloud_run = &config.cloud_run;
+    if cloud_run.inject_secrets {
+        return Vec::new();
+    }
+    [
+        ("[cloud_run].secrets", cloud_run.secrets.is_some()),
+        (
+            "[cloud_run].check_secret_versions",
+            cloud_run.check_secret_versions,
+        ),
+    ]
+    .into_iter()
+    .filter(|(_, is_set)| *is_set)
+    .map(|(field, _)| ConfigWarning {
+        field,
+        kind: WarningKind::NoEffect,
+        reason: "[cloud_run].inject_secrets = false skips secret injection".into(),
+    })
+    .collect()
+}
+
+/// Warm-up runs in `propel deploy`; a deploy step inside Cloud Build
+/// shifts traffic itself.
+pub fn warmup_via_cloud_build(config: &PropelConfig, _flags: &ProjectFlags) -> Vec<ConfigWarning> {
+    if config.cloud_run.warmup_requests == 0 || !config.ci.deploy_via_cloud_build {
+        return Vec::new();
+    }
+    vec![ConfigWarning {
+        field: "[cloud_run].warmup_requests",
+        kind: WarningKind::NoEffect,
+        reason: "[ci].deploy_via_cloud_build = true deploys from Cloud Build without warm-up"
+            .into(),
+    }]
+}
+
+/// A key in both `[build].build_env` and `[build.env]` with different
+/// values: the build compiles with one and the service runs with the other.
+pub fn build_env_differs_from_env(
+    config: &PropelConfig,
+    _flags: &ProjectFlags,
+) -> Vec<ConfigWarning> {
+    let build = &config.build;
+    let mut keys: Vec<&String> = build
+        .build_env
+        .iter()
+        .filter(|(key, value)| build.env.get(*key).is_some_and(|env| env != *value))
+        .map(|(key, _)| key)
+        .collect();
+    keys.sort();
+    keys.into_iter()
+        .map(|key| ConfigWarning {
+            field: "[build].build_env",
+            kind: WarningKind::Conflict,
+            reason: format!(
+                "{key} = {:?} at build time, but [build.env] sets {key} = {:?} at runtime",
+                build.build_env[key], build.env[key]
+            )
+            .into(),
+        })
+        .collect()
+}
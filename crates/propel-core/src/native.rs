@@ -0,0 +1,317 @@
+//! Detection of `-sys` crates that link against system libraries.
+//!
+//! A dependency such as `openssl-sys` compiles fine locally (the developer
+//! has `libssl-dev` installed) and then fails inside Cloud Build because the
+//! builder image lacks the headers. This module walks the resolved
+//! dependency graph from `cargo metadata` and maps known native crates to
+//! the Debian packages they need, so deploy can suggest the exact
+//! `[build]` entries before submitting a doomed build.
+
+use std::collections::{HashSet, VecDeque};
+use std::path::Path;
+
+use cargo_metadata::{DependencyKind, Metadata, MetadataCommand, Package};
+
+use crate::BuildConfig;
+
+/// A crate that links against a system library.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NativeDependency {
+    /// Crate name as it appears in the dependency graph.
+    pub crate_name: &'static str,
+    /// Debian packages needed in the builder stages (`[build].extra_packages`).
+    pub build_packages: &'static [&'static str],
+    /// Debian packages providing the shared library at runtime
+    /// (`[build].runtime_packages`).
+    pub runtime_packages: &'static [&'static str],
+    /// Features that compile the library from source, making the
+    /// system packages unnecessary.
+    pub bundled_features: &'static [&'static str],
+}
+
+/// Known native crates and the packages they need.
+pub const KNOWN_NATIVE_DEPENDENCIES: &[NativeDependency] = &[
+    NativeDependency {
+        crate_name: "openssl-sys",
+        build_packages: &["libssl-dev", "pkg-config"],
+        runtime_packages: &["libssl3", "ca-certificates"],
+        bundled_features: &["vendored"],
+    },
+    NativeDependency {
+        crate_name: "pq-sys",
+        build_packages: &["libpq-dev"],
+        runtime_packages: &["libpq5"],
+        bundled_features: &["bundled"],
+    },
+    NativeDependency {
+        crate_name: "libsqlite3-sys",
+        build_packages: &["libsqlite3-dev"],
+        runtime_packages: &["libsqlite3-0"],
+        bundled_features: &["bundled", "bundled-sqlcipher"],
+    },
+    NativeDependency {
+        crate_name: "mysqlclient-sys",
+        build_packages: &["default-libmysqlclient-dev", "pkg-config"],
+        runtime_packages: &["libmariadb3"],
+        bundled_features: &["bundled"],
+    },
+    NativeDependency {
+        crate_name: "protobuf-src",
+        build_packages: &["cmake"],
+        runtime_packages: &[],
+        bundled_features: &[],
+    },
+];
+
+/// Run `cargo metadata` with dependency resolution for the project.
+///
+/// Unlike [`crate::CargoProject::discover`] this resolves the full
+/// dependency graph, so it reads `Cargo.lock` (and may consult the
+/// registry index when the lockfile is missing or stale).
+///
+/// # Errors
+///
+/// Returns [`crate::Error::CargoMetadata`] if `cargo metadata` fails.
+pub fn resolve_metadata(project_dir: &Path) -> crate::Result<Metadata> {
+    let manifest_path = project_dir.join("Cargo.toml");
+    tracing::debug!(path = %manifest_path.display(), "running cargo metadata with deps");
+    MetadataCommand::new()
+        .manifest_path(&manifest_path)
+        .exec()
+        .map_err(|e| crate::Error::CargoMetadata {
+            manifest_path,
+            detail: e.to_string(),
+        })
+}
+
+/// Find known native crates reachable from the package at `manifest_path`.
+///
+/// Only normal and build dependencies are followed. Crates built with one of their
+/// [`bundled_features`](NativeDependency::bundled_features) are skipped.
+/// Results follow the order of [`KNOWN_NATIVE_DEPENDENCIES`].
+pub fn detect_native_dependencies(
+    metadata: &Metadata,
+    manifest_path: &Path,
+) -> Vec<&'static NativeDependency> {
+    let found: Vec<&NativeDependency> = reachable_packages(metadata, manifest_path)
+        .into_iter()
+        .filter_map(|(pkg, features)| {
+            KNOWN_NATIVE_DEPENDENCIES
+                .iter()
+                .find(|n| n.crate_name == pkg.name.as_str())
+                .filter(|n| {
+                    !n.bundled_features
+                        .iter()
+                        .any(|f| features.iter().any(|nf| nf.as_str() == *f))
+                })
+        })
+        .collect();
+
+    KNOWN_NATIVE_DEPENDENCIES
+        .iter()
+        .filter(|n| found.contains(n))
+        .collect()
+}
+
+/// Runtime image suggested when runtime packages are needed but the
+/// configured image is distroless (no shell, no `apt-get`).
+pub const DEBIAN_RUNTIME_IMAGE: &str = "debian:bookworm-slim";
+
+/// Shared libraries already present in `gcr.io/distroless/cc-debian12`.
+const DISTROLESS_PROVIDED: &[&str] = &["libssl3", "ca-certificates"];
+
+/// Packages reachable from the package at `manifest_path` through normal
+/// and build dependencies, paired with their resolved features.
+///
+/// Dev-dependencies are not followed — they never reach the release build.
+/// The root package itself is included.
+pub(crate) fn reachable_packages<'a>(
+    metadata: &'a Metadata,
+    manifest_path: &Path,
+) -> Vec<(&'a Package, &'a [String])> {
+    let Some(resolve) = &metadata.resolve else {
+        return Vec::new();
+    };
+    let Some(root) = metadata
+        .packages
+        .iter()
+        .find(|p| p.manifest_path.as_std_path() == manifest_path)
+    else {
+        return Vec::new();
+    };
+
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::from([&root.id]);
+    let mut reachable = Vec::new();
+
+    while let Some(id) = queue.pop_front() {
+        if !seen.insert(id) {
+            continue;
+        }
+        let Some(node) = resolve.nodes.iter().find(|n| &n.id == id) else {
+            continue;
+        };
+        if let Some(pkg) = metadata.packages.iter().find(|p| &p.id == id) {
+            reachable.push((pkg, node.features.as_slice()));
+        }
+        for dep in &node.deps {
+            if dep
+                .dep_kinds
+                .iter()
+                .any(|k| k.kind != DependencyKind::Development)
+            {
+                queue.push_back(&dep.pkg);
+            }
+        }
+    }
+    reachable
+}
+
+/// System packages the build configuration is missing for detected native crates.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackageSuggestion {
+    /// Crates that triggered the suggestion.
+    pub crates: Vec<&'static str>,
+    /// Packages to add to `[build].extra_packages`.
+    pub build: Vec<String>,
+    /// Packages to add to `[build].runtime_packages`.
+    pub runtime: Vec<String>,
+    /// Replacement for a distroless `[build].runtime_image`, set when
+    /// runtime packages have to be installed.
+    pub runtime_image: Option<&'static str>,
+}
+
+impl PackageSuggestion {
+    /// Compute what `config` is missing for the `detected` native crates.
+    ///
+    /// On a distroless runtime image, libraries the image already ships are
+    /// not suggested — unless another crate forces a switch to
+    /// [`DEBIAN_RUNTIME_IMAGE`], which ships none of them.
+    pub fn for_config(detected: &[&'static NativeDependency], config: &BuildConfig) -> Self {
+        let distroless = config.runtime_image.contains("distroless");
+        let needs_switch = distroless
+            && detected
+                .iter()
+                .flat_map(|n| n.runtime_packages)
+                .any(|pkg| !DISTROLESS_PROVIDED.contains(pkg));
+        let provided: &[&str] = if distroless && !needs_switch {
+            DISTROLESS_PROVIDED
+        } else {
+            &[]
+        };
+
+        let mut suggestion = Self {
+            runtime_image: needs_switch.then_some(DEBIAN_RUNTIME_IMAGE),
+            ..Self::default()
+        };
+        for native in detected {
+            let mut triggered = false;
+            for pkg in native.build_packages {
+                if !config.extra_packages.iter().any(|p| p == pkg)
+                    && !suggestion.build.iter().any(|p| p == pkg)
+                {
+                    suggestion.build.push((*pkg).to_owned());
+                    triggered = true;
+                }
+            }
+            for pkg in native.runtime_packages {
+                if !provided.contains(pkg)
+                    && !config.runtime_packages.iter().any(|p| p == pkg)
+                    && !suggestion.runtime.iter().any(|p| p == pkg)
+                {
+                    suggestion.runtime.push((*pkg).to_owned());
+                    triggered = true;
+                }
+            }
+            if triggered {
+                suggestion.crates.push(native.crate_name);
+            }
+        }
+        suggestion
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.build.is_empty() && self.runtime.is_empty() && self.runtime_image.is_none()
+    }
+
+    /// Render the `[build]` TOML with the suggested packages merged into
+    /// the existing lists, ready to paste into `propel.toml`.
+    pub fn toml_snippet(&self, config: &BuildConfig) -> String {
+        let mut out = String::from("[build]\n");
+        if let Some(image) = self.runtime_image {
+            out.push_str(&format!("runtime_image = \"{image}\"\n"));
+        }
+        if !self.build.is_empty() {
+            let merged = merge(&config.extra_packages, &self.build);
+            out.push_str(&format!(
+                "extra_packages = {}\n",
+                toml_string_array(&merged)
+            ));
+        }
+        if !self.runtime.is_empty() {
+            let merged = merge(&config.runtime_packages, &self.runtime);
+            out.push_str(&format!(
+                "runtime_packages = {}\n",
+                toml_string_array(&merged)
+            ));
+        }
+        out
+    }
+
+    /// Merge the suggestion into `build` in memory, as
+    /// [`apply_to_config`](Self::apply_to_config) would on disk.
+    pub fn apply_to(&self, build: &mut BuildConfig) {
+        if let Some(image) = self.runtime_image {
+            build.runtime_image = image.to_owned();
+        }
+        build.extra_packages.extend(self.build.iter().cloned());
+        build.runtime_packages.extend(self.runtime.iter().cloned());
+    }
+
+    /// Write the suggestion into `propel.toml` under `project_dir`,
+    /// preserving existing formatting and comments.
+    ///
+    /// Creates the file (and the `[build]` table) when missing.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`crate::Error::ConfigLoad`], [`crate::Error::ConfigEdit`],
+    /// or [`crate::Error::ConfigWrite`] if the file cannot be read, parsed,
+    /// or written back.
+    pub fn apply_to_config(&self, project_dir: &Path) -> crate::Result<()> {
+        crate::config::edit_config(project_dir, "build", |build| {
+            if let Some(image) = self.runtime_image {
+                build.insert("runtime_image", toml_edit::value(image));
+            }
+            append_packages(build, "extra_packages", &self.build);
+            append_packages(build, "runtime_packages", &self.runtime);
+        })
+    }
+}
+
+fn append_packages(table: &mut dyn toml_edit::TableLike, key: &str, packages: &[String]) {
+    if packages.is_empty() {
+        return;
+    }
+    let item = table
+        .entry(key)
+        .or_insert_with(|| toml_edit::value(toml_edit::Array::new()));
+    if let Some(array) = item.as_array_mut() {
+        for pkg in packages {
+            array.push(pkg.as_str());
+        }
+    } else {
+        let mut array = toml_edit::Array::new();
+        array.extend(packages.iter().map(String::as_str));
+        *item = toml_edit::value(array);
+    }
+}
+
+fn merge<'a>(existing: &'a [String], added: &'a [String]) -> Vec<&'a str> {
+    existing.iter().chain(added).map(String::as_str).collect()
+}
+
+fn toml_string_array(items: &[&str]) -> String {
+    let quoted: Vec<String> = items.iter().map(|s| format!("\"{s}\"")).collect();
+    format!("[{}]", quoted.join(", "))
+}
@@ -0,0 +1,69 @@
+//! Detection of dependencies Cloud Build cannot fetch anonymously.
+//!
+//! Cloud Build runs with no SSH keys or registry credentials, so a
+//! `git = "ssh://…"` dependency or a crate from an alternate registry
+//! fails deep inside `cargo chef cook`. Detecting these from the resolved
+//! graph lets deploy fail fast with a pointer to
+//! [`BuildConfig::git_credentials_secret`](crate::BuildConfig::git_credentials_secret).
+
+use std::path::Path;
+
+use cargo_metadata::Metadata;
+
+/// A dependency whose source needs credentials to fetch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrivateDependency {
+    /// Package name.
+    pub name: String,
+    /// Source as reported by `cargo metadata` (e.g. `git+ssh://…`).
+    pub source: String,
+}
+
+impl std::fmt::Display for PrivateDependency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.name, self.source)
+    }
+}
+
+/// Find dependencies fetched over SSH or from a registry other than crates.io.
+///
+/// `git+https://` sources are not reported: public repositories fetch fine
+/// without credentials, and a private one cannot be told apart from
+/// metadata alone. Path dependencies are bundled and never need fetching.
+pub fn detect_private_dependencies(
+    metadata: &Metadata,
+    manifest_path: &Path,
+) -> Vec<PrivateDependency> {
+    let mut found: Vec<PrivateDependency> =
+        crate::native::reachable_packages(metadata, manifest_path)
+            .into_iter()
+            .filter_map(|(pkg, _)| {
+                let source = pkg.source.as_ref()?;
+                is_private_source(&source.repr).then(|| PrivateDependency {
+                    name: pkg.name.to_string(),
+                    source: source.repr.clone(),
+                })
+            })
+            .collect();
+    found.sort_by(|a, b| a.name.cmp(&b.name));
+    found
+}
+
+/// Whether a `cargo metadata` source string needs credentials to fetch.
+pub fn is_private_source(source: &str) -> bool {
+    if source.starts_with("git+ssh://") {
+        return true;
+    }
+    if let Some(index) = source
+        .strip_prefix("registry+")
+        .or_else(|| source.strip_prefix("sparse+"))
+    {
+        return !CRATES_IO_INDEXES.contains(&index.trim_end_matches('/'));
+    }
+    false
+}
+
+const CRATES_IO_INDEXES: &[&str] = &[
+    "https://github.com/rust-lang/crates.io-index",
+    "https://index.crates.io",
+];
@@ -0,0 +1,137 @@
+//! Heuristic scan for directories a service reads at runtime.
+//!
+//! With `[build].include` set, only the listed paths reach the runtime
+//! image. A directory the code reads but the list leaves out still
+//! deploys — and then fails on the first request that touches it, e.g. a
+//! `templates/` directory added after `include = ["migrations/"]`.
+//!
+//! The scan looks for string literals passed to common file-access calls
+//! (`fs::read*`, `File::open`, `ServeDir::new`, `ServeFile::new`) and for
+//! `[build.env]` values that look like paths. Paths assembled at runtime
+//! are missed, and `include_str!`/`include_bytes!` are compiled into the
+//! binary, so they never count.
+
+use std::collections::HashMap;
+
+/// Runtime working directory of the generated Dockerfile, where the
+/// included paths are copied.
+const RUNTIME_WORKDIR: &str = "/app/";
+
+/// Calls whose first argument, when a string literal, is a path read at
+/// runtime. `fs::read` also matches `fs::read_to_string` and
+/// `fs::read_dir`, from `std` or `tokio`.
+const ACCESS_CALLS: &[&str] = &["fs::read", "File::open", "ServeDir::new", "ServeFile::new"];
+
+/// A path literal passed to a runtime file-access call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathReference {
+    /// The literal as written, e.g. `templates/index.html`.
+    pub path: String,
+    /// 1-based line of the literal.
+    pub line: usize,
+}
+
+/// Path literals passed to runtime file-access calls in `source`.
+///
+/// Calls inside `//` comments are skipped, as are macros such as
+/// `include_str!` that embed the file at compile time.
+pub fn scan_source(source: &str) -> Vec<PathReference> {
+    let mut found = Vec::new();
+    for call in ACCESS_CALLS {
+        for (start, _) in source.match_indices(call) {
+            if in_line_comment(source, start) || !at_path_boundary(source, start) {
+                continue;
+            }
+            if let Some((offset, path)) = first_string_argument(&source[start + call.len()..]) {
+                let literal_at = start + call.len() + offset;
+                found.push(PathReference {
+                    path: path.to_owned(),
+                    line: source[..literal_at].matches('\n').count() + 1,
+                });
+            }
+        }
+    }
+    found.sort_by_key(|r| r.line);
+    found
+}
+
+/// `[build.env]` entries whose value may name a path in the image,
+/// as `(key, top-level entry)`, sorted by key.
+///
+/// Values such as `info` or `8080` come back too — only the caller knows
+/// which top-level names are directories in the project.
+pub fn env_path_references(env: &HashMap<String, String>) -> Vec<(String, String)> {
+    let mut found: Vec<(String, String)> = env
+        .iter()
+        .filter_map(|(key, value)| Some((key.clone(), top_level_entry(value)?.to_owned())))
+        .collect();
+    found.sort();
+    found
+}
+
+/// First component of `path` relative to the runtime working directory:
+/// `templates` for `templates/index.html`, `./templates/`, or
+/// `/app/templates`.
+///
+/// `None` for paths outside the image's working directory (`/etc/...`,
+/// `../...`), URLs, and unresolved `{{...}}` templates.
+pub fn top_level_entry(path: &str) -> Option<&str> {
+    let path = path.trim();
+    if path.contains("://") || path.contains("{{") {
+        return None;
+    }
+    let relative = match path.strip_prefix(RUNTIME_WORKDIR) {
+        Some(rest) => rest,
+        None if path.starts_with('/') => return None,
+        None => path,
+    };
+    relative
+        .split('/')
+        .find(|component| !component.is_empty() && *component != ".")
+        .filter(|component| *component != "..")
+}
+
+/// Whether `include` copies the top-level directory `dir`.
+///
+/// A listed subdirectory (`templates/email/`) counts: the scan only knows
+/// top-level names, and a partial listing is deliberate more often than not.
+pub fn is_included(dir: &str, include: &[String]) -> bool {
+    include
+        .iter()
+        .filter_map(|entry| top_level_entry(entry))
+        .any(|entry| entry == dir)
+}
+
+fn in_line_comment(source: &str, at: usize) -> bool {
+    let line_start = source[..at].rfind('\n').map_or(0, |i| i + 1);
+    source[line_start..at].contains("//")
+}
+
+/// `fs::read` in `std::fs::read` or `tokio::fs::read`, but not in
+/// `my_fs::read` or `vfs::read`.
+fn at_path_boundary(source: &str, at: usize) -> bool {
+    !source[..at]
+        .chars()
+        .next_back()
+        .is_some_and(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Offset and contents of the string literal opening the argument list
+/// at the start of `rest`, which continues a call's path.
+///
+/// Accepts the rest of a method name (`_to_string`), whitespace, and a
+/// `r"..."` raw string; escapes end the match, since paths rarely have them.
+fn first_string_argument(rest: &str) -> Option<(usize, &str)> {
+    let after_name = rest.trim_start_matches(|c: char| c.is_alphanumeric() || c == '_');
+    let args = after_name.strip_prefix('(')?;
+    let literal = args.trim_start();
+    // arch-lint: allow(no-silent-result-drop) reason="Option: a literal without the raw-string prefix"
+    let literal = literal.strip_prefix('r').unwrap_or(literal);
+    let contents = literal.strip_prefix('"')?;
+    let end = contents.find(['"', '\\'])?;
+    if contents[end..].starts_with('\\') {
+        return None;
+    }
+    let offset = rest.len() - contents.len();
+    Some((offset, &contents[..end]))
+}
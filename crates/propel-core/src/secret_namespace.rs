@@ -0,0 +1,78 @@
+//! Mapping between the secret keys an app sees and Secret Manager names.
+//!
+//! With `[project].secret_prefix = "staging_"`, `propel secret set FOO`
+//! stores `staging_FOO`, deploy mounts it as the env var `FOO`, and
+//! `secret list`/`delete` and `destroy --include-secrets` only see names
+//! carrying the prefix. Every secret operation goes through
+//! [`SecretNamespace`] so the two spellings never drift apart.
+
+/// Secret Manager names reserved to one `[project].secret_prefix`.
+///
+/// The default (empty) namespace maps every key to itself and owns every
+/// secret in the project.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SecretNamespace<'a> {
+    prefix: &'a str,
+}
+
+impl<'a> SecretNamespace<'a> {
+    pub fn new(prefix: &'a str) -> Self {
+        Self { prefix }
+    }
+
+    /// The prefix, empty without one.
+    pub fn prefix(&self) -> &'a str {
+        self.prefix
+    }
+
+    /// Secret Manager name storing `key`.
+    pub fn secret_name(&self, key: &str) -> String {
+        format!("{}{key}", self.prefix)
+    }
+
+    /// Key stored under `secret_name`, or `None` for a secret outside the
+    /// namespace. A name that is just the prefix has no key.
+    pub fn key<'n>(&self, secret_name: &'n str) -> Option<&'n str> {
+        secret_name
+            .strip_prefix(self.prefix)
+            .filter(|key| !key.is_empty())
+    }
+
+    /// Keys of the secrets in `secret_names` that belong to the namespace,
+    /// in their original order.
+    pub fn keys(&self, secret_names: &[String]) -> Vec<String> {
+        secret_names
+            .iter()
+            .filter_map(|name| self.key(name))
+            .map(str::to_owned)
+            .collect()
+    }
+
+    /// Mount `key` as the env var of the same name.
+    pub fn binding(&self, key: &str) -> SecretBinding {
+        SecretBinding {
+            env: key.to_owned(),
+            secret: self.secret_name(key),
+            version: None,
+        }
+    }
+}
+
+/// A Secret Manager secret mounted as an environment variable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretBinding {
+    /// Env var the app reads, also the key pinned in `[cloud_run.secrets]`.
+    pub env: String,
+    /// Secret Manager name.
+    pub secret: String,
+    /// Version pinned by a `[secrets]` entry. `None` takes the version
+    /// from `[cloud_run.secrets]`, `latest` by default.
+    pub version: Option<String>,
+}
+
+/// A secret mounted under its own name, as without a prefix.
+impl From<&str> for SecretBinding {
+    fn from(name: &str) -> Self {
+        SecretNamespace::default().binding(name)
+    }
+}
@@ -0,0 +1,127 @@
+//! Cloud Run service names.
+//!
+//! Cloud Run accepts lowercase letters, digits, and hyphens, starting with
+//! a letter, not ending with a hyphen, at most [`MAX_SERVICE_NAME_LEN`]
+//! characters. Cargo package names are looser (`my_api`, `MyApi`), so a
+//! package name is not always a usable service name.
+
+/// Longest service name Cloud Run accepts.
+pub const MAX_SERVICE_NAME_LEN: usize = 49;
+
+/// Check `name` against Cloud Run's service name rules.
+pub fn validate_service_name(name: &str) -> crate::Result<()> {
+    let reason = if name.is_empty() {
+        Some("must not be empty")
+    } else if name.len() > MAX_SERVICE_NAME_LEN {
+        Some("must be at most 49 characters")
+    } else if !name.starts_with(|c: char| c.is_ascii_lowercase()) {
+        Some("must start with a lowercase letter")
+    } else if name.ends_with('-') {
+        Some("must not end with a hyphen")
+    } else if !name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    {
+        Some("may only contain lowercase letters, digits, and hyphens")
+    } else {
+        None
+    };
+    match reason {
+        Some(reason) => Err(crate::Error::InvalidServiceName {
+            name: name.to_owned(),
+            reason,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Derive a Cloud Run service name from a package (or any) name.
+///
+/// Lowercases, turns `_`, `.`, and any other disallowed character into a
+/// hyphen, collapses repeated hyphens, drops leading non-letters and
+/// trailing hyphens, and truncates to [`MAX_SERVICE_NAME_LEN`]. A valid
+/// service name without repeated hyphens maps to itself. Returns `None`
+/// when `name` has no ASCII letter to start with.
+pub fn derive_service_name(name: &str) -> Option<String> {
+    let mut derived = String::with_capacity(name.len());
+    for c in name.chars() {
+        let c = c.to_ascii_lowercase();
+        if c.is_ascii_lowercase() || (c.is_ascii_digit() && !derived.is_empty()) {
+            derived.push(c);
+        } else if !derived.is_empty() && !derived.ends_with('-') {
+            derived.push('-');
+        }
+    }
+    derived.truncate(MAX_SERVICE_NAME_LEN);
+    let derived = derived.trim_end_matches('-');
+    (!derived.is_empty()).then(|| derived.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derive_examples() {
+        let cases = [
+            ("my-api", Some("my-api")),
+            ("my_api", Some("my-api")),
+            ("My.Service", Some("my-service")),
+            ("api__v2", Some("api-v2")),
+            ("_private", Some("private")),
+            ("2fa-service", Some("fa-service")),
+            ("trailing_", Some("trailing")),
+            ("___", None),
+            ("123", None),
+        ];
+        for (name, expected) in cases {
+            assert_eq!(derive_service_name(name).as_deref(), expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn derive_truncates_without_trailing_hyphen() {
+        let name = format!("{}_tail", "a".repeat(48));
+        assert_eq!(derive_service_name(&name).unwrap(), "a".repeat(48));
+    }
+
+    #[test]
+    fn validate_rejects_cloud_run_invalid_names() {
+        for name in ["", "my_api", "My-api", "1api", "api-", &"a".repeat(50)] {
+            assert!(validate_service_name(name).is_err(), "{name:?}");
+        }
+        assert!(validate_service_name("my-api-2").is_ok());
+    }
+
+    mod proptests {
+        use super::*;
+        use proptest::prelude::*;
+
+        proptest! {
+            #[test]
+            fn derived_names_are_valid(name in "\\PC{0,80}") {
+                if let Some(derived) = derive_service_name(&name) {
+                    prop_assert!(validate_service_name(&derived).is_ok(), "{derived:?}");
+                }
+            }
+
+            #[test]
+            fn derivation_is_idempotent(name in "\\PC{0,80}") {
+                if let Some(derived) = derive_service_name(&name) {
+                    prop_assert_eq!(derive_service_name(&derived), Some(derived));
+                }
+            }
+
+            #[test]
+            fn crate_names_with_a_letter_always_derive(name in "[A-Za-z][A-Za-z0-9_-]{0,63}") {
+                prop_assert!(derive_service_name(&name).is_some());
+            }
+
+            #[test]
+            fn valid_service_names_map_to_themselves(name in "[a-z]([a-z0-9-]{0,47}[a-z0-9])?") {
+                prop_assume!(!name.contains("--"));
+                prop_assert_eq!(derive_service_name(&name), Some(name));
+            }
+        }
+    }
+}
@@ -56,6 +56,8 @@ fn discover_single_package() {
     assert_eq!(project.binaries[0].name, "my-api");
     assert!(project.manifest_path.ends_with("Cargo.toml"));
     assert_eq!(project.package_dir, project.workspace_root);
+    assert_eq!(project.member_path(), None);
+    assert_eq!(project.bundle_root(), project.package_dir);
 }
 
 #[test]
@@ -107,6 +109,38 @@ edition.workspace = true
     assert_eq!(project.name, "api");
 }
 
+#[test]
+fn rust_version_follows_workspace_inheritance() {
+    let tmp = TempDir::new().unwrap();
+    std::fs::write(
+        tmp.path().join("Cargo.toml"),
+        r#"[workspace]
+members = ["api", "worker"]
+
+[workspace.package]
+rust-version = "1.83"
+"#,
+    )
+    .unwrap();
+    for (member, rust_version) in [("api", "rust-version.workspace = true"), ("worker", "")] {
+        let dir = tmp.path().join(member);
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(
+            dir.join("Cargo.toml"),
+            format!(
+                "[package]\nname = \"{member}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n{rust_version}\n"
+            ),
+        )
+        .unwrap();
+        std::fs::write(dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+    }
+
+    let api = CargoProject::discover(&tmp.path().join("api")).unwrap();
+    assert_eq!(api.rust_version().unwrap().as_deref(), Some("1.83.0"));
+    let worker = CargoProject::discover(&tmp.path().join("worker")).unwrap();
+    assert_eq!(worker.rust_version().unwrap(), None);
+}
+
 #[test]
 fn discover_multiple_binaries_with_default_run() {
     let tmp = TempDir::new().unwrap();
@@ -240,6 +274,9 @@ edition = "2021"
         project.workspace_root.canonicalize().unwrap(),
         tmp.path().canonicalize().unwrap()
     );
+    // and the bundle is built from it
+    assert_eq!(project.member_path(), Some(std::path::Path::new("api")));
+    assert_eq!(project.bundle_root(), project.workspace_root);
 }
 
 // ── Error cases ──
@@ -1,4 +1,7 @@
-use propel_core::PropelConfig;
+use propel_core::{
+    BuildConfig, CiConfig, CiResources, Platform, ProbeConfig, ProbeKind, PropelConfig, RunAsUser,
+    SecretVersions, TagStrategy,
+};
 use tempfile::TempDir;
 
 #[test]
@@ -18,6 +21,9 @@ fn load_returns_defaults_when_no_config_file() {
     assert_eq!(config.cloud_run.max_instances, 10);
     assert_eq!(config.cloud_run.concurrency, 80);
     assert_eq!(config.cloud_run.port, 8080);
+    assert!(!config.cloud_run.cpu_always_allocated);
+    assert!(config.cloud_run.inject_secrets);
+    assert!(config.cloud_run.allow_unauthenticated);
 }
 
 #[test]
@@ -41,6 +47,10 @@ cpu = 2
 min_instances = 1
 max_instances = 50
 concurrency = 200
+cpu_always_allocated = true
+inject_secrets = false
+allow_unauthenticated = false
+warmup_requests = 5
 port = 3000
 "#;
     std::fs::write(tmp.path().join("propel.toml"), toml).unwrap();
@@ -65,6 +75,10 @@ port = 3000
     assert_eq!(config.cloud_run.min_instances, 1);
     assert_eq!(config.cloud_run.max_instances, 50);
     assert_eq!(config.cloud_run.concurrency, 200);
+    assert!(config.cloud_run.cpu_always_allocated);
+    assert!(!config.cloud_run.inject_secrets);
+    assert!(!config.cloud_run.allow_unauthenticated);
+    assert_eq!(config.cloud_run.warmup_requests, 5);
     assert_eq!(config.cloud_run.port, 3000);
 }
 
@@ -136,6 +150,15 @@ include = ["migrations/", "templates/"]
     assert_eq!(include, vec!["migrations/", "templates/"]);
 }
 
+#[test]
+fn load_include_check_defaults_on() {
+    let config = load_toml("[build]\ninclude = [\"migrations/\"]\n").unwrap();
+    assert!(config.build.include_check);
+
+    let config = load_toml("[build]\ninclude_check = false\n").unwrap();
+    assert!(!config.build.include_check);
+}
+
 #[test]
 fn load_build_env() {
     let tmp = TempDir::new().unwrap();
@@ -209,3 +232,1286 @@ include = []
     let include = config.build.include.unwrap();
     assert!(include.is_empty());
 }
+
+// ── cloud_build Tests ──
+
+#[test]
+fn load_defaults_cloud_build_unset() {
+    let tmp = TempDir::new().unwrap();
+    let config = PropelConfig::load(tmp.path()).unwrap();
+
+    assert!(config.cloud_build.region.is_none());
+    assert!(config.cloud_build.logs_bucket.is_none());
+}
+
+#[test]
+fn load_cloud_build_region_and_logs_bucket() {
+    let tmp = TempDir::new().unwrap();
+    let toml = r#"
+[cloud_build]
+region = "europe-west1"
+logs_bucket = "gs://acme-build-logs/propel"
+"#;
+    std::fs::write(tmp.path().join("propel.toml"), toml).unwrap();
+
+    let config = PropelConfig::load(tmp.path()).unwrap();
+
+    assert_eq!(config.cloud_build.region.as_deref(), Some("europe-west1"));
+    assert_eq!(
+        config.cloud_build.logs_bucket.as_deref(),
+        Some("gs://acme-build-logs/propel")
+    );
+}
+
+#[test]
+fn load_cloud_build_rejects_logs_bucket_without_scheme() {
+    let tmp = TempDir::new().unwrap();
+    let toml = r#"
+[cloud_build]
+logs_bucket = "acme-build-logs"
+"#;
+    std::fs::write(tmp.path().join("propel.toml"), toml).unwrap();
+
+    let err = PropelConfig::load(tmp.path()).unwrap_err().to_string();
+    assert!(err.contains("gs://"), "expected gs:// hint, got: {err}");
+}
+
+#[test]
+fn cloud_build_max_bundle_size_defaults_and_overrides() {
+    let config = load_toml("[cloud_build]\n").unwrap();
+    assert_eq!(config.cloud_build.max_bundle_bytes(), 1536 * 1024 * 1024);
+
+    let config = load_toml("[cloud_build]\nmax_bundle_mb = 4096\n").unwrap();
+    assert_eq!(config.cloud_build.max_bundle_bytes(), 4096 * 1024 * 1024);
+}
+
+// ── ci Tests ──
+
+#[test]
+fn load_defaults_ci_deploys_directly() {
+    let tmp = TempDir::new().unwrap();
+    let config = PropelConfig::load(tmp.path()).unwrap();
+
+    assert!(!config.ci.deploy_via_cloud_build);
+}
+
+#[test]
+fn load_ci_deploy_via_cloud_build() {
+    let tmp = TempDir::new().unwrap();
+    let toml = r#"
+[ci]
+deploy_via_cloud_build = true
+"#;
+    std::fs::write(tmp.path().join("propel.toml"), toml).unwrap();
+
+    let config = PropelConfig::load(tmp.path()).unwrap();
+
+    assert!(config.ci.deploy_via_cloud_build);
+}
+
+#[test]
+fn ci_resources_default_from_service_name() {
+    let resources = CiConfig::default().resources("billing-api");
+
+    assert_eq!(
+        resources,
+        CiResources {
+            wif_pool_id: "billing-api-github".to_owned(),
+            wif_provider_id: "github".to_owned(),
+            service_account_id: "billing-api-deploy".to_owned(),
+        }
+    );
+}
+
+#[test]
+fn ci_resources_default_sanitizes_service_name() {
+    let resources = CiResources::defaults("My_Service");
+
+    assert_eq!(resources.wif_pool_id, "my-service-github");
+    assert_eq!(resources.service_account_id, "my-service-deploy");
+}
+
+#[test]
+fn ci_resources_default_fit_gcp_length_limits() {
+    let resources = CiResources::defaults("a-very-long-service-name-that-overflows");
+
+    assert!(resources.wif_pool_id.len() <= 32);
+    assert!(resources.service_account_id.len() <= 30);
+    assert!(resources.wif_pool_id.ends_with("-github"));
+    assert!(resources.service_account_id.ends_with("-deploy"));
+    assert!(!resources.service_account_id.contains("--"));
+}
+
+#[test]
+fn ci_resources_prefer_configured_ids() {
+    let tmp = TempDir::new().unwrap();
+    let toml = r#"
+[ci]
+wif_pool_id = "propel-github"
+service_account_id = "propel-deploy"
+"#;
+    std::fs::write(tmp.path().join("propel.toml"), toml).unwrap();
+
+    let config = PropelConfig::load(tmp.path()).unwrap();
+    let resources = config.ci.resources("svc");
+
+    assert_eq!(resources.wif_pool_id, "propel-github");
+    assert_eq!(resources.wif_provider_id, "github");
+    assert_eq!(resources.service_account_id, "propel-deploy");
+    assert!(!config.ci.resources_pinned());
+}
+
+#[test]
+fn ci_resources_save_round_trip() {
+    let tmp = TempDir::new().unwrap();
+    let toml = r#"# keep me
+[project]
+gcp_project_id = "my-project"
+
+[ci]
+deploy_via_cloud_build = true
+"#;
+    std::fs::write(tmp.path().join("propel.toml"), toml).unwrap();
+
+    let resources = CiResources::defaults("svc");
+    resources.save(tmp.path()).unwrap();
+
+    let written = std::fs::read_to_string(tmp.path().join("propel.toml")).unwrap();
+    assert!(written.starts_with("# keep me\n"));
+
+    let config = PropelConfig::load(tmp.path()).unwrap();
+    assert!(config.ci.deploy_via_cloud_build);
+    assert!(config.ci.resources_pinned());
+    // A renamed service keeps targeting the saved resources.
+    assert_eq!(config.ci.resources("renamed"), resources);
+}
+
+#[test]
+fn ci_resources_save_creates_config() {
+    let tmp = TempDir::new().unwrap();
+
+    CiResources::defaults("svc").save(tmp.path()).unwrap();
+
+    let config = PropelConfig::load(tmp.path()).unwrap();
+    assert_eq!(config.ci.wif_pool_id.as_deref(), Some("svc-github"));
+}
+
+// ── auth Tests ──
+
+#[test]
+fn load_defaults_auth_key_file_activation_off() {
+    let tmp = TempDir::new().unwrap();
+    let config = PropelConfig::load(tmp.path()).unwrap();
+
+    assert!(!config.auth.activate_key_file);
+}
+
+#[test]
+fn load_auth_activate_key_file() {
+    let tmp = TempDir::new().unwrap();
+    std::fs::write(
+        tmp.path().join("propel.toml"),
+        "[auth]\nactivate_key_file = true\n",
+    )
+    .unwrap();
+
+    let config = PropelConfig::load(tmp.path()).unwrap();
+
+    assert!(config.auth.activate_key_file);
+}
+
+// ── cloud_run probe Tests ──
+
+fn load_toml(toml: &str) -> propel_core::Result<PropelConfig> {
+    let tmp = TempDir::new().unwrap();
+    std::fs::write(tmp.path().join("propel.toml"), toml).unwrap();
+    PropelConfig::load(tmp.path())
+}
+
+#[test]
+fn load_reports_unknown_keys_without_failing() {
+    let tmp = TempDir::new().unwrap();
+    std::fs::write(
+        tmp.path().join("propel.toml"),
+        "[project]\nregoin = \"us-central1\"\n\n[build.env]\nFREE_FORM = \"1\"\n\n\
+         [cloud_run.startup_probe]\npath = \"/health\"\nperiodd = 5\n",
+    )
+    .unwrap();
+
+    let (config, unknown) = PropelConfig::load_reporting_unknown_keys(tmp.path()).unwrap();
+
+    assert_eq!(config.build.env["FREE_FORM"], "1");
+    assert_eq!(
+        unknown,
+        ["project.regoin", "cloud_run.startup_probe.periodd"]
+    );
+    assert!(PropelConfig::load(tmp.path()).is_ok());
+}
+
+#[test]
+fn load_defaults_no_probes() {
+    let config = load_toml("").unwrap();
+
+    assert!(config.cloud_run.startup_probe.is_none());
+    assert!(config.cloud_run.liveness_probe.is_none());
+}
+
+#[test]
+fn load_probes_with_defaults_for_omitted_fields() {
+    let config = load_toml(
+        r#"
+[cloud_run.startup_probe]
+path = "/health"
+initial_delay = 10
+failure_threshold = 24
+
+[cloud_run.liveness_probe]
+path = "/live"
+period = 30
+timeout = 5
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        config.cloud_run.startup_probe,
+        Some(ProbeConfig {
+            path: Some("/health".to_owned()),
+            initial_delay: 10,
+            period: 10,
+            failure_threshold: 24,
+            timeout: 1,
+        })
+    );
+    let liveness = config.cloud_run.liveness_probe.unwrap();
+    assert_eq!(liveness.period, 30);
+    assert_eq!(liveness.timeout, 5);
+    assert_eq!(liveness.failure_threshold, 3);
+}
+
+#[test]
+fn startup_probe_without_path_is_tcp() {
+    let config = load_toml("[cloud_run.startup_probe]\ninitial_delay = 30\n").unwrap();
+
+    assert!(config.cloud_run.startup_probe.unwrap().path.is_none());
+}
+
+#[test]
+fn warmup_path_prefers_startup_then_liveness_probe() {
+    let cases = [
+        ("", "/"),
+        ("[cloud_run.startup_probe]\ninitial_delay = 30\n", "/"),
+        ("[cloud_run.liveness_probe]\npath = \"/live\"\n", "/live"),
+        (
+            "[cloud_run.startup_probe]\npath = \"/health\"\n\
+             [cloud_run.liveness_probe]\npath = \"/live\"\n",
+            "/health",
+        ),
+    ];
+    for (toml, expected) in cases {
+        let config = load_toml(toml).unwrap();
+        assert_eq!(config.cloud_run.warmup_path(), expected, "{toml:?}");
+    }
+}
+
+#[test]
+fn warmup_handler_points_the_startup_probe_at_warmup() {
+    let config = load_toml("[cloud_run]\nwarmup_handler = true\n").unwrap();
+    assert_eq!(
+        config.cloud_run.effective_startup_probe(),
+        Some(propel_core::ProbeConfig {
+            path: Some("/warmup".to_owned()),
+            ..propel_core::ProbeConfig::default()
+        })
+    );
+    assert_eq!(config.cloud_run.warmup_path(), "/warmup");
+
+    let config = load_toml(
+        "[cloud_run]\nwarmup_handler = true\n\n\
+         [cloud_run.startup_probe]\nfailure_threshold = 24\n",
+    )
+    .unwrap();
+    let probe = config.cloud_run.effective_startup_probe().unwrap();
+    assert_eq!(probe.path.as_deref(), Some("/warmup"));
+    assert_eq!(probe.failure_threshold, 24);
+}
+
+#[test]
+fn explicit_startup_probe_path_overrides_warmup_handler() {
+    let config = load_toml(
+        "[cloud_run]\nwarmup_handler = true\n\n\
+         [cloud_run.startup_probe]\npath = \"/ready\"\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+        config
+            .cloud_run
+            .effective_startup_probe()
+            .unwrap()
+            .path
+            .as_deref(),
+        Some("/ready")
+    );
+    assert_eq!(config.cloud_run.warmup_path(), "/ready");
+}
+
+#[test]
+fn effective_startup_probe_without_warmup_handler_is_the_table() {
+    assert_eq!(
+        load_toml("").unwrap().cloud_run.effective_startup_probe(),
+        None
+    );
+
+    let config = load_toml("[cloud_run.startup_probe]\ninitial_delay = 30\n").unwrap();
+    assert_eq!(
+        config.cloud_run.effective_startup_probe(),
+        config.cloud_run.startup_probe
+    );
+}
+
+#[test]
+fn liveness_probe_requires_path() {
+    let err = load_toml("[cloud_run.liveness_probe]\nperiod = 10\n")
+        .unwrap_err()
+        .to_string();
+
+    assert!(err.contains("[cloud_run.liveness_probe]"), "got: {err}");
+    assert!(err.contains("path is required"), "got: {err}");
+}
+
+#[test]
+fn probe_validation_rejects_out_of_range_values() {
+    let cases = [
+        ("path = \"health\"", "must start with '/'"),
+        ("initial_delay = 241", "initial_delay must be at most 240"),
+        ("period = 0", "period must be between 1 and 240"),
+        (
+            "failure_threshold = 0",
+            "failure_threshold must be at least 1",
+        ),
+        (
+            "period = 5\ntimeout = 6",
+            "timeout must be between 1 and period (5)",
+        ),
+        ("timeout = 0", "timeout must be between 1 and period"),
+    ];
+    for (body, expected) in cases {
+        let err = load_toml(&format!("[cloud_run.startup_probe]\n{body}\n"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains(expected), "{body}: got {err}");
+    }
+}
+
+#[test]
+fn liveness_probe_allows_longer_intervals_than_startup() {
+    let probe = ProbeConfig {
+        path: Some("/live".to_owned()),
+        initial_delay: 600,
+        period: 3600,
+        ..ProbeConfig::default()
+    };
+
+    assert!(probe.validate(ProbeKind::Liveness).is_ok());
+    assert!(probe.validate(ProbeKind::Startup).is_err());
+}
+
+#[test]
+fn load_cloud_run_extra_flags() {
+    let config =
+        load_toml("[cloud_run]\nextra_flags = [\"--gpu=1\", \"--session-affinity\"]\n").unwrap();
+
+    assert_eq!(
+        config.cloud_run.extra_flags,
+        ["--gpu=1", "--session-affinity"]
+    );
+    assert!(load_toml("").unwrap().cloud_run.extra_flags.is_empty());
+}
+
+#[test]
+fn extra_flags_reject_split_values() {
+    for flag in ["gpu=1", "1", "--gpu 1"] {
+        let err = load_toml(&format!("[cloud_run]\nextra_flags = [{flag:?}]\n")).unwrap_err();
+        assert!(
+            matches!(err, propel_core::Error::InvalidExtraFlag { .. }),
+            "{flag}: {err:?}"
+        );
+    }
+    load_toml("[cloud_run]\nextra_flags = [\"--labels=team=a b\"]\n").unwrap();
+}
+
+#[test]
+fn artifact_location_defaults_to_the_region() {
+    let config = load_toml("[project]\nregion = \"europe-west1\"\n").unwrap();
+    assert_eq!(config.build.artifact_location, None);
+    assert_eq!(config.artifact_location(), "europe-west1");
+
+    let config =
+        load_toml("[project]\nregion = \"europe-west1\"\n\n[build]\nartifact_location = \"us\"\n")
+            .unwrap();
+    assert_eq!(config.artifact_location(), "us");
+    assert_eq!(config.project.region, "europe-west1");
+}
+
+#[test]
+fn artifact_location_accepts_multi_regions_and_regions_only() {
+    for location in [
+        "us",
+        "europe",
+        "asia",
+        "us-central1",
+        "northamerica-northeast1",
+    ] {
+        load_toml(&format!("[build]\nartifact_location = {location:?}\n"))
+            .unwrap_or_else(|e| panic!("{location}: {e}"));
+    }
+    for location in ["US", "eu", "global", "us-east1-b", ""] {
+        let err = load_toml(&format!("[build]\nartifact_location = {location:?}\n")).unwrap_err();
+        assert!(
+            matches!(err, propel_core::Error::InvalidArtifactLocation { .. }),
+            "{location}: {err:?}"
+        );
+    }
+}
+
+#[test]
+fn load_cloud_run_timeout_seconds() {
+    let config = load_toml("[cloud_run]\ntimeout_seconds = 900\n").unwrap();
+
+    assert_eq!(config.cloud_run.timeout_seconds, Some(900));
+    assert_eq!(load_toml("").unwrap().cloud_run.timeout_seconds, None);
+}
+
+#[test]
+fn timeout_seconds_must_be_in_cloud_run_range() {
+    for seconds in [1, 30, 3600] {
+        assert!(
+            load_toml(&format!("[cloud_run]\ntimeout_seconds = {seconds}\n")).is_ok(),
+            "{seconds}"
+        );
+    }
+    for seconds in [0, 3601] {
+        let err = load_toml(&format!("[cloud_run]\ntimeout_seconds = {seconds}\n")).unwrap_err();
+        assert!(
+            matches!(err, propel_core::Error::InvalidRequestTimeout { .. }),
+            "{seconds}: {err:?}"
+        );
+        assert!(err.to_string().contains("between 1 and 3600"), "{err}");
+    }
+}
+
+#[test]
+fn load_cloud_run_service_account() {
+    let config =
+        load_toml("[cloud_run]\nservice_account = \"api@proj.iam.gserviceaccount.com\"\n").unwrap();
+
+    assert_eq!(
+        config.cloud_run.service_account.as_deref(),
+        Some("api@proj.iam.gserviceaccount.com")
+    );
+    assert_eq!(load_toml("").unwrap().cloud_run.service_account, None);
+}
+
+#[test]
+fn service_account_must_be_an_email() {
+    for value in ["api", "@proj.iam.gserviceaccount.com"] {
+        let err = load_toml(&format!("[cloud_run]\nservice_account = \"{value}\"\n")).unwrap_err();
+        assert!(
+            matches!(err, propel_core::Error::InvalidServiceAccount { .. }),
+            "{value}: {err:?}"
+        );
+    }
+}
+
+#[test]
+fn load_cloud_run_vpc_settings() {
+    let config = load_toml(
+        "[cloud_run]\n\
+         vpc_connector = \"projects/proj/locations/us-central1/connectors/db\"\n\
+         vpc_egress = \"all-traffic\"\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+        config.cloud_run.vpc_connector.as_deref(),
+        Some("projects/proj/locations/us-central1/connectors/db")
+    );
+    assert_eq!(
+        config.cloud_run.vpc_egress,
+        Some(propel_core::VpcEgress::AllTraffic)
+    );
+
+    let defaults = load_toml("").unwrap();
+    assert_eq!(defaults.cloud_run.vpc_connector, None);
+    assert_eq!(defaults.cloud_run.vpc_egress, None);
+}
+
+#[test]
+fn load_cloud_run_cloudsql_instances() {
+    let config = load_toml(
+        "[cloud_run]\n\
+         cloudsql_instances = [\"proj:us-central1:main\", \"other-proj:europe-west1:replica\"]\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+        config.cloud_run.cloudsql_instances,
+        ["proj:us-central1:main", "other-proj:europe-west1:replica"]
+    );
+    assert!(
+        load_toml("")
+            .unwrap()
+            .cloud_run
+            .cloudsql_instances
+            .is_empty()
+    );
+}
+
+#[test]
+fn cloudsql_instances_must_be_connection_names() {
+    for value in [
+        "main",
+        "proj:main",
+        "proj::main",
+        "proj:us-central1:main:extra",
+    ] {
+        let err = load_toml(&format!(
+            "[cloud_run]\ncloudsql_instances = [\"{value}\"]\n"
+        ))
+        .unwrap_err();
+        assert!(
+            matches!(&err, propel_core::Error::InvalidCloudSqlInstance { value: v } if v == value),
+            "{value}: {err:?}"
+        );
+        assert!(err.to_string().contains("connectionName"), "{err}");
+    }
+}
+
+#[test]
+fn vpc_egress_rejects_unknown_values() {
+    for value in ["all", "private-ranges", "ALL-TRAFFIC"] {
+        let err = load_toml(&format!("[cloud_run]\nvpc_egress = \"{value}\"\n")).unwrap_err();
+        assert!(
+            matches!(err, propel_core::Error::ConfigParse { .. }),
+            "{value}: {err:?}"
+        );
+        assert!(err.to_string().contains("propel.toml"), "{err}");
+    }
+}
+
+#[test]
+fn load_cloud_run_canary() {
+    let config = load_toml("[cloud_run]\ncanary = { percent = 10 }\n").unwrap();
+
+    assert_eq!(
+        config.cloud_run.canary,
+        Some(propel_core::CanaryConfig { percent: 10 })
+    );
+    assert_eq!(load_toml("").unwrap().cloud_run.canary, None);
+}
+
+#[test]
+fn canary_percent_must_split_the_traffic() {
+    for percent in [0, 100] {
+        let err = load_toml(&format!(
+            "[cloud_run]\ncanary = {{ percent = {percent} }}\n"
+        ))
+        .unwrap_err();
+        assert!(
+            matches!(err, propel_core::Error::InvalidCanaryPercent { .. }),
+            "{percent}: {err:?}"
+        );
+    }
+}
+
+#[test]
+fn canary_is_rejected_with_deploy_via_cloud_build() {
+    let err = load_toml(
+        "[cloud_run]\ncanary = { percent = 10 }\n\n[ci]\ndeploy_via_cloud_build = true\n",
+    )
+    .unwrap_err();
+
+    assert!(
+        matches!(err, propel_core::Error::CanaryWithCloudBuildDeploy),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn load_cloud_run_env() {
+    let config =
+        load_toml("[cloud_run.env]\nRUST_LOG = \"info\"\nGIT_SHA = \"{{commit}}\"\n").unwrap();
+
+    assert_eq!(config.cloud_run.env["RUST_LOG"], "info");
+    assert_eq!(config.cloud_run.env["GIT_SHA"], "{{commit}}");
+}
+
+// ── [cloud_run].secrets ──
+
+#[test]
+fn cloud_run_secrets_default_to_none() {
+    let config = load_toml("").unwrap();
+    assert!(config.cloud_run.secrets.is_none());
+    assert_eq!(config.cloud_run.secret_version("API_KEY"), "latest");
+}
+
+#[test]
+fn cloud_run_secrets_list_form_tracks_latest() {
+    let config = load_toml("[cloud_run]\nsecrets = [\"API_KEY\", \"JWT\"]\n").unwrap();
+
+    assert_eq!(
+        config.cloud_run.secrets,
+        Some(SecretVersions::from([
+            ("API_KEY", "latest"),
+            ("JWT", "latest")
+        ]))
+    );
+}
+
+#[test]
+fn cloud_run_secrets_map_form_pins_versions() {
+    let config = load_toml(
+        "[cloud_run.secrets]\nSUPABASE_JWT_SECRET = \"3\"\nAPI_KEY = \"latest\"\nDB_URL = 7\n",
+    )
+    .unwrap();
+
+    let cloud_run = &config.cloud_run;
+    assert_eq!(cloud_run.secret_version("SUPABASE_JWT_SECRET"), "3");
+    assert_eq!(cloud_run.secret_version("API_KEY"), "latest");
+    assert_eq!(cloud_run.secret_version("DB_URL"), "7");
+    let secrets = cloud_run.secrets.as_ref().unwrap();
+    assert_eq!(
+        secrets.names().collect::<Vec<_>>(),
+        ["API_KEY", "DB_URL", "SUPABASE_JWT_SECRET"]
+    );
+    assert!(!secrets.contains("OTHER"));
+}
+
+#[test]
+fn cloud_run_secrets_reject_invalid_versions() {
+    for toml in [
+        "[cloud_run.secrets]\nAPI_KEY = \"v3\"\n",
+        "[cloud_run.secrets]\nAPI_KEY = \"0\"\n",
+        "[cloud_run.secrets]\nAPI_KEY = -1\n",
+        "[cloud_run]\nsecrets = \"API_KEY\"\n",
+    ] {
+        assert!(load_toml(toml).is_err(), "{toml:?} should be rejected");
+    }
+}
+
+// ── expected_account_suffix ──
+
+#[test]
+fn expected_account_suffix_parses() {
+    let config = load_toml("[project]\nexpected_account_suffix = \"@corp.com\"").unwrap();
+    assert_eq!(
+        config.project.expected_account_suffix.as_deref(),
+        Some("@corp.com")
+    );
+}
+
+#[test]
+fn account_matches_without_suffix_accepts_any_account() {
+    let config = load_toml("").unwrap();
+    assert!(config.project.account_matches("someone@gmail.com"));
+}
+
+#[test]
+fn account_matches_checks_suffix() {
+    let config = load_toml("[project]\nexpected_account_suffix = \"@corp.com\"").unwrap();
+    assert!(config.project.account_matches("alice@corp.com"));
+    assert!(config.project.account_matches("  Alice@Corp.COM\n"));
+    assert!(
+        config
+            .project
+            .account_matches("deploy@proj.iam.gserviceaccount.com@corp.com")
+    );
+    assert!(!config.project.account_matches("alice@gmail.com"));
+    assert!(!config.project.account_matches("alice@corp.com.evil.io"));
+    assert!(!config.project.account_matches(""));
+}
+
+#[test]
+fn save_gcp_project_id_keeps_the_rest_of_the_file() {
+    let tmp = TempDir::new().unwrap();
+    let path = tmp.path().join("propel.toml");
+    std::fs::write(
+        &path,
+        "# my service\n[project]\nregion = \"europe-west1\" # closest\n",
+    )
+    .unwrap();
+
+    propel_core::ProjectConfig::save_gcp_project_id(tmp.path(), "my-proj").unwrap();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert!(content.contains("# my service"), "{content}");
+    assert!(
+        content.contains("region = \"europe-west1\" # closest"),
+        "{content}"
+    );
+    let config = PropelConfig::load(tmp.path()).unwrap();
+    assert_eq!(config.project.gcp_project_id.as_deref(), Some("my-proj"));
+    assert_eq!(config.project.region, "europe-west1");
+}
+
+#[test]
+fn save_gcp_project_id_creates_propel_toml() {
+    let tmp = TempDir::new().unwrap();
+
+    propel_core::ProjectConfig::save_gcp_project_id(tmp.path(), "my-proj").unwrap();
+
+    let config = PropelConfig::load(tmp.path()).unwrap();
+    assert_eq!(config.project.gcp_project_id.as_deref(), Some("my-proj"));
+}
+
+#[test]
+fn save_region_replaces_the_region_only() {
+    let tmp = TempDir::new().unwrap();
+    let path = tmp.path().join("propel.toml");
+    std::fs::write(
+        &path,
+        "[project]\n# where it runs\nregion = \"europe-west1\"\n\n[cloud_run]\nmemory = \"1Gi\"\n",
+    )
+    .unwrap();
+
+    propel_core::ProjectConfig::save_region(tmp.path(), "asia-northeast1").unwrap();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert!(content.contains("# where it runs"), "{content}");
+    let config = PropelConfig::load(tmp.path()).unwrap();
+    assert_eq!(config.project.region, "asia-northeast1");
+    assert_eq!(config.cloud_run.memory, "1Gi");
+}
+
+// ── secret_prefix ──
+
+#[test]
+fn secret_prefix_defaults_to_the_whole_project() {
+    let config = load_toml("").unwrap();
+    assert_eq!(config.project.secret_prefix, None);
+    assert_eq!(config.project.secret_namespace().secret_name("FOO"), "FOO");
+}
+
+#[test]
+fn secret_prefix_namespaces_secret_names() {
+    let config = load_toml("[project]\nsecret_prefix = \"staging_\"").unwrap();
+    let namespace = config.project.secret_namespace();
+    assert_eq!(namespace.prefix(), "staging_");
+    assert_eq!(namespace.secret_name("FOO"), "staging_FOO");
+}
+
+#[test]
+fn secret_prefix_rejects_characters_secret_ids_disallow() {
+    for prefix in ["", "staging.", "prod/"] {
+        let err = load_toml(&format!("[project]\nsecret_prefix = {prefix:?}"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("secret_prefix"), "{prefix:?}: {err}");
+    }
+    assert!(load_toml("[project]\nsecret_prefix = \"prod-eu_\"").is_ok());
+}
+
+// ── [build].tag_strategy ──
+
+#[test]
+fn tag_strategy_defaults_to_sha() {
+    assert_eq!(load_toml("").unwrap().build.tag_strategy, TagStrategy::Sha);
+
+    let config = load_toml("[build]\ntag_strategy = \"version\"\n").unwrap();
+    assert_eq!(config.build.tag_strategy, TagStrategy::Version);
+    assert!(load_toml("[build]\ntag_strategy = \"digest\"\n").is_err());
+}
+
+#[test]
+fn tag_strategy_picks_the_tag() {
+    assert_eq!(TagStrategy::Sha.tag("0123abc", "1.2.0"), "0123abc");
+    assert_eq!(
+        TagStrategy::Sha.tag("0123abc-dirty", "1.2.0"),
+        "0123abc-dirty"
+    );
+    assert_eq!(TagStrategy::Latest.tag("0123abc", "1.2.0"), "latest");
+    assert_eq!(TagStrategy::Version.tag("0123abc", "1.2.0"), "1.2.0");
+}
+
+#[test]
+fn version_tags_are_valid_docker_tags() {
+    assert_eq!(
+        TagStrategy::Version.tag("", "1.0.0-rc.1+build.5"),
+        "1.0.0-rc.1-build.5"
+    );
+    let long = "1".repeat(200);
+    assert_eq!(
+        TagStrategy::Version.tag("", &long).len(),
+        TagStrategy::MAX_TAG_LEN
+    );
+}
+
+// ── [build].platform ──
+
+#[test]
+fn build_platform_defaults_to_none() {
+    assert_eq!(load_toml("").unwrap().build.platform, None);
+}
+
+#[test]
+fn build_platform_parses_docker_platform_strings() {
+    let config = load_toml("[build]\nplatform = \"linux/arm64\"\n").unwrap();
+    assert_eq!(config.build.platform, Some(Platform::Arm64));
+
+    let config = load_toml("[build]\nplatform = \"linux/amd64\"\n").unwrap();
+    assert_eq!(config.build.platform, Some(Platform::Amd64));
+    assert_eq!(Platform::Arm64.to_string(), "linux/arm64");
+}
+
+#[test]
+fn build_platform_rejects_unknown_platform() {
+    assert!(load_toml("[build]\nplatform = \"linux/riscv64\"\n").is_err());
+}
+
+#[test]
+fn build_platform_rejects_arm64_with_musl_builder() {
+    let err = load_toml(
+        r#"
+[build]
+platform = "linux/arm64"
+base_image = "clux/muslrust:stable"
+"#,
+    )
+    .unwrap_err()
+    .to_string();
+
+    assert!(err.contains("musl/static"), "got: {err}");
+}
+
+#[test]
+fn build_platform_allows_amd64_with_musl_builder() {
+    let config = load_toml(
+        r#"
+[build]
+platform = "linux/amd64"
+base_image = "rust:1.93-alpine"
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(config.build.platform, Some(Platform::Amd64));
+}
+
+// ── [build].chef_preinstalled / chef_image ──
+
+#[test]
+fn chef_options_default_to_installing_on_base_image() {
+    let config = load_toml("[build]\n").unwrap();
+    assert!(!config.build.chef_preinstalled);
+    assert_eq!(config.build.chef_image, None);
+    assert_eq!(config.build.builder_image(), config.build.base_image);
+}
+
+#[test]
+fn chef_image_overrides_builder_image() {
+    let config = load_toml(
+        r#"
+[build]
+chef_image = "registry.example.com/rust-chef:1.93"
+chef_preinstalled = true
+"#,
+    )
+    .unwrap();
+
+    assert!(config.build.chef_preinstalled);
+    assert_eq!(
+        config.build.builder_image(),
+        "registry.example.com/rust-chef:1.93"
+    );
+}
+
+#[test]
+fn chef_preinstalled_rejects_pinned_chef_version() {
+    let err = load_toml(
+        r#"
+[build]
+chef_preinstalled = true
+cargo_chef_version = "0.1.70"
+"#,
+    )
+    .unwrap_err()
+    .to_string();
+
+    assert!(err.contains("cargo_chef_version"), "got: {err}");
+}
+
+// ── [build].locked / offline ──
+
+#[test]
+fn locked_follows_lockfile_unless_set() {
+    let config = load_toml("[build]\n").unwrap();
+    assert_eq!(config.build.locked, None);
+    assert!(!config.build.offline);
+    assert!(config.build.is_locked(true));
+    assert!(!config.build.is_locked(false));
+
+    let config = load_toml("[build]\nlocked = false\n").unwrap();
+    assert!(!config.build.is_locked(true));
+
+    let config = load_toml("[build]\nlocked = true\n").unwrap();
+    assert!(config.build.is_locked(false));
+}
+
+#[test]
+fn offline_requires_vendored_sources() {
+    let config = load_toml("[build]\noffline = true\n").unwrap();
+
+    let err = config.build.validate_offline(false).unwrap_err();
+    assert!(
+        matches!(err, propel_core::Error::ConflictingBuildOptions { .. }),
+        "{err:?}"
+    );
+    assert!(err.to_string().contains("cargo vendor"), "{err}");
+    config.build.validate_offline(true).unwrap();
+}
+
+// ── [build].features / no_default_features / rustflags ──
+
+#[test]
+fn cargo_flags_follow_features_and_lockfile() {
+    let config = load_toml("[build]\n").unwrap();
+    assert!(config.build.features.is_empty());
+    assert!(!config.build.no_default_features);
+    assert_eq!(config.build.rustflags, None);
+    assert_eq!(config.build.cargo_flags(false), "");
+    assert_eq!(config.build.cargo_flags(true), " --locked");
+
+    let config = load_toml(
+        "[build]\nfeatures = [\"cloud\", \"sqlx/postgres\"]\nno_default_features = true\n\
+         rustflags = \"-C target-cpu=x86-64-v3\"\n",
+    )
+    .unwrap();
+    assert_eq!(
+        config.build.cargo_flags(true),
+        " --locked --no-default-features --features cloud,sqlx/postgres"
+    );
+    assert_eq!(
+        config.build.rustflags.as_deref(),
+        Some("-C target-cpu=x86-64-v3")
+    );
+}
+
+#[test]
+fn features_must_be_feature_names() {
+    for feature in ["", "a b", "a,b", "/x", "serde/", "\"quoted\""] {
+        let toml = format!("[build]\nfeatures = [{feature:?}]\n");
+        let err = load_toml(&toml).unwrap_err();
+        assert!(
+            matches!(err, propel_core::Error::InvalidFeature { .. }),
+            "{feature:?}: {err:?}"
+        );
+    }
+}
+
+#[test]
+fn rustflags_conflicts_with_build_env_rustflags() {
+    let err = load_toml(
+        "[build]\nrustflags = \"-C target-cpu=native\"\n\
+         [build.build_env]\nRUSTFLAGS = \"-C opt-level=3\"\n",
+    )
+    .unwrap_err();
+    assert!(
+        matches!(err, propel_core::Error::ConflictingBuildOptions { .. }),
+        "{err:?}"
+    );
+
+    let err = load_toml("[build]\nrustflags = \"-C a\\n-C b\"\n").unwrap_err();
+    assert!(err.to_string().contains("one"), "{err}");
+}
+
+// ── [build].target / static_binary ──
+
+#[test]
+fn static_binary_picks_the_platform_musl_target_and_static_runtime() {
+    let config = load_toml("[build]\n").unwrap();
+    assert_eq!(config.build.rust_target(), None);
+    assert!(!config.build.is_static());
+    assert_eq!(
+        config.build.effective_runtime_image(),
+        "gcr.io/distroless/cc-debian12"
+    );
+
+    let config = load_toml("[build]\nstatic_binary = true\n").unwrap();
+    assert_eq!(
+        config.build.rust_target(),
+        Some("x86_64-unknown-linux-musl")
+    );
+    assert_eq!(
+        config.build.cargo_flags(true),
+        " --locked --target x86_64-unknown-linux-musl"
+    );
+    assert_eq!(
+        config.build.effective_runtime_image(),
+        BuildConfig::STATIC_RUNTIME_IMAGE
+    );
+
+    let config = load_toml(
+        "[build]\nstatic_binary = true\nplatform = \"linux/arm64\"\nruntime_image = \"scratch\"\n",
+    )
+    .unwrap();
+    assert_eq!(
+        config.build.rust_target(),
+        Some("aarch64-unknown-linux-musl")
+    );
+    assert_eq!(config.build.effective_runtime_image(), "scratch");
+}
+
+#[test]
+fn target_is_passed_through_and_only_musl_is_static() {
+    let config = load_toml("[build]\ntarget = \"x86_64-unknown-linux-gnu\"\n").unwrap();
+    assert_eq!(config.build.rust_target(), Some("x86_64-unknown-linux-gnu"));
+    assert!(!config.build.is_static());
+    assert_eq!(
+        config.build.effective_runtime_image(),
+        "gcr.io/distroless/cc-debian12"
+    );
+
+    let config = load_toml("[build]\ntarget = \"x86_64-unknown-linux-musl\"\n").unwrap();
+    assert!(config.build.is_static());
+    assert_eq!(
+        config.build.effective_runtime_image(),
+        BuildConfig::STATIC_RUNTIME_IMAGE
+    );
+}
+
+#[test]
+fn target_must_be_a_triple_and_musl_for_static_binary() {
+    for target in ["", "x86_64 linux", "x86_64;rm"] {
+        let err = load_toml(&format!("[build]\ntarget = {target:?}\n")).unwrap_err();
+        assert!(
+            matches!(err, propel_core::Error::InvalidTarget { .. }),
+            "{target:?}: {err:?}"
+        );
+    }
+
+    let err = load_toml("[build]\nstatic_binary = true\ntarget = \"x86_64-unknown-linux-gnu\"\n")
+        .unwrap_err();
+    assert!(
+        matches!(err, propel_core::Error::ConflictingBuildOptions { .. }),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn run_as_user_accepts_uid_or_name() {
+    let config = load_toml("[build]\nrun_as_user = 65532\n").unwrap();
+    assert_eq!(config.build.run_as_user, Some(RunAsUser::Uid(65532)));
+
+    let config = load_toml("[build]\nrun_as_user = \"nonroot\"\n").unwrap();
+    assert_eq!(
+        config.build.run_as_user,
+        Some(RunAsUser::Name("nonroot".to_owned()))
+    );
+
+    assert!(load_toml("").unwrap().build.run_as_user.is_none());
+}
+
+#[test]
+fn run_as_user_rejects_root_and_out_of_range_uids() {
+    for value in ["0", "\"root\"", "2147483648", "\"Admin User\"", "\"\""] {
+        let err = load_toml(&format!("[build]\nrun_as_user = {value}\n")).unwrap_err();
+        assert!(
+            matches!(err, propel_core::Error::InvalidRunAsUser { .. }),
+            "{value}: {err:?}"
+        );
+    }
+    load_toml("[build]\nrun_as_user = 2147483647\n").unwrap();
+}
+
+#[test]
+fn run_as_user_forms_for_dockerfile() {
+    let uid = RunAsUser::Uid(RunAsUser::DISTROLESS_NONROOT_UID);
+    assert_eq!(uid.user_instruction(), "65532:65532");
+    assert_eq!(uid.chown(), "65532:65532");
+
+    let name = RunAsUser::Name("nonroot".to_owned());
+    assert_eq!(name.user_instruction(), "nonroot");
+    assert_eq!(name.chown(), "nonroot:nonroot");
+}
+
+#[test]
+fn online_build_needs_no_vendored_sources() {
+    let config = load_toml("[build]\n").unwrap();
+    config.build.validate_offline(false).unwrap();
+}
+
+#[test]
+fn build_platform_checks_chef_image_for_musl() {
+    let err = load_toml(
+        r#"
+[build]
+platform = "linux/arm64"
+chef_image = "clux/muslrust:stable"
+"#,
+    )
+    .unwrap_err()
+    .to_string();
+
+    assert!(err.contains("musl/static"), "got: {err}");
+}
+
+// ── config_hash ──
+
+#[test]
+fn config_hash_is_short_hex_and_stable() {
+    let hash = PropelConfig::default().config_hash();
+    assert_eq!(hash.len(), 12);
+    assert!(hash.chars().all(|c| c.is_ascii_hexdigit()));
+    assert_eq!(PropelConfig::default().config_hash(), hash);
+}
+
+#[test]
+fn config_hash_ignores_formatting_order_and_explicit_defaults() {
+    let a = load_toml(
+        r#"
+[cloud_run]
+memory = "1Gi"
+
+[cloud_run.env]
+A = "1"
+B = "2"
+C = "3"
+"#,
+    )
+    .unwrap();
+    let b = load_toml(
+        r#"
+[project]
+region = "us-central1"
+
+[cloud_run]
+cpu = 1
+memory = "1Gi"
+
+[cloud_run.env]
+C = "3"
+A = "1"
+B = "2"
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(a.config_hash(), b.config_hash());
+}
+
+#[test]
+fn config_hash_changes_with_config() {
+    let base = load_toml("[cloud_run]\nmemory = \"1Gi\"\n").unwrap();
+    let changed = load_toml("[cloud_run]\nmemory = \"2Gi\"\n").unwrap();
+    assert_ne!(base.config_hash(), changed.config_hash());
+}
+
+#[test]
+fn config_hash_covers_every_section() {
+    let config = load_toml(
+        r#"
+[build]
+platform = "linux/arm64"
+include = ["migrations/"]
+
+[cloud_run]
+secrets = { JWT = "3" }
+
+[cloud_run.startup_probe]
+path = "/health"
+"#,
+    )
+    .unwrap();
+    assert_ne!(config.config_hash(), PropelConfig::default().config_hash());
+}
+
+// ── propel.toml that is not a readable file ──
+
+#[test]
+fn load_rejects_directory_in_place_of_propel_toml() {
+    let tmp = TempDir::new().unwrap();
+    std::fs::create_dir(tmp.path().join("propel.toml")).unwrap();
+
+    let err = PropelConfig::load(tmp.path()).unwrap_err();
+
+    assert!(
+        matches!(err, propel_core::Error::ConfigIsDirectory { .. }),
+        "{err:?}"
+    );
+    assert!(err.to_string().contains("is a directory"), "{err}");
+}
+
+#[cfg(unix)]
+#[test]
+fn load_reports_unreadable_propel_toml_with_chmod_hint() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp = TempDir::new().unwrap();
+    let path = tmp.path().join("propel.toml");
+    std::fs::write(&path, "[project]\n").unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o000)).unwrap();
+    if std::fs::read(&path).is_ok() {
+        // Running as root: permissions are not enforced.
+        return;
+    }
+
+    let err = PropelConfig::load(tmp.path()).unwrap_err();
+
+    assert!(
+        matches!(err, propel_core::Error::ConfigPermissionDenied { .. }),
+        "{err:?}"
+    );
+    assert!(err.to_string().contains("chmod u+r"), "{err}");
+}
+
+#[test]
+fn load_empty_propel_toml_uses_defaults() {
+    let tmp = TempDir::new().unwrap();
+    std::fs::write(tmp.path().join("propel.toml"), "").unwrap();
+
+    let config = PropelConfig::load(tmp.path()).unwrap();
+
+    assert_eq!(config.config_hash(), PropelConfig::default().config_hash());
+}
+
+#[test]
+fn secrets_map_env_vars_to_pinned_secrets() {
+    let config =
+        load_toml("[secrets]\nDATABASE_URL = \"prod-db-url:3\"\nJWT_SECRET = \"shared-jwt\"\n")
+            .unwrap();
+
+    let db = &config.secrets["DATABASE_URL"];
+    assert_eq!(
+        (db.name.as_str(), db.version.as_str()),
+        ("prod-db-url", "3")
+    );
+    let jwt = &config.secrets["JWT_SECRET"];
+    assert_eq!(
+        (jwt.name.as_str(), jwt.version.as_str()),
+        ("shared-jwt", "latest")
+    );
+    assert!(load_toml("").unwrap().secrets.is_empty());
+}
+
+#[test]
+fn secrets_reject_malformed_references() {
+    for value in ["", "bad name", "db-url:", "db-url:v2", ":3"] {
+        let err = load_toml(&format!("[secrets]\nDATABASE_URL = {value:?}\n")).unwrap_err();
+        assert!(
+            matches!(err, propel_core::Error::ConfigParse { .. }),
+            "{value}: {err:?}"
+        );
+    }
+}
+
+#[test]
+fn secrets_are_rejected_without_injection() {
+    let err =
+        load_toml("[cloud_run]\ninject_secrets = false\n\n[secrets]\nDB = \"db\"\n").unwrap_err();
+
+    assert!(
+        matches!(err, propel_core::Error::SecretsWithoutInjection),
+        "{err:?}"
+    );
+}
@@ -0,0 +1,120 @@
+use propel_core::{DeployEvent, DeployHistory, DeployRecord, HISTORY_DIR};
+use tempfile::TempDir;
+
+fn promoted() -> DeployRecord {
+    DeployRecord {
+        revision: Some("svc-00002".to_owned()),
+        percent: Some(10),
+        ..DeployRecord::new(
+            "2026-03-10T12:00:00Z".to_owned(),
+            DeployEvent::CanaryPromoted,
+            "svc",
+            "us-central1",
+        )
+    }
+}
+
+#[test]
+fn missing_history_is_empty() {
+    let tmp = TempDir::new().unwrap();
+
+    assert!(DeployHistory::new(tmp.path()).records().unwrap().is_empty());
+    assert!(!tmp.path().join(HISTORY_DIR).exists());
+}
+
+#[test]
+fn appended_records_read_back_in_order() {
+    let tmp = TempDir::new().unwrap();
+    let history = DeployHistory::new(tmp.path());
+    let deploy = DeployRecord {
+        image: Some("img:abc123".to_owned()),
+        account: Some("bob@corp.com".to_owned()),
+        project_number: Some("123456789012".to_owned()),
+        ..DeployRecord::new(
+            "2026-03-10T11:00:00Z".to_owned(),
+            DeployEvent::Deploy,
+            "svc",
+            "us-central1",
+        )
+    };
+
+    history.append(&deploy).unwrap();
+    history.append(&promoted()).unwrap();
+
+    assert_eq!(history.records().unwrap(), [deploy, promoted()]);
+}
+
+#[test]
+fn record_is_one_json_line_without_unset_fields() {
+    let tmp = TempDir::new().unwrap();
+    DeployHistory::new(tmp.path()).append(&promoted()).unwrap();
+
+    let content =
+        std::fs::read_to_string(tmp.path().join(HISTORY_DIR).join("deploys.jsonl")).unwrap();
+    assert_eq!(
+        content,
+        "{\"at\":\"2026-03-10T12:00:00Z\",\"event\":\"canary_promoted\",\"service\":\"svc\",\
+         \"region\":\"us-central1\",\"revision\":\"svc-00002\",\"percent\":10}\n"
+    );
+}
+
+#[test]
+fn deploy_record_on_disk_names_the_account_by_email_only() {
+    let tmp = TempDir::new().unwrap();
+    let record = DeployRecord {
+        image: Some("img:abc123".to_owned()),
+        account: Some("bob@corp.com".to_owned()),
+        project_number: Some("123456789012".to_owned()),
+        ..DeployRecord::new(
+            "2026-03-10T11:00:00Z".to_owned(),
+            DeployEvent::Deploy,
+            "svc",
+            "us-central1",
+        )
+    };
+    DeployHistory::new(tmp.path()).append(&record).unwrap();
+
+    let content =
+        std::fs::read_to_string(tmp.path().join(HISTORY_DIR).join("deploys.jsonl")).unwrap();
+    let written: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(&content).unwrap();
+    let keys: Vec<&str> = written.keys().map(String::as_str).collect();
+    assert_eq!(
+        keys,
+        [
+            "account",
+            "at",
+            "event",
+            "image",
+            "project_number",
+            "region",
+            "service"
+        ]
+    );
+    assert_eq!(written["account"], "bob@corp.com");
+    assert_eq!(written["project_number"], "123456789012");
+}
+
+#[test]
+fn history_dir_ignores_itself() {
+    let tmp = TempDir::new().unwrap();
+    DeployHistory::new(tmp.path()).append(&promoted()).unwrap();
+
+    let gitignore =
+        std::fs::read_to_string(tmp.path().join(HISTORY_DIR).join(".gitignore")).unwrap();
+    assert!(gitignore.lines().any(|line| line == "*"), "{gitignore}");
+}
+
+#[test]
+fn unreadable_lines_are_skipped() {
+    let tmp = TempDir::new().unwrap();
+    let history = DeployHistory::new(tmp.path());
+    history.append(&promoted()).unwrap();
+
+    let file = tmp.path().join(HISTORY_DIR).join("deploys.jsonl");
+    let mut content = std::fs::read_to_string(&file).unwrap();
+    content.push_str("{\"at\":\"2026-03-10T13:00:00Z\",\"event\":\"rolled_sideways\"}\n{\"at\":");
+    std::fs::write(&file, content).unwrap();
+
+    assert_eq!(history.records().unwrap(), [promoted()]);
+}
@@ -0,0 +1,44 @@
+use std::path::Path;
+
+use cargo_metadata::{Metadata, MetadataCommand};
+use propel_core::detect_deprecated_sdk;
+
+fn fixture() -> Metadata {
+    MetadataCommand::parse(include_str!("fixtures/metadata_deprecated_sdk.json")).unwrap()
+}
+
+#[test]
+fn detects_direct_propel_sdk_dependency() {
+    let found = detect_deprecated_sdk(&fixture(), Path::new("/work/apps/legacy-app/Cargo.toml"))
+        .expect("legacy-app depends on propel-sdk");
+
+    assert_eq!(found.requirement, "^0.2");
+}
+
+#[test]
+fn ignores_packages_already_on_propel() {
+    let found = detect_deprecated_sdk(&fixture(), Path::new("/work/apps/modern-app/Cargo.toml"));
+
+    assert_eq!(found, None);
+}
+
+#[test]
+fn migration_snippet_swaps_dependency_and_imports() {
+    let found =
+        detect_deprecated_sdk(&fixture(), Path::new("/work/apps/legacy-app/Cargo.toml")).unwrap();
+    let snippet = found.migration_snippet();
+
+    assert!(snippet.contains("- propel-sdk = \"0.2\""), "{snippet}");
+    assert!(
+        snippet.contains(&format!(
+            "+ propel = \"{}.{}\"",
+            env!("CARGO_PKG_VERSION_MAJOR"),
+            env!("CARGO_PKG_VERSION_MINOR")
+        )),
+        "{snippet}"
+    );
+    assert!(
+        snippet.contains("+ use propel::{PropelAuth, PropelState};"),
+        "{snippet}"
+    );
+}
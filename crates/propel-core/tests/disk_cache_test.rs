@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+use propel_core::{CACHE_DIR, DiskCache, Error};
+use tempfile::TempDir;
+
+const HOUR: Duration = Duration::from_secs(3600);
+
+#[test]
+fn put_then_get_within_ttl() {
+    let tmp = TempDir::new().unwrap();
+    let cache = DiskCache::new(tmp.path());
+
+    cache
+        .put("apis-my-proj", &vec!["run.googleapis.com"], HOUR)
+        .unwrap();
+
+    assert_eq!(
+        cache.get::<Vec<String>>("apis-my-proj").unwrap(),
+        Some(vec!["run.googleapis.com".to_owned()])
+    );
+}
+
+#[test]
+fn missing_key_is_a_miss() {
+    let tmp = TempDir::new().unwrap();
+
+    assert_eq!(
+        DiskCache::new(tmp.path()).get::<u32>("absent").unwrap(),
+        None
+    );
+}
+
+#[test]
+fn expired_entry_is_a_miss() {
+    let tmp = TempDir::new().unwrap();
+    let cache = DiskCache::new(tmp.path());
+
+    cache.put("short", &1u32, Duration::ZERO).unwrap();
+    assert_eq!(cache.get::<u32>("short").unwrap(), None);
+
+    std::fs::write(
+        tmp.path().join(CACHE_DIR).join("old.json"),
+        r#"{"expires_at": 1700000000, "value": 7}"#,
+    )
+    .unwrap();
+    assert_eq!(cache.get::<u32>("old").unwrap(), None);
+}
+
+#[test]
+fn corrupt_or_mistyped_entry_is_a_miss() {
+    let tmp = TempDir::new().unwrap();
+    let cache = DiskCache::new(tmp.path());
+    cache.put("value", &"text", HOUR).unwrap();
+
+    assert_eq!(cache.get::<u32>("value").unwrap(), None);
+
+    std::fs::write(tmp.path().join(CACHE_DIR).join("value.json"), "{\"expires_").unwrap();
+    assert_eq!(cache.get::<String>("value").unwrap(), None);
+
+    cache.put("value", &"again", HOUR).unwrap();
+    assert_eq!(
+        cache.get::<String>("value").unwrap().as_deref(),
+        Some("again")
+    );
+}
+
+#[test]
+fn keys_cannot_leave_the_cache_directory() {
+    let tmp = TempDir::new().unwrap();
+    let cache = DiskCache::new(tmp.path());
+
+    for key in ["../propel", "a/b", "", ".hidden", "/etc/passwd", "a\\b"] {
+        assert!(
+            matches!(
+                cache.put(key, &1u32, HOUR),
+                Err(Error::InvalidCacheKey { .. })
+            ),
+            "{key:?}"
+        );
+        assert!(
+            matches!(cache.get::<u32>(key), Err(Error::InvalidCacheKey { .. })),
+            "{key:?}"
+        );
+    }
+    assert!(!tmp.path().join(".propel").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn cache_directory_symlinked_outside_the_project_is_refused() {
+    let project = TempDir::new().unwrap();
+    let elsewhere = TempDir::new().unwrap();
+    std::fs::create_dir(project.path().join(".propel")).unwrap();
+    std::os::unix::fs::symlink(elsewhere.path(), project.path().join(CACHE_DIR)).unwrap();
+
+    let err = DiskCache::new(project.path())
+        .put("key", &1u32, HOUR)
+        .unwrap_err();
+
+    assert!(matches!(err, Error::CacheOutsideProject { .. }), "{err:?}");
+    assert!(
+        std::fs::read_dir(elsewhere.path())
+            .unwrap()
+            .next()
+            .is_none()
+    );
+}
+
+#[test]
+fn cache_directory_is_git_ignored() {
+    let tmp = TempDir::new().unwrap();
+    DiskCache::new(tmp.path()).put("key", &1u32, HOUR).unwrap();
+
+    let gitignore = std::fs::read_to_string(tmp.path().join(CACHE_DIR).join(".gitignore")).unwrap();
+    assert!(gitignore.lines().any(|line| line == "*"));
+}
+
+#[test]
+fn concurrent_writers_leave_one_complete_entry() {
+    let tmp = TempDir::new().unwrap();
+    let cache = DiskCache::new(tmp.path());
+
+    std::thread::scope(|scope| {
+        for writer in 0..8u32 {
+            let cache = cache.clone();
+            scope.spawn(move || {
+                for round in 0..25u32 {
+                    let value = vec![writer; 64 + round as usize];
+                    cache.put("shared", &value, HOUR).unwrap();
+                }
+            });
+        }
+    });
+
+    let value = cache.get::<Vec<u32>>("shared").unwrap().unwrap();
+    assert!(value.iter().all(|v| *v == value[0]), "{value:?}");
+    let leftovers: Vec<_> = std::fs::read_dir(tmp.path().join(CACHE_DIR))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .filter(|name| name.to_string_lossy().ends_with(".tmp"))
+        .collect();
+    assert!(leftovers.is_empty(), "{leftovers:?}");
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use propel_core::{TemplateContext, TemplateError, substitute, substitute_env};
+
+fn runtime_ctx() -> TemplateContext<'static> {
+    TemplateContext::runtime(
+        "3f2c1a9",
+        "0.4.3",
+        "billing-api",
+        "europe-west1",
+        "2026-10-16T09:30:00Z",
+    )
+}
+
+#[test]
+fn substitute_without_templates_is_identity() {
+    let out = substitute("plain value with } and {", &runtime_ctx()).unwrap();
+    assert_eq!(out, "plain value with } and {");
+}
+
+#[test]
+fn substitute_replaces_every_runtime_variable() {
+    let out = substitute(
+        "{{service}}@{{version}} ({{commit}}) in {{region}} at {{deploy_time}}",
+        &runtime_ctx(),
+    )
+    .unwrap();
+    assert_eq!(
+        out,
+        "billing-api@0.4.3 (3f2c1a9) in europe-west1 at 2026-10-16T09:30:00Z"
+    );
+}
+
+#[test]
+fn substitute_allows_whitespace_inside_braces() {
+    assert_eq!(
+        substitute("{{ commit }}", &runtime_ctx()).unwrap(),
+        "3f2c1a9"
+    );
+}
+
+#[test]
+fn substitute_build_context_has_commit_and_version() {
+    let ctx = TemplateContext::build("3f2c1a9", "0.4.3");
+    assert_eq!(
+        substitute("v{{version}}+{{commit}}", &ctx).unwrap(),
+        "v0.4.3+3f2c1a9"
+    );
+}
+
+#[test]
+fn substitute_build_context_rejects_runtime_variables() {
+    let ctx = TemplateContext::build("3f2c1a9", "0.4.3");
+    for name in ["service", "region", "deploy_time"] {
+        let err = substitute(&format!("{{{{{name}}}}}"), &ctx).unwrap_err();
+        assert_eq!(
+            err,
+            TemplateError::Unavailable {
+                name: name.to_owned()
+            }
+        );
+    }
+}
+
+#[test]
+fn substitute_rejects_unknown_variable() {
+    let err = substitute("{{branch}}", &runtime_ctx()).unwrap_err();
+    assert_eq!(
+        err,
+        TemplateError::Unknown {
+            name: "branch".to_owned()
+        }
+    );
+    assert!(err.to_string().contains("{{branch}}"));
+    assert!(
+        err.to_string()
+            .contains("commit, version, service, region, deploy_time")
+    );
+}
+
+#[test]
+fn substitute_explains_url_is_unsupported() {
+    let err = substitute("{{url}}", &runtime_ctx()).unwrap_err();
+    assert_eq!(err, TemplateError::UrlUnavailable);
+    assert!(err.to_string().contains("only known after the deploy"));
+}
+
+#[test]
+fn substitute_rejects_unterminated_placeholder() {
+    let err = substitute("v{{version", &runtime_ctx()).unwrap_err();
+    assert!(matches!(err, TemplateError::Unterminated { .. }));
+}
+
+#[test]
+fn substitute_env_names_table_and_key_on_error() {
+    let env = HashMap::from([
+        ("OK".to_owned(), "{{commit}}".to_owned()),
+        ("BAD".to_owned(), "{{service}}".to_owned()),
+    ]);
+    let err = substitute_env("build.env", &env, &TemplateContext::build("abc", "1.0.0"))
+        .unwrap_err()
+        .to_string();
+    assert!(err.starts_with("[build.env].BAD: "), "got: {err}");
+}
+
+#[test]
+fn substitute_env_resolves_all_values() {
+    let env = HashMap::from([
+        ("GIT_SHA".to_owned(), "{{commit}}".to_owned()),
+        ("STATIC".to_owned(), "/app/templates".to_owned()),
+    ]);
+    let resolved = substitute_env("cloud_run.env", &env, &runtime_ctx()).unwrap();
+    assert_eq!(resolved["GIT_SHA"], "3f2c1a9");
+    assert_eq!(resolved["STATIC"], "/app/templates");
+}
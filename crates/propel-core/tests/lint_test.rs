@@ -0,0 +1,234 @@
+use propel_core::lint::{
+    always_allocated_min_instances, build_env_differs_from_env, ejected_build_fields,
+    secrets_without_injection, warmup_via_cloud_build,
+};
+use propel_core::{ConfigWarning, ProjectFlags, PropelConfig, check_config};
+
+const EJECTED: ProjectFlags = ProjectFlags { ejected: true };
+const GENERATED: ProjectFlags = ProjectFlags { ejected: false };
+
+fn parse(toml: &str) -> PropelConfig {
+    toml::from_str(toml).unwrap()
+}
+
+fn fields(warnings: &[ConfigWarning]) -> Vec<&'static str> {
+    warnings.iter().map(|w| w.field).collect()
+}
+
+#[test]
+fn ejected_build_fields_cases() {
+    // (propel.toml, flags, fields expected to be reported)
+    let cases: &[(&str, ProjectFlags, &[&str])] = &[
+        ("", EJECTED, &[]),
+        (
+            "[build]\nextra_packages = [\"libpq-dev\"]\n",
+            GENERATED,
+            &[],
+        ),
+        (
+            "[build]\nextra_packages = [\"libpq-dev\"]\n",
+            EJECTED,
+            &["[build].extra_packages"],
+        ),
+        (
+            "[build]\nbase_image = \"rust:1.82-slim\"\n",
+            EJECTED,
+            &["[build].base_image"],
+        ),
+        (
+            "[build]\nruntime_image = \"debian:bookworm-slim\"\nruntime_packages = [\"libpq5\"]\n",
+            EJECTED,
+            &["[build].runtime_image", "[build].runtime_packages"],
+        ),
+        (
+            "[build]\ncargo_chef_version = \"0.1.70\"\n",
+            EJECTED,
+            &["[build].cargo_chef_version"],
+        ),
+        (
+            "[build]\nchef_preinstalled = true\nchef_image = \"chef:1\"\n",
+            EJECTED,
+            &["[build].chef_preinstalled", "[build].chef_image"],
+        ),
+        (
+            "[build]\ninclude = [\"migrations/\"]\n",
+            EJECTED,
+            &["[build].include"],
+        ),
+        (
+            "[build.env]\nAPP_ENV = \"production\"\n",
+            EJECTED,
+            &["[build.env]"],
+        ),
+        (
+            "[build.build_env]\nPROTO_DIR = \"/app/proto\"\n",
+            EJECTED,
+            &["[build].build_env"],
+        ),
+        (
+            "[build]\nfeatures = [\"cloud\"]\nno_default_features = true\nrustflags = \"-C target-cpu=native\"\n",
+            EJECTED,
+            &[
+                "[build].features",
+                "[build].no_default_features",
+                "[build].rustflags",
+            ],
+        ),
+        (
+            "[build]\ntarget = \"x86_64-unknown-linux-musl\"\nstatic_binary = true\n",
+            EJECTED,
+            &["[build].target", "[build].static_binary"],
+        ),
+        // Explicitly set to the default: nothing to warn about.
+        (
+            "[build]\nbase_image = \"rust:1.93-bookworm\"\ncargo_chef_version = \"0.1.73\"\n",
+            EJECTED,
+            &[],
+        ),
+        // Still applied to an ejected Dockerfile by Cloud Build or deploy.
+        (
+            "[build]\ngit_credentials_secret = \"git-creds\"\nplatform = \"linux/arm64\"\nbundle_manifest = true\ncache_mounts = true\n",
+            EJECTED,
+            &[],
+        ),
+    ];
+
+    for (toml, flags, expected) in cases {
+        let warnings = ejected_build_fields(&parse(toml), flags);
+        assert_eq!(fields(&warnings), *expected, "config: {toml:?}, {flags:?}");
+    }
+}
+
+#[test]
+fn check_config_runs_all_rules() {
+    let config = parse("[build]\nextra_packages = [\"libpq-dev\"]\n");
+    assert_eq!(
+        check_config(&config, &EJECTED),
+        ejected_build_fields(&config, &EJECTED)
+    );
+    assert!(check_config(&config, &GENERATED).is_empty());
+}
+
+#[test]
+fn warning_message_names_field_and_reason() {
+    let config = parse("[build]\nextra_packages = [\"libpq-dev\"]\n");
+    let warning = &check_config(&config, &EJECTED)[0];
+    assert_eq!(
+        warning.to_string(),
+        "[build].extra_packages has no effect: project is ejected (.propel/Dockerfile is used as written)"
+    );
+}
+
+#[test]
+fn always_allocated_min_instances_cases() {
+    // (propel.toml, fields expected to be reported)
+    let cases: &[(&str, &[&str])] = &[
+        ("", &[]),
+        ("[cloud_run]\ncpu_always_allocated = true\n", &[]),
+        ("[cloud_run]\nmin_instances = 1\n", &[]),
+        (
+            "[cloud_run]\ncpu_always_allocated = true\nmin_instances = 1\n",
+            &["[cloud_run].cpu_always_allocated"],
+        ),
+    ];
+
+    for (toml, expected) in cases {
+        let warnings = always_allocated_min_instances(&parse(toml), &GENERATED);
+        assert_eq!(fields(&warnings), *expected, "config: {toml:?}");
+    }
+}
+
+#[test]
+fn cost_warning_message_says_it_adds_cost() {
+    let config = parse("[cloud_run]\ncpu_always_allocated = true\nmin_instances = 2\n");
+    let warnings = check_config(&config, &GENERATED);
+    assert_eq!(warnings.len(), 1);
+    assert!(
+        warnings[0]
+            .to_string()
+            .starts_with("[cloud_run].cpu_always_allocated adds cost: "),
+        "{}",
+        warnings[0]
+    );
+}
+
+#[test]
+fn secrets_without_injection_cases() {
+    // (propel.toml, fields expected to be reported)
+    let cases: &[(&str, &[&str])] = &[
+        ("", &[]),
+        ("[cloud_run]\ninject_secrets = false\n", &[]),
+        ("[cloud_run]\nsecrets = [\"API_KEY\"]\n", &[]),
+        (
+            "[cloud_run]\ninject_secrets = false\nsecrets = [\"API_KEY\"]\n",
+            &["[cloud_run].secrets"],
+        ),
+        ("[cloud_run]\ncheck_secret_versions = true\n", &[]),
+        (
+            "[cloud_run]\ninject_secrets = false\ncheck_secret_versions = true\n",
+            &["[cloud_run].check_secret_versions"],
+        ),
+    ];
+
+    for (toml, expected) in cases {
+        let warnings = secrets_without_injection(&parse(toml), &GENERATED);
+        assert_eq!(fields(&warnings), *expected, "config: {toml:?}");
+    }
+}
+
+#[test]
+fn warmup_via_cloud_build_cases() {
+    // (propel.toml, fields expected to be reported)
+    let cases: &[(&str, &[&str])] = &[
+        ("", &[]),
+        ("[cloud_run]\nwarmup_requests = 5\n", &[]),
+        ("[ci]\ndeploy_via_cloud_build = true\n", &[]),
+        (
+            "[cloud_run]\nwarmup_requests = 5\n[ci]\ndeploy_via_cloud_build = true\n",
+            &["[cloud_run].warmup_requests"],
+        ),
+    ];
+
+    for (toml, expected) in cases {
+        let warnings = warmup_via_cloud_build(&parse(toml), &GENERATED);
+        assert_eq!(fields(&warnings), *expected, "config: {toml:?}");
+    }
+}
+
+#[test]
+fn build_env_differs_from_env_cases() {
+    // (propel.toml, number of conflicting keys expected)
+    let cases: &[(&str, usize)] = &[
+        ("", 0),
+        ("[build.build_env]\nPROTO_DIR = \"/app/proto\"\n", 0),
+        (
+            "[build.build_env]\nPROTO_DIR = \"/app/proto\"\n[build.env]\nPROTO_DIR = \"/app/proto\"\n",
+            0,
+        ),
+        (
+            "[build.build_env]\nPROTO_DIR = \"/src/proto\"\nMODE = \"a\"\n\
+             [build.env]\nPROTO_DIR = \"/app/proto\"\nMODE = \"b\"\nOTHER = \"x\"\n",
+            2,
+        ),
+    ];
+
+    for (toml, expected) in cases {
+        let warnings = build_env_differs_from_env(&parse(toml), &GENERATED);
+        assert_eq!(warnings.len(), *expected, "config: {toml:?}");
+        assert!(warnings.iter().all(|w| w.field == "[build].build_env"));
+    }
+}
+
+#[test]
+fn conflict_warning_names_the_key_and_both_values() {
+    let config = parse(
+        "[build.build_env]\nPROTO_DIR = \"/src/proto\"\n[build.env]\nPROTO_DIR = \"/app/proto\"\n",
+    );
+    let warnings = check_config(&config, &GENERATED);
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(
+        warnings[0].to_string(),
+        "[build].build_env conflicts: PROTO_DIR = \"/src/proto\" at build time, \
+         but [build.env] sets PROTO_DIR = \"/app/proto\" at runtime"
+    );
+}
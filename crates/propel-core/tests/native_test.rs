@@ -0,0 +1,184 @@
+use std::path::Path;
+
+use cargo_metadata::{Metadata, MetadataCommand};
+use propel_core::{
+    BuildConfig, DEBIAN_RUNTIME_IMAGE, KNOWN_NATIVE_DEPENDENCIES, PackageSuggestion, PropelConfig,
+    detect_native_dependencies,
+};
+use tempfile::TempDir;
+
+const FIXTURE_MANIFEST: &str = "/work/fixture-app/Cargo.toml";
+
+/// Resolved `cargo metadata` for a project depending on `openssl-sys`,
+/// `pq-sys`, `libsqlite3-sys` (with `bundled`), and `mysqlclient-sys`
+/// as a dev-dependency only.
+fn fixture_metadata() -> Metadata {
+    MetadataCommand::parse(include_str!("fixtures/metadata_native_deps.json")).unwrap()
+}
+
+fn detected_names(metadata: &Metadata) -> Vec<&'static str> {
+    detect_native_dependencies(metadata, Path::new(FIXTURE_MANIFEST))
+        .iter()
+        .map(|n| n.crate_name)
+        .collect()
+}
+
+// ── Detection ──
+
+#[test]
+fn detects_native_runtime_dependencies() {
+    let names = detected_names(&fixture_metadata());
+    assert_eq!(names, vec!["openssl-sys", "pq-sys"]);
+}
+
+#[test]
+fn skips_crates_built_with_bundled_feature() {
+    let names = detected_names(&fixture_metadata());
+    assert!(!names.contains(&"libsqlite3-sys"));
+}
+
+#[test]
+fn skips_dev_only_dependencies() {
+    let names = detected_names(&fixture_metadata());
+    assert!(!names.contains(&"mysqlclient-sys"));
+}
+
+#[test]
+fn unknown_manifest_detects_nothing() {
+    let metadata = fixture_metadata();
+    assert!(detect_native_dependencies(&metadata, Path::new("/elsewhere/Cargo.toml")).is_empty());
+}
+
+// ── Suggestions ──
+
+fn fixture_detected() -> Vec<&'static propel_core::NativeDependency> {
+    detect_native_dependencies(&fixture_metadata(), Path::new(FIXTURE_MANIFEST))
+}
+
+#[test]
+fn suggestion_switches_distroless_runtime_for_shared_libraries() {
+    let suggestion = PackageSuggestion::for_config(&fixture_detected(), &BuildConfig::default());
+
+    assert_eq!(suggestion.crates, vec!["openssl-sys", "pq-sys"]);
+    assert_eq!(
+        suggestion.build,
+        vec!["libssl-dev", "pkg-config", "libpq-dev"]
+    );
+    // debian-slim ships none of the libraries distroless/cc provides
+    assert_eq!(
+        suggestion.runtime,
+        vec!["libssl3", "ca-certificates", "libpq5"]
+    );
+    assert_eq!(suggestion.runtime_image, Some(DEBIAN_RUNTIME_IMAGE));
+}
+
+#[test]
+fn suggestion_keeps_distroless_when_it_provides_the_libraries() {
+    let openssl: Vec<_> = KNOWN_NATIVE_DEPENDENCIES
+        .iter()
+        .filter(|n| n.crate_name == "openssl-sys")
+        .collect();
+    let suggestion = PackageSuggestion::for_config(&openssl, &BuildConfig::default());
+
+    assert_eq!(suggestion.build, vec!["libssl-dev", "pkg-config"]);
+    assert!(suggestion.runtime.is_empty());
+    assert_eq!(suggestion.runtime_image, None);
+}
+
+#[test]
+fn suggestion_empty_when_config_already_covers_packages() {
+    let config = BuildConfig {
+        runtime_image: DEBIAN_RUNTIME_IMAGE.to_owned(),
+        extra_packages: vec![
+            "pkg-config".to_owned(),
+            "libssl-dev".to_owned(),
+            "libpq-dev".to_owned(),
+        ],
+        runtime_packages: vec![
+            "ca-certificates".to_owned(),
+            "libssl3".to_owned(),
+            "libpq5".to_owned(),
+        ],
+        ..Default::default()
+    };
+
+    assert!(PackageSuggestion::for_config(&fixture_detected(), &config).is_empty());
+}
+
+#[test]
+fn toml_snippet_merges_existing_packages() {
+    let config = BuildConfig {
+        runtime_image: DEBIAN_RUNTIME_IMAGE.to_owned(),
+        extra_packages: vec!["libssl-dev".to_owned()],
+        runtime_packages: vec!["libssl3".to_owned(), "ca-certificates".to_owned()],
+        ..Default::default()
+    };
+    let suggestion = PackageSuggestion::for_config(&fixture_detected(), &config);
+
+    assert_eq!(
+        suggestion.toml_snippet(&config),
+        "[build]\n\
+         extra_packages = [\"libssl-dev\", \"pkg-config\", \"libpq-dev\"]\n\
+         runtime_packages = [\"libssl3\", \"ca-certificates\", \"libpq5\"]\n"
+    );
+}
+
+// ── Applying to propel.toml ──
+
+#[test]
+fn apply_to_config_preserves_existing_content() {
+    let tmp = TempDir::new().unwrap();
+    std::fs::write(
+        tmp.path().join("propel.toml"),
+        "# my settings\n[project]\ngcp_project_id = \"demo\"\n\n[build]\nextra_packages = [\"libssl-dev\"]\n",
+    )
+    .unwrap();
+    let before = PropelConfig::load(tmp.path()).unwrap();
+    let suggestion = PackageSuggestion::for_config(&fixture_detected(), &before.build);
+
+    suggestion.apply_to_config(tmp.path()).unwrap();
+
+    let content = std::fs::read_to_string(tmp.path().join("propel.toml")).unwrap();
+    assert!(content.starts_with("# my settings\n"));
+    let after = PropelConfig::load(tmp.path()).unwrap();
+    assert_eq!(after.project.gcp_project_id.as_deref(), Some("demo"));
+    assert_eq!(after.build.runtime_image, DEBIAN_RUNTIME_IMAGE);
+    assert_eq!(
+        after.build.extra_packages,
+        vec!["libssl-dev", "pkg-config", "libpq-dev"]
+    );
+    assert!(PackageSuggestion::for_config(&fixture_detected(), &after.build).is_empty());
+}
+
+#[test]
+fn apply_to_matches_apply_to_config() {
+    let tmp = TempDir::new().unwrap();
+    let mut config = BuildConfig {
+        extra_packages: vec!["libssl-dev".to_owned()],
+        ..Default::default()
+    };
+    let suggestion = PackageSuggestion::for_config(&fixture_detected(), &config);
+
+    suggestion.apply_to(&mut config);
+    suggestion.apply_to_config(tmp.path()).unwrap();
+
+    let written = PropelConfig::load(tmp.path()).unwrap().build;
+    assert_eq!(config.runtime_image, written.runtime_image);
+    assert_eq!(
+        config.extra_packages,
+        vec!["libssl-dev", "pkg-config", "libpq-dev"]
+    );
+    assert_eq!(config.runtime_packages, written.runtime_packages);
+    assert!(PackageSuggestion::for_config(&fixture_detected(), &config).is_empty());
+}
+
+#[test]
+fn apply_to_config_creates_missing_file() {
+    let tmp = TempDir::new().unwrap();
+    let suggestion = PackageSuggestion::for_config(&fixture_detected(), &BuildConfig::default());
+
+    suggestion.apply_to_config(tmp.path()).unwrap();
+
+    let config = PropelConfig::load(tmp.path()).unwrap();
+    assert_eq!(config.build.runtime_packages, suggestion.runtime);
+}
@@ -0,0 +1,53 @@
+use std::path::Path;
+
+use cargo_metadata::MetadataCommand;
+use propel_core::detect_private_dependencies;
+use propel_core::private_deps::is_private_source;
+
+// ── Source classification ──
+
+#[test]
+fn ssh_git_sources_are_private() {
+    assert!(is_private_source(
+        "git+ssh://git@github.com/acme/internal.git?branch=main#abc"
+    ));
+}
+
+#[test]
+fn https_git_sources_are_not_flagged() {
+    assert!(!is_private_source(
+        "git+https://github.com/someone/public.git#abc"
+    ));
+}
+
+#[test]
+fn crates_io_sources_are_public() {
+    assert!(!is_private_source(
+        "registry+https://github.com/rust-lang/crates.io-index"
+    ));
+    assert!(!is_private_source("sparse+https://index.crates.io/"));
+}
+
+#[test]
+fn alternate_registries_are_private() {
+    assert!(is_private_source(
+        "sparse+https://crates.acme.example/index/"
+    ));
+    assert!(is_private_source(
+        "registry+https://git.acme.example/cargo-index"
+    ));
+}
+
+// ── Detection ──
+
+#[test]
+fn detects_private_dependencies_from_metadata() {
+    let metadata =
+        MetadataCommand::parse(include_str!("fixtures/metadata_private_deps.json")).unwrap();
+    let found = detect_private_dependencies(&metadata, Path::new("/work/private-app/Cargo.toml"));
+    let names: Vec<&str> = found.iter().map(|d| d.name.as_str()).collect();
+
+    // test-harness is ssh but dev-only; public-fork is https; serde is crates.io
+    assert_eq!(names, vec!["acme-legacy", "acme-proto", "internal-auth"]);
+    assert!(found[2].source.starts_with("git+ssh://"));
+}
@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+
+use propel_core::{PathReference, env_path_references, is_included, scan_source, top_level_entry};
+
+fn paths(source: &str) -> Vec<String> {
+    scan_source(source).into_iter().map(|r| r.path).collect()
+}
+
+#[test]
+fn std_fs_reads_are_found() {
+    let source = r#"
+fn load() {
+    let page = std::fs::read_to_string("templates/index.html").unwrap();
+    let logo = fs::read("static/logo.png").unwrap();
+    for entry in std::fs::read_dir("migrations").unwrap() {}
+}
+"#;
+
+    assert_eq!(
+        paths(source),
+        ["templates/index.html", "static/logo.png", "migrations"]
+    );
+}
+
+#[test]
+fn tokio_fs_and_file_open_are_found() {
+    let source = r#"
+async fn load() {
+    let seed = tokio::fs::read_to_string("seeds/users.json").await?;
+    let file = File::open("data/geo.db")?;
+}
+"#;
+
+    assert_eq!(paths(source), ["seeds/users.json", "data/geo.db"]);
+}
+
+#[test]
+fn serve_dir_and_serve_file_are_found() {
+    let source = r#"
+let app = Router::new()
+    .nest_service("/assets", ServeDir::new("assets"))
+    .route_service("/", ServeFile::new("public/index.html"));
+"#;
+
+    assert_eq!(paths(source), ["assets", "public/index.html"]);
+}
+
+#[test]
+fn reference_reports_the_literal_line() {
+    let source =
+        "fn main() {\n    let s = fs::read_to_string(\n        \"templates/a.html\",\n    );\n}\n";
+
+    assert_eq!(
+        scan_source(source),
+        [PathReference {
+            path: "templates/a.html".to_owned(),
+            line: 3,
+        }]
+    );
+}
+
+#[test]
+fn raw_string_literals_are_found() {
+    assert_eq!(
+        paths(r##"let s = fs::read_to_string(r"templates/a.html");"##),
+        ["templates/a.html"]
+    );
+}
+
+#[test]
+fn compile_time_includes_are_ignored() {
+    let source = r#"
+const PAGE: &str = include_str!("../templates/index.html");
+const LOGO: &[u8] = include_bytes!("../static/logo.png");
+"#;
+
+    assert!(scan_source(source).is_empty());
+}
+
+#[test]
+fn commented_out_calls_are_ignored() {
+    let source = r#"
+// let page = std::fs::read_to_string("templates/old.html");
+/// fs::read("docs/example.txt")
+let page = fs::read_to_string("templates/new.html"); // was fs::read("old/")
+"#;
+
+    assert_eq!(paths(source), ["templates/new.html"]);
+}
+
+#[test]
+fn computed_paths_and_lookalike_calls_are_ignored() {
+    let source = r#"
+let page = fs::read_to_string(dir.join("index.html"));
+let page = fs::read_to_string(format!("{dir}/index.html"));
+let blob = vfs::read("templates/x");
+let blob = my_fs::read("templates/y");
+"#;
+
+    assert!(scan_source(source).is_empty());
+}
+
+#[test]
+fn top_level_entry_is_relative_to_the_workdir() {
+    assert_eq!(top_level_entry("templates/index.html"), Some("templates"));
+    assert_eq!(top_level_entry("./templates/"), Some("templates"));
+    assert_eq!(top_level_entry("/app/templates"), Some("templates"));
+    assert_eq!(top_level_entry("assets"), Some("assets"));
+}
+
+#[test]
+fn top_level_entry_rejects_paths_outside_the_image_workdir() {
+    assert_eq!(top_level_entry("/etc/ssl/certs"), None);
+    assert_eq!(top_level_entry("../shared/x"), None);
+    assert_eq!(top_level_entry("https://example.com/a"), None);
+    assert_eq!(top_level_entry("{{commit}}"), None);
+    assert_eq!(top_level_entry(""), None);
+}
+
+#[test]
+fn env_values_yield_their_top_level_entry() {
+    let env = HashMap::from([
+        ("TEMPLATE_DIR".to_owned(), "/app/templates".to_owned()),
+        ("RUST_LOG".to_owned(), "info".to_owned()),
+        ("CA_BUNDLE".to_owned(), "/etc/ssl/certs/ca.pem".to_owned()),
+        ("GIT_SHA".to_owned(), "{{commit}}".to_owned()),
+    ]);
+
+    assert_eq!(
+        env_path_references(&env),
+        [
+            ("RUST_LOG".to_owned(), "info".to_owned()),
+            ("TEMPLATE_DIR".to_owned(), "templates".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn include_entries_cover_their_top_level_directory() {
+    let include = vec!["migrations/".to_owned(), "./templates/email/".to_owned()];
+
+    assert!(is_included("migrations", &include));
+    assert!(is_included("templates", &include));
+    assert!(!is_included("static", &include));
+    assert!(!is_included("migrations", &[]));
+}
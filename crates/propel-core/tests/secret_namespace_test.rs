@@ -0,0 +1,65 @@
+use propel_core::{SecretBinding, SecretNamespace};
+
+fn names(names: &[&str]) -> Vec<String> {
+    names.iter().map(|s| (*s).to_owned()).collect()
+}
+
+#[test]
+fn default_namespace_maps_keys_to_themselves() {
+    let namespace = SecretNamespace::default();
+
+    assert_eq!(namespace.secret_name("FOO"), "FOO");
+    assert_eq!(namespace.key("staging_FOO"), Some("staging_FOO"));
+    assert_eq!(
+        namespace.keys(&names(&["FOO", "staging_FOO"])),
+        names(&["FOO", "staging_FOO"])
+    );
+}
+
+#[test]
+fn prefixed_namespace_only_owns_prefixed_names() {
+    let namespace = SecretNamespace::new("staging_");
+
+    assert_eq!(namespace.key("staging_FOO"), Some("FOO"));
+    assert_eq!(namespace.key("FOO"), None);
+    assert_eq!(namespace.key("prod_FOO"), None);
+    assert_eq!(namespace.key("staging_"), None);
+    assert_eq!(
+        namespace.keys(&names(&["prod_FOO", "staging_FOO", "BAR", "staging_BAR"])),
+        names(&["FOO", "BAR"])
+    );
+}
+
+#[test]
+fn set_list_delete_and_deploy_agree_on_names() {
+    let namespace = SecretNamespace::new("staging_");
+
+    // `secret set FOO` stores the prefixed name...
+    let stored = namespace.secret_name("FOO");
+    assert_eq!(stored, "staging_FOO");
+    // ...which `secret list` shows as FOO again...
+    assert_eq!(namespace.keys(std::slice::from_ref(&stored)), ["FOO"]);
+    // ...`secret delete FOO` targets the same name...
+    assert_eq!(namespace.secret_name("FOO"), stored);
+    // ...and deploy mounts it as FOO.
+    assert_eq!(
+        namespace.binding("FOO"),
+        SecretBinding {
+            env: "FOO".to_owned(),
+            secret: stored,
+            version: None,
+        }
+    );
+}
+
+#[test]
+fn unprefixed_binding_uses_one_name() {
+    assert_eq!(
+        SecretBinding::from("API_KEY"),
+        SecretBinding {
+            env: "API_KEY".to_owned(),
+            secret: "API_KEY".to_owned(),
+            version: None,
+        }
+    );
+}
@@ -0,0 +1,204 @@
+//! Scripted `gcloud` for end-to-end tests of the `propel` binary.
+//!
+//! [`FakeGcloud::install`] writes a `gcloud` shell script into a directory
+//! and [`FakeGcloud::path`] puts that directory first on `PATH`, so
+//! `propel deploy`, `doctor`, or `destroy` run as real subprocesses and
+//! call it instead of the Cloud SDK. The script answers from a
+//! [`Script`], usually parsed from fixture files:
+//!
+//! ```toml
+//! # The first entry whose `args` matches answers. `args` is a shell glob
+//! # over the arguments joined by spaces; only `*` is special.
+//! [[command]]
+//! args = "config get-value account"
+//! stdout = "dev@example.com\n"
+//!
+//! # Fails the first build only; the second call falls through to the
+//! # next matching entry.
+//! [[command]]
+//! args = "builds submit *"
+//! stderr = "ERROR: build step 0 failed\n"
+//! exit = 1
+//! times = 1
+//! ```
+//!
+//! Every call is appended to a log ([`FakeGcloud::calls`]). A call no entry
+//! answers exits with [`UNSCRIPTED_EXIT`] and is also logged separately
+//! ([`FakeGcloud::unscripted`]), so a test can tell a missing fixture entry
+//! from a failure it scripted.
+
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+/// Exit code of a call that no [`Response`] matches.
+pub const UNSCRIPTED_EXIT: i32 = 97;
+
+/// Scripted answers, in the order they are tried.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Script {
+    #[serde(default, rename = "command")]
+    pub responses: Vec<Response>,
+}
+
+/// How the fake `gcloud` answers calls whose arguments match `args`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Response {
+    /// Glob over the space-joined arguments: `*` matches anything, every
+    /// other character itself.
+    pub args: String,
+    #[serde(default)]
+    pub stdout: String,
+    #[serde(default)]
+    pub stderr: String,
+    #[serde(default)]
+    pub exit: i32,
+    /// Answer only the first `times` matching calls; later ones fall
+    /// through to the next matching entry.
+    #[serde(default)]
+    pub times: Option<u32>,
+    /// Read stdin to the end before answering, for calls propel pipes data
+    /// into (`secrets versions add --data-file=-`).
+    #[serde(default)]
+    pub read_stdin: bool,
+}
+
+impl Script {
+    /// Parse a fixture, usually `include_str!`ed.
+    pub fn parse(toml: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(toml)
+    }
+
+    /// `self`'s responses followed by `other`'s, which answer only calls
+    /// none of `self`'s match. Put a fixture that overrides a call (a
+    /// failing build) before the one it overrides.
+    pub fn then(mut self, other: Script) -> Self {
+        self.responses.extend(other.responses);
+        self
+    }
+
+    /// The `gcloud` shell script, logging calls under `state_dir`.
+    fn render(&self, state_dir: &Path) -> String {
+        let state = quote(&state_dir.display().to_string());
+        let mut out = format!(
+            "#!/bin/sh\n\
+             # Scripted gcloud written by propel-testkit.\n\
+             args=\"$*\"\n\
+             printf '%s\\n' \"$args\" >> {state}/calls\n\
+             under_limit() {{\n    \
+                 n=$(cat {state}/times-$1 2>/dev/null || echo 0)\n    \
+                 [ \"$n\" -lt \"$2\" ] || return 1\n    \
+                 echo $((n + 1)) > {state}/times-$1\n\
+             }}\n"
+        );
+        for (index, response) in self.responses.iter().enumerate() {
+            let limit = match response.times {
+                Some(times) => format!(" && under_limit {index} {times}"),
+                None => String::new(),
+            };
+            let stdin = if response.read_stdin {
+                "    cat > /dev/null\n"
+            } else {
+                ""
+            };
+            // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+            let _ = write!(
+                out,
+                "if case \"$args\" in {pattern}) true ;; *) false ;; esac{limit}; then\n\
+                 {stdin}    printf '%s' {stdout}\n    \
+                 printf '%s' {stderr} >&2\n    \
+                 exit {exit}\n\
+                 fi\n",
+                pattern = glob(&response.args),
+                stdout = quote(&response.stdout),
+                stderr = quote(&response.stderr),
+                exit = response.exit,
+            );
+        }
+        // arch-lint: allow(no-silent-result-drop) reason="fmt::Write for String is infallible"
+        let _ = write!(
+            out,
+            "printf '%s\\n' \"$args\" >> {state}/unscripted\n\
+             printf 'fake gcloud: no scripted response for: gcloud %s\\n' \"$args\" >&2\n\
+             exit {UNSCRIPTED_EXIT}\n"
+        );
+        out
+    }
+}
+
+/// A `gcloud` script installed in a directory.
+#[derive(Debug)]
+pub struct FakeGcloud {
+    bin_dir: PathBuf,
+    state_dir: PathBuf,
+}
+
+impl FakeGcloud {
+    /// Write `dir/bin/gcloud` answering from `script`; call logs go to
+    /// `dir/state`.
+    pub fn install(dir: &Path, script: &Script) -> std::io::Result<Self> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let bin_dir = dir.join("bin");
+        let state_dir = dir.join("state");
+        std::fs::create_dir_all(&bin_dir)?;
+        std::fs::create_dir_all(&state_dir)?;
+        std::fs::write(state_dir.join("calls"), "")?;
+        std::fs::write(state_dir.join("unscripted"), "")?;
+        let gcloud = bin_dir.join("gcloud");
+        std::fs::write(&gcloud, script.render(&state_dir))?;
+        std::fs::set_permissions(&gcloud, std::fs::Permissions::from_mode(0o755))?;
+        Ok(Self { bin_dir, state_dir })
+    }
+
+    /// `PATH` with the fake `gcloud` first, ahead of the caller's `PATH`
+    /// (which still provides cargo and git).
+    pub fn path(&self) -> OsString {
+        let inherited = std::env::var_os("PATH");
+        let rest = inherited.iter().flat_map(std::env::split_paths);
+        std::env::join_paths(std::iter::once(self.bin_dir.clone()).chain(rest))
+            .expect("test directories contain no ':'")
+    }
+
+    /// Arguments of every call so far, space-joined, in call order.
+    pub fn calls(&self) -> std::io::Result<Vec<String>> {
+        self.read_log("calls")
+    }
+
+    /// Calls no response matched.
+    pub fn unscripted(&self) -> std::io::Result<Vec<String>> {
+        self.read_log("unscripted")
+    }
+
+    fn read_log(&self, name: &str) -> std::io::Result<Vec<String>> {
+        let log = std::fs::read_to_string(self.state_dir.join(name))?;
+        Ok(log.lines().map(str::to_owned).collect())
+    }
+}
+
+/// `value` as one single-quoted shell word.
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// `pattern` as a `case` pattern where only `*` is special.
+fn glob(pattern: &str) -> String {
+    if pattern.is_empty() {
+        return quote(pattern);
+    }
+    pattern
+        .split('*')
+        .map(|literal| {
+            if literal.is_empty() {
+                String::new()
+            } else {
+                quote(literal)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("*")
+}
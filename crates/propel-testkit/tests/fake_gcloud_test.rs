@@ -0,0 +1,177 @@
+use std::process::Output;
+
+use propel_testkit::{FakeGcloud, Response, Script, UNSCRIPTED_EXIT};
+use tempfile::TempDir;
+
+fn install(script: &Script) -> (TempDir, FakeGcloud) {
+    let tmp = TempDir::new().unwrap();
+    let gcloud = FakeGcloud::install(tmp.path(), script).unwrap();
+    (tmp, gcloud)
+}
+
+fn run(gcloud: &FakeGcloud, args: &[&str]) -> Output {
+    std::process::Command::new("gcloud")
+        .args(args)
+        .env("PATH", gcloud.path())
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn answers_the_first_matching_response() {
+    let script = Script::parse(
+        r#"
+        [[command]]
+        args = "config get-value account"
+        stdout = "dev@example.com\n"
+
+        [[command]]
+        args = "run deploy * --format value(status.url)"
+        stdout = "https://svc.a.run.app\n"
+        stderr = "Deploying...\n"
+
+        [[command]]
+        args = "run deploy *"
+        exit = 1
+        "#,
+    )
+    .unwrap();
+    let (_tmp, gcloud) = install(&script);
+
+    let output = run(&gcloud, &["config", "get-value", "account"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "dev@example.com\n");
+
+    let output = run(
+        &gcloud,
+        &["run", "deploy", "svc", "--format", "value(status.url)"],
+    );
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "https://svc.a.run.app\n");
+    assert_eq!(output.stderr, b"Deploying...\n");
+
+    let output = run(&gcloud, &["run", "deploy", "svc"]);
+    assert_eq!(output.status.code(), Some(1));
+
+    assert_eq!(
+        gcloud.calls().unwrap(),
+        [
+            "config get-value account",
+            "run deploy svc --format value(status.url)",
+            "run deploy svc",
+        ]
+    );
+    assert!(gcloud.unscripted().unwrap().is_empty());
+}
+
+#[test]
+fn unscripted_calls_fail_and_are_logged() {
+    let (_tmp, gcloud) = install(&Script::default());
+
+    let output = run(&gcloud, &["projects", "list"]);
+
+    assert_eq!(output.status.code(), Some(UNSCRIPTED_EXIT));
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("gcloud projects list"),
+        "{output:?}"
+    );
+    assert_eq!(gcloud.unscripted().unwrap(), ["projects list"]);
+}
+
+#[test]
+fn only_star_is_special_and_output_is_literal() {
+    let script = Script::default().then(Script {
+        responses: vec![Response {
+            args: "secrets list --filter name~[a-z]? *".to_owned(),
+            stdout: "it's $HOME `x` \\n\n".to_owned(),
+            ..Response::default()
+        }],
+    });
+    let (_tmp, gcloud) = install(&script);
+
+    let output = run(
+        &gcloud,
+        &["secrets", "list", "--filter", "name~[a-z]?", "--quiet"],
+    );
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(stdout(&output), "it's $HOME `x` \\n\n");
+
+    let output = run(
+        &gcloud,
+        &["secrets", "list", "--filter", "name~b1", "--quiet"],
+    );
+    assert_eq!(output.status.code(), Some(UNSCRIPTED_EXIT));
+}
+
+#[test]
+fn times_limits_a_response_then_falls_through() {
+    let flaky = Script::parse(
+        r#"
+        [[command]]
+        args = "builds submit *"
+        stderr = "transient\n"
+        exit = 1
+        times = 2
+        "#,
+    )
+    .unwrap();
+    let ok = Script::parse(
+        r#"
+        [[command]]
+        args = "builds submit *"
+        stdout = "DONE\n"
+        "#,
+    )
+    .unwrap();
+    let (_tmp, gcloud) = install(&flaky.then(ok));
+
+    let codes: Vec<Option<i32>> = (0..3)
+        .map(|_| run(&gcloud, &["builds", "submit", "."]).status.code())
+        .collect();
+
+    assert_eq!(codes, [Some(1), Some(1), Some(0)]);
+}
+
+#[test]
+fn read_stdin_consumes_piped_data() {
+    use std::io::Write;
+
+    let script = Script::parse(
+        r#"
+        [[command]]
+        args = "secrets versions add KEY --data-file=-"
+        read_stdin = true
+        stdout = "Created version [1].\n"
+        "#,
+    )
+    .unwrap();
+    let (_tmp, gcloud) = install(&script);
+
+    let mut child = std::process::Command::new("gcloud")
+        .args(["secrets", "versions", "add", "KEY", "--data-file=-"])
+        .env("PATH", gcloud.path())
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(&vec![b'x'; 1 << 20])
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "Created version [1].\n");
+}
+
+#[test]
+fn fixtures_reject_unknown_keys() {
+    let err = Script::parse("[[command]]\nargs = \"version\"\nexit_code = 1\n").unwrap_err();
+    assert!(err.to_string().contains("exit_code"), "{err}");
+}
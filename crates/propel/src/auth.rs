@@ -1,20 +1,31 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
 use axum::{
     extract::{Request, State},
     http::StatusCode,
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Response},
 };
 use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode};
 use secrecy::ExposeSecret;
 use serde::{Deserialize, Serialize};
+use tower::{Layer, Service};
 
 use crate::PropelState;
+use crate::crypto::constant_time_eq;
+use crate::metrics::{self, AuthEvent};
 
 /// JWT claims from Supabase Auth.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SupabaseClaims {
     pub sub: String,
     pub aud: String,
+    /// Issuer, `{SUPABASE_URL}/auth/v1` for tokens minted by Supabase Auth.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
     pub email: Option<String>,
     pub role: Option<String>,
     pub exp: usize,
@@ -57,6 +68,9 @@ pub enum AuthIdentity {
 ///    - `role: "service_role"` → [`AuthIdentity::ServiceRole`]
 ///    - Other roles → [`AuthIdentity::User`]
 ///
+/// To accept the server key only on some paths, build the layer with
+/// [`PropelAuth::builder`] instead.
+///
 /// Usage:
 /// ```rust,no_run
 /// use axum::{Router, middleware, routing::get};
@@ -78,81 +92,356 @@ impl PropelAuth {
         mut request: Request,
         next: Next,
     ) -> Result<Response, StatusCode> {
-        // 1. Try X-Server-Key header first (cheap constant-time check)
-        if let Some(key) = request
-            .headers()
-            .get("x-server-key")
-            // arch-lint: allow(no-silent-result-drop) reason="non-ASCII HeaderValue is invalid for server key; treating as absent"
-            .and_then(|v| v.to_str().ok())
-        {
-            let expected = state.server_key.as_ref().ok_or_else(|| {
-                tracing::warn!(
-                    path = %request.uri(),
-                    "X-Server-Key header sent but PROPEL_SERVER_KEY not configured",
-                );
-                StatusCode::UNAUTHORIZED
-            })?;
-
-            if !constant_time_eq(key.as_bytes(), expected.expose_secret().as_bytes()) {
-                tracing::warn!(path = %request.uri(), "invalid server key");
-                return Err(StatusCode::UNAUTHORIZED);
+        authenticate(&state, &AuthOptions::default(), &mut request)?;
+        Ok(next.run(request).await)
+    }
+
+    /// Configure the middleware, e.g. to accept `X-Server-Key` only on
+    /// some paths:
+    ///
+    /// ```rust,no_run
+    /// use axum::{Router, routing::get};
+    /// use propel::{PropelAuth, PropelState};
+    ///
+    /// async fn handler() -> &'static str { "ok" }
+    ///
+    /// let state = PropelState::load().unwrap();
+    /// let app: Router = Router::new()
+    ///     .route("/internal/jobs", get(handler))
+    ///     .route("/api/me", get(handler))
+    ///     .layer(PropelAuth::builder().server_key_paths(["/internal"]).layer(state.clone()))
+    ///     .with_state(state);
+    /// ```
+    pub fn builder() -> PropelAuthBuilder {
+        PropelAuthBuilder::default()
+    }
+}
+
+/// Builder for a [`PropelAuthLayer`], created by [`PropelAuth::builder`].
+#[derive(Debug, Clone, Default)]
+pub struct PropelAuthBuilder {
+    options: AuthOptions,
+}
+
+/// How the `iss` claim of a JWT is checked against `SUPABASE_URL`.
+///
+/// Tokens minted by another Supabase project that shares the JWT secret
+/// (copied across environments) verify fine otherwise, because only
+/// `aud = "authenticated"` is checked. The check is skipped when
+/// `SUPABASE_URL` is empty.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IssuerCheck {
+    /// Do not look at `iss`.
+    Off,
+    /// Reject a token whose `iss` is not `{SUPABASE_URL}/auth/v1`; accept
+    /// one without `iss`, logging a warning. The default, so tokens from
+    /// older issuers keep working.
+    #[default]
+    WarnIfMissing,
+    /// Also reject a token without `iss`.
+    Required,
+}
+
+/// Options set through a [`PropelAuthBuilder`]; [`PropelAuth::verify`]
+/// uses the defaults.
+#[derive(Debug, Clone, Default)]
+struct AuthOptions {
+    /// `None` accepts the server key on every path.
+    server_key_paths: Option<Arc<[PathPattern]>>,
+    issuer_check: IssuerCheck,
+}
+
+impl PropelAuthBuilder {
+    /// Accept `X-Server-Key` only on these paths.
+    ///
+    /// `"/internal"` matches `/internal` and everything below it
+    /// (`/internal/jobs`), but not `/internals`. A leading `=` matches one
+    /// path exactly: `"=/internal/health"`.
+    ///
+    /// Elsewhere a server key is not used: the request falls back to
+    /// `Authorization: Bearer` when that header is present, and is
+    /// rejected with 403 otherwise, even when the key is correct. Without
+    /// this setting every path accepts the server key.
+    pub fn server_key_paths<I, P>(mut self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<str>,
+    {
+        self.options.server_key_paths = Some(
+            paths
+                .into_iter()
+                .map(|p| PathPattern::parse(p.as_ref()))
+                .collect(),
+        );
+        self
+    }
+
+    /// How strictly to check the JWT `iss` claim (default
+    /// [`IssuerCheck::WarnIfMissing`]).
+    pub fn issuer_check(mut self, issuer_check: IssuerCheck) -> Self {
+        self.options.issuer_check = issuer_check;
+        self
+    }
+
+    /// Finish the builder into a layer for `Router::layer`.
+    pub fn layer(self, state: PropelState) -> PropelAuthLayer {
+        PropelAuthLayer {
+            state,
+            options: self.options,
+        }
+    }
+}
+
+/// Path rule from [`PropelAuthBuilder::server_key_paths`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PathPattern {
+    /// The path itself and everything below it.
+    Prefix(String),
+    Exact(String),
+}
+
+impl PathPattern {
+    fn parse(pattern: &str) -> Self {
+        match pattern.strip_prefix('=') {
+            Some(exact) => Self::Exact(exact.to_owned()),
+            None => Self::Prefix(pattern.trim_end_matches('/').to_owned()),
+        }
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        match self {
+            Self::Exact(exact) => path == exact,
+            Self::Prefix(prefix) => path
+                .strip_prefix(prefix.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/')),
+        }
+    }
+}
+
+/// Tower layer running the same checks as [`PropelAuth::verify`], with the
+/// options of a [`PropelAuthBuilder`].
+#[derive(Debug, Clone)]
+pub struct PropelAuthLayer {
+    state: PropelState,
+    options: AuthOptions,
+}
+
+impl<S> Layer<S> for PropelAuthLayer {
+    type Service = PropelAuthService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        PropelAuthService {
+            inner,
+            auth: self.clone(),
+        }
+    }
+}
+
+/// Service produced by [`PropelAuthLayer`].
+#[derive(Debug, Clone)]
+pub struct PropelAuthService<S> {
+    inner: S,
+    auth: PropelAuthLayer,
+}
+
+impl<S> Service<Request> for PropelAuthService<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request) -> Self::Future {
+        // The clone may not be ready; keep the one that was polled.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let result = authenticate(&self.auth.state, &self.auth.options, &mut request);
+        Box::pin(async move {
+            match result {
+                Ok(()) => inner.call(request).await,
+                Err(status) => Ok(status.into_response()),
+            }
+        })
+    }
+}
+
+/// Authenticate `request`, attaching [`AuthIdentity`] (and the claims for
+/// JWTs) to its extensions and counting the outcome in [`metrics`].
+fn authenticate(
+    state: &PropelState,
+    options: &AuthOptions,
+    request: &mut Request,
+) -> Result<(), StatusCode> {
+    // 1. Try X-Server-Key header first (cheap constant-time check)
+    if let Some(key) = request
+        .headers()
+        .get("x-server-key")
+        // arch-lint: allow(no-silent-result-drop) reason="non-ASCII HeaderValue is invalid for server key; treating as absent"
+        .and_then(|v| v.to_str().ok())
+    {
+        let path = request.uri().path();
+        let allowed = options
+            .server_key_paths
+            .as_deref()
+            .is_none_or(|paths| paths.iter().any(|p| p.matches(path)));
+        if allowed {
+            match verify_server_key(key, state) {
+                Ok(()) => {}
+                Err(ServerKeyError::NotConfigured) => {
+                    metrics::record(AuthEvent::ServerKeyNotConfigured);
+                    tracing::warn!(
+                        path = %request.uri(),
+                        "X-Server-Key header sent but PROPEL_SERVER_KEY not configured",
+                    );
+                    return Err(StatusCode::UNAUTHORIZED);
+                }
+                Err(ServerKeyError::Mismatch) => {
+                    metrics::record(AuthEvent::InvalidServerKey);
+                    tracing::warn!(path = %request.uri(), "invalid server key");
+                    return Err(StatusCode::UNAUTHORIZED);
+                }
             }
 
+            metrics::record(AuthEvent::ServerKey);
             tracing::debug!(path = %request.uri(), "authenticated via server key");
             request.extensions_mut().insert(AuthIdentity::ServerKey);
-            return Ok(next.run(request).await);
+            return Ok(());
+        }
+
+        if !request.headers().contains_key("authorization") {
+            metrics::record(AuthEvent::ServerKeyPathDenied);
+            tracing::warn!(path = %request.uri(), "server key not accepted on this path");
+            return Err(StatusCode::FORBIDDEN);
         }
+        tracing::debug!(
+            path = %request.uri(),
+            "server key not accepted on this path; trying Authorization header",
+        );
+    }
 
-        // 2. Fall back to Authorization: Bearer JWT
-        let auth_header = request
-            .headers()
-            .get("authorization")
-            // arch-lint: allow(no-silent-result-drop) reason="non-ASCII Authorization header is malformed; treating as absent triggers 401"
-            .and_then(|v| v.to_str().ok())
-            .ok_or_else(|| {
-                tracing::warn!(path = %request.uri(), "missing authentication");
-                StatusCode::UNAUTHORIZED
-            })?;
-
-        let token = auth_header.strip_prefix("Bearer ").ok_or_else(|| {
-            tracing::warn!(path = %request.uri(), "malformed Authorization header");
+    // 2. Fall back to Authorization: Bearer JWT
+    let auth_header = request
+        .headers()
+        .get("authorization")
+        // arch-lint: allow(no-silent-result-drop) reason="non-ASCII Authorization header is malformed; treating as absent triggers 401"
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| {
+            metrics::record(AuthEvent::MissingCredentials);
+            tracing::warn!(path = %request.uri(), "missing authentication");
             StatusCode::UNAUTHORIZED
         })?;
 
-        let mut validation = Validation::new(Algorithm::HS256);
-        validation.set_audience(&["authenticated"]);
+    let token = auth_header.strip_prefix("Bearer ").ok_or_else(|| {
+        metrics::record(AuthEvent::MalformedHeader);
+        tracing::warn!(path = %request.uri(), "malformed Authorization header");
+        StatusCode::UNAUTHORIZED
+    })?;
 
-        let key = DecodingKey::from_secret(state.supabase_jwt_secret.expose_secret().as_bytes());
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.set_audience(&["authenticated"]);
 
-        let token_data = decode::<SupabaseClaims>(token, &key, &validation).map_err(|e| {
-            tracing::warn!(path = %request.uri(), error = %e, "JWT verification failed");
-            StatusCode::UNAUTHORIZED
-        })?;
+    let key = DecodingKey::from_secret(state.supabase_jwt_secret.expose_secret().as_bytes());
 
-        let claims = token_data.claims;
-        let identity = if claims.role.as_deref() == Some("service_role") {
-            tracing::debug!(sub = %claims.sub, "authenticated as service_role");
-            AuthIdentity::ServiceRole(claims.clone())
-        } else {
-            tracing::debug!(sub = %claims.sub, "authenticated as user");
-            AuthIdentity::User(claims.clone())
-        };
+    let token_data = decode::<SupabaseClaims>(token, &key, &validation).map_err(|e| {
+        metrics::record(AuthEvent::InvalidJwt);
+        tracing::warn!(path = %request.uri(), error = %e, "JWT verification failed");
+        StatusCode::UNAUTHORIZED
+    })?;
 
-        // Attach both AuthIdentity and SupabaseClaims (backward compat)
-        request.extensions_mut().insert(identity);
-        request.extensions_mut().insert(claims);
+    let claims = token_data.claims;
+    if let Err(reason) = check_issuer(&claims, &state.supabase_url, options.issuer_check) {
+        metrics::record(AuthEvent::InvalidIssuer);
+        tracing::warn!(path = %request.uri(), iss = ?claims.iss, "JWT rejected: {reason}");
+        return Err(StatusCode::UNAUTHORIZED);
+    }
 
-        Ok(next.run(request).await)
+    let identity = if claims.role.as_deref() == Some("service_role") {
+        metrics::record(AuthEvent::ServiceRole);
+        tracing::debug!(sub = %claims.sub, "authenticated as service_role");
+        AuthIdentity::ServiceRole(claims.clone())
+    } else {
+        metrics::record(AuthEvent::User);
+        tracing::debug!(sub = %claims.sub, "authenticated as user");
+        AuthIdentity::User(claims.clone())
+    };
+
+    // Attach both AuthIdentity and SupabaseClaims (backward compat)
+    request.extensions_mut().insert(identity);
+    request.extensions_mut().insert(claims);
+
+    Ok(())
+}
+
+/// Check `claims.iss` against `{supabase_url}/auth/v1`, returning why a
+/// token is rejected.
+fn check_issuer(
+    claims: &SupabaseClaims,
+    supabase_url: &str,
+    issuer_check: IssuerCheck,
+) -> Result<(), &'static str> {
+    let supabase_url = supabase_url.trim().trim_end_matches('/');
+    if issuer_check == IssuerCheck::Off || supabase_url.is_empty() {
+        return Ok(());
     }
+    match &claims.iss {
+        Some(iss) if *iss == format!("{supabase_url}/auth/v1") => Ok(()),
+        Some(_) => Err("issuer does not match SUPABASE_URL"),
+        None if issuer_check == IssuerCheck::Required => Err("token has no issuer"),
+        None => {
+            tracing::warn!(
+                sub = %claims.sub,
+                "JWT has no iss claim; accepting it (use IssuerCheck::Required to reject)",
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Why [`verify_server_key`] rejected a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum ServerKeyError {
+    /// `PROPEL_SERVER_KEY` is not set, so no key is accepted.
+    #[error("PROPEL_SERVER_KEY is not configured")]
+    NotConfigured,
+    /// The key does not match `PROPEL_SERVER_KEY`.
+    #[error("invalid server key")]
+    Mismatch,
 }
 
-/// Constant-time byte comparison to prevent timing attacks on server key validation.
-fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
-    if a.len() != b.len() {
-        return false;
+/// Check an `X-Server-Key` header value against `PROPEL_SERVER_KEY`.
+///
+/// This is the check [`PropelAuth::verify`] runs, exposed so custom
+/// middleware or extractors accept exactly the same keys. The comparison
+/// is [`constant_time_eq`].
+///
+/// ```rust
+/// use propel::auth::{ServerKeyError, verify_server_key};
+/// use propel::PropelState;
+/// use secrecy::SecretString;
+///
+/// let state = PropelState {
+///     supabase_url: "https://example.supabase.co".to_owned(),
+///     supabase_anon_key: SecretString::from("anon".to_owned()),
+///     supabase_jwt_secret: SecretString::from("jwt-secret".to_owned()),
+///     server_key: Some(SecretString::from("server-key".to_owned())),
+/// };
+/// assert_eq!(verify_server_key("server-key", &state), Ok(()));
+/// assert_eq!(verify_server_key("guess", &state), Err(ServerKeyError::Mismatch));
+/// ```
+pub fn verify_server_key(header_value: &str, state: &PropelState) -> Result<(), ServerKeyError> {
+    let expected = state
+        .server_key
+        .as_ref()
+        .ok_or(ServerKeyError::NotConfigured)?;
+    if constant_time_eq(header_value.as_bytes(), expected.expose_secret().as_bytes()) {
+        Ok(())
+    } else {
+        Err(ServerKeyError::Mismatch)
     }
-    a.iter()
-        .zip(b.iter())
-        .fold(0u8, |acc, (x, y)| acc | (x ^ y))
-        == 0
 }
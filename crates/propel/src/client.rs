@@ -0,0 +1,323 @@
+//! Calls from one propel service to another on Cloud Run.
+//!
+//! [`ServiceClient`] authenticates every request to a target service with
+//! one or both of ([`ServiceAuth`]):
+//!
+//! - **Server key** — `X-Server-Key` with the key both services share in
+//!   `PROPEL_SERVER_KEY`. The receiving service accepts it through
+//!   [`PropelAuth`](crate::PropelAuth), ideally only on internal paths.
+//! - **Identity token** — a Google-signed identity token for the target
+//!   URL, fetched from the metadata server and cached until shortly before
+//!   it expires. It is sent as `X-Serverless-Authorization`, which Cloud
+//!   Run checks against `roles/run.invoker` on a private service
+//!   (`[cloud_run] allow_unauthenticated = false`), leaving
+//!   `Authorization` free for a user's Supabase JWT.
+//!
+//! A private target that also checks the server key needs both: Cloud Run
+//! rejects a request without an identity token before
+//! [`PropelAuth`](crate::PropelAuth) sees `X-Server-Key`.
+//!
+//! Calling side (`api`):
+//!
+//! ```rust,no_run
+//! use propel::client::ServiceClient;
+//!
+//! # async fn run() -> Result<(), propel::SdkError> {
+//! // A public worker: server key when PROPEL_SERVER_KEY is set, identity
+//! // token otherwise.
+//! let worker = ServiceClient::from_env("https://worker-abc123-an.a.run.app");
+//! let response = worker
+//!     .post("/internal/jobs", &serde_json::json!({ "id": 42 }))
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Receiving side (`worker`), accepting the server key on `/internal`
+//! only:
+//!
+//! ```rust,no_run
+//! use axum::{Router, routing::post};
+//! use propel::{PropelAuth, PropelState};
+//!
+//! async fn enqueue() -> &'static str { "queued" }
+//!
+//! let state = PropelState::load().unwrap();
+//! let app: Router = Router::new()
+//!     .route("/internal/jobs", post(enqueue))
+//!     .layer(PropelAuth::builder().server_key_paths(["/internal"]).layer(state.clone()))
+//!     .with_state(state);
+//! ```
+
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use jsonwebtoken::{DecodingKey, Validation};
+use reqwest::{Method, RequestBuilder, Response};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::error::SdkError;
+
+/// Metadata server endpoint that mints identity tokens for the instance's
+/// service account.
+pub const METADATA_IDENTITY_URL: &str =
+    "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/identity";
+
+/// Header carrying the identity token; Cloud Run checks it instead of
+/// `Authorization` when both are present.
+pub const SERVERLESS_AUTHORIZATION_HEADER: &str = "x-serverless-authorization";
+
+/// Header carrying the shared server key, as [`PropelAuth`](crate::PropelAuth)
+/// expects it.
+pub const SERVER_KEY_HEADER: &str = "x-server-key";
+
+/// A cached identity token is fetched again once it expires within this
+/// margin.
+pub const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(300);
+
+/// How a [`ServiceClient`] authenticates its requests.
+#[derive(Debug, Clone)]
+pub enum ServiceAuth {
+    /// Send `X-Server-Key` with this key.
+    ServerKey(SecretString),
+    /// Send an identity token for the target URL from a [`TokenSource`].
+    IdentityToken,
+    /// Send both: the identity token gets the request past Cloud Run's
+    /// invoker check on a private target, and `X-Server-Key` past
+    /// [`PropelAuth`](crate::PropelAuth).
+    ServerKeyAndIdentityToken(SecretString),
+}
+
+/// A Google-signed identity token and when it expires.
+#[derive(Clone)]
+pub struct IdentityToken {
+    value: SecretString,
+    expires_at: SystemTime,
+}
+
+impl fmt::Debug for IdentityToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IdentityToken")
+            .field("value", &"[REDACTED]")
+            .field("expires_at", &self.expires_at)
+            .finish()
+    }
+}
+
+#[derive(Deserialize)]
+struct ExpiryClaim {
+    exp: u64,
+}
+
+impl IdentityToken {
+    pub fn new(value: SecretString, expires_at: SystemTime) -> Self {
+        Self { value, expires_at }
+    }
+
+    /// Wrap a JWT, reading its expiry from the `exp` claim.
+    ///
+    /// The signature is not checked: the token comes from the metadata
+    /// server, and the receiving side verifies it.
+    pub fn from_jwt(jwt: &str) -> Result<Self, SdkError> {
+        let mut validation = Validation::default();
+        validation.insecure_disable_signature_validation();
+        validation.validate_exp = false;
+        validation.validate_aud = false;
+        let claims =
+            jsonwebtoken::decode::<ExpiryClaim>(jwt, &DecodingKey::from_secret(&[]), &validation)
+                .map_err(|e| SdkError::IdentityToken(format!("not a JWT with `exp`: {e}")))?
+                .claims;
+        Ok(Self::new(
+            SecretString::from(jwt.to_owned()),
+            UNIX_EPOCH + Duration::from_secs(claims.exp),
+        ))
+    }
+
+    pub fn expires_at(&self) -> SystemTime {
+        self.expires_at
+    }
+
+    /// Whether the token is still good past the refresh margin.
+    fn is_fresh(&self, now: SystemTime) -> bool {
+        now + TOKEN_REFRESH_MARGIN < self.expires_at
+    }
+}
+
+/// Where a [`ServiceClient`] gets identity tokens; [`MetadataServer`] on
+/// Cloud Run, a stub in tests.
+pub trait TokenSource: Send + Sync {
+    /// Fetch an identity token whose audience is `audience`.
+    fn fetch(&self, audience: &str)
+    -> impl Future<Output = Result<IdentityToken, SdkError>> + Send;
+}
+
+/// Fetches identity tokens from the Cloud Run metadata server.
+#[derive(Debug, Clone, Default)]
+pub struct MetadataServer {
+    http: reqwest::Client,
+}
+
+impl MetadataServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl TokenSource for MetadataServer {
+    async fn fetch(&self, audience: &str) -> Result<IdentityToken, SdkError> {
+        let response = self
+            .http
+            .get(METADATA_IDENTITY_URL)
+            .header("Metadata-Flavor", "Google")
+            .query(&[("audience", audience), ("format", "full")])
+            .send()
+            .await
+            .map_err(|e| SdkError::IdentityToken(e.to_string()))?;
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| SdkError::IdentityToken(e.to_string()))?;
+        if !status.is_success() {
+            return Err(SdkError::IdentityToken(format!(
+                "metadata server answered {status}: {}",
+                body.trim()
+            )));
+        }
+        IdentityToken::from_jwt(body.trim())
+    }
+}
+
+/// HTTP client for one target service, attaching [`ServiceAuth`] to every
+/// request. Clones share the connection pool and the cached token.
+#[derive(Debug)]
+pub struct ServiceClient<S = MetadataServer> {
+    base_url: Arc<str>,
+    auth: ServiceAuth,
+    http: reqwest::Client,
+    tokens: Arc<S>,
+    cached: Arc<Mutex<Option<IdentityToken>>>,
+}
+
+impl<S> Clone for ServiceClient<S> {
+    fn clone(&self) -> Self {
+        Self {
+            base_url: Arc::clone(&self.base_url),
+            auth: self.auth.clone(),
+            http: self.http.clone(),
+            tokens: Arc::clone(&self.tokens),
+            cached: Arc::clone(&self.cached),
+        }
+    }
+}
+
+impl ServiceClient {
+    /// Client for the service at `base_url` (its Cloud Run URL, which is
+    /// also the identity token audience).
+    pub fn new(base_url: impl Into<String>, auth: ServiceAuth) -> Self {
+        Self::with_token_source(base_url, auth, MetadataServer::new())
+    }
+
+    /// Like [`new`](Self::new), with the server key from
+    /// `PROPEL_SERVER_KEY` when it is set, and identity tokens otherwise.
+    ///
+    /// The server key alone only reaches a public target
+    /// (`allow_unauthenticated = true`): a private one answers 403 before
+    /// the key is checked. Call a private target with
+    /// [`ServiceAuth::ServerKeyAndIdentityToken`] or
+    /// [`ServiceAuth::IdentityToken`] instead.
+    pub fn from_env(base_url: impl Into<String>) -> Self {
+        let auth = match std::env::var("PROPEL_SERVER_KEY") {
+            Ok(key) if !key.trim().is_empty() => ServiceAuth::ServerKey(SecretString::from(key)),
+            _ => ServiceAuth::IdentityToken,
+        };
+        Self::new(base_url, auth)
+    }
+}
+
+impl<S: TokenSource> ServiceClient<S> {
+    /// Client that takes identity tokens from `tokens`.
+    pub fn with_token_source(base_url: impl Into<String>, auth: ServiceAuth, tokens: S) -> Self {
+        let base_url: String = base_url.into();
+        Self {
+            base_url: base_url.trim_end_matches('/').into(),
+            auth,
+            http: reqwest::Client::new(),
+            tokens: Arc::new(tokens),
+            cached: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// A request to `path` (starting with `/`) with authentication
+    /// attached, for methods and options the helpers do not cover.
+    pub async fn request(&self, method: Method, path: &str) -> Result<RequestBuilder, SdkError> {
+        let request = self
+            .http
+            .request(method, format!("{}{path}", self.base_url));
+        Ok(match &self.auth {
+            ServiceAuth::ServerKey(key) => request.header(SERVER_KEY_HEADER, key.expose_secret()),
+            ServiceAuth::IdentityToken => self.with_identity_token(request).await?,
+            ServiceAuth::ServerKeyAndIdentityToken(key) => self
+                .with_identity_token(request)
+                .await?
+                .header(SERVER_KEY_HEADER, key.expose_secret()),
+        })
+    }
+
+    async fn with_identity_token(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<RequestBuilder, SdkError> {
+        let token = self.identity_token().await?;
+        Ok(request.header(
+            SERVERLESS_AUTHORIZATION_HEADER,
+            format!("Bearer {}", token.expose_secret()),
+        ))
+    }
+
+    /// `GET path`. Error statuses are returned as responses; use
+    /// [`Response::error_for_status`] to turn them into errors.
+    pub async fn get(&self, path: &str) -> Result<Response, SdkError> {
+        Ok(self.request(Method::GET, path).await?.send().await?)
+    }
+
+    /// `POST path` with `body` as JSON.
+    pub async fn post<B: Serialize + ?Sized>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<Response, SdkError> {
+        Ok(self
+            .request(Method::POST, path)
+            .await?
+            .json(body)
+            .send()
+            .await?)
+    }
+
+    /// The cached token, fetched again near expiry. The lock is held across
+    /// the fetch so concurrent requests share one.
+    async fn identity_token(&self) -> Result<SecretString, SdkError> {
+        let mut cached = self.cached.lock().await;
+        if let Some(token) = cached.as_ref().filter(|t| t.is_fresh(SystemTime::now())) {
+            return Ok(token.value.clone());
+        }
+        let token = self.tokens.fetch(&self.base_url).await?;
+        tracing::debug!(
+            audience = %self.base_url,
+            expires_at = ?token.expires_at,
+            "fetched identity token"
+        );
+        let value = token.value.clone();
+        *cached = Some(token);
+        Ok(value)
+    }
+}
@@ -0,0 +1,31 @@
+//! Timing-safe primitives shared by the auth middleware.
+//!
+//! Reuse these in custom middleware or extractors (webhook signatures,
+//! API keys) instead of comparing secrets with `==`, which returns at the
+//! first differing byte.
+
+use std::hint::black_box;
+
+/// Compare two byte strings without leaking where they differ.
+///
+/// Returns the same result as `a == b`. The running time depends only on
+/// the length of the longer input: every byte position up to it is
+/// visited, and a length mismatch is folded into the result instead of
+/// returning early. Pass the secret as `b` and keep its length fixed
+/// (e.g. compare hex digests), so the only length an attacker can vary is
+/// their own.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    let len = a.len().max(b.len());
+    // Non-zero when the lengths differ, without branching on it.
+    let mut diff = a.len() ^ b.len();
+    for i in 0..len {
+        // Past the end of the shorter input, compare against zero; the
+        // length mismatch already makes `diff` non-zero.
+        // arch-lint: allow(no-silent-result-drop) reason="Option: None = index past the shorter input; 0 is a padding byte, not a dropped error"
+        let x = a.get(i).copied().unwrap_or(0);
+        // arch-lint: allow(no-silent-result-drop) reason="Option: None = index past the shorter input; 0 is a padding byte, not a dropped error"
+        let y = b.get(i).copied().unwrap_or(0);
+        diff |= usize::from(black_box(x ^ y));
+    }
+    black_box(diff) == 0
+}
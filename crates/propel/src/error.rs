@@ -11,4 +11,10 @@ pub enum SdkError {
 
     #[error("failed to fetch JWKS: {0}")]
     JwksFetch(String),
+
+    #[error("failed to fetch identity token: {0}")]
+    IdentityToken(String),
+
+    #[error("service request failed: {0}")]
+    ServiceRequest(#[from] reqwest::Error),
 }
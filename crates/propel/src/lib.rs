@@ -24,9 +24,19 @@
 //! ```
 
 pub mod auth;
+pub mod client;
+pub mod crypto;
 pub mod error;
+pub mod metrics;
 pub mod state;
+pub mod warmup;
 
-pub use auth::{AuthIdentity, PropelAuth, SupabaseClaims};
+pub use auth::{
+    AuthIdentity, IssuerCheck, PropelAuth, PropelAuthBuilder, PropelAuthLayer, ServerKeyError,
+    SupabaseClaims, verify_server_key,
+};
+pub use client::{ServiceAuth, ServiceClient};
 pub use error::SdkError;
+pub use metrics::{AuthMetrics, auth_metrics};
 pub use state::PropelState;
+pub use warmup::{WARMUP_PATH, WarmupState, warmup_handler};
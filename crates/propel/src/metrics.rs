@@ -0,0 +1,162 @@
+//! Counters for the auth middleware.
+//!
+//! [`PropelAuth`](crate::PropelAuth) counts every request it authenticates
+//! or rejects. Read the counters with [`auth_metrics`], or mount
+//! [`auth_metrics_handler`] to expose them in the Prometheus text format:
+//!
+//! ```rust,no_run
+//! use axum::{Router, routing::get};
+//!
+//! let app: Router = Router::new().route("/metrics", get(propel::metrics::auth_metrics_handler));
+//! ```
+//!
+//! Counters are per process. On Cloud Run each instance counts only the
+//! requests it served and starts from zero when it is started, so a scrape
+//! sees one instance at a time: alert on rates, not totals, or aggregate
+//! across instances (e.g. via log-based metrics).
+
+use std::fmt;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use axum::http::header;
+use axum::response::IntoResponse;
+
+/// Everything the middleware counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AuthEvent {
+    User,
+    ServiceRole,
+    ServerKey,
+    /// Neither `X-Server-Key` nor `Authorization` was sent.
+    MissingCredentials,
+    /// `Authorization` without a `Bearer ` token.
+    MalformedHeader,
+    /// The JWT failed signature, audience, or expiry checks.
+    InvalidJwt,
+    /// The JWT `iss` does not belong to `SUPABASE_URL`.
+    InvalidIssuer,
+    InvalidServerKey,
+    /// `X-Server-Key` sent but `PROPEL_SERVER_KEY` is not set.
+    ServerKeyNotConfigured,
+    /// `X-Server-Key` sent on a path outside `server_key_paths`.
+    ServerKeyPathDenied,
+}
+
+const EVENT_COUNT: usize = 10;
+
+static COUNTERS: OnceLock<[AtomicU64; EVENT_COUNT]> = OnceLock::new();
+
+fn counters() -> &'static [AtomicU64; EVENT_COUNT] {
+    COUNTERS.get_or_init(|| std::array::from_fn(|_| AtomicU64::new(0)))
+}
+
+/// Count one request.
+pub(crate) fn record(event: AuthEvent) {
+    counters()[event as usize].fetch_add(1, Ordering::Relaxed);
+}
+
+/// Snapshot of the auth counters since the process started.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AuthMetrics {
+    /// Requests authenticated with a user JWT.
+    pub user: u64,
+    /// Requests authenticated with a `service_role` JWT.
+    pub service_role: u64,
+    /// Requests authenticated with `X-Server-Key`.
+    pub server_key: u64,
+    /// Rejections: no credentials at all (401).
+    pub missing_credentials: u64,
+    /// Rejections: `Authorization` is not `Bearer <token>` (401).
+    pub malformed_header: u64,
+    /// Rejections: JWT verification failed (401).
+    pub invalid_jwt: u64,
+    /// Rejections: JWT issued for another Supabase project, or without
+    /// `iss` under [`IssuerCheck::Required`](crate::auth::IssuerCheck) (401).
+    pub invalid_issuer: u64,
+    /// Rejections: wrong `X-Server-Key` (401).
+    pub invalid_server_key: u64,
+    /// Rejections: `X-Server-Key` sent but none is configured (401).
+    pub server_key_not_configured: u64,
+    /// Rejections: `X-Server-Key` on a path it is not accepted on (403).
+    pub server_key_path_denied: u64,
+}
+
+impl AuthMetrics {
+    /// Total rejected requests, over every reason.
+    pub fn failures(&self) -> u64 {
+        self.failure_rows().iter().map(|(_, n)| n).sum()
+    }
+
+    fn success_rows(&self) -> [(&'static str, u64); 3] {
+        [
+            ("user", self.user),
+            ("service_role", self.service_role),
+            ("server_key", self.server_key),
+        ]
+    }
+
+    fn failure_rows(&self) -> [(&'static str, u64); 7] {
+        [
+            ("missing_credentials", self.missing_credentials),
+            ("malformed_header", self.malformed_header),
+            ("invalid_jwt", self.invalid_jwt),
+            ("invalid_issuer", self.invalid_issuer),
+            ("invalid_server_key", self.invalid_server_key),
+            ("server_key_not_configured", self.server_key_not_configured),
+            ("server_key_path_denied", self.server_key_path_denied),
+        ]
+    }
+}
+
+/// Prometheus text exposition format.
+impl fmt::Display for AuthMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "# HELP propel_auth_success_total Requests authenticated by PropelAuth."
+        )?;
+        writeln!(f, "# TYPE propel_auth_success_total counter")?;
+        for (method, n) in self.success_rows() {
+            writeln!(f, "propel_auth_success_total{{method=\"{method}\"}} {n}")?;
+        }
+        writeln!(
+            f,
+            "# HELP propel_auth_failure_total Requests rejected by PropelAuth."
+        )?;
+        writeln!(f, "# TYPE propel_auth_failure_total counter")?;
+        for (reason, n) in self.failure_rows() {
+            writeln!(f, "propel_auth_failure_total{{reason=\"{reason}\"}} {n}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Read the current auth counters of this process.
+pub fn auth_metrics() -> AuthMetrics {
+    let c = counters();
+    let get = |event: AuthEvent| c[event as usize].load(Ordering::Relaxed);
+    AuthMetrics {
+        user: get(AuthEvent::User),
+        service_role: get(AuthEvent::ServiceRole),
+        server_key: get(AuthEvent::ServerKey),
+        missing_credentials: get(AuthEvent::MissingCredentials),
+        malformed_header: get(AuthEvent::MalformedHeader),
+        invalid_jwt: get(AuthEvent::InvalidJwt),
+        invalid_issuer: get(AuthEvent::InvalidIssuer),
+        invalid_server_key: get(AuthEvent::InvalidServerKey),
+        server_key_not_configured: get(AuthEvent::ServerKeyNotConfigured),
+        server_key_path_denied: get(AuthEvent::ServerKeyPathDenied),
+    }
+}
+
+/// Axum handler serving [`auth_metrics`] as Prometheus text.
+///
+/// Mount it outside [`PropelAuth`](crate::PropelAuth), or behind it if
+/// the scraper can authenticate.
+pub async fn auth_metrics_handler() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        auth_metrics().to_string(),
+    )
+}
@@ -0,0 +1,100 @@
+//! Warm-up endpoint for Cloud Run startup probes.
+//!
+//! Mount [`warmup_handler`] at [`WARMUP_PATH`] and set
+//! `[cloud_run].warmup_handler = true` in `propel.toml`: the startup probe
+//! then calls it, so the instance gets no traffic until the warm-up (e.g.
+//! opening a database pool) has succeeded once.
+//!
+//! ```rust,no_run
+//! use axum::Router;
+//!
+//! async fn open_pool() -> Result<(), std::io::Error> { Ok(()) }
+//!
+//! let app: Router = Router::new()
+//!     .route(propel::WARMUP_PATH, propel::warmup_handler(|| async { open_pool().await }));
+//! ```
+//!
+//! The warm-up runs on the first request and again after each failure;
+//! once it succeeds, requests answer `200` without running it. Concurrent
+//! requests wait for the run in progress instead of starting another.
+
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{MethodRouter, get};
+use tokio::sync::OnceCell;
+
+/// Route the generated startup probe targets.
+pub const WARMUP_PATH: &str = "/warmup";
+
+/// Whether the instance has warmed up. Clones share the state.
+#[derive(Debug, Clone, Default)]
+pub struct WarmupState {
+    warmed: Arc<OnceCell<()>>,
+}
+
+impl WarmupState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether a warm-up has succeeded.
+    pub fn is_warmed(&self) -> bool {
+        self.warmed.initialized()
+    }
+
+    /// Run `warm` unless a warm-up already succeeded. A failure leaves the
+    /// state cold, so the next call runs it again.
+    pub async fn warm<F, Fut, T, E>(&self, warm: F) -> Result<(), E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        self.warmed
+            .get_or_try_init(|| async { warm().await.map(|_| ()) })
+            .await
+            .map(|_| ())
+    }
+
+    /// GET handler that warms up with `warm` and answers `200` once warm,
+    /// `503` while the warm-up fails. The error is logged, not returned.
+    pub fn handler<F, Fut, T, E, S>(&self, warm: F) -> MethodRouter<S>
+    where
+        F: Fn() -> Fut + Clone + Send + Sync + 'static,
+        Fut: Future<Output = Result<T, E>> + Send,
+        E: fmt::Display,
+        S: Clone + Send + Sync + 'static,
+    {
+        let state = self.clone();
+        get(move || {
+            let state = state.clone();
+            let warm = warm.clone();
+            async move { warmup_response(state.warm(warm).await) }
+        })
+    }
+}
+
+/// [`WarmupState::handler`] over a fresh state, for apps that don't check
+/// [`WarmupState::is_warmed`] themselves.
+pub fn warmup_handler<F, Fut, T, E, S>(warm: F) -> MethodRouter<S>
+where
+    F: Fn() -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = Result<T, E>> + Send,
+    E: fmt::Display,
+    S: Clone + Send + Sync + 'static,
+{
+    WarmupState::new().handler(warm)
+}
+
+fn warmup_response<E: fmt::Display>(result: Result<(), E>) -> Response {
+    match result {
+        Ok(()) => (StatusCode::OK, "warm").into_response(),
+        Err(e) => {
+            tracing::warn!(error = %e, "warm-up failed");
+            (StatusCode::SERVICE_UNAVAILABLE, "warming up").into_response()
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use axum::{
+    Json, Router,
+    http::{HeaderMap, StatusCode},
+    routing::{get, post},
+};
+use jsonwebtoken::{EncodingKey, Header};
+use propel::client::{IdentityToken, TokenSource};
+use propel::{PropelAuth, PropelState, SdkError, ServiceAuth, ServiceClient};
+use secrecy::SecretString;
+use serde_json::{Value, json};
+
+/// Token source that counts fetches and hands out tokens living
+/// `lifetime`, or fails when `lifetime` is `None`.
+#[derive(Clone)]
+struct StubTokens {
+    fetches: Arc<AtomicU32>,
+    lifetime: Option<Duration>,
+}
+
+impl StubTokens {
+    fn new(lifetime: Option<Duration>) -> Self {
+        Self {
+            fetches: Arc::new(AtomicU32::new(0)),
+            lifetime,
+        }
+    }
+
+    fn fetches(&self) -> u32 {
+        self.fetches.load(Ordering::SeqCst)
+    }
+}
+
+impl TokenSource for StubTokens {
+    async fn fetch(&self, audience: &str) -> Result<IdentityToken, SdkError> {
+        let n = self.fetches.fetch_add(1, Ordering::SeqCst) + 1;
+        let lifetime = self
+            .lifetime
+            .ok_or_else(|| SdkError::IdentityToken("metadata server unreachable".to_owned()))?;
+        Ok(IdentityToken::new(
+            SecretString::from(format!("token-{n}-for-{audience}")),
+            SystemTime::now() + lifetime,
+        ))
+    }
+}
+
+/// Serve `app` on a local port and return its base URL.
+async fn serve(app: Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{addr}")
+}
+
+/// Echoes the auth headers and the JSON body it received.
+async fn echo_server() -> String {
+    async fn headers(headers: HeaderMap) -> Json<Value> {
+        let header = |name: &str| headers.get(name).map(|v| v.to_str().unwrap().to_owned());
+        Json(json!({
+            "server_key": header("x-server-key"),
+            "serverless": header("x-serverless-authorization"),
+            "authorization": header("authorization"),
+        }))
+    }
+    async fn echo(headers: HeaderMap, Json(body): Json<Value>) -> Json<Value> {
+        Json(json!({
+            "server_key": headers.get("x-server-key").map(|v| v.to_str().unwrap().to_owned()),
+            "body": body,
+        }))
+    }
+    serve(
+        Router::new()
+            .route("/headers", get(headers))
+            .route("/echo", post(echo)),
+    )
+    .await
+}
+
+async fn json_of(response: reqwest::Response) -> Value {
+    assert_eq!(response.status(), 200);
+    response.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn server_key_is_sent_without_fetching_tokens() {
+    let url = echo_server().await;
+    let tokens = StubTokens::new(Some(Duration::from_secs(3600)));
+    let client = ServiceClient::with_token_source(
+        format!("{url}/"),
+        ServiceAuth::ServerKey(SecretString::from("shared-key".to_owned())),
+        tokens.clone(),
+    );
+
+    let headers = json_of(client.get("/headers").await.unwrap()).await;
+    assert_eq!(headers["server_key"], "shared-key");
+    assert_eq!(headers["serverless"], Value::Null);
+
+    let echoed = json_of(client.post("/echo", &json!({ "id": 42 })).await.unwrap()).await;
+    assert_eq!(
+        echoed,
+        json!({ "server_key": "shared-key", "body": { "id": 42 } })
+    );
+    assert_eq!(tokens.fetches(), 0);
+}
+
+#[tokio::test]
+async fn identity_token_is_cached_and_sent_as_serverless_authorization() {
+    let url = echo_server().await;
+    let tokens = StubTokens::new(Some(Duration::from_secs(3600)));
+    let client =
+        ServiceClient::with_token_source(url.clone(), ServiceAuth::IdentityToken, tokens.clone());
+
+    let first = json_of(client.get("/headers").await.unwrap()).await;
+    let second = json_of(client.clone().get("/headers").await.unwrap()).await;
+
+    let expected = format!("Bearer token-1-for-{url}");
+    assert_eq!(first["serverless"], expected.as_str());
+    assert_eq!(second["serverless"], expected.as_str());
+    assert_eq!(first["authorization"], Value::Null);
+    assert_eq!(first["server_key"], Value::Null);
+    assert_eq!(tokens.fetches(), 1, "clones share the cached token");
+}
+
+#[tokio::test]
+async fn server_key_and_identity_token_are_both_sent() {
+    let url = echo_server().await;
+    let tokens = StubTokens::new(Some(Duration::from_secs(3600)));
+    let client = ServiceClient::with_token_source(
+        url.clone(),
+        ServiceAuth::ServerKeyAndIdentityToken(SecretString::from("shared-key".to_owned())),
+        tokens.clone(),
+    );
+
+    let headers = json_of(client.get("/headers").await.unwrap()).await;
+    assert_eq!(headers["server_key"], "shared-key");
+    assert_eq!(
+        headers["serverless"],
+        format!("Bearer token-1-for-{url}").as_str()
+    );
+    assert_eq!(headers["authorization"], Value::Null);
+    assert_eq!(tokens.fetches(), 1);
+}
+
+#[tokio::test]
+async fn identity_token_is_refetched_near_expiry() {
+    let url = echo_server().await;
+    // Inside the refresh margin from the start.
+    let tokens = StubTokens::new(Some(Duration::from_secs(60)));
+    let client = ServiceClient::with_token_source(url, ServiceAuth::IdentityToken, tokens.clone());
+
+    client.get("/headers").await.unwrap();
+    let headers = json_of(client.get("/headers").await.unwrap()).await;
+
+    assert!(
+        headers["serverless"]
+            .as_str()
+            .unwrap()
+            .starts_with("Bearer token-2-"),
+        "{headers}"
+    );
+    assert_eq!(tokens.fetches(), 2);
+}
+
+#[tokio::test]
+async fn token_fetch_failure_is_an_error_and_not_cached() {
+    let tokens = StubTokens::new(None);
+    let client = ServiceClient::with_token_source(
+        "http://127.0.0.1:9",
+        ServiceAuth::IdentityToken,
+        tokens.clone(),
+    );
+
+    for _ in 0..2 {
+        let err = client.get("/headers").await.unwrap_err();
+        assert!(matches!(err, SdkError::IdentityToken(_)), "{err:?}");
+    }
+    assert_eq!(tokens.fetches(), 2);
+}
+
+#[test]
+fn identity_token_reads_expiry_from_jwt() {
+    let exp = 2_000_000_000u64;
+    let jwt = jsonwebtoken::encode(
+        &Header::new(jsonwebtoken::Algorithm::HS256),
+        &json!({ "aud": "https://worker.a.run.app", "exp": exp }),
+        &EncodingKey::from_secret(b"not-googles-key"),
+    )
+    .unwrap();
+
+    let token = IdentityToken::from_jwt(&jwt).unwrap();
+    assert_eq!(token.expires_at(), UNIX_EPOCH + Duration::from_secs(exp));
+    assert!(!format!("{token:?}").contains(&jwt));
+
+    for bad in ["", "not-a-jwt", "a.b.c"] {
+        let err = IdentityToken::from_jwt(bad).unwrap_err();
+        assert!(
+            matches!(err, SdkError::IdentityToken(_)),
+            "{bad:?}: {err:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn server_key_client_passes_propel_auth_on_internal_paths() {
+    let key = "shared-key";
+    let state = PropelState {
+        supabase_url: String::new(),
+        supabase_anon_key: SecretString::from("anon".to_owned()),
+        supabase_jwt_secret: SecretString::from("jwt-secret".to_owned()),
+        server_key: Some(SecretString::from(key.to_owned())),
+    };
+    let app = Router::new()
+        .route("/internal/jobs", post(|| async { "queued" }))
+        .route("/api/me", get(|| async { "me" }))
+        .layer(
+            PropelAuth::builder()
+                .server_key_paths(["/internal"])
+                .layer(state.clone()),
+        )
+        .with_state(state.clone());
+    let url = serve(app).await;
+
+    let worker = ServiceClient::new(
+        url.clone(),
+        ServiceAuth::ServerKey(SecretString::from(key.to_owned())),
+    );
+    let response = worker.post("/internal/jobs", &json!({})).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.text().await.unwrap(), "queued");
+    // The server key is not accepted outside `/internal`.
+    let response = worker.get("/api/me").await.unwrap();
+    assert!(response.status().is_client_error());
+
+    let stranger = ServiceClient::new(
+        url,
+        ServiceAuth::ServerKey(SecretString::from("guess".to_owned())),
+    );
+    let response = stranger.post("/internal/jobs", &json!({})).await.unwrap();
+    assert!(response.status().is_client_error());
+}
+
+#[test]
+fn from_env_picks_server_key_when_configured() {
+    // SAFETY: no other test in this binary reads or writes the environment.
+    unsafe { std::env::set_var("PROPEL_SERVER_KEY", "from-env") };
+    let client = ServiceClient::from_env("https://worker.a.run.app/");
+    // SAFETY: as above.
+    unsafe { std::env::remove_var("PROPEL_SERVER_KEY") };
+    let debug = format!("{client:?}");
+    assert!(debug.contains("ServerKey"), "{debug}");
+    assert!(!debug.contains("from-env"), "{debug}");
+    assert_eq!(client.base_url(), "https://worker.a.run.app");
+
+    let client = ServiceClient::from_env("https://worker.a.run.app");
+    assert!(format!("{client:?}").contains("IdentityToken"));
+}
@@ -0,0 +1,55 @@
+use propel::crypto::constant_time_eq;
+use proptest::prelude::*;
+
+#[test]
+fn equal_inputs_match() {
+    assert!(constant_time_eq(b"", b""));
+    assert!(constant_time_eq(b"server-key", b"server-key"));
+}
+
+#[test]
+fn differing_inputs_do_not_match() {
+    assert!(!constant_time_eq(b"server-key", b"server-kez"));
+    assert!(!constant_time_eq(b"x", b"y"));
+}
+
+#[test]
+fn prefix_and_zero_padding_do_not_match() {
+    // The shorter input is padded with zero bytes internally; the length
+    // difference must still be detected.
+    assert!(!constant_time_eq(b"key", b"key\0"));
+    assert!(!constant_time_eq(b"key\0", b"key"));
+    assert!(!constant_time_eq(b"", b"\0"));
+    assert!(!constant_time_eq(b"", b"key"));
+}
+
+proptest! {
+    #[test]
+    fn matches_eq_for_arbitrary_bytes(
+        a in proptest::collection::vec(any::<u8>(), 0..64),
+        b in proptest::collection::vec(any::<u8>(), 0..64),
+    ) {
+        prop_assert_eq!(constant_time_eq(&a, &b), a == b);
+    }
+
+    #[test]
+    fn matches_eq_for_near_misses(
+        a in proptest::collection::vec(any::<u8>(), 1..64),
+        index in any::<prop::sample::Index>(),
+        flip in 1u8..,
+    ) {
+        let mut b = a.clone();
+        let i = index.index(b.len());
+        b[i] ^= flip;
+        prop_assert!(constant_time_eq(&a, &a));
+        prop_assert!(!constant_time_eq(&a, &b));
+    }
+
+    #[test]
+    fn is_symmetric(
+        a in proptest::collection::vec(any::<u8>(), 0..32),
+        b in proptest::collection::vec(any::<u8>(), 0..32),
+    ) {
+        prop_assert_eq!(constant_time_eq(&a, &b), constant_time_eq(&b, &a));
+    }
+}
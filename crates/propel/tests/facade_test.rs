@@ -7,7 +7,9 @@ use axum::{
 };
 use http_body_util::BodyExt;
 use jsonwebtoken::{Algorithm, EncodingKey, Header};
-use propel::auth::{AuthIdentity, PropelAuth, SupabaseClaims};
+use propel::auth::{
+    AuthIdentity, IssuerCheck, PropelAuth, ServerKeyError, SupabaseClaims, verify_server_key,
+};
 use propel::state::PropelState;
 use secrecy::SecretString;
 use tower::ServiceExt;
@@ -47,6 +49,7 @@ fn valid_claims() -> SupabaseClaims {
         iat: now,
         exp: now + 3600,
         aud: "authenticated".to_owned(),
+        iss: None,
     }
 }
 
@@ -59,6 +62,7 @@ fn service_role_claims() -> SupabaseClaims {
         iat: now,
         exp: now + 3600,
         aud: "authenticated".to_owned(),
+        iss: None,
     }
 }
 
@@ -532,3 +536,316 @@ async fn empty_server_key_header_returns_401() {
 
     assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
 }
+
+// ── verify_server_key ──
+
+#[test]
+fn verify_server_key_accepts_configured_key() {
+    assert_eq!(verify_server_key(TEST_SERVER_KEY, &test_state()), Ok(()));
+}
+
+#[test]
+fn verify_server_key_rejects_wrong_and_truncated_keys() {
+    let state = test_state();
+    assert_eq!(
+        verify_server_key("wrong-key", &state),
+        Err(ServerKeyError::Mismatch)
+    );
+    assert_eq!(
+        verify_server_key(&TEST_SERVER_KEY[..TEST_SERVER_KEY.len() - 1], &state),
+        Err(ServerKeyError::Mismatch)
+    );
+    assert_eq!(verify_server_key("", &state), Err(ServerKeyError::Mismatch));
+}
+
+#[test]
+fn verify_server_key_without_configured_key() {
+    assert_eq!(
+        verify_server_key(TEST_SERVER_KEY, &test_state_no_server_key()),
+        Err(ServerKeyError::NotConfigured)
+    );
+}
+
+// ── Server key path scoping ──
+
+fn build_scoped_app(state: PropelState, server_key_paths: &[&str]) -> Router {
+    Router::new()
+        .route("/internal", get(|| async { "ok" }))
+        .route("/internal/jobs", get(|| async { "ok" }))
+        .route("/internals", get(|| async { "ok" }))
+        .route("/health/deep", get(|| async { "ok" }))
+        .route("/health/deep/more", get(|| async { "ok" }))
+        .route("/protected", get(|| async { "ok" }))
+        .layer(
+            PropelAuth::builder()
+                .server_key_paths(server_key_paths)
+                .layer(state.clone()),
+        )
+        .with_state(state)
+}
+
+async fn server_key_status(app: Router, uri: &str, key: &str) -> StatusCode {
+    app.oneshot(
+        Request::builder()
+            .uri(uri)
+            .header("x-server-key", key)
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await
+    .unwrap()
+    .status()
+}
+
+#[tokio::test]
+async fn scoped_server_key_allowed_under_prefix() {
+    for uri in ["/internal", "/internal/jobs"] {
+        let app = build_scoped_app(test_state(), &["/internal"]);
+        assert_eq!(
+            server_key_status(app, uri, TEST_SERVER_KEY).await,
+            StatusCode::OK,
+            "{uri}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn scoped_server_key_prefix_respects_segment_boundary() {
+    let app = build_scoped_app(test_state(), &["/internal/"]);
+    assert_eq!(
+        server_key_status(app, "/internals", TEST_SERVER_KEY).await,
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[tokio::test]
+async fn scoped_server_key_exact_path() {
+    let app = build_scoped_app(test_state(), &["=/health/deep"]);
+    assert_eq!(
+        server_key_status(app, "/health/deep", TEST_SERVER_KEY).await,
+        StatusCode::OK
+    );
+    let app = build_scoped_app(test_state(), &["=/health/deep"]);
+    assert_eq!(
+        server_key_status(app, "/health/deep/more", TEST_SERVER_KEY).await,
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[tokio::test]
+async fn scoped_server_key_disallowed_path_returns_403_even_with_correct_key() {
+    let app = build_scoped_app(test_state(), &["/internal"]);
+    assert_eq!(
+        server_key_status(app, "/protected", TEST_SERVER_KEY).await,
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[tokio::test]
+async fn scoped_server_key_wrong_key_on_allowed_path_returns_401() {
+    let app = build_scoped_app(test_state(), &["/internal"]);
+    assert_eq!(
+        server_key_status(app, "/internal/jobs", "wrong-key").await,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn scoped_server_key_disallowed_path_falls_back_to_jwt() {
+    let app = Router::new()
+        .route(
+            "/protected",
+            get(|req: Request<Body>| async move {
+                match req.extensions().get::<AuthIdentity>().unwrap() {
+                    AuthIdentity::User(claims) => format!("user:{}", claims.sub),
+                    AuthIdentity::ServiceRole(_) => "service_role".to_owned(),
+                    AuthIdentity::ServerKey => "server_key".to_owned(),
+                }
+            }),
+        )
+        .layer(
+            PropelAuth::builder()
+                .server_key_paths(["/internal"])
+                .layer(test_state()),
+        );
+    let token = make_token(&valid_claims(), TEST_SECRET);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/protected")
+                .header("x-server-key", TEST_SERVER_KEY)
+                .header("authorization", format!("Bearer {token}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&body[..], b"user:user-123");
+}
+
+#[tokio::test]
+async fn scoped_server_key_fallback_still_verifies_jwt() {
+    let app = build_scoped_app(test_state(), &["/internal"]);
+    let token = make_token(&valid_claims(), "wrong-secret-at-least-32-chars-long!!");
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/protected")
+                .header("x-server-key", TEST_SERVER_KEY)
+                .header("authorization", format!("Bearer {token}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn builder_without_scope_accepts_server_key_everywhere() {
+    let state = test_state();
+    let app = Router::new()
+        .route("/protected", get(|| async { "ok" }))
+        .layer(PropelAuth::builder().layer(state.clone()))
+        .with_state(state);
+    assert_eq!(
+        server_key_status(app, "/protected", TEST_SERVER_KEY).await,
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn builder_layer_accepts_jwt_on_any_path() {
+    let app = build_scoped_app(test_state(), &["/internal"]);
+    let token = make_token(&valid_claims(), TEST_SECRET);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/protected")
+                .header("authorization", format!("Bearer {token}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+// ── Issuer check ──
+
+const TEST_ISSUER: &str = "https://test.supabase.co/auth/v1";
+
+fn claims_with_issuer(iss: Option<&str>) -> SupabaseClaims {
+    SupabaseClaims {
+        iss: iss.map(str::to_owned),
+        ..valid_claims()
+    }
+}
+
+async fn jwt_status(app: Router, claims: &SupabaseClaims) -> StatusCode {
+    let token = make_token(claims, TEST_SECRET);
+    app.oneshot(
+        Request::builder()
+            .uri("/protected")
+            .header("authorization", format!("Bearer {token}"))
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await
+    .unwrap()
+    .status()
+}
+
+fn build_issuer_app(issuer_check: IssuerCheck) -> Router {
+    let state = test_state();
+    Router::new()
+        .route("/protected", get(|| async { "ok" }))
+        .layer(
+            PropelAuth::builder()
+                .issuer_check(issuer_check)
+                .layer(state.clone()),
+        )
+        .with_state(state)
+}
+
+#[tokio::test]
+async fn matching_issuer_is_accepted() {
+    let claims = claims_with_issuer(Some(TEST_ISSUER));
+    assert_eq!(
+        jwt_status(build_app(test_state()), &claims).await,
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn matching_issuer_tolerates_trailing_slash_in_supabase_url() {
+    let state = PropelState {
+        supabase_url: "https://test.supabase.co/".to_owned(),
+        ..test_state()
+    };
+    let claims = claims_with_issuer(Some(TEST_ISSUER));
+    assert_eq!(jwt_status(build_app(state), &claims).await, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn mismatched_issuer_is_rejected() {
+    let claims = claims_with_issuer(Some("https://other-project.supabase.co/auth/v1"));
+    assert_eq!(
+        jwt_status(build_app(test_state()), &claims).await,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn missing_issuer_is_accepted_by_default() {
+    let claims = claims_with_issuer(None);
+    assert_eq!(
+        jwt_status(build_app(test_state()), &claims).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        jwt_status(build_issuer_app(IssuerCheck::WarnIfMissing), &claims).await,
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn missing_issuer_is_rejected_when_required() {
+    let app = build_issuer_app(IssuerCheck::Required);
+    assert_eq!(
+        jwt_status(app, &claims_with_issuer(None)).await,
+        StatusCode::UNAUTHORIZED
+    );
+    let app = build_issuer_app(IssuerCheck::Required);
+    assert_eq!(
+        jwt_status(app, &claims_with_issuer(Some(TEST_ISSUER))).await,
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn issuer_check_off_accepts_any_issuer() {
+    let claims = claims_with_issuer(Some("https://other-project.supabase.co/auth/v1"));
+    assert_eq!(
+        jwt_status(build_issuer_app(IssuerCheck::Off), &claims).await,
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn issuer_check_skipped_without_supabase_url() {
+    let state = PropelState {
+        supabase_url: String::new(),
+        ..test_state()
+    };
+    let claims = claims_with_issuer(Some("https://other-project.supabase.co/auth/v1"));
+    assert_eq!(jwt_status(build_app(state), &claims).await, StatusCode::OK);
+}
@@ -0,0 +1,222 @@
+//! Counters are process-wide, so these tests live in their own binary and
+//! run one at a time; each asserts deltas around the requests it sends.
+
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode},
+    middleware,
+    routing::get,
+};
+use http_body_util::BodyExt;
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use propel::metrics::auth_metrics_handler;
+use propel::{AuthMetrics, PropelAuth, PropelState, SupabaseClaims, auth_metrics};
+use secrecy::SecretString;
+use tower::ServiceExt;
+
+const TEST_SECRET: &str = "test-jwt-secret-at-least-32-chars-long";
+const TEST_SERVER_KEY: &str = "test-server-key-at-least-32-chars-long";
+
+static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+fn test_state(server_key: Option<&str>) -> PropelState {
+    PropelState {
+        supabase_url: "https://test.supabase.co".to_owned(),
+        supabase_anon_key: SecretString::from("anon-key".to_owned()),
+        supabase_jwt_secret: SecretString::from(TEST_SECRET.to_owned()),
+        server_key: server_key.map(|k| SecretString::from(k.to_owned())),
+    }
+}
+
+fn token(role: &str, secret: &str) -> String {
+    let now = jsonwebtoken::get_current_timestamp() as usize;
+    let claims = SupabaseClaims {
+        sub: "user-123".to_owned(),
+        email: None,
+        role: Some(role.to_owned()),
+        iat: now,
+        exp: now + 3600,
+        aud: "authenticated".to_owned(),
+        iss: None,
+    };
+    let key = EncodingKey::from_secret(secret.as_bytes());
+    jsonwebtoken::encode(&Header::new(Algorithm::HS256), &claims, &key).unwrap()
+}
+
+fn build_app(state: PropelState) -> Router {
+    Router::new()
+        .route("/protected", get(|| async { "ok" }))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            PropelAuth::verify,
+        ))
+        .with_state(state)
+}
+
+async fn send(app: Router, headers: &[(&str, &str)]) -> StatusCode {
+    let mut request = Request::builder().uri("/protected");
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    app.oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+        .status()
+}
+
+/// Counter increase from `before` to now.
+fn delta(before: AuthMetrics) -> AuthMetrics {
+    let after = auth_metrics();
+    AuthMetrics {
+        user: after.user - before.user,
+        service_role: after.service_role - before.service_role,
+        server_key: after.server_key - before.server_key,
+        missing_credentials: after.missing_credentials - before.missing_credentials,
+        malformed_header: after.malformed_header - before.malformed_header,
+        invalid_jwt: after.invalid_jwt - before.invalid_jwt,
+        invalid_issuer: after.invalid_issuer - before.invalid_issuer,
+        invalid_server_key: after.invalid_server_key - before.invalid_server_key,
+        server_key_not_configured: after.server_key_not_configured
+            - before.server_key_not_configured,
+        server_key_path_denied: after.server_key_path_denied - before.server_key_path_denied,
+    }
+}
+
+#[tokio::test]
+async fn successful_auth_is_counted_per_method() {
+    let _serial = SERIAL.lock().await;
+    let before = auth_metrics();
+    let user = format!("Bearer {}", token("authenticated", TEST_SECRET));
+    let service = format!("Bearer {}", token("service_role", TEST_SECRET));
+    let app = build_app(test_state(Some(TEST_SERVER_KEY)));
+
+    assert_eq!(
+        send(app.clone(), &[("authorization", &user)]).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        send(app.clone(), &[("authorization", &user)]).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        send(app.clone(), &[("authorization", &service)]).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        send(app, &[("x-server-key", TEST_SERVER_KEY)]).await,
+        StatusCode::OK
+    );
+
+    assert_eq!(
+        delta(before),
+        AuthMetrics {
+            user: 2,
+            service_role: 1,
+            server_key: 1,
+            ..Default::default()
+        }
+    );
+}
+
+#[tokio::test]
+async fn failures_are_counted_per_reason() {
+    let _serial = SERIAL.lock().await;
+    let before = auth_metrics();
+    let forged = format!(
+        "Bearer {}",
+        token("authenticated", "wrong-secret-at-least-32-chars!!")
+    );
+    let app = build_app(test_state(Some(TEST_SERVER_KEY)));
+
+    let cases: &[&[(&str, &str)]] = &[
+        &[],
+        &[("authorization", "Basic abc")],
+        &[("authorization", &forged)],
+        &[("x-server-key", "wrong-key")],
+    ];
+    for headers in cases {
+        assert_eq!(send(app.clone(), headers).await, StatusCode::UNAUTHORIZED);
+    }
+    let unconfigured = build_app(test_state(None));
+    assert_eq!(
+        send(unconfigured, &[("x-server-key", TEST_SERVER_KEY)]).await,
+        StatusCode::UNAUTHORIZED
+    );
+
+    let counted = delta(before);
+    assert_eq!(
+        counted,
+        AuthMetrics {
+            missing_credentials: 1,
+            malformed_header: 1,
+            invalid_jwt: 1,
+            invalid_server_key: 1,
+            server_key_not_configured: 1,
+            ..Default::default()
+        }
+    );
+    assert_eq!(counted.failures(), 5);
+}
+
+#[tokio::test]
+async fn server_key_path_denial_is_counted() {
+    let _serial = SERIAL.lock().await;
+    let before = auth_metrics();
+    let state = test_state(Some(TEST_SERVER_KEY));
+    let app = Router::new()
+        .route("/protected", get(|| async { "ok" }))
+        .layer(
+            PropelAuth::builder()
+                .server_key_paths(["/internal"])
+                .layer(state.clone()),
+        )
+        .with_state(state);
+
+    assert_eq!(
+        send(app, &[("x-server-key", TEST_SERVER_KEY)]).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        delta(before),
+        AuthMetrics {
+            server_key_path_denied: 1,
+            ..Default::default()
+        }
+    );
+}
+
+#[tokio::test]
+async fn handler_renders_prometheus_text() {
+    let _serial = SERIAL.lock().await;
+    let app = Router::new().route("/metrics", get(auth_metrics_handler));
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/metrics")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(
+        response.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/plain")
+    );
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let text = std::str::from_utf8(&body).unwrap();
+    let metrics = auth_metrics();
+    assert!(text.contains("# TYPE propel_auth_success_total counter"));
+    assert!(text.contains(&format!(
+        "propel_auth_success_total{{method=\"user\"}} {}",
+        metrics.user
+    )));
+    assert!(text.contains(&format!(
+        "propel_auth_failure_total{{reason=\"server_key_path_denied\"}} {}",
+        metrics.server_key_path_denied
+    )));
+}
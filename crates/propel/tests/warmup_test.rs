@@ -0,0 +1,104 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode},
+};
+use propel::{WARMUP_PATH, WarmupState, warmup_handler};
+use tower::ServiceExt;
+
+/// Warm-up that fails its first `failures` runs, counting every run.
+fn flaky(
+    failures: u32,
+) -> (
+    Arc<AtomicU32>,
+    impl Fn() -> std::future::Ready<Result<(), String>> + Clone + Send + Sync + 'static,
+) {
+    let runs = Arc::new(AtomicU32::new(0));
+    let counter = Arc::clone(&runs);
+    let warm = move || {
+        let run = counter.fetch_add(1, Ordering::SeqCst);
+        std::future::ready(if run < failures {
+            Err(format!("pool not ready (run {run})"))
+        } else {
+            Ok(())
+        })
+    };
+    (runs, warm)
+}
+
+async fn probe(app: &Router) -> StatusCode {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .uri(WARMUP_PATH)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .status()
+}
+
+#[tokio::test]
+async fn state_is_warmed_after_success() {
+    let state = WarmupState::new();
+    assert!(!state.is_warmed());
+
+    state
+        .warm(|| async { Ok::<_, String>("pool") })
+        .await
+        .unwrap();
+
+    assert!(state.is_warmed());
+    assert!(state.clone().is_warmed());
+}
+
+#[tokio::test]
+async fn failed_warmup_stays_cold_and_retries() {
+    let state = WarmupState::new();
+    let (runs, warm) = flaky(1);
+
+    assert!(state.warm(&warm).await.is_err());
+    assert!(!state.is_warmed());
+
+    state.warm(&warm).await.unwrap();
+    assert!(state.is_warmed());
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn warmup_runs_once_after_success() {
+    let state = WarmupState::new();
+    let (runs, warm) = flaky(0);
+
+    for _ in 0..3 {
+        state.warm(&warm).await.unwrap();
+    }
+
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn handler_answers_503_until_warm() {
+    let (runs, warm) = flaky(2);
+    let app = Router::new().route(WARMUP_PATH, warmup_handler(warm));
+
+    assert_eq!(probe(&app).await, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(probe(&app).await, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(probe(&app).await, StatusCode::OK);
+    assert_eq!(probe(&app).await, StatusCode::OK);
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn handler_shares_state_with_the_app() {
+    let state = WarmupState::new();
+    let app = Router::new().route(WARMUP_PATH, state.handler(|| async { Ok::<_, String>(()) }));
+
+    assert!(!state.is_warmed());
+    assert_eq!(probe(&app).await, StatusCode::OK);
+    assert!(state.is_warmed());
+}
@@ -0,0 +1,106 @@
+//! Example service exercising propel end to end.
+//!
+//! - `/` and `/health` are public. `/` renders `templates/index.html`,
+//!   which `[build] include` ships in the image, with values from
+//!   `[build.env]`.
+//! - `/api/me` takes any Supabase user JWT.
+//! - `/admin/metrics` takes only a `service_role` JWT.
+//! - `/internal/jobs` takes only `X-Server-Key`, which no other path
+//!   accepts.
+
+use std::path::PathBuf;
+
+use axum::{
+    Extension, Json, Router,
+    extract::State,
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    routing::{get, post},
+};
+use propel::{AuthIdentity, PropelAuth, PropelState, metrics::auth_metrics_handler};
+
+/// Settings from the environment, set by `[build.env]` when deployed.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// Directory holding `index.html` (`TEMPLATE_DIR`).
+    pub template_dir: PathBuf,
+    /// Heading of the index page (`GREETING`).
+    pub greeting: String,
+    /// Commit the image was built from (`GIT_SHA`).
+    pub git_sha: String,
+}
+
+impl Settings {
+    /// Read the settings, falling back to values that work from the
+    /// example directory with `cargo run`.
+    pub fn from_env() -> Self {
+        let var =
+            |key: &str, default: &str| std::env::var(key).unwrap_or_else(|_| default.to_owned());
+        Self {
+            template_dir: PathBuf::from(var("TEMPLATE_DIR", "templates")),
+            greeting: var("GREETING", "Hello from propel!"),
+            git_sha: var("GIT_SHA", "a local build"),
+        }
+    }
+}
+
+/// The service's router.
+pub fn app(state: PropelState, settings: Settings) -> Router {
+    let public = Router::new()
+        .route("/health", get(health))
+        .route("/", get(index))
+        .with_state(settings);
+
+    let protected = Router::new()
+        .route("/api/me", get(me))
+        .route("/admin/metrics", get(admin_metrics))
+        .route("/internal/jobs", post(run_job))
+        .layer(
+            PropelAuth::builder()
+                .server_key_paths(["/internal"])
+                .layer(state),
+        );
+
+    public.merge(protected)
+}
+
+async fn health() -> &'static str {
+    "ok"
+}
+
+async fn index(State(settings): State<Settings>) -> Result<Html<String>, StatusCode> {
+    let path = settings.template_dir.join("index.html");
+    let template = tokio::fs::read_to_string(&path).await.map_err(|e| {
+        tracing::error!(path = %path.display(), error = %e, "cannot read template");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Html(
+        template
+            .replace("{{greeting}}", &settings.greeting)
+            .replace("{{git_sha}}", &settings.git_sha),
+    ))
+}
+
+async fn me(Extension(identity): Extension<AuthIdentity>) -> Result<Response, StatusCode> {
+    match identity {
+        AuthIdentity::User(claims) | AuthIdentity::ServiceRole(claims) => Ok(Json(
+            serde_json::json!({ "sub": claims.sub, "email": claims.email, "role": claims.role }),
+        )
+        .into_response()),
+        AuthIdentity::ServerKey => Err(StatusCode::FORBIDDEN),
+    }
+}
+
+async fn admin_metrics(Extension(identity): Extension<AuthIdentity>) -> Response {
+    match identity {
+        AuthIdentity::ServiceRole(_) => auth_metrics_handler().await.into_response(),
+        AuthIdentity::User(_) | AuthIdentity::ServerKey => StatusCode::FORBIDDEN.into_response(),
+    }
+}
+
+async fn run_job(Extension(identity): Extension<AuthIdentity>) -> StatusCode {
+    match identity {
+        AuthIdentity::ServerKey => StatusCode::ACCEPTED,
+        AuthIdentity::User(_) | AuthIdentity::ServiceRole(_) => StatusCode::FORBIDDEN,
+    }
+}
@@ -0,0 +1,145 @@
+use std::path::Path;
+
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode},
+};
+use full_demo::{Settings, app};
+use http_body_util::BodyExt;
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use propel::{PropelState, SupabaseClaims};
+use propel_core::{PropelConfig, is_included, top_level_entry};
+use secrecy::SecretString;
+use tower::ServiceExt;
+
+const TEST_SECRET: &str = "test-jwt-secret-at-least-32-chars-long";
+const TEST_SERVER_KEY: &str = "test-server-key-at-least-32-chars-long";
+
+fn demo_app() -> Router {
+    let state = PropelState {
+        supabase_url: "https://test.supabase.co".to_owned(),
+        supabase_anon_key: SecretString::from("anon-key".to_owned()),
+        supabase_jwt_secret: SecretString::from(TEST_SECRET.to_owned()),
+        server_key: Some(SecretString::from(TEST_SERVER_KEY.to_owned())),
+    };
+    let settings = Settings {
+        template_dir: Path::new(env!("CARGO_MANIFEST_DIR")).join("templates"),
+        greeting: "Hello, test!".to_owned(),
+        git_sha: "0123abc".to_owned(),
+    };
+    app(state, settings)
+}
+
+fn token(role: &str) -> String {
+    let now = jsonwebtoken::get_current_timestamp() as usize;
+    let claims = SupabaseClaims {
+        sub: format!("{role}-123"),
+        aud: "authenticated".to_owned(),
+        iss: Some("https://test.supabase.co/auth/v1".to_owned()),
+        email: None,
+        role: Some(role.to_owned()),
+        exp: now + 3600,
+        iat: now,
+    };
+    let key = EncodingKey::from_secret(TEST_SECRET.as_bytes());
+    jsonwebtoken::encode(&Header::new(Algorithm::HS256), &claims, &key).unwrap()
+}
+
+async fn send(request: Request<Body>) -> (StatusCode, String) {
+    let response = demo_app().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+fn get(uri: &str) -> Request<Body> {
+    Request::get(uri).body(Body::empty()).unwrap()
+}
+
+fn get_as(uri: &str, role: &str) -> Request<Body> {
+    Request::get(uri)
+        .header("authorization", format!("Bearer {}", token(role)))
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn public_routes_need_no_credentials() {
+    assert_eq!(
+        send(get("/health")).await,
+        (StatusCode::OK, "ok".to_owned())
+    );
+
+    let (status, body) = send(get("/")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("<h1>Hello, test!</h1>"), "{body}");
+    assert!(body.contains("Built from 0123abc."), "{body}");
+}
+
+#[tokio::test]
+async fn user_routes_need_a_jwt() {
+    assert_eq!(send(get("/api/me")).await.0, StatusCode::UNAUTHORIZED);
+
+    let (status, body) = send(get_as("/api/me", "authenticated")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains(r#""sub":"authenticated-123""#), "{body}");
+}
+
+#[tokio::test]
+async fn admin_routes_need_the_service_role() {
+    assert_eq!(
+        send(get_as("/admin/metrics", "authenticated")).await.0,
+        StatusCode::FORBIDDEN
+    );
+
+    let (status, body) = send(get_as("/admin/metrics", "service_role")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("propel_auth"), "{body}");
+}
+
+#[tokio::test]
+async fn server_key_is_only_accepted_on_internal_routes() {
+    let with_key = |method: &str, uri: &str| {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("x-server-key", TEST_SERVER_KEY)
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    assert_eq!(
+        send(with_key("POST", "/internal/jobs")).await.0,
+        StatusCode::ACCEPTED
+    );
+    assert_eq!(
+        send(with_key("GET", "/api/me")).await.0,
+        StatusCode::FORBIDDEN
+    );
+
+    let user_job = Request::post("/internal/jobs")
+        .header(
+            "authorization",
+            format!("Bearer {}", token("authenticated")),
+        )
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(send(user_job).await.0, StatusCode::FORBIDDEN);
+}
+
+#[test]
+fn propel_toml_ships_the_template_dir() {
+    let config = PropelConfig::load(Path::new(env!("CARGO_MANIFEST_DIR"))).unwrap();
+    let include = config.build.include.expect("[build] include is set");
+
+    let template_dir = top_level_entry(&config.build.env["TEMPLATE_DIR"]).unwrap();
+    assert_eq!(template_dir, "templates");
+    assert!(is_included(template_dir, &include));
+    assert!(
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join(template_dir)
+            .join("index.html")
+            .is_file()
+    );
+}